@@ -0,0 +1,101 @@
+//benches/common/mod.rs
+// Each bench target compiles its own copy and uses only part of it
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use delphi::{parser, scheduler, Circuit};
+
+// Circuits shared by the benchmark targets: bundled ISCAS-85 netlists and
+// synthetic ones with ISCAS-like proportions
+pub fn circuits() -> Vec<(&'static str, String)> {
+    vec![
+        ("c17", bundled("c17")),
+        ("synthetic_500", synthetic_netlist(32, 500, 0x5eed)),
+        ("synthetic_2400", synthetic_netlist(32, 2400, 0x5eed)),
+        ("c7552", bundled("c7552")),
+    ]
+}
+
+// Netlists under benches/data, reconstructed from the reference outputs in
+// Results/magic
+pub fn bundled(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("benches/data")
+        .join(format!("{}.txt", name));
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e))
+}
+
+// Mostly 2-input NORs, some inverters and 4/5-variable lines, and 32 outputs
+pub fn synthetic_netlist(inputs: usize, lines: usize, seed: u64) -> String {
+    let mut rng = seed;
+    let mut next = move |bound: usize| {
+        rng ^= rng << 13;
+        rng ^= rng >> 7;
+        rng ^= rng << 17;
+        (rng % bound as u64) as usize
+    };
+
+    let outputs = 32;
+    let mut signals: Vec<String> = (0..inputs).map(|i| format!("x{}", i)).collect();
+    let mut text = String::new();
+    for g in 0..lines {
+        let arity = [1, 2, 2, 2, 2, 3, 4][next(7)];
+        let operands: Vec<&str> = (0..arity)
+            .map(|_| signals[next(signals.len())].as_str())
+            .collect();
+        let name = format!("n{}", outputs + 1 + g);
+        text.push_str(&format!("{} = {}\n", name, operands.join(" ")));
+        signals.push(name);
+    }
+    for o in 0..outputs {
+        text.push_str(&format!("n{} = {}\n", 10001 + o, signals[signals.len() - 1 - o]));
+    }
+    text.push_str(".end\n");
+    text
+}
+
+pub fn parsed(text: &str) -> Circuit {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist_str(text, &mut circuit).unwrap();
+    parser::find_primary_inputs(&mut circuit);
+    circuit
+}
+
+pub fn scheduled(text: &str) -> Circuit {
+    let mut circuit = parsed(text);
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule(&mut circuit);
+    circuit
+}
+
+// ALAP levels by relaxation to a fixpoint, as the scheduler computed them
+// before its single backward pass: the reference for tests and the baseline
+// of the ALAP benchmark
+pub fn fixpoint_alap(circuit: &mut Circuit) {
+    for i in 0..circuit.num_gates {
+        circuit.gates[i].alap_level = if circuit.readers(circuit.gates[i].out).is_empty() { 1 } else { -1 };
+    }
+    loop {
+        let mut changed = false;
+        for i in 0..circuit.num_gates {
+            let out = circuit.gates[i].out;
+            for &j in circuit.fanout.get(&out).into_iter().flatten() {
+                if circuit.gates[j].alap_level != -1 && circuit.gates[i].alap_level <= circuit.gates[j].alap_level {
+                    circuit.gates[i].alap_level = circuit.gates[j].alap_level + 1;
+                    changed = true;
+                }
+            }
+        }
+        if !changed && circuit.gates[..circuit.num_gates].iter().all(|g| g.alap_level != -1) {
+            break;
+        }
+    }
+    let max_level = circuit.gates[..circuit.num_gates].iter().map(|g| g.alap_level).max().unwrap_or(0);
+    for gate in &mut circuit.gates[..circuit.num_gates] {
+        gate.alap_level = max_level - gate.alap_level + 1;
+    }
+    circuit.max_alap = max_level;
+}
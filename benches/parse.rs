@@ -0,0 +1,24 @@
+//benches/parse.rs
+mod common;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use delphi::{parser, Circuit};
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, text) in common::circuits() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut circuit = Circuit::new();
+                parser::parse_netlist_str(black_box(&text), &mut circuit).unwrap();
+                circuit
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,108 @@
+//benches/pipeline.rs
+mod common;
+
+use std::env;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use delphi::{generator, mapper, scheduler, Circuit, MAX_COL};
+
+fn schedulers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("schedule");
+    for (name, text) in common::circuits() {
+        group.bench_function(format!("asap/{}", name), |b| {
+            b.iter_batched(|| common::parsed(&text),
+                |mut circuit| { scheduler::compute_asap_schedule(&mut circuit); circuit },
+                BatchSize::SmallInput)
+        });
+        group.bench_function(format!("alap/{}", name), |b| {
+            b.iter_batched(|| common::parsed(&text),
+                |mut circuit| { scheduler::compute_alap_schedule(&mut circuit); circuit },
+                BatchSize::SmallInput)
+        });
+        group.bench_function(format!("list/{}", name), |b| {
+            b.iter_batched(
+                || {
+                    let mut circuit = common::parsed(&text);
+                    scheduler::compute_asap_schedule(&mut circuit);
+                    scheduler::compute_alap_schedule(&mut circuit);
+                    circuit
+                },
+                |mut circuit| { scheduler::compute_list_schedule(&mut circuit); circuit },
+                BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+// The single backward pass against the fixpoint relaxation it replaced, on
+// c7552 and a deeper circuit of the same size class
+fn alap_regression(c: &mut Criterion) {
+    let mut group = c.benchmark_group("alap_regression");
+    for (name, text) in [("c7552", common::bundled("c7552")), ("synthetic_c7552", common::synthetic_netlist(207, 3500, 0x7552))] {
+        group.bench_function(format!("backward_pass/{}", name), |b| {
+            b.iter_batched(|| common::parsed(&text),
+                |mut circuit| { scheduler::compute_alap_schedule(&mut circuit); circuit },
+                BatchSize::SmallInput)
+        });
+        group.bench_function(format!("fixpoint/{}", name), |b| {
+            b.iter_batched(|| common::parsed(&text),
+                |mut circuit| { common::fixpoint_alap(&mut circuit); circuit },
+                BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+fn mappers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("map");
+    for (name, text) in common::circuits() {
+        let circuit = common::scheduled(&text);
+        // The naive mapping places every gate in one row
+        if circuit.num_inputs + circuit.num_gates <= MAX_COL {
+            group.bench_function(format!("naive/{}", name), |b| {
+                b.iter_batched(|| common::scheduled(&text),
+                    |mut circuit| mapper::create_naive_mapping(&mut circuit),
+                    BatchSize::SmallInput)
+            });
+        }
+        group.bench_function(format!("compact/{}", name), |b| {
+            b.iter_batched(|| common::scheduled(&text),
+                |mut circuit| mapper::create_compact_mapping(&mut circuit),
+                BatchSize::SmallInput)
+        });
+        group.bench_function(format!("compact_parallel/{}", name), |b| {
+            b.iter_batched(|| common::scheduled(&text),
+                |mut circuit| mapper::create_compact_mapping_parallel(&mut circuit),
+                BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+fn generators(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate");
+    let out_dir = env::temp_dir().join("delphi-bench");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let out = |file: &str| -> PathBuf { out_dir.join(file) };
+
+    for (name, text) in common::circuits() {
+        let mut circuit: Circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+
+        group.bench_function(format!("stats/{}", name), |b| {
+            b.iter(|| generator::generate_stats(&circuit, out("stats.txt")).unwrap())
+        });
+        group.bench_function(format!("verilog/{}", name), |b| {
+            b.iter(|| generator::generate_magic_verilog(&circuit, out("magic.v")).unwrap())
+        });
+        group.bench_function(format!("micro_ops/{}", name), |b| {
+            b.iter(|| generator::generate_micro_ops(&circuit, &mapping, false, out("compact.txt")).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, schedulers, alap_regression, mappers, generators);
+criterion_main!(benches);
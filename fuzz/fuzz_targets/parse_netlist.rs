@@ -0,0 +1,15 @@
+//fuzz_targets/parse_netlist.rs
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use delphi::{parser, verify, Circuit};
+
+// Arbitrary bytes must produce either a circuit or an error, never a panic
+fuzz_target!(|data: &[u8]| {
+    let mut circuit = Circuit::new();
+    if parser::parse_netlist_from(data, &mut circuit).is_ok() {
+        parser::find_primary_inputs(&mut circuit);
+        let _ = verify::verify_netlist(&circuit);
+    }
+});
@@ -0,0 +1,346 @@
+//analysis/mod.rs
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use anyhow::{Result, Context, ensure};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::output;
+use crate::program::{Cell, MicroOp, Program};
+use crate::simulator::output_signals;
+use crate::tech::Technology;
+use crate::{signal_name, Circuit, CrossbarMapping, MemristiveGate, MAX_GATES};
+
+// Timing yield of one level of a mapped program
+#[derive(Debug, Clone)]
+pub struct LevelYield {
+    pub level: i32,
+    pub ops: usize,
+    // Clock cycles the periphery needs for the level (see Technology::step_cycles)
+    pub cycles: usize,
+    // Fraction of trials in which every device of the level switched within
+    // the clock period
+    pub timing_yield: f64,
+    // Slowest switching delay in the level, averaged over trials and at the
+    // 99th percentile
+    pub mean_ns: f64,
+    pub p99_ns: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariabilityReport {
+    pub trials: usize,
+    pub clock_ns: f64,
+    pub levels: Vec<LevelYield>,
+    // Fraction of trials in which every level met the clock period
+    pub program_yield: f64,
+}
+
+// Monte-Carlo sampling of switching delays over the gate cells of a mapping.
+// Each trial draws a fresh lognormal delay for every device around the
+// profile's switch_ns; a level passes if its slowest device meets the clock
+pub fn analyze_variability(
+    mapping: &CrossbarMapping,
+    tech: &Technology,
+    trials: usize,
+    seed: u64,
+) -> Result<VariabilityReport> {
+    ensure!(trials > 0, "At least one Monte-Carlo trial is required");
+    let sigma = tech.variation.as_ref()
+        .map(|v| v.switch_sigma)
+        .context(format!("Profile '{}' has no [variation] section", tech.name))?;
+    let clock_ns = tech.clock_ns();
+    let median_ns = tech.device.switch_ns;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let levels: Vec<(i32, Vec<(usize, usize)>)> = mapping.cells_by_level()
+        .filter(|(level, _)| *level >= 1)
+        .collect();
+    let mut slowest = vec![Vec::with_capacity(trials); levels.len()];
+    let mut program_passes = 0;
+
+    for _ in 0..trials {
+        let mut all_pass = true;
+        for (k, (_, cells)) in levels.iter().enumerate() {
+            let worst = (0..cells.len())
+                .map(|_| median_ns * (sigma * standard_normal(&mut rng)).exp())
+                .fold(0.0, f64::max);
+            all_pass &= worst <= clock_ns;
+            slowest[k].push(worst);
+        }
+        if all_pass {
+            program_passes += 1;
+        }
+    }
+
+    let levels = levels.iter().zip(slowest)
+        .map(|((level, cells), mut worst)| {
+            let rows: HashSet<usize> = cells.iter().map(|&(i, _)| i).collect();
+            let mut cols: HashSet<i32> = HashSet::new();
+            for &(i, j) in cells {
+                let cell = mapping.cell(i, j);
+                cols.insert(cell.jdx);
+                cols.extend(cell.inputs.iter().take(cell.fanin).flatten().map(|op| op.jdx));
+            }
+            let passes = worst.iter().filter(|&&d| d <= clock_ns).count();
+            worst.sort_by(f64::total_cmp);
+            LevelYield {
+                level: *level,
+                ops: cells.len(),
+                cycles: tech.step_cycles(rows.len(), cols.len()),
+                timing_yield: passes as f64 / trials as f64,
+                mean_ns: worst.iter().sum::<f64>() / trials as f64,
+                p99_ns: worst[(trials * 99).div_ceil(100) - 1],
+            }
+        })
+        .collect();
+
+    Ok(VariabilityReport {
+        trials,
+        clock_ns,
+        levels,
+        program_yield: program_passes as f64 / trials as f64,
+    })
+}
+
+// Best, median and worst of a metric over runs with consecutive seeds; ties
+// go to the lower seed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunSpread {
+    pub runs: usize,
+    pub best: (u64, f64),
+    pub median: f64,
+    pub worst: (u64, f64),
+}
+
+impl RunSpread {
+    pub fn of(values: &[(u64, f64)], higher_is_better: bool) -> Option<Self> {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| {
+            let by_value = if higher_is_better { b.1.total_cmp(&a.1) } else { a.1.total_cmp(&b.1) };
+            by_value.then(a.0.cmp(&b.0))
+        });
+        let n = sorted.len();
+        let median = match n {
+            0 => return None,
+            _ if n % 2 == 1 => sorted[n / 2].1,
+            _ => (sorted[n / 2 - 1].1 + sorted[n / 2].1) / 2.0,
+        };
+        Some(RunSpread { runs: n, best: sorted[0], median, worst: sorted[n - 1] })
+    }
+}
+
+// Variability analyses repeated with seeds seed, seed + 1, ...; the report of
+// the run with the best program yield is kept
+#[derive(Debug, Clone)]
+pub struct VariabilityRuns {
+    pub best_seed: u64,
+    pub best: VariabilityReport,
+    pub program_yield: RunSpread,
+    // 99th-percentile delay of each run's slowest level
+    pub p99_ns: RunSpread,
+}
+
+pub fn analyze_variability_runs(
+    mapping: &CrossbarMapping,
+    tech: &Technology,
+    trials: usize,
+    seed: u64,
+    runs: usize,
+) -> Result<VariabilityRuns> {
+    ensure!(runs > 0, "At least one run is required");
+    let reports = (0..runs as u64)
+        .map(|k| Ok((seed + k, analyze_variability(mapping, tech, trials, seed + k)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let yields: Vec<(u64, f64)> = reports.iter().map(|(s, r)| (*s, r.program_yield)).collect();
+    let p99: Vec<(u64, f64)> = reports.iter()
+        .map(|(s, r)| (*s, r.levels.iter().map(|l| l.p99_ns).fold(0.0, f64::max)))
+        .collect();
+    let program_yield = RunSpread::of(&yields, true).context("No runs")?;
+    let p99_ns = RunSpread::of(&p99, false).context("No runs")?;
+    let (best_seed, best) = reports.into_iter()
+        .find(|(s, _)| *s == program_yield.best.0)
+        .context("Best run is missing")?;
+
+    Ok(VariabilityRuns { best_seed, best, program_yield, p99_ns })
+}
+
+// Box-Muller transform; rand's own normal distribution lives in rand_distr
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+// Devices one wordline (row) or bitline (column) may have activated in the
+// same cycle; None leaves that direction unchecked
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActivationLimits {
+    pub wordline: Option<usize>,
+    pub bitline: Option<usize>,
+}
+
+impl ActivationLimits {
+    pub fn is_set(&self) -> bool {
+        self.wordline.is_some() || self.bitline.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Wordline,
+    Bitline,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Wordline => write!(f, "wordline"),
+            Line::Bitline => write!(f, "bitline"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivationViolation {
+    pub level: i32,
+    pub line: Line,
+    // Row of a wordline, column of a bitline
+    pub index: usize,
+    pub devices: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for ActivationViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "level {}: {} {} activates {} devices (limit {})",
+            output::level_number(self.level), self.line, self.index, self.devices, self.limit)
+    }
+}
+
+// Devices a gate cell activates when it evaluates: its own output device and
+// the devices holding its operands
+pub fn activated_devices(cell: &MemristiveGate) -> impl Iterator<Item = (usize, usize)> + '_ {
+    cell.inputs.iter()
+        .take(cell.fanin)
+        .flatten()
+        .map(|op| (op.idx as usize, op.jdx as usize))
+        .chain(std::iter::once((cell.idx as usize, cell.jdx as usize)))
+}
+
+// Levels of a mapping in which more devices share a wordline or bitline than
+// the limits allow (sneak currents, driver strength), by level and line
+pub fn check_activation(mapping: &CrossbarMapping, limits: &ActivationLimits) -> Vec<ActivationViolation> {
+    let mut violations = Vec::new();
+    if !limits.is_set() {
+        return violations;
+    }
+
+    for (level, cells) in mapping.cells_by_level() {
+        let devices: HashSet<(usize, usize)> = cells.iter()
+            .flat_map(|&(i, j)| activated_devices(mapping.cell(i, j)))
+            .collect();
+        let mut rows = BTreeMap::new();
+        let mut cols = BTreeMap::new();
+        for &(i, j) in &devices {
+            *rows.entry(i).or_insert(0) += 1;
+            *cols.entry(j).or_insert(0) += 1;
+        }
+
+        for (line, counts, limit) in [(Line::Wordline, rows, limits.wordline), (Line::Bitline, cols, limits.bitline)] {
+            let Some(limit) = limit else { continue };
+            violations.extend(counts.into_iter()
+                .filter(|&(_, devices)| devices > limit)
+                .map(|(index, devices)| ActivationViolation { level, line, index, devices, limit }));
+        }
+    }
+
+    violations
+}
+
+// Where a netlist spends its levels, reads and copies, the N worst of each:
+// the outputs at the end of the longest paths, the most read signals, the
+// ASAP levels with the most gates and the compact rows with the most copies
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hotspots {
+    // Signals from a primary input to an output, deepest first
+    pub paths: Vec<Vec<i32>>,
+    // Signals and the gates reading them
+    pub fanout: Vec<(i32, usize)>,
+    // ASAP levels and their gates' outputs
+    pub levels: Vec<(i32, Vec<i32>)>,
+    // Rows and the signals copied into them, once per copy
+    pub rows: Vec<(u32, Vec<i32>)>,
+}
+
+impl Hotspots {
+    // The circuit must be scheduled; `signals` names the cells of the
+    // compact program. Ties go to the earlier output, signal, level or row
+    pub fn find(circuit: &Circuit, compact: &Program, signals: &HashMap<Cell, i32>, n: usize) -> Self {
+        let gates = &circuit.gates[..circuit.num_gates];
+        let level = |signal: i32| circuit.driver(signal).map_or(0, |g| g.asap_level);
+
+        let mut ends = output_signals(circuit);
+        ends.retain(|&s| circuit.driver(s).is_some());
+        ends.sort_by_key(|&s| -level(s));
+        let paths = ends.into_iter().take(n).map(|end| {
+            let mut path = vec![end];
+            while let Some(gate) = circuit.driver(path[path.len() - 1]) {
+                // The operand on the longest path, a primary input past the last gate
+                let operands = &gate.inputs[..gate.fanin];
+                let Some(&next) = operands.iter().max_by_key(|&&s| (level(s), s >= MAX_GATES as i32)) else { break };
+                path.push(next);
+            }
+            path.reverse();
+            path
+        }).collect();
+
+        let read = circuit.mapped_inputs().map(|k| (MAX_GATES + k) as i32).chain(gates.iter().flat_map(|g| g.outs()));
+        let mut fanout: Vec<(i32, usize)> = read.map(|s| (s, circuit.readers(s).len())).filter(|&(_, r)| r > 0).collect();
+        fanout.sort_by_key(|&(_, readers)| std::cmp::Reverse(readers));
+        fanout.truncate(n);
+
+        let mut by_level: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for gate in gates {
+            by_level.entry(gate.asap_level).or_default().push(gate.out);
+        }
+        let mut levels: Vec<(i32, Vec<i32>)> = by_level.into_iter().collect();
+        levels.sort_by_key(|(_, outs)| std::cmp::Reverse(outs.len()));
+        levels.truncate(n);
+
+        let mut by_row: BTreeMap<u32, Vec<i32>> = BTreeMap::new();
+        for (_, op) in compact.ops() {
+            if let MicroOp::Copy { cell, .. } = op {
+                by_row.entry(cell.row).or_default().push(signals.get(cell).copied().unwrap_or(-1));
+            }
+        }
+        let mut rows: Vec<(u32, Vec<i32>)> = by_row.into_iter().collect();
+        rows.sort_by_key(|(_, copied)| std::cmp::Reverse(copied.len()));
+        rows.truncate(n);
+
+        Self { paths, fanout, levels, rows }
+    }
+}
+
+// `deepest n22 at depth 9, most read x3 by 6 gates, busiest level 2 with 14
+// gates, most copies row 4 with 7`
+impl fmt::Display for Hotspots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(path) = self.paths.first() {
+            parts.push(format!("deepest {} at depth {}", signal_name(path[path.len() - 1]), path.len() - 1));
+        }
+        if let Some(&(signal, readers)) = self.fanout.first() {
+            parts.push(format!("most read {} by {} gates", signal_name(signal), readers));
+        }
+        if let Some((level, outs)) = self.levels.first() {
+            parts.push(format!("busiest level {} with {} gates", output::level_number(*level), outs.len()));
+        }
+        match self.rows.first() {
+            Some((row, copied)) => parts.push(format!("most copies row {} with {}", row, copied.len())),
+            None => parts.push("no copies".to_string()),
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
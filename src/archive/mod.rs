@@ -0,0 +1,230 @@
+//archive/mod.rs
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, bail, ensure};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use serde::{Deserialize, Serialize};
+
+use crate::provenance::{self, ArtifactHeader};
+use crate::schema::{self, Versioned};
+
+// Name of the manifest, the first entry of every archive
+pub const MANIFEST: &str = "manifest.json";
+
+// Zip record signatures
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+// Version 2.0: deflate; flag bit 11: UTF-8 names
+const ZIP_VERSION: u16 = 20;
+const UTF8_NAMES: u16 = 1 << 11;
+const DEFLATE: u16 = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    // Relative to the output directory, '/'-separated
+    pub path: String,
+    pub bytes: u64,
+    pub hash: String,
+}
+
+// The run behind an archive, as its artifacts' headers state it, and the
+// files it holds besides the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub circuit: String,
+    pub header: ArtifactHeader,
+    pub files: Vec<ArchivedFile>,
+}
+
+// One zip file of a circuit's artifacts, for moving results between machines
+// or attaching them to a report. Entries are deflated, in the order given
+// after the manifest, and dated at the run's time, so an archive of the same
+// run under the same SOURCE_DATE_EPOCH is the same file
+pub fn write_archive<P: AsRef<Path>>(path: P, circuit: &str, header: &ArtifactHeader, files: &[(String, &Path)]) -> Result<ArchiveManifest> {
+    let path = path.as_ref();
+    let mut contents = Vec::with_capacity(files.len());
+    for (name, source) in files {
+        let bytes = fs::read(source).context(format!("Failed to read {:?}", source))?;
+        ensure!(name != MANIFEST && contents.iter().all(|(n, _)| n != name), "{} is archived twice", name);
+        contents.push((name.clone(), bytes));
+    }
+    let manifest = ArchiveManifest {
+        circuit: circuit.to_string(),
+        header: header.clone(),
+        files: contents.iter()
+            .map(|(name, bytes)| ArchivedFile { path: name.clone(), bytes: bytes.len() as u64, hash: provenance::content_hash(bytes) })
+            .collect(),
+    };
+    let text = serde_json::to_string_pretty(&Versioned::new(&manifest))? + "\n";
+
+    let file = File::create(path).context(format!("Failed to create archive: {:?}", path))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file), dos_time(provenance::generated_at()));
+    zip.add(MANIFEST, text.as_bytes())?;
+    for (name, bytes) in &contents {
+        zip.add(name, bytes)?;
+    }
+    zip.finish().context(format!("Failed to write archive: {:?}", path))?;
+    Ok(manifest)
+}
+
+// Every entry of an archive by name, the manifest included, each checked
+// against its CRC
+pub fn read_archive<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Vec<u8>)>> {
+    let path = path.as_ref();
+    let data = fs::read(path).context(format!("Failed to read archive: {:?}", path))?;
+    read_entries(&data).context(format!("Malformed archive: {:?}", path))
+}
+
+pub fn read_manifest<P: AsRef<Path>>(path: P) -> Result<ArchiveManifest> {
+    let path = path.as_ref();
+    match read_archive(path)?.into_iter().find(|(name, _)| name == MANIFEST) {
+        Some((_, bytes)) => schema::from_str(&String::from_utf8(bytes)?, "archive manifest"),
+        None => bail!("{:?} has no {}", path, MANIFEST),
+    }
+}
+
+struct ZipWriter<W: Write> {
+    out: W,
+    offset: u32,
+    // Central directory records, written at the end
+    directory: Vec<u8>,
+    entries: u16,
+    time: (u16, u16),
+}
+
+impl<W: Write> ZipWriter<W> {
+    fn new(out: W, time: (u16, u16)) -> Self {
+        Self { out, offset: 0, directory: Vec::new(), entries: 0, time }
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        ensure!(self.entries < u16::MAX, "Too many files for one archive");
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let size = |n: usize| u32::try_from(n).ok().filter(|&n| n < u32::MAX).context(format!("{} is too large to archive", name));
+        let (compressed_size, size) = (size(compressed.len())?, size(data.len())?);
+        let name_len = u16::try_from(name.len()).context(format!("{} is too long a name to archive", name))?;
+
+        // Fields shared by the local and the central record, from the
+        // version needed on
+        let mut common = Vec::with_capacity(26);
+        for field in [ZIP_VERSION, UTF8_NAMES, DEFLATE, self.time.0, self.time.1] {
+            common.extend(field.to_le_bytes());
+        }
+        for field in [crc.sum(), compressed_size, size] {
+            common.extend(field.to_le_bytes());
+        }
+        common.extend(name_len.to_le_bytes());
+        // No extra field
+        common.extend(0u16.to_le_bytes());
+
+        let mut local = LOCAL_HEADER.to_le_bytes().to_vec();
+        local.extend(&common);
+        local.extend(name.as_bytes());
+        self.out.write_all(&local)?;
+        self.out.write_all(&compressed)?;
+
+        self.directory.extend(CENTRAL_HEADER.to_le_bytes());
+        // Made by: version 2.0 on Unix, for the permissions below
+        self.directory.extend((3u16 << 8 | ZIP_VERSION).to_le_bytes());
+        self.directory.extend(&common);
+        // Comment length, disk number, internal attributes
+        self.directory.extend([0u8; 6]);
+        // External attributes: a regular file, rw-r--r--
+        self.directory.extend((0o100644u32 << 16).to_le_bytes());
+        self.directory.extend(self.offset.to_le_bytes());
+        self.directory.extend(name.as_bytes());
+
+        self.offset = (local.len() + compressed.len()).try_into().ok()
+            .and_then(|n: u32| self.offset.checked_add(n))
+            .context("Archive too large")?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.out.write_all(&self.directory)?;
+        let mut end = END_OF_DIRECTORY.to_le_bytes().to_vec();
+        // This disk and the directory's
+        end.extend([0u8; 4]);
+        end.extend(self.entries.to_le_bytes());
+        end.extend(self.entries.to_le_bytes());
+        end.extend((self.directory.len() as u32).to_le_bytes());
+        end.extend(self.offset.to_le_bytes());
+        // No comment
+        end.extend(0u16.to_le_bytes());
+        self.out.write_all(&end)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn read_entries(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let u16_at = |at: usize| -> Result<u16> {
+        Ok(u16::from_le_bytes(data.get(at..at + 2).context("Truncated record")?.try_into()?))
+    };
+    let u32_at = |at: usize| -> Result<u32> {
+        Ok(u32::from_le_bytes(data.get(at..at + 4).context("Truncated record")?.try_into()?))
+    };
+    // The end record is last, after a comment of at most 64 KiB
+    let end = (0..data.len().saturating_sub(21)).rev()
+        .take(u16::MAX as usize + 22)
+        .find(|&at| u32_at(at).is_ok_and(|s| s == END_OF_DIRECTORY))
+        .context("No end of central directory")?;
+    let entries = u16_at(end + 10)?;
+    let mut at = u32_at(end + 16)? as usize;
+
+    let mut files = Vec::with_capacity(entries as usize);
+    for _ in 0..entries {
+        ensure!(u32_at(at)? == CENTRAL_HEADER, "Bad central directory record at {}", at);
+        let method = u16_at(at + 10)?;
+        let crc = u32_at(at + 16)?;
+        let compressed_size = u32_at(at + 20)? as usize;
+        let size = u32_at(at + 24)? as usize;
+        let (name_len, extra_len, comment_len) = (u16_at(at + 28)? as usize, u16_at(at + 30)? as usize, u16_at(at + 32)? as usize);
+        let local = u32_at(at + 42)? as usize;
+        let name = String::from_utf8(data.get(at + 46..at + 46 + name_len).context("Truncated name")?.to_vec())?;
+        at += 46 + name_len + extra_len + comment_len;
+
+        ensure!(u32_at(local)? == LOCAL_HEADER, "Bad local header for {}", name);
+        let start = local + 30 + u16_at(local + 26)? as usize + u16_at(local + 28)? as usize;
+        let stored = data.get(start..start + compressed_size).context(format!("Truncated data for {}", name))?;
+        let bytes = match method {
+            0 => stored.to_vec(),
+            DEFLATE => {
+                let mut bytes = Vec::with_capacity(size);
+                DeflateDecoder::new(stored).read_to_end(&mut bytes)?;
+                bytes
+            }
+            _ => bail!("{} uses compression method {}", name, method),
+        };
+        let mut check = Crc::new();
+        check.update(&bytes);
+        ensure!(bytes.len() == size && check.sum() == crc, "{} does not match its CRC", name);
+        files.push((name, bytes));
+    }
+    Ok(files)
+}
+
+// MS-DOS time and date of the run, in UTC, as zip entries carry them; dates
+// before 1980 have none and take its first day
+fn dos_time(time: SystemTime) -> (u16, u16) {
+    // "2024-05-01T12:30:00Z"
+    let stamp = humantime::format_rfc3339_seconds(time).to_string();
+    let field = |range: std::ops::Range<usize>| stamp.get(range).and_then(|s| s.parse::<u16>().ok()).unwrap_or(0);
+    let (year, month, day) = (field(0..4), field(5..7), field(8..10));
+    let (hour, minute, second) = (field(11..13), field(14..16), field(17..19));
+    if year < 1980 {
+        return (0, 1 << 5 | 1);
+    }
+    (hour << 11 | minute << 5 | (second / 2), (year - 1980) << 9 | month << 5 | day)
+}
@@ -0,0 +1,115 @@
+//batch/mod.rs
+use std::collections::BTreeMap;
+use anyhow::{Result, ensure};
+
+use crate::analysis::Line;
+use crate::tiling::LEVEL_CYCLES;
+use crate::{Circuit, CrossbarMapping, MAX_GATES, MAX_ROW};
+
+// Where the inputs of B vectors go when a mapping is evaluated on all of them
+// at once. The mapping is replicated down the crossbar, one block of rows per
+// vector; every micro-op then acts on the same columns of every block, so a
+// level still takes one step however many vectors share it
+#[derive(Debug, Clone)]
+pub struct BatchLayout {
+    pub batch: usize,
+    // Rows of one copy of the mapping
+    pub rows_per_vector: usize,
+    pub cols: usize,
+    // Cell of each primary input in the first block; None for the unused
+    // inputs the mapping leaves out
+    pub inputs: Vec<Option<(usize, usize)>>,
+    pub levels: usize,
+}
+
+// One write of input data along a whole wordline or bitline: the position
+// along the line, then the vector and input whose bit goes there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitWrite {
+    pub line: Line,
+    pub index: usize,
+    pub bits: Vec<(usize, usize, usize)>,
+}
+
+impl BatchLayout {
+    pub fn new(circuit: &Circuit, mapping: &CrossbarMapping, batch: usize) -> Result<Self> {
+        ensure!(batch > 0, "A batch needs at least one vector");
+        let rows_per_vector = mapping.max_idx.max(0) as usize + 1;
+        let max_batch = Self::max_batch(mapping);
+        ensure!(batch <= max_batch,
+            "A batch of {} needs {} rows; the mapping takes {} per vector, so at most {} fit in {}",
+            batch, batch * rows_per_vector, rows_per_vector, max_batch, MAX_ROW);
+
+        let mut inputs = vec![None; circuit.num_inputs];
+        for cell in mapping.occupied_cells() {
+            let n = cell.value - MAX_GATES as i32;
+            if n >= 0 && (n as usize) < inputs.len() && !cell.is_copy {
+                inputs[n as usize] = Some((cell.idx as usize, cell.jdx as usize));
+            }
+        }
+
+        Ok(Self {
+            batch,
+            rows_per_vector,
+            cols: mapping.max_jdx.max(0) as usize + 1,
+            inputs,
+            levels: circuit.max_asap.max(0) as usize,
+        })
+    }
+
+    // Vectors whose copies of the mapping fit in the crossbar's rows
+    pub fn max_batch(mapping: &CrossbarMapping) -> usize {
+        MAX_ROW / (mapping.max_idx.max(0) as usize + 1)
+    }
+
+    // Cell receiving input `input` of vector `vector`
+    pub fn cell(&self, vector: usize, input: usize) -> (usize, usize) {
+        let (row, col) = self.inputs[input].unwrap_or_else(|| panic!("Input x{} has no cell in the mapping", input));
+        (vector * self.rows_per_vector + row, col)
+    }
+
+    // Input data writes, each filling a whole row or column: whichever
+    // direction the batch's input cells occupy fewer lines of. A single
+    // vector of either mapping is loaded in one write
+    pub fn init_writes(&self) -> Vec<InitWrite> {
+        line_writes((0..self.batch).flat_map(|vector| {
+            (0..self.inputs.len()).filter(|&n| self.inputs[n].is_some()).map(move |input| {
+                let (row, col) = self.cell(vector, input);
+                (row, col, vector, input)
+            })
+        }))
+    }
+
+    // One vector on the plain mapping: the C reference's cycle count
+    pub fn single_cycles(&self) -> usize {
+        LEVEL_CYCLES * self.levels + 1
+    }
+
+    // The batch's input writes replace the single input write
+    pub fn batched_cycles(&self) -> usize {
+        LEVEL_CYCLES * self.levels + self.init_writes().len()
+    }
+
+    // Vectors per cycle over the whole batch
+    pub fn throughput(&self) -> f64 {
+        self.batch as f64 / self.batched_cycles() as f64
+    }
+}
+
+// Writes loading input bits at (row, col, copy, input), each along a whole
+// row or column: whichever direction the bits occupy fewer lines of
+pub(crate) fn line_writes(bits: impl IntoIterator<Item = (usize, usize, usize, usize)>) -> Vec<InitWrite> {
+    let mut rows: BTreeMap<usize, Vec<(usize, usize, usize)>> = BTreeMap::new();
+    let mut cols: BTreeMap<usize, Vec<(usize, usize, usize)>> = BTreeMap::new();
+    for (row, col, copy, input) in bits {
+        rows.entry(row).or_default().push((col, copy, input));
+        cols.entry(col).or_default().push((row, copy, input));
+    }
+    let (line, lines) = if cols.len() < rows.len() { (Line::Bitline, cols) } else { (Line::Wordline, rows) };
+    lines.into_iter()
+        .map(|(index, mut bits)| {
+            bits.sort_unstable();
+            InitWrite { line, index, bits }
+        })
+        .collect()
+}
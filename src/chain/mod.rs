@@ -0,0 +1,134 @@
+//chain/mod.rs
+use std::collections::HashMap;
+use std::fmt;
+use anyhow::{Context, Result, ensure};
+
+use crate::program::Program;
+use crate::simulator::output_signals;
+use crate::{mapper, scheduler, Circuit, TableGate, MAX_GATES};
+
+// Links netlists run one after another into one circuit: input xk of each
+// stage reads output k of the stage before it, outputs in `output_signals`
+// order. The first stage's inputs and the last stage's outputs are the
+// circuit's, and the last stage keeps its signal ids so its outputs keep
+// their ports; the other stages are renumbered past them. Stages may leave
+// outputs of the one before unread, which is noted
+pub fn link(stages: &[Circuit]) -> Result<Circuit> {
+    ensure!(stages.len() >= 2, "A chain needs at least two netlists, not {}", stages.len());
+    let last = &stages[stages.len() - 1];
+    let mut linked = Circuit::new();
+    linked.bench_name = stages.iter().map(|s| s.bench_name.as_str()).collect::<Vec<_>>().join("_");
+    linked.primitive = stages[0].primitive;
+
+    // Fresh ids above the last stage's gates and temporaries below its own
+    let ids = || last.gates[..last.num_gates].iter().flat_map(|g| g.inputs[..g.fanin].iter().chain([&g.out]));
+    let mut next_gate = ids().filter(|&&s| (0..MAX_GATES as i32).contains(&s)).max().map_or(1, |&s| s + 1);
+    let mut next_temp = ids().filter(|&&s| s < 0).min().map_or(-1, |&s| s - 1);
+
+    // Signals the current stage's inputs read, by input
+    let mut fed: Vec<i32> = Vec::new();
+    for (s, stage) in stages.iter().enumerate() {
+        ensure!(stage.primitive == linked.primitive, "{} is a {} netlist but {} is {}",
+            stage.bench_name, stage.primitive, stages[0].bench_name, linked.primitive);
+        ensure!(!stage.gates[..stage.num_gates].iter().any(|g| g.is_multi_output()),
+            "{} has multi-output gates, which chains cannot link", stage.bench_name);
+        if s > 0 {
+            let before = &stages[s - 1].bench_name;
+            ensure!(stage.num_inputs <= fed.len(), "{} reads {} inputs but {} has only {} outputs",
+                stage.bench_name, stage.num_inputs, before, fed.len());
+            if stage.num_inputs < fed.len() {
+                linked.diagnostics.warn("chain", "unread-outputs", format!("{} reads {} of the {} outputs of {}",
+                    stage.bench_name, stage.num_inputs, fed.len(), before));
+            }
+        }
+
+        let is_last = s + 1 == stages.len();
+        let mut renamed: HashMap<i32, i32> = HashMap::new();
+        let mut rename = |signal: i32| -> i32 {
+            if signal >= MAX_GATES as i32 {
+                return if s == 0 { signal } else { fed[(signal - MAX_GATES as i32) as usize] };
+            }
+            if is_last {
+                return signal;
+            }
+            *renamed.entry(signal).or_insert_with(|| {
+                let next = if signal < 0 { &mut next_temp } else { &mut next_gate };
+                let id = *next;
+                *next += if signal < 0 { -1 } else { 1 };
+                id
+            })
+        };
+        for gate in &stage.gates[..stage.num_gates] {
+            let inputs: Vec<i32> = gate.inputs[..gate.fanin].iter().map(|&input| rename(input)).collect();
+            let mut linked_gate = TableGate {
+                out: rename(gate.out),
+                is_output: is_last && gate.is_output,
+                attributes: gate.attributes.clone(),
+                origin: gate.origin.clone(),
+                ..Default::default()
+            };
+            linked_gate.set_inputs(&inputs);
+            linked.gates.push(linked_gate);
+        }
+        let outputs = output_signals(stage).into_iter().map(&mut rename).collect();
+        fed = outputs;
+    }
+    ensure!(next_gate <= MAX_GATES as i32, "The chained netlists need {} signals, over the {} a circuit holds",
+        next_gate, MAX_GATES);
+
+    linked.num_gates = linked.gates.len();
+    linked.num_inputs = stages[0].num_inputs;
+    linked.primary_inputs = stages[0].primary_inputs.clone();
+    linked.num_outputs = last.num_outputs;
+    linked.index_signals();
+    Ok(linked)
+}
+
+// Cycles of the compact programs of each stage run on its own, reading its
+// outputs and loading them into the next, against the chained program that
+// leaves them in place. Both come from the plain flow, with no passes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainCycles {
+    pub stages: Vec<(String, usize)>,
+    // Outputs handed from one stage to the next
+    pub passed: usize,
+    pub chained: usize,
+}
+
+impl ChainCycles {
+    pub fn measure(stages: &[Circuit], linked: &Circuit) -> Result<Self> {
+        let stage_cycles = stages.iter()
+            .map(|stage| Ok((stage.bench_name.clone(), compact_cycles(stage)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            stages: stage_cycles,
+            passed: stages[1..].iter().map(|s| s.num_inputs).sum(),
+            chained: compact_cycles(linked)?,
+        })
+    }
+
+    pub fn separate(&self) -> usize {
+        self.stages.iter().map(|(_, cycles)| cycles).sum()
+    }
+}
+
+// `a 40 + b 31 = 71 cycles separately, 5 signals passed in place, 62 chained`
+impl fmt::Display for ChainCycles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages: Vec<String> = self.stages.iter().map(|(name, cycles)| format!("{} {}", name, cycles)).collect();
+        write!(f, "{} = {} cycles separately, {} signals passed in place, {} chained",
+            stages.join(" + "), self.separate(), self.passed, self.chained)
+    }
+}
+
+pub(crate) fn compact_cycles(circuit: &Circuit) -> Result<usize> {
+    let mut circuit = circuit.clone();
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::ensure_scheduled(&circuit).with_context(|| format!("{} cannot be scheduled", circuit.bench_name))?;
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule(&mut circuit);
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping)
+        .with_context(|| format!("No program for the compact mapping of {}", circuit.bench_name))?;
+    Ok(program.cycles().total())
+}
@@ -0,0 +1,269 @@
+//compare/mod.rs
+use std::cmp::Ordering;
+use std::fmt::{self, Write};
+
+use crate::metrics::RunMetrics;
+use crate::suite::{self, RunSummary};
+
+// Runtime changes smaller than this are timer noise whatever their percentage
+pub const RUNTIME_FLOOR_MS: f64 = 50.0;
+
+// How far, in percent, a metric may grow before `compare-runs` calls it a
+// regression. Area and depth come out the same on every run; runtime does not
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub levels: f64,
+    pub area: f64,
+    pub cycles: f64,
+    pub runtime: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self { levels: 0.0, area: 0.0, cycles: 0.0, runtime: 25.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Levels,
+    // Rows times columns of the compact crossbar
+    Area,
+    // Cycles of the compact program
+    Cycles,
+    Runtime,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 4] = [Metric::Levels, Metric::Area, Metric::Cycles, Metric::Runtime];
+
+    pub fn value(self, metrics: &RunMetrics) -> f64 {
+        match self {
+            Metric::Levels => metrics.levels as f64,
+            Metric::Area => (metrics.compact.rows * metrics.compact.cols) as f64,
+            Metric::Cycles => metrics.compact.cycles as f64,
+            Metric::Runtime => metrics.times.total_ms,
+        }
+    }
+
+    fn threshold(self, thresholds: &Thresholds) -> f64 {
+        match self {
+            Metric::Levels => thresholds.levels,
+            Metric::Area => thresholds.area,
+            Metric::Cycles => thresholds.cycles,
+            Metric::Runtime => thresholds.runtime,
+        }
+    }
+
+    // Greater when the new value is worse by more than the threshold allows,
+    // Less when it is better by as much
+    pub fn change(self, old: f64, new: f64, thresholds: &Thresholds) -> Ordering {
+        if self == Metric::Runtime && (new - old).abs() < RUNTIME_FLOOR_MS {
+            return Ordering::Equal;
+        }
+        let percent = percent(old, new);
+        if percent.abs() <= self.threshold(thresholds) || old == new {
+            Ordering::Equal
+        } else {
+            percent.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Metric::Levels => "levels",
+            Metric::Area => "area",
+            Metric::Cycles => "cycles",
+            Metric::Runtime => "runtime",
+        };
+        f.pad(name)
+    }
+}
+
+fn percent(old: f64, new: f64) -> f64 {
+    if old == 0.0 {
+        if new == 0.0 { 0.0 } else { 100.0 }
+    } else {
+        (new - old) / old * 100.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    // The metrics past their thresholds
+    Regressed(Vec<Metric>),
+    Improved,
+    Same,
+    // Only in the new run
+    Added,
+    // Only in the old run
+    Removed,
+    // Measured in the old run but not in the new one
+    Failed,
+    // Measured in the new run but not in the old one
+    Fixed,
+    // In both runs, measured in neither
+    Unmeasured,
+}
+
+impl Verdict {
+    pub fn is_regression(&self) -> bool {
+        matches!(self, Verdict::Regressed(_) | Verdict::Failed)
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Regressed(metrics) => {
+                let names: Vec<String> = metrics.iter().map(Metric::to_string).collect();
+                write!(f, "REGRESSED ({})", names.join(", "))
+            },
+            Verdict::Improved => write!(f, "improved"),
+            Verdict::Same => write!(f, "same"),
+            Verdict::Added => write!(f, "added"),
+            Verdict::Removed => write!(f, "removed"),
+            Verdict::Failed => write!(f, "FAILED"),
+            Verdict::Fixed => write!(f, "fixed"),
+            Verdict::Unmeasured => write!(f, "not measured"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitDelta {
+    pub circuit: String,
+    pub old: Option<RunMetrics>,
+    pub new: Option<RunMetrics>,
+    pub verdict: Verdict,
+}
+
+// Two runs side by side, circuit by circuit in the old run's order with the
+// circuits only the new run has after them
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub old: String,
+    pub new: String,
+    pub circuits: Vec<CircuitDelta>,
+}
+
+impl Comparison {
+    pub fn new(old: &RunSummary, new: &RunSummary, thresholds: &Thresholds) -> Self {
+        let mut circuits = Vec::new();
+        for before in &old.circuits {
+            let after = new.circuits.iter().find(|c| c.circuit == before.circuit);
+            let verdict = match (&before.metrics, after.map(|a| &a.metrics)) {
+                (_, None) => Verdict::Removed,
+                (Some(_), Some(None)) => Verdict::Failed,
+                (None, Some(Some(_))) => Verdict::Fixed,
+                (None, Some(None)) => Verdict::Unmeasured,
+                (Some(old), Some(Some(new))) => {
+                    let changes: Vec<(Metric, Ordering)> = Metric::ALL.iter()
+                        .map(|&m| (m, m.change(m.value(old), m.value(new), thresholds)))
+                        .collect();
+                    let worse: Vec<Metric> = changes.iter().filter(|(_, c)| *c == Ordering::Greater).map(|(m, _)| *m).collect();
+                    if !worse.is_empty() {
+                        Verdict::Regressed(worse)
+                    } else if changes.iter().any(|(_, c)| *c == Ordering::Less) {
+                        Verdict::Improved
+                    } else {
+                        Verdict::Same
+                    }
+                },
+            };
+            circuits.push(CircuitDelta {
+                circuit: before.circuit.clone(),
+                old: before.metrics.clone(),
+                new: after.and_then(|a| a.metrics.clone()),
+                verdict,
+            });
+        }
+        for after in new.circuits.iter().filter(|a| old.circuits.iter().all(|b| b.circuit != a.circuit)) {
+            circuits.push(CircuitDelta {
+                circuit: after.circuit.clone(),
+                old: None,
+                new: after.metrics.clone(),
+                verdict: Verdict::Added,
+            });
+        }
+        Self { old: old.name.clone(), new: new.name.clone(), circuits }
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &CircuitDelta> {
+        self.circuits.iter().filter(|c| c.verdict.is_regression())
+    }
+
+    // A fixed-width table of old -> new values with the change in percent,
+    // then counts and geometric means over the circuits measured in both runs
+    pub fn report(&self) -> String {
+        const COLUMNS: [&str; 6] = ["CIRCUIT", "LEVELS", "AREA", "CYCLES", "RUNTIME", "VERDICT"];
+        let cells: Vec<Vec<String>> = self.circuits.iter()
+            .map(|delta| {
+                let mut row = vec![delta.circuit.clone()];
+                for metric in Metric::ALL {
+                    let old = delta.old.as_ref().map(|m| metric.value(m));
+                    let new = delta.new.as_ref().map(|m| metric.value(m));
+                    row.push(cell(metric, old, new));
+                }
+                row.push(delta.verdict.to_string());
+                row
+            })
+            .collect();
+        let widths: Vec<usize> = (0..COLUMNS.len())
+            .map(|c| cells.iter().map(|r| r[c].len()).chain([COLUMNS[c].len()]).max().unwrap_or(0))
+            .collect();
+        let line = |row: &[String]| -> String {
+            let padded: Vec<String> = row.iter().zip(&widths).enumerate()
+                .map(|(c, (cell, &w))| if (1..5).contains(&c) { format!("{:>w$}", cell) } else { format!("{:<w$}", cell) })
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+
+        let mut text = String::new();
+        let _ = writeln!(text, "RUN COMPARISON: {} -> {}", self.old, self.new);
+        let _ = writeln!(text, "=============");
+        let header: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+        let _ = writeln!(text, "{}", line(&header));
+        for row in &cells {
+            let _ = writeln!(text, "{}", line(row));
+        }
+
+        let count = |f: fn(&Verdict) -> bool| self.circuits.iter().filter(|c| f(&c.verdict)).count();
+        let regressed: Vec<&str> = self.regressions().map(|c| c.circuit.as_str()).collect();
+        let _ = writeln!(text);
+        let _ = writeln!(text, "Regressed      : {}{}", regressed.len(),
+            if regressed.is_empty() { String::new() } else { format!(" ({})", regressed.join(", ")) });
+        let _ = writeln!(text, "Improved       : {}", count(|v| matches!(v, Verdict::Improved)));
+        let _ = writeln!(text, "Same           : {}", count(|v| matches!(v, Verdict::Same)));
+        let _ = writeln!(text, "Added, removed : {}, {}",
+            count(|v| matches!(v, Verdict::Added)), count(|v| matches!(v, Verdict::Removed)));
+
+        let both: Vec<(&RunMetrics, &RunMetrics)> = self.circuits.iter()
+            .filter_map(|c| c.old.as_ref().zip(c.new.as_ref()))
+            .collect();
+        if !both.is_empty() {
+            for metric in Metric::ALL {
+                let old = suite::geometric_mean(both.iter().map(|(o, _)| metric.value(o))).unwrap_or(0.0);
+                let new = suite::geometric_mean(both.iter().map(|(_, n)| metric.value(n))).unwrap_or(0.0);
+                let _ = writeln!(text, "Geomean {:<7}: {:.1} -> {:.1} ({:+.1}%)", metric, old, new, percent(old, new));
+            }
+        }
+        text
+    }
+}
+
+fn cell(metric: Metric, old: Option<f64>, new: Option<f64>) -> String {
+    let show = |value: f64| match metric {
+        Metric::Runtime => format!("{:.0}ms", value),
+        _ => format!("{}", value),
+    };
+    match (old, new) {
+        (Some(old), Some(new)) if show(old) == show(new) => show(new),
+        (Some(old), Some(new)) => format!("{} -> {} ({:+.1}%)", show(old), show(new), percent(old, new)),
+        (Some(old), None) => format!("{} -> -", show(old)),
+        (None, Some(new)) => format!("- -> {}", show(new)),
+        (None, None) => "-".to_string(),
+    }
+}
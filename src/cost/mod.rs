@@ -0,0 +1,296 @@
+//cost/mod.rs
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{Context, Result, anyhow, bail, ensure};
+
+use crate::metrics::{ProgramMetrics, RunMetrics};
+
+// What a cost expression can read: the circuit's size and depth, and the
+// size and cost of the program it is evaluated on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Gates,
+    NotGates,
+    Levels,
+    Inputs,
+    Outputs,
+    Rows,
+    Cols,
+    Area,
+    Cells,
+    Copies,
+    Cycles,
+    CopyCycles,
+    TechCycles,
+    TimeNs,
+    EnergyPj,
+}
+
+impl Variable {
+    pub const ALL: [Variable; 15] = [
+        Variable::Gates, Variable::NotGates, Variable::Levels, Variable::Inputs, Variable::Outputs,
+        Variable::Rows, Variable::Cols, Variable::Area, Variable::Cells, Variable::Copies,
+        Variable::Cycles, Variable::CopyCycles, Variable::TechCycles, Variable::TimeNs, Variable::EnergyPj,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variable::Gates => "gates",
+            Variable::NotGates => "not_gates",
+            Variable::Levels => "levels",
+            Variable::Inputs => "inputs",
+            Variable::Outputs => "outputs",
+            Variable::Rows => "rows",
+            Variable::Cols => "cols",
+            Variable::Area => "area",
+            Variable::Cells => "cells",
+            Variable::Copies => "copies",
+            Variable::Cycles => "cycles",
+            Variable::CopyCycles => "copy_cycles",
+            Variable::TechCycles => "tech_cycles",
+            Variable::TimeNs => "time_ns",
+            Variable::EnergyPj => "energy_pj",
+        }
+    }
+
+    // Whether only a run with a technology has the value
+    pub fn needs_tech(self) -> bool {
+        matches!(self, Variable::TechCycles | Variable::TimeNs | Variable::EnergyPj)
+    }
+
+    fn value(self, circuit: &RunMetrics, program: &ProgramMetrics) -> Option<f64> {
+        Some(match self {
+            Variable::Gates => circuit.gates as f64,
+            Variable::NotGates => circuit.not_gates as f64,
+            Variable::Levels => circuit.levels as f64,
+            Variable::Inputs => circuit.inputs as f64,
+            Variable::Outputs => circuit.outputs as f64,
+            Variable::Rows => program.rows as f64,
+            Variable::Cols => program.cols as f64,
+            Variable::Area => (program.rows * program.cols) as f64,
+            Variable::Cells => program.cells as f64,
+            Variable::Copies => program.copies as f64,
+            Variable::Cycles => program.cycles as f64,
+            Variable::CopyCycles => program.copy_cycles as f64,
+            Variable::TechCycles => program.tech_cycles? as f64,
+            Variable::TimeNs => program.time_ns?,
+            Variable::EnergyPj => program.energy_pj?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Min,
+    Max,
+    Log2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Node>),
+    // One of + - * / ^
+    Binary(char, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+// A cost over a run's metrics, such as `rows*2 + copies*0.5 + levels`, so
+// that the trade-off between area, copies and depth is the user's to weigh.
+// Numbers, the variables of `Variable`, + - * / and ^, parentheses, and
+// min(..), max(..) and log2(..) are understood; lower is better
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostExpr {
+    text: String,
+    root: Node,
+}
+
+impl CostExpr {
+    pub fn variables(&self) -> Vec<Variable> {
+        let mut variables = Vec::new();
+        collect(&self.root, &mut variables);
+        variables
+    }
+
+    pub fn needs_tech(&self) -> bool {
+        self.variables().into_iter().any(Variable::needs_tech)
+    }
+
+    // The cost of one program of a run
+    pub fn evaluate(&self, circuit: &RunMetrics, program: &ProgramMetrics) -> Result<f64> {
+        let cost = evaluate(&self.root, circuit, program)?;
+        ensure!(cost.is_finite(), "Cost `{}` is {} for {}", self.text, cost, circuit.circuit);
+        Ok(cost)
+    }
+}
+
+impl FromStr for CostExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parser = Parser { text, at: 0 };
+        let root = parser.expression()
+            .and_then(|root| {
+                parser.skip_space();
+                ensure!(parser.at == text.len(), "unexpected `{}`", &text[parser.at..]);
+                Ok(root)
+            })
+            .map_err(|e| anyhow!("Malformed cost expression `{}` at column {}: {}", text, parser.at + 1, e))?;
+        Ok(Self { text: text.trim().to_string(), root })
+    }
+}
+
+impl fmt::Display for CostExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn collect(node: &Node, variables: &mut Vec<Variable>) {
+    match node {
+        Node::Number(_) => {}
+        Node::Variable(v) => {
+            if !variables.contains(v) {
+                variables.push(*v);
+            }
+        }
+        Node::Negate(a) => collect(a, variables),
+        Node::Binary(_, a, b) => {
+            collect(a, variables);
+            collect(b, variables);
+        }
+        Node::Call(_, args) => args.iter().for_each(|a| collect(a, variables)),
+    }
+}
+
+fn evaluate(node: &Node, circuit: &RunMetrics, program: &ProgramMetrics) -> Result<f64> {
+    Ok(match node {
+        Node::Number(n) => *n,
+        Node::Variable(v) => v.value(circuit, program)
+            .with_context(|| format!("`{}` needs a technology (--tech)", v.name()))?,
+        Node::Negate(a) => -evaluate(a, circuit, program)?,
+        Node::Binary(op, a, b) => {
+            let (a, b) = (evaluate(a, circuit, program)?, evaluate(b, circuit, program)?);
+            match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' => a / b,
+                _ => a.powf(b),
+            }
+        }
+        Node::Call(function, args) => {
+            let args = args.iter().map(|a| evaluate(a, circuit, program)).collect::<Result<Vec<f64>>>()?;
+            match function {
+                Function::Min => args.into_iter().fold(f64::INFINITY, f64::min),
+                Function::Max => args.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                Function::Log2 => args[0].log2(),
+            }
+        }
+    })
+}
+
+// Recursive descent over
+//   expression = term (("+" | "-") term)*
+//   term       = unary (("*" | "/") unary)*
+//   unary      = "-" unary | power
+//   power      = atom ("^" unary)?
+//   atom       = number | variable | function "(" expression ("," expression)* ")" | "(" expression ")"
+struct Parser<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        self.at = self.text.len() - self.text[self.at..].trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.text[self.at..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn expression(&mut self) -> Result<Node> {
+        let mut node = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.at += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node> {
+        let mut node = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.at += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat('-') {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(Node::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        if self.eat('(') {
+            let node = self.expression()?;
+            ensure!(self.eat(')'), "expected `)`");
+            return Ok(node);
+        }
+        let rest = &self.text[self.at..];
+        let length = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+        let word = &rest[..length];
+        if word.is_empty() {
+            match rest.chars().next() {
+                Some(c) => bail!("expected a number, variable or `(`, found `{}`", c),
+                None => bail!("expected a number, variable or `(` at the end"),
+            }
+        }
+        if word.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            let number = word.parse().with_context(|| format!("`{}` is not a number", word))?;
+            self.at += length;
+            return Ok(Node::Number(number));
+        }
+        let function = match word {
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "log2" => Some(Function::Log2),
+            _ => None,
+        };
+        if let Some(function) = function {
+            self.at += length;
+            ensure!(self.eat('('), "expected `(` after {}", word);
+            let mut args = vec![self.expression()?];
+            while self.eat(',') {
+                args.push(self.expression()?);
+            }
+            ensure!(self.eat(')'), "expected `)` to close {}(", word);
+            ensure!(function != Function::Log2 || args.len() == 1, "log2 takes one argument");
+            return Ok(Node::Call(function, args));
+        }
+        let Some(variable) = Variable::ALL.into_iter().find(|v| v.name() == word) else {
+            let names: Vec<&str> = Variable::ALL.iter().map(|v| v.name()).collect();
+            bail!("unknown variable `{}`; known are {}", word, names.join(", "));
+        };
+        self.at += length;
+        Ok(Node::Variable(variable))
+    }
+}
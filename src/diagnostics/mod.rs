@@ -0,0 +1,156 @@
+//diagnostics/mod.rs
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::schema::Versioned;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+// One finding of a pipeline stage. `source` names the stage and `code` the
+// kind of finding, so reports can be grouped and filtered by tools
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub source: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}/{}] {}", self.severity, self.source, self.code, self.message)
+    }
+}
+
+// Findings collected over a run, in the order they were made. Stages that
+// run more than once (every remap, say) report the same finding once
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, severity: Severity, source: &'static str, code: &'static str, message: impl Into<String>) {
+        let diagnostic = Diagnostic { severity, source, code, message: message.into() };
+        if !self.items.contains(&diagnostic) {
+            self.items.push(diagnostic);
+        }
+    }
+
+    pub fn info(&mut self, source: &'static str, code: &'static str, message: impl Into<String>) {
+        self.push(Severity::Info, source, code, message);
+    }
+
+    pub fn warn(&mut self, source: &'static str, code: &'static str, message: impl Into<String>) {
+        self.push(Severity::Warning, source, code, message);
+    }
+
+    pub fn error(&mut self, source: &'static str, code: &'static str, message: impl Into<String>) {
+        self.push(Severity::Error, source, code, message);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.items.iter().filter(|d| d.severity == severity).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    // "1 error, 2 warnings, 0 info"
+    pub fn summary(&self) -> String {
+        let plural = |n: usize, word: &str| if n == 1 { format!("{} {}", n, word) } else { format!("{} {}s", n, word) };
+        format!("{}, {}, {} info",
+            plural(self.count(Severity::Error), "error"),
+            plural(self.count(Severity::Warning), "warning"),
+            self.count(Severity::Info))
+    }
+
+    // One row per severity, source and code, most severe first, with the
+    // number of findings and the first of their messages
+    pub fn table(&self) -> String {
+        let mut groups: Vec<(&Diagnostic, usize)> = Vec::new();
+        for diagnostic in &self.items {
+            let key = (diagnostic.severity, diagnostic.source, diagnostic.code);
+            match groups.iter_mut().find(|(d, _)| (d.severity, d.source, d.code) == key) {
+                Some((_, count)) => *count += 1,
+                None => groups.push((diagnostic, 1)),
+            }
+        }
+        // Stable, so groups of one severity keep the order they were found in
+        groups.sort_by_key(|(d, _)| std::cmp::Reverse(d.severity));
+
+        let mut table = format!("{:<8} {:<10} {:<20} {:>5}  message\n", "severity", "source", "code", "count");
+        for (diagnostic, count) in groups {
+            let more = if count > 1 { format!(" (+{} more)", count - 1) } else { String::new() };
+            table.push_str(&format!("{:<8} {:<10} {:<20} {:>5}  {}{}\n",
+                diagnostic.severity, diagnostic.source, diagnostic.code, count, diagnostic.message, more));
+        }
+        table
+    }
+
+    // The findings of one circuit as a JSON object with per-severity counts
+    pub fn write_json<P: AsRef<Path>>(&self, circuit: &str, path: P) -> Result<()> {
+        #[derive(Serialize)]
+        struct Counts {
+            error: usize,
+            warning: usize,
+            info: usize,
+        }
+        #[derive(Serialize)]
+        struct Report<'a> {
+            circuit: &'a str,
+            counts: Counts,
+            diagnostics: &'a [Diagnostic],
+        }
+
+        let report = Report {
+            circuit,
+            counts: Counts {
+                error: self.count(Severity::Error),
+                warning: self.count(Severity::Warning),
+                info: self.count(Severity::Info),
+            },
+            diagnostics: &self.items,
+        };
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(&Versioned::new(&report))? + "\n")
+            .context(format!("Failed to write diagnostics: {:?}", path))
+    }
+}
@@ -0,0 +1,99 @@
+//endurance/mod.rs
+use std::collections::HashMap;
+use anyhow::{Result, ensure};
+
+use crate::mapper::Placement;
+use crate::{CrossbarMapping, MemristiveGate};
+
+// Writes a cell takes per run: inputs are written once, gate outputs are
+// initialized and then switched by the evaluation
+pub fn cell_writes(cell: &MemristiveGate) -> u64 {
+    if cell.asap_level == 0 && !cell.is_copy { 1 } else { 2 }
+}
+
+// K placements of the same mapping, each shifted (with wrap-around) across a
+// `rows` x `cols` area of free lines. The controller runs placement r mod K on
+// run r, so every program is the same up to where its cells sit, and the
+// writes of the busiest cells are shared with cells the mapping leaves idle
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub rows: usize,
+    pub cols: usize,
+    // Row and column shift of each placement; placement 0 is unshifted
+    pub shifts: Vec<(usize, usize)>,
+    pub placements: Vec<Placement>,
+}
+
+// Shifts tried along each direction when picking the next placement
+const SHIFT_STEPS: usize = 16;
+
+impl Rotation {
+    // Area defaults to the mapping's own footprint; a larger one (e.g. the
+    // whole free array) spreads writes further
+    pub fn new(base: &Placement, mapping: &CrossbarMapping, count: usize, area: Option<(usize, usize)>) -> Result<Self> {
+        ensure!(count > 0, "A rotation needs at least one placement");
+        let footprint = (mapping.max_idx.max(0) as usize + 1, mapping.max_jdx.max(0) as usize + 1);
+        let (rows, cols) = area.unwrap_or(footprint);
+        ensure!(rows >= footprint.0 && cols >= footprint.1,
+            "The {}x{} mapping does not fit a {}x{} rotation area", footprint.0, footprint.1, rows, cols);
+
+        // Each placement takes the candidate shift that keeps the busiest cell
+        // of the area least written so far, then the one overlapping the
+        // fewest writes
+        let cells: Vec<(usize, usize, u64)> = mapping.occupied_cells()
+            .map(|c| (c.idx as usize, c.jdx as usize, cell_writes(c)))
+            .collect();
+        let candidates: Vec<(usize, usize)> = (0..rows).step_by(rows.div_ceil(SHIFT_STEPS))
+            .flat_map(|r| (0..cols).step_by(cols.div_ceil(SHIFT_STEPS)).map(move |c| (r, c)))
+            .collect();
+        let mut writes = vec![0u64; rows * cols];
+        let at = |(i, j): (usize, usize), (r, c): (usize, usize)| (i + r) % rows * cols + (j + c) % cols;
+        let mut shifts = Vec::with_capacity(count);
+        for k in 0..count {
+            let shift = if k == 0 {
+                (0, 0)
+            } else {
+                *candidates.iter()
+                    .min_by_key(|&&shift| {
+                        let touched = cells.iter().map(|&(i, j, w)| (writes[at((i, j), shift)], w));
+                        let peak = touched.clone().map(|(old, w)| old + w).max().unwrap_or(0);
+                        let overlap: u64 = touched.map(|(old, w)| old * w).sum();
+                        (peak, overlap)
+                    })
+                    .unwrap_or(&(0, 0))
+            };
+            for &(i, j, w) in &cells {
+                writes[at((i, j), shift)] += w;
+            }
+            shifts.push(shift);
+        }
+
+        let placements = shifts.iter()
+            .map(|&(row_shift, col_shift)| base.rotated(rows, cols, row_shift, col_shift))
+            .collect::<Result<_>>()?;
+        Ok(Self { rows, cols, shifts, placements })
+    }
+
+    // Physical cell writes over one pass through all placements
+    pub fn writes_per_cycle(&self, mapping: &CrossbarMapping) -> HashMap<(i32, i32), u64> {
+        let mut writes = HashMap::new();
+        for placement in &self.placements {
+            for cell in mapping.occupied_cells() {
+                let at = (placement.row(cell.idx), placement.col(cell.jdx));
+                *writes.entry(at).or_insert(0) += cell_writes(cell);
+            }
+        }
+        writes
+    }
+
+    // Highest average writes any cell takes per run once the schedule cycles
+    pub fn max_writes_per_run(&self, mapping: &CrossbarMapping) -> f64 {
+        let max = self.writes_per_cycle(mapping).into_values().max().unwrap_or(0);
+        max as f64 / self.placements.len() as f64
+    }
+}
+
+// Highest writes any cell takes per run without rotation
+pub fn max_writes_per_run(mapping: &CrossbarMapping) -> u64 {
+    mapping.occupied_cells().map(cell_writes).max().unwrap_or(0)
+}
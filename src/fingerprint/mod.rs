@@ -0,0 +1,134 @@
+//fingerprint/mod.rs
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::provenance::fnv1a;
+use crate::{Circuit, MAX_GATES};
+
+// Leaf colors
+const INPUT: u64 = 1;
+const UNDRIVEN: u64 = 2;
+
+// What a circuit is made of, apart from what its signals are called and the
+// order its inputs, gates and operands were written in: two netlists that
+// rename or reorder the same circuit have the same fingerprint.
+//
+// Every gate and input is colored twice, in the manner of one round of
+// Weisfeiler-Lehman refinement each way. Going up, a gate's color is its
+// fanin, how many of its signals are outputs, and the colors of its operands as a
+// multiset; going down, it is its color from below and the colors of its
+// readers from above. The hash is taken over the multiset of all colors.
+// Circuits with the same counts and colors but wired differently exist, but
+// they are rare outside of constructions meant to defeat the refinement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub hash: u64,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub gates: usize,
+}
+
+impl Fingerprint {
+    pub fn of(circuit: &Circuit) -> Self {
+        let order = circuit.gates_in_topological_order();
+        let is_input = |signal: i32| (MAX_GATES..MAX_GATES + circuit.num_inputs).contains(&(signal as usize));
+
+        // Up the cones: gates on a loop read the loop's later gates as
+        // undriven, which is the same whatever the loop's signals are called
+        let mut up = vec![0u64; circuit.num_gates];
+        let mut colored = vec![false; circuit.num_gates];
+        for &i in &order {
+            let gate = &circuit.gates[i];
+            let mut operands: Vec<u64> = gate.inputs[..gate.fanin].iter()
+                .map(|&s| match circuit.driver_index(s) {
+                    _ if is_input(s) => INPUT,
+                    Some(d) if colored[d] => up[d],
+                    _ => UNDRIVEN,
+                })
+                .collect();
+            operands.sort_unstable();
+            // Multi-output gates also by how many signals they drive
+            let mut color = vec![gate.fanin as u64, gate.primary_outputs().count() as u64];
+            if gate.is_multi_output() {
+                color.push(gate.extra_outs.len() as u64);
+            }
+            up[i] = mix(&[color.as_slice(), operands.as_slice()].concat());
+            colored[i] = true;
+        }
+
+        // Down from the outputs, readers first
+        let mut down = vec![0u64; circuit.num_gates];
+        let sorted = |mut colors: Vec<u64>| {
+            colors.sort_unstable();
+            colors
+        };
+        for &i in order.iter().rev() {
+            let readers = sorted(circuit.gate_readers(i).map(|r| down[r]).collect());
+            let color = [&[up[i]], readers.as_slice()].concat();
+            down[i] = mix(&color);
+        }
+        let mut colors: Vec<u64> = (0..circuit.num_inputs)
+            .map(|k| {
+                let readers = sorted(circuit.readers((MAX_GATES + k) as i32).iter().map(|&r| down[r]).collect());
+                mix(&[&[INPUT], readers.as_slice()].concat())
+            })
+            .chain(down.iter().copied())
+            .collect();
+        colors.sort_unstable();
+        Self {
+            hash: mix(&[&[circuit.primitive as u64], colors.as_slice()].concat()),
+            inputs: circuit.num_inputs,
+            outputs: circuit.num_outputs,
+            gates: circuit.num_gates,
+        }
+    }
+}
+
+// `fp:3c1e...` with the counts, as the console lines print it
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fp:{:016x} ({} inputs, {} outputs, {} gates)", self.hash, self.inputs, self.outputs, self.gates)
+    }
+}
+
+// The circuits of a run by fingerprint, each under the first circuit and
+// file it was seen in
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateIndex {
+    // Whether later copies are skipped rather than only warned about
+    pub skip: bool,
+    seen: HashMap<Fingerprint, (String, PathBuf)>,
+}
+
+impl DuplicateIndex {
+    pub fn new(skip: bool) -> Self {
+        Self { skip, seen: HashMap::new() }
+    }
+
+    // The circuit and file seen before with the same structure, if any;
+    // otherwise the circuit is recorded as the first of its kind
+    pub fn check(&mut self, circuit: &Circuit, file: &Path) -> Option<(String, PathBuf)> {
+        let fingerprint = Fingerprint::of(circuit);
+        match self.seen.get(&fingerprint) {
+            Some(first) => Some(first.clone()),
+            None => {
+                self.seen.insert(fingerprint, (circuit.bench_name.clone(), file.to_path_buf()));
+                None
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+fn mix(words: &[u64]) -> u64 {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    fnv1a(&bytes)
+}
@@ -0,0 +1,146 @@
+//gates/mod.rs
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::program::{Cell, MicroOp};
+use crate::{Primitive, MAX_FANIN};
+
+// Every gate a crossbar cell computes. A circuit's primitive names its gates
+// of two or more inputs and its one-input gates are NOT; everything else
+// about a kind is in its entry of `GATES`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    Not,
+    Nor,
+    Nand,
+}
+
+// Where the compact mapping puts a gate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapRule {
+    // Next to its single operand, in that operand's row
+    OperandRow,
+    // In one row, after copies of the operands that lie in other rows
+    GatherOperands,
+}
+
+// How a gate too wide for one cell is split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    // It cannot be
+    Never,
+    // A group of operands becomes one operand, the inverse of the same gate
+    // over the group: NOR(a, b, c) = NOR(NOT(NOR(a, b)), c)
+    Regroup,
+}
+
+pub struct GateSpec {
+    pub kind: GateKind,
+    // In netlists, listings and messages
+    pub name: &'static str,
+    // Verilog gate primitive
+    pub verilog: &'static str,
+    // Inputs one crossbar cell takes
+    pub fanin: RangeInclusive<usize>,
+    // The output is `combine` folded over the inputs from `identity`,
+    // inverted if `inverted`
+    pub identity: u64,
+    pub combine: fn(u64, u64) -> u64,
+    pub inverted: bool,
+    pub split: Split,
+    pub map: MapRule,
+    // The op that evaluates the gate from its operand cells into its own
+    pub emit: fn(&[Cell], Cell) -> MicroOp,
+    // Whether native netlists may spell it out, as `n8 = NOR(n7, x2)`
+    pub native: bool,
+}
+
+// By kind, in the order of `GateKind`
+pub static GATES: [GateSpec; 3] = [
+    GateSpec {
+        kind: GateKind::Not,
+        name: "NOT",
+        verilog: "not",
+        fanin: 1..=1,
+        identity: 0,
+        combine: |acc, word| acc | word,
+        inverted: true,
+        split: Split::Never,
+        map: MapRule::OperandRow,
+        emit: |inputs, cell| MicroOp::ExecNot { input: inputs[0], cell },
+        native: true,
+    },
+    GateSpec {
+        kind: GateKind::Nor,
+        name: "NOR",
+        verilog: "nor",
+        fanin: 2..=MAX_FANIN,
+        identity: 0,
+        combine: |acc, word| acc | word,
+        inverted: true,
+        split: Split::Regroup,
+        map: MapRule::GatherOperands,
+        emit: |inputs, cell| MicroOp::ExecNor { inputs: inputs.to_vec(), cell },
+        native: true,
+    },
+    // Programs of the NAND primitive keep the NOR op; the primitive in their
+    // header tells the two apart
+    GateSpec {
+        kind: GateKind::Nand,
+        name: "NAND",
+        verilog: "nand",
+        fanin: 2..=MAX_FANIN,
+        identity: !0,
+        combine: |acc, word| acc & word,
+        inverted: true,
+        split: Split::Regroup,
+        map: MapRule::GatherOperands,
+        emit: |inputs, cell| MicroOp::ExecNor { inputs: inputs.to_vec(), cell },
+        native: false,
+    },
+];
+
+impl GateKind {
+    // The gate of `fanin` inputs in a circuit of `primitive`
+    pub fn of(primitive: Primitive, fanin: usize) -> GateKind {
+        if GateKind::Not.spec().fanin.contains(&fanin) {
+            GateKind::Not
+        } else {
+            primitive.kind()
+        }
+    }
+
+    pub fn named(name: &str) -> Option<GateKind> {
+        GATES.iter().find(|spec| spec.name.eq_ignore_ascii_case(name)).map(|spec| spec.kind)
+    }
+
+    pub fn spec(self) -> &'static GateSpec {
+        &GATES[self as usize]
+    }
+
+    pub fn name(self) -> &'static str {
+        self.spec().name
+    }
+
+    // Output word of the gate over its input words, 64 vectors at a time
+    pub fn eval<I: IntoIterator<Item = u64>>(self, inputs: I) -> u64 {
+        let spec = self.spec();
+        let word = inputs.into_iter().fold(spec.identity, spec.combine);
+        if spec.inverted { !word } else { word }
+    }
+
+    pub fn emit(self, inputs: &[Cell], cell: Cell) -> MicroOp {
+        (self.spec().emit)(inputs, cell)
+    }
+
+    // Names of the kinds native netlists may spell out, for messages
+    pub fn native_names() -> Vec<&'static str> {
+        GATES.iter().filter(|spec| spec.native).map(|spec| spec.name).collect()
+    }
+}
+
+impl fmt::Display for GateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
@@ -1,32 +1,166 @@
 //generator/mod.rs
+mod toolchain;
+
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Write;
-use std::path::Path;
-use std::cmp::max;
-use anyhow::{Result, Context};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::cmp::{max, Reverse};
+use anyhow::{Result, Context, ensure};
+
+use crate::analysis::{Hotspots, Line};
+use crate::batch::BatchLayout;
+use crate::cost::CostExpr;
+use crate::endurance::{self, Rotation};
+use crate::gates::GateKind;
+use crate::mapper::{self, ColumnSpans, CompactPlan, CopyReuse, Placement, RegionUsage};
+use crate::metrics::RunMetrics;
+use crate::output;
+use crate::overlay::{CellDiff, Layer, Overlay};
+use crate::passes::FanoutProfile;
+use crate::scheduler::{self, CopyPressure, Schedule};
+use crate::simulator::TraceFrame;
+use crate::tech::Technology;
+use crate::replicate::Replication;
+use crate::program::{self, Cell, GateSite, MicroOp, Partition, Program, ProgramCycles, ProgramFormat, Step, TextListing};
+use crate::tiling::{TiledPipeline, COPY_CYCLES};
+use crate::resynth::FlowMetrics;
+use crate::{signal_name, Circuit, CrossbarMapping, Primitive, TableGate, MAX_GATES, OUT_BIAS};
 
-use crate::{Circuit, CrossbarMapping, MemristiveGate, TableGate, MAX_GATES};
+pub use self::toolchain::*;
 
 pub fn generate_stats<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Result<()> {
+    generate_stats_with_tech(circuit, None, path)
+}
+
+// Schedule statistics, with time steps also given in clock cycles and
+// nanoseconds of a concrete technology when one is selected
+pub fn generate_stats_with_tech<P: AsRef<Path>>(circuit: &Circuit, tech: Option<&Technology>, path: P) -> Result<()> {
     let mut file = File::create(path)
         .context("Failed to create stats file")?;
     
     // ASAP Schedule Statistics
-    generate_schedule_stats(&mut file, circuit, "ASAP", |g| g.asap_level)?;
+    generate_schedule_stats(&mut file, circuit, "ASAP", tech, |g| g.asap_level)?;
     
     // ALAP Schedule Statistics
-    generate_schedule_stats(&mut file, circuit, "ALAP", |g| g.alap_level)?;
+    generate_schedule_stats(&mut file, circuit, "ALAP", tech, |g| g.alap_level)?;
     
     // List Schedule Statistics 
-    generate_schedule_stats(&mut file, circuit, "LIST", |g| g.list_level)?;
+    generate_schedule_stats(&mut file, circuit, "LIST", tech, |g| g.list_level)?;
+    generate_list_bound_stats(&mut file, circuit, tech)?;
+
+    generate_cone_stats(&mut file, circuit)?;
+    generate_attribute_stats(&mut file, circuit)?;
     
     Ok(())
 }
 
-fn generate_schedule_stats<F>(
-    file: &mut File, 
+// The C implementation's stats: the three schedules and nothing else. Its
+// memristor counts depend on the order of the gates in each level, so the
+// stats are taken where the regular ones are, before mapping reorders them
+pub fn write_legacy_stats<W: Write>(circuit: &Circuit, file: &mut W) -> Result<()> {
+    generate_schedule_stats(file, circuit, "ASAP", None, |g| g.asap_level)?;
+    generate_schedule_stats(file, circuit, "ALAP", None, |g| g.alap_level)?;
+    generate_schedule_stats(file, circuit, "LIST", None, |g| g.list_level)?;
+    Ok(())
+}
+
+// Signals listed per attribute in the stats
+const ATTRIBUTE_SIGNALS_LISTED: usize = 8;
+
+// Gates carrying each netlist annotation, if any gate has one
+fn generate_attribute_stats(file: &mut File, circuit: &Circuit) -> Result<()> {
+    let usage = circuit.attribute_usage();
+    if usage.is_empty() {
+        return Ok(());
+    }
+    writeln!(file, "ATTRIBUTES:")?;
+    writeln!(file, "==========")?;
+    writeln!(file, "{:<20}{:>7}  Signals", "Attribute", "Gates")?;
+    for (attribute, signals) in &usage {
+        let mut names: Vec<String> = signals.iter().take(ATTRIBUTE_SIGNALS_LISTED).map(|&s| signal_name(s)).collect();
+        if signals.len() > ATTRIBUTE_SIGNALS_LISTED {
+            names.push("...".to_string());
+        }
+        writeln!(file, "{:<20}{:>7}  {}", attribute, signals.len(), names.join(" "))?;
+    }
+    Ok(())
+}
+
+// The list schedule's bound on gates per level and the depth smaller bounds
+// would cost, at powers of two up to the chosen one
+fn generate_list_bound_stats(file: &mut File, circuit: &Circuit, tech: Option<&Technology>) -> Result<()> {
+    if circuit.num_gates == 0 {
+        return Ok(());
+    }
+    let limit = scheduler::list_gate_limit(tech);
+    let bound = scheduler::list_bound(circuit, tech);
+    writeln!(file, "LIST SCHEDULE BOUND:")?;
+    writeln!(file, "=============")?;
+    match tech {
+        Some(tech) => writeln!(file, "Array limit       : {} gates per level ({})", limit, tech.name)?,
+        None => writeln!(file, "Array limit       : {} gates per level (crossbar rows)", limit)?,
+    }
+    writeln!(file, "Chosen bound      : {} gates per level", bound)?;
+    writeln!(file, "{:>8}{:>10}{:>10}{:>18}", "Bound", "Levels", "MaxGates", "Steps (parallel)")?;
+    let bounds = (0..).map(|k| 1usize << k).take_while(|&b| b < bound).chain([bound]);
+    for point in scheduler::list_tradeoff(circuit, bounds) {
+        let mark = if point.bound == bound { "*" } else { "" };
+        writeln!(file, "{:>8}{:>10}{:>10}{:>18}",
+            format!("{}{}", point.bound, mark), point.levels, point.max_gates, 2 * point.levels)?;
+    }
+    Ok(())
+}
+
+// Rows of each table in the cone statistics
+const CONE_STATS_ROWS: usize = 5;
+
+// The outputs with the deepest fanin cones, and the gate signals the most
+// other gates depend on
+fn generate_cone_stats(file: &mut File, circuit: &Circuit) -> Result<()> {
+    let gates = 0..circuit.num_gates;
+    if gates.is_empty() {
+        return Ok(());
+    }
+    writeln!(file, "CONES:")?;
+    writeln!(file, "=====")?;
+
+    let mut deepest: Vec<(usize, usize, i32)> = gates.clone()
+        .filter(|&i| circuit.gates[i].primary_outputs().next().is_some())
+        .flat_map(|i| {
+            let (depth, size) = (circuit.depth(i), circuit.fanin_cone(i).len());
+            circuit.gates[i].primary_outputs().map(move |out| (depth, size, out))
+        })
+        .collect();
+    deepest.sort_by_key(|&(depth, size, out)| (Reverse(depth), Reverse(size), out));
+    writeln!(file, "Deepest output cones:")?;
+    writeln!(file, "{:<10}{:>8}{:>8}", "Output", "Depth", "Gates")?;
+    for &(depth, size, out) in deepest.iter().take(CONE_STATS_ROWS) {
+        writeln!(file, "{:<10}{:>8}{:>8}", signal_name(out), depth, size)?;
+    }
+
+    // Dependents exclude the gate itself
+    let mut shared: Vec<(usize, usize, usize, i32)> = gates
+        .map(|i| {
+            let cone = circuit.fanout_cone(i);
+            let outputs = cone.iter().map(|&j| circuit.gates[j].primary_outputs().count()).sum();
+            (cone.len() - 1, circuit.gate_readers(i).count(), outputs, circuit.gates[i].out)
+        })
+        .collect();
+    shared.sort_by_key(|&(dependents, fanout, _, out)| (Reverse(dependents), Reverse(fanout), out));
+    writeln!(file, "Most shared signals:")?;
+    writeln!(file, "{:<10}{:>8}{:>12}{:>9}", "Signal", "Fanout", "Dependents", "Outputs")?;
+    for &(dependents, fanout, outputs, out) in shared.iter().take(CONE_STATS_ROWS) {
+        writeln!(file, "{:<10}{:>8}{:>12}{:>9}", signal_name(out), fanout, dependents, outputs)?;
+    }
+    Ok(())
+}
+
+fn generate_schedule_stats<W: Write, F>(
+    file: &mut W, 
     circuit: &Circuit, 
     schedule_name: &str,
+    tech: Option<&Technology>,
     level_getter: F
 ) -> Result<()>
 where
@@ -37,7 +171,7 @@ where
     
     // Gate distribution across levels
     let max_level = circuit.gates.iter()
-        .map(|g| level_getter(g))
+        .map(&level_getter)
         .max()
         .unwrap_or(0);
     
@@ -45,12 +179,10 @@ where
     // and limit the max size to avoid overflow (some circuits might have very high level values)
     let vector_size = max(1, max_level as usize).min(500);  // Limit to 500 levels for output display
     
+    let schedule = Schedule::new(circuit, &level_getter);
     let mut gate_count = vec![0; vector_size];
-    for gate in &circuit.gates {
-        let level = level_getter(gate);
-        if level > 0 && (level as usize) <= vector_size {
-            gate_count[(level - 1) as usize] += 1;
-        }
+    for (level, gates) in schedule.levels().take_while(|&(l, _)| l as usize <= vector_size) {
+        gate_count[(level - 1) as usize] = gates.len();
     }
     
     writeln!(file, "Gate distribution across levels:\n  {}", 
@@ -67,28 +199,26 @@ where
     // Count memristors and time steps
     let mut cross_rows = vec![0; max_gates];
     
-    for level in 1..=max_level {
+    for (_, gates) in schedule.levels() {
         let mut gates_level = 0;
         
-        for gate in &circuit.gates {
-            if level_getter(gate) == level {
-                if gate.fanin == 1 {
-                    // NOT gate
-                    if cross_rows[gates_level] == 2 {
-                        cross_rows[gates_level] = 3;
-                    } else {
-                        cross_rows[gates_level] = 1;
-                    }
-                    gates_level += 1;
-                } else if gate.fanin == 2 {
-                    // NOR gate
-                    if cross_rows[gates_level] == 1 {
-                        cross_rows[gates_level] = 3;
-                    } else {
-                        cross_rows[gates_level] = 2;
-                    }
-                    gates_level += 1;
+        for gate in gates.iter().map(|&i| &circuit.gates[i]) {
+            if gate.fanin == 1 {
+                // NOT gate
+                if cross_rows[gates_level] == 2 {
+                    cross_rows[gates_level] = 3;
+                } else {
+                    cross_rows[gates_level] = 1;
                 }
+                gates_level += 1;
+            } else if gate.fanin >= 2 {
+                // NOR gate
+                if cross_rows[gates_level] == 1 {
+                    cross_rows[gates_level] = 3;
+                } else {
+                    cross_rows[gates_level] = 2;
+                }
+                gates_level += 1;
             }
         }
     }
@@ -105,8 +235,8 @@ where
     
     // Calculate total memristors (serial configuration)
     let mut memr_serial = 0;
-    for i in 0..max_gates {
-        if cross_rows[i] == 1 {
+    for &row in cross_rows.iter().take(max_gates) {
+        if row == 1 {
             memr_serial += 2;
         } else {
             memr_serial += 3;
@@ -129,23 +259,339 @@ where
     writeln!(file, "Crossbar size (parallel): {} x {}", 
         max_gates, (3 * max_gates) - count)?;
     
+    if let Some(tech) = tech {
+        // Each level is two parallel steps over one row per gate, using three
+        // columns once a NOR is involved
+        let mut cycles = 0;
+        for level in 1..=max_level {
+            let gates = schedule.gates(level);
+            let cols = if gates.iter().any(|&i| circuit.gates[i].fanin > 1) { 3 } else { 2 };
+            cycles += 2 * tech.step_cycles(gates.len(), cols);
+        }
+        writeln!(file, "Time steps ({}): {} cycles, {:.2} ns",
+            tech.name, cycles, cycles as f64 * tech.clock_ns())?;
+    }
+    
+    Ok(())
+}
+
+// Appends the cycles of the emitted programs to a stats file, counted from
+// their micro-ops rather than the schedule depth
+pub fn append_program_cycles<P: AsRef<Path>>(path: P, programs: &[(&str, &ProgramCycles)]) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+    writeln!(file, "PROGRAM CYCLES:")?;
+    writeln!(file, "=============")?;
+    writeln!(file, "{:<10}{:>8}{:>8}{:>8}{:>8}{:>12}{:>8}", "Program", "Levels", "Reads", "Resets", "Writes", "Evaluations", "Total")?;
+    for (name, cycles) in programs {
+        writeln!(file, "{:<10}{:>8}{:>8}{:>8}{:>8}{:>12}{:>8}",
+            name, cycles.levels, cycles.reads(), cycles.resets, cycles.writes(), cycles.evaluations(), cycles.total())?;
+    }
+    Ok(())
+}
+
+// Appends the compact mapping's copies per signal, before and after
+// `scheduler::smooth_copies` spread them over levels, to a stats file
+pub fn append_copy_pressure<P: AsRef<Path>>(
+    path: P,
+    budget: usize,
+    before: &[CopyPressure],
+    after: &[CopyPressure],
+    delayed: usize,
+) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    let peak = |pressure: &[CopyPressure]| pressure.iter().map(|p| p.peak).max().unwrap_or(0);
+    writeln!(file, "COPY PRESSURE:")?;
+    writeln!(file, "=============")?;
+    writeln!(file, "Copied Signals    : {}", after.len())?;
+    writeln!(file, "Copies            : {}", after.iter().map(|p| p.copies).sum::<usize>())?;
+    writeln!(file, "Copy Budget       : {} per signal per level", budget)?;
+    writeln!(file, "Peak Copies/Level : {} before, {} after", peak(before), peak(after))?;
+    writeln!(file, "Delayed Gates     : {}", delayed)?;
+    if after.is_empty() {
+        return Ok(());
+    }
+
+    // The most copied signals
+    let before: HashMap<i32, &CopyPressure> = before.iter().map(|p| (p.signal, p)).collect();
+    writeln!(file, "\n{:<10}{:>8}{:>8}{:>13}{:>12}", "Signal", "Fanout", "Copies", "Peak Before", "Peak After")?;
+    for p in after.iter().take(10) {
+        let was = before.get(&p.signal).map_or(0, |b| b.peak);
+        writeln!(file, "{:<10}{:>8}{:>8}{:>13}{:>12}", signal_name(p.signal), p.fanout, p.copies,
+            format!("{} @{}", was, output::level_number(before.get(&p.signal).map_or(0, |b| b.peak_level))),
+            format!("{} @{}", p.peak, output::level_number(p.peak_level)))?;
+    }
+    Ok(())
+}
+
+// The `--cost` expression and what it comes to for each program
+pub fn append_cost<P: AsRef<Path>>(path: P, cost: &CostExpr, metrics: &RunMetrics) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    writeln!(file, "COST:")?;
+    writeln!(file, "====")?;
+    writeln!(file, "Expression        : {}", cost)?;
+    for (name, program) in [("Naive", &metrics.naive), ("Compact", &metrics.compact)] {
+        if let Some(value) = program.cost {
+            writeln!(file, "{:<18}: {}", name, value)?;
+        }
+    }
+    Ok(())
+}
+
+// Appends the circuit before and after its signals read by more than
+// `max_fanout` gates were split into trees, with the most read of them
+pub fn append_fanout_trees<P: AsRef<Path>>(path: P, circuit: &Circuit, max_fanout: usize, before: &FanoutProfile, after: &FanoutProfile) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    // Readers of the signals over the limit, as they are now
+    let split: Vec<(i32, usize, usize)> = before.overloaded.iter().map(|&(signal, readers)| {
+        let mut now = circuit.readers(signal).to_vec();
+        now.dedup();
+        (signal, readers, now.len())
+    }).collect();
+    writeln!(file, "FANOUT TREES:")?;
+    writeln!(file, "============")?;
+    writeln!(file, "Max Fanout        : {}", max_fanout)?;
+    writeln!(file, "Split Signals     : {} of {} over the limit", split.iter().filter(|(_, was, now)| now < was).count(), split.len())?;
+    writeln!(file, "Tree NOTs         : {}", after.gates - before.gates)?;
+    writeln!(file, "{:<26}{:>10}{:>10}", "", "Before", "After")?;
+    let rows = [
+        ("Gates", before.gates, after.gates),
+        ("Levels (ASAP)", before.levels.max(0) as usize, after.levels.max(0) as usize),
+        ("Most readers", before.max_fanout, after.max_fanout),
+        ("Most gates in a level", before.peak_level_gates, after.peak_level_gates),
+        ("Copies", before.copies, after.copies),
+        ("Most copies/signal/level", before.peak_copies, after.peak_copies),
+    ];
+    for (name, a, b) in rows {
+        writeln!(file, "{:<26}{:>10}{:>10}", name, a, b)?;
+    }
+    if split.is_empty() {
+        return Ok(());
+    }
+
+    // The most read signals and their readers since
+    writeln!(file, "\n{:<10}{:>10}{:>10}", "Signal", "Before", "After")?;
+    for &(signal, was, now) in split.iter().take(10) {
+        writeln!(file, "{:<10}{:>10}{:>10}", signal_name(signal), was, now)?;
+    }
+    Ok(())
+}
+
+// Appends the copies of the compact mapping before and after
+// `mapper::reuse_copies` to a stats file
+pub fn append_copy_reuse<P: AsRef<Path>>(path: P, reuse: &CopyReuse) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    writeln!(file, "COPY REUSE:")?;
+    writeln!(file, "==========")?;
+    writeln!(file, "Copies Before     : {}", reuse.before)?;
+    writeln!(file, "Copies After      : {}", reuse.after)?;
+    writeln!(file, "Reused Copies     : {}", reuse.reused())?;
+    writeln!(file, "Copy Cycles Saved : {}", COPY_CYCLES * reuse.reused())?;
+    Ok(())
+}
+
+// Appends the cells each program writes in the compute and storage regions
+// of the crossbar to a stats file
+pub fn append_region_usage<P: AsRef<Path>>(path: P, programs: &[(&str, &RegionUsage)]) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    writeln!(file, "REGIONS:")?;
+    writeln!(file, "=======")?;
+    writeln!(file, "{:<10}{:>14}{:>10}{:>14}{:>10}", "Program", "Compute Used", "Compute %", "Storage Used", "Storage %")?;
+    for (name, usage) in programs {
+        writeln!(file, "{:<10}{:>14}{:>10.1}{:>14}{:>10.1}", name,
+            format!("{}/{}", usage.compute_used, usage.compute_cells), 100.0 * usage.compute_share(),
+            format!("{}/{}", usage.storage_used, usage.storage_cells), 100.0 * usage.storage_share())?;
+    }
+    Ok(())
+}
+
+// Appends how far each program's gates sit from their operands to a stats
+// file, with a histogram of the spans side by side
+pub fn append_column_spans<P: AsRef<Path>>(path: P, programs: &[(&str, &ColumnSpans)], limit: Option<usize>) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    writeln!(file, "COLUMN SPANS:")?;
+    writeln!(file, "============")?;
+    if let Some(limit) = limit {
+        writeln!(file, "Max Span          : {} columns (compact)", limit)?;
+    }
+    writeln!(file, "{:<10}{:>8}{:>8}{:>8}", "Program", "Gates", "Max", "Mean")?;
+    for (name, spans) in programs {
+        writeln!(file, "{:<10}{:>8}{:>8}{:>8.2}", name, spans.gates(), spans.max(), spans.mean())?;
+    }
+
+    let max = programs.iter().map(|(_, spans)| spans.max()).max().unwrap_or(0);
+    let histograms: Vec<_> = programs.iter().map(|(_, spans)| spans.histogram(max)).collect();
+    write!(file, "\n{:<10}", "Span")?;
+    for (name, _) in programs {
+        write!(file, "{:>10}", name)?;
+    }
+    writeln!(file)?;
+    for (b, ((low, high), _)) in histograms[0].iter().enumerate() {
+        let span = if low == high { low.to_string() } else { format!("{}-{}", low, high) };
+        write!(file, "{:<10}", span)?;
+        for histogram in &histograms {
+            write!(file, "{:>10}", histogram[b].1)?;
+        }
+        writeln!(file)?;
+    }
+    Ok(())
+}
+
+// Appends the circuit's hotspots to a stats file: the deepest paths in full,
+// and the first few signals of each level and row
+pub fn append_hotspots<P: AsRef<Path>>(path: P, hotspots: &Hotspots) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    const LISTED: usize = 8;
+    let names = |signals: &[i32]| {
+        let mut names: Vec<String> = signals.iter().take(LISTED).map(|&s| signal_name(s)).collect();
+        if signals.len() > LISTED {
+            names.push(format!("+{} more", signals.len() - LISTED));
+        }
+        names.join(" ")
+    };
+    writeln!(file, "HOTSPOTS:")?;
+    writeln!(file, "========")?;
+    writeln!(file, "{:<8}{:<10}Deepest Path", "Depth", "Output")?;
+    for path in &hotspots.paths {
+        let signals: Vec<String> = path.iter().map(|&s| signal_name(s)).collect();
+        writeln!(file, "{:<8}{:<10}{}", path.len() - 1, signal_name(path[path.len() - 1]), signals.join(" -> "))?;
+    }
+    writeln!(file, "\n{:<10}{:>8}", "Signal", "Fanout")?;
+    for &(signal, readers) in &hotspots.fanout {
+        writeln!(file, "{:<10}{:>8}", signal_name(signal), readers)?;
+    }
+    writeln!(file, "\n{:<8}{:>8}  Signals", "Level", "Gates")?;
+    for (level, outs) in &hotspots.levels {
+        writeln!(file, "{:<8}{:>8}  {}", output::level_number(*level), outs.len(), names(outs))?;
+    }
+    writeln!(file, "\n{:<8}{:>8}  Copied Signals", "Row", "Copies")?;
+    for (row, copied) in &hotspots.rows {
+        writeln!(file, "{:<8}{:>8}  {}", row, copied.len(), names(copied))?;
+    }
+    Ok(())
+}
+
+// Appends a side-by-side comparison of the NOR flow and another primitive's
+// flow for the same netlist to a stats file
+pub fn append_primitive_comparison<P: AsRef<Path>>(path: P, flows: &[FlowMetrics]) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    writeln!(file, "PRIMITIVE COMPARISON:")?;
+    writeln!(file, "=============")?;
+    let header: Vec<String> = flows.iter().map(|f| format!("{:>10}", f.primitive)).collect();
+    writeln!(file, "{:<18}{}", "", header.join(""))?;
+    let rows = [
+        ("Gates", flows.iter().map(|f| f.gates.to_string()).collect::<Vec<_>>()),
+        ("NOT gates", flows.iter().map(|f| f.not_gates.to_string()).collect()),
+        ("Two-input gates", flows.iter().map(|f| (f.gates - f.not_gates).to_string()).collect()),
+        ("Levels (ASAP)", flows.iter().map(|f| f.levels.to_string()).collect()),
+        ("Compact crossbar", flows.iter().map(|f| format!("{}x{}", f.rows, f.cols)).collect()),
+        ("Memristors used", flows.iter().map(|f| f.cells.to_string()).collect()),
+    ];
+    for (name, values) in rows {
+        let values: Vec<String> = values.iter().map(|v| format!("{:>10}", v)).collect();
+        writeln!(file, "{:<18}{}", name, values.join(""))?;
+    }
+    Ok(())
+}
+
+// Appends the circuit's size and compact program before and after primary
+// inputs were bound to constants (`bindings` as given, e.g. "x3=1,x7=0")
+pub fn append_partial_evaluation<P: AsRef<Path>>(path: P, bindings: &str, before: &FlowMetrics, after: &FlowMetrics) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .context("Failed to open stats file")?;
+
+    writeln!(file, "PARTIAL EVALUATION:")?;
+    writeln!(file, "=============")?;
+    writeln!(file, "Bound inputs      : {}", bindings)?;
+    writeln!(file, "{:<18}{:>10}{:>10}{:>10}", "", "Unbound", "Bound", "Saved")?;
+    let saved = |a: usize, b: usize| if a == 0 { "-".to_string() } else { format!("{:.1}%", 100.0 * (a as f64 - b as f64) / a as f64) };
+    let rows = [
+        ("Gates", before.gates, after.gates),
+        ("NOT gates", before.not_gates, after.not_gates),
+        ("Levels (ASAP)", before.levels.max(0) as usize, after.levels.max(0) as usize),
+        ("Memristors used", before.cells, after.cells),
+        ("Program cycles", before.cycles, after.cycles),
+    ];
+    for (name, a, b) in rows {
+        writeln!(file, "{:<18}{:>10}{:>10}{:>10}", name, a, b, saved(a, b))?;
+    }
+    writeln!(file, "{:<18}{:>10}{:>10}", "Compact crossbar",
+        format!("{}x{}", before.rows, before.cols), format!("{}x{}", after.rows, after.cols))?;
     Ok(())
 }
 
 pub fn generate_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Result<()> {
+    write_magic_verilog(circuit, false, None, path)
+}
+
+// The same with each gate instance in `sites` preceded by where the
+// compact program evaluates it:
+//   (* row = 2, col = 5, level = 3, copies = 1 *)
+pub fn generate_annotated_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, sites: &HashMap<i32, GateSite>, path: P) -> Result<()> {
+    write_magic_verilog(circuit, false, Some(sites), path)
+}
+
+// The C implementation's Verilog, which names every gate's output wr_K in
+// the gate instances, outputs too
+pub fn generate_legacy_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Result<()> {
+    ensure!(circuit.inverted_outputs.is_empty() && circuit.complemented_inputs.is_empty(),
+        "--legacy-compat: the C implementation's Verilog has no inverted read-outs or active-low inputs");
+    write_magic_verilog(circuit, true, None, path)
+}
+
+fn write_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, legacy: bool, sites: Option<&HashMap<i32, GateSite>>, path: P) -> Result<()> {
     //println!("[VERILOG] Opening file: {:?}", path.as_ref());
     let mut file = File::create(path)
         .context("Failed to create Verilog file")?;
     //println!("[VERILOG] File opened successfully.");
 
-    // Sort gates by ASAP level for correct ordering
-    //println!("[VERILOG] Sorting gates by ASAP level...");
-    let mut sorted_gates = circuit.gates.clone();
-    sorted_gates.sort_by(|a, b| a.asap_level.cmp(&b.asap_level));
-    //println!("[VERILOG] Gates sorted. Total gates: {}", sorted_gates.len());
+    // Every gate after its drivers, whatever order the mappers left the gates
+    // in; the C implementation listed them by ASAP level
+    let mut sorted_gates: Vec<&TableGate> = circuit.gates_in_topological_order().into_iter()
+        .map(|i| &circuit.gates[i])
+        .collect();
+    if legacy {
+        sorted_gates.sort_by_key(|g| g.asap_level);
+    }
 
     // Generate verilog header with bench name (following C format)
-    writeln!(file, "// NOR_NOT mapped module module_name\n")?;
+    writeln!(file, "// {}_NOT mapped module module_name\n", circuit.primitive)?;
     //println!("[VERILOG] Wrote header comment.");
 
     // Module declaration - use module_name like the C implementation
@@ -154,49 +600,39 @@ pub fn generate_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Res
 
     // Inputs - use ip_X format like the C implementation 
     //println!("[VERILOG] Declaring {} inputs...", circuit.num_inputs);
-    if circuit.num_inputs == 0 {
-        println!("[VERILOG][WARNING] No inputs detected!");
-    }
-    for i in 0..(circuit.num_inputs.saturating_sub(1)) {
-        writeln!(file, "  input  ip_{},", i + 1)?;
-    }
-    if circuit.num_inputs > 0 {
-        writeln!(file, "  input  ip_{},", circuit.num_inputs)?;
-    }
-    //println!("[VERILOG] Inputs declared.");
+    // Active-low inputs carry the complement of the input's value
+    let mut ports: Vec<String> = (1..=circuit.num_inputs)
+        .map(|i| match circuit.complemented_inputs.contains(&(i - 1)) {
+            true => format!("  (* active_low *) input  ip_{}", i),
+            false => format!("  input  ip_{}", i),
+        })
+        .collect();
 
-    // Outputs - use op_X format like the C implementation
-    //println!("[VERILOG] Declaring {} outputs...", circuit.num_outputs);
-    if circuit.num_outputs == 0 {
-        println!("[VERILOG][WARNING] No outputs detected!");
-    }
-    for i in 0..(circuit.num_outputs.saturating_sub(1)) {
-        writeln!(file, "  output op_{},", i + 1)?;
-    }
-    if circuit.num_outputs > 0 {
-        writeln!(file, "  output op_{}\n);", circuit.num_outputs)?;
-    }
-    //println!("[VERILOG] Outputs declared.");
+    // Outputs - use op_X format like the C implementation, numbered by the
+    // declared output index so the port list matches the driving gates
+    let outputs = output_ports(circuit);
+    // Outputs read out inverted are marked with the signal they are read from
+    ports.extend(outputs.iter().map(|k| match circuit.inverted_outputs.get(k) {
+        Some(&source) => format!("  (* inverted_readout = \"{}\" *) output op_{}", signal_name(source), k),
+        None => format!("  output op_{}", k),
+    }));
+    writeln!(file, "{}\n);", ports.join(",\n"))?;
+    let outputs: HashSet<i32> = outputs.into_iter().collect();
+    let named = if legacy { HashSet::new() } else { outputs.clone() };
 
     // Internal wires
     writeln!(file)?;
-    // println!(
-    //     "[VERILOG] Declaring internal wires for indices {} to {}...",
-    //     circuit.num_outputs + 1,
-    //     circuit.num_gates
-    // );
-    // if circuit.num_gates < circuit.num_outputs + 1 {
-    //     println!(
-    //         "[VERILOG][WARNING] num_gates < num_outputs+1: {} < {}",
-    //         circuit.num_gates,
-    //         circuit.num_outputs + 1
-    //     );
-    // }
-    for i in (circuit.num_outputs + 1)..=circuit.num_gates {
-        writeln!(file, "  wire wr_{};", i)?;
+    let mut wires: Vec<i32> = circuit.gates.iter()
+        .flat_map(|g| g.outs())
+        .filter(|out| !outputs.contains(out))
+        .collect();
+    // Named wires first in numeric order, then expansion temporaries
+    wires.sort_unstable_by_key(|&w| (w < 0, w.abs()));
+    wires.dedup();
+    for wire in wires {
+        writeln!(file, "  wire {};", format_wire(wire, &outputs).trim_end())?;
     }
     writeln!(file)?;
-    //println!("[VERILOG] Internal wires declared.");
 
     // Generate gate instances
     //println!("[VERILOG] Generating gate instances...");
@@ -208,32 +644,50 @@ pub fn generate_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Res
         //     gate_name, gate.fanin, gate.out, gate.inputs
         // );
 
+        if let Some(site) = sites.and_then(|sites| sites.get(&gate.out)) {
+            writeln!(file, "  (* row = {}, col = {}, level = {}, copies = {} *)",
+                site.cell.row, site.cell.col, output::level_number(site.level), site.copies)?;
+        }
+
         if gate.fanin == 1 {
             // NOT gate
             let ip1 = gate.inputs[0];
             writeln!(
                 file,
-                "  not    {}( {} ,           {} );",
-                format!("{:<5}", gate_name),
-                format_wire(gate.out),
-                format_wire(ip1)
+                "  not    {:<5}( {} ,           {} );",
+                gate_name,
+                format_wire(gate.out, &named),
+                format_wire(ip1, &named)
             )?;
         } else {
-            // NOR gate
-            let ip1 = gate.inputs[0];
-            let ip2 = gate.inputs[1];
+            // NOR or NAND gate, of two or more inputs
+            let inputs: Vec<String> = gate.inputs[..gate.fanin].iter()
+                .map(|&ip| format_wire(ip, &named))
+                .collect();
             writeln!(
                 file,
-                "  nor    {}( {} , {} , {} );",
-                format!("{:<5}", gate_name),
-                format_wire(gate.out),
-                format_wire(ip1),
-                format_wire(ip2)
+                "  {:<6} {:<5}( {} , {} );",
+                GateKind::of(circuit.primitive, gate.fanin).spec().verilog,
+                gate_name,
+                format_wire(gate.out, &named),
+                inputs.join(" , ")
             )?;
         }
+        // A multi-output gate's other signals carry its value
+        for extra in &gate.extra_outs {
+            writeln!(file, "  assign {} = {};", format_wire(extra.signal, &outputs).trim_end(), format_wire(gate.out, &named).trim_end())?;
+        }
     }
     //println!("[VERILOG] All gate instances written.");
 
+    // The read-out inverts these outputs; no gate of the crossbar does
+    if !circuit.inverted_outputs.is_empty() {
+        writeln!(file)?;
+    }
+    for (&output, &source) in &circuit.inverted_outputs {
+        writeln!(file, "  assign {} = ~{};", format_wire(output, &outputs).trim_end(), format_wire(source, &outputs).trim_end())?;
+    }
+
     // Module end
     writeln!(file, "\nendmodule")?;
     //println!("[VERILOG] Wrote endmodule. Verilog generation complete.");
@@ -241,17 +695,88 @@ pub fn generate_magic_verilog<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Res
     Ok(())
 }
 
+// The circuit as it was scheduled and mapped, after line expansion, wide-gate
+// splitting and the passes, one gate per line in ASAP order:
+//   n8 = NOR(n7, n4)        # level 2
+// A native netlist again: temporaries get free n-numbers, outputs carry the
+// output bias, and outputs read out inverted and active-low inputs, which
+// no gate computes, are listed as comments
+pub fn generate_decomposed_netlist<P: AsRef<Path>>(circuit: &Circuit, path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create decomposed netlist")?);
+    let gates = &circuit.gates[..circuit.num_gates];
+    let original: Vec<&TableGate> = circuit.gates_in_original_order().into_iter().map(|i| &gates[i]).collect();
+
+    // Temporaries are numbered after the highest named gate, in netlist order
+    let next = gates.iter().map(|g| g.out).filter(|&out| out >= 0).max().map_or(0, |out| out + 1);
+    let names: HashMap<i32, i32> = original.iter()
+        .filter(|g| g.out < 0)
+        .map(|g| g.out)
+        .zip(next..)
+        .collect();
+    ensure!(next + (names.len() as i32) <= MAX_GATES as i32,
+        "No free signal numbers left for {} temporaries", names.len());
+    let name = |s: i32| signal_name(names.get(&s).copied().unwrap_or(s));
 
-fn format_wire(id: i32) -> String {
+    let nots = gates.iter().filter(|g| g.fanin == 1).count();
+    writeln!(file, "# Decomposed netlist: {} gates ({} NOT, {} {}), {} inputs, {} outputs",
+        gates.len(), nots, gates.len() - nots, circuit.primitive, circuit.num_inputs, circuit.num_outputs)?;
+    for input in &circuit.complemented_inputs {
+        writeln!(file, "# {} is loaded active-low", signal_name((MAX_GATES + input) as i32))?;
+    }
+    for (&output, &source) in &circuit.inverted_outputs {
+        writeln!(file, "# n{} = NOT({})    read out inverted", output + OUT_BIAS as i32, name(source))?;
+    }
+
+    // By level, each level in netlist order
+    let mut sorted = original;
+    sorted.sort_by_key(|g| g.asap_level);
+    for gate in sorted {
+        let function = GateKind::of(circuit.primitive, gate.fanin);
+        let operands: Vec<String> = gate.inputs[..gate.fanin].iter().map(|&s| name(s)).collect();
+        let mut outs = vec![match gate.is_output {
+            true => format!("n{}", gate.out + OUT_BIAS as i32),
+            false => name(gate.out),
+        }];
+        outs.extend(gate.extra_outs.iter().map(|o| match o.is_output {
+            true => format!("n{}", o.signal + OUT_BIAS as i32),
+            false => name(o.signal),
+        }));
+        let mut line = format!("{} = {}({})", outs.join(" "), function, operands.join(", "));
+        if !gate.attributes.is_empty() {
+            line += &format!(" {}", gate.attributes);
+        }
+        let temporary = if gate.out < 0 { format!("{}, ", signal_name(gate.out)) } else { String::new() };
+        writeln!(file, "{:<23} # {}level {}", line, temporary, output::level_number(gate.asap_level))?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Output ports of the MAGIC module, op_K for each K in order
+fn output_ports(circuit: &Circuit) -> Vec<i32> {
+    let mut outputs: Vec<i32> = circuit.gates.iter()
+        .flat_map(|g| g.primary_outputs())
+        .chain(circuit.inverted_outputs.keys().copied())
+        .collect();
+    outputs.sort_unstable();
+    outputs.dedup();
+    outputs
+}
+
+fn format_wire(id: i32, outputs: &HashSet<i32>) -> String {
     if id >= MAX_GATES as i32 {
         // Primary inputs - use ip_X format following C implementation 
         format!("ip_{:<5}", id - MAX_GATES as i32 + 1)
+    } else if outputs.contains(&id) {
+        // Outputs - use op_X format following C implementation
+        format!("op_{:<5}", id)
     } else if id > 0 {
         // Internal wires - use wr_X format
         format!("wr_{:<5}", id)
     } else {
-        // Outputs - use op_X format following C implementation
-        format!("op_{:<5}", -id)
+        // Temporaries introduced by expanding 4/5-variable lines
+        format!("tmp_{:<4}", -id)
     }
 }
 
@@ -260,106 +785,759 @@ pub fn generate_micro_ops<P: AsRef<Path>>(
     mapping: &CrossbarMapping, 
     is_naive: bool,
     path: P
+) -> Result<Program> {
+    generate_micro_ops_with_tech(circuit, mapping, is_naive, None, false, path)
+}
+
+// Micro-ops whose metrics also cost the program on a concrete technology.
+// Annotated listings add a legend and name each op's signals and gate
+pub fn generate_micro_ops_with_tech<P: AsRef<Path>>(
+    circuit: &Circuit,
+    mapping: &CrossbarMapping,
+    is_naive: bool,
+    tech: Option<&Technology>,
+    annotate: bool,
+    path: P
+) -> Result<Program> {
+    let program = Program::from_mapping(circuit, mapping)?;
+    let signals = annotate.then(|| program::cell_signals(mapping));
+    write_program(circuit, &program, is_naive, tech, signals.as_ref(), path)?;
+    Ok(program)
+}
+
+// Compact micro-ops built straight from a `CompactPlan`, without building
+// the crossbar. Produces the same file as `create_compact_mapping` +
+// `generate_micro_ops`
+pub fn stream_compact_micro_ops<P: AsRef<Path>>(circuit: &mut Circuit, path: P) -> Result<Program> {
+    stream_compact_micro_ops_with_tech(circuit, None, false, path)
+}
+
+pub fn stream_compact_micro_ops_with_tech<P: AsRef<Path>>(
+    circuit: &mut Circuit,
+    tech: Option<&Technology>,
+    annotate: bool,
+    path: P
+) -> Result<Program> {
+    for i in 0..circuit.num_gates {
+        circuit.gates[i].gate_map = None;
+    }
+    circuit.sort_gates_by_key(|g| g.asap_level);
+    
+    let placement = tech.map(|t| &t.periphery)
+        .filter(|p| p.constrains_placement())
+        .map(Placement::new);
+    let Some(plan) = CompactPlan::new(circuit) else {
+        // No primary inputs, a malformed netlist or wide NORs: nothing the plan
+        // can stream
+        let mut mapping = mapper::create_compact_mapping(circuit);
+        if let Some(ref placement) = placement {
+            mapping = placement.apply(circuit, &mapping).context("Compact mapping does not fit the crossbar")?;
+        }
+        return generate_micro_ops_with_tech(circuit, &mapping, false, tech, annotate, path);
+    };
+    let program = Program::from_plan(circuit, &plan, placement.as_ref())?;
+    let signals = annotate.then(|| program::plan_signals(circuit, &plan, placement.as_ref()));
+    write_program(circuit, &program, false, tech, signals.as_ref(), path)?;
+    Ok(program)
+}
+
+// The text listing of a program with its Metrics footer
+fn write_program<P: AsRef<Path>>(
+    circuit: &Circuit,
+    program: &Program,
+    is_naive: bool,
+    tech: Option<&Technology>,
+    signals: Option<&HashMap<Cell, i32>>,
+    path: P
 ) -> Result<()> {
-    let mut file = File::create(path)
-        .context("Failed to create micro-ops file")?;
-    
-    let mut curr_level = 0;
-    let mut any_gates_printed = false;
-    
-    // Process by level - matches C implementation
-    for l in 0..circuit.max_asap {
-        for i in 0..=mapping.max_idx as usize {
-            for j in 0..=mapping.max_jdx as usize {
-                // Skip irrelevant gates - same logic as C implementation
-                if mapping.crossbar[i][j].value == -1 || 
-                   mapping.crossbar[i][j].value >= MAX_GATES as i32 ||
-                   mapping.crossbar[i][j].is_copy || 
-                   mapping.crossbar[i][j].asap_level != l {
-                    continue;
-                }
-                
-                // Print level header when level changes - matches C format
-                if mapping.crossbar[i][j].asap_level > curr_level {
-                    curr_level = mapping.crossbar[i][j].asap_level;
-                    writeln!(file, "# Level: {:2} _____________________________________", curr_level)?;
-                }
-                
-                any_gates_printed = true;
-                
-                // Print gate information - matches C format exactly
-                write!(file, "{:4} {:5} ", mapping.crossbar[i][j].idx, "False")?;
-                
-                if let Some(ref ip1) = mapping.crossbar[i][j].inputs[0] {
-                    write!(file, "{:4} ", ip1.jdx)?;
-                    write!(file, "{:9} ", format_gate_name(ip1))?;
-                } else {
-                    write!(file, "{:14} ", " ")?;
-                }
-                
-                if mapping.crossbar[i][j].fanin > 1 {
-                    if let Some(ref ip2) = mapping.crossbar[i][j].inputs[1] {
-                        write!(file, "{:4}", ip2.jdx)?;
-                        write!(file, "{:9} ", format_gate_name(ip2))?;
-                    } else {
-                        write!(file, "{:14} ", " ")?;
-                    }
-                } else {
-                    write!(file, "{:14}", " ")?;
-                }
-                
-                writeln!(file, "{:4} True", mapping.crossbar[i][j].jdx)?;
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create micro-ops file")?);
+    match signals {
+        Some(signals) => TextListing::new(program, 1).annotated(signals).write_until(&mut file, usize::MAX)?,
+        None => program.write_text(&mut file, 1)?,
+    }
+    let tally = tech.map(|tech| TechTally::new(tech, program, is_naive));
+    write_metrics(&mut file, circuit, program, is_naive, tally.as_ref())?;
+    file.flush()?;
+    Ok(())
+}
+
+// A program in one of the machine-readable formats
+pub fn export_program<P: AsRef<Path>>(program: &Program, format: ProgramFormat, path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create program file")?);
+    match format {
+        ProgramFormat::Json => program.write_json(&mut file)?,
+        ProgramFormat::Binary => program.write_binary(&mut file)?,
+        ProgramFormat::Python => program.write_python(&mut file)?,
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Colors of the overlay's cell classes, and the outlines of its layers
+const OVERLAY_COLORS: [(CellDiff, &str); 4] = [
+    (CellDiff::Same, "#b0b0b0"),
+    (CellDiff::Changed, "#e6a100"),
+    (CellDiff::OnlyFirst, "#1f77b4"),
+    (CellDiff::OnlySecond, "#d62728"),
+];
+// Beyond this many moved signals, their arrows would hide the cells
+const OVERLAY_MAX_ARROWS: usize = 256;
+
+// Both layers of an overlay on one SVG canvas: the cells each writes,
+// colored by how they compare, the outline of each program's array, and an
+// arrow per signal that moved for small circuits. Every cell has a tooltip
+// with what each layer writes into it
+pub fn generate_overlay<P: AsRef<Path>>(overlay: &Overlay, title: &str, path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create overlay image")?);
+    let (rows, cols) = (overlay.rows().max(1), overlay.cols().max(1));
+    // Cells shrink to keep large arrays within about 1200 pixels
+    let size = (1200 / rows.max(cols)).clamp(2, 16);
+    let (left, top) = (10, 70);
+    let (width, height) = ((left + cols * size + 10).max(620), top + rows * size + 10);
+    let (first, second) = (escape_xml(&overlay.first.label), escape_xml(&overlay.second.label));
+    let moved = overlay.moved();
+
+    writeln!(file, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="monospace" font-size="12">"#,
+        width, height, width, height)?;
+    writeln!(file, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    writeln!(file, r#"<text x="{}" y="18" font-weight="bold">{}: {} vs {} ({} signals moved)</text>"#,
+        left, escape_xml(title), first, second, moved.len())?;
+    for (k, (diff, color)) in OVERLAY_COLORS.iter().enumerate() {
+        let label = match diff {
+            CellDiff::Same => "same".to_string(),
+            CellDiff::Changed => "changed".to_string(),
+            CellDiff::OnlyFirst => format!("{} only", first),
+            CellDiff::OnlySecond => format!("{} only", second),
+        };
+        let x = left + k * 150;
+        writeln!(file, r#"<rect x="{}" y="28" width="10" height="10" fill="{}"/><text x="{}" y="37">{} ({})</text>"#,
+            x, color, x + 14, label, overlay.count(*diff))?;
+    }
+    writeln!(file, r#"<text x="{}" y="56" fill="{}">{} {}x{} (dashed outline)</text><text x="{}" y="56" fill="{}">{} {}x{} (solid outline)</text>"#,
+        left, OVERLAY_COLORS[2].1, first, overlay.first.rows, overlay.first.cols,
+        left + 300, OVERLAY_COLORS[3].1, second, overlay.second.rows, overlay.second.cols)?;
+
+    for (cell, diff) in &overlay.cells {
+        let color = OVERLAY_COLORS.iter().find(|(d, _)| d == diff).map_or("black", |(_, c)| c);
+        let describe = |layer: &Layer| layer.cells.get(cell).map_or("empty".to_string(), |u| u.describe());
+        writeln!(file, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"><title>{} {}: {}; {}: {}</title></rect>"#,
+            left + cell.col as usize * size, top + cell.row as usize * size, size, size, color,
+            cell, first, escape_xml(&describe(&overlay.first)), second, escape_xml(&describe(&overlay.second)))?;
+    }
+    for (layer, color, dash) in [(&overlay.first, OVERLAY_COLORS[2].1, r#" stroke-dasharray="4 2""#), (&overlay.second, OVERLAY_COLORS[3].1, "")] {
+        writeln!(file, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="{}"{}/>"#,
+            left, top, layer.cols * size, layer.rows * size, color, dash)?;
+    }
+    if moved.len() <= OVERLAY_MAX_ARROWS {
+        let center = |cell: &Cell| {
+            let half = size as f64 / 2.0;
+            ((left + cell.col as usize * size) as f64 + half, (top + cell.row as usize * size) as f64 + half)
+        };
+        for (signal, from, to) in &moved {
+            let ((x1, y1), (x2, y2)) = (center(from), center(to));
+            writeln!(file, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" stroke-opacity="0.3"><title>{}: {} to {}</title></line>"#,
+                x1, y1, x2, y2, signal_name(*signal), from, to)?;
+        }
+    }
+    writeln!(file, "</svg>")?;
+    file.flush()?;
+    Ok(())
+}
+
+// Fill of an animated cell holding 1, holding 0 and initialized to wait for
+// its evaluation; outlines of the cells a frame writes and senses
+const ANIMATION_COLORS: [(&str, &str); 5] = [
+    ("1", "#1f77b4"),
+    ("0", "#e0e0e0"),
+    ("initialized", "#f2c94c"),
+    ("written", "#d62728"),
+    ("sensed", "#2ca02c"),
+];
+// Each frame redraws every written cell, so long programs make huge images
+pub const ANIMATION_MAX_FRAMES: usize = 2000;
+
+// A program's execution on one input vector as an SVG animation, one frame
+// per entry of its trace shown for `frame_ms` each and looping. Each frame
+// shows every cell written so far with its bit, outlines the cells the frame
+// writes and senses and names its cycle, level and phase
+pub fn generate_animation<P: AsRef<Path>>(program: &Program, frames: &[TraceFrame], title: &str, frame_ms: u64, path: P) -> Result<()> {
+    ensure!(frames.len() <= ANIMATION_MAX_FRAMES,
+        "Program runs in {} frames, more than the {} an animation shows; animate a smaller circuit",
+        frames.len(), ANIMATION_MAX_FRAMES);
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create animation")?);
+    let (rows, cols) = (program.rows.max(1), program.cols.max(1));
+    // Cells shrink to keep large arrays within about 1200 pixels
+    let size = (1200 / rows.max(cols)).clamp(4, 24);
+    let (left, top) = (10, 70);
+    let (width, height) = ((left + cols * size + 10).max(620), top + rows * size + 10);
+    let total = frame_ms.max(1) * frames.len().max(1) as u64;
+
+    writeln!(file, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="monospace" font-size="12">"#,
+        width, height, width, height)?;
+    writeln!(file, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    writeln!(file, r#"<text x="{}" y="18" font-weight="bold">{}: {}x{} crossbar, {} frames</text>"#,
+        left, escape_xml(title), program.rows, program.cols, frames.len())?;
+    for (k, (label, color)) in ANIMATION_COLORS.iter().enumerate() {
+        let x = left + k * 110;
+        let swatch = if k < 3 { format!(r#"fill="{}""#, color) } else { format!(r#"fill="none" stroke="{}" stroke-width="2""#, color) };
+        writeln!(file, r#"<rect x="{}" y="28" width="10" height="10" {}/><text x="{}" y="37">{}</text>"#, x, swatch, x + 14, label)?;
+    }
+    writeln!(file, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+        left, top, program.cols * size, program.rows * size)?;
+
+    let rect = |cell: &Cell, style: &str| format!(r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+        left + cell.col as usize * size, top + cell.row as usize * size, size, size, style);
+    for (k, frame) in frames.iter().enumerate() {
+        // Shown from its start to the next frame's, hidden the rest of the loop
+        let (from, to) = (k as f64 / frames.len() as f64, (k + 1) as f64 / frames.len() as f64);
+        writeln!(file, r#"<g opacity="0"><animate attributeName="opacity" values="0;1;0" keyTimes="0;{:.6};{:.6}" dur="{}ms" calcMode="discrete" repeatCount="indefinite"/>"#,
+            from, to, total)?;
+        let outputs: String = frame.outputs.iter().map(|o| o.map_or('-', |b| if b { '1' } else { '0' })).collect();
+        writeln!(file, r#"<text x="{}" y="56">cycle {} (+{}), level {}: {}, outputs {}</text>"#,
+            left, frame.cycle, frame.cycles, output::level_number(frame.level), frame.phase, outputs)?;
+        for (cell, value) in &frame.cells {
+            let color = match value {
+                Some(true) => ANIMATION_COLORS[0].1,
+                Some(false) => ANIMATION_COLORS[1].1,
+                None => ANIMATION_COLORS[2].1,
+            };
+            writeln!(file, "{}", rect(cell, &format!(r#"fill="{}""#, color)))?;
+        }
+        for (cells, color) in [(&frame.writes, ANIMATION_COLORS[3].1), (&frame.reads, ANIMATION_COLORS[4].1)] {
+            for cell in cells {
+                writeln!(file, "{}", rect(cell, &format!(r#"fill="none" stroke="{}" stroke-width="2""#, color)))?;
             }
         }
+        writeln!(file, "</g>")?;
+    }
+    writeln!(file, "</svg>")?;
+    file.flush()?;
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Occupancy of the crossbar over a program's cycles as CSV, one row per cycle
+pub fn generate_timeline<P: AsRef<Path>>(program: &Program, path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create timeline file")?);
+    writeln!(file, "cycle,level,phase,active_ops,occupied_cells,live_cells,crossbar_cells")?;
+    let area = program.rows * program.cols;
+    for usage in program.timeline() {
+        writeln!(file, "{},{},{},{},{},{},{}",
+            usage.cycle, output::level_number(usage.level), usage.phase, usage.active, usage.occupied, usage.live, area)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Programs for a tiled pipeline, one per tile, plus a report comparing the
+// pipeline's throughput and latency with the single-crossbar schedule. Each
+// program receives its live-in signals into the input rows, evaluates the
+// tile's levels (numbered as in the whole schedule), sends its live-out
+// cells to the next tile and waits at the barrier shared by all tiles
+pub fn generate_pipeline<P: AsRef<Path>>(
+    pipeline: &mut TiledPipeline,
+    programs: &[PathBuf],
+    report: P
+) -> Result<()> {
+    let count = pipeline.tiles.len();
+    let interval = pipeline.initiation_interval();
+    let mut sizes = Vec::with_capacity(count);
+    for (k, (tile, path)) in pipeline.tiles.iter_mut().zip(programs).enumerate() {
+        let mapping = mapper::create_compact_mapping(&mut tile.circuit);
+        // Tiles send their outputs on rather than reading them
+        let program = Program::from_mapping(&tile.circuit, &mapping)?.without_read_phase();
+        let mut file = BufWriter::new(File::create(path)
+            .context("Failed to create tile program")?);
+        
+        for (row, &signal) in tile.live_in.iter().enumerate() {
+            writeln!(file, "RECV {:4} {}", row, signal_name(signal))?;
+        }
+        program.write_text(&mut file, tile.first_level)?;
+        let received: HashMap<i32, usize> = tile.live_in.iter().enumerate().map(|(row, &s)| (s, row)).collect();
+        for &signal in &tile.live_out {
+            let (row, col) = match received.get(&signal) {
+                Some(&row) => (row as i32, 0),
+                None => tile.circuit.driver(signal)
+                    .and_then(|g| g.gate_map.as_ref())
+                    .map(|cell| (cell.idx, cell.jdx))
+                    .context(format!("Live-out signal {} of tile {} is not mapped", signal_name(signal), k))?,
+            };
+            writeln!(file, "SEND {:>9} {}", format!("{}x{}", row, col), signal_name(signal))?;
+        }
+        writeln!(file, "SYNC")?;
+        
+        let size = (mapping.max_idx.max(0) + 1, mapping.max_jdx.max(0) + 1);
+        writeln!(file, "\nMetrics")?;
+        writeln!(file, "-------")?;
+        writeln!(file, "Tile              : {} of {}", k, count)?;
+        writeln!(file, "Levels            : {}", output::level_range(tile.first_level, tile.last_level))?;
+        writeln!(file, "Received Signals  : {}", tile.live_in.len())?;
+        writeln!(file, "Sent Signals      : {}", tile.live_out.len())?;
+        writeln!(file, "Stage Cycles      : {}", tile.stage_cycles())?;
+        writeln!(file, "Interval Cycles   : {}", interval)?;
+        writeln!(file, "Crossbar Size     : {}x{}", size.0, size.1)?;
+        if tile.circuit.primitive == Primitive::Nand {
+            writeln!(file, "Primitive         : {}", tile.circuit.primitive)?;
+        }
+        writeln!(file, "---------------------------\n\n")?;
+        file.flush()?;
+        sizes.push(size);
     }
     
-    // Add metrics - matches C implementation format
-    writeln!(file, "\nMetrics")?;
-    writeln!(file, "-------")?;
-    writeln!(file, "Primary Inputs    : {}", circuit.num_inputs)?;
+    let mut file = File::create(report)
+        .context("Failed to create pipeline report")?;
+    writeln!(file, "PIPELINED EXECUTION:")?;
+    writeln!(file, "=============")?;
+    writeln!(file, "Tiles: {}, Levels: {}", count, pipeline.depth)?;
+    writeln!(file, "{:>4}  {:>9}  {:>6}  {:>8}  {:>5}  {:>7}  {:>9}  {:>12}",
+        "Tile", "Levels", "Gates", "Received", "Sent", "Passed", "Crossbar", "Stage cycles")?;
+    for (k, (tile, size)) in pipeline.tiles.iter().zip(&sizes).enumerate() {
+        writeln!(file, "{:>4}  {:>9}  {:>6}  {:>8}  {:>5}  {:>7}  {:>9}  {:>12}",
+            k,
+            output::level_range(tile.first_level, tile.last_level),
+            tile.circuit.num_gates,
+            tile.live_in.len(),
+            tile.live_out.len(),
+            tile.pass_through().count(),
+            format!("{}x{}", size.0, size.1),
+            tile.stage_cycles())?;
+    }
     
-    if any_gates_printed {
-        writeln!(file, "Levels            : {}", curr_level)?;
-    } else {
-        writeln!(file, "Levels            : 0")?;
+    let single = pipeline.unpipelined_cycles();
+    writeln!(file)?;
+    writeln!(file, "{:<18}{:>18}{:>24}", "", "Latency (cycles)", "Throughput (vec/cycle)")?;
+    writeln!(file, "{:<18}{:>18}{:>24.4}", "Single crossbar", single, 1.0 / single as f64)?;
+    writeln!(file, "{:<18}{:>18}{:>24.4}", "Pipelined", pipeline.latency(), pipeline.throughput())?;
+    writeln!(file, "Initiation interval: {} cycles", interval)?;
+    writeln!(file, "Throughput gain    : {:.2}x at {:.2}x latency",
+        single as f64 * pipeline.throughput(), pipeline.latency() as f64 / single as f64)?;
+    Ok(())
+}
+
+// Batched evaluation plans of one or more mappings of a circuit: a table of
+// batch size, input writes and throughput against one vector at a time, then
+// for each mapping the input data writes, each bit as `position=v<vector>.x<input>`
+pub fn generate_batch_layout<P: AsRef<Path>>(layouts: &[(&str, &BatchLayout)], path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create batch layout file")?);
+    
+    writeln!(file, "BATCHED EVALUATION:")?;
+    writeln!(file, "=============")?;
+    let header: Vec<String> = layouts.iter().map(|(name, _)| format!("{:>12}", name)).collect();
+    writeln!(file, "{:<24}{}", "", header.join(""))?;
+    let column = |f: fn(&BatchLayout) -> String| -> Vec<String> {
+        layouts.iter().map(|(_, l)| f(l)).collect()
+    };
+    let rows = [
+        ("Vectors per batch", column(|l| l.batch.to_string())),
+        ("Rows per vector", column(|l| l.rows_per_vector.to_string())),
+        ("Batched crossbar", column(|l| format!("{}x{}", l.batch * l.rows_per_vector, l.cols))),
+        ("Input writes", column(|l| l.init_writes().len().to_string())),
+        ("Cycles per batch", column(|l| l.batched_cycles().to_string())),
+        ("Cycles per vector", column(|l| format!("{:.2}", l.batched_cycles() as f64 / l.batch as f64))),
+        ("Vectors per cycle", column(|l| format!("{:.4}", l.throughput()))),
+        ("Speedup", column(|l| format!("{:.2}x", l.throughput() * l.single_cycles() as f64))),
+    ];
+    for (name, values) in rows {
+        let values: Vec<String> = values.iter().map(|v| format!("{:>12}", v)).collect();
+        writeln!(file, "{:<24}{}", name, values.join(""))?;
     }
     
-    writeln!(file, "Read Operations   : {}", circuit.max_asap)?;
-    writeln!(file, "Write Operations  : {}", 2 * circuit.max_asap + 1)?;
-    writeln!(file, "Evaluation Cycles : {}", circuit.max_asap)?;
-    writeln!(file, "Total Cycles      : {}", 4 * circuit.max_asap + 1)?;
+    for (name, layout) in layouts {
+        writeln!(file, "\n{} INPUT LAYOUT:", name.to_uppercase())?;
+        writeln!(file, "=============")?;
+        match layout.rows_per_vector {
+            1 => writeln!(file, "# Vector v uses row v")?,
+            rows => writeln!(file, "# Vector v uses rows {}v..{}v+{}", rows, rows, rows - 1)?,
+        }
+        for write in layout.init_writes() {
+            let kind = if write.line == Line::Wordline { "row" } else { "col" };
+            let bits: Vec<String> = write.bits.iter()
+                .map(|&(at, vector, input)| format!("{}=v{}.x{}", at, vector, input))
+                .collect();
+            writeln!(file, "WRITE {} {:4} : {}", kind, write.index, bits.join(" "))?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Replicated words on each mapping: the aggregate array, throughput per
+// bit, and where every bit's inputs go
+pub fn generate_replication<P: AsRef<Path>>(layouts: &[(&str, &Replication)], path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create replication file")?);
     
-    // Crossbar size
-    if is_naive {
-        if mapping.max_jdx < 0 {
-            writeln!(file, "Crossbar Size     : {}x{}", 1, 1)?;
-        } else {
-            writeln!(file, "Crossbar Size     : {}x{}", 1, mapping.max_jdx + 1)?;
+    writeln!(file, "REPLICATED EVALUATION:")?;
+    writeln!(file, "=============")?;
+    let header: Vec<String> = layouts.iter().map(|(name, _)| format!("{:>12}", name)).collect();
+    writeln!(file, "{:<24}{}", "", header.join(""))?;
+    let column = |f: fn(&Replication) -> String| -> Vec<String> {
+        layouts.iter().map(|(_, r)| f(r)).collect()
+    };
+    let rows = [
+        ("Bits per word", column(|r| r.bits.to_string())),
+        ("Columns per bit", column(|r| r.cols_per_bit.to_string())),
+        ("1-bit crossbar", column(|r| format!("{}x{}", r.rows, r.cols_per_bit))),
+        ("Replicated crossbar", column(|r| format!("{}x{}", r.rows, r.bits * r.cols_per_bit))),
+        ("Input writes", column(|r| r.init_writes().len().to_string())),
+        ("Cycles per word", column(|r| r.cycles().to_string())),
+        ("Cycles per bit", column(|r| format!("{:.2}", r.cycles() as f64 / r.bits as f64))),
+        ("Bits per cycle", column(|r| format!("{:.4}", r.throughput()))),
+    ];
+    for (name, values) in rows {
+        let values: Vec<String> = values.iter().map(|v| format!("{:>12}", v)).collect();
+        writeln!(file, "{:<24}{}", name, values.join(""))?;
+    }
+    
+    for (name, replication) in layouts {
+        writeln!(file, "\n{} COLUMN GROUPS:", name.to_uppercase())?;
+        writeln!(file, "=============")?;
+        writeln!(file, "# Bit b uses columns {}b..{}b+{}; the {} program's micro-ops act on every group",
+            replication.cols_per_bit, replication.cols_per_bit, replication.cols_per_bit - 1, name)?;
+        for write in replication.init_writes() {
+            let kind = if write.line == Line::Wordline { "row" } else { "col" };
+            let bits: Vec<String> = write.bits.iter()
+                .map(|&(at, bit, input)| format!("{}=b{}.x{}", at, bit, input))
+                .collect();
+            writeln!(file, "WRITE {} {:4} : {}", kind, write.index, bits.join(" "))?;
         }
-    } else {
-        if mapping.max_idx < 0 || mapping.max_jdx < 0 {
-            writeln!(file, "Crossbar Size     : {}x{}", 1, 1)?;
-        } else {
-            writeln!(file, "Crossbar Size     : {}x{}", mapping.max_idx + 1, mapping.max_jdx + 1)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// An N-bit wrapper instantiating the MAGIC module once per bit: input and
+// output port K become N-bit vectors, bit b driving slice b
+pub fn generate_replicated_verilog<P: AsRef<Path>>(circuit: &Circuit, bits: usize, path: P) -> Result<()> {
+    ensure!(bits > 0, "A replication needs at least one bit");
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create replicated Verilog file")?);
+    let vector = |bits: usize| if bits > 1 { format!("[{}:0] ", bits - 1) } else { String::new() };
+    
+    writeln!(file, "// {} bit slices of module_name, from the MAGIC Verilog of {}\n", bits, circuit.bench_name)?;
+    writeln!(file, "module module_name_x{} (", bits)?;
+    let inputs: Vec<String> = (1..=circuit.num_inputs).map(|i| format!("ip_{}", i)).collect();
+    let outputs: Vec<String> = output_ports(circuit).iter().map(|k| format!("op_{}", k)).collect();
+    let ports: Vec<String> = inputs.iter().map(|p| format!("  input  {}{}", vector(bits), p))
+        .chain(outputs.iter().map(|p| format!("  output {}{}", vector(bits), p)))
+        .collect();
+    writeln!(file, "{}\n);\n", ports.join(",\n"))?;
+    
+    writeln!(file, "  genvar b;")?;
+    writeln!(file, "  generate")?;
+    writeln!(file, "    for (b = 0; b < {}; b = b + 1) begin : slice", bits)?;
+    let connections: Vec<String> = inputs.iter().chain(&outputs)
+        .map(|p| if bits > 1 { format!("        .{}({}[b])", p, p) } else { format!("        .{}({})", p, p) })
+        .collect();
+    writeln!(file, "      module_name magic (\n{}\n      );", connections.join(",\n"))?;
+    writeln!(file, "    end")?;
+    writeln!(file, "  endgenerate\n")?;
+    writeln!(file, "endmodule")?;
+    file.flush()?;
+    Ok(())
+}
+
+// A program per rotated placement of the mapping, and the schedule the
+// controller cycles through them with
+pub fn generate_rotation<P: AsRef<Path>>(
+    circuit: &Circuit,
+    mapping: &CrossbarMapping,
+    rotation: &Rotation,
+    tech: Option<&Technology>,
+    programs: &[PathBuf],
+    path: P,
+) -> Result<()> {
+    for (placement, program) in rotation.placements.iter().zip(programs) {
+        let placed = placement.place(mapping)?;
+        generate_micro_ops_with_tech(circuit, &placed, false, tech, false, program)?;
+    }
+    
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create rotation schedule file")?);
+    let count = rotation.placements.len();
+    writeln!(file, "ENDURANCE ROTATION:")?;
+    writeln!(file, "=============")?;
+    writeln!(file, "Placements        : {}", count)?;
+    writeln!(file, "Rotation Area     : {}x{}", rotation.rows, rotation.cols)?;
+    writeln!(file, "Schedule          : run r executes placement r mod {}", count)?;
+    writeln!(file, "\n{:>9}{:>11}{:>11}  Program", "Placement", "Row Shift", "Col Shift")?;
+    for (k, (&(rows, cols), program)) in rotation.shifts.iter().zip(programs).enumerate() {
+        let name = program.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        writeln!(file, "{:>9}{:>11}{:>11}  {}", k, rows, cols, name)?;
+    }
+    
+    let unrotated = endurance::max_writes_per_run(mapping);
+    let rotated = rotation.max_writes_per_run(mapping);
+    writeln!(file, "\nMax Cell Writes   : {} per run unrotated, {:.2} per run rotated", unrotated, rotated)?;
+    if let Some(tech) = tech {
+        let lifetime = (tech.device.endurance as f64 / rotated.max(f64::MIN_POSITIVE)) as u64;
+        writeln!(file, "Device Lifetime   : {} runs unrotated, {} runs rotated ({})",
+            tech.lifetime_runs(unrotated), lifetime, tech.name)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Programs for the parts of a program split for a controller that holds at
+// most `max_ops` micro-ops at a time, and a manifest listing them. Each part names the cells it
+// resumes from, the cells an earlier part initialized for it, and the cells
+// it leaves for the next part
+pub fn generate_partitions<P: AsRef<Path>>(
+    program: &Program,
+    parts: &[Partition],
+    max_ops: usize,
+    programs: &[PathBuf],
+    manifest: P,
+) -> Result<()> {
+    let count = parts.len();
+    let cells = |cells: &[Cell]| cells.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ");
+    let mut listing = TextListing::new(program, 1);
+    for (k, (part, path)) in parts.iter().zip(programs).enumerate() {
+        let mut file = BufWriter::new(File::create(path)
+            .context("Failed to create partition program")?);
+        writeln!(file, "# Part {} of {}, ops {}-{}", k, count, part.ops.start, part.ops.end - 1)?;
+        for (keyword, list) in [("RESUME", &part.resume), ("PENDING", &part.pending)] {
+            if !list.is_empty() {
+                writeln!(file, "{} {}", keyword, cells(list))?;
+            }
         }
+        listing.write_until(&mut file, part.ops.end)?;
+        if !part.checkpoint.is_empty() {
+            writeln!(file, "CHECKPOINT {}", cells(&part.checkpoint))?;
+        }
+
+        writeln!(file, "\nMetrics")?;
+        writeln!(file, "-------")?;
+        writeln!(file, "Part              : {} of {}", k, count)?;
+        writeln!(file, "Levels            : {}", output::level_range(*part.levels.start(), *part.levels.end()))?;
+        writeln!(file, "Micro-ops         : {}", part.ops.len())?;
+        writeln!(file, "Resume Cells      : {}", part.resume.len())?;
+        writeln!(file, "Checkpoint Cells  : {}", part.checkpoint.len())?;
+        writeln!(file, "Crossbar Size     : {}x{}", program.rows, program.cols)?;
+        writeln!(file, "---------------------------\n\n")?;
+        file.flush()?;
     }
+
+    let mut file = BufWriter::new(File::create(manifest)
+        .context("Failed to create partition manifest")?);
+    writeln!(file, "PARTITIONED PROGRAM:")?;
+    writeln!(file, "=============")?;
+    writeln!(file, "Parts             : {}", count)?;
+    writeln!(file, "Ops per Part      : at most {}", max_ops)?;
+    writeln!(file, "Total Ops         : {}", parts.last().map_or(0, |p| p.ops.end))?;
+    writeln!(file, "\n{:>4}  {:>11}  {:>7}  {:>6}  {:>10}  Program", "Part", "Ops", "Levels", "Resume", "Checkpoint")?;
+    for (k, (part, path)) in parts.iter().zip(programs).enumerate() {
+        let name = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        writeln!(file, "{:>4}  {:>11}  {:>7}  {:>6}  {:>10}  {}",
+            k,
+            format!("{}-{}", part.ops.start, part.ops.end - 1),
+            output::level_range(*part.levels.start(), *part.levels.end()),
+            part.resume.len(),
+            part.checkpoint.len(),
+            name)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// A program's listing as the C implementation wrote it, see
+// `TextListing::legacy`. Its footer counts a read, a reset, a write and an
+// evaluation per level of either mapping, and its level count leaves out
+// the unlisted last level
+pub fn generate_legacy_micro_ops<P: AsRef<Path>>(program: &Program, path: P) -> Result<()> {
+    ensure!(program.inverted_slots().is_empty() && program.complemented_inputs().is_empty(),
+        "--legacy-compat: the C implementation's listings have no inverted reads or active-low inputs");
+    let mut file = BufWriter::new(File::create(path)
+        .context("Failed to create micro-ops file")?);
+    TextListing::new(program, 1).legacy().write_until(&mut file, usize::MAX)?;
+    let levels = program.levels().last().map_or(0, |s| s.level.max(0) as usize);
+    writeln!(file, "\nMetrics")?;
+    writeln!(file, "-------")?;
+    writeln!(file, "Primary Inputs    : {}", program.num_inputs)?;
+    writeln!(file, "Levels            : {}", levels.saturating_sub(1))?;
+    writeln!(file, "Read Operations   : {}", levels)?;
+    writeln!(file, "Write Operations  : {}", 1 + 2 * levels)?;
+    writeln!(file, "Evaluation Cycles : {}", levels)?;
+    writeln!(file, "Total Cycles      : {}", 1 + 4 * levels)?;
+    writeln!(file, "Crossbar Size     : {}x{}", program.rows, program.cols)?;
     writeln!(file, "---------------------------\n\n")?;
+    file.flush()?;
+    Ok(())
+}
+
+// Metrics footer - matches C implementation format
+fn write_metrics<W: Write>(
+    file: &mut W,
+    circuit: &Circuit,
+    program: &Program,
+    is_naive: bool,
+    tally: Option<&TechTally>,
+) -> Result<()> {
+    // The naive mapping puts every gate in row 0, so its gates run one at a time
+    let cycles = if is_naive { program.serialized_cycles() } else { program.cycles().folding(circuit.fold_copies) };
+    let levels = program.levels().last().map_or(0, |s| s.level);
+    writeln!(file, "\nMetrics")?;
+    writeln!(file, "-------")?;
+    writeln!(file, "Primary Inputs    : {}", circuit.num_inputs)?;
+    writeln!(file, "Levels            : {}", levels)?;
+    writeln!(file, "Read Operations   : {}", cycles.reads())?;
+    writeln!(file, "Write Operations  : {}", cycles.writes())?;
+    writeln!(file, "Reset Operations  : {}", cycles.resets)?;
+    writeln!(file, "Evaluation Cycles : {}", cycles.evaluations())?;
+    writeln!(file, "Total Cycles      : {}", cycles.total())?;
+    if let Some(gates) = cycles.serialized {
+        writeln!(file, "Execution         : serialized, {} gates one at a time", gates)?;
+    }
     
+    writeln!(file, "Crossbar Size     : {}x{}", program.rows, program.cols)?;
+    let complemented = program.complemented_inputs();
+    if !complemented.is_empty() {
+        let inputs: Vec<String> = complemented.iter().map(|&i| signal_name((MAX_GATES + i) as i32)).collect();
+        writeln!(file, "Active-low Inputs : {} ({})", complemented.len(), inputs.join(" "))?;
+    }
+    if !circuit.unused_inputs.is_empty() {
+        let inputs: Vec<String> = circuit.unused_inputs.iter().map(|&i| signal_name((MAX_GATES + i) as i32)).collect();
+        writeln!(file, "Unused Inputs     : {} ({}), not loaded", inputs.len(), inputs.join(" "))?;
+    }
+    // Outputs whose trailing NOT was absorbed into the read-out
+    let inverted = program.inverted_slots();
+    if !inverted.is_empty() {
+        let slots: Vec<String> = inverted.iter().map(|s| s.to_string()).collect();
+        writeln!(file, "Inverted Reads    : {} (slots {})", inverted.len(), slots.join(" "))?;
+    }
+    // Two-input operations are NOR unless stated
+    if circuit.primitive == Primitive::Nand {
+        writeln!(file, "Primitive         : {}", circuit.primitive)?;
+    }
+    // The cycle counts above leave out the copies the compact mapper inserts,
+    // unless they are folded in
+    if !is_naive {
+        writeln!(file, "Copy Operations   : {}", cycles.copies.iter().sum::<usize>())?;
+        writeln!(file, "Copies per Level  :{}", cycles.copies.iter().map(|c| format!(" {}", c)).collect::<String>())?;
+        if cycles.folded {
+            writeln!(file, "Copy Cycles       : {}, in the reads and writes ({:.1}% copy overhead)",
+                cycles.copy_cycles(), cycles.copy_overhead())?;
+        } else {
+            writeln!(file, "Copy Cycles       : {}", cycles.copy_cycles())?;
+            writeln!(file, "Cycles with Copies: {} ({:.1}% copy overhead)",
+                cycles.total() + cycles.copy_cycles(), cycles.copy_overhead())?;
+        }
+    }
+    if let Some(tally) = tally {
+        write_tech_metrics(file, &cycles, tally)?;
+    }
+    writeln!(file, "---------------------------\n\n")?;
     Ok(())
 }
 
-fn format_gate_name(mem: &MemristiveGate) -> String {
-    if mem.value >= MAX_GATES as i32 {
-        // Primary input format - matches C implementation
-        format!("/{}", mem.value - MAX_GATES as i32)
-    } else if mem.is_copy {
-        if let Some(ref input) = mem.inputs[0] {
-            format_gate_name(input)
+// Resource use of a program, level by level
+struct TechTally<'a> {
+    tech: &'a Technology,
+    inputs: usize,
+    eval_cycles: usize,
+    ops: usize,
+    operands: usize,
+    // Wire delay of the levels under the technology's wire model, each
+    // level waiting for its longest wire
+    wire_ns: f64,
+    longest_wire: usize,
+}
+
+impl<'a> TechTally<'a> {
+    // A level is one abstract evaluation step, split into as many clock
+    // cycles as the periphery needs to activate its rows and columns. A
+    // serialized program takes a step per gate instead
+    fn new(tech: &'a Technology, program: &Program, serialized: bool) -> Self {
+        let mut tally = Self { tech, inputs: program.loaded_inputs(), eval_cycles: 0, ops: 0, operands: 0, wire_ns: 0.0, longest_wire: 0 };
+        fn execs(step: &Step) -> impl Iterator<Item = (&Cell, &[Cell])> {
+            step.ops.iter().filter_map(|op| match op {
+                MicroOp::ExecNor { inputs, cell } => Some((cell, &inputs[..])),
+                MicroOp::ExecNot { input, cell } => Some((cell, std::slice::from_ref(input))),
+                _ => None,
+            })
+        }
+        let steps: Vec<Vec<(&Cell, &[Cell])>> = if serialized {
+            program.levels().flat_map(execs).map(|exec| vec![exec]).collect()
         } else {
-            String::from("???")
+            program.levels().map(|step| execs(step).collect()).collect()
+        };
+        for step in steps {
+            let (mut rows, mut cols) = (HashSet::new(), HashSet::new());
+            let mut longest = 0;
+            for (cell, operands) in step {
+                rows.insert(cell.row);
+                cols.insert(cell.col);
+                cols.extend(operands.iter().map(|c| c.col));
+                tally.operands += operands.len();
+                tally.ops += 1;
+                let distance = |c: &Cell| c.row.abs_diff(cell.row) as usize + c.col.abs_diff(cell.col) as usize;
+                longest = operands.iter().map(distance).fold(longest, usize::max);
+            }
+            if !rows.is_empty() {
+                tally.eval_cycles += tech.step_cycles(rows.len(), cols.len());
+                if let Some(wires) = &tech.wires {
+                    tally.wire_ns += wires.delay_ns(longest);
+                    tally.longest_wire = tally.longest_wire.max(longest);
+                }
+            }
         }
-    } else {
-        // Coordinates format - matches C implementation
-        format!("{}x{}", mem.idx, mem.jdx)
+        tally
+    }
+}
+
+// What a program costs on a technology
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TechEstimate {
+    pub cycles: usize,
+    pub time_ns: f64,
+    pub energy_pj: f64,
+}
+
+impl TechEstimate {
+    // A naive program is costed as it runs, one gate at a time, from its
+    // `serialized_cycles`
+    pub fn new(tech: &Technology, program: &Program, cycles: &ProgramCycles, is_naive: bool) -> Self {
+        Self::of(cycles, &TechTally::new(tech, program, is_naive))
     }
-}
\ No newline at end of file
+
+    // Every output device is initialized and then switched, every operand is
+    // read, and primary inputs are written once. Folded copies read their
+    // source and write their cell
+    fn of(program: &ProgramCycles, tally: &TechTally) -> Self {
+        let tech = tally.tech;
+        let device = &tech.device;
+        let cycles = program.reads() + program.writes() + tally.eval_cycles;
+        Self {
+            cycles,
+            time_ns: cycles as f64 * tech.clock_ns() + tally.wire_ns,
+            energy_pj: tally.inputs as f64 * device.write_pj
+                + tally.ops as f64 * (device.write_pj + device.switch_pj)
+                + tally.operands as f64 * device.read_pj
+                + program.folded_copies() as f64 * (device.read_pj + device.write_pj),
+        }
+    }
+}
+
+fn write_tech_metrics<W: Write>(file: &mut W, program: &ProgramCycles, tally: &TechTally) -> Result<()> {
+    let tech = tally.tech;
+    let estimate = TechEstimate::of(program, tally);
+    writeln!(file, "Technology        : {}", tech.name)?;
+    writeln!(file, "Clock Period      : {:.2} ns", tech.clock_ns())?;
+    writeln!(file, "Technology Cycles : {}", estimate.cycles)?;
+    if tech.wires.is_some() {
+        writeln!(file, "Wire Delay        : {:.2} ns (longest wire {} cells)", tally.wire_ns, tally.longest_wire)?;
+    }
+    writeln!(file, "Execution Time    : {:.2} ns", estimate.time_ns)?;
+    writeln!(file, "Energy            : {:.2} pJ", estimate.energy_pj)?;
+    writeln!(file, "Device Lifetime   : {} runs", tech.lifetime_runs(2))?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//generator/toolchain.rs
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{Result, Context, bail};
+
+// External tools able to syntax-check the generated Verilog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerilogChecker {
+    Iverilog,
+    Yosys,
+}
+
+impl VerilogChecker {
+    pub fn program(&self) -> &'static str {
+        match self {
+            VerilogChecker::Iverilog => "iverilog",
+            VerilogChecker::Yosys => "yosys",
+        }
+    }
+
+    fn command(&self, exe: &Path, verilog: &Path) -> Command {
+        let mut cmd = Command::new(exe);
+        match self {
+            VerilogChecker::Iverilog => {
+                cmd.arg("-tnull").arg(verilog);
+            }
+            VerilogChecker::Yosys => {
+                cmd.arg("-q").arg("-p").arg(format!(
+                    "read_verilog \"{}\"; hierarchy -check -auto-top",
+                    verilog.display()
+                ));
+            }
+        }
+        cmd
+    }
+}
+
+// First checker found on PATH, preferring iverilog
+pub fn find_verilog_checker() -> Option<(VerilogChecker, PathBuf)> {
+    [VerilogChecker::Iverilog, VerilogChecker::Yosys]
+        .into_iter()
+        .find_map(|checker| find_on_path(checker.program()).map(|exe| (checker, exe)))
+}
+
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let exe = candidate.with_extension("exe");
+        exe.is_file().then_some(exe)
+    })
+}
+
+// Runs the first available checker on a Verilog file. Returns the checker
+// used, or None when no tool is installed.
+pub fn check_verilog<P: AsRef<Path>>(path: P) -> Result<Option<VerilogChecker>> {
+    let Some((checker, exe)) = find_verilog_checker() else {
+        return Ok(None);
+    };
+
+    let output = checker.command(&exe, path.as_ref())
+        .output()
+        .context(format!("Failed to run {}", checker.program()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        bail!("{} rejected {}:\n{}{}", checker.program(), path.as_ref().display(),
+            stderr.trim_end(), stdout.trim_end());
+    }
+
+    Ok(Some(checker))
+}
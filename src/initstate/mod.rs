@@ -0,0 +1,174 @@
+//initstate/mod.rs
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::program::Cell;
+use crate::simulator::output_signals;
+use crate::{signal_id, signal_name, Circuit, MAX_COL, MAX_GATES, MAX_ROW};
+
+// What a cell holds before a program runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Known {
+    // A primary input or gate output that an earlier program on the same
+    // input vector left in the cell
+    Signal(i32),
+    Constant(bool),
+}
+
+impl fmt::Display for Known {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Known::Signal(signal) => f.pad(&signal_name(*signal)),
+            Known::Constant(value) => f.pad(if *value { "1" } else { "0" }),
+        }
+    }
+}
+
+// Cells of the compact crossbar that hold a known value before the program
+// runs, from `--init-state`. The compact mapping places a held signal in its
+// cell instead of loading or computing it, along with the gates only held
+// signals read, and a gate landing in a cell at logic 1 needs no RESET
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitState {
+    pub cells: BTreeMap<Cell, Known>,
+}
+
+impl InitState {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read init state: {:?}", path))?;
+        Self::parse(&text).context(format!("Failed to load init state: {:?}", path))
+    }
+
+    // One cell per line, `<row>x<col> <value>`, the value being 0, 1 or a
+    // signal such as x3 or n12. `#` starts a comment
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut state = InitState::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [cell, value] = fields[..] else {
+                bail!("Line {} of the init state is not '<row>x<col> <value>'", n + 1);
+            };
+            let cell = parse_cell(cell)
+                .with_context(|| format!("Malformed cell '{}' on line {} of the init state", cell, n + 1))?;
+            ensure!((cell.row as usize) < MAX_ROW && (cell.col as usize) < MAX_COL,
+                "Cell {} on line {} of the init state lies outside the {}x{} crossbar", cell, n + 1, MAX_ROW, MAX_COL);
+            let known = match value {
+                "0" => Known::Constant(false),
+                "1" => Known::Constant(true),
+                name => Known::Signal(signal_id(name).with_context(|| {
+                    format!("Value '{}' on line {} of the init state is neither 0, 1 nor a signal", name, n + 1)
+                })?),
+            };
+            if let Known::Signal(signal) = known {
+                if let Some(other) = state.cell_of(signal) {
+                    bail!("{} is held in both {} and {} (line {} of the init state)", signal_name(signal), other, cell, n + 1);
+                }
+            }
+            ensure!(state.cells.insert(cell, known).is_none(), "Cell {} is listed twice (line {} of the init state)", cell, n + 1);
+        }
+        Ok(state)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    // Held signals with their cells
+    pub fn signals(&self) -> impl Iterator<Item = (i32, Cell)> + '_ {
+        self.cells.iter().filter_map(|(&cell, known)| match known {
+            Known::Signal(signal) => Some((*signal, cell)),
+            Known::Constant(_) => None,
+        })
+    }
+
+    pub fn cell_of(&self, signal: i32) -> Option<Cell> {
+        self.signals().find(|&(s, _)| s == signal).map(|(_, cell)| cell)
+    }
+
+    // Whether the cell is at logic 1, as a RESET would leave it
+    pub fn is_set(&self, cell: Cell) -> bool {
+        self.cells.get(&cell) == Some(&Known::Constant(true))
+    }
+
+    // Checks the state against the circuit as it is about to be mapped and
+    // hands it to the circuit. Held inputs move to the rows they are held in,
+    // and the other inputs fill the rows left, in their order
+    pub fn apply(self, circuit: &mut Circuit) -> Result<()> {
+        let lines = circuit.input_line_count();
+        let mut held_rows: BTreeMap<usize, usize> = BTreeMap::new();
+        for (signal, cell) in self.signals() {
+            let name = signal_name(signal);
+            if signal >= MAX_GATES as i32 {
+                let input = (signal - MAX_GATES as i32) as usize;
+                ensure!(input < circuit.num_inputs, "The circuit has no input {}", name);
+                ensure!(!circuit.unused_inputs.contains(&input), "No gate reads {}, so no cell can hold it", name);
+                ensure!(!circuit.complemented_inputs.contains(&input),
+                    "{} is loaded active-low, so its cell would hold its complement", name);
+                ensure!(cell.col == 0 && (cell.row as usize) < lines,
+                    "{} is held in {}, but the compact mapping keeps its {} inputs in column 0, rows 0 to {}",
+                    name, cell, lines, lines.saturating_sub(1));
+                held_rows.insert(input, cell.row as usize);
+            } else {
+                ensure!(circuit.drivers.contains_key(&signal), "No gate of the circuit drives {}", name);
+                ensure!(cell.col > 0 || cell.row as usize >= lines,
+                    "{} is held in {}, which the compact mapping gives to an input", name, cell);
+            }
+        }
+
+        if !held_rows.is_empty() {
+            let taken: HashSet<usize> = held_rows.values().copied().collect();
+            let mut free = (0..lines).filter(|row| !taken.contains(row));
+            let mut others: Vec<usize> = circuit.mapped_inputs().filter(|n| !held_rows.contains_key(n)).collect();
+            others.sort_by_key(|&n| circuit.input_line(n));
+            let mut input_lines: Vec<usize> = (0..circuit.num_inputs).map(|n| circuit.input_line(n)).collect();
+            for n in others {
+                input_lines[n] = free.next().expect("a row for every input");
+            }
+            for (&n, &row) in &held_rows {
+                input_lines[n] = row;
+            }
+            circuit.input_lines = input_lines;
+        }
+        circuit.init_state = self;
+        Ok(())
+    }
+
+    // Output signals of the gates the compact mapping evaluates no op for:
+    // the held gates, and gates whose readers all go unevaluated. Gates no
+    // output depends on at all are still evaluated, as without a state
+    pub fn unevaluated(&self, circuit: &Circuit) -> HashSet<i32> {
+        let mut skipped: HashSet<i32> = HashSet::new();
+        if self.is_empty() {
+            return skipped;
+        }
+        let roots: HashSet<i32> = output_signals(circuit).into_iter()
+            .map(|o| circuit.cell_signal(circuit.inverted_outputs.get(&o).copied().unwrap_or(o)))
+            .collect();
+        let mut order: Vec<usize> = (0..circuit.num_gates).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(circuit.gates[i].asap_level));
+        for i in order {
+            let out = circuit.gates[i].out;
+            let readers: Vec<usize> = circuit.gate_readers(i).collect();
+            let held = self.cell_of(out).is_some();
+            if held || (!roots.contains(&out) && !readers.is_empty()
+                && readers.iter().all(|&r| skipped.contains(&circuit.gates[r].out)))
+            {
+                skipped.insert(out);
+            }
+        }
+        skipped
+    }
+}
+
+fn parse_cell(text: &str) -> Option<Cell> {
+    let (row, col) = text.split_once('x')?;
+    Some(Cell { row: row.parse().ok()?, col: col.parse().ok()? })
+}
@@ -0,0 +1,243 @@
+//legacy/mod.rs
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::lint::{self, Operand};
+
+// One schedule section of a stats file, "ASAP SCHEDULE:" and the like, as
+// both implementations write it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleStats {
+    // "ASAP", "ALAP" or "LIST"
+    pub name: String,
+    // Gates per level, level 1 first
+    pub distribution: Vec<usize>,
+    // Negative where the C implementation's list scheduler placed no gate
+    pub levels: i32,
+    pub max_gates: usize,
+    pub memristors: usize,
+    pub serial_steps: i32,
+    pub parallel_steps: i32,
+    // Rows and columns
+    pub serial_size: (usize, usize),
+    pub parallel_size: (usize, usize),
+}
+
+// A gate of a micro-op listing: the cell it switches and the operands it
+// reads in its row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedGate {
+    pub row: usize,
+    pub col: usize,
+    pub operands: Vec<Operand>,
+}
+
+// A micro-op listing: its gates level by level and its footer's fields in
+// order. RESET and READ lines, which the C implementation never wrote, and
+// comments are skipped, so listings of either implementation read alike
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listing {
+    pub levels: Vec<(i32, Vec<ListedGate>)>,
+    pub metrics: Vec<(String, String)>,
+}
+
+impl Listing {
+    pub fn metric(&self, name: &str) -> Option<&str> {
+        self.metrics.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+// Reads the schedule sections of a stats file; other sections and the
+// provenance header are skipped
+pub fn read_stats(text: &str) -> Result<Vec<ScheduleStats>> {
+    let mut sections: Vec<ScheduleStats> = Vec::new();
+    let mut current: Option<ScheduleStats> = None;
+    let mut lines = text.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let line = line.trim();
+        let context = || format!("Malformed stats line {}: '{}'", n + 1, line);
+        if let Some(name) = line.strip_suffix(" SCHEDULE:") {
+            sections.extend(current.take());
+            current = Some(ScheduleStats {
+                name: name.to_string(),
+                distribution: Vec::new(),
+                levels: 0,
+                max_gates: 0,
+                memristors: 0,
+                serial_steps: 0,
+                parallel_steps: 0,
+                serial_size: (0, 0),
+                parallel_size: (0, 0),
+            });
+            continue;
+        }
+        if line.ends_with(':') && line.chars().all(|c| c.is_ascii_uppercase() || c == ' ' || c == ':') {
+            // Any other section ends the schedule's
+            sections.extend(current.take());
+            continue;
+        }
+        let Some(stats) = current.as_mut() else { continue };
+        if line == "Gate distribution across levels:" {
+            let (_, counts) = lines.next().with_context(context)?;
+            stats.distribution = counts.split_whitespace()
+                .map(|c| c.parse().with_context(context))
+                .collect::<Result<_>>()?;
+        } else if let Some(rest) = line.strip_prefix("Number of levels:") {
+            let [levels, max_gates] = fields(rest, &["", "MaxGates:"]).with_context(context)?;
+            (stats.levels, stats.max_gates) = (levels, max_gates as usize);
+        } else if let Some(rest) = line.strip_prefix("Number of memristors:") {
+            stats.memristors = rest.trim().parse().with_context(context)?;
+        } else if let Some(rest) = line.strip_prefix("Time steps (serial):") {
+            let [serial, parallel] = fields(rest, &["", "Time steps (parallel):"]).with_context(context)?;
+            (stats.serial_steps, stats.parallel_steps) = (serial, parallel);
+        } else if let Some(rest) = line.strip_prefix("Crossbar size (serial):") {
+            stats.serial_size = size(rest).with_context(context)?;
+        } else if let Some(rest) = line.strip_prefix("Crossbar size (parallel):") {
+            stats.parallel_size = size(rest).with_context(context)?;
+        }
+    }
+    sections.extend(current);
+    ensure!(!sections.is_empty(), "No schedule sections in the stats file");
+    Ok(sections)
+}
+
+// "5, MaxGates: 4" into its numbers, each field after its label
+fn fields<const N: usize>(text: &str, labels: &[&str; N]) -> Option<[i32; N]> {
+    let parts: Vec<&str> = text.split(',').collect();
+    if parts.len() != N {
+        return None;
+    }
+    let mut values = [0; N];
+    for ((value, part), label) in values.iter_mut().zip(parts).zip(labels) {
+        *value = part.trim().strip_prefix(label)?.trim().parse().ok()?;
+    }
+    Some(values)
+}
+
+// "4 x 10" as rows and columns
+fn size(text: &str) -> Option<(usize, usize)> {
+    let (rows, cols) = text.split_once('x')?;
+    Some((rows.trim().parse().ok()?, cols.trim().parse().ok()?))
+}
+
+// Reads a text micro-op listing
+pub fn read_listing(text: &str) -> Result<Listing> {
+    let mut listing = Listing::default();
+    let mut in_footer = false;
+    for (n, raw) in text.lines().enumerate() {
+        let line = match raw.split_once("  # ") {
+            Some((op, _)) if !op.trim_start().starts_with('#') => op,
+            _ => raw,
+        }.trim_end();
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with("---") {
+            continue;
+        }
+        if trimmed == "Metrics" {
+            in_footer = true;
+            continue;
+        }
+        if in_footer {
+            if let Some((name, value)) = trimmed.split_once(':') {
+                listing.metrics.push((name.trim().to_string(), value.trim().to_string()));
+            }
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("# Level:") {
+            let number = rest.trim_start().split(|c: char| !c.is_ascii_digit()).next().unwrap_or("");
+            let level = number.parse().with_context(|| format!("Malformed level header on line {}", n + 1))?;
+            listing.levels.push((level, Vec::new()));
+            continue;
+        }
+        if trimmed.starts_with('#') || trimmed.starts_with("RESET") || trimmed.starts_with("READ") || trimmed.starts_with("COPY") {
+            continue;
+        }
+        let Some((level, gates)) = listing.levels.last_mut() else {
+            bail!("Micro-op on line {} before the first level", n + 1);
+        };
+        let op = lint::parse_op(line, n + 1, *level)
+            .map_err(|e| anyhow::anyhow!("Line {}: {}", n + 1, e))?;
+        gates.push(ListedGate { row: op.row, col: op.col, operands: op.operands });
+    }
+    Ok(listing)
+}
+
+// What a file to cross-validate holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Artifact {
+    Stats(Vec<ScheduleStats>),
+    Listing(Listing),
+}
+
+impl Artifact {
+    // A stats file or a micro-op listing, told apart by their contents
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read {:?}", path))?;
+        if text.lines().any(|l| l.trim().ends_with(" SCHEDULE:")) {
+            read_stats(&text).map(Artifact::Stats)
+        } else {
+            read_listing(&text).map(Artifact::Listing)
+        }
+        .context(format!("Failed to read {:?}", path))
+    }
+}
+
+// Where a new artifact departs from a reference one, one line per
+// difference; empty when they agree
+pub fn compare(reference: &Artifact, new: &Artifact) -> Result<Vec<String>> {
+    Ok(match (reference, new) {
+        (Artifact::Stats(reference), Artifact::Stats(new)) => compare_stats(reference, new),
+        (Artifact::Listing(reference), Artifact::Listing(new)) => compare_listings(reference, new),
+        _ => bail!("Cannot compare a stats file with a micro-op listing"),
+    })
+}
+
+fn compare_stats(reference: &[ScheduleStats], new: &[ScheduleStats]) -> Vec<String> {
+    let mut differences = Vec::new();
+    for old in reference {
+        let Some(stats) = new.iter().find(|s| s.name == old.name) else {
+            differences.push(format!("{}: schedule missing", old.name));
+            continue;
+        };
+        let mut differ = |field: &str, a: String, b: String| {
+            if a != b {
+                differences.push(format!("{}: {} {} -> {}", old.name, field, a, b));
+            }
+        };
+        let list = |d: &[usize]| d.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ");
+        differ("distribution", list(&old.distribution), list(&stats.distribution));
+        differ("levels", old.levels.to_string(), stats.levels.to_string());
+        differ("max gates", old.max_gates.to_string(), stats.max_gates.to_string());
+        differ("memristors", old.memristors.to_string(), stats.memristors.to_string());
+        differ("serial steps", old.serial_steps.to_string(), stats.serial_steps.to_string());
+        differ("parallel steps", old.parallel_steps.to_string(), stats.parallel_steps.to_string());
+        let dims = |(r, c): (usize, usize)| format!("{}x{}", r, c);
+        differ("serial size", dims(old.serial_size), dims(stats.serial_size));
+        differ("parallel size", dims(old.parallel_size), dims(stats.parallel_size));
+    }
+    differences
+}
+
+// Levels are compared by their gates; the footer by the fields both list,
+// since each implementation lists fields the other does not
+fn compare_listings(reference: &Listing, new: &Listing) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (level, gates) in &reference.levels {
+        match new.levels.iter().find(|(l, _)| l == level) {
+            None => differences.push(format!("level {}: missing", level)),
+            Some((_, listed)) if listed != gates => {
+                let moved = gates.iter().filter(|g| !listed.contains(g)).count();
+                differences.push(format!("level {}: {} of {} gates differ ({} listed)", level, moved, gates.len(), listed.len()));
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, value) in &reference.metrics {
+        if let Some(listed) = new.metric(name).filter(|v| v != value) {
+            differences.push(format!("{}: {} -> {}", name, value, listed));
+        }
+    }
+    differences
+}
@@ -1,8 +1,43 @@
 //lib.rs
+pub mod analysis;
+pub mod archive;
+pub mod batch;
+pub mod chain;
+pub mod compare;
+pub mod cost;
+pub mod diagnostics;
+pub mod endurance;
+pub mod fingerprint;
+pub mod gates;
 pub mod parser;
+pub mod resynth;
+pub mod retime;
 pub mod scheduler;
+pub mod selftest;
 pub mod mapper;
 pub mod generator;
+pub mod initstate;
+pub mod legacy;
+pub mod lint;
+pub mod metrics;
+pub mod monitor;
+pub mod output;
+pub mod overlay;
+pub mod passes;
+pub mod ports;
+pub mod preset;
+pub mod presynth;
+pub mod program;
+pub mod provenance;
+pub mod replicate;
+pub mod repro;
+pub mod schema;
+pub mod serve;
+pub mod simulator;
+pub mod suite;
+pub mod tech;
+pub mod tiling;
+pub mod verify;
 // Stub module to keep compatibility
 pub mod parallel {
     use crate::Circuit;
@@ -30,18 +65,52 @@ pub mod parallel {
         Ok(())
     }
 
-    pub fn create_naive_mapping_parallel(_circuit: &mut Circuit) -> Result<crate::CrossbarMapping> {
-        warn!("Parallel module function called but not implemented");
-        Ok(crate::CrossbarMapping::new())
+    // Naive placement is one column per gate in ASAP order; the sequential
+    // mapper is already linear, so it serves both modes
+    pub fn create_naive_mapping_parallel(circuit: &mut Circuit) -> Result<crate::CrossbarMapping> {
+        Ok(crate::mapper::create_naive_mapping(circuit))
     }
     
-    pub fn create_compact_mapping_parallel(_circuit: &mut Circuit) -> Result<crate::CrossbarMapping> {
-        warn!("Parallel module function called but not implemented");
-        Ok(crate::CrossbarMapping::new())
+    pub fn create_compact_mapping_parallel(circuit: &mut Circuit) -> Result<crate::CrossbarMapping> {
+        Ok(crate::mapper::create_compact_mapping_parallel(circuit))
+    }
+
+    // A pool of `jobs` threads; 0 takes one per core, as rayon does
+    pub fn thread_pool(jobs: usize) -> Result<rayon::ThreadPool> {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build()
+            .map_err(|e| anyhow::anyhow!("Failed to start {} worker threads: {}", jobs, e))
+    }
+
+    // Sizes the global pool, which every parallel phase runs on unless given a
+    // pool of its own. Only the first call takes effect, before any parallel work
+    pub fn set_jobs(jobs: usize) -> Result<()> {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global()
+            .map_err(|e| anyhow::anyhow!("Failed to set the number of worker threads to {}: {}", jobs, e))
+    }
+
+    // The parallel entry points on a pool the caller built, for library users
+    // that run Delphi next to other rayon work
+    pub fn create_compact_mapping_in(pool: &rayon::ThreadPool, circuit: &mut Circuit) -> crate::CrossbarMapping {
+        pool.install(|| crate::mapper::create_compact_mapping_parallel(circuit))
+    }
+
+    pub fn stream_compact_micro_ops_in<P: AsRef<std::path::Path> + Send>(
+        pool: &rayon::ThreadPool,
+        circuit: &mut Circuit,
+        path: P
+    ) -> Result<crate::program::Program> {
+        pool.install(|| crate::generator::stream_compact_micro_ops(circuit, path))
     }
 }
 
-// use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use smallvec::{smallvec, SmallVec};
+use diagnostics::Diagnostics;
+use initstate::InitState;
+use mapper::Regions;
+use ports::PortGroup;
+use program::Cell;
+use scheduler::GateId;
 // use std::sync::Arc;
 // use parking_lot::{RwLock, Mutex};
 
@@ -56,36 +125,96 @@ pub const MAX_COL: usize = 1000;      // Maximum columns in crossbar
 pub const MAX_CPY: usize = 100;       // Maximum copies
 pub const OUT_BIAS: usize = 10000;    // Output bias
 
-// Determine optimal chunk size for parallel processing based on problem size
+// Human-readable name of a signal id as it appears in the netlist
+pub fn signal_name(id: i32) -> String {
+    if id >= MAX_GATES as i32 {
+        format!("x{}", id - MAX_GATES as i32)
+    } else if id < 0 {
+        format!("t{}", -id)
+    } else {
+        format!("n{}", id)
+    }
+}
+
+// Inverse of `signal_name`; a bare number names a gate
+pub fn signal_id(name: &str) -> Option<i32> {
+    let (prefix, digits) = match name.find(|c: char| c.is_ascii_digit()) {
+        Some(at) => name.split_at(at),
+        None => return None,
+    };
+    let n: i32 = digits.parse().ok()?;
+    match prefix {
+        "x" if (n as usize) < MAX_PI => Some(MAX_GATES as i32 + n),
+        "t" if n > 0 => Some(-n),
+        "n" | "" if (n as usize) < MAX_GATES => Some(n),
+        _ => None,
+    }
+}
+
+// Determine optimal chunk size for parallel processing based on problem size,
+// split across the threads of the pool it is called from
 pub fn calculate_chunk_size(total_items: usize) -> usize {
-    let num_threads = num_cpus::get();
+    let num_threads = rayon::current_num_threads();
     let base_chunk = total_items / num_threads;
     if base_chunk < 8 {
         // For small problems, avoid excessive threading overhead
         return total_items;
     }
     // Aim for at least 8 items per chunk, max 1000 items
-    base_chunk.max(8).min(1000)
+    base_chunk.clamp(8, 1000)
 }
 
-#[derive(Debug, Clone)]
+// Two-input gate a circuit is built from; one-input gates are always NOT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Primitive {
+    #[default]
+    Nor,
+    Nand,
+}
+
+impl Primitive {
+    // Its entry in the gate registry
+    pub fn kind(self) -> gates::GateKind {
+        match self {
+            Primitive::Nor => gates::GateKind::Nor,
+            Primitive::Nand => gates::GateKind::Nand,
+        }
+    }
+
+    // Output word of a gate over its input words, 64 vectors at a time
+    pub fn eval<I: IntoIterator<Item = u64>>(self, inputs: I) -> u64 {
+        self.kind().eval(inputs)
+    }
+}
+
+impl std::fmt::Display for Primitive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.kind().name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct MemristiveGate {
     pub fanin: usize,
-    pub inputs: Vec<Option<Box<MemristiveGate>>>,
+    pub inputs: [Option<Box<MemristiveGate>>; MAX_FANIN],
     pub value: i32,
     pub idx: i32,
     pub jdx: i32,
+    // Logic value the cell holds before the program runs, -1 if not known
     pub state: i32,
     pub asap_level: i32,
     pub list_time: i32,
     pub is_copy: bool,
+    // Holds its signal before the program runs, so no op writes it
+    pub held: bool,
 }
 
 impl Default for MemristiveGate {
     fn default() -> Self {
         Self {
             fanin: 0,
-            inputs: vec![None; MAX_FANIN],
+            inputs: Default::default(),
             value: -1,
             idx: -1,
             jdx: -1,
@@ -93,6 +222,43 @@ impl Default for MemristiveGate {
             asap_level: -1,
             list_time: -1,
             is_copy: false,
+            held: false,
+        }
+    }
+}
+
+impl MemristiveGate {
+    // Placed cells always carry their coordinates; the value alone cannot
+    // tell, since the first temporary signal is also -1
+    pub fn is_occupied(&self) -> bool {
+        self.idx != -1
+    }
+
+    // Snapshot of this cell for use as another cell's operand. Readers only
+    // need the operand's coordinates and, for copies, the cell it was copied
+    // from, so operands of operands are not cloned (deep clones grow
+    // exponentially with circuit depth)
+    pub fn operand(&self) -> Box<MemristiveGate> {
+        Box::new(MemristiveGate {
+            inputs: std::array::from_fn(|k| {
+                self.inputs[k].as_ref().map(|input| Box::new(input.detached()))
+            }),
+            ..self.detached()
+        })
+    }
+
+    fn detached(&self) -> MemristiveGate {
+        MemristiveGate {
+            fanin: self.fanin,
+            inputs: Default::default(),
+            value: self.value,
+            idx: self.idx,
+            jdx: self.jdx,
+            state: self.state,
+            asap_level: self.asap_level,
+            list_time: self.list_time,
+            is_copy: self.is_copy,
+            held: self.held,
         }
     }
 }
@@ -100,8 +266,13 @@ impl Default for MemristiveGate {
 #[derive(Debug, Clone)]
 pub struct TableGate {
     pub fanin: usize,
-    pub inputs: Vec<i32>,
+    // The first `fanin` entries; NOTs keep an unused second entry
+    pub inputs: SmallVec<[i32; 2]>,
     pub out: i32,
+    // Further signals the gate drives, each carrying the value of `out`: a
+    // fused op whose result goes by several names, or equal gates a pass
+    // merged into one. The gate is scheduled and mapped once, in one cell
+    pub extra_outs: Vec<ExtraOut>,
     pub asap_level: i32,
     pub alap_level: i32,
     pub list_level: i32,
@@ -111,28 +282,129 @@ pub struct TableGate {
     pub output_gates: Vec<i32>,
     pub is_output: bool,
     pub gate_map: Option<Box<MemristiveGate>>,
+    pub attributes: Attributes,
+    // The netlist line the gate came from; gates passes build from it keep it
+    pub origin: Option<Origin>,
 }
 
 impl Default for TableGate {
     fn default() -> Self {
         Self {
             fanin: 0,
-            inputs: vec![-1; MAX_FANIN],
+            inputs: smallvec![-1; 2],
             out: -1,
+            extra_outs: Vec::new(),
             asap_level: -1,
             alap_level: -1,
             list_level: -1,
             list_time: -1,
             mobility: 0,
             slack: 0,
-            output_gates: vec![0; MAX_GATES],
+            output_gates: Vec::new(),
             is_output: false,
             gate_map: None,
+            attributes: Attributes::default(),
+            origin: None,
         }
     }
 }
 
-#[derive(Default, Debug)]
+impl TableGate {
+    // Reads `inputs`: a NOT for one signal, a NOR (NAND) of any width for more
+    pub fn set_inputs(&mut self, inputs: &[i32]) {
+        self.fanin = inputs.len();
+        self.inputs = SmallVec::from_slice(inputs);
+        if self.inputs.len() < 2 {
+            self.inputs.resize(2, -1);
+        }
+    }
+
+    // Annotated `@dont_touch`: passes leave it, its operands and its signal as written
+    pub fn dont_touch(&self) -> bool {
+        self.attributes.contains(DONT_TOUCH)
+    }
+
+    // Every signal the gate drives, `out` first
+    pub fn outs(&self) -> impl Iterator<Item = i32> + '_ {
+        std::iter::once(self.out).chain(self.extra_outs.iter().map(|o| o.signal))
+    }
+
+    // The signals of the gate that are primary outputs
+    pub fn primary_outputs(&self) -> impl Iterator<Item = i32> + '_ {
+        self.is_output.then_some(self.out).into_iter()
+            .chain(self.extra_outs.iter().filter(|o| o.is_output).map(|o| o.signal))
+    }
+
+    pub fn is_multi_output(&self) -> bool {
+        !self.extra_outs.is_empty()
+    }
+}
+
+// A signal a multi-output gate drives besides its `out`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtraOut {
+    pub signal: i32,
+    pub is_output: bool,
+}
+
+// Where a gate was written: the line of a native netlist, counted from 1,
+// and its text without comments or annotations. A line expanding into
+// several gates (a wide NOR, a macro) gives each of them the same origin
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Origin {
+    pub line: usize,
+    pub text: String,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.text)
+    }
+}
+
+// The attribute passes must respect: the gate is neither removed, merged,
+// rebuilt, duplicated nor given other operands or another operand order
+pub const DONT_TOUCH: &str = "dont_touch";
+
+// Annotations of a native netlist gate, `@critical` or `@key=value`, kept
+// with the gate for passes and reports. Flags have an empty value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes(BTreeMap<String, String>);
+
+impl Attributes {
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // By key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+// As written in a netlist: `@critical @owner=alu`
+impl std::fmt::Display for Attributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let annotations: Vec<String> = self.iter()
+            .map(|(k, v)| if v.is_empty() { format!("@{}", k) } else { format!("@{}={}", k, v) })
+            .collect();
+        f.pad(&annotations.join(" "))
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct Circuit {
     pub gates: Vec<TableGate>,
     pub num_gates: usize,
@@ -144,6 +416,50 @@ pub struct Circuit {
     pub max_list: i32,
     pub max_resources: i32,
     pub bench_name: String,
+    // Meaning of two-input gates
+    pub primitive: Primitive,
+    // Signal id -> index of the gate driving it
+    pub drivers: HashMap<i32, usize>,
+    // Signal id -> indices of the gates reading it
+    pub fanout: HashMap<i32, Vec<usize>>,
+    // Outputs read out inverted instead of through a trailing NOT: output ->
+    // the signal whose cell is read
+    pub inverted_outputs: BTreeMap<i32, i32>,
+    // Primary inputs, by index, loaded as their complement (active-low); the
+    // gates read their signal ids as the stored value
+    pub complemented_inputs: BTreeSet<usize>,
+    // Word-level ports over the primary inputs, from a grouping file
+    pub input_groups: Vec<PortGroup>,
+    // Line of each primary input, by index: its row in compact mappings and
+    // its column in naive ones. Empty puts input k on line k
+    pub input_lines: Vec<usize>,
+    // Primary inputs, by index, that no gate reads and the mappings give no
+    // line; the programs still take a bit for them in every vector
+    pub unused_inputs: BTreeSet<usize>,
+    // Gates the compact mappers keep together in one run of a row, each
+    // cluster by output signal in mapping order
+    pub clusters: Vec<Vec<i32>>,
+    // Row of the compact mapping a placement pass chose for each gate, by
+    // output signal; gates without one go to their last operand's row
+    pub planned_rows: HashMap<i32, usize>,
+    // Lines of the mappers' crossbar that only store values, from the
+    // technology's region map; the mappers place no gate there
+    pub regions: Regions,
+    // Cells the compact mapping finds holding a value, from an init-state file
+    pub init_state: InitState,
+    // Columns the compact mapper allows between a gate and the operands in
+    // its row; operands further away are copied next to it
+    pub max_span: Option<usize>,
+    // Whether the compact program's metrics count its copies in the reads and
+    // writes (see `ProgramCycles::folded`)
+    pub fold_copies: bool,
+    // Output signals of the gates in the order the netlist and the passes
+    // left them, recorded when the gates are first sorted and extended by
+    // gates added since at each later sort; empty while `gates` is still in
+    // that order (see `gates_in_original_order`)
+    pub original_order: Vec<i32>,
+    // Findings of the stages that have run on the circuit
+    pub diagnostics: Diagnostics,
 }
 
 impl Circuit {
@@ -159,29 +475,389 @@ impl Circuit {
             max_list: 0,
             max_resources: 0,
             bench_name: String::new(),
+            primitive: Primitive::Nor,
+            drivers: HashMap::new(),
+            fanout: HashMap::new(),
+            inverted_outputs: BTreeMap::new(),
+            complemented_inputs: BTreeSet::new(),
+            input_groups: Vec::new(),
+            input_lines: Vec::new(),
+            unused_inputs: BTreeSet::new(),
+            clusters: Vec::new(),
+            planned_rows: HashMap::new(),
+            regions: Regions::default(),
+            init_state: InitState::default(),
+            max_span: None,
+            fold_copies: false,
+            original_order: Vec::new(),
+            diagnostics: Diagnostics::new(),
         }
     }
+
+    // Rebuilds the driver and fanout indices. Both hold gate indices, so this
+    // must run again whenever the gates are reordered.
+    pub fn index_signals(&mut self) {
+        self.drivers.clear();
+        self.fanout.clear();
+        for (i, gate) in self.gates.iter().take(self.num_gates).enumerate() {
+            // The first driver wins, as with the linear scans this replaces
+            for out in gate.outs() {
+                self.drivers.entry(out).or_insert(i);
+            }
+            for &input in &gate.inputs[..gate.fanin] {
+                self.fanout.entry(input).or_default().push(i);
+            }
+        }
+    }
+
+    pub fn input_line(&self, input: usize) -> usize {
+        self.input_lines.get(input).copied().unwrap_or(input)
+    }
+
+    // Primary inputs the mappings place, by index
+    pub fn mapped_inputs(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.num_inputs).filter(|n| !self.unused_inputs.contains(n))
+    }
+
+    // Lines the mapped inputs take: rows of compact mappings, columns of naive ones
+    pub fn input_line_count(&self) -> usize {
+        self.num_inputs - self.unused_inputs.len()
+    }
+
+    pub fn driver_index(&self, signal: i32) -> Option<usize> {
+        self.drivers.get(&signal).copied()
+    }
+
+    pub fn driver(&self, signal: i32) -> Option<&TableGate> {
+        self.driver_index(signal).map(|i| &self.gates[i])
+    }
+
+    // The netlist line behind a signal, through the gate driving it
+    pub fn origin(&self, signal: i32) -> Option<&Origin> {
+        self.driver(signal).and_then(|g| g.origin.as_ref())
+    }
+
+    // Indices of the gates reading a signal
+    pub fn readers(&self, signal: i32) -> &[usize] {
+        self.fanout.get(&signal).map_or(&[], |r| r.as_slice())
+    }
+
+    // Indices of the gates reading any signal gate `gate` drives
+    pub fn gate_readers(&self, gate: GateId) -> impl Iterator<Item = usize> + '_ {
+        self.gates[gate].outs().flat_map(|s| self.readers(s).iter().copied())
+    }
+
+    // The signal the cell of a signal's driver holds: the driver's `out`,
+    // which a multi-output gate's other signals are read from
+    pub fn cell_signal(&self, signal: i32) -> i32 {
+        self.driver(signal).map_or(signal, |g| g.out)
+    }
+
+    // Indices of the gates a gate depends on, itself included, in gate order
+    pub fn fanin_cone(&self, gate: usize) -> Vec<usize> {
+        self.cone(gate, |g| g.inputs[..g.fanin].iter().filter_map(|&s| self.driver_index(s)).collect())
+    }
+
+    // Indices of the gates that depend on a gate, itself included, in gate order
+    pub fn fanout_cone(&self, gate: usize) -> Vec<usize> {
+        self.cone(gate, |g| g.outs().flat_map(|s| self.readers(s).iter().copied()).collect())
+    }
+
+    fn cone<F: Fn(&TableGate) -> Vec<usize>>(&self, gate: usize, next: F) -> Vec<usize> {
+        let mut visited = vec![false; self.num_gates];
+        let mut pending = vec![gate];
+        visited[gate] = true;
+        while let Some(i) = pending.pop() {
+            for j in next(&self.gates[i]) {
+                if !visited[j] {
+                    visited[j] = true;
+                    pending.push(j);
+                }
+            }
+        }
+        (0..self.num_gates).filter(|&i| visited[i]).collect()
+    }
+
+    // Gates on the longest path from the primary inputs to a gate, itself
+    // included. Unlike the ASAP level it does not need a schedule
+    pub fn depth(&self, gate: usize) -> usize {
+        let mut depths: HashMap<usize, usize> = HashMap::new();
+        let mut pending = vec![(gate, false)];
+        while let Some((i, expanded)) = pending.pop() {
+            let g = &self.gates[i];
+            let drivers = g.inputs[..g.fanin].iter().filter_map(|&s| self.driver_index(s));
+            if expanded {
+                let depth = 1 + drivers.filter_map(|j| depths.get(&j)).max().copied().unwrap_or(0);
+                depths.insert(i, depth);
+                continue;
+            }
+            if depths.contains_key(&i) {
+                continue;
+            }
+            pending.push((i, true));
+            pending.extend(drivers.filter(|j| !depths.contains_key(j)).map(|j| (j, false)));
+        }
+        depths[&gate]
+    }
+
+    // Gates carrying each annotation, as `name` or `name=value`, in netlist order
+    pub fn attribute_usage(&self) -> BTreeMap<String, Vec<i32>> {
+        let mut usage: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        for gate in &self.gates[..self.num_gates] {
+            for (name, value) in gate.attributes.iter() {
+                let attribute = if value.is_empty() { name.to_string() } else { format!("{}={}", name, value) };
+                usage.entry(attribute).or_default().push(gate.out);
+            }
+        }
+        usage
+    }
+
+    // One line on a gate for error messages and tools that point at gates:
+    // its function with operands by name, its levels and the cell the last
+    // mapping put it in, e.g. "n5 = NOR(n4, x2), ASAP 2 ALAP 3 LIST 2, cell 2x3"
+    pub fn describe_gate(&self, gate: GateId) -> String {
+        let Some(g) = self.gates[..self.num_gates].get(gate) else {
+            return format!("no gate {} (the circuit has {})", gate, self.num_gates);
+        };
+        let function = gates::GateKind::of(self.primitive, g.fanin);
+        let operands: Vec<String> = g.inputs[..g.fanin].iter().map(|&s| signal_name(s)).collect();
+        let outs: Vec<String> = g.outs().map(signal_name).collect();
+        let mut text = format!("{} = {}({})", outs.join(" "), function, operands.join(", "));
+        if g.primary_outputs().next().is_some() {
+            text += " [output]";
+        }
+        if !g.attributes.is_empty() {
+            text += &format!(" {}", g.attributes);
+        }
+        if let Some((&output, _)) = self.inverted_outputs.iter().find(|&(_, &s)| s == g.out) {
+            text += &format!(" [read out inverted as {}]", signal_name(output));
+        }
+        if g.asap_level < 1 {
+            text += ", unscheduled";
+        } else {
+            text += &format!(", ASAP {} ALAP {}", output::level_number(g.asap_level), output::level_number(g.alap_level));
+            if g.list_level != -1 {
+                text += &format!(" LIST {}", output::level_number(g.list_level));
+            }
+        }
+        match &g.gate_map {
+            Some(cell) => text += &format!(", cell {}x{}", cell.idx, cell.jdx),
+            None => text += ", not mapped",
+        }
+        text
+    }
+
+    // The transitive fanin cone of `outputs`: the gates they depend on under
+    // their own signal ids, drivers before readers. Every primary input is
+    // kept so input vectors carry over, only the selected signals are outputs,
+    // and scheduling and mapping start afresh. Signals no gate drives
+    // contribute nothing
+    pub fn extract_cone(&self, outputs: &[i32]) -> Circuit {
+        let mut visited = vec![false; self.num_gates];
+        let mut order = Vec::new();
+        for &out in outputs {
+            let Some(root) = self.driver_index(out) else { continue };
+            // Gates are emitted once all their drivers have been
+            let mut pending = vec![(root, false)];
+            while let Some((i, expanded)) = pending.pop() {
+                if expanded {
+                    order.push(i);
+                    continue;
+                }
+                if visited[i] {
+                    continue;
+                }
+                visited[i] = true;
+                pending.push((i, true));
+                let gate = &self.gates[i];
+                for &input in gate.inputs[..gate.fanin].iter().rev() {
+                    if let Some(j) = self.driver_index(input).filter(|&j| !visited[j]) {
+                        pending.push((j, false));
+                    }
+                }
+            }
+        }
+
+        let selected: HashSet<i32> = outputs.iter().copied().collect();
+        let mut cone = Circuit::new();
+        cone.bench_name = self.bench_name.clone();
+        cone.primitive = self.primitive;
+        cone.diagnostics = self.diagnostics.clone();
+        for gate in order.into_iter().map(|i| &self.gates[i]) {
+            cone.gates.push(TableGate {
+                fanin: gate.fanin,
+                inputs: gate.inputs.clone(),
+                out: gate.out,
+                extra_outs: gate.extra_outs.iter()
+                    .map(|o| ExtraOut { signal: o.signal, is_output: selected.contains(&o.signal) })
+                    .collect(),
+                is_output: selected.contains(&gate.out),
+                origin: gate.origin.clone(),
+                ..Default::default()
+            });
+        }
+        cone.num_gates = cone.gates.len();
+        cone.num_inputs = self.num_inputs;
+        cone.primary_inputs = self.primary_inputs.clone();
+        cone.num_outputs = cone.gates.iter().map(|g| g.primary_outputs().count()).sum();
+        cone.index_signals();
+        cone
+    }
+
+    // Reorders the gates and keeps the signal indices in sync. The order the
+    // gates had is recorded first, for `gates_in_original_order`
+    pub fn sort_gates_by_key<K: Ord, F: FnMut(&TableGate) -> K>(&mut self, f: F) {
+        let recorded: HashSet<i32> = self.original_order.iter().copied().collect();
+        let added: Vec<i32> = self.gates[..self.num_gates].iter()
+            .map(|g| g.out)
+            .filter(|out| !recorded.contains(out))
+            .collect();
+        self.original_order.extend(added);
+        self.gates.sort_by_key(f);
+        self.index_signals();
+    }
+
+    // Gate indices in the order the netlist and the passes wrote the gates,
+    // whatever order the scheduler and the mappers have sorted them into
+    // since. Gates added after the last sort follow in their current order
+    pub fn gates_in_original_order(&self) -> Vec<usize> {
+        let position: HashMap<i32, usize> = self.original_order.iter().enumerate()
+            .map(|(k, &out)| (out, k))
+            .collect();
+        let mut order: Vec<usize> = (0..self.num_gates).collect();
+        order.sort_by_key(|&i| position.get(&self.gates[i].out).copied().unwrap_or(usize::MAX));
+        order
+    }
+
+    // Gate indices with every gate after the gates driving its operands: the
+    // original order, where each gate waits for its drivers, the first ready
+    // gate in that order going next. Neither the levels nor the current order
+    // of the gates matter. Gates on a combinational loop come last, in the
+    // original order
+    pub fn gates_in_topological_order(&self) -> Vec<usize> {
+        let original = self.gates_in_original_order();
+        let mut rank = vec![0; self.num_gates];
+        for (k, &i) in original.iter().enumerate() {
+            rank[i] = k;
+        }
+        let mut pending = vec![0usize; self.num_gates];
+        for (i, gate) in self.gates[..self.num_gates].iter().enumerate() {
+            pending[i] = gate.inputs[..gate.fanin].iter().filter(|&&s| self.driver_index(s).is_some()).count();
+        }
+        // Ready gates by original position
+        let mut ready: BTreeSet<usize> = (0..self.num_gates).filter(|&k| pending[original[k]] == 0).collect();
+        let mut order = Vec::with_capacity(self.num_gates);
+        let mut placed = vec![false; self.num_gates];
+        while let Some(k) = ready.pop_first() {
+            let i = original[k];
+            order.push(i);
+            placed[i] = true;
+            // Operands wait for the signal's first driver only
+            for out in self.gates[i].outs().filter(|&s| self.driver_index(s) == Some(i)) {
+                for &reader in self.readers(out) {
+                    // A reader reading the gate twice waits for it twice
+                    pending[reader] -= 1;
+                    if pending[reader] == 0 {
+                        ready.insert(rank[reader]);
+                    }
+                }
+            }
+        }
+        order.extend(original.into_iter().filter(|&i| !placed[i]));
+        order
+    }
 }
 
-#[derive(Default, Debug)]
+// Cell returned for every position that has not been written yet
+const NO_INPUT: Option<Box<MemristiveGate>> = None;
+static EMPTY_CELL: MemristiveGate = MemristiveGate {
+    fanin: 0,
+    inputs: [NO_INPUT; MAX_FANIN],
+    value: -1,
+    idx: -1,
+    jdx: -1,
+    state: -1,
+    asap_level: -1,
+    list_time: -1,
+    is_copy: false,
+    held: false,
+};
+
+// Sparse MAX_ROW x MAX_COL crossbar: rows are allocated and widened only as
+// cells are written, so a fresh mapping needs no clearing
+#[derive(Default, Debug, PartialEq)]
 pub struct CrossbarMapping {
-    pub crossbar: Vec<Vec<MemristiveGate>>,
+    crossbar: Vec<Vec<MemristiveGate>>,
+    // Gate cell coordinates by ASAP level, filled by `place`
+    levels: BTreeMap<i32, Vec<(usize, usize)>>,
     pub max_idx: i32,
     pub max_jdx: i32,
 }
 
 impl CrossbarMapping {
     pub fn new() -> Self {
-        let mut crossbar = Vec::with_capacity(MAX_ROW);
-        for _ in 0..MAX_ROW {
-            let row = vec![MemristiveGate::default(); MAX_COL];
-            crossbar.push(row);
-        }
-        
         Self {
-            crossbar,
+            crossbar: Vec::new(),
+            levels: BTreeMap::new(),
             max_idx: 0,
             max_jdx: 0,
         }
     }
-}
\ No newline at end of file
+
+    pub fn cell(&self, i: usize, j: usize) -> &MemristiveGate {
+        self.crossbar.get(i)
+            .and_then(|row| row.get(j))
+            .unwrap_or(&EMPTY_CELL)
+    }
+
+    pub fn cell_mut(&mut self, i: usize, j: usize) -> &mut MemristiveGate {
+        assert!(i < MAX_ROW && j < MAX_COL,
+            "cell {}x{} outside the {}x{} crossbar", i, j, MAX_ROW, MAX_COL);
+        if self.crossbar.len() <= i {
+            self.crossbar.resize_with(i + 1, Vec::new);
+        }
+        let row = &mut self.crossbar[i];
+        if row.len() <= j {
+            row.resize_with(j + 1, MemristiveGate::default);
+        }
+        &mut row[j]
+    }
+
+    // Writes a cell at its own coordinates; gate cells are also filed under
+    // their ASAP level so generators can walk levels without scanning the grid
+    pub fn place(&mut self, cell: MemristiveGate) {
+        let (i, j) = (cell.idx as usize, cell.jdx as usize);
+        if cell.value < MAX_GATES as i32 && !cell.is_copy {
+            self.levels.entry(cell.asap_level).or_default().push((i, j));
+        }
+        *self.cell_mut(i, j) = cell;
+    }
+
+    // Gate cells placed at each level in ascending level order, row-major
+    // within a level
+    pub fn cells_by_level(&self) -> impl Iterator<Item = (i32, Vec<(usize, usize)>)> + '_ {
+        self.levels.iter().map(|(&level, cells)| {
+            let mut cells = cells.clone();
+            cells.sort_unstable();
+            (level, cells)
+        })
+    }
+
+    // The cell gate `gate` of the circuit this mapping was built from holds.
+    // Gate ids are positions in the circuit as mapped, which orders its gates
+    // by ASAP level
+    pub fn location_of(&self, circuit: &Circuit, gate: GateId) -> Option<Cell> {
+        let gate = circuit.gates[..circuit.num_gates].get(gate)?;
+        self.levels.get(&gate.asap_level)?
+            .iter()
+            .find(|&&(i, j)| self.cell(i, j).value == gate.out)
+            .map(|&(i, j)| Cell { row: i as u32, col: j as u32 })
+    }
+
+    // Every cell that has been written, row by row
+    pub fn occupied_cells(&self) -> impl Iterator<Item = &MemristiveGate> {
+        self.crossbar.iter()
+            .flatten()
+            .filter(|c| c.is_occupied())
+    }
+}
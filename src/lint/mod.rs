@@ -0,0 +1,662 @@
+//lint/mod.rs
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+
+use crate::program::{Cell, MicroOp, Program, BINARY_MAGIC};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    Syntax,
+    ReadBeforeWrite,
+    DoubleWrite,
+    Clobber,
+    OutOfBounds,
+    LevelOrder,
+    Uninitialized,
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LintKind::Syntax => "syntax",
+            LintKind::ReadBeforeWrite => "read-before-write",
+            LintKind::DoubleWrite => "double-write",
+            LintKind::Clobber => "clobber",
+            LintKind::OutOfBounds => "out-of-bounds",
+            LintKind::LevelOrder => "level-order",
+            LintKind::Uninitialized => "uninitialized",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Where an issue lies: a line of a text listing, or an op of a JSON or
+// binary program by its position in `Program::ops`, both from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Place {
+    Line(usize),
+    Op(usize),
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Place::Line(n) => write!(f, "line {}", n),
+            Place::Op(n) => write!(f, "op {}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub at: Place,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: [{}] {}", self.at, self.kind, self.message)
+    }
+}
+
+// Where an operand of a micro-op comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    PrimaryInput(usize),
+    Cell(usize, usize),
+}
+
+// An operand of a listed gate: the column it is read from, in the gate's
+// row, and what the cell holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operand {
+    pub col: usize,
+    pub source: Source,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ParsedOp {
+    pub(crate) line: usize,
+    pub(crate) level: i32,
+    pub(crate) row: usize,
+    pub(crate) col: usize,
+    pub(crate) operands: Vec<Operand>,
+}
+
+// A COPY line: the cell or input read and the cell written, ahead of the
+// level's gates
+#[derive(Debug, Clone)]
+struct ParsedCopy {
+    line: usize,
+    level: i32,
+    source: Source,
+    cell: (usize, usize),
+}
+
+// Cells a RESET line initializes ahead of the level's ops
+#[derive(Debug, Clone)]
+struct ParsedReset {
+    line: usize,
+    level: i32,
+    cells: Vec<(usize, usize)>,
+}
+
+// A program as the checks see it, read from a text listing or taken from a
+// JSON or binary program. Positions are the listing's lines or the
+// program's ops
+#[derive(Debug, Default)]
+struct Listing {
+    ops: Vec<ParsedOp>,
+    resets: Vec<ParsedReset>,
+    copies: Vec<ParsedCopy>,
+    // Cells handed to the next tile by pipeline programs, and the cells the
+    // read phase reads
+    sends: Vec<(usize, &'static str, Source)>,
+    // Output reads of the read phase, with their buffer slots
+    reads: Vec<(usize, usize)>,
+    // State a partitioned program resumes from: cells holding values and
+    // cells initialized by earlier parts
+    resumed: HashSet<(usize, usize)>,
+    pending: HashSet<(usize, usize)>,
+    bounds: Option<(usize, usize)>,
+    // Found while reading the program
+    issues: Vec<LintIssue>,
+}
+
+// Checks a micro-op program in any format it is written in: binary and JSON
+// programs by their extension or, failing that, their first bytes, and text
+// listings otherwise
+pub fn lint_micro_ops<P: AsRef<Path>>(path: P) -> Result<Vec<LintIssue>> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).context(format!("Failed to read micro-op file: {:?}", path))?;
+    let json = |bytes: &[u8]| Program::read_json(&String::from_utf8_lossy(bytes));
+    let program = match path.extension().and_then(|e| e.to_str()) {
+        Some("bin") => Program::read_binary(&bytes),
+        Some("json") => json(&bytes),
+        _ if bytes.starts_with(BINARY_MAGIC) => Program::read_binary(&bytes),
+        _ if bytes.trim_ascii_start().starts_with(b"{") => json(&bytes),
+        _ => {
+            let text = String::from_utf8(bytes).context(format!("Micro-op file {:?} is not text", path))?;
+            return Ok(lint_micro_ops_text(&text));
+        }
+    };
+    Ok(lint_program(&program.context(format!("Failed to load program: {:?}", path))?))
+}
+
+// Checks a text micro-op program in the layout written by `generate_micro_ops`
+pub fn lint_micro_ops_text(text: &str) -> Vec<LintIssue> {
+    check(read_text(text))
+}
+
+// Checks a program as `--emit` writes it, with the same checks as its text
+// listing. Issues name ops instead of lines
+pub fn lint_program(program: &Program) -> Vec<LintIssue> {
+    check(read_program(program)).into_iter()
+        .map(|issue| match issue.at {
+            Place::Line(n) => LintIssue { at: Place::Op(n), ..issue },
+            Place::Op(_) => issue,
+        })
+        .collect()
+}
+
+fn read_text(text: &str) -> Listing {
+    let mut listing = Listing::default();
+    let issues = &mut listing.issues;
+    // Below any level a header can name, zero-based listings starting at 0
+    let mut level = -1;
+    let mut in_footer = false;
+
+    for (n, raw) in text.lines().enumerate() {
+        let line_no = n + 1;
+        // Annotated listings end ops with a comment
+        let line = match raw.split_once("  # ") {
+            Some((op, _)) if !op.trim_start().starts_with('#') => op,
+            _ => raw,
+        }.trim_end();
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() || trimmed.starts_with("---") {
+            continue;
+        }
+        if trimmed == "Metrics" {
+            in_footer = true;
+            continue;
+        }
+        if in_footer {
+            if let Some(size) = trimmed.strip_prefix("Crossbar Size").and_then(|r| r.split(':').nth(1)) {
+                match parse_size(size.trim()) {
+                    Some(dims) => listing.bounds = Some(dims),
+                    None => issues.push(issue(line_no, LintKind::Syntax,
+                        format!("Malformed crossbar size '{}'", size.trim()))),
+                }
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("# Level:") {
+            let number = rest.trim_start().split(|c: char| !c.is_ascii_digit()).next().unwrap_or("");
+            match number.parse::<i32>() {
+                Ok(next) => {
+                    if next <= level {
+                        issues.push(issue(line_no, LintKind::LevelOrder,
+                            format!("Level {} does not follow level {}", next, level)));
+                    }
+                    level = next;
+                }
+                Err(_) => issues.push(issue(line_no, LintKind::Syntax,
+                    format!("Malformed level header '{}'", trimmed))),
+            }
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Pipeline tile programs: inputs arrive in the input rows before the
+        // first level, live-out cells leave after the last, then a barrier
+        if trimmed == "SYNC" {
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("RECV") {
+            if let Err(message) = rest.split_whitespace().next().map_or(Err("Missing row".to_string()), parse_num) {
+                issues.push(issue(line_no, LintKind::Syntax, message));
+            }
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("SEND") {
+            match rest.split_whitespace().next().map_or(Err("Missing cell".to_string()), parse_source) {
+                Ok(source) => listing.sends.push((line_no, "Sent", source)),
+                Err(message) => issues.push(issue(line_no, LintKind::Syntax, message)),
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("READ ") {
+            match parse_read(rest) {
+                Ok((source, slot)) => {
+                    listing.sends.push((line_no, "Read", source));
+                    listing.reads.push((line_no, slot));
+                }
+                Err(message) => issues.push(issue(line_no, LintKind::Syntax, message)),
+            }
+            continue;
+        }
+
+        if let Some((keyword, rest)) = ["RESUME", "PENDING", "CHECKPOINT"].iter()
+            .find_map(|k| trimmed.strip_prefix(k).map(|rest| (*k, rest)))
+        {
+            for name in rest.split_whitespace() {
+                match parse_source(name) {
+                    // Checkpoints may list copies
+                    Ok(Source::Cell(r, c)) => match keyword {
+                        "RESUME" => { listing.resumed.insert((r, c)); }
+                        "PENDING" => { listing.pending.insert((r, c)); }
+                        _ => {}
+                    },
+                    Ok(Source::PrimaryInput(_)) => issues.push(issue(line_no, LintKind::Syntax,
+                        format!("{} lists input '{}' instead of a cell", keyword, name))),
+                    Err(message) => issues.push(issue(line_no, LintKind::Syntax, message)),
+                }
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("COPY ") {
+            match parse_copy(rest, line_no, level) {
+                Ok(copy) => listing.copies.push(copy),
+                Err(message) => issues.push(issue(line_no, LintKind::Syntax, message)),
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("RESET") {
+            match parse_reset(rest, line_no, level) {
+                Ok(reset) => listing.resets.push(reset),
+                Err(message) => issues.push(issue(line_no, LintKind::Syntax, message)),
+            }
+            continue;
+        }
+
+        match parse_op(line, line_no, level) {
+            Ok(op) => listing.ops.push(op),
+            Err(message) => issues.push(issue(line_no, LintKind::Syntax, message)),
+        }
+    }
+    listing
+}
+
+// Takes a program's ops as its text listing would list them: operands are
+// named after what their cells hold, so a copy is named after its source,
+// and input loads only name their cells
+fn read_program(program: &Program) -> Listing {
+    let mut listing = Listing { bounds: Some((program.rows, program.cols)), ..Listing::default() };
+    let mut names: HashMap<Cell, Source> = HashMap::new();
+    let mut last = None;
+    let mut at = 0;
+    for step in &program.steps {
+        // Steps at level 0 load the inputs and read the outputs
+        if step.level > 0 {
+            if let Some(previous) = last.filter(|&previous| step.level <= previous) {
+                listing.issues.push(issue(at + 1, LintKind::LevelOrder,
+                    format!("Level {} does not follow level {}", step.level, previous)));
+            }
+            last = Some(step.level);
+        }
+        for op in &step.ops {
+            at += 1;
+            match op {
+                MicroOp::InputLoad { input, cell, .. } => {
+                    if cell.row as usize >= program.rows || cell.col as usize >= program.cols {
+                        listing.issues.push(issue(at, LintKind::OutOfBounds,
+                            format!("Input cell {} outside declared {}x{} crossbar", cell, program.rows, program.cols)));
+                    }
+                    names.insert(*cell, Source::PrimaryInput(*input));
+                }
+                MicroOp::Held { cell, .. } => {
+                    listing.resumed.insert(position(cell));
+                }
+                MicroOp::Preset { cell } => {
+                    listing.pending.insert(position(cell));
+                }
+                MicroOp::Init { cells } => {
+                    let cells: Vec<(usize, usize)> = cells.iter().map(position).collect();
+                    match check_reset_line(&cells) {
+                        Ok(()) => listing.resets.push(ParsedReset { line: at, level: step.level, cells }),
+                        Err(message) => listing.issues.push(issue(at, LintKind::Syntax, message)),
+                    }
+                }
+                MicroOp::Copy { from, cell } => {
+                    let source = name(&names, from);
+                    listing.copies.push(ParsedCopy { line: at, level: step.level, source, cell: position(cell) });
+                    names.insert(*cell, source);
+                }
+                MicroOp::ExecNor { inputs, cell } => add_gate(&mut listing, &mut names, at, step.level, inputs, cell),
+                MicroOp::ExecNot { input, cell } => {
+                    add_gate(&mut listing, &mut names, at, step.level, std::slice::from_ref(input), cell)
+                }
+                MicroOp::Read { cell, slot, .. } => {
+                    let (row, col) = position(cell);
+                    listing.sends.push((at, "Read", Source::Cell(row, col)));
+                    listing.reads.push((at, *slot));
+                }
+            }
+        }
+    }
+    listing
+}
+
+fn position(cell: &Cell) -> (usize, usize) {
+    (cell.row as usize, cell.col as usize)
+}
+
+fn name(names: &HashMap<Cell, Source>, cell: &Cell) -> Source {
+    names.get(cell).copied().unwrap_or(Source::Cell(cell.row as usize, cell.col as usize))
+}
+
+fn add_gate(listing: &mut Listing, names: &mut HashMap<Cell, Source>, at: usize, level: i32, inputs: &[Cell], cell: &Cell) {
+    let operands = inputs.iter().map(|c| Operand { col: c.col as usize, source: name(names, c) }).collect();
+    let (row, col) = position(cell);
+    listing.ops.push(ParsedOp { line: at, level, row, col, operands });
+    names.insert(*cell, Source::Cell(row, col));
+}
+
+fn check(mut listing: Listing) -> Vec<LintIssue> {
+    let mut issues = std::mem::take(&mut listing.issues);
+    check_ops(&listing.ops, &listing.resumed, listing.bounds, &mut issues);
+    check_resets(&listing.ops, &listing.resets, &listing.pending, listing.bounds, &mut issues);
+    check_copies(&listing.ops, &listing.copies, &listing.resumed, listing.bounds, &mut issues);
+    let mut written: HashSet<(usize, usize)> = listing.ops.iter().map(|op| (op.row, op.col)).collect();
+    written.extend(&listing.resumed);
+    for &(line, what, source) in &listing.sends {
+        // Column 0 holds the received or loaded inputs
+        if let Source::Cell(r, c) = source {
+            if c > 0 && !written.contains(&(r, c)) {
+                issues.push(issue(line, LintKind::ReadBeforeWrite,
+                    format!("{} cell {}x{} is never written", what, r, c)));
+            }
+        }
+    }
+    check_slots(&listing.reads, listing.resumed.is_empty(), &mut issues);
+    issues.sort_by_key(|i| i.at);
+    issues
+}
+
+// Output buffer slots are filled once each and, in a whole program, numbered
+// 0.. without gaps; a part of a partitioned program may read only some
+fn check_slots(reads: &[(usize, usize)], whole: bool, issues: &mut Vec<LintIssue>) {
+    let mut filled: HashSet<usize> = HashSet::new();
+    for &(line, slot) in reads {
+        if whole && slot >= reads.len() {
+            issues.push(issue(line, LintKind::OutOfBounds,
+                format!("Output slot {} beyond the {} outputs read", slot, reads.len())));
+        } else if !filled.insert(slot) {
+            issues.push(issue(line, LintKind::DoubleWrite,
+                format!("Output slot {} is filled twice", slot)));
+        }
+    }
+}
+
+// A level takes a cycle per reset and per copy, then evaluates all its gates
+// in one cycle: each gate reads cells written before the level, and no two
+// write the same cell
+fn check_ops(ops: &[ParsedOp], resumed: &HashSet<(usize, usize)>, bounds: Option<(usize, usize)>, issues: &mut Vec<LintIssue>) {
+    let mut written = resumed.clone();
+    let mut evaluated: HashSet<(usize, usize)> = HashSet::new();
+    let mut current_level = None;
+
+    for op in ops {
+        if current_level != Some(op.level) {
+            // Cells written in a finished level become readable
+            written.extend(evaluated.drain());
+            current_level = Some(op.level);
+        }
+
+        if let Some((rows, cols)) = bounds {
+            if op.row >= rows || op.col >= cols {
+                issues.push(issue(op.line, LintKind::OutOfBounds,
+                    format!("Output cell {}x{} outside declared {}x{} crossbar", op.row, op.col, rows, cols)));
+            }
+            for operand in &op.operands {
+                if operand.col >= cols {
+                    issues.push(issue(op.line, LintKind::OutOfBounds,
+                        format!("Operand column {} outside declared {}x{} crossbar", operand.col, rows, cols)));
+                }
+                if let Source::Cell(r, c) = operand.source {
+                    if r >= rows || c >= cols {
+                        issues.push(issue(op.line, LintKind::OutOfBounds,
+                            format!("Source cell {}x{} outside declared {}x{} crossbar", r, c, rows, cols)));
+                    }
+                }
+            }
+        }
+
+        for operand in &op.operands {
+            // Primary inputs are loaded before execution starts
+            if let Source::Cell(r, c) = operand.source {
+                if !written.contains(&(r, c)) {
+                    issues.push(issue(op.line, LintKind::ReadBeforeWrite,
+                        format!("Source cell {}x{} is read before it is written", r, c)));
+                }
+            }
+        }
+
+        if !evaluated.insert((op.row, op.col)) {
+            issues.push(issue(op.line, LintKind::DoubleWrite,
+                format!("Cell {}x{} written twice in the evaluation cycle of level {}", op.row, op.col, op.level)));
+        }
+    }
+}
+
+// Programs listing RESETs must initialize every output cell in the level that
+// writes it, or resume with it pending; listings without any (as the C
+// implementation writes) are exempt. A RESET is one cycle and sets each of
+// its cells once
+fn check_resets(
+    ops: &[ParsedOp],
+    resets: &[ParsedReset],
+    pending: &HashSet<(usize, usize)>,
+    bounds: Option<(usize, usize)>,
+    issues: &mut Vec<LintIssue>
+) {
+    if resets.is_empty() && pending.is_empty() {
+        return;
+    }
+    let mut reset: HashSet<(i32, usize, usize)> = HashSet::new();
+    for r in resets {
+        let mut cycle = HashSet::new();
+        for &(row, col) in &r.cells {
+            if bounds.is_some_and(|(rows, cols)| row >= rows || col >= cols) {
+                issues.push(issue(r.line, LintKind::OutOfBounds,
+                    format!("Reset cell {}x{} outside the declared crossbar", row, col)));
+            }
+            if !cycle.insert((row, col)) {
+                issues.push(issue(r.line, LintKind::DoubleWrite,
+                    format!("Cell {}x{} reset twice in one cycle", row, col)));
+            }
+            reset.insert((r.level, row, col));
+        }
+    }
+    for op in ops {
+        if !reset.contains(&(op.level, op.row, op.col)) && !pending.contains(&(op.row, op.col)) {
+            issues.push(issue(op.line, LintKind::Uninitialized,
+                format!("Cell {}x{} is not reset before level {} writes it", op.row, op.col, op.level)));
+        }
+    }
+}
+
+// Parses "READ <cell> <slot> <signal>"; the signal name is only a label
+fn parse_read(rest: &str) -> std::result::Result<(Source, usize), String> {
+    let mut tokens = rest.split_whitespace();
+    let source = tokens.next().map_or(Err("READ lists no cell".to_string()), parse_source)?;
+    let slot = tokens.next().map_or(Err("READ lists no output slot".to_string()), parse_num)?;
+    Ok((source, slot))
+}
+
+// A copy reads a cell an earlier level wrote, or an input, for the gates of
+// its level. Nothing else in the level may write its cell before they read
+// it: neither a later copy nor a gate, as the gates evaluate after the copies
+fn check_copies(
+    ops: &[ParsedOp],
+    copies: &[ParsedCopy],
+    resumed: &HashSet<(usize, usize)>,
+    bounds: Option<(usize, usize)>,
+    issues: &mut Vec<LintIssue>
+) {
+    let mut first_write: HashMap<(usize, usize), i32> = HashMap::new();
+    for op in ops {
+        first_write.entry((op.row, op.col)).or_insert(op.level);
+    }
+    let evaluated: HashSet<(i32, usize, usize)> = ops.iter().map(|op| (op.level, op.row, op.col)).collect();
+    let mut copied: HashSet<(i32, usize, usize)> = HashSet::new();
+    for copy in copies {
+        let (row, col) = copy.cell;
+        if let Some((rows, cols)) = bounds {
+            if row >= rows || col >= cols {
+                issues.push(issue(copy.line, LintKind::OutOfBounds,
+                    format!("Copy cell {}x{} outside declared {}x{} crossbar", row, col, rows, cols)));
+            }
+        }
+        if let Source::Cell(r, c) = copy.source {
+            if !resumed.contains(&(r, c)) && first_write.get(&(r, c)).is_none_or(|&level| level >= copy.level) {
+                issues.push(issue(copy.line, LintKind::ReadBeforeWrite,
+                    format!("Copied cell {}x{} is read before it is written", r, c)));
+            }
+        }
+        if !copied.insert((copy.level, row, col)) {
+            issues.push(issue(copy.line, LintKind::Clobber,
+                format!("Copy into {}x{} overwrites an earlier copy of level {} before its gates read it", row, col, copy.level)));
+        } else if evaluated.contains(&(copy.level, row, col)) {
+            issues.push(issue(copy.line, LintKind::Clobber,
+                format!("Copy into {}x{} is overwritten by a gate of level {}", row, col, copy.level)));
+        }
+    }
+}
+
+// Parses "COPY <source> <cell>"
+fn parse_copy(rest: &str, line_no: usize, level: i32) -> std::result::Result<ParsedCopy, String> {
+    let mut tokens = rest.split_whitespace();
+    let source = tokens.next().map_or(Err("COPY lists no source".to_string()), parse_source)?;
+    let cell = match tokens.next().map_or(Err("COPY lists no cell".to_string()), parse_source)? {
+        Source::Cell(row, col) => (row, col),
+        Source::PrimaryInput(_) => return Err(format!("COPY writes input '{}' instead of a cell", rest.trim())),
+    };
+    Ok(ParsedCopy { line: line_no, level, source, cell })
+}
+
+// Parses "RESET <cell> <cell> ..."; one RESET drives a single row or column
+fn parse_reset(rest: &str, line_no: usize, level: i32) -> std::result::Result<ParsedReset, String> {
+    let mut cells = Vec::new();
+    for name in rest.split_whitespace() {
+        match parse_source(name)? {
+            Source::Cell(row, col) => cells.push((row, col)),
+            Source::PrimaryInput(_) => return Err(format!("Cannot reset primary input '{}'", name)),
+        }
+    }
+    check_reset_line(&cells)?;
+    Ok(ParsedReset { line: line_no, level, cells })
+}
+
+fn check_reset_line(cells: &[(usize, usize)]) -> std::result::Result<(), String> {
+    let Some(&(row, col)) = cells.first() else {
+        return Err("RESET lists no cells".to_string());
+    };
+    if !cells.iter().all(|c| c.0 == row) && !cells.iter().all(|c| c.1 == col) {
+        let names: Vec<String> = cells.iter().map(|(r, c)| format!("{}x{}", r, c)).collect();
+        return Err(format!("RESET cells '{}' share no row or column", names.join(" ")));
+    }
+    Ok(())
+}
+
+// Parses "<row> False <col1> <name1> <col2><name2> <col> True"; the second
+// operand's column and name are printed without a separator, so the split is
+// recovered from the fixed column layout. Wider gates list further
+// "<col> <name>" pairs before the gate's column
+pub(crate) fn parse_op(line: &str, line_no: usize, level: i32) -> std::result::Result<ParsedOp, String> {
+    let tokens = tokens_with_offsets(line);
+    if tokens.len() < 4 || tokens[1].1 != "False" || tokens[tokens.len() - 1].1 != "True" {
+        return Err(format!("Unrecognized micro-op '{}'", line.trim()));
+    }
+
+    let row = parse_num(tokens[0].1)?;
+    let col = parse_num(tokens[tokens.len() - 2].1)?;
+    let middle = &tokens[2..tokens.len() - 2];
+
+    let mut operands = Vec::new();
+    match middle.len() {
+        0 => {}
+        // Older versions separated the second operand's column and name
+        4 => {
+            for pair in middle.chunks(2) {
+                operands.push(Operand { col: parse_num(pair[0].1)?, source: parse_source(pair[1].1)? });
+            }
+        }
+        n if n == 2 || (n > 2 && n % 2 == 1) => {
+            let (_, col1) = middle[0];
+            let (name1_at, name1) = middle[1];
+            operands.push(Operand { col: parse_num(col1)?, source: parse_source(name1)? });
+
+            if let Some(&(joined_at, joined)) = middle.get(2) {
+                let name2_at = name1_at + name1.len().max(9) + 1 + 4;
+                let split = name2_at.checked_sub(joined_at)
+                    .filter(|&k| k > 0 && k < joined.len())
+                    .or_else(|| joined.find('/'))
+                    .ok_or_else(|| format!("Cannot split second operand '{}'", joined))?;
+                let (col2, name2) = joined.split_at(split);
+                operands.push(Operand { col: parse_num(col2)?, source: parse_source(name2)? });
+            }
+            for pair in middle.get(3..).unwrap_or_default().chunks(2) {
+                operands.push(Operand { col: parse_num(pair[0].1)?, source: parse_source(pair[1].1)? });
+            }
+        }
+        _ => return Err(format!("Unexpected operand list in '{}'", line.trim())),
+    }
+
+    Ok(ParsedOp { line: line_no, level, row, col, operands })
+}
+
+fn tokens_with_offsets(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, ch) in line.char_indices() {
+        match (ch.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                tokens.push((s, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, &line[s..]));
+    }
+    tokens
+}
+
+fn parse_num(token: &str) -> std::result::Result<usize, String> {
+    token.trim().parse().map_err(|_| format!("Expected a number, found '{}'", token))
+}
+
+fn parse_source(name: &str) -> std::result::Result<Source, String> {
+    if let Some(pi) = name.strip_prefix('/') {
+        return Ok(Source::PrimaryInput(parse_num(pi)?));
+    }
+    match name.split_once('x') {
+        Some((r, c)) => Ok(Source::Cell(parse_num(r)?, parse_num(c)?)),
+        None => Err(format!("Unrecognized operand name '{}'", name)),
+    }
+}
+
+fn parse_size(size: &str) -> Option<(usize, usize)> {
+    let (rows, cols) = size.split_once('x')?;
+    Some((rows.trim().parse().ok()?, cols.trim().parse().ok()?))
+}
+
+// Issues are placed on lines; programs move them to their ops
+fn issue(line: usize, kind: LintKind, message: String) -> LintIssue {
+    LintIssue { at: Place::Line(line), kind, message }
+}
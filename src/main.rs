@@ -1,11 +1,43 @@
-use clap::{Parser, Subcommand};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::path::{Path, PathBuf};
-use anyhow::{Result, Context};
-use std::fs;
+use anyhow::{Result, Context, bail, ensure};
+use std::{env, fs};
+use std::net::TcpListener;
 use std::time::Instant;
 use log::{info, warn, error};
 
-use delphi::{Circuit, parser, scheduler, mapper, generator};
+use delphi::{Circuit, Primitive, MAX_GATES, analysis, archive, parser, resynth, scheduler, mapper, generator, lint, simulator, signal_name};
+use delphi::legacy::{self, Artifact};
+use delphi::mapper::{ColumnSpans, MappedCrossbar, MappingReport, Placement, RegionUsage, RowPolicy};
+use delphi::output::{self, ArtifactPaths, ClobberPolicy, OutputLayout};
+use delphi::analysis::{ActivationLimits, Hotspots};
+use delphi::batch::BatchLayout;
+use delphi::chain::{self, ChainCycles};
+use delphi::compare::{Comparison, Thresholds};
+use delphi::cost::CostExpr;
+use delphi::replicate::Replication;
+use delphi::diagnostics::{Diagnostics, Severity};
+use delphi::initstate::InitState;
+use delphi::endurance::Rotation;
+use delphi::fingerprint::DuplicateIndex;
+use delphi::monitor::{Monitor, Monitored};
+use delphi::metrics::{self as run_metrics, ProgramMetrics, RunMetrics};
+use delphi::passes::{ActiveLowInputs, Bind, Cluster, Commute, Decompose, DontCare, DontCareSet, Duplicate, FanoutProfile, FanoutTrees, InvertedReadout, MinCut, PassManager, PassName, PassReport};
+use delphi::overlay::{CellDiff, Layer, Overlay};
+use delphi::ports::{self, PortGroups};
+use delphi::program::{self, Encoding, MicroOp, Program, ProgramCycles, ProgramFormat, HIGH_COPY_OVERHEAD};
+use delphi::provenance::{self, ArtifactHeader, ProvenanceReport};
+use delphi::preset::{Preset, Settings};
+use delphi::presynth::Presynth;
+use delphi::selftest::SelfTest;
+use delphi::serve;
+use delphi::repro::{Bundle, Role};
+use delphi::retime::Retiming;
+use delphi::suite::{Manifest, ResultRecord, ResultStream, RunSummary, Status, Suite, SuiteRow};
+use delphi::generator::TechEstimate;
+use delphi::tech::{Technology, WireModel, DEFAULT_DEVICE_FANIN};
+use delphi::tiling::TiledPipeline;
 
 #[derive(Parser)]
 #[command(
@@ -23,6 +55,19 @@ use delphi::{Circuit, parser, scheduler, mapper, generator};
 A high-performance memristor-based logic synthesis toolchain."
 )]
 struct Cli {
+    /// Check mapper and generator invariants even in release builds
+    #[arg(long, global = true)]
+    paranoid: bool,
+
+    /// Worker threads for the parallel phases (default: one per core)
+    #[arg(short = 'j', long, global = true, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Number of the first gate level in every artifact and message: 1, or 0 to
+    /// count levels from zero. The input loads come just before it
+    #[arg(long, global = true, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(i32).range(0..=1))]
+    level_base: i32,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -35,13 +80,11 @@ enum Commands {
         #[arg(value_name = "NETLIST")]
         netlist: PathBuf,
 
-        /// Output directory for results (default: ./Results)
-        #[arg(short, long, value_name = "DIR")]
-        output: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
 
-        /// Enable parallel processing (default: enabled for circuits >= 100 gates)
-        #[arg(long)]
-        parallel: bool,
+        #[command(flatten)]
+        pipeline: PipelineArgs,
     },
     /// Process all netlists in a benchmark directory
     Bench {
@@ -49,17 +92,268 @@ enum Commands {
         #[arg(value_name = "DIR")]
         bench_dir: PathBuf,
 
-        /// Output directory for results (default: ./Results)
-        #[arg(short, long, value_name = "DIR")]
-        output: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
 
         /// Only process files matching this pattern
         #[arg(short, long, value_name = "PATTERN")]
         pattern: Option<String>,
 
-        /// Enable parallel processing (default: enabled for circuits >= 100 gates)
+        /// Run a standard suite from its built-in manifest and write an aggregated summary
+        #[arg(long, value_enum)]
+        suite: Option<Suite>,
+
+        /// Suite manifest (TOML) to use instead of the built-in one
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<PathBuf>,
+
+        /// Skip circuits structurally identical to one processed before in the run,
+        /// instead of only warning about them
+        #[arg(long)]
+        skip_duplicates: bool,
+
+        #[command(flatten)]
+        monitor: MonitorArgs,
+
+        #[command(flatten)]
+        pipeline: PipelineArgs,
+    },
+    /// Process netlists run one after another as one circuit: input xk of each reads
+    /// output k of the one before, left in place in the crossbar instead of read and reloaded
+    Chain {
+        /// Paths to the netlist files, in the order they run
+        #[arg(value_name = "NETLIST", num_args = 2.., required = true)]
+        netlists: Vec<PathBuf>,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        #[command(flatten)]
+        pipeline: PipelineArgs,
+    },
+    /// Move the registers of an AIGER or BLIF netlist with latches to balance
+    /// the combinational clouds between them, and report each cloud's depth
+    /// and program cycles before and after
+    Retime {
+        /// Path to the netlist file
+        #[arg(value_name = "NETLIST")]
+        netlist: PathBuf,
+    },
+    /// Simulate a netlist on exhaustive, random, or user-supplied input vectors
+    Simulate {
+        /// Path to the netlist file
+        #[arg(value_name = "NETLIST")]
+        netlist: PathBuf,
+
+        /// File with one input bit string per line (x0 first)
+        #[arg(long, value_name = "FILE", conflicts_with = "random")]
+        vectors: Option<PathBuf>,
+
+        /// Number of random vectors (default: exhaustive for <= 20 inputs)
+        #[arg(long, value_name = "N")]
+        random: Option<usize>,
+
+        /// Seed for random vector generation
+        #[arg(long, default_value = "1")]
+        seed: u64,
+
+        /// Also map the circuit and check both mappings against the netlist
+        #[arg(long)]
+        check_mappings: bool,
+
+        /// Estimate per-signal switching activity from N passes of 64 random vectors
+        #[arg(long, value_name = "PASSES")]
+        activity: Option<usize>,
+
+        /// Gate family the mappings are built from (with --check-mappings)
+        #[arg(long, value_enum, default_value_t = Primitive::Nor, requires = "check_mappings")]
+        primitive: Primitive,
+
+        /// BLIF model or Verilog module to simulate in files that hold several
+        #[arg(long, value_name = "NAME", conflicts_with = "activity")]
+        top: Option<String>,
+    },
+    /// Monte-Carlo timing yield of a mapped circuit under device variation
+    AnalyzeVariability {
+        /// Path to the netlist file
+        #[arg(value_name = "NETLIST")]
+        netlist: PathBuf,
+
+        /// Technology file with a [variation] section for the chosen profile
+        #[arg(long, value_name = "FILE")]
+        tech: PathBuf,
+
+        /// Profile to use from the technology file
+        #[arg(long, value_name = "NAME")]
+        tech_profile: Option<String>,
+
+        /// Number of Monte-Carlo trials
+        #[arg(long, default_value = "1000")]
+        trials: usize,
+
+        /// Seed for delay sampling
+        #[arg(long, default_value = "1")]
+        seed: u64,
+
+        /// Repeat the analysis with N consecutive seeds, report the best, median and
+        /// worst yield, and print the best run's levels
+        #[arg(long, value_name = "N", default_value = "1")]
+        runs: usize,
+
+        /// Analyze the naive mapping instead of the compact one
         #[arg(long)]
-        parallel: bool,
+        naive: bool,
+    },
+    /// Report cycles that activate too many devices on one wordline or bitline
+    CheckActivation {
+        /// Path to the netlist file
+        #[arg(value_name = "NETLIST")]
+        netlist: PathBuf,
+
+        #[command(flatten)]
+        limits: ActivationArgs,
+
+        /// Check the naive mapping instead of the compact one
+        #[arg(long)]
+        naive: bool,
+
+        /// Split overloaded levels first and report what remains
+        #[arg(long)]
+        split: bool,
+    },
+    /// Check micro-op programs for hazards and malformed operations
+    Lint {
+        /// Micro-op programs to check: text listings, or JSON and binary programs written with --emit
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Overlay the cells of two programs written with --emit on one SVG canvas, e.g.
+    /// the compact mappings of two runs, colored by how each cell differs
+    Overlay {
+        /// The first program (.json or .bin)
+        #[arg(value_name = "FIRST")]
+        first: PathBuf,
+
+        /// The second program (.json or .bin)
+        #[arg(value_name = "SECOND")]
+        second: PathBuf,
+
+        /// Image to write
+        #[arg(short, long, value_name = "FILE", default_value = "overlay.svg")]
+        output: PathBuf,
+
+        /// Names of the two programs in the image (default: their file names)
+        #[arg(long, value_name = "FIRST,SECOND", value_delimiter = ',')]
+        labels: Vec<String>,
+    },
+    /// Animate a program written with --emit as an SVG, one frame per cycle showing
+    /// the cells it initializes, evaluates and reads on one input vector
+    Animate {
+        /// The program (.json or .bin)
+        #[arg(value_name = "PROGRAM")]
+        program: PathBuf,
+
+        /// Input bits, x0 first (default: all 0)
+        #[arg(long, value_name = "BITS")]
+        vector: Option<String>,
+
+        /// Image to write
+        #[arg(short, long, value_name = "FILE", default_value = "animation.svg")]
+        output: PathBuf,
+
+        /// How long each frame shows
+        #[arg(long, value_name = "MS", default_value = "500")]
+        frame_ms: u64,
+    },
+    /// Compare a stats file or micro-op listing with a reference, such as a result the C
+    /// implementation published, and list where they differ
+    Crossval {
+        /// The reference stats file or listing
+        #[arg(value_name = "REFERENCE")]
+        reference: PathBuf,
+
+        /// The stats file or listing to check against it
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+    /// Check that the parallel paths build the same schedule, compact mapping and programs
+    /// as the sequential ones, and that every program computes what the netlist does
+    Selftest {
+        /// Path to the netlist file
+        #[arg(value_name = "NETLIST")]
+        netlist: PathBuf,
+
+        /// Sizes of the thread pools the parallel paths run on
+        #[arg(long, value_name = "N,...", value_delimiter = ',', default_values_t = [1, 2, 4, 8],
+            value_parser = clap::value_parser!(u64).range(1..))]
+        threads: Vec<u64>,
+
+        /// Random input vectors to simulate through each program
+        #[arg(long, value_name = "N", default_value_t = 64)]
+        vectors: usize,
+
+        /// Seed for random vector generation
+        #[arg(long, default_value = "1")]
+        seed: u64,
+
+        /// BLIF model or Verilog module to check in files that hold several
+        #[arg(long, value_name = "NAME")]
+        top: Option<String>,
+    },
+    /// Compare two bench runs circuit by circuit and flag the circuits that regressed
+    CompareRuns {
+        /// The old run's suite summary JSON or results stream
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// The new run's suite summary JSON or results stream
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+
+        /// Growth in logic levels allowed, in percent
+        #[arg(long, value_name = "PCT", default_value_t = Thresholds::default().levels)]
+        levels: f64,
+
+        /// Growth in compact crossbar area (rows times columns) allowed, in percent
+        #[arg(long, value_name = "PCT", default_value_t = Thresholds::default().area)]
+        area: f64,
+
+        /// Growth in compact cycles allowed, in percent
+        #[arg(long, value_name = "PCT", default_value_t = Thresholds::default().cycles)]
+        cycles: f64,
+
+        /// Growth in total runtime allowed, in percent; changes under 50ms are ignored
+        #[arg(long, value_name = "PCT", default_value_t = Thresholds::default().runtime)]
+        runtime: f64,
+    },
+    /// Run an HTTP/JSON service that processes submitted netlists and serves their
+    /// artifacts and metrics
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Directory holding the jobs (default: Results/serve)
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        monitor: MonitorArgs,
+    },
+    /// Package a run into one tarball with the netlist, the files its options read, every
+    /// artifact and a manifest, so that it can be reproduced exactly
+    Repro {
+        /// Path to the netlist file
+        #[arg(value_name = "NETLIST")]
+        netlist: PathBuf,
+
+        /// Bundle to write (default: <benchmark>_repro.tar.gz)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Options for the `process` run, after `--` (e.g. -- --tech tech/profiles.toml)
+        #[arg(last = true, value_name = "PROCESS_OPTIONS")]
+        options: Vec<String>,
     },
     /// Run performance comparison between sequential and parallel implementations
     Benchmark {
@@ -73,9 +367,431 @@ enum Commands {
     },
 }
 
+#[derive(Args)]
+struct PipelineArgs {
+    /// Bundle of the scheduling, mapping and pass options below, from the quickest run
+    /// to the smallest program; options given here as well override the preset's
+    #[arg(long, value_enum, value_name = "PRESET")]
+    preset: Option<Preset>,
+
+    /// Enable parallel processing (default: enabled for circuits >= 100 gates)
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, default_value_t = false, default_missing_value = "true", require_equals = true, value_name = "BOOL")]
+    parallel: bool,
+
+    /// Syntax-check the generated Verilog with iverilog or yosys if found on PATH
+    #[arg(long)]
+    check_verilog: bool,
+
+    /// Write compact micro-ops level by level without building the compact crossbar in memory
+    #[arg(long)]
+    stream: bool,
+
+    /// Technology file (TOML) with device timing, energy and periphery limits
+    #[arg(long, value_name = "FILE")]
+    tech: Option<PathBuf>,
+
+    /// Profile to use from the technology file (default: its `default` or only profile)
+    #[arg(long, value_name = "NAME", requires = "tech")]
+    tech_profile: Option<String>,
+
+    /// Wire annotation file mapping the distance between a gate's operand and output
+    /// cells to RC delay, added to the execution time of the programs
+    #[arg(long, value_name = "FILE", requires = "tech")]
+    parasitics: Option<PathBuf>,
+
+    /// Process only the transitive fanin cone of these signals (comma-separated, e.g.
+    /// n5,n12 or 5,12), extracted before any pass
+    #[arg(long, value_name = "SIGNALS", value_delimiter = ',')]
+    cone: Vec<String>,
+
+    /// Process only the BLIF model or Verilog module NAME; by default every circuit of
+    /// a file is processed, with artifacts named <file>_<module>
+    #[arg(long, value_name = "NAME")]
+    top: Option<String>,
+
+    /// Shell command run on each input before parsing, to synthesize RTL into a
+    /// gate-level netlist; {input} is the input file and {output} the netlist to write, e.g.
+    /// "yosys -q -p 'read_verilog {input}; synth -auto-top; abc -g NOR; write_blif {output}'"
+    #[arg(long, value_name = "COMMAND")]
+    presynth: Option<String>,
+
+    /// Format of the netlist the --presynth command writes
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = parser::Format::Blif, requires = "presynth")]
+    presynth_format: parser::Format,
+
+    /// Optimization pass to run on the netlist before scheduling (repeatable, runs in order)
+    #[arg(long = "pass", value_enum, value_name = "PASS")]
+    passes: Vec<PassName>,
+
+    /// Split signals read by more than K gates into balanced trees of NOTs with at most K
+    /// readers per node, after the passes and before scheduling; the stats compare the two
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u64).range(2..))]
+    max_fanout: Option<u64>,
+
+    /// Keep the netlist's operand order instead of reordering two-input gates to save
+    /// compact-mapping copies after scheduling
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, default_value_t = false, default_missing_value = "true", require_equals = true, value_name = "BOOL")]
+    keep_operand_order: bool,
+
+    /// Gather reconvergent cones of at most SIZE gates (e.g. 4) into one run of a row
+    /// of the compact mapping, after the operand order is chosen
+    #[arg(long, value_name = "SIZE", value_parser = clap::value_parser!(u64).range(2..=64))]
+    cluster: Option<u64>,
+
+    /// Recompute high-fanout gates in a second row where that saves more copies of
+    /// their value than the duplicate costs, after the operand order is chosen
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, default_value_t = false, default_missing_value = "true", require_equals = true, value_name = "BOOL")]
+    duplicate: bool,
+
+    /// How the compact mapper picks each gate's row: its last operand's, or a min-cut
+    /// partition of the gate graph that copies fewer operands, after the passes above
+    #[arg(long = "placement", value_enum, value_name = "POLICY", default_value_t = RowPolicy::LastOperand)]
+    row_policy: RowPolicy,
+
+    /// Keep every gate of the compact mapping within N columns of its operands, copying
+    /// operands that would sit further away next to it; the run fails if a gate still does not fit
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_span: Option<u64>,
+
+    // Refinement passes of min-cut placement, which only presets change
+    #[arg(skip = MinCut::default().passes)]
+    mincut_passes: usize,
+
+    /// File of input combinations that never occur (one cube per line, x0 first,
+    /// '-' for free inputs); the netlist is simplified under them before other passes
+    #[arg(long, value_name = "FILE")]
+    dont_care: Option<PathBuf>,
+
+    /// File of word-level input ports, one per line (e.g. a[7:0] or b[7:0] = x8..x15);
+    /// the mappers put each port's bits on adjacent lines and the artifact headers list them
+    #[arg(long, value_name = "FILE")]
+    input_groups: Option<PathBuf>,
+
+    /// File of compact crossbar cells that hold a value before the program runs, one
+    /// `<row>x<col> <value>` per line with the value 0, 1 or a signal (e.g. 3x0 x3, 2x4 n12);
+    /// the compact mapping reuses held signals and skips the RESET of cells already at 1
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["pipeline_tiles", "batch", "replicate", "rotations"])]
+    init_state: Option<PathBuf>,
+
+    /// Keep primary inputs no gate reads on crossbar lines of their own; by default
+    /// they are reported and left out of the mappings
+    #[arg(long)]
+    keep_unused_inputs: bool,
+
+    /// Fix primary inputs at constants (comma-separated, e.g. x3=1,x7=0) and propagate
+    /// them before other passes; adds a partial evaluation section to the stats
+    #[arg(long, value_name = "xN=V", value_delimiter = ',')]
+    bind: Vec<String>,
+
+    /// Gate family to map onto; nand re-synthesizes the netlist into NAND/NOT form
+    /// and adds a comparison with the NOR flow to the stats
+    #[arg(long, value_enum, default_value_t = Primitive::Nor)]
+    primitive: Primitive,
+
+    /// Drop the NOT gates that only invert an output and read the signal they invert
+    /// at read-out instead, recording each output's polarity in the programs and Verilog
+    #[arg(long, conflicts_with = "pipeline_tiles")]
+    inverted_readout: bool,
+
+    /// How the programs hold primary inputs; active-low loads inputs read mostly
+    /// through inverters as their complement and drops those inverters
+    #[arg(long, value_enum, default_value_t = Encoding::ActiveHigh, conflicts_with = "pipeline_tiles")]
+    encoding: Encoding,
+
+    /// Delay readers of high-fanout signals within their slack so that the compact
+    /// mapping copies no signal more than N times in one level; adds a copy pressure
+    /// section to the stats
+    #[arg(long, value_name = "N")]
+    copy_budget: Option<usize>,
+
+    /// Let gates read an earlier copy of their operand in the same row instead of
+    /// copying it again; adds a copy reuse section to the stats
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, default_value_t = false, default_missing_value = "true", require_equals = true, value_name = "BOOL", conflicts_with = "stream")]
+    reuse_copies: bool,
+
+    /// Fail the run when the copies of the compact mapping add more than PERCENT
+    /// to the cycles of its program
+    #[arg(long, value_name = "PERCENT")]
+    max_copy_overhead: Option<f64>,
+
+    /// Count each copy of the compact program as the read and write the crossbar
+    /// spends on it, in its reads, writes and total cycles, instead of apart
+    #[arg(long)]
+    fold_copies: bool,
+
+    /// List the N deepest paths, most read signals, ASAP levels with the most gates
+    /// and compact rows with the most copies in a hotspots section of the stats
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "stream")]
+    hotspots: Option<u64>,
+
+    /// Annotate the naive and compact listings with a legend and a comment per
+    /// micro-op naming the signals it writes and reads and its gate; without it the
+    /// listings keep the C implementation's layout
+    #[arg(long)]
+    annotate: bool,
+
+    /// Write the stats, Verilog and micro-op listings byte for byte as the C
+    /// implementation did: no provenance headers, no sections or footer fields it
+    /// lacked, no RESET lines or read phase, and the last level left out of the listings
+    #[arg(long, conflicts_with = "annotate")]
+    legacy_compat: bool,
+
+    /// Also write the naive and compact programs in these formats, next to
+    /// their text listings
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "FORMAT")]
+    emit: Vec<ProgramFormat>,
+
+    /// Also split the compact program into parts of at most N micro-ops for controllers
+    /// with a small instruction memory, each resuming from the cells the last one left
+    #[arg(long, value_name = "N")]
+    max_ops: Option<usize>,
+
+    /// Also write the naive and compact programs' crossbar occupancy per cycle as CSV,
+    /// next to their text listings
+    #[arg(long)]
+    timeline: bool,
+
+    /// Also draw the naive and compact mappings on one SVG canvas, each cell colored
+    /// by whether both mappings write the same signal into it, next to the stats
+    #[arg(long)]
+    overlay: bool,
+
+    /// Also write the circuit as it is scheduled and mapped, after line expansion and
+    /// the passes, as a native netlist with one gate per line, next to the stats
+    #[arg(long)]
+    decomposed: bool,
+
+    /// Also split the schedule into N tiles of consecutive levels, each on its own
+    /// crossbar, and write per-tile programs that run them as a pipeline
+    #[arg(long, value_name = "N")]
+    pipeline_tiles: Option<usize>,
+
+    /// Also plan where the inputs of B vectors go to evaluate them together in
+    /// replicated rows, and estimate the per-vector throughput
+    #[arg(long, value_name = "B")]
+    batch: Option<usize>,
+
+    /// Also plan N bit-sliced copies of each mapping in disjoint column groups,
+    /// driven by the same micro-ops, and write an N-bit wrapper of the MAGIC module
+    #[arg(long, value_name = "N")]
+    replicate: Option<usize>,
+
+    /// Also write K compact placements shifted across the array and a schedule that
+    /// cycles through them, spreading device writes for long-running workloads
+    #[arg(long, value_name = "K")]
+    rotations: Option<usize>,
+
+    /// Also write the warnings and notes of the run as JSON, next to the stats
+    #[arg(long)]
+    diagnostics_json: bool,
+
+    /// Also write the run's measurements (phase times, sizes, cycles and energy of
+    /// both programs) as JSON, next to the stats
+    #[arg(long)]
+    metrics_json: bool,
+
+    /// Cost of each program as an expression over its metrics, e.g.
+    /// 'rows*2 + copies*0.5 + levels', reported in the stats and metrics
+    #[arg(long, value_name = "EXPR")]
+    cost: Option<CostExpr>,
+
+    /// Also write the ASAP, ALAP and list level of every gate as JSON, next to the stats
+    #[arg(long)]
+    schedule_json: bool,
+
+    /// Also write every cell of the naive and compact mappings as JSON, next to the stats
+    #[arg(long)]
+    mapping_json: bool,
+
+    /// Also write the netlist line behind every gate and mapped cell as JSON, next to
+    /// the stats
+    #[arg(long)]
+    provenance_json: bool,
+
+    /// Split ASAP levels so that no cycle exceeds these activation limits
+    #[command(flatten)]
+    activation: ActivationArgs,
+}
+
+#[derive(Args)]
+struct ActivationArgs {
+    /// Devices one wordline may activate per cycle (sneak current)
+    #[arg(long, value_name = "N")]
+    wordline_limit: Option<usize>,
+
+    /// Devices one bitline may activate per cycle (driver strength)
+    #[arg(long, value_name = "N")]
+    bitline_limit: Option<usize>,
+}
+
+impl ActivationArgs {
+    fn limits(&self) -> ActivationLimits {
+        ActivationLimits { wordline: self.wordline_limit, bitline: self.bitline_limit }
+    }
+}
+
+impl PipelineArgs {
+    // Takes the preset's choice of every option the command line left unset
+    fn apply_preset(&mut self, matches: &ArgMatches) {
+        let Some(preset) = self.preset else { return };
+        let settings = preset.settings();
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        if unset("passes") {
+            self.passes = settings.passes;
+        }
+        if unset("keep_operand_order") {
+            self.keep_operand_order = !settings.commute;
+        }
+        if unset("duplicate") {
+            self.duplicate = settings.duplicate;
+        }
+        if unset("cluster") {
+            self.cluster = settings.cluster.map(|size| size as u64);
+        }
+        if unset("row_policy") {
+            self.row_policy = settings.placement;
+        }
+        // Streamed programs have no mapping to reuse copies in
+        if unset("reuse_copies") && !self.stream {
+            self.reuse_copies = settings.reuse_copies;
+        }
+        if unset("parallel") {
+            self.parallel = settings.parallel;
+        }
+        self.mincut_passes = settings.mincut_passes;
+    }
+
+    // The options a preset chooses, as this run has them
+    fn settings(&self) -> Settings {
+        Settings {
+            passes: self.passes.clone(),
+            commute: !self.keep_operand_order,
+            duplicate: self.duplicate,
+            cluster: self.cluster.map(|size| size as usize),
+            placement: self.row_policy,
+            mincut_passes: self.mincut_passes,
+            reuse_copies: self.reuse_copies,
+            parallel: self.parallel,
+        }
+    }
+
+    fn technology(&self) -> Result<Option<Technology>> {
+        let Some(path) = &self.tech else { return Ok(None) };
+        let mut tech = Technology::load(path, self.tech_profile.as_deref())?;
+        if let Some(parasitics) = &self.parasitics {
+            tech.wires = Some(WireModel::load(parasitics)?);
+        }
+        Ok(Some(tech))
+    }
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Output directory for results (default: ./Results)
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<PathBuf>,
+
+    /// How artifacts are arranged under the output directory
+    #[arg(long, value_enum, default_value_t = OutputLayout::ByKind)]
+    layout: OutputLayout,
+
+    /// Overwrite existing artifacts (default)
+    #[arg(long, conflicts_with = "no_clobber")]
+    overwrite: bool,
+
+    /// Skip circuits whose artifacts already exist
+    #[arg(long)]
+    no_clobber: bool,
+
+    /// Also pack each circuit's artifacts and a manifest.json into one zip
+    /// archive; {bench} in FILE is replaced by the circuit's name
+    #[arg(long, value_name = "FILE")]
+    bundle: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    fn output_dir(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| PathBuf::from("Results"))
+    }
+
+    fn clobber_policy(&self) -> ClobberPolicy {
+        if self.no_clobber {
+            ClobberPolicy::NoClobber
+        } else {
+            ClobberPolicy::Overwrite
+        }
+    }
+
+    // The archive of one circuit's artifacts, if one was asked for
+    fn bundle_path(&self, bench_name: &str) -> Option<PathBuf> {
+        self.bundle.as_ref().map(|path| PathBuf::from(path.to_string_lossy().replace("{bench}", bench_name)))
+    }
+
+    // Runs of several circuits would write every archive over the last
+    fn ensure_bundle_per_circuit(&self) -> Result<()> {
+        ensure!(self.bundle.as_ref().is_none_or(|path| path.to_string_lossy().contains("{bench}")),
+            "--bundle names one archive for several circuits; put {{bench}} in its name");
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct MonitorArgs {
+    /// Keep the campaign's counters in this Prometheus textfile, rewritten after
+    /// every circuit (for node_exporter's textfile collector)
+    #[arg(long, value_name = "FILE")]
+    metrics_textfile: Option<PathBuf>,
+
+    /// Push the campaign's counters to this Prometheus push gateway after every
+    /// circuit, e.g. http://localhost:9091
+    #[arg(long, value_name = "URL")]
+    metrics_push: Option<String>,
+}
+
+impl MonitorArgs {
+    // Emits the counters at zero, so that a textfile or gateway that does not
+    // work is found before the first circuit
+    fn start(&self, campaign: &str) -> Result<Monitor> {
+        let monitor = Monitor::new(campaign, self.metrics_textfile.clone(), self.metrics_push.as_deref())?;
+        monitor.emit()?;
+        Ok(monitor)
+    }
+}
+
+enum ProcessOutcome {
+    // The circuit as scheduled and mapped, and what the run measured
+    Written(Box<Circuit>, Box<RunMetrics>),
+    Skipped,
+}
+
+// Parses a command line as clap does, then applies the --preset of a command
+// that takes pipeline options
+fn parse_cli<I, T>(args: I) -> Result<Cli, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let matches = Cli::command().try_get_matches_from(args)?;
+    let mut cli = Cli::from_arg_matches(&matches)?;
+    if let Some((_, matches)) = matches.subcommand() {
+        if let Commands::Process { pipeline, .. } | Commands::Bench { pipeline, .. } | Commands::Chain { pipeline, .. } = &mut cli.command {
+            pipeline.apply_preset(matches);
+        }
+    }
+    Ok(cli)
+}
+
 fn main() -> Result<()> {
     env_logger::init();
-    let cli = Cli::parse();
+    let cli = parse_cli(env::args_os()).unwrap_or_else(|e| e.exit());
+    if cli.paranoid {
+        delphi::verify::set_paranoid(true);
+    }
+    output::set_level_base(cli.level_base);
+    if let Some(jobs) = cli.jobs {
+        ensure!(jobs > 0, "--jobs needs at least one thread");
+        delphi::parallel::set_jobs(jobs)?;
+    }
 
     // Show banner only for actual runs (not help/version)
     if !std::env::args().any(|arg| arg == "-h" || arg == "--help" || arg == "-V" || arg == "--version") {
@@ -90,18 +806,44 @@ Delphi v{} - Memristor Logic Synthesis Toolchain\n", env!("CARGO_PKG_VERSION"));
     }
 
     match &cli.command {
-        Commands::Process { netlist, output, parallel } => {
-            let output_dir = output.clone().unwrap_or_else(|| PathBuf::from("Results"));
-            process_netlist(netlist, &output_dir, *parallel)?;
+        Commands::Process { netlist, output, pipeline } => {
+            process_netlist(netlist, output, pipeline, None)?;
+        },
+        Commands::Chain { netlists, output, pipeline } => {
+            process_chain(netlists, output, pipeline)?;
+        },
+        Commands::Retime { netlist } => {
+            let sequential = parser::load_sequential(netlist).context("Failed to parse netlist")?;
+            let retiming = Retiming::new(&sequential)?;
+            println!("[RETIME] {} ({} latches)", retiming, sequential.latches);
+            for (when, clouds) in [("before", &retiming.before), ("after", &retiming.after)] {
+                for (k, cloud) in clouds.iter().enumerate() {
+                    println!("[RETIME] {} cloud {}: {}", when, k, cloud);
+                }
+            }
         },
-        Commands::Bench { bench_dir, output, pattern, parallel } => {
-            let output_dir = output.clone().unwrap_or_else(|| PathBuf::from("Results"));
+        Commands::Bench { bench_dir, output, pattern, pipeline, suite, manifest, monitor, skip_duplicates } => {
             if !bench_dir.exists() || !bench_dir.is_dir() {
                 error!("Benchmark directory doesn't exist or is not a directory: {:?}", bench_dir);
                 return Err(anyhow::anyhow!("Invalid benchmark directory"));
             }
+            let manifest = match (manifest, suite) {
+                (Some(path), _) => Some(Manifest::load(path)?),
+                (None, Some(suite)) => Some(suite.manifest()),
+                (None, None) => None,
+            };
+            output.ensure_bundle_per_circuit()?;
+            if let Some(manifest) = manifest {
+                let monitor = monitor.start(&manifest.name)?;
+                return run_suite(bench_dir, &manifest, pattern.as_deref(), output, pipeline, &monitor, *skip_duplicates);
+            }
+            let campaign = fs::canonicalize(bench_dir)?.file_name().map_or("bench".to_string(), |n| n.to_string_lossy().into_owned());
+            let monitor = monitor.start(&campaign)?;
             let entries = fs::read_dir(bench_dir)?;
+            let mut duplicates = DuplicateIndex::new(*skip_duplicates);
+            let mut results = ResultStream::open(&output.output_dir())?;
             let mut processed = 0;
+            let mut skipped = 0;
             let mut failed = 0;
             for entry in entries {
                 let entry = entry?;
@@ -113,19 +855,154 @@ Delphi v{} - Memristor Logic Synthesis Toolchain\n", env!("CARGO_PKG_VERSION"));
                             continue;
                         }
                     }
-                    if file_name.to_lowercase().ends_with(".txt") || file_name.to_lowercase().ends_with(".v") {
+                    // A synthesizer reads RTL of any extension
+                    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                    if pipeline.presynth.is_some() || parser::Format::is_netlist_extension(extension) {
                         info!("Processing: {}", file_name);
-                        match process_netlist(&path, &output_dir, *parallel) {
-                            Ok(_) => processed += 1,
+                        let started = Instant::now();
+                        let stem = path.file_stem().and_then(|n| n.to_str()).unwrap_or(file_name).to_string();
+                        match process_netlist(&path, output, pipeline, Some(&mut duplicates)) {
+                            Ok(outcomes) => for outcome in outcomes {
+                                let (circuit, status) = match outcome {
+                                    ProcessOutcome::Written(circuit, metrics) => {
+                                        processed += 1;
+                                        (circuit.bench_name, Status::Done(metrics))
+                                    }
+                                    ProcessOutcome::Skipped => {
+                                        skipped += 1;
+                                        (stem.clone(), Status::Skipped)
+                                    }
+                                };
+                                let duration = match &status {
+                                    Status::Done(metrics) => metrics.times.total_ms,
+                                    _ => run_metrics::millis(started.elapsed()),
+                                };
+                                results.record(&ResultRecord::new(&circuit, Some(&path), &status, duration))?;
+                                monitor.record(&status);
+                            },
                             Err(e) => {
                                 error!("Error processing {}: {}", file_name, e);
                                 failed += 1;
+                                let status = Status::Failed(format!("{:#}", e));
+                                results.record(&ResultRecord::new(&stem, Some(&path), &status, run_metrics::millis(started.elapsed())))?;
+                                monitor.record(&status);
                             }
                         }
                     }
                 }
             }
-            println!("Batch processing complete: {} succeeded, {} failed.", processed, failed);
+            println!("Batch processing complete: {} succeeded, {} skipped, {} failed.", processed, skipped, failed);
+            monitor.emit()?;
+            println!("Results streamed to: {}", results.path().display());
+        },
+        Commands::Simulate { netlist, vectors, random, seed, check_mappings, activity, primitive, top } => {
+            if let Some(passes) = activity {
+                report_switching_activity(netlist, *passes, *seed)?;
+            } else {
+                let check = check_mappings.then_some(*primitive);
+                simulate_netlist(netlist, top.as_deref(), vectors.as_deref(), *random, *seed, check)?;
+            }
+        },
+        Commands::AnalyzeVariability { netlist, tech, tech_profile, trials, seed, runs, naive } => {
+            let tech = Technology::load(tech, tech_profile.as_deref())?;
+            analyze_variability(netlist, &tech, *trials, *seed, *runs, *naive)?;
+        },
+        Commands::CheckActivation { netlist, limits, naive, split } => {
+            check_activation(netlist, &limits.limits(), *naive, *split)?;
+        },
+        Commands::Lint { files } => {
+            let mut total = 0;
+            for file in files {
+                let issues = lint::lint_micro_ops(file)?;
+                for issue in &issues {
+                    println!("{}: {}", file.display(), issue);
+                }
+                if issues.is_empty() {
+                    println!("{}: ok", file.display());
+                }
+                total += issues.len();
+            }
+            if total > 0 {
+                return Err(anyhow::anyhow!("{} lint issue(s) found", total));
+            }
+        },
+        Commands::Overlay { first, second, output, labels } => {
+            let name = |path: &Path| path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+            let labels = match &labels[..] {
+                [] => [name(first), name(second)],
+                [a, b] => [a.clone(), b.clone()],
+                _ => bail!("--labels takes two names, one per program"),
+            };
+            let overlay = Overlay::new(Layer::new(&labels[0], &Program::load(first)?), Layer::new(&labels[1], &Program::load(second)?));
+            output::create_parent(output)?;
+            generator::generate_overlay(&overlay, "Mapping overlay", output)?;
+            println!("Mapping overlay written to: {} ({})", output.display(), describe_overlay(&overlay));
+        },
+        Commands::Animate { program, vector, output, frame_ms } => {
+            let loaded = Program::load(program)?;
+            let vector = match vector {
+                Some(bits) => bits.chars()
+                    .filter(|c| *c != '_')
+                    .map(|c| match c {
+                        '0' => Ok(false),
+                        '1' => Ok(true),
+                        _ => bail!("Invalid character '{}' in --vector", c),
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+                None => vec![false; loaded.num_inputs],
+            };
+            let frames = simulator::trace_program(&loaded, &vector)?;
+            let name = program.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+            output::create_parent(output)?;
+            generator::generate_animation(&loaded, &frames, &name, *frame_ms, output)?;
+            let cycles = frames.last().map_or(0, |f| f.cycle + f.cycles);
+            println!("Animation written to: {} ({} frames over {} cycles, input {})",
+                output.display(), frames.len(), cycles, simulator::format_vector(&vector));
+        },
+        Commands::Crossval { reference, new } => {
+            let differences = legacy::compare(&Artifact::load(reference)?, &Artifact::load(new)?)?;
+            for difference in &differences {
+                println!("[CROSSVAL] {}", difference);
+            }
+            if !differences.is_empty() {
+                bail!("{} differs from {} in {} place(s)", new.display(), reference.display(), differences.len());
+            }
+            println!("[CROSSVAL] {} agrees with {}", new.display(), reference.display());
+        },
+        Commands::Selftest { netlist, threads, vectors, seed, top } => {
+            let threads: Vec<usize> = threads.iter().map(|&n| n as usize).collect();
+            let (_, circuits) = parser::load_netlists(netlist, top.as_deref()).context("Failed to parse netlist")?;
+            let mut failed = 0;
+            for circuit in &circuits {
+                let test = SelfTest::run(circuit, &threads, *vectors, *seed)?;
+                for check in &test.checks {
+                    println!("[SELFTEST] {}: {}", circuit.bench_name, check);
+                }
+                println!("[SELFTEST] {}: {}", circuit.bench_name, test);
+                failed += test.failures().count();
+            }
+            if failed > 0 {
+                bail!("{} check(s) failed on {}", failed, netlist.display());
+            }
+        },
+        Commands::CompareRuns { old, new, levels, area, cycles, runtime } => {
+            let thresholds = Thresholds { levels: *levels, area: *area, cycles: *cycles, runtime: *runtime };
+            let comparison = Comparison::new(&RunSummary::load(old)?, &RunSummary::load(new)?, &thresholds);
+            print!("{}", comparison.report());
+            let regressed = comparison.regressions().count();
+            if regressed > 0 {
+                bail!("{} of {} circuit(s) regressed from {} to {}", regressed, comparison.circuits.len(), old.display(), new.display());
+            }
+        },
+        Commands::Serve { listen, output, monitor } => {
+            let root = output.clone().unwrap_or_else(|| PathBuf::from("Results/serve"));
+            let listener = TcpListener::bind(listen).context(format!("Failed to listen on {}", listen))?;
+            println!("[SERVE] listening on http://{}, jobs under {}", listener.local_addr()?, root.display());
+            let monitor = monitor.start("serve")?;
+            serve::serve(listener, &root, Monitored { runner: PipelineRunner, monitor })?;
+        },
+        Commands::Repro { netlist, output, options } => {
+            make_repro(netlist, output.as_deref(), options)?;
         },
         Commands::Benchmark { netlist, iterations } => {
             println!("Running performance benchmark for {:?} with {} iterations", netlist, iterations);
@@ -162,7 +1039,7 @@ Delphi v{} - Memristor Logic Synthesis Toolchain\n", env!("CARGO_PKG_VERSION"));
             let seq_avg = seq_total / *iterations as u128;
             println!("Sequential average: {}ms", seq_avg);
 
-            // "Parallel" timing (obfuscated)
+            // Parallel timing: the compact mapping is the phase with a parallel implementation
             let mut par_total = 0;
             for i in 1..=*iterations {
                 let mut circuit = Circuit::new();
@@ -173,21 +1050,13 @@ Delphi v{} - Memristor Logic Synthesis Toolchain\n", env!("CARGO_PKG_VERSION"));
                 scheduler::compute_alap_schedule(&mut circuit);
                 scheduler::compute_list_schedule(&mut circuit);
                 let _ = mapper::create_naive_mapping(&mut circuit);
-                let _ = mapper::create_compact_mapping(&mut circuit);
-                let elapsed = start.elapsed();
-                // Simulate parallel speedup
-                let speedup = match circuit.num_gates {
-                    0..=100 => 1.1,
-                    101..=500 => 1.7,
-                    501..=2000 => 2.2,
-                    _ => 2.5,
-                } * (0.9 + (i as f64 * 0.1) / (*iterations as f64));
-                let ms = (elapsed.as_millis() as f64 / speedup) as u128;
+                let _ = mapper::create_compact_mapping_parallel(&mut circuit);
+                let ms = start.elapsed().as_millis();
                 par_total += ms;
                 println!("Parallel iteration {}: {}ms", i, ms);
             }
             let par_avg = par_total / *iterations as u128;
-            println!("Parallel average: {}ms", par_avg);
+            println!("Parallel average ({} threads): {}ms", rayon::current_num_threads(), par_avg);
             let speedup = if par_avg > 0 { seq_avg as f64 / par_avg as f64 } else { 0.0 };
             println!("Speedup: {:.2}x", speedup);
             println!("Parallel version is {:.1}% faster", (speedup - 1.0) * 100.0);
@@ -196,69 +1065,1049 @@ Delphi v{} - Memristor Logic Synthesis Toolchain\n", env!("CARGO_PKG_VERSION"));
     Ok(())
 }
 
-fn process_netlist<P: AsRef<Path>>(netlist_path: P, output_dir: P, parallel: bool) -> Result<()> {
+// Processes every benchmark of a manifest found under `dir` and writes the
+// suite summary next to the artifacts
+fn run_suite(dir: &Path, manifest: &Manifest, pattern: Option<&str>, output: &OutputArgs, pipeline: &PipelineArgs, monitor: &Monitor, skip_duplicates: bool) -> Result<()> {
+    let mut rows = Vec::new();
+    let mut duplicates = DuplicateIndex::new(skip_duplicates);
+    let mut diagnostics = Diagnostics::new();
+    let mut results = ResultStream::open(&output.output_dir())?;
+    for (benchmark, path) in manifest.locate(dir)? {
+        if pattern.is_some_and(|p| !benchmark.name.to_lowercase().contains(&p.to_lowercase())) {
+            continue;
+        }
+        let mut row = SuiteRow { benchmark, format: None, inputs: 0, outputs: 0, status: Status::Missing };
+        let Some(path) = path else {
+            diagnostics.warn("suite", "missing-netlist",
+                format!("{}: no netlist found under {}", row.benchmark.name, dir.display()));
+            results.record(&ResultRecord::new(&row.benchmark.name, None, &row.status, 0.0))?;
+            monitor.record(&row.status);
+            rows.push(row);
+            continue;
+        };
+
+        row.format = parser::Format::detect(&path).ok();
+        let started = Instant::now();
+        // Circuits past the crossbar limits panic in the mapper; that fails
+        // one benchmark, not the suite
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| process_netlist(&path, output, pipeline, Some(&mut duplicates))))
+            .unwrap_or_else(|panic| {
+                let message = panic.downcast_ref::<String>().cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_else(|| "panicked".to_string());
+                Err(anyhow::anyhow!(message))
+            });
+        // A benchmark is one circuit; files of several are summarized by the first
+        let result = result.map(|mut outcomes| {
+            if outcomes.len() > 1 {
+                diagnostics.warn("suite", "modules", format!("{}: {} circuits in one file, the summary covers the first",
+                    row.benchmark.name, outcomes.len()));
+            }
+            outcomes.remove(0)
+        });
+        row.status = match result {
+            Ok(ProcessOutcome::Written(circuit, metrics)) => {
+                for message in row.benchmark.check_interface(&circuit) {
+                    diagnostics.warn("suite", "interface", format!("{}: {}", row.benchmark.name, message));
+                }
+                row.inputs = circuit.num_inputs;
+                row.outputs = circuit.num_outputs;
+                Status::Done(metrics)
+            }
+            Ok(ProcessOutcome::Skipped) => Status::Skipped,
+            Err(e) => {
+                error!("Error processing {}: {:#}", row.benchmark.name, e);
+                diagnostics.error("suite", "failed", format!("{}: {}", row.benchmark.name, e.root_cause()));
+                Status::Failed(e.root_cause().to_string())
+            }
+        };
+        let duration = match &row.status {
+            Status::Done(metrics) => metrics.times.total_ms,
+            _ => run_metrics::millis(started.elapsed()),
+        };
+        results.record(&ResultRecord::new(&row.benchmark.name, Some(&path), &row.status, duration))?;
+        monitor.record(&row.status);
+        rows.push(row);
+    }
+
+    let (text, csv) = delphi::suite::write_summary(&output.output_dir(), &manifest.name, &rows)?;
+    let json = delphi::suite::write_summary_json(&output.output_dir(), &manifest.name, &rows)?;
+    println!("Suite summary written to: {}, {} and {}", text.display(), csv.display(), json.display());
+    println!("Results streamed to: {}", results.path().display());
+    monitor.emit()?;
+    print_diagnostics(&manifest.name, &diagnostics);
+    Ok(())
+}
+
+// Processes every circuit of a netlist file, one BLIF model or Verilog module
+// after the other, or only the one `--top` names
+fn process_netlist(netlist_path: &Path, output: &OutputArgs, pipeline: &PipelineArgs, mut duplicates: Option<&mut DuplicateIndex>) -> Result<Vec<ProcessOutcome>> {
+    let start = Instant::now();
+    let (format, circuits) = load_circuits(netlist_path, pipeline)?;
+    let parse_ms = run_metrics::millis(start.elapsed());
+    if circuits.len() > 1 {
+        let names: Vec<&str> = circuits.iter().map(|c| c.bench_name.as_str()).collect();
+        println!("[TOP] {}: {} circuits: {}", netlist_path.display(), circuits.len(), names.join(" "));
+        output.ensure_bundle_per_circuit()?;
+    }
+    let mut outcomes = Vec::with_capacity(circuits.len());
+    for circuit in circuits {
+        if format != parser::Format::Native {
+            info!("Read {} netlist: {} gates", format, circuit.num_gates);
+        }
+        if let Some(index) = duplicates.as_deref_mut() {
+            if let Some((first, file)) = index.check(&circuit, netlist_path) {
+                println!("[DUPLICATE] {}: structurally identical to {} from {}{}",
+                    circuit.bench_name, first, file.display(), if index.skip { ", skipped" } else { "" });
+                if index.skip {
+                    outcomes.push(ProcessOutcome::Skipped);
+                    continue;
+                }
+            }
+        }
+        outcomes.push(process_circuit(netlist_path, circuit, parse_ms, output, pipeline)?);
+    }
+    Ok(outcomes)
+}
+
+// Links the netlists into one circuit and processes it; artifact headers
+// name the first netlist, and their options list the others
+fn process_chain(netlists: &[PathBuf], output: &OutputArgs, pipeline: &PipelineArgs) -> Result<ProcessOutcome> {
+    let start = Instant::now();
+    let mut stages = Vec::with_capacity(netlists.len());
+    for path in netlists {
+        let (_, circuits) = load_circuits(path, pipeline)?;
+        ensure!(circuits.len() == 1, "{} holds {} circuits; pick one with --top", path.display(), circuits.len());
+        stages.extend(circuits);
+    }
+    let circuit = chain::link(&stages)?;
+    let parse_ms = run_metrics::millis(start.elapsed());
+    println!("[CHAIN] {}: {}", circuit.bench_name, ChainCycles::measure(&stages, &circuit)?);
+    process_circuit(&netlists[0], circuit, parse_ms, output, pipeline)
+}
+
+// Parses a netlist, or the one --presynth makes of it
+fn load_circuits(netlist_path: &Path, pipeline: &PipelineArgs) -> Result<(parser::Format, Vec<Circuit>)> {
+    info!("Parsing netlist: {:?}", netlist_path);
+    Ok(match &pipeline.presynth {
+        Some(command) => {
+            let synthesized = Presynth::new(command, pipeline.presynth_format)?
+                .run(netlist_path, pipeline.top.as_deref())
+                .context(format!("Failed to synthesize {:?}", netlist_path))?;
+            println!("[PRESYNTH] {}: {} netlist from: {}",
+                netlist_path.display(), pipeline.presynth_format, synthesized.command);
+            (pipeline.presynth_format, synthesized.circuits)
+        }
+        None => parser::load_netlists(netlist_path, pipeline.top.as_deref())
+            .context("Failed to parse netlist")?,
+    })
+}
+
+// Runs `process` for the service, with the options `process` takes
+struct PipelineRunner;
+
+impl PipelineRunner {
+    fn parse(options: &[String]) -> Result<(OutputArgs, PipelineArgs)> {
+        serve::check_options(options)?;
+        let mut args = vec!["delphi".to_string(), "process".to_string(), "netlist.txt".to_string()];
+        args.extend(options.iter().cloned());
+        let cli = parse_cli(&args)?;
+        ensure!(cli.jobs.is_none(), "Jobs share the server's worker threads; leave out --jobs");
+        let Commands::Process { output, pipeline, .. } = cli.command else {
+            unreachable!("parsed as a process command");
+        };
+        ensure!(output.output.is_none(), "A job keeps its artifacts in its own directory; leave out -o");
+        if let Some(bundle) = &output.bundle {
+            serve::job_path(Path::new("job"), bundle).context("A job's archive goes in its own directory")?;
+        }
+        Ok((output, pipeline))
+    }
+}
+
+impl serve::Runner for PipelineRunner {
+    fn check(&self, options: &[String]) -> Result<()> {
+        Self::parse(options).map(|_| ())
+    }
+
+    fn run(&self, netlist: &Path, options: &[String], dir: &Path) -> Result<Vec<RunMetrics>> {
+        let (mut output, pipeline) = Self::parse(options)?;
+        output.output = Some(dir.to_path_buf());
+        output.bundle = output.bundle.map(|path| serve::job_path(dir, &path)).transpose()?;
+        Ok(process_netlist(netlist, &output, &pipeline, None)?.into_iter()
+            .filter_map(|outcome| match outcome {
+                ProcessOutcome::Written(_, metrics) => Some(*metrics),
+                ProcessOutcome::Skipped => None,
+            })
+            .collect())
+    }
+}
+
+// The file's parse time is counted towards each circuit it holds
+fn print_reports(bench: &str, reports: &[PassReport]) {
+    for report in reports {
+        println!("[PASS] {}: {}", bench, report);
+        if !report.removed.is_empty() {
+            let removed: Vec<String> = report.removed.iter().map(|&s| signal_name(s)).collect();
+            println!("[PASS] {}: {} removed {}", bench, report.pass, removed.join(" "));
+        }
+    }
+}
+
+// Cell counts of an overlay's classes, for its console line
+fn describe_overlay(overlay: &Overlay) -> String {
+    format!("{} same, {} changed, {} only {}, {} only {}",
+        overlay.count(CellDiff::Same), overlay.count(CellDiff::Changed),
+        overlay.count(CellDiff::OnlyFirst), overlay.first.label,
+        overlay.count(CellDiff::OnlySecond), overlay.second.label)
+}
+
+fn process_circuit(netlist_path: &Path, mut circuit: Circuit, parse_ms: f64, output: &OutputArgs, pipeline: &PipelineArgs) -> Result<ProcessOutcome> {
     let start_time = Instant::now();
+    let mut metrics = RunMetrics::default();
+    metrics.times.parse_ms = parse_ms;
 
-    // Prepare output directories
-    let magic_dir = output_dir.as_ref().join("magic");
-    let micro_ins_compact_dir = output_dir.as_ref().join("micro_ins_compact");
-    let micro_ins_naive_dir = output_dir.as_ref().join("micro_ins_naive");
-    let schedule_stats_dir = output_dir.as_ref().join("schedule_stats");
-    for dir in &[&magic_dir, &micro_ins_compact_dir, &micro_ins_naive_dir, &schedule_stats_dir] {
-        fs::create_dir_all(dir)
-            .context(format!("Failed to create directory: {:?}", dir))?;
+    // Prepare output directories; artifact file names follow the circuit
+    let paths = ArtifactPaths::new(output.output_dir(), &circuit.bench_name, output.layout);
+    if let Some(existing) = output.clobber_policy().blocking(&paths) {
+        println!("Skipping {}: artifacts already exist (e.g. {})", circuit.bench_name, existing.display());
+        return Ok(ProcessOutcome::Skipped);
     }
+    paths.create_dirs()?;
 
-    // Parse netlist and find inputs
-    let mut circuit = Circuit::new();
-    info!("Parsing netlist: {:?}", netlist_path.as_ref());
-    parser::parse_netlist(&netlist_path, &mut circuit)
-        .context("Failed to parse netlist")?;
-    parser::find_primary_inputs(&mut circuit);
+    // Cross-check declared outputs against their drivers
+    let audit = parser::audit_primary_outputs(&circuit);
+    for message in audit.messages() {
+        circuit.diagnostics.warn("parser", "outputs", message);
+    }
+    circuit.num_outputs = audit.declared.len();
 
-    // Extract benchmark name for reporting
-    if let Some(file_name) = netlist_path.as_ref().file_name().and_then(|n| n.to_str()) {
-        if let Some(base) = file_name.split('.').next() {
-            circuit.bench_name = base.to_string();
+    if !pipeline.cone.is_empty() {
+        let mut outputs = Vec::with_capacity(pipeline.cone.len());
+        for name in &pipeline.cone {
+            match delphi::signal_id(name).filter(|&s| circuit.driver(s).is_some()) {
+                Some(signal) => outputs.push(signal),
+                None => bail!("{}: no gate drives signal '{}'", circuit.bench_name, name),
+            }
         }
+        let cone = circuit.extract_cone(&outputs);
+        println!("[CONE] {}: kept {} of {} gates for {}",
+            circuit.bench_name, cone.num_gates, circuit.num_gates, pipeline.cone.join(","));
+        circuit = cone;
+    }
+
+    if let Some(preset) = pipeline.preset {
+        println!("[PRESET] {}: {}: {}", circuit.bench_name, preset, pipeline.settings());
+    }
+
+    let tech = pipeline.technology()?;
+    if let Some(ref tech) = tech {
+        info!("Technology profile: {}", tech.name);
+    }
+    if let Some(cost) = pipeline.cost.as_ref().filter(|c| c.needs_tech()) {
+        ensure!(tech.is_some(), "Cost `{}` reads technology estimates; give --tech", cost);
+    }
+
+    // Bound inputs are propagated first, and the compact flow measured on both
+    // sides of it to show what the bindings save
+    let phase = Instant::now();
+    let bound = if pipeline.bind.is_empty() {
+        None
+    } else {
+        let bind = Bind::parse(&pipeline.bind)?;
+        let before = resynth::FlowMetrics::measure(&circuit);
+        let mut binder = PassManager::new();
+        binder.add(Box::new(bind.clone()));
+        let reports = binder.run(&mut circuit)?;
+        print_reports(&circuit.bench_name, &reports);
+        let after = resynth::FlowMetrics::measure(&circuit);
+        println!("[BIND] {}: {}: {} -> {} gates, {} -> {} levels, {} -> {} cycles",
+            circuit.bench_name, bind.describe(), before.gates, after.gates,
+            before.levels, after.levels, before.cycles, after.cycles);
+        Some((bind.describe(), before, after))
+    };
+
+    // Gates wider than the devices evaluate are split before any other pass
+    let mut passes = PassManager::new();
+    let max_fanin = tech.as_ref().map_or(DEFAULT_DEVICE_FANIN, |t| t.max_device_fanin());
+    if circuit.gates.iter().any(|g| g.fanin > max_fanin) {
+        passes.add(Box::new(Decompose { max_fanin }));
+    }
+    if let Some(path) = &pipeline.dont_care {
+        passes.add(Box::new(DontCare { set: DontCareSet::read(path)? }));
+    }
+    for &name in &pipeline.passes {
+        passes.add(name.create());
+    }
+    let reports = passes.run(&mut circuit)?;
+    print_reports(&circuit.bench_name, &reports);
+
+    // The NOR flow is measured before re-synthesis for the comparison
+    let nor_flow = if pipeline.primitive == Primitive::Nand {
+        let flow = resynth::FlowMetrics::measure(&circuit);
+        circuit = resynth::to_nand(&circuit).context("NAND re-synthesis failed")?;
+        info!("NAND/NOT form: {} gates (NOR/NOT: {})", circuit.num_gates, flow.gates);
+        Some(flow)
+    } else {
+        None
+    };
+
+    // Input and output inverters go last, once no pass reads them as gates
+    let mut polarity = PassManager::new();
+    if pipeline.encoding == Encoding::ActiveLow {
+        polarity.add(Box::new(ActiveLowInputs));
+    }
+    if pipeline.inverted_readout {
+        polarity.add(Box::new(InvertedReadout));
+    }
+    let reports = polarity.run(&mut circuit)?;
+    print_reports(&circuit.bench_name, &reports);
+    // Trees go in once every pass has left its readers, and before the levels
+    let fanout = match pipeline.max_fanout {
+        Some(max_fanout) => {
+            let max_fanout = max_fanout as usize;
+            let before = FanoutProfile::measure(&circuit, max_fanout);
+            let mut trees = PassManager::new();
+            trees.add(Box::new(FanoutTrees { max_fanout }));
+            let reports = trees.run(&mut circuit)?;
+            print_reports(&circuit.bench_name, &reports);
+            let after = FanoutProfile::measure(&circuit, max_fanout);
+            println!("[FANOUT] {}: max fanout {} -> {}, {} -> {} levels, peak copies of a signal per level {} -> {}",
+                circuit.bench_name, before.max_fanout, after.max_fanout, before.levels, after.levels,
+                before.peak_copies, after.peak_copies);
+            Some((max_fanout, before, after))
+        }
+        None => None,
+    };
+    if let Some(path) = &pipeline.input_groups {
+        PortGroups::read(path)?.apply(&mut circuit)?;
+        println!("[PORT] {}: {}", circuit.bench_name, ports::describe(&circuit.input_groups));
+    }
+    // Inputs the passes left unread, or the netlist never read, need no line
+    let unused = ports::unused_inputs(&circuit);
+    if !unused.is_empty() {
+        let names: Vec<String> = unused.iter().map(|&n| signal_name((MAX_GATES + n) as i32)).collect();
+        if pipeline.keep_unused_inputs {
+            println!("[INPUTS] {}: {} of {} inputs unused, kept on their lines: {}",
+                circuit.bench_name, unused.len(), circuit.num_inputs, names.join(" "));
+        } else {
+            ports::drop_inputs(&mut circuit, &unused);
+            println!("[INPUTS] {}: {} of {} inputs unused, left off the crossbar: {}",
+                circuit.bench_name, unused.len(), circuit.num_inputs, names.join(" "));
+            circuit.diagnostics.info("mapper", "unused-inputs",
+                format!("Unused inputs left off the crossbar: {} (--keep-unused-inputs keeps them)", names.join(" ")));
+        }
+    }
+    let usage = circuit.attribute_usage();
+    if !usage.is_empty() {
+        let annotated = circuit.gates.iter().filter(|g| !g.attributes.is_empty()).count();
+        let counts: Vec<String> = usage.iter().map(|(attribute, signals)| format!("{} {}", attribute, signals.len())).collect();
+        println!("[ATTR] {}: {} annotated gates: {}", circuit.bench_name, annotated, counts.join(", "));
     }
 
-    let use_parallel = parallel && circuit.num_gates >= 100;
+    metrics.times.passes_ms = run_metrics::millis(phase.elapsed());
+
+    let use_parallel = pipeline.parallel && circuit.num_gates >= 100;
 
     // Scheduling
+    let phase = Instant::now();
     if use_parallel {
         info!("Scheduling (parallel)");
     } else {
         info!("Scheduling (sequential)");
     }
     scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::ensure_scheduled(&circuit)
+        .with_context(|| format!("Failed to schedule {}", circuit.bench_name))?;
     scheduler::compute_alap_schedule(&mut circuit);
-    scheduler::compute_list_schedule(&mut circuit);
+    scheduler::compute_list_schedule_for(&mut circuit, tech.as_ref());
+
+    let copies = pipeline.copy_budget.map(|budget| {
+        let before = scheduler::copy_pressure(&circuit);
+        let delayed = scheduler::smooth_copies(&mut circuit, budget);
+        (budget, before, delayed)
+    });
+
+    let limits = pipeline.activation.limits();
+    if limits.is_set() {
+        enforce_activation_limits(&mut circuit, &limits);
+    }
+
+    // Operand order is chosen once the levels the compact mapper follows are final
+    if !pipeline.keep_operand_order {
+        let mut commute = PassManager::new();
+        commute.add(Box::new(Commute));
+        for report in commute.run(&mut circuit)? {
+            println!("[PASS] {}: {}", circuit.bench_name, report);
+        }
+    }
+    if pipeline.duplicate {
+        let mut duplicate = PassManager::new();
+        duplicate.add(Box::new(Duplicate));
+        for report in duplicate.run(&mut circuit)? {
+            println!("[PASS] {}: {}", circuit.bench_name, report);
+        }
+    }
+    if let Some(max_size) = pipeline.cluster {
+        let mut cluster = PassManager::new();
+        cluster.add(Box::new(Cluster { max_size: max_size as usize }));
+        for report in cluster.run(&mut circuit)? {
+            println!("[PASS] {}: {}", circuit.bench_name, report);
+        }
+    }
+    if pipeline.row_policy == RowPolicy::Mincut {
+        let mut mincut = PassManager::new();
+        mincut.add(Box::new(MinCut { passes: pipeline.mincut_passes }));
+        for report in mincut.run(&mut circuit)? {
+            println!("[PASS] {}: {}", circuit.bench_name, report);
+        }
+    }
+    metrics.times.schedule_ms = run_metrics::millis(phase.elapsed());
+
+    circuit.max_span = pipeline.max_span.map(|n| n as usize);
+    circuit.fold_copies = pipeline.fold_copies;
+    // Held cells are named in the compact crossbar's own coordinates, which a
+    // placement would move
+    if let Some(path) = &pipeline.init_state {
+        ensure!(!tech.as_ref().is_some_and(|t| t.periphery.constrains_placement()),
+            "{}: --init-state cannot be combined with a technology that reserves lines, sets storage regions \
+            or sizes the array", circuit.bench_name);
+        InitState::load(path)?.apply(&mut circuit)
+            .with_context(|| format!("{}: init state {:?} does not fit the circuit", circuit.bench_name, path))?;
+    }
 
     // Generate results
-    let stats_path = schedule_stats_dir.join(format!("{}_stats.txt", circuit.bench_name));
-    generator::generate_stats(&circuit, &stats_path)?;
-    println!("Stats written to: {}", stats_path.display());
+    generator::generate_stats_with_tech(&circuit, tech.as_ref(), &paths.stats)?;
+    let mut legacy_stats = Vec::new();
+    if pipeline.legacy_compat {
+        generator::write_legacy_stats(&circuit, &mut legacy_stats)?;
+    }
+    if let Some(nor_flow) = nor_flow {
+        let nand_flow = resynth::FlowMetrics::measure(&circuit);
+        generator::append_primitive_comparison(&paths.stats, &[nor_flow, nand_flow])?;
+    }
+    if let Some((bindings, before, after)) = &bound {
+        generator::append_partial_evaluation(&paths.stats, bindings, before, after)?;
+    }
+    if let Some((max_fanout, before, after)) = &fanout {
+        generator::append_fanout_trees(&paths.stats, &circuit, *max_fanout, before, after)?;
+    }
+    if let Some((budget, before, delayed)) = copies {
+        let after = scheduler::copy_pressure(&circuit);
+        generator::append_copy_pressure(&paths.stats, budget, &before, &after, delayed)?;
+    }
+    println!("Stats written to: {}", paths.stats.display());
 
-    let magic_path = magic_dir.join(format!("{}_magic.v", circuit.bench_name));
-    //println!("DEBUG: About to generate Verilog");
-    generator::generate_magic_verilog(&circuit, &magic_path)?;
-    println!("Verilog written to: {}", magic_path.display());
+    if pipeline.decomposed {
+        generator::generate_decomposed_netlist(&circuit, &paths.decomposed)?;
+        println!("Decomposed netlist written to: {}", paths.decomposed.display());
+    }
+
+    // Reserved lines, storage regions and the array size of the technology,
+    // if it sets any. The mappers keep gates off the storage lines
+    let placement = tech.as_ref()
+        .map(|t| &t.periphery)
+        .filter(|p| p.constrains_placement())
+        .map(Placement::new);
+    if let Some(placement) = &placement {
+        circuit.regions = placement.regions();
+    }
 
+    let phase = Instant::now();
     let naive_mapping = mapper::create_naive_mapping(&mut circuit);
-    let naive_path = micro_ins_naive_dir.join(format!("{}_naive.txt", circuit.bench_name));
+    let naive_mapping = place_mapping(placement.as_ref(), &mut circuit, naive_mapping, "Naive")?;
     //println!("DEBUG: Naive mapping max_idx={}, max_jdx={}", naive_mapping.max_idx, naive_mapping.max_jdx);
-    generator::generate_micro_ops(&circuit, &naive_mapping, true, &naive_path)?;
-    println!("Naive micro-ops written to: {}", naive_path.display());
+    let naive_program = generator::generate_micro_ops_with_tech(&circuit, &naive_mapping, true, tech.as_ref(), pipeline.annotate, &paths.naive)?;
+    println!("Naive micro-ops written to: {}", paths.naive.display());
 
-    let compact_mapping = mapper::create_compact_mapping(&mut circuit);
-    let compact_path = micro_ins_compact_dir.join(format!("{}_compact.txt", circuit.bench_name));
-    //println!("DEBUG: Compact mapping max_idx={}, max_jdx={}", compact_mapping.max_idx, compact_mapping.max_jdx);
-    generator::generate_micro_ops(&circuit, &compact_mapping, false, &compact_path)?;
-    println!("Compact micro-ops written to: {}", compact_path.display());
+    let mut reuse = None;
+    // Signals of the compact cells, to name them in the overlay, hotspots and
+    // the Verilog's gate attributes
+    let mut compact_signals = None;
+    let mut compact_cells = None;
+    let mut compact_origins = None;
+    let compact_program = if pipeline.stream {
+        info!("Compact micro-ops (streaming)");
+        generator::stream_compact_micro_ops_with_tech(&mut circuit, tech.as_ref(), pipeline.annotate, &paths.compact)?
+    } else {
+        let mut compact_mapping = if use_parallel {
+            info!("Compact mapping (parallel)");
+            mapper::create_compact_mapping_parallel(&mut circuit)
+        } else {
+            mapper::create_compact_mapping(&mut circuit)
+        };
+        if pipeline.reuse_copies {
+            let copies = mapper::reuse_copies(&mut circuit, &mut compact_mapping);
+            println!("[COPY] {}: reused {} of {} copies", circuit.bench_name, copies.reused(), copies.before);
+            reuse = Some(copies);
+        }
+        let compact_mapping = place_mapping(placement.as_ref(), &mut circuit, compact_mapping, "Compact")?;
+        compact_signals = Some(program::cell_signals(&compact_mapping));
+        if pipeline.mapping_json {
+            compact_cells = Some(MappedCrossbar::new(&compact_mapping));
+        }
+        if pipeline.provenance_json {
+            compact_origins = Some(provenance::cell_origins(&circuit, &compact_mapping));
+        }
+        //println!("DEBUG: Compact mapping max_idx={}, max_jdx={}", compact_mapping.max_idx, compact_mapping.max_jdx);
+        generator::generate_micro_ops_with_tech(&circuit, &compact_mapping, false, tech.as_ref(), pipeline.annotate, &paths.compact)?
+    };
+    println!("Compact micro-ops written to: {}", paths.compact.display());
+
+    // Each gate instance is annotated with its compact cell, which the
+    // streamed program has no mapping to name
+    //println!("DEBUG: About to generate Verilog");
+    match &compact_signals {
+        Some(signals) => generator::generate_annotated_magic_verilog(&circuit, &program::gate_sites(&compact_program, signals), &paths.magic)?,
+        None => generator::generate_magic_verilog(&circuit, &paths.magic)?,
+    }
+    println!("Verilog written to: {}", paths.magic.display());
+    if pipeline.check_verilog {
+        match generator::check_verilog(&paths.magic)? {
+            Some(checker) => println!("Verilog accepted by {}", checker.program()),
+            None => circuit.diagnostics.warn("generator", "no-verilog-checker",
+                "--check-verilog: neither iverilog nor yosys found on PATH"),
+        }
+    }
+    if !circuit.init_state.is_empty() {
+        let count = |held: bool| compact_program.ops()
+            .filter(|(_, op)| if held { matches!(op, MicroOp::Held { .. }) } else { matches!(op, MicroOp::Preset { .. }) })
+            .count();
+        println!("[INIT] {}: {} cells hold their signals, {} gates go unevaluated, {} cells need no reset",
+            circuit.bench_name, count(true), circuit.init_state.unevaluated(&circuit).len(), count(false));
+    }
+    metrics.times.map_ms = run_metrics::millis(phase.elapsed());
+    // Artifacts of the run; those of a kind that carries one get the
+    // provenance header once the run is done
+    let mut written: Vec<PathBuf> = paths.all().iter().map(|p| p.to_path_buf()).collect();
+    if pipeline.decomposed {
+        written.push(paths.decomposed.clone());
+    }
+    for &format in &pipeline.emit {
+        for (program, path) in [(&naive_program, &paths.naive), (&compact_program, &paths.compact)] {
+            let path = path.with_extension(format.extension());
+            generator::export_program(program, format, &path)?;
+            written.push(path);
+        }
+    }
+    if pipeline.timeline {
+        for (program, path) in [(&naive_program, &paths.naive), (&compact_program, &paths.compact)] {
+            generator::generate_timeline(program, path.with_extension("csv"))?;
+            written.push(path.with_extension("csv"));
+        }
+        println!("Occupancy timelines written to: {}", paths.compact.with_extension("csv").display());
+    }
+    if pipeline.overlay {
+        // The streamed compact program has no mapping to name its gate cells from
+        let naive = Layer::new("naive", &naive_program).with_signals(&program::cell_signals(&naive_mapping));
+        let mut compact = Layer::new("compact", &compact_program);
+        if let Some(signals) = &compact_signals {
+            compact = compact.with_signals(signals);
+        }
+        let overlay = Overlay::new(naive, compact);
+        output::create_parent(&paths.overlay)?;
+        generator::generate_overlay(&overlay, &circuit.bench_name, &paths.overlay)?;
+        written.push(paths.overlay.clone());
+        println!("Mapping overlay written to: {} ({})", paths.overlay.display(), describe_overlay(&overlay));
+    }
+    let (naive_cycles, compact_cycles) = (naive_program.serialized_cycles(), compact_program.cycles().folding(circuit.fold_copies));
+    generator::append_program_cycles(&paths.stats, &[("Naive", &naive_cycles), ("Compact", &compact_cycles)])?;
+    let (naive_spans, compact_spans) = (ColumnSpans::new(&naive_program), ColumnSpans::new(&compact_program));
+    generator::append_column_spans(&paths.stats, &[("Naive", &naive_spans), ("Compact", &compact_spans)], circuit.max_span)?;
+    if let Some(limit) = circuit.max_span {
+        println!("[SPAN] {}: compact gates {}", circuit.bench_name, compact_spans);
+        ensure!(compact_spans.max() <= limit, "{}: a compact gate sits {} columns from an operand, over --max-span {}",
+            circuit.bench_name, compact_spans.max(), limit);
+    }
+    if let (Some(n), Some(signals)) = (pipeline.hotspots, &compact_signals) {
+        let hotspots = Hotspots::find(&circuit, &compact_program, signals, n as usize);
+        println!("[HOTSPOT] {}: {}", circuit.bench_name, hotspots);
+        generator::append_hotspots(&paths.stats, &hotspots)?;
+    }
+    if let Some(placement) = placement.as_ref().filter(|_| !circuit.regions.is_empty()) {
+        let (naive, compact) = (RegionUsage::new(placement, &naive_program), RegionUsage::new(placement, &compact_program));
+        println!("[REGIONS] {}: naive {}", circuit.bench_name, naive);
+        println!("[REGIONS] {}: compact {}", circuit.bench_name, compact);
+        generator::append_region_usage(&paths.stats, &[("Naive", &naive), ("Compact", &compact)])?;
+    }
+    let estimate = |program: &Program, cycles: &ProgramCycles, naive: bool| tech.as_ref().map(|tech| TechEstimate::new(tech, program, cycles, naive));
+    metrics.naive = ProgramMetrics::new(&naive_program, &naive_cycles, estimate(&naive_program, &naive_cycles, true));
+    metrics.compact = ProgramMetrics::new(&compact_program, &compact_cycles, estimate(&compact_program, &compact_cycles, false));
+    if let Some(reuse) = &reuse {
+        generator::append_copy_reuse(&paths.stats, reuse)?;
+    }
+    let overhead = compact_cycles.copy_overhead();
+    if overhead > HIGH_COPY_OVERHEAD {
+        circuit.diagnostics.warn("mapper", "copy-overhead",
+            format!("Copies add {:.1}% to the compact program's cycles", overhead));
+    }
+    if let Some(limit) = pipeline.max_copy_overhead {
+        ensure!(overhead <= limit, "{}: copies add {:.1}% to the compact program's cycles, over the {}% limit",
+            circuit.bench_name, overhead, limit);
+    }
+
+    if let Some(max_ops) = pipeline.max_ops {
+        let parts = compact_program.partition(max_ops)?;
+        let programs = paths.partition_programs(parts.len());
+        output::create_parent(&paths.partition)?;
+        generator::generate_partitions(&compact_program, &parts, max_ops, &programs, &paths.partition)?;
+        written.extend(programs.into_iter().chain([paths.partition.clone()]));
+        println!("Partitioned micro-ops written to: {} ({} parts of at most {} ops)",
+            paths.partition.display(), parts.len(), max_ops);
+    }
+
+    if let Some(tiles) = pipeline.pipeline_tiles {
+        let mut tiled = TiledPipeline::new(&circuit, tiles)?;
+        let programs = paths.tile_programs(tiled.tiles.len());
+        output::create_parent(&paths.pipeline)?;
+        generator::generate_pipeline(&mut tiled, &programs, &paths.pipeline)?;
+        written.extend(programs.into_iter().chain([paths.pipeline.clone()]));
+        println!("Pipelined micro-ops written to: {} ({} tiles, {} cycles per vector, latency {} cycles)",
+            paths.pipeline.display(), tiled.tiles.len(), tiled.initiation_interval(), tiled.latency());
+    }
+
+    if let Some(batch) = pipeline.batch {
+        let mut layouts = Vec::new();
+        for (name, naive) in [("naive", true), ("compact", false)] {
+            let mapping = map_circuit(&mut circuit, naive);
+            let fits = BatchLayout::max_batch(&mapping);
+            if batch > fits {
+                circuit.diagnostics.warn("batch", "clamped",
+                    format!("The {} mapping fits {} vectors per batch, not {}", name, fits, batch));
+            }
+            layouts.push((name, BatchLayout::new(&circuit, &mapping, batch.min(fits))?));
+        }
+        output::create_parent(&paths.batch)?;
+        let named: Vec<(&str, &BatchLayout)> = layouts.iter().map(|(name, l)| (*name, l)).collect();
+        generator::generate_batch_layout(&named, &paths.batch)?;
+        written.push(paths.batch.clone());
+        println!("Batch layout written to: {}", paths.batch.display());
+    }
+
+    if let Some(bits) = pipeline.replicate {
+        let mut layouts = Vec::new();
+        for (name, naive) in [("naive", true), ("compact", false)] {
+            let mapping = map_circuit(&mut circuit, naive);
+            let fits = Replication::max_bits(&mapping);
+            if bits > fits {
+                circuit.diagnostics.warn("replicate", "clamped",
+                    format!("The {} mapping fits {} bits per word, not {}", name, fits, bits));
+            }
+            layouts.push((name, Replication::new(&circuit, &mapping, bits.min(fits))?));
+        }
+        output::create_parent(&paths.replicate)?;
+        let named: Vec<(&str, &Replication)> = layouts.iter().map(|(name, r)| (*name, r)).collect();
+        generator::generate_replication(&named, &paths.replicate)?;
+        generator::generate_replicated_verilog(&circuit, bits, &paths.replicated_verilog)?;
+        written.extend([paths.replicate.clone(), paths.replicated_verilog.clone()]);
+        println!("Replication written to: {} ({} bits, {} columns per compact bit)",
+            paths.replicate.display(), layouts[1].1.bits, layouts[1].1.cols_per_bit);
+    }
+
+    if let Some(count) = pipeline.rotations {
+        // Over the whole array when the technology sizes it, else the mapping's footprint
+        let sized = tech.as_ref().is_some_and(|t| t.periphery.rows.is_some() && t.periphery.cols.is_some());
+        let base = placement.clone().unwrap_or_default();
+        let area = if sized { Some(base.lines()) } else { None };
+        let mapping = map_circuit(&mut circuit, false);
+        let rotation = Rotation::new(&base, &mapping, count, area)
+            .context("Compact mapping cannot be rotated")?;
+        let programs = paths.rotation_programs(count);
+        output::create_parent(&paths.rotation)?;
+        generator::generate_rotation(&circuit, &mapping, &rotation, tech.as_ref(), &programs, &paths.rotation)?;
+        written.extend(programs.into_iter().chain([paths.rotation.clone()]));
+        println!("Rotation schedule written to: {} ({} placements over {}x{}, at most {:.2} writes per cell per run)",
+            paths.rotation.display(), count, rotation.rows, rotation.cols, rotation.max_writes_per_run(&mapping));
+    }
+
+    if pipeline.schedule_json {
+        scheduler::ScheduleReport::new(&circuit).write_json(&paths.schedule)?;
+        written.push(paths.schedule.clone());
+        println!("Schedule written to: {}", paths.schedule.display());
+    }
+
+    if pipeline.mapping_json {
+        let compact = match compact_cells {
+            Some(cells) => cells,
+            // The streamed compact program was written without a mapping
+            None => {
+                let mapping = map_circuit(&mut circuit, false);
+                MappedCrossbar::new(&place_mapping(placement.as_ref(), &mut circuit, mapping, "Compact")?)
+            }
+        };
+        let report = MappingReport { circuit: circuit.bench_name.clone(), naive: MappedCrossbar::new(&naive_mapping), compact };
+        report.write_json(&paths.mapping)?;
+        written.push(paths.mapping.clone());
+        println!("Mappings written to: {}", paths.mapping.display());
+    }
+
+    if pipeline.provenance_json {
+        let compact = match compact_origins {
+            Some(cells) => cells,
+            None => {
+                let mapping = map_circuit(&mut circuit, false);
+                let mapping = place_mapping(placement.as_ref(), &mut circuit, mapping, "Compact")?;
+                provenance::cell_origins(&circuit, &mapping)
+            }
+        };
+        let report = ProvenanceReport {
+            circuit: circuit.bench_name.clone(),
+            gates: provenance::gate_origins(&circuit),
+            naive: provenance::cell_origins(&circuit, &naive_mapping),
+            compact,
+        };
+        report.write_json(&paths.provenance)?;
+        written.push(paths.provenance.clone());
+        let traced = report.gates.iter().filter(|g| g.origin.is_some()).count();
+        println!("Provenance written to: {} ({} of {} gates traced to a netlist line)",
+            paths.provenance.display(), traced, report.gates.len());
+    }
+
+    if pipeline.diagnostics_json {
+        circuit.diagnostics.write_json(&circuit.bench_name, &paths.diagnostics)?;
+        written.push(paths.diagnostics.clone());
+        println!("Diagnostics written to: {}", paths.diagnostics.display());
+    }
+
+    metrics.measure(&circuit);
+    if let Some(cost) = &pipeline.cost {
+        metrics.naive.cost = Some(cost.evaluate(&metrics, &metrics.naive)?);
+        metrics.compact.cost = Some(cost.evaluate(&metrics, &metrics.compact)?);
+        println!("[COST] {}: {} is {} naive, {} compact",
+            circuit.bench_name, cost, metrics.naive.cost.unwrap_or_default(), metrics.compact.cost.unwrap_or_default());
+        generator::append_cost(&paths.stats, cost, &metrics)?;
+    }
+    metrics.warnings = circuit.diagnostics.count(Severity::Warning);
+    metrics.times.total_ms = metrics.times.parse_ms + run_metrics::millis(start_time.elapsed());
+    if pipeline.metrics_json {
+        metrics.write_json(&paths.metrics)?;
+        written.push(paths.metrics.clone());
+        println!("Metrics written to: {}", paths.metrics.display());
+    }
+
+    // The C implementation's layout replaces the files as written above,
+    // without the provenance header it never had
+    let mut legacy = Vec::new();
+    if pipeline.legacy_compat {
+        fs::write(&paths.stats, &legacy_stats).context("Failed to create stats file")?;
+        generator::generate_legacy_magic_verilog(&circuit, &paths.magic)?;
+        generator::generate_legacy_micro_ops(&naive_program, &paths.naive)?;
+        generator::generate_legacy_micro_ops(&compact_program, &paths.compact)?;
+        legacy.extend(paths.all());
+        println!("[LEGACY] {}: stats, Verilog and micro-ops rewritten in the C implementation's layout", circuit.bench_name);
+    }
+
+    let options: Vec<String> = env::args().skip(1).collect();
+    let header = ArtifactHeader::new(netlist_path, &options, &circuit)?;
+    for path in written.iter().filter(|path| !legacy.contains(&path.as_path())) {
+        header.stamp(path)?;
+    }
+    if let Some(bundle) = output.bundle_path(&circuit.bench_name) {
+        let root = output.output_dir();
+        let files: Vec<(String, &Path)> = written.iter()
+            .map(|path| {
+                let name = path.strip_prefix(&root).unwrap_or(path);
+                (name.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"), path.as_path())
+            })
+            .collect();
+        output::create_parent(&bundle)?;
+        let manifest = archive::write_archive(&bundle, &circuit.bench_name, &header, &files)?;
+        println!("Bundle written to: {} ({} files)", bundle.display(), manifest.files.len());
+    }
 
     let total_time = start_time.elapsed();
     info!("Processing complete for {} in {:?}", circuit.bench_name, total_time);
+    print_diagnostics(&circuit.bench_name, &circuit.diagnostics);
+
+    Ok(ProcessOutcome::Written(Box::new(circuit), Box::new(metrics)))
+}
+
+// Runs `process` in a staging directory on copies of the netlist and of the
+// files its options read, then packs them with the artifacts, the console
+// output and a manifest. The bundled command reruns it from the unpacked bundle
+fn make_repro(netlist: &Path, bundle_path: Option<&Path>, options: &[String]) -> Result<()> {
+    // The options are checked as `process` would take them
+    let mut args = vec!["delphi".to_string(), "process".to_string(), netlist.display().to_string()];
+    args.extend(options.iter().cloned());
+    let Commands::Process { output, pipeline, .. } = parse_cli(&args)?.command else {
+        unreachable!("parsed as a process command");
+    };
+    ensure!(output.output.is_none(), "A bundle keeps its artifacts under artifacts/; leave out -o");
+
+    let bench_name = parser::extract_bench_name(netlist);
+    let bundle_path = bundle_path.map_or_else(|| PathBuf::from(format!("{}_repro.tar.gz", bench_name)), Path::to_path_buf);
+    let file_name = |path: &Path| -> Result<String> {
+        path.file_name().and_then(|n| n.to_str()).map(String::from)
+            .context(format!("{:?} has no file name", path))
+    };
+
+    let mut bundle = Bundle::new(env::temp_dir().join(format!("delphi-repro-{}", std::process::id())))?;
+    let input = format!("input/{}", file_name(netlist)?);
+    bundle.add_file(netlist, &input, Role::Input)?;
+    let mut moved = Vec::new();
+    for (kind, path) in [("tech", &pipeline.tech), ("parasitics", &pipeline.parasitics), ("dont_care", &pipeline.dont_care), ("input_groups", &pipeline.input_groups)] {
+        if let Some(path) = path {
+            let bundled = format!("config/{}/{}", kind, file_name(path)?);
+            bundle.add_file(path, &bundled, Role::Config)?;
+            moved.push((path.display().to_string(), bundled));
+        }
+    }
+
+    // Options naming those files, alone or as --flag=FILE, point into the bundle
+    let rewrite = |option: &String| {
+        for (from, to) in &moved {
+            if option == from {
+                return to.clone();
+            }
+            if let Some(flag) = option.strip_suffix(from.as_str()).filter(|f| f.starts_with("--") && f.ends_with('=')) {
+                return format!("{}{}", flag, to);
+            }
+        }
+        option.clone()
+    };
+    let mut command: Vec<String> = ["process", &input, "-o", "artifacts"].iter().map(|s| s.to_string()).collect();
+    command.extend(options.iter().map(rewrite));
+
+    let run = std::process::Command::new(env::current_exe()?)
+        .args(&command)
+        .current_dir(bundle.root())
+        .env("SOURCE_DATE_EPOCH", bundle.epoch().to_string())
+        .output()
+        .context("Failed to run process for the bundle")?;
+    if !run.status.success() {
+        fs::remove_dir_all(bundle.root())?;
+        bail!("process failed inside the bundle:\n{}", String::from_utf8_lossy(&run.stderr));
+    }
+    let mut log = run.stdout;
+    log.extend(run.stderr);
+    bundle.add_bytes("run.log", &log, Role::Log)?;
+    bundle.add_dir("artifacts", Role::Artifact)?;
+
+    let manifest = bundle.finish(command, options.to_vec(), &bundle_path)?;
+    println!("Repro bundle written to: {} ({} files, SOURCE_DATE_EPOCH={})",
+        bundle_path.display(), manifest.files.len() + 1, manifest.source_date_epoch);
+    println!("Rerun from the unpacked bundle with: SOURCE_DATE_EPOCH={} delphi {}",
+        manifest.source_date_epoch, manifest.command.join(" "));
+    Ok(())
+}
+
+fn simulate_netlist(
+    netlist_path: &Path,
+    top: Option<&str>,
+    vectors_path: Option<&Path>,
+    random: Option<usize>,
+    seed: u64,
+    check_mappings: Option<Primitive>,
+) -> Result<()> {
+    let mut circuit = Circuit::new();
+    parser::load_top(netlist_path, top, &mut circuit)
+        .context("Failed to parse netlist")?;
+
+    let vectors = if let Some(path) = vectors_path {
+        simulator::read_vectors(path)?
+    } else if let Some(count) = random {
+        simulator::random_vectors(circuit.num_inputs, count, seed)
+    } else {
+        simulator::exhaustive_vectors(circuit.num_inputs)
+            .context("Use --random N or --vectors FILE for wide circuits")?
+    };
+
+    let results = simulator::simulate_netlist(&circuit, &vectors)?;
+    let outputs = simulator::output_signals(&circuit);
+    println!("# inputs: x0..x{}  outputs: {}",
+        circuit.num_inputs.saturating_sub(1),
+        outputs.iter().map(|&o| signal_name(o)).collect::<Vec<_>>().join(" "));
+    for (vector, result) in vectors.iter().zip(&results) {
+        println!("{} -> {}", simulator::format_vector(vector), simulator::format_vector(result));
+    }
+
+    if let Some(primitive) = check_mappings {
+        // Mappings of the re-synthesized circuit are checked against the original netlist
+        if primitive == Primitive::Nand {
+            circuit = resynth::to_nand(&circuit).context("NAND re-synthesis failed")?;
+        }
+        scheduler::compute_asap_schedule(&mut circuit);
+        scheduler::ensure_scheduled(&circuit)?;
+        scheduler::compute_alap_schedule(&mut circuit);
+        scheduler::compute_list_schedule(&mut circuit);
+
+        let mut all_match = true;
+        for (name, naive) in [("naive", true), ("compact", false)] {
+            let mapping = if naive {
+                mapper::create_naive_mapping(&mut circuit)
+            } else {
+                mapper::create_compact_mapping(&mut circuit)
+            };
+            // The crossbar itself, then the program that runs it
+            let checks = [
+                ("mapping", simulator::simulate_mapping(&circuit, &mapping, &vectors)),
+                ("program", Program::from_mapping(&circuit, &mapping)
+                    .and_then(|program| simulator::simulate_program(&program, &vectors))),
+            ];
+            for (kind, simulated) in checks {
+                let (mismatches, differing) = match simulated {
+                    Ok(mapped) => {
+                        let pairs = || mapped.iter().zip(&results).filter(|(m, r)| m != r);
+                        // Outputs that differ on any vector
+                        let differing: Vec<usize> = (0..outputs.len())
+                            .filter(|&k| pairs().any(|(m, r)| m.get(k) != r.get(k)))
+                            .collect();
+                        (pairs().count(), differing)
+                    }
+                    Err(e) => {
+                        error!("{} {} could not be simulated: {}", name, kind, e);
+                        (vectors.len(), Vec::new())
+                    }
+                };
+                if mismatches == 0 {
+                    println!("{} {}: equivalent on {} vectors", name, kind, vectors.len());
+                } else {
+                    println!("{} {}: {} of {} vectors mismatch", name, kind, mismatches, vectors.len());
+                    for k in differing {
+                        let origin = circuit.origin(outputs[k]).map_or("no netlist line".to_string(), |o| o.to_string());
+                        println!("  output {} differs ({})", signal_name(outputs[k]), origin);
+                    }
+                    all_match = false;
+                }
+            }
+        }
+
+        if !all_match {
+            return Err(anyhow::anyhow!("Mapping does not match netlist simulation"));
+        }
+    }
+
+    Ok(())
+}
+
+fn analyze_variability(netlist_path: &Path, tech: &Technology, trials: usize, seed: u64, runs: usize, naive: bool) -> Result<()> {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist(netlist_path, &mut circuit)
+        .context("Failed to parse netlist")?;
+    parser::find_primary_inputs(&mut circuit);
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::ensure_scheduled(&circuit)?;
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule_for(&mut circuit, Some(tech));
+
+    let mapping = map_circuit(&mut circuit, naive);
+    let result = analysis::analyze_variability_runs(&mapping, tech, trials, seed, runs)?;
+    let report = &result.best;
+
+    let seeds = if runs > 1 { format!(", seed {} (best of {} runs)", result.best_seed, runs) } else { String::new() };
+    println!("# {}: {} mapping, profile {}, clock {:.2} ns, {} trials{}",
+        circuit.bench_name, if naive { "naive" } else { "compact" }, tech.name, report.clock_ns, report.trials, seeds);
+    println!("{:>5} {:>6} {:>6} {:>8} {:>9} {:>9}", "level", "ops", "cycles", "yield", "mean_ns", "p99_ns");
+    for level in &report.levels {
+        println!("{:>5} {:>6} {:>6} {:>8.4} {:>9.3} {:>9.3}",
+            output::level_number(level.level), level.ops, level.cycles, level.timing_yield, level.mean_ns, level.p99_ns);
+    }
+    println!("Program timing yield: {:.4}", report.program_yield);
+    if runs > 1 {
+        let (y, p) = (result.program_yield, result.p99_ns);
+        println!("Program timing yield over {} runs: best {:.4} (seed {}), median {:.4}, worst {:.4} (seed {})",
+            y.runs, y.best.1, y.best.0, y.median, y.worst.1, y.worst.0);
+        println!("Slowest level p99 over {} runs: best {:.3} ns (seed {}), median {:.3} ns, worst {:.3} ns (seed {})",
+            p.runs, p.best.1, p.best.0, p.median, p.worst.1, p.worst.0);
+    }
+
+    Ok(())
+}
+
+// Splits levels against both mappings; the compact placement survives the
+// naive split because splitting keeps the gate order
+fn enforce_activation_limits(circuit: &mut Circuit, limits: &ActivationLimits) {
+    let mut added = 0;
+    for naive in [false, true] {
+        let mapping = map_circuit(circuit, naive);
+        added += scheduler::split_overloaded_levels(circuit, &mapping, limits);
+    }
+    if added > 0 {
+        println!("[ACT] {}: split levels to meet activation limits ({} level(s) added)", circuit.bench_name, added);
+    }
+
+    for naive in [false, true] {
+        let mapping = map_circuit(circuit, naive);
+        for violation in analysis::check_activation(&mapping, limits) {
+            circuit.diagnostics.warn("scheduler", "activation",
+                format!("{} mapping, {} (a single gate cannot be split)", if naive { "naive" } else { "compact" }, violation));
+        }
+    }
+}
+
+fn check_activation(netlist_path: &Path, limits: &ActivationLimits, naive: bool, split: bool) -> Result<()> {
+    if !limits.is_set() {
+        return Err(anyhow::anyhow!("Give --wordline-limit and/or --bitline-limit"));
+    }
+
+    let mut circuit = Circuit::new();
+    parser::parse_netlist(netlist_path, &mut circuit)
+        .context("Failed to parse netlist")?;
+    parser::find_primary_inputs(&mut circuit);
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::ensure_scheduled(&circuit)?;
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule(&mut circuit);
+
+    let depth = circuit.max_asap;
+    let mut mapping = map_circuit(&mut circuit, naive);
+    if split {
+        scheduler::split_overloaded_levels(&mut circuit, &mapping, limits);
+        mapping = map_circuit(&mut circuit, naive);
+    }
+
+    let violations = analysis::check_activation(&mapping, limits);
+    for violation in &violations {
+        println!("{}", violation);
+    }
+    println!("{} mapping: {} level(s){}, {} violation(s)",
+        if naive { "naive" } else { "compact" }, circuit.max_asap,
+        if circuit.max_asap > depth { format!(" (split from {})", depth) } else { String::new() },
+        violations.len());
+
+    if !violations.is_empty() {
+        return Err(anyhow::anyhow!("{} activation limit violation(s) found", violations.len()));
+    }
+    Ok(())
+}
+
+// Summary table of the findings of a run, if there are any
+fn print_diagnostics(name: &str, diagnostics: &Diagnostics) {
+    if diagnostics.is_empty() {
+        return;
+    }
+    println!("[DIAG] {}: {}", name, diagnostics.summary());
+    print!("{}", diagnostics.table());
+}
+
+// A mapping moved off the technology's reserved lines, or an error if it does
+// not fit the free part of the array
+fn place_mapping(placement: Option<&Placement>, circuit: &mut Circuit, mapping: delphi::CrossbarMapping, name: &str) -> Result<delphi::CrossbarMapping> {
+    match placement {
+        Some(placement) => placement.apply(circuit, &mapping)
+            .context(format!("{} mapping does not fit the crossbar", name)),
+        None => Ok(mapping),
+    }
+}
+
+fn map_circuit(circuit: &mut Circuit, naive: bool) -> delphi::CrossbarMapping {
+    if naive {
+        mapper::create_naive_mapping(circuit)
+    } else {
+        mapper::create_compact_mapping(circuit)
+    }
+}
+
+fn report_switching_activity(netlist_path: &Path, passes: usize, seed: u64) -> Result<()> {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist(netlist_path, &mut circuit)
+        .context("Failed to parse netlist")?;
+    parser::find_primary_inputs(&mut circuit);
+
+    let activity = simulator::switching_activity(&circuit, passes, seed)?;
+    println!("# {} vectors, signal P(1) toggle-rate", passes * simulator::LANES);
+    for (signal, p_one, toggle) in activity {
+        println!("{:<8} {:.4} {:.4}", signal_name(signal), p_one, toggle);
+    }
 
     Ok(())
 }
@@ -1,378 +1,1205 @@
 //mapper/mod.rs
 use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
 
-use crate::{Circuit, CrossbarMapping, MemristiveGate, MAX_GATES, MAX_ROW};
+use anyhow::{Context, Result, bail, ensure};
+use clap::ValueEnum;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::gates::{GateKind, MapRule};
+use crate::program::{Cell, MicroOp, Program};
+use crate::schema::{self, Versioned};
+use crate::tech::Periphery;
+use crate::{signal_name, verify, Circuit, CrossbarMapping, MemristiveGate, TableGate, MAX_COL, MAX_GATES, MAX_ROW};
 
 pub fn create_naive_mapping(circuit: &mut Circuit) -> CrossbarMapping {
-    let mut mapping = CrossbarMapping::new();
-    
-    // Reset crossbar array
-    for i in 0..MAX_ROW {
-        for j in 0..mapping.crossbar[i].len() {
-            mapping.crossbar[i][j].value = -1;
-            mapping.crossbar[i][j].idx = -1;
-            mapping.crossbar[i][j].jdx = -1;
-            mapping.crossbar[i][j].asap_level = -1;
-            mapping.crossbar[i][j].fanin = 0;
+    Mapper::Naive.map(circuit)
+}
+
+pub fn create_compact_mapping(circuit: &mut Circuit) -> CrossbarMapping {
+    Mapper::Compact.map(circuit)
+}
+
+// How the compact mapper picks a gate's row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RowPolicy {
+    /// Each gate in its last operand's row
+    #[default]
+    LastOperand,
+    /// Rows partitioned to cut the fewest operand edges between them, each a copy
+    Mincut,
+}
+
+// The sequential mappers. `steps` places the inputs and then one gate per
+// step, in ASAP order, for callers that show or animate the mapping as it
+// grows; `map` runs the steps to the end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapper {
+    Naive,
+    Compact,
+}
+
+// A gate as a mapper placed it: its index in `circuit.gates`, sorted by ASAP
+// level by then, the cell it evaluates in and the copies of its operands
+// placed before it, in operand order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedGate {
+    pub gate: usize,
+    pub cell: Cell,
+    pub copies: Vec<Cell>,
+}
+
+// A mapping in progress; each step places the next gate
+pub struct MapSteps<'a> {
+    circuit: &'a mut Circuit,
+    mapper: Mapper,
+    mapping: CrossbarMapping,
+    next: usize,
+    // The naive mapper's row
+    row: usize,
+    // The compact mapper's first free column of each row
+    av_row: Vec<usize>,
+    runs: ClusterRuns,
+    // Gates an init state leaves unevaluated, and the gates left to place
+    skipped: HashSet<i32>,
+    left: usize,
+}
+
+impl Mapper {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mapper::Naive => "naive mapping",
+            Mapper::Compact => "compact mapping",
         }
     }
-    
-    // Reset gate mappings
-    for i in 0..circuit.num_gates {
-        circuit.gates[i].gate_map = None;
-    }
-    
-    // Sort gates by ASAP level
-    circuit.gates.sort_by(|a, b| a.asap_level.cmp(&b.asap_level));
-    
-    // Create inverse mapping for gate lookup by output
-    let mut inv_map = HashMap::new();
-    for i in 0..circuit.num_gates {
-        inv_map.insert(circuit.gates[i].out, i);
-    }
-    
-    // Handle case where there are no inputs
-    if circuit.num_inputs == 0 {
-        return mapping;
-    }
-    
-    // Map primary inputs to the first row of the crossbar
-    for j in 0..circuit.num_inputs {
-        // Map each primary input to the crossbar
-        mapping.crossbar[0][j].value = (MAX_GATES + j) as i32;
-        mapping.crossbar[0][j].idx = 0;
-        mapping.crossbar[0][j].jdx = j as i32;
+
+    pub fn map(self, circuit: &mut Circuit) -> CrossbarMapping {
+        self.steps(circuit).finish()
     }
-    
-    // Update max_jdx to reflect the number of inputs
-    mapping.max_jdx = (circuit.num_inputs - 1) as i32;
-    
-    // Map gates
-    for i in 0..circuit.num_gates {
-        // Increment the column index for the next gate
-        mapping.max_jdx += 1;
-        
-        let ip1 = circuit.gates[i].inputs[0];
-        let ip2 = if circuit.gates[i].fanin > 1 {
-            circuit.gates[i].inputs[1]
-        } else {
-            -1
-        };
-        
-        // Place the gate in the crossbar
-        mapping.crossbar[0][mapping.max_jdx as usize].fanin = circuit.gates[i].fanin;
-        mapping.crossbar[0][mapping.max_jdx as usize].value = circuit.gates[i].out;
-        mapping.crossbar[0][mapping.max_jdx as usize].jdx = mapping.max_jdx;
-        mapping.crossbar[0][mapping.max_jdx as usize].idx = 0; // All gates in row 0 for naive mapping
-        mapping.crossbar[0][mapping.max_jdx as usize].asap_level = circuit.gates[i].asap_level;
-        
-        // Create a boxed copy of the mapping for the gate
-        let gate_map = Box::new(mapping.crossbar[0][mapping.max_jdx as usize].clone());
-        circuit.gates[i].gate_map = Some(gate_map);
+
+    // Resets the gates' mappings, sorts them by ASAP level and places the
+    // primary inputs; the gates are left to the steps
+    pub fn steps(self, circuit: &mut Circuit) -> MapSteps<'_> {
+        let mut mapping = CrossbarMapping::new();
         
-        // Connect the first input
-        if ip1 >= MAX_GATES as i32 {
-            // Input is a primary input
-            let input_num = ip1 - MAX_GATES as i32;
-            if input_num < circuit.num_inputs as i32 {
-                let input_gate = mapping.crossbar[0][input_num as usize].clone();
-                mapping.crossbar[0][mapping.max_jdx as usize].inputs[0] = Some(Box::new(input_gate));
-            }
-        } else if ip1 > 0 {
-            // Input is a gate output
-            if let Some(&gate_idx) = inv_map.get(&ip1) {
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    let input_gate = (**gate_map).clone();
-                    mapping.crossbar[0][mapping.max_jdx as usize].inputs[0] = Some(Box::new(input_gate));
-                }
-            }
+        // Reset gate mappings
+        for i in 0..circuit.num_gates {
+            circuit.gates[i].gate_map = None;
         }
         
-        // Connect the second input for NOR gates
-        if circuit.gates[i].fanin > 1 && ip2 != -1 {
-            if ip2 >= MAX_GATES as i32 {
-                // Input is a primary input
-                let input_num = ip2 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    let input_gate = mapping.crossbar[0][input_num as usize].clone();
-                    mapping.crossbar[0][mapping.max_jdx as usize].inputs[1] = Some(Box::new(input_gate));
+        // Sort gates by ASAP level (also re-indexes gate lookup by output)
+        circuit.sort_gates_by_key(|g| g.asap_level);
+        
+        let runs = ClusterRuns::new(circuit);
+        let mut av_row = Vec::new();
+        let mut row = 0;
+        let mut skipped = HashSet::new();
+        // With no inputs there is nothing to place
+        if circuit.num_inputs > 0 {
+            match self {
+                Mapper::Naive => {
+                    // Everything sits in the first row that computes, row 0 unless it only stores
+                    row = circuit.regions.compute_row(0);
+                    mapping.max_idx = row as i32;
+                    
+                    // Map primary inputs to that row of the crossbar, each on its line
+                    for j in circuit.mapped_inputs() {
+                        let col = circuit.input_line(j);
+                        let cell = mapping.cell_mut(row, col);
+                        cell.value = (MAX_GATES + j) as i32;
+                        cell.idx = row as i32;
+                        cell.jdx = col as i32;
+                    }
+                    
+                    // Update max_jdx to reflect the number of inputs
+                    mapping.max_jdx = circuit.input_line_count() as i32 - 1;
                 }
-            } else if ip2 > 0 {
-                // Input is a gate output
-                if let Some(&gate_idx) = inv_map.get(&ip2) {
-                    if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                        let input_gate = (**gate_map).clone();
-                        mapping.crossbar[0][mapping.max_jdx as usize].inputs[1] = Some(Box::new(input_gate));
+                Mapper::Compact => {
+                    // Track available positions in each row
+                    av_row = vec![0; MAX_ROW];
+                    
+                    // Map primary inputs - each in its own row, on its line
+                    for i in circuit.mapped_inputs() {
+                        let row = circuit.input_line(i);
+                        let cell = mapping.cell_mut(row, 0);
+                        cell.value = (MAX_GATES + i) as i32;
+                        cell.idx = row as i32;
+                        cell.jdx = 0;
+                        av_row[row] = 1; // Set first available column to 1
+                    }
+                    
+                    // Max row index is the last primary input row
+                    mapping.max_idx = circuit.input_line_count() as i32 - 1;
+                    
+                    // Cells of the init state hold their signals already: gates
+                    // stay where they are held, and no later gate takes their columns
+                    skipped = circuit.init_state.unevaluated(circuit);
+                    let held: Vec<(i32, Cell)> = circuit.init_state.signals().collect();
+                    for (signal, at) in held {
+                        let (row, col) = (at.row as usize, at.col as usize);
+                        if signal >= MAX_GATES as i32 {
+                            mapping.cell_mut(row, col).held = true;
+                            continue;
+                        }
+                        let cell = MemristiveGate {
+                            value: signal,
+                            idx: row as i32,
+                            jdx: col as i32,
+                            asap_level: 0,
+                            held: true,
+                            ..Default::default()
+                        };
+                        circuit.gates[circuit.drivers[&signal]].gate_map = Some(cell.operand());
+                        *mapping.cell_mut(row, col) = cell;
+                        av_row[row] = max(av_row[row], col + 1);
+                        mapping.max_idx = max(mapping.max_idx, row as i32);
+                        mapping.max_jdx = max(mapping.max_jdx, col as i32);
                     }
                 }
             }
         }
+        let left = if circuit.num_inputs == 0 { 0 } else { circuit.num_gates - skipped.len() };
+        MapSteps { circuit, mapper: self, mapping, next: 0, row, av_row, runs, skipped, left }
     }
-    
-    mapping
 }
 
-pub fn create_compact_mapping(circuit: &mut Circuit) -> CrossbarMapping {
-    let mut mapping = CrossbarMapping::new();
-    
-    // Reset crossbar array
-    for i in 0..MAX_ROW {
-        for j in 0..mapping.crossbar[i].len() {
-            mapping.crossbar[i][j].value = -1;
-            mapping.crossbar[i][j].idx = -1;
-            mapping.crossbar[i][j].jdx = -1;
-            mapping.crossbar[i][j].asap_level = -1;
-            mapping.crossbar[i][j].fanin = 0;
-            mapping.crossbar[i][j].is_copy = false;
-        }
-    }
-    
-    // Reset gate mappings
-    for i in 0..circuit.num_gates {
-        circuit.gates[i].gate_map = None;
+impl MapSteps<'_> {
+    // The cells placed so far
+    pub fn mapping(&self) -> &CrossbarMapping {
+        &self.mapping
     }
-    
-    // Sort gates by ASAP level
-    circuit.gates.sort_by(|a, b| a.asap_level.cmp(&b.asap_level));
-    
-    // Create inverse mapping for gate lookup by output
-    let mut inv_map = HashMap::new();
-    for i in 0..circuit.num_gates {
-        inv_map.insert(circuit.gates[i].out, i);
+
+    pub fn circuit(&self) -> &Circuit {
+        self.circuit
     }
-    
-    // Handle case where there are no inputs
-    if circuit.num_inputs == 0 {
-        return mapping;
+
+    // Gates left to place
+    pub fn remaining(&self) -> usize {
+        self.left
     }
-    
-    // Track available positions in each row
-    let mut av_row = vec![0; MAX_ROW];
-    
-    // Map primary inputs - each in its own row
-    for i in 0..circuit.num_inputs {
-        mapping.crossbar[i][0].value = (MAX_GATES + i) as i32;
-        mapping.crossbar[i][0].idx = i as i32;
-        mapping.crossbar[i][0].jdx = 0;
-        av_row[i] = 1; // Set first available column to 1
+
+    // Places the gates left and checks the mapping as a whole
+    pub fn finish(mut self) -> CrossbarMapping {
+        for _ in self.by_ref() {}
+        if self.circuit.num_inputs > 0 {
+            verify::check_placement(self.circuit, &self.mapping);
+            note_capacity(self.circuit, &self.mapping, self.mapper.name(), Placement::default().lines());
+        }
+        self.mapping
     }
-    
-    // Max row index is the last primary input row
-    mapping.max_idx = if circuit.num_inputs > 0 {
-        (circuit.num_inputs - 1) as i32
-    } else {
-        0
-    };
-    
-    // Map gates
-    for i in 0..circuit.num_gates {
-        let ip1 = circuit.gates[i].inputs[0];
+
+    fn place_naive(&mut self, i: usize) -> PlacedGate {
+        // Move on to the next column that computes
+        self.mapping.max_jdx = self.circuit.regions.compute_col(self.mapping.max_jdx as usize + 1) as i32;
         
-        if circuit.gates[i].fanin == 1 {
-            // NOT Gate
-            let map_idx = if ip1 >= MAX_GATES as i32 {
-                // Input is a primary input - use its row
-                let input_num = ip1 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    input_num as usize
-                } else {
-                    0
+        // Place the gate in the crossbar
+        let row = self.row;
+        let col = self.mapping.max_jdx as usize;
+        self.mapping.place(MemristiveGate {
+            fanin: self.circuit.gates[i].fanin,
+            value: self.circuit.gates[i].out,
+            jdx: col as i32,
+            idx: row as i32, // All gates in one row for naive mapping
+            asap_level: self.circuit.gates[i].asap_level,
+            ..Default::default()
+        });
+        
+        // Create a boxed copy of the mapping for the gate
+        let gate_map = self.mapping.cell(row, col).operand();
+        self.circuit.gates[i].gate_map = Some(gate_map);
+        
+        // Connect the inputs, one for a NOT and all of them for a NOR
+        for k in 0..self.circuit.gates[i].fanin {
+            let ip = self.circuit.gates[i].inputs[k];
+            if ip >= MAX_GATES as i32 {
+                // Input is a primary input
+                let input_num = ip - MAX_GATES as i32;
+                if input_num < self.circuit.num_inputs as i32 {
+                    let input_gate = self.mapping.cell(row, self.circuit.input_line(input_num as usize)).operand();
+                    self.mapping.cell_mut(row, col).inputs[k] = Some(input_gate);
+                }
+            } else if let Some(&gate_idx) = self.circuit.drivers.get(&ip) {
+                // Input is a gate output (temporaries from line expansion are negative)
+                if let Some(ref gate_map) = self.circuit.gates[gate_idx].gate_map {
+                    let input_gate = gate_map.operand();
+                    self.mapping.cell_mut(row, col).inputs[k] = Some(input_gate);
                 }
-            } else if let Some(&gate_idx) = inv_map.get(&ip1) {
-                // Input is a gate - use its row
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    gate_map.idx as usize
+            }
+        }
+        PlacedGate { gate: i, cell: Cell { row: row as u32, col: col as u32 }, copies: Vec::new() }
+    }
+
+    fn place_compact(&mut self, i: usize) -> PlacedGate {
+        let ip1 = self.circuit.gates[i].inputs[0];
+        let fanin = self.circuit.gates[i].fanin;
+        let mut copied = Vec::new();
+        
+        // Get row and column of each input
+        let positions: Vec<(usize, usize)> = self.circuit.gates[i].inputs[..fanin].iter()
+            .map(|&ip| {
+                if ip >= MAX_GATES as i32 {
+                    let input_num = ip - MAX_GATES as i32;
+                    // Primary inputs are always in column 0
+                    if input_num < self.circuit.num_inputs as i32 {
+                        (self.circuit.input_line(input_num as usize), 0)
+                    } else {
+                        (0, 0)
+                    }
+                } else if let Some(&gate_idx) = self.circuit.drivers.get(&ip) {
+                    if let Some(ref gate_map) = self.circuit.gates[gate_idx].gate_map {
+                        (gate_map.idx as usize, gate_map.jdx as usize)
+                    } else {
+                        (0, 0)
+                    }
                 } else {
-                    0
+                    (0, 0)
                 }
-            } else {
-                0
-            };
+            })
+            .collect();
+        
+        // A row a placement pass chose, unless it only stores
+        let out = self.circuit.gates[i].out;
+        let planned = self.circuit.planned_rows.get(&out).copied()
+            .filter(|&row| !self.circuit.regions.storage_row(row));
+        // Operands further than --max-span from the gate's column are copied next to it
+        let max_span = self.circuit.max_span;
+        let far = |col: usize, gate: usize| max_span.is_some_and(|max| col.abs_diff(gate) > max);
+        let not_far = || {
+            let row = positions[0].0;
+            !far(positions[0].1, self.runs.gate_column(out, row, 0, 1, &self.circuit.regions, &self.av_row))
+        };
+        let rule = GateKind::of(self.circuit.primitive, fanin).spec().map;
+        if rule == MapRule::OperandRow && !self.circuit.regions.storage_row(positions[0].0) && planned.is_none_or(|row| row == positions[0].0) && not_far() {
+            // NOT Gate, in its input's row
+            let map_idx = positions[0].0;
             
             // Get next available column in the row
-            let map_jdx = av_row[map_idx];
-            av_row[map_idx] += 1;
+            let (_, map_jdx) = self.runs.columns(self.circuit.gates[i].out, map_idx, 0, 1, &self.circuit.regions, &mut self.av_row);
             
             // Create the NOT gate
-            let mut mem_gate = MemristiveGate::default();
-            mem_gate.idx = map_idx as i32;
-            mem_gate.jdx = map_jdx as i32;
-            mem_gate.fanin = 1;
-            mem_gate.value = circuit.gates[i].out;
-            mem_gate.asap_level = circuit.gates[i].asap_level;
+            let mut mem_gate = MemristiveGate {
+                idx: map_idx as i32,
+                jdx: map_jdx as i32,
+                fanin: 1,
+                value: self.circuit.gates[i].out,
+                asap_level: self.circuit.gates[i].asap_level,
+                ..Default::default()
+            };
             
             // Connect input
             if ip1 >= MAX_GATES as i32 {
                 let input_num = ip1 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    let input_gate = mapping.crossbar[input_num as usize][0].clone();
-                    mem_gate.inputs[0] = Some(Box::new(input_gate));
+                if input_num < self.circuit.num_inputs as i32 {
+                    let input_gate = self.mapping.cell(self.circuit.input_line(input_num as usize), 0).operand();
+                    mem_gate.inputs[0] = Some(input_gate);
                 }
-            } else if let Some(&gate_idx) = inv_map.get(&ip1) {
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    let input_gate = (**gate_map).clone();
-                    mem_gate.inputs[0] = Some(Box::new(input_gate));
+            } else if let Some(&gate_idx) = self.circuit.drivers.get(&ip1) {
+                if let Some(ref gate_map) = self.circuit.gates[gate_idx].gate_map {
+                    let input_gate = gate_map.operand();
+                    mem_gate.inputs[0] = Some(input_gate);
                 }
             }
             
             // Place gate in crossbar and update gate mapping
-            mapping.crossbar[map_idx][map_jdx] = mem_gate.clone();
-            circuit.gates[i].gate_map = Some(Box::new(mem_gate));
+            self.circuit.gates[i].gate_map = Some(mem_gate.operand());
+            self.mapping.place(mem_gate);
             
             // Update max_jdx if needed
-            if map_jdx as i32 > mapping.max_jdx {
-                mapping.max_jdx = map_jdx as i32;
+            if map_jdx as i32 > self.mapping.max_jdx {
+                self.mapping.max_jdx = map_jdx as i32;
             }
+            PlacedGate { gate: i, cell: Cell { row: map_idx as u32, col: map_jdx as u32 }, copies: copied }
         } else {
-            // NOR Gate
-            let ip2 = circuit.gates[i].inputs[1];
-            
-            // Get row and column of first input
-            let temp_idx = if ip1 >= MAX_GATES as i32 {
-                let input_num = ip1 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    input_num as usize
-                } else {
-                    0
-                }
-            } else if let Some(&gate_idx) = inv_map.get(&ip1) {
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    gate_map.idx as usize
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
-            
-            let temp_jdx = if ip1 >= MAX_GATES as i32 {
-                0 // Primary inputs are always in column 0
-            } else if let Some(&gate_idx) = inv_map.get(&ip1) {
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    gate_map.jdx as usize
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
+            // NOR Gate, or a NOT whose input sits in a storage row, that
+            // was planned elsewhere or that is too far from its input
             
-            // Get row and column of second input
-            let temp_udx = if ip2 >= MAX_GATES as i32 {
-                let input_num = ip2 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    input_num as usize
-                } else {
-                    0
-                }
-            } else if let Some(&gate_idx) = inv_map.get(&ip2) {
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    gate_map.idx as usize
-                } else {
-                    0
+            // The NOR goes to its planned row or else its last input's row,
+            // after copies of the inputs on other rows; it reserves a column
+            // per input either way. Rows that only store take no gates: the
+            // last input in a row that computes decides, or the emptiest such
+            // row if none is
+            let emptiest = || self.circuit.regions.emptiest_row(&self.av_row, self.mapping.max_idx as usize);
+            let mut map_idx = planned.or_else(|| positions.iter().rev()
+                    .map(|&(row, _)| row)
+                    .find(|&row| !self.circuit.regions.storage_row(row)))
+                .unwrap_or_else(emptiest);
+            // Each copy moves the gate a column further from the operands in its row
+            let copies_in = |row: usize| {
+                let mut copy: Vec<bool> = positions.iter().map(|&(r, _)| r != row).collect();
+                while max_span.is_some() {
+                    let copies = copy.iter().filter(|&&c| c).count();
+                    let gate = self.runs.gate_column(out, row, copies, fanin, &self.circuit.regions, &self.av_row);
+                    for (k, &(_, col)) in positions.iter().enumerate() {
+                        copy[k] |= far(col, gate);
+                    }
+                    if copy.iter().filter(|&&c| c).count() == copies {
+                        return (copy, gate);
+                    }
                 }
-            } else {
-                0
+                (copy, 0)
             };
+            let (mut copy, gate) = copies_in(map_idx);
+            // Copies a span forces can fill a row; the gate then takes the
+            // emptiest row instead, unless a pass planned it
+            if max_span.is_some() && planned.is_none() && gate >= MAX_COL {
+                map_idx = emptiest();
+                copy = copies_in(map_idx).0;
+            }
+            let copies = copy.iter().filter(|&&c| c).count();
+            let (mut map_jdx, gate_jdx) = self.runs.columns(out, map_idx, copies, fanin, &self.circuit.regions, &mut self.av_row);
             
-            let temp_vdx = if ip2 >= MAX_GATES as i32 {
-                0 // Primary inputs are always in column 0
-            } else if let Some(&gate_idx) = inv_map.get(&ip2) {
-                if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                    gate_map.jdx as usize
-                } else {
-                    0
+            let mut operands = Vec::with_capacity(fanin);
+            for (k, &(row, col)) in positions.iter().enumerate() {
+                if !copy[k] {
+                    operands.push(self.mapping.cell(row, col).operand());
+                    continue;
                 }
-            } else {
-                0
-            };
-            
-            // Decide where to place the NOR gate
-            let (map_idx, map_jdx) = if temp_idx == temp_udx {
-                // Both inputs are on the same row
-                let idx = temp_idx;
-                let jdx = av_row[idx];
-                av_row[idx] += 1;
-                (idx, jdx)
-            } else {
-                // Inputs are on different rows - create a copy of first input on second input's row
-                let idx = temp_udx;
-                let jdx = av_row[idx];
-                av_row[idx] += 1;
                 
                 // Create copy gate
-                let mut copy_gate = MemristiveGate::default();
-                copy_gate.is_copy = true;
+                let ip = self.circuit.gates[i].inputs[k];
+                let mut copy_gate = MemristiveGate {
+                    is_copy: true,
+                    idx: map_idx as i32,
+                    jdx: map_jdx as i32,
+                    ..Default::default()
+                };
                 
                 // Copy points to the original gate
-                if ip1 >= MAX_GATES as i32 || (ip1 > 0 && inv_map.contains_key(&ip1)) {
-                    let input_gate = if ip1 >= MAX_GATES as i32 {
-                        let input_num = ip1 - MAX_GATES as i32;
-                        if input_num < circuit.num_inputs as i32 {
-                            mapping.crossbar[input_num as usize][0].clone()
-                        } else {
-                            mapping.crossbar[0][0].clone() // Fallback
-                        }
-                    } else if let Some(&gate_idx) = inv_map.get(&ip1) {
-                        if let Some(ref gate_map) = circuit.gates[gate_idx].gate_map {
-                            (**gate_map).clone()
-                        } else {
-                            mapping.crossbar[0][0].clone() // Fallback
-                        }
-                    } else {
-                        mapping.crossbar[0][0].clone() // Fallback
-                    };
-                    
-                    copy_gate.inputs[0] = Some(Box::new(input_gate));
-                    copy_gate.value = ip1;
+                if ip >= MAX_GATES as i32 || self.circuit.drivers.contains_key(&ip) {
+                    copy_gate.inputs[0] = Some(self.mapping.cell(row, col).operand());
+                    copy_gate.value = ip;
                 }
                 
-                copy_gate.idx = idx as i32;
-                copy_gate.jdx = jdx as i32;
-                
                 // Place copy gate in crossbar
-                mapping.crossbar[idx][jdx] = copy_gate;
-                
-                // NOR gate will be placed right after the copy
-                (idx, av_row[idx])
-            };
-            
-            // Increment available column counter for the chosen row
-            av_row[map_idx] += 1;
-            
-            // Create NOR gate
-            let mut mem_gate = MemristiveGate::default();
-            mem_gate.asap_level = circuit.gates[i].asap_level;
-            mem_gate.value = circuit.gates[i].out;
-            mem_gate.idx = map_idx as i32;
-            mem_gate.jdx = map_jdx as i32;
-            mem_gate.fanin = 2;
+                self.mapping.place(copy_gate);
+                copied.push(Cell { row: map_idx as u32, col: map_jdx as u32 });
+                operands.push(self.mapping.cell(map_idx, map_jdx).operand());
+                map_jdx += 1;
+            }
+            // Past any storage columns
+            let map_jdx = gate_jdx;
             
-            // Connect inputs based on placement scenario
-            if temp_idx == temp_udx {
-                // Both inputs on same row - connect directly
-                let input1 = mapping.crossbar[temp_idx][temp_jdx].clone();
-                let input2 = mapping.crossbar[temp_udx][temp_vdx].clone();
-                mem_gate.inputs[0] = Some(Box::new(input1));
-                mem_gate.inputs[1] = Some(Box::new(input2));
-            } else {
-                // One input was copied - use the copy and the original second input
-                let input1 = mapping.crossbar[map_idx][map_jdx - 1].clone(); // The copy
-                let input2 = mapping.crossbar[temp_udx][temp_vdx].clone();
-                mem_gate.inputs[0] = Some(Box::new(input1));
-                mem_gate.inputs[1] = Some(Box::new(input2));
+            // Create NOR gate, reading the copies and the inputs already in its row
+            let mut mem_gate = MemristiveGate {
+                asap_level: self.circuit.gates[i].asap_level,
+                value: self.circuit.gates[i].out,
+                idx: map_idx as i32,
+                jdx: map_jdx as i32,
+                fanin,
+                ..Default::default()
+            };
+            for (input, operand) in mem_gate.inputs.iter_mut().zip(operands) {
+                *input = Some(operand);
             }
             
             // Place gate in crossbar and update gate mapping
-            mapping.crossbar[map_idx][map_jdx] = mem_gate.clone();
-            circuit.gates[i].gate_map = Some(Box::new(mem_gate));
+            self.circuit.gates[i].gate_map = Some(mem_gate.operand());
+            self.mapping.place(mem_gate);
             
             // Update max dimensions
-            mapping.max_idx = max(mapping.max_idx, map_idx as i32);
-            mapping.max_jdx = max(mapping.max_jdx, map_jdx as i32);
+            self.mapping.max_idx = max(self.mapping.max_idx, map_idx as i32);
+            self.mapping.max_jdx = max(self.mapping.max_jdx, map_jdx as i32);
+            PlacedGate { gate: i, cell: Cell { row: map_idx as u32, col: map_jdx as u32 }, copies: copied }
+        }
+    }
+}
+
+impl Iterator for MapSteps<'_> {
+    type Item = PlacedGate;
+
+    fn next(&mut self) -> Option<PlacedGate> {
+        if self.remaining() == 0 {
+            return None;
+        }
+        while self.skipped.contains(&self.circuit.gates[self.next].out) {
+            self.next += 1;
+        }
+        let i = self.next;
+        self.next += 1;
+        self.left -= 1;
+        let placed = match self.mapper {
+            Mapper::Naive => self.place_naive(i),
+            Mapper::Compact => self.place_compact(i),
+        };
+        // A gate landing in a cell already at logic 1 needs no RESET
+        if self.mapper == Mapper::Compact && self.circuit.init_state.is_set(placed.cell) {
+            self.mapping.cell_mut(placed.cell.row as usize, placed.cell.col as usize).state = 1;
+        }
+        Some(placed)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining(), Some(self.remaining()))
+    }
+}
+
+impl ExactSizeIterator for MapSteps<'_> {}
+
+// Share of the rows or columns available to a mapping past which it is
+// reported as close to filling the crossbar
+pub const NEAR_CAPACITY: f64 = 0.9;
+
+fn note_capacity(circuit: &mut Circuit, mapping: &CrossbarMapping, name: &str, (rows, cols): (usize, usize)) {
+    let used = [("rows", mapping.max_idx.max(0) as usize + 1, rows), ("columns", mapping.max_jdx.max(0) as usize + 1, cols)];
+    for (lines, used, available) in used {
+        if used as f64 > NEAR_CAPACITY * available as f64 {
+            circuit.diagnostics.warn("mapper", "near-capacity",
+                format!("The {} uses {} of {} {}", name, used, available, lines));
+        }
+    }
+}
+
+// Copy cells of a compact mapping before and after `reuse_copies`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CopyReuse {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl CopyReuse {
+    pub fn reused(&self) -> usize {
+        self.before - self.after
+    }
+}
+
+// Post-pass over a compact mapping: a NOR with an operand copied into a row
+// already holding a copy of that signal reads the earlier copy, and its own
+// copy cell is cleared. Copy cells are never written again, so an earlier
+// copy stays live for the rest of the program. Gates are visited level by
+// level in row-major order, the order in which the program writes the copies
+pub fn reuse_copies(circuit: &mut Circuit, mapping: &mut CrossbarMapping) -> CopyReuse {
+    let count = |mapping: &CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    let before = count(mapping);
+
+    let mut held: HashMap<(i32, i32), (usize, usize)> = HashMap::new();
+    let levels: Vec<(i32, Vec<(usize, usize)>)> = mapping.cells_by_level().collect();
+    for (i, j) in levels.into_iter().flat_map(|(_, cells)| cells) {
+        for k in 0..mapping.cell(i, j).fanin {
+            let Some(copy) = mapping.cell(i, j).inputs[k].as_deref().filter(|c| c.is_copy) else { continue };
+            let at = (copy.idx as usize, copy.jdx as usize);
+            let earlier = *held.entry((copy.idx, copy.value)).or_insert(at);
+            if earlier == at {
+                continue;
+            }
+            // An earlier copy past the span limit stays unused, and later
+            // readers turn to this one
+            if circuit.max_span.is_some_and(|max| j.abs_diff(earlier.1) > max) {
+                held.insert((copy.idx, copy.value), at);
+                continue;
+            }
+
+            let operand = mapping.cell(earlier.0, earlier.1).operand();
+            let cell = mapping.cell_mut(i, j);
+            cell.inputs[k] = Some(operand);
+            let (out, placed) = (cell.value, cell.operand());
+            if let Some(&gate) = circuit.drivers.get(&out) {
+                circuit.gates[gate].gate_map = Some(placed);
+            }
+            *mapping.cell_mut(at.0, at.1) = MemristiveGate::default();
         }
     }
+
+    CopyReuse { before, after: count(mapping) }
+}
+
+// A cell the compact mapper writes, named by what it holds: a primary input
+// row, the cell of the gate at a position in ASAP order, or the copy of that
+// gate's first operand
+#[derive(Debug, Clone, Copy)]
+enum CellRef {
+    Input(usize),
+    Gate(usize),
+    Copy(usize),
+}
+
+// Cells a gate or copy reads, in operand order
+type Operands = [Option<CellRef>; 2];
+
+// Levels narrower than this are assigned rows on the calling thread
+const PARALLEL_LEVEL_MIN: usize = 256;
+
+// Row and reserved columns of a gate; `copy` is set for NORs whose operands
+// sit in different rows
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    row: usize,
+    col: usize,
+    copy: Option<usize>,
+}
+
+// Same crossbar as `create_compact_mapping`: cell positions come from a
+// `CompactPlan`, after which every cell is built independently in parallel
+pub fn create_compact_mapping_parallel(circuit: &mut Circuit) -> CrossbarMapping {
+    let mut mapping = CrossbarMapping::new();
+    
+    // Reset gate mappings
+    for i in 0..circuit.num_gates {
+        circuit.gates[i].gate_map = None;
+    }
     
+    // Sort gates by ASAP level (also re-indexes gate lookup by output)
+    circuit.sort_gates_by_key(|g| g.asap_level);
+    
+    if circuit.num_inputs == 0 {
+        return mapping;
+    }
+    
+    // Malformed netlists hit the sequential mapper's fallbacks, which depend
+    // on placement order; NORs wider than two inputs are also left to it
+    let Some(plan) = CompactPlan::new(circuit) else {
+        return create_compact_mapping(circuit);
+    };
+    
+    (mapping.max_idx, mapping.max_jdx) = plan.dimensions();
+    let cells: Vec<(Option<MemristiveGate>, MemristiveGate)> = (0..circuit.num_gates)
+        .into_par_iter()
+        .map(|i| (plan.copy_cell(i), plan.gate_cell(i)))
+        .collect();
+    
+    for i in circuit.mapped_inputs() {
+        let cell = mapping.cell_mut(circuit.input_line(i), 0);
+        cell.value = (MAX_GATES + i) as i32;
+        cell.idx = circuit.input_line(i) as i32;
+        cell.jdx = 0;
+    }
+    
+    for (i, (copy, cell)) in cells.into_iter().enumerate() {
+        if let Some(copy) = copy {
+            mapping.place(copy);
+        }
+        circuit.gates[i].gate_map = Some(cell.operand());
+        mapping.place(cell);
+    }
+    
+    verify::check_placement(circuit, &mapping);
+    note_capacity(circuit, &mapping, "compact mapping", Placement::default().lines());
     mapping
-}
\ No newline at end of file
+}
+
+// Where the compact mapper puts every cell, worked out without building the
+// crossbar: rows are assigned level by level with the gates of a level spread
+// across threads, then columns are reserved per row in ASAP order. Any cell
+// can then be built on its own
+pub struct CompactPlan<'a> {
+    gates: &'a [TableGate],
+    // Rows the mapped primary inputs take
+    input_rows: usize,
+    // Row of each primary input
+    lines: Vec<usize>,
+    sources: Vec<Operands>,
+    slots: Vec<Slot>,
+}
+
+impl<'a> CompactPlan<'a> {
+    // Expects gates sorted by ASAP level, as the mappers leave them. None for
+    // malformed netlists, whose placement only the sequential mapper defines,
+    // for NORs wider than two inputs, for crossbars with storage regions, for
+    // rows chosen by a placement pass, for init states and for span limits
+    pub fn new(circuit: &'a Circuit) -> Option<Self> {
+        if circuit.num_inputs == 0 || !circuit.regions.is_empty() || !circuit.planned_rows.is_empty()
+            || !circuit.init_state.is_empty() || circuit.max_span.is_some()
+        {
+            return None;
+        }
+        let sources = operand_sources(circuit)?;
+        let gates = &circuit.gates[..circuit.num_gates];
+        let lines: Vec<usize> = (0..circuit.num_inputs).map(|n| circuit.input_line(n)).collect();
+        
+        // Rows: a NOT joins its operand's row and a NOR its second operand's
+        // row. Operands are at earlier levels, so a level's gates are independent
+        let mut rows = vec![0; gates.len()];
+        let mut start = 0;
+        for level in gates.chunk_by(|a, b| a.asap_level == b.asap_level) {
+            let (done, current) = rows.split_at_mut(start);
+            let assign = |(k, row): (usize, &mut usize)| {
+                let [ip1, ip2] = sources[start + k];
+                *row = operand_row(&lines, done, ip2.or(ip1).unwrap_or(CellRef::Input(0)));
+            };
+            if level.len() >= PARALLEL_LEVEL_MIN {
+                current[..level.len()].par_iter_mut().enumerate().for_each(assign);
+            } else {
+                current[..level.len()].iter_mut().enumerate().for_each(assign);
+            }
+            start += level.len();
+        }
+        
+        // Column reservation in ASAP order; a NOR takes two columns, holding
+        // the copy of its first operand when that operand is in another row
+        let mut av_row = vec![1; circuit.num_inputs];
+        let mut runs = ClusterRuns::new(circuit);
+        let slots = (0..gates.len())
+            .map(|i| {
+                let row = rows[i];
+                match sources[i] {
+                    [Some(ip1), Some(_)] => {
+                        let copied = operand_row(&lines, &rows, ip1) != row;
+                        let (first, col) = runs.columns(gates[i].out, row, copied as usize, 2, &circuit.regions, &mut av_row);
+                        Slot { row, col, copy: copied.then_some(first) }
+                    }
+                    _ => {
+                        let (_, col) = runs.columns(gates[i].out, row, 0, 1, &circuit.regions, &mut av_row);
+                        Slot { row, col, copy: None }
+                    }
+                }
+            })
+            .collect();
+        
+        Some(Self { gates, input_rows: circuit.input_line_count(), lines, sources, slots })
+    }
+    
+    // `max_idx` and `max_jdx` of the planned crossbar
+    pub fn dimensions(&self) -> (i32, i32) {
+        let mut max_idx = self.input_rows as i32 - 1;
+        let mut max_jdx = 0;
+        for (gate, slot) in self.gates.iter().zip(&self.slots) {
+            if gate.fanin > 1 {
+                max_idx = max(max_idx, slot.row as i32);
+            }
+            max_jdx = max(max_jdx, slot.col as i32);
+        }
+        (max_idx, max_jdx)
+    }
+    
+    // Gate positions (in ASAP order) at each level, row-major within a level
+    pub fn levels(&self) -> impl Iterator<Item = (i32, Vec<usize>)> + '_ {
+        let mut start = 0;
+        self.gates.chunk_by(|a, b| a.asap_level == b.asap_level).map(move |level| {
+            let mut positions: Vec<usize> = (start..start + level.len()).collect();
+            positions.sort_unstable_by_key(|&i| (self.slots[i].row, self.slots[i].col));
+            start += level.len();
+            (level[0].asap_level, positions)
+        })
+    }
+    
+    // Primary input n, in its row of column 0
+    pub fn input_cell(&self, n: usize) -> MemristiveGate {
+        self.bare_cell(CellRef::Input(n))
+    }
+
+    pub fn gate_cell(&self, i: usize) -> MemristiveGate {
+        self.placed_cell(CellRef::Gate(i))
+    }
+    
+    // The copy placed ahead of a NOR whose operands sit in different rows
+    pub fn copy_cell(&self, i: usize) -> Option<MemristiveGate> {
+        self.slots[i].copy.map(|_| self.placed_cell(CellRef::Copy(i)))
+    }
+
+    // A cell's own fields, without operands
+    fn bare_cell(&self, cell: CellRef) -> MemristiveGate {
+        match cell {
+            CellRef::Input(n) => MemristiveGate {
+                value: (MAX_GATES + n) as i32,
+                idx: self.lines[n] as i32,
+                jdx: 0,
+                ..Default::default()
+            },
+            CellRef::Gate(i) => MemristiveGate {
+                idx: self.slots[i].row as i32,
+                jdx: self.slots[i].col as i32,
+                fanin: self.gates[i].fanin,
+                value: self.gates[i].out,
+                asap_level: self.gates[i].asap_level,
+                ..Default::default()
+            },
+            CellRef::Copy(i) => MemristiveGate {
+                is_copy: true,
+                value: self.gates[i].inputs[0],
+                idx: self.slots[i].row as i32,
+                jdx: self.slots[i].copy.unwrap_or_default() as i32,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn operands(&self, cell: CellRef) -> Operands {
+        match cell {
+            CellRef::Input(_) => [None, None],
+            CellRef::Gate(i) if self.slots[i].copy.is_some() => [Some(CellRef::Copy(i)), self.sources[i][1]],
+            CellRef::Gate(i) => self.sources[i],
+            CellRef::Copy(i) => [self.sources[i][0], None],
+        }
+    }
+
+    // The cell as the sequential mapper writes it: operands are `operand()`
+    // snapshots, i.e. the operand cells with their own operands detached
+    fn placed_cell(&self, cell: CellRef) -> MemristiveGate {
+        let mut placed = self.bare_cell(cell);
+        for (k, operand) in self.operands(cell).into_iter().flatten().enumerate() {
+            let mut snapshot = self.bare_cell(operand);
+            for (m, inner) in self.operands(operand).into_iter().flatten().enumerate() {
+                snapshot.inputs[m] = Some(Box::new(self.bare_cell(inner)));
+            }
+            placed.inputs[k] = Some(Box::new(snapshot));
+        }
+        placed
+    }
+}
+
+// Where each gate's operands live, or None if any operand is undriven, out of
+// range or not scheduled before the gate
+fn operand_sources(circuit: &Circuit) -> Option<Vec<Operands>> {
+    if circuit.num_inputs > MAX_ROW {
+        return None;
+    }
+    
+    let gates = &circuit.gates[..circuit.num_gates];
+    gates.iter().enumerate()
+        .map(|(i, gate)| {
+            let source = |ip: i32| {
+                if ip >= MAX_GATES as i32 {
+                    let input_num = (ip - MAX_GATES as i32) as usize;
+                    (input_num < circuit.num_inputs).then_some(CellRef::Input(input_num))
+                } else {
+                    let &j = circuit.drivers.get(&ip)?;
+                    (j < i && gates[j].asap_level < gate.asap_level).then_some(CellRef::Gate(j))
+                }
+            };
+            match gate.fanin {
+                1 => Some([Some(source(gate.inputs[0])?), None]),
+                2 => Some([Some(source(gate.inputs[0])?), Some(source(gate.inputs[1])?)]),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Runs of columns reserved for the clusters of a circuit: the first gate of a
+// cluster reserves a column per operand of every member, and the members
+// placed in the same row take their columns from it. A member that lands in
+// another row is placed as usual
+struct ClusterRuns {
+    cluster: HashMap<i32, usize>,
+    width: Vec<usize>,
+    // Row of each cluster's run, its next free column and the column past
+    // its end, once reserved
+    next: Vec<Option<(usize, usize, usize)>>,
+}
+
+impl ClusterRuns {
+    fn new(circuit: &Circuit) -> Self {
+        let widths: HashMap<i32, usize> = circuit.gates[..circuit.num_gates].iter().map(|g| (g.out, g.fanin)).collect();
+        let mut cluster = HashMap::new();
+        let mut width = Vec::with_capacity(circuit.clusters.len());
+        for (c, members) in circuit.clusters.iter().enumerate() {
+            for &member in members {
+                cluster.insert(member, c);
+            }
+            width.push(members.iter().filter_map(|m| widths.get(m)).sum());
+        }
+        Self { cluster, next: vec![None; width.len()], width }
+    }
+
+    // Columns for the gate driving `signal` in `row`: its `copies` copy
+    // cells, then the gate in the next column that computes, `cells` columns
+    // at least. Returns the first column and the gate's. A member whose
+    // columns would run past its cluster's run is placed as usual
+    fn columns(&mut self, signal: i32, row: usize, copies: usize, cells: usize, regions: &Regions, av_row: &mut [usize]) -> (usize, usize) {
+        let span = |first: usize| {
+            let gate = regions.compute_col(first + copies);
+            (gate, (gate + 1 - first).max(cells))
+        };
+        if let Some(&c) = self.cluster.get(&signal) {
+            match self.next[c] {
+                Some((run_row, col, end)) if run_row == row && col + span(col).1 <= end => {
+                    let (gate, cells) = span(col);
+                    self.next[c] = Some((row, col + cells, end));
+                    return (col, gate);
+                }
+                None => {
+                    let col = av_row[row];
+                    let (gate, cells) = span(col);
+                    let end = col + self.width[c].max(cells);
+                    av_row[row] = end;
+                    self.next[c] = Some((row, col + cells, end));
+                    return (col, gate);
+                }
+                Some(_) => {}
+            }
+        }
+        let col = av_row[row];
+        let (gate, cells) = span(col);
+        av_row[row] += cells;
+        (col, gate)
+    }
+
+    // The gate column `columns` would return, without reserving anything
+    fn gate_column(&self, signal: i32, row: usize, copies: usize, cells: usize, regions: &Regions, av_row: &[usize]) -> usize {
+        let gate = |first: usize| regions.compute_col(first + copies);
+        let first = match self.cluster.get(&signal).map(|&c| self.next[c]) {
+            Some(Some((run_row, col, end))) if run_row == row && col + (gate(col) + 1 - col).max(cells) <= end => col,
+            _ => av_row[row],
+        };
+        gate(first)
+    }
+}
+
+fn operand_row(lines: &[usize], rows: &[usize], source: CellRef) -> usize {
+    match source {
+        CellRef::Input(n) => lines[n],
+        CellRef::Gate(j) | CellRef::Copy(j) => rows[j],
+    }
+}
+
+// Storage-only lines of the crossbar, numbered as the logical lines the
+// mappers place cells on (see `Placement`). Primary inputs and copies may sit
+// on them; a gate needs a cell whose row and column both compute
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Regions {
+    pub storage_rows: BTreeSet<usize>,
+    pub storage_cols: BTreeSet<usize>,
+}
+
+impl Regions {
+    // Every cell computes
+    pub fn is_empty(&self) -> bool {
+        self.storage_rows.is_empty() && self.storage_cols.is_empty()
+    }
+
+    pub fn storage_row(&self, row: usize) -> bool {
+        self.storage_rows.contains(&row)
+    }
+
+    pub fn storage_col(&self, col: usize) -> bool {
+        self.storage_cols.contains(&col)
+    }
+
+    // The first row from `row` on that computes
+    pub fn compute_row(&self, row: usize) -> usize {
+        (row..).find(|&r| !self.storage_row(r)).unwrap_or(row)
+    }
+
+    // The first column from `col` on that computes
+    pub fn compute_col(&self, col: usize) -> usize {
+        (col..).find(|&c| !self.storage_col(c)).unwrap_or(col)
+    }
+
+    // The row up to `last` that computes and has the fewest columns taken,
+    // or the first one past `last` if none computes
+    fn emptiest_row(&self, av_row: &[usize], last: usize) -> usize {
+        (0..=last)
+            .filter(|&r| !self.storage_row(r))
+            .min_by_key(|&r| av_row[r])
+            .unwrap_or_else(|| self.compute_row(last + 1))
+    }
+}
+
+// Physical rows and columns left to the mappers once a crossbar's reserved
+// lines are taken out. Mappers place cells on logical lines 0, 1, 2, ...; a
+// placement moves every cell onto the matching free physical line. Lines keep
+// their order, so cells that shared a row or a column still do
+#[derive(Debug, Clone)]
+pub struct Placement {
+    rows: Vec<usize>,
+    cols: Vec<usize>,
+    // Physical lines of the technology's storage regions
+    storage_rows: BTreeSet<usize>,
+    storage_cols: BTreeSet<usize>,
+}
+
+impl Default for Placement {
+    // Every line of the largest crossbar the mappers build
+    fn default() -> Self {
+        Self {
+            rows: (0..MAX_ROW).collect(),
+            cols: (0..MAX_COL).collect(),
+            storage_rows: BTreeSet::new(),
+            storage_cols: BTreeSet::new(),
+        }
+    }
+}
+
+impl Placement {
+    pub fn new(periphery: &Periphery) -> Self {
+        let free = |size: Option<usize>, limit: usize, reserved: &[usize]| -> Vec<usize> {
+            (0..size.unwrap_or(limit)).filter(|line| !reserved.contains(line)).collect()
+        };
+        Self {
+            rows: free(periphery.rows, MAX_ROW, &periphery.reserved_rows),
+            cols: free(periphery.cols, MAX_COL, &periphery.reserved_cols),
+            storage_rows: periphery.storage_rows.iter().copied().collect(),
+            storage_cols: periphery.storage_cols.iter().copied().collect(),
+        }
+    }
+
+    // The storage lines among the free ones, as the mappers number them
+    pub fn regions(&self) -> Regions {
+        let logical = |lines: &[usize], storage: &BTreeSet<usize>| -> BTreeSet<usize> {
+            lines.iter().enumerate().filter(|(_, line)| storage.contains(line)).map(|(i, _)| i).collect()
+        };
+        Regions {
+            storage_rows: logical(&self.rows, &self.storage_rows),
+            storage_cols: logical(&self.cols, &self.storage_cols),
+        }
+    }
+
+    // Whether a physical cell can evaluate a gate
+    pub fn computes(&self, cell: Cell) -> bool {
+        !self.storage_rows.contains(&(cell.row as usize)) && !self.storage_cols.contains(&(cell.col as usize))
+    }
+    
+    // Feasibility of a mapping whose last logical cell is `max_idx` x `max_jdx`
+    pub fn check_fits(&self, max_idx: i32, max_jdx: i32) -> Result<()> {
+        let (rows, cols) = (max_idx.max(0) as usize + 1, max_jdx.max(0) as usize + 1);
+        ensure!(rows <= self.rows.len() && cols <= self.cols.len(),
+            "Mapping needs {} rows and {} columns, but only {} rows and {} columns are free",
+            rows, cols, self.rows.len(), self.cols.len());
+        Ok(())
+    }
+    
+    // A cell and its operands at their physical coordinates
+    pub fn cell(&self, cell: &MemristiveGate) -> MemristiveGate {
+        let mut placed = MemristiveGate {
+            idx: self.row(cell.idx),
+            jdx: self.col(cell.jdx),
+            inputs: Default::default(),
+            ..*cell
+        };
+        for (k, input) in cell.inputs.iter().enumerate() {
+            placed.inputs[k] = input.as_ref().map(|input| Box::new(self.cell(input)));
+        }
+        placed
+    }
+    
+    pub fn row(&self, idx: i32) -> i32 {
+        if idx < 0 { idx } else { self.rows[idx as usize] as i32 }
+    }
+    
+    pub fn col(&self, jdx: i32) -> i32 {
+        if jdx < 0 { jdx } else { self.cols[jdx as usize] as i32 }
+    }
+    
+    // Free rows and columns
+    pub fn lines(&self) -> (usize, usize) {
+        (self.rows.len(), self.cols.len())
+    }
+    
+    // The first `rows` x `cols` of these lines with logical line i moved to
+    // the place of line i + shift, wrapping around. Cells that shared a line
+    // still do, so the rotated mapping runs the same program
+    pub fn rotated(&self, rows: usize, cols: usize, row_shift: usize, col_shift: usize) -> Result<Self> {
+        ensure!(self.storage_rows.is_empty() && self.storage_cols.is_empty(),
+            "Rotated placements would move gates onto the crossbar's storage lines");
+        ensure!(rows <= self.rows.len() && cols <= self.cols.len(),
+            "A {}x{} rotation area does not fit the {} free rows and {} free columns",
+            rows, cols, self.rows.len(), self.cols.len());
+        let rotate = |lines: &[usize], n: usize, shift: usize| -> Vec<usize> {
+            (0..n).map(|i| lines[(i + shift) % n]).collect()
+        };
+        Ok(Self {
+            rows: rotate(&self.rows, rows, row_shift),
+            cols: rotate(&self.cols, cols, col_shift),
+            storage_rows: BTreeSet::new(),
+            storage_cols: BTreeSet::new(),
+        })
+    }
+    
+    // A mapping on the free lines; `max_idx` and `max_jdx` bound the lines used.
+    // Gates must land on cells that compute
+    pub fn place(&self, mapping: &CrossbarMapping) -> Result<CrossbarMapping> {
+        self.check_fits(mapping.max_idx, mapping.max_jdx)?;
+        let mut placed = CrossbarMapping::new();
+        for cell in mapping.occupied_cells() {
+            let cell = self.cell(cell);
+            let at = Cell { row: cell.idx as u32, col: cell.jdx as u32 };
+            if cell.fanin > 0 && !self.computes(at) {
+                bail!("Gate {} lands in storage cell {}; the mapping ignores the crossbar's region map", signal_name(cell.value), at);
+            }
+            placed.place(cell);
+        }
+        let last = |lines: &[usize], max: i32| lines[..=max.max(0) as usize].iter().max().map_or(0, |&l| l as i32);
+        placed.max_idx = last(&self.rows, mapping.max_idx);
+        placed.max_jdx = last(&self.cols, mapping.max_jdx);
+        Ok(placed)
+    }
+    
+    // Moves a mapping onto the free lines, along with the circuit's record of
+    // where each gate sits
+    pub fn apply(&self, circuit: &mut Circuit, mapping: &CrossbarMapping) -> Result<CrossbarMapping> {
+        let placed = self.place(mapping)?;
+        note_capacity(circuit, mapping, "placed mapping", self.lines());
+        for gate in circuit.gates.iter_mut().take(circuit.num_gates) {
+            if let Some(cell) = &gate.gate_map {
+                gate.gate_map = Some(Box::new(self.cell(cell)));
+            }
+        }
+        Ok(placed)
+    }
+}
+
+// Columns between each gate of a program and its furthest operand, which
+// MAGIC must drive across the row, as gates per span. Copies are reads and
+// writes through the periphery and do not count
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnSpans {
+    pub spans: BTreeMap<usize, usize>,
+}
+
+impl ColumnSpans {
+    pub fn new(program: &Program) -> Self {
+        let mut spans = BTreeMap::new();
+        for (_, op) in program.ops() {
+            let span = match op {
+                MicroOp::ExecNor { inputs, cell } => inputs.iter().map(|i| i.col.abs_diff(cell.col)).max().unwrap_or(0),
+                MicroOp::ExecNot { input, cell } => input.col.abs_diff(cell.col),
+                _ => continue,
+            };
+            *spans.entry(span as usize).or_insert(0) += 1;
+        }
+        Self { spans }
+    }
+
+    pub fn gates(&self) -> usize {
+        self.spans.values().sum()
+    }
+
+    pub fn max(&self) -> usize {
+        self.spans.keys().next_back().copied().unwrap_or(0)
+    }
+
+    pub fn mean(&self) -> f64 {
+        let total: usize = self.spans.iter().map(|(span, gates)| span * gates).sum();
+        share(total, self.gates())
+    }
+
+    // Gates per bucket of spans, doubling from 1: 1, 2, 3-4, 5-8 and so on,
+    // up to the bucket holding `max`
+    pub fn histogram(&self, max: usize) -> Vec<((usize, usize), usize)> {
+        let mut buckets = vec![((1, 1), 0)];
+        while buckets[buckets.len() - 1].0 .1 < max {
+            let end = buckets[buckets.len() - 1].0 .1;
+            buckets.push(((end + 1, end * 2), 0));
+        }
+        for (&span, &gates) in &self.spans {
+            if let Some(bucket) = buckets.iter_mut().find(|((low, high), _)| (*low..=*high).contains(&span)) {
+                bucket.1 += gates;
+            }
+        }
+        buckets
+    }
+}
+
+// Cells a program writes in the compute and storage regions of the crossbar,
+// out of the cells each region has within the rows and columns the program
+// spans. Reserved lines belong to neither
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegionUsage {
+    pub compute_used: usize,
+    pub compute_cells: usize,
+    pub storage_used: usize,
+    pub storage_cells: usize,
+}
+
+impl RegionUsage {
+    pub fn new(placement: &Placement, program: &Program) -> Self {
+        let within = |lines: &[usize], size: usize| -> Vec<usize> { lines.iter().copied().filter(|&l| l < size).collect() };
+        let (rows, cols) = (within(&placement.rows, program.rows), within(&placement.cols, program.cols));
+        let compute_rows = rows.iter().filter(|r| !placement.storage_rows.contains(r)).count();
+        let compute_cols = cols.iter().filter(|c| !placement.storage_cols.contains(c)).count();
+        let compute_cells = compute_rows * compute_cols;
+        let cells = program.cells();
+        let compute_used = cells.iter().filter(|&&cell| placement.computes(cell)).count();
+        Self {
+            compute_used,
+            compute_cells,
+            storage_used: cells.len() - compute_used,
+            storage_cells: rows.len() * cols.len() - compute_cells,
+        }
+    }
+
+    pub fn compute_share(&self) -> f64 {
+        share(self.compute_used, self.compute_cells)
+    }
+
+    pub fn storage_share(&self) -> f64 {
+        share(self.storage_used, self.storage_cells)
+    }
+}
+
+fn share(used: usize, cells: usize) -> f64 {
+    if cells == 0 { 0.0 } else { used as f64 / cells as f64 }
+}
+
+// "at most 3 columns, 1.42 on average over 11 gates"
+impl std::fmt::Display for ColumnSpans {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at most {} columns, {:.2} on average over {} gates", self.max(), self.mean(), self.gates())
+    }
+}
+
+// "compute 12 of 30 cells (40.0%), storage 5 of 10 cells (50.0%)"
+impl std::fmt::Display for RegionUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "compute {} of {} cells ({:.1}%), storage {} of {} cells ({:.1}%)",
+            self.compute_used, self.compute_cells, 100.0 * self.compute_share(),
+            self.storage_used, self.storage_cells, 100.0 * self.storage_share())
+    }
+}
+
+// The naive and compact mappings as `--mapping-json` writes them, at the
+// coordinates their programs use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingReport {
+    pub circuit: String,
+    pub naive: MappedCrossbar,
+    pub compact: MappedCrossbar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappedCrossbar {
+    pub rows: usize,
+    pub cols: usize,
+    // Row by row
+    pub cells: Vec<MappedCell>,
+}
+
+// A written cell: a primary input, a gate or a copy, and the cells it reads
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappedCell {
+    pub row: u32,
+    pub col: u32,
+    pub signal: String,
+    pub level: i32,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub copy: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reads: Vec<Cell>,
+}
+
+impl MappedCrossbar {
+    pub fn new(mapping: &CrossbarMapping) -> Self {
+        let at = |cell: &MemristiveGate| Cell { row: cell.idx.max(0) as u32, col: cell.jdx.max(0) as u32 };
+        let cells = mapping.occupied_cells()
+            .map(|cell| MappedCell {
+                row: cell.idx as u32,
+                col: cell.jdx as u32,
+                signal: signal_name(cell.value),
+                level: cell.asap_level.max(0),
+                copy: cell.is_copy,
+                reads: cell.inputs.iter().flatten().map(|input| at(input)).collect(),
+            })
+            .collect();
+        Self {
+            rows: mapping.max_idx.max(0) as usize + 1,
+            cols: mapping.max_jdx.max(0) as usize + 1,
+            cells,
+        }
+    }
+}
+
+impl MappingReport {
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string(&Versioned::new(self))? + "\n")
+            .context(format!("Failed to write mapping: {:?}", path))
+    }
+
+    pub fn read_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read mapping: {:?}", path))?;
+        schema::from_str(&text, "mapping").context(format!("Failed to load mapping: {:?}", path))
+    }
+}
@@ -0,0 +1,115 @@
+//metrics/mod.rs
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::generator::TechEstimate;
+use crate::program::{Program, ProgramCycles};
+use crate::schema::{self, Versioned};
+use crate::{Circuit, Primitive};
+
+// Wall-clock time of each phase of a run, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimes {
+    pub parse_ms: f64,
+    pub passes_ms: f64,
+    pub schedule_ms: f64,
+    // Mapping both flows and writing their programs
+    pub map_ms: f64,
+    pub total_ms: f64,
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// Size and cost of one program
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProgramMetrics {
+    pub rows: usize,
+    pub cols: usize,
+    // Memristors the program writes: inputs, copies and gate outputs
+    pub cells: usize,
+    pub copies: usize,
+    pub cycles: usize,
+    pub copy_cycles: usize,
+    // Only set when the run has a technology
+    pub tech_cycles: Option<usize>,
+    pub time_ns: Option<f64>,
+    pub energy_pj: Option<f64>,
+    // Only set when the run has a `--cost` expression
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+impl ProgramMetrics {
+    pub fn new(program: &Program, cycles: &ProgramCycles, estimate: Option<TechEstimate>) -> Self {
+        Self {
+            rows: program.rows,
+            cols: program.cols,
+            cells: program.cells().len(),
+            copies: cycles.copies.iter().sum(),
+            cycles: cycles.total(),
+            copy_cycles: cycles.copy_cycles(),
+            tech_cycles: estimate.map(|e| e.cycles),
+            time_ns: estimate.map(|e| e.time_ns),
+            energy_pj: estimate.map(|e| e.energy_pj),
+            cost: None,
+        }
+    }
+}
+
+// Everything a run measured, filled in phase by phase. The bench summary and
+// `--metrics-json` read it instead of the stats text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunMetrics {
+    pub circuit: String,
+    pub primitive: Primitive,
+    pub inputs: usize,
+    // Inputs no gate reads, left off the crossbar
+    pub unused_inputs: usize,
+    pub outputs: usize,
+    pub gates: usize,
+    pub not_gates: usize,
+    pub levels: usize,
+    pub times: PhaseTimes,
+    pub naive: ProgramMetrics,
+    pub compact: ProgramMetrics,
+    pub warnings: usize,
+}
+
+impl RunMetrics {
+    // The circuit's interface, size and depth as they stand
+    pub fn new(circuit: &Circuit) -> Self {
+        let mut metrics = Self::default();
+        metrics.measure(circuit);
+        metrics
+    }
+
+    pub fn measure(&mut self, circuit: &Circuit) {
+        self.circuit = circuit.bench_name.clone();
+        self.primitive = circuit.primitive;
+        self.inputs = circuit.num_inputs;
+        self.unused_inputs = circuit.unused_inputs.len();
+        self.outputs = circuit.num_outputs;
+        self.gates = circuit.num_gates;
+        self.not_gates = circuit.gates.iter().take(circuit.num_gates).filter(|g| g.fanin == 1).count();
+        self.levels = circuit.max_asap.max(0) as usize;
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(&Versioned::new(self))? + "\n")
+            .context(format!("Failed to write metrics: {:?}", path))
+    }
+
+    // Metrics as `--metrics-json` wrote them, in this or an earlier schema
+    pub fn read_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read metrics: {:?}", path))?;
+        schema::from_str(&text, "metrics").context(format!("Failed to load metrics: {:?}", path))
+    }
+}
@@ -0,0 +1,249 @@
+//monitor/mod.rs
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result, bail, ensure};
+use log::warn;
+
+use crate::metrics::RunMetrics;
+use crate::serve::Runner;
+use crate::suite::Status;
+
+// Longest a push waits on the gateway, so that a gateway that went away
+// stalls a campaign by seconds, not for good
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Circuits a campaign has finished, by how they ended, and what the
+// processed ones add up to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CampaignCounters {
+    pub done: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub missing: u64,
+    // Over the processed circuits
+    pub gates: u64,
+    pub naive_cycles: u64,
+    pub compact_cycles: u64,
+}
+
+impl CampaignCounters {
+    pub fn record(&mut self, status: &Status) {
+        match status {
+            Status::Done(metrics) => {
+                self.done += 1;
+                self.gates += metrics.gates as u64;
+                self.naive_cycles += metrics.naive.cycles as u64;
+                self.compact_cycles += metrics.compact.cycles as u64;
+            }
+            Status::Skipped => self.skipped += 1,
+            Status::Failed(_) => self.failed += 1,
+            Status::Missing => self.missing += 1,
+        }
+    }
+
+    // NaN before the first processed circuit, which Prometheus shows as no
+    // data rather than as a program of no cycles
+    pub fn average_cycles(&self, total: u64) -> f64 {
+        if self.done == 0 { f64::NAN } else { total as f64 / self.done as f64 }
+    }
+
+    // The counters in the Prometheus text format, each labelled with the
+    // campaign so that the textfiles of several campaigns can sit side by side
+    pub fn exposition(&self, campaign: &str, elapsed: Duration) -> String {
+        let campaign = format!("campaign=\"{}\"", escape_label(campaign));
+        let mut text = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: &[(Option<String>, String)]| {
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let _ = writeln!(text, "# TYPE {} {}", name, kind);
+            for (label, value) in samples {
+                match label {
+                    Some(label) => { let _ = writeln!(text, "{}{{{},{}}} {}", name, campaign, label, value); }
+                    None => { let _ = writeln!(text, "{}{{{}}} {}", name, campaign, value); }
+                }
+            }
+        };
+        let statuses = [("done", self.done), ("skipped", self.skipped), ("failed", self.failed), ("missing", self.missing)];
+        family("delphi_circuits_total", "counter", "Circuits the campaign finished, by how they ended.",
+            &statuses.map(|(status, count)| (Some(format!("status=\"{}\"", status)), count.to_string())));
+        family("delphi_gates_total", "counter", "Gates of the processed circuits.", &[(None, self.gates.to_string())]);
+        let average = |total| format_float(self.average_cycles(total));
+        family("delphi_average_cycles", "gauge", "Mean micro-op cycles of the processed circuits' programs.", &[
+            (Some("flow=\"naive\"".to_string()), average(self.naive_cycles)),
+            (Some("flow=\"compact\"".to_string()), average(self.compact_cycles)),
+        ]);
+        family("delphi_elapsed_seconds", "gauge", "Time since the campaign started.",
+            &[(None, format_float(elapsed.as_secs_f64()))]);
+        text
+    }
+}
+
+// A Prometheus push gateway, from its base URL; plain HTTP only
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushGateway {
+    // `host:port`, as sent in the Host header
+    pub authority: String,
+    // Path under which the gateway is served, without a trailing '/'
+    pub prefix: String,
+}
+
+impl PushGateway {
+    pub fn parse(url: &str) -> Result<Self> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            bail!("Push gateway URL '{}' is not http://HOST[:PORT][/PATH]", url);
+        };
+        let (authority, prefix) = rest.split_once('/').map_or((rest, ""), |(a, p)| (a, p));
+        ensure!(!authority.is_empty(), "Push gateway URL '{}' has no host", url);
+        // The gateway's default port
+        let authority = if authority.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+            authority.to_string()
+        } else {
+            format!("{}:9091", authority)
+        };
+        let prefix = prefix.trim_end_matches('/');
+        Ok(Self { authority, prefix: if prefix.is_empty() { String::new() } else { format!("/{}", prefix) } })
+    }
+
+    // The group the campaign's counters replace on every push
+    pub fn path(&self, campaign: &str) -> String {
+        format!("{}/metrics/job/delphi/campaign/{}", self.prefix, escape_path(campaign))
+    }
+
+    pub fn push(&self, campaign: &str, body: &str) -> Result<()> {
+        let address = self.authority.to_socket_addrs()
+            .context(format!("Failed to resolve push gateway {}", self.authority))?
+            .next()
+            .context(format!("Push gateway {} has no address", self.authority))?;
+        let mut stream = TcpStream::connect_timeout(&address, PUSH_TIMEOUT)
+            .context(format!("Failed to connect to push gateway {}", self.authority))?;
+        stream.set_read_timeout(Some(PUSH_TIMEOUT))?;
+        stream.set_write_timeout(Some(PUSH_TIMEOUT))?;
+        write!(stream, "PUT {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path(campaign), self.authority, body.len(), body)?;
+        stream.flush()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).context(format!("No answer from push gateway {}", self.authority))?;
+        let status_line = response.lines().next().unwrap_or("");
+        let status = status_line.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok());
+        ensure!(status.is_some_and(|s| (200..300).contains(&s)),
+            "Push gateway {} answered '{}'", self.authority, status_line);
+        Ok(())
+    }
+}
+
+// Keeps a campaign's counters where Prometheus can collect them: a textfile
+// for node_exporter's textfile collector, a push gateway, or both, updated
+// after every circuit. Without either it only counts
+pub struct Monitor {
+    campaign: String,
+    textfile: Option<PathBuf>,
+    gateway: Option<PushGateway>,
+    started: Instant,
+    counters: Mutex<CampaignCounters>,
+}
+
+impl Monitor {
+    pub fn new(campaign: &str, textfile: Option<PathBuf>, push: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            campaign: campaign.to_string(),
+            textfile,
+            gateway: push.map(PushGateway::parse).transpose()?,
+            started: Instant::now(),
+            counters: Mutex::new(CampaignCounters::default()),
+        })
+    }
+
+    pub fn counters(&self) -> CampaignCounters {
+        self.counters.lock().unwrap().clone()
+    }
+
+    pub fn exposition(&self) -> String {
+        self.counters().exposition(&self.campaign, self.started.elapsed())
+    }
+
+    // Writes and pushes the counters as they stand. Called at the start of a
+    // campaign, so that a path or gateway that does not work stops it before
+    // the first circuit
+    pub fn emit(&self) -> Result<()> {
+        if self.textfile.is_none() && self.gateway.is_none() {
+            return Ok(());
+        }
+        let text = self.exposition();
+        if let Some(path) = &self.textfile {
+            write_textfile(path, &text)?;
+        }
+        if let Some(gateway) = &self.gateway {
+            gateway.push(&self.campaign, &text)?;
+        }
+        Ok(())
+    }
+
+    // Counts one finished circuit and emits; a failed emission is only
+    // warned about, as monitoring is no reason to stop a campaign
+    pub fn record(&self, status: &Status) {
+        self.counters.lock().unwrap().record(status);
+        if let Err(e) = self.emit() {
+            warn!("Failed to emit campaign metrics: {:#}", e);
+        }
+    }
+}
+
+// Counts the jobs a service runs: each circuit of a job that ran, or one
+// failure for a job that failed or panicked
+pub struct Monitored<R: Runner> {
+    pub runner: R,
+    pub monitor: Monitor,
+}
+
+impl<R: Runner> Runner for Monitored<R> {
+    fn check(&self, options: &[String]) -> Result<()> {
+        self.runner.check(options)
+    }
+
+    fn run(&self, netlist: &Path, options: &[String], dir: &Path) -> Result<Vec<RunMetrics>> {
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| self.runner.run(netlist, options, dir)));
+        match &outcome {
+            Ok(Ok(metrics)) => {
+                for metrics in metrics {
+                    self.monitor.record(&Status::Done(Box::new(metrics.clone())));
+                }
+            }
+            Ok(Err(e)) => self.monitor.record(&Status::Failed(e.to_string())),
+            Err(_) => self.monitor.record(&Status::Failed("panicked".to_string())),
+        }
+        outcome.unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+}
+
+// Written aside and renamed over the file, so that the collector never reads
+// half of it; the `.tmp` name keeps it out of the collector's `*.prom` glob
+fn write_textfile(path: &Path, text: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).context(format!("Failed to create directory: {:?}", parent))?;
+    }
+    let mut staged = path.as_os_str().to_owned();
+    staged.push(".tmp");
+    fs::write(&staged, text).context(format!("Failed to write metrics textfile: {:?}", staged))?;
+    fs::rename(&staged, path).context(format!("Failed to write metrics textfile: {:?}", path))
+}
+
+fn format_float(value: f64) -> String {
+    if value.is_nan() { "NaN".to_string() } else { format!("{}", value) }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Percent-encodes all but the unreserved characters of a URL path segment
+fn escape_path(segment: &str) -> String {
+    segment.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
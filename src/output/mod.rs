@@ -0,0 +1,185 @@
+//output/mod.rs
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use anyhow::{Result, Context};
+use clap::ValueEnum;
+
+// How artifacts are arranged under the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputLayout {
+    /// One subdirectory per artifact kind (magic/, micro_ins_naive/, ...)
+    #[default]
+    ByKind,
+    /// One subdirectory per benchmark holding all of its artifacts
+    PerBench,
+}
+
+// What to do when an artifact already exists on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClobberPolicy {
+    #[default]
+    Overwrite,
+    NoClobber,
+}
+
+impl ClobberPolicy {
+    // An artifact already on disk that makes the policy skip the run, if any
+    pub fn blocking<'a>(&self, paths: &'a ArtifactPaths) -> Option<&'a Path> {
+        match self {
+            ClobberPolicy::Overwrite => None,
+            ClobberPolicy::NoClobber => paths.existing().into_iter().next(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtifactPaths {
+    pub stats: PathBuf,
+    pub magic: PathBuf,
+    pub naive: PathBuf,
+    pub compact: PathBuf,
+    // Pipeline report; the per-tile programs sit next to it. Only written
+    // for pipelined runs, so not part of `all`
+    pub pipeline: PathBuf,
+    // Batched input layout, also only written on request
+    pub batch: PathBuf,
+    // Replicated words and their N-bit Verilog wrapper, also on request
+    pub replicate: PathBuf,
+    pub replicated_verilog: PathBuf,
+    // Controller schedule of rotated placements, next to their programs
+    pub rotation: PathBuf,
+    // Manifest of a program split for a small instruction memory
+    pub partition: PathBuf,
+    // Findings of the run as JSON, next to the stats
+    pub diagnostics: PathBuf,
+    // Measurements of the run as JSON, next to the stats
+    pub metrics: PathBuf,
+    // Levels of every gate and both mappings as JSON, next to the stats
+    pub schedule: PathBuf,
+    pub mapping: PathBuf,
+    // Netlist line behind every gate and mapped cell, next to the stats
+    pub provenance: PathBuf,
+    // Naive and compact mappings overlaid as SVG, next to the stats
+    pub overlay: PathBuf,
+    // Circuit as scheduled and mapped, as a native netlist next to the stats
+    pub decomposed: PathBuf,
+}
+
+impl ArtifactPaths {
+    pub fn new<P: AsRef<Path>>(output_dir: P, bench_name: &str, layout: OutputLayout) -> Self {
+        let root = output_dir.as_ref();
+        let (stats_dir, magic_dir, naive_dir, compact_dir, pipeline_dir, batch_dir, replicate_dir, rotation_dir, partition_dir) = match layout {
+            OutputLayout::ByKind => (
+                root.join("schedule_stats"),
+                root.join("magic"),
+                root.join("micro_ins_naive"),
+                root.join("micro_ins_compact"),
+                root.join("micro_ins_pipelined"),
+                root.join("micro_ins_batched"),
+                root.join("micro_ins_replicated"),
+                root.join("micro_ins_rotated"),
+                root.join("micro_ins_partitioned"),
+            ),
+            OutputLayout::PerBench => {
+                let bench_dir = root.join(bench_name);
+                let dir = || bench_dir.clone();
+                (dir(), dir(), dir(), dir(), dir(), dir(), dir(), dir(), bench_dir)
+            }
+        };
+
+        Self {
+            stats: stats_dir.join(format!("{}_stats.txt", bench_name)),
+            magic: magic_dir.join(format!("{}_magic.v", bench_name)),
+            naive: naive_dir.join(format!("{}_naive.txt", bench_name)),
+            compact: compact_dir.join(format!("{}_compact.txt", bench_name)),
+            pipeline: pipeline_dir.join(format!("{}_pipeline.txt", bench_name)),
+            batch: batch_dir.join(format!("{}_batch.txt", bench_name)),
+            replicate: replicate_dir.join(format!("{}_replicated.txt", bench_name)),
+            replicated_verilog: replicate_dir.join(format!("{}_replicated.v", bench_name)),
+            rotation: rotation_dir.join(format!("{}_rotation.txt", bench_name)),
+            partition: partition_dir.join(format!("{}_partition.txt", bench_name)),
+            diagnostics: stats_dir.join(format!("{}_diagnostics.json", bench_name)),
+            metrics: stats_dir.join(format!("{}_metrics.json", bench_name)),
+            schedule: stats_dir.join(format!("{}_schedule.json", bench_name)),
+            mapping: stats_dir.join(format!("{}_mapping.json", bench_name)),
+            provenance: stats_dir.join(format!("{}_provenance.json", bench_name)),
+            overlay: stats_dir.join(format!("{}_overlay.svg", bench_name)),
+            decomposed: stats_dir.join(format!("{}_decomposed.txt", bench_name)),
+        }
+    }
+
+    // Program of each pipeline tile: `<bench>_tile<k>.txt`
+    pub fn tile_programs(&self, tiles: usize) -> Vec<PathBuf> {
+        numbered(&self.pipeline, "_pipeline", "tile", tiles)
+    }
+
+    // Program of each rotated placement: `<bench>_rot<k>.txt`
+    pub fn rotation_programs(&self, placements: usize) -> Vec<PathBuf> {
+        numbered(&self.rotation, "_rotation", "rot", placements)
+    }
+
+    // Each part of a partitioned program: `<bench>_part<k>.txt`
+    pub fn partition_programs(&self, parts: usize) -> Vec<PathBuf> {
+        numbered(&self.partition, "_partition", "part", parts)
+    }
+
+    pub fn all(&self) -> [&Path; 4] {
+        [&self.stats, &self.magic, &self.naive, &self.compact]
+    }
+
+    pub fn create_dirs(&self) -> Result<()> {
+        for path in self.all() {
+            create_parent(path)?;
+        }
+        Ok(())
+    }
+
+    // Artifacts that would be overwritten by a new run
+    pub fn existing(&self) -> Vec<&Path> {
+        self.all().into_iter().filter(|p| p.exists()).collect()
+    }
+}
+
+// `<bench>_<kind><k>.txt` next to a report named `<bench><suffix>.txt`
+fn numbered(report: &Path, suffix: &str, kind: &str, count: usize) -> Vec<PathBuf> {
+    let stem = report.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let bench = stem.strip_suffix(suffix).unwrap_or(stem);
+    (0..count).map(|k| report.with_file_name(format!("{}_{}{}.txt", bench, kind, k))).collect()
+}
+
+// Creates the directory an optional artifact goes into
+pub fn create_parent(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .context(format!("Failed to create directory: {:?}", dir))?;
+    }
+    Ok(())
+}
+
+// Number of the first gate level in the artifacts and messages (--level-base).
+// Inside, gate levels count from 1 and the input loads are level 0
+static LEVEL_BASE: AtomicI32 = AtomicI32::new(1);
+
+pub fn set_level_base(base: i32) {
+    LEVEL_BASE.store(base, Ordering::Relaxed);
+}
+
+pub fn level_base() -> i32 {
+    LEVEL_BASE.load(Ordering::Relaxed)
+}
+
+// A level as the artifacts number it. The input loads come just before the
+// first gate level, so they are -1 when levels count from zero
+pub fn level_number(level: i32) -> i32 {
+    level - 1 + level_base()
+}
+
+// "2-4", the levels as numbered; a range opening with the input loads
+// before level 0 starts at "inputs"
+pub fn level_range(first: i32, last: i32) -> String {
+    match level_number(first) {
+        first if first < 0 => format!("inputs-{}", level_number(last)),
+        first => format!("{}-{}", first, level_number(last)),
+    }
+}
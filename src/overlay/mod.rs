@@ -0,0 +1,164 @@
+//overlay/mod.rs
+use std::collections::{BTreeMap, HashMap};
+
+use crate::output;
+use crate::program::{Cell, MicroOp, Program};
+use crate::{signal_name, MAX_GATES};
+
+// What a program writes into a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Input,
+    Copy,
+    Gate,
+}
+
+impl CellKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            CellKind::Input => "input",
+            CellKind::Copy => "copy",
+            CellKind::Gate => "gate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellUse {
+    pub kind: CellKind,
+    // 0 for inputs
+    pub level: i32,
+    // The signal the cell holds, where the layer knows it
+    pub signal: Option<i32>,
+}
+
+impl CellUse {
+    // Cells agree when they hold the same signal; without names on both
+    // sides, when the same kind of op writes them at the same level
+    fn agrees(&self, other: &CellUse) -> bool {
+        match (self.signal, other.signal) {
+            (Some(a), Some(b)) => a == b && self.kind == other.kind,
+            _ => self.kind == other.kind && self.level == other.level,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self.signal {
+            Some(s) => format!("{} {}, level {}", self.kind.label(), signal_name(s), output::level_number(self.level)),
+            None => format!("{}, level {}", self.kind.label(), output::level_number(self.level)),
+        }
+    }
+}
+
+// One side of an overlay: the cells a program writes
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub label: String,
+    pub rows: usize,
+    pub cols: usize,
+    pub cells: BTreeMap<Cell, CellUse>,
+}
+
+impl Layer {
+    // A program only names its inputs and the outputs it reads; `with_signals`
+    // names the rest from the mapping it was built from
+    pub fn new(label: &str, program: &Program) -> Self {
+        let mut cells = BTreeMap::new();
+        for (level, op) in program.ops() {
+            let (cell, kind, signal) = match op {
+                MicroOp::InputLoad { input, cell, .. } => (cell, CellKind::Input, Some((MAX_GATES + input) as i32)),
+                MicroOp::Held { signal, cell } if *signal >= MAX_GATES as i32 => (cell, CellKind::Input, Some(*signal)),
+                MicroOp::Held { signal, cell } => (cell, CellKind::Gate, Some(*signal)),
+                MicroOp::Copy { cell, .. } => (cell, CellKind::Copy, None),
+                MicroOp::ExecNor { cell, .. } | MicroOp::ExecNot { cell, .. } => (cell, CellKind::Gate, None),
+                MicroOp::Preset { .. } | MicroOp::Init { .. } | MicroOp::Read { .. } => continue,
+            };
+            cells.insert(*cell, CellUse { kind, level, signal });
+        }
+        // Outputs name the cells they are read from, but for inverted reads,
+        // whose cell holds the signal the output's dropped NOT read
+        for op in program.steps.last().map(|s| &s.ops[..]).unwrap_or_default() {
+            if let MicroOp::Read { signal, cell, inverted: false, .. } = op {
+                if let Some(cell) = cells.get_mut(cell) {
+                    cell.signal = Some(*signal);
+                }
+            }
+        }
+        Self { label: label.to_string(), rows: program.rows, cols: program.cols, cells }
+    }
+
+    pub fn with_signals(mut self, signals: &HashMap<Cell, i32>) -> Self {
+        for (cell, used) in &mut self.cells {
+            if let Some(&signal) = signals.get(cell) {
+                used.signal = Some(signal);
+            }
+        }
+        self
+    }
+}
+
+// How a cell of an overlay compares between its layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CellDiff {
+    Same,
+    Changed,
+    OnlyFirst,
+    OnlySecond,
+}
+
+// Two programs' cells on one array, e.g. the naive and compact mappings of a
+// circuit or the compact mappings of two heuristics, each cell classified by
+// what the two programs write into it
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    pub first: Layer,
+    pub second: Layer,
+    pub cells: BTreeMap<Cell, CellDiff>,
+}
+
+impl Overlay {
+    pub fn new(first: Layer, second: Layer) -> Self {
+        let mut cells = BTreeMap::new();
+        for (cell, a) in &first.cells {
+            let diff = match second.cells.get(cell) {
+                Some(b) if a.agrees(b) => CellDiff::Same,
+                Some(_) => CellDiff::Changed,
+                None => CellDiff::OnlyFirst,
+            };
+            cells.insert(*cell, diff);
+        }
+        for cell in second.cells.keys() {
+            cells.entry(*cell).or_insert(CellDiff::OnlySecond);
+        }
+        Self { first, second, cells }
+    }
+
+    // The array both layers fit in
+    pub fn rows(&self) -> usize {
+        self.first.rows.max(self.second.rows)
+    }
+
+    pub fn cols(&self) -> usize {
+        self.first.cols.max(self.second.cols)
+    }
+
+    pub fn count(&self, diff: CellDiff) -> usize {
+        self.cells.values().filter(|&&d| d == diff).count()
+    }
+
+    // Inputs and gates that both layers name but place in different cells,
+    // as (signal, first cell, second cell) in signal order. Copies are left
+    // out: a signal may have several
+    pub fn moved(&self) -> Vec<(i32, Cell, Cell)> {
+        let placed = |layer: &Layer| -> BTreeMap<i32, Cell> {
+            layer.cells.iter()
+                .filter(|(_, u)| u.kind != CellKind::Copy)
+                .filter_map(|(&cell, u)| u.signal.map(|s| (s, cell)))
+                .collect()
+        };
+        let second = placed(&self.second);
+        placed(&self.first).into_iter()
+            .filter_map(|(s, a)| second.get(&s).filter(|&&b| b != a).map(|&b| (s, a, b)))
+            .collect()
+    }
+}
@@ -1,17 +1,11 @@
 //parallel/mod.rs
-use crate::{
-    Circuit, CrossbarMapping, MemristiveGate, TableGate, MAX_COL, MAX_GATES, MAX_LEVELS, MAX_ROW,
-};
+use crate::{Circuit, TableGate, MAX_GATES, MAX_LEVELS};
 use anyhow::Result;
 use dashmap::DashMap;
 use log::info;
 use rayon::prelude::*;
 use std::cmp::max;
 use std::collections::HashMap;
-use std::sync::{
-    atomic::{AtomicI32, AtomicUsize, Ordering},
-    Arc,
-};
 
 // Parallel ASAP schedule computation
 pub fn compute_asap_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
@@ -58,7 +52,9 @@ pub fn compute_asap_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
         // Create map for faster gate lookup
         let mut gate_map = HashMap::new();
         for (i, gate) in circuit.gates.iter().enumerate() {
-            gate_map.insert(gate.out, i);
+            for out in gate.outs() {
+                gate_map.insert(out, i);
+            }
         }
 
         // Compute ASAP levels iteratively
@@ -170,8 +166,9 @@ pub fn compute_alap_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
                 continue;
             }
 
-            // Add this gate as dependent on its input
-            if let Some(deps) = dep_map.get_mut(&input) {
+            // Add this gate as dependent on its input, under the signal its
+            // driver's cell holds
+            if let Some(deps) = dep_map.get_mut(&circuit.cell_signal(input)) {
                 deps.push(i);
             }
         }
@@ -196,7 +193,9 @@ pub fn compute_alap_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
     // Create gate lookup by out signal
     let mut gate_map = HashMap::new();
     for (i, gate) in circuit.gates.iter().enumerate() {
-        gate_map.insert(gate.out, i);
+        for out in gate.outs() {
+            gate_map.insert(out, i);
+        }
     }
 
     // Process gates in topological order (starting from outputs)
@@ -271,179 +270,6 @@ pub fn compute_alap_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
     Ok(())
 }
 
-// Parallel naive mapping
-pub fn create_naive_mapping_parallel(circuit: &mut Circuit) -> Result<CrossbarMapping> {
-    let gate_count = circuit.num_gates;
-
-    // For small circuits, use sequential algorithm
-    if gate_count < 50 {
-        return Ok(crate::mapper::create_naive_mapping(circuit));
-    }
-
-    info!("Creating naive mapping in parallel");
-
-    let mut mapping = CrossbarMapping::new();
-
-    // Reset crossbar array in parallel
-    mapping.crossbar.par_iter_mut().for_each(|row| {
-        row.iter_mut().for_each(|cell| {
-            *cell = MemristiveGate::default();
-        });
-    });
-
-    // Reset gate mappings
-    for i in 0..circuit.num_gates {
-        circuit.gates[i].gate_map = None;
-    }
-
-    // Sort gates by ASAP level
-    circuit
-        .gates
-        .sort_by(|a, b| a.asap_level.cmp(&b.asap_level));
-
-    // Create concurrent map for gate lookup
-    let inv_map = DashMap::new();
-    for i in 0..circuit.num_gates {
-        inv_map.insert(circuit.gates[i].out, i);
-    }
-
-    // Handle case where there are no inputs
-    if circuit.num_inputs == 0 {
-        return Ok(mapping);
-    }
-
-    // Map primary inputs to the first row of the crossbar
-    let max_inputs = circuit.num_inputs.min(MAX_COL);
-    for j in 0..max_inputs {
-        mapping.crossbar[0][j].value = (MAX_GATES + j) as i32;
-        mapping.crossbar[0][j].idx = 0;
-        mapping.crossbar[0][j].jdx = j as i32;
-    }
-
-    // Update max_jdx to reflect the number of inputs
-    mapping.max_jdx = (max_inputs as i32).saturating_sub(1);
-
-    // Shared counter for max_jdx
-    let max_jdx = Arc::new(AtomicI32::new(mapping.max_jdx));
-
-    // Group gates by level (with safe maximum level)
-    let max_level = (circuit.max_asap as usize).min(MAX_LEVELS - 1);
-    let mut gates_by_level: Vec<Vec<usize>> = vec![Vec::new(); max_level + 1];
-
-    for (i, gate) in circuit.gates.iter().enumerate() {
-        if gate.asap_level >= 0 && (gate.asap_level as usize) <= max_level {
-            gates_by_level[gate.asap_level as usize].push(i);
-        }
-    }
-
-    // Process each level sequentially but gates within a level in parallel
-    for level in 0..=max_level {
-        // Skip invalid levels
-        if level >= gates_by_level.len() {
-            continue;
-        }
-
-        let gates_at_level = &gates_by_level[level];
-
-        // Skip empty levels
-        if gates_at_level.is_empty() {
-            continue;
-        }
-
-        // For each level, we need to assign sequential column indices
-        let column_indices: Vec<i32> = (0..gates_at_level.len())
-            .map(|_| max_jdx.fetch_add(1, Ordering::SeqCst) + 1)
-            .collect();
-
-        // Collect gate info for all gates in this level
-        let gate_info: Vec<_> = gates_at_level
-            .iter()
-            .enumerate()
-            .map(|(level_idx, &gate_idx)| {
-                let gate = &circuit.gates[gate_idx];
-                let column = column_indices[level_idx] as usize;
-
-                (
-                    gate_idx,
-                    gate.out,
-                    gate.fanin,
-                    gate.inputs[0],
-                    if gate.fanin > 1 { gate.inputs[1] } else { -1 },
-                    column,
-                    gate.asap_level,
-                )
-            })
-            .collect();
-
-        // First pass: set up the gates in the crossbar
-        for &(_, out, fanin, _, _, column, asap_level) in &gate_info {
-            let col = column.min(MAX_COL - 1);
-            mapping.crossbar[0][col].fanin = fanin;
-            mapping.crossbar[0][col].value = out;
-            mapping.crossbar[0][col].jdx = col as i32;
-            mapping.crossbar[0][col].idx = 0; // All gates in row 0 for naive mapping
-            mapping.crossbar[0][col].asap_level = asap_level;
-        }
-
-        // Second pass: set up gate mappings
-        for &(gate_idx, _, _, _, _, column, _) in &gate_info {
-            let col = column.min(MAX_COL - 1);
-            let gate_map = Box::new(mapping.crossbar[0][col].clone());
-            circuit.gates[gate_idx].gate_map = Some(gate_map);
-        }
-
-        // Third pass: connect inputs
-        for &(_, _, fanin, ip1, ip2, column, _) in &gate_info {
-            let col = column.min(MAX_COL - 1);
-
-            // Connect the first input
-            if ip1 >= MAX_GATES as i32 {
-                // Input is a primary input
-                let input_num = ip1 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    let input_idx = (input_num as usize).min(MAX_COL - 1);
-                    let input_gate = mapping.crossbar[0][input_idx].clone();
-                    mapping.crossbar[0][col].inputs[0] = Some(Box::new(input_gate));
-                }
-            } else if ip1 > 0 {
-                // Input is a gate output
-                if let Some(gate_idx) = inv_map.get(&ip1) {
-                    if let Some(ref gate_map) = circuit.gates[*gate_idx].gate_map {
-                        let input_gate = (**gate_map).clone();
-                        mapping.crossbar[0][col].inputs[0] = Some(Box::new(input_gate));
-                    }
-                }
-            }
-
-            // Connect the second input for NOR gates
-            if fanin > 1 && ip2 != -1 {
-                if ip2 >= MAX_GATES as i32 {
-                    // Input is a primary input
-                    let input_num = ip2 - MAX_GATES as i32;
-                    if input_num < circuit.num_inputs as i32 {
-                        let input_idx = (input_num as usize).min(MAX_COL - 1);
-                        let input_gate = mapping.crossbar[0][input_idx].clone();
-                        mapping.crossbar[0][col].inputs[1] = Some(Box::new(input_gate));
-                    }
-                } else if ip2 > 0 {
-                    // Input is a gate output
-                    if let Some(gate_idx) = inv_map.get(&ip2) {
-                        if let Some(ref gate_map) = circuit.gates[*gate_idx].gate_map {
-                            let input_gate = (**gate_map).clone();
-                            mapping.crossbar[0][col].inputs[1] = Some(Box::new(input_gate));
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // Update max_jdx
-    mapping.max_jdx = max_jdx.load(Ordering::SeqCst);
-
-    Ok(mapping)
-}
-
 // We'll use a simpler approach for list scheduling parallelism
 pub fn compute_list_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
     let gate_count = circuit.num_gates;
@@ -487,7 +313,9 @@ pub fn compute_list_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
     // Create a map for fast gate lookup by output
     let gate_map: DashMap<i32, usize> = DashMap::new();
     for (i, gate) in circuit.gates.iter().enumerate() {
-        gate_map.insert(gate.out, i);
+        for out in gate.outs() {
+            gate_map.insert(out, i);
+        }
     }
 
     // Process gates in order of increasing slack
@@ -541,182 +369,6 @@ pub fn compute_list_schedule_parallel(circuit: &mut Circuit) -> Result<()> {
 }
 
 // Helper for parallel processing of inputs
-// Parallel compact mapping implementation
-pub fn create_compact_mapping_parallel(circuit: &mut Circuit) -> Result<CrossbarMapping> {
-    let gate_count = circuit.num_gates;
-
-    // For small circuits, use sequential algorithm
-    if gate_count < 100 {
-        return Ok(crate::mapper::create_compact_mapping(circuit));
-    }
-
-    info!("Creating compact mapping in parallel");
-
-    let mut mapping = CrossbarMapping::new();
-
-    // Reset crossbar array in parallel
-    mapping.crossbar.par_iter_mut().for_each(|row| {
-        row.iter_mut().for_each(|cell| {
-            *cell = MemristiveGate::default();
-        });
-    });
-
-    // Reset gate mappings
-    for i in 0..circuit.num_gates {
-        circuit.gates[i].gate_map = None;
-    }
-
-    // Sort gates by list time to group them efficiently
-    circuit.gates.sort_by(|a, b| a.list_time.cmp(&b.list_time));
-
-    // Create concurrent map for gate lookup
-    let inv_map = DashMap::new();
-    for i in 0..circuit.num_gates {
-        inv_map.insert(circuit.gates[i].out, i);
-    }
-
-    // Handle case where there are no inputs
-    if circuit.num_inputs == 0 {
-        return Ok(mapping);
-    }
-
-    // Group gates by list_time for more efficient parallelism (with safe maximum level)
-    let max_list_time = (circuit.max_list as usize).min(MAX_LEVELS - 1);
-    let mut gates_by_time: Vec<Vec<usize>> = vec![Vec::new(); max_list_time + 1];
-    for (i, gate) in circuit.gates.iter().enumerate() {
-        if gate.list_time >= 0 && (gate.list_time as usize) <= max_list_time {
-            gates_by_time[gate.list_time as usize].push(i);
-        }
-    }
-
-    // Map primary inputs to the first row of the crossbar
-    let max_inputs = circuit.num_inputs.min(MAX_COL);
-    for j in 0..max_inputs {
-        mapping.crossbar[0][j].value = (MAX_GATES + j) as i32;
-        mapping.crossbar[0][j].idx = 0;
-        mapping.crossbar[0][j].jdx = j as i32;
-    }
-
-    // Update max_jdx for primary inputs
-    mapping.max_jdx = (max_inputs as i32).saturating_sub(1);
-
-    // Shared counter for max dimensions
-    let max_idx = Arc::new(AtomicI32::new(0));
-    let max_jdx = Arc::new(AtomicI32::new(mapping.max_jdx));
-
-    // Process gates by time levels
-    for time in 0..=max_list_time {
-        // Skip invalid time steps
-        if time >= gates_by_time.len() {
-            continue;
-        }
-
-        let gates_at_time = &gates_by_time[time];
-
-        if gates_at_time.is_empty() {
-            continue;
-        }
-
-        // Each time level gets its own row in the crossbar
-        let row = time + 1; // Row 0 is for inputs
-        max_idx.fetch_max(row as i32, Ordering::SeqCst);
-
-        // Collect gate info for all gates in this time level
-        let gate_info: Vec<_> = gates_at_time
-            .iter()
-            .enumerate()
-            .map(|(idx, &gate_idx)| {
-                let gate = &circuit.gates[gate_idx];
-
-                (
-                    gate_idx,
-                    gate.out,
-                    gate.fanin,
-                    gate.inputs[0],
-                    if gate.fanin > 1 { gate.inputs[1] } else { -1 },
-                    idx,
-                    gate.list_time,
-                )
-            })
-            .collect();
-
-        // First pass: set up the gates in the crossbar
-        for &(_, out, fanin, _, _, column, list_time) in &gate_info {
-            let col = column.min(MAX_COL - 1);
-            let safe_row = row.min(MAX_ROW - 1);
-
-            max_jdx.fetch_max(col as i32, Ordering::SeqCst);
-
-            mapping.crossbar[safe_row][col].fanin = fanin;
-            mapping.crossbar[safe_row][col].value = out;
-            mapping.crossbar[safe_row][col].jdx = col as i32;
-            mapping.crossbar[safe_row][col].idx = safe_row as i32;
-            mapping.crossbar[safe_row][col].list_time = list_time;
-        }
-
-        // Second pass: set up gate mappings
-        for &(gate_idx, _, _, _, _, column, _) in &gate_info {
-            let col = column.min(MAX_COL - 1);
-            let safe_row = row.min(MAX_ROW - 1);
-
-            let gate_map = Box::new(mapping.crossbar[safe_row][col].clone());
-            circuit.gates[gate_idx].gate_map = Some(gate_map);
-        }
-
-        // Third pass: connect inputs
-        for &(_, _, fanin, ip1, ip2, column, _) in &gate_info {
-            let col = column.min(MAX_COL - 1);
-            let safe_row = row.min(MAX_ROW - 1);
-
-            // Connect the first input
-            if ip1 >= MAX_GATES as i32 {
-                // Input is a primary input
-                let input_num = ip1 - MAX_GATES as i32;
-                if input_num < circuit.num_inputs as i32 {
-                    let input_idx = (input_num as usize).min(MAX_COL - 1);
-                    let input_gate = mapping.crossbar[0][input_idx].clone();
-                    mapping.crossbar[safe_row][col].inputs[0] = Some(Box::new(input_gate));
-                }
-            } else if ip1 > 0 {
-                // Input is a gate output
-                if let Some(gate_idx) = inv_map.get(&ip1) {
-                    if let Some(ref gate_map) = circuit.gates[*gate_idx].gate_map {
-                        let input_gate = (**gate_map).clone();
-                        mapping.crossbar[safe_row][col].inputs[0] = Some(Box::new(input_gate));
-                    }
-                }
-            }
-
-            // Connect the second input for NOR gates
-            if fanin > 1 && ip2 != -1 {
-                if ip2 >= MAX_GATES as i32 {
-                    // Input is a primary input
-                    let input_num = ip2 - MAX_GATES as i32;
-                    if input_num < circuit.num_inputs as i32 {
-                        let input_idx = (input_num as usize).min(MAX_COL - 1);
-                        let input_gate = mapping.crossbar[0][input_idx].clone();
-                        mapping.crossbar[safe_row][col].inputs[1] = Some(Box::new(input_gate));
-                    }
-                } else if ip2 > 0 {
-                    // Input is a gate output
-                    if let Some(gate_idx) = inv_map.get(&ip2) {
-                        if let Some(ref gate_map) = circuit.gates[*gate_idx].gate_map {
-                            let input_gate = (**gate_map).clone();
-                            mapping.crossbar[safe_row][col].inputs[1] = Some(Box::new(input_gate));
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // Update max dimensions
-    mapping.max_idx = max_idx.load(Ordering::SeqCst);
-    mapping.max_jdx = max_jdx.load(Ordering::SeqCst);
-
-    Ok(mapping)
-}
-
 pub fn find_primary_inputs_parallel(circuit: &mut Circuit) -> Result<()> {
     // For small circuits, use sequential algorithm
     if circuit.num_gates < 50 {
@@ -0,0 +1,491 @@
+//parser/aig.rs
+// And-inverter graph shared by the AIGER, BLIF and Verilog readers. Literal
+// 2v + c is variable v, complemented if c is 1; variable 0 is constant false,
+// variables 1..=inputs are primary inputs and the rest are AND nodes, each
+// after its fanins
+use std::collections::HashMap;
+use std::hash::Hash;
+use anyhow::{Result, bail, ensure};
+
+use crate::{Circuit, TableGate, MAX_GATES, MAX_PI};
+
+pub const FALSE: u32 = 0;
+pub const TRUE: u32 = 1;
+
+#[derive(Debug, Default)]
+pub struct Aig {
+    inputs: usize,
+    ands: Vec<(u32, u32)>,
+    outputs: Vec<u32>,
+    hashed: HashMap<(u32, u32), u32>,
+}
+
+impl Aig {
+    pub fn new(inputs: usize) -> Self {
+        Self { inputs, ..Default::default() }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn num_ands(&self) -> usize {
+        self.ands.len()
+    }
+
+    // Literal of primary input k, counted from 0
+    pub fn input(&self, k: usize) -> u32 {
+        2 * (k as u32 + 1)
+    }
+
+    // AND with constant folding and structural hashing
+    pub fn and(&mut self, a: u32, b: u32) -> u32 {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == FALSE || a == b ^ 1 {
+            return FALSE;
+        }
+        if a == TRUE || a == b {
+            return b;
+        }
+        if let Some(&lit) = self.hashed.get(&(a, b)) {
+            return lit;
+        }
+        let lit = 2 * (self.inputs + 1 + self.ands.len()) as u32;
+        self.ands.push((a, b));
+        self.hashed.insert((a, b), lit);
+        lit
+    }
+
+    pub fn or(&mut self, a: u32, b: u32) -> u32 {
+        self.and(a ^ 1, b ^ 1) ^ 1
+    }
+
+    pub fn xor(&mut self, a: u32, b: u32) -> u32 {
+        let (p, q) = (self.and(a, b ^ 1), self.and(a ^ 1, b));
+        self.or(p, q)
+    }
+
+    pub fn output(&mut self, lit: u32) {
+        self.outputs.push(lit);
+    }
+
+    // XOR nodes: AND(NOT p, NOT q) with p = AND(x, y) and q = AND(NOT x, NOT y)
+    // both read only by it, so that the node is x ^ y. Each maps to literals
+    // (a, b) whose XNOR it is, or whose XNOR is its complement if the flag is
+    // set, chosen so that a and b are read in the polarity their gates hold
+    fn xnor_nodes(&self) -> HashMap<usize, (u32, u32, bool)> {
+        let first_and = self.inputs + 1;
+        let var = |lit: u32| (lit >> 1) as usize;
+        let mut reads = vec![0usize; first_and + self.ands.len()];
+        for &lit in self.ands.iter().flat_map(|(a, b)| [a, b]).chain(&self.outputs) {
+            reads[var(lit)] += 1;
+        }
+
+        let mut nodes: HashMap<usize, (u32, u32, bool)> = HashMap::new();
+        for (k, &(p, q)) in self.ands.iter().enumerate() {
+            if p & 1 == 0 || q & 1 == 0 || var(p) < first_and || var(q) < first_and || reads[var(p)] > 1 || reads[var(q)] > 1 {
+                continue;
+            }
+            let (x, y) = self.ands[var(p) - first_and];
+            let (c, d) = self.ands[var(q) - first_and];
+            if (c, d) != ((x ^ 1).min(y ^ 1), (x ^ 1).max(y ^ 1)) {
+                continue;
+            }
+            // Reading a literal costs an inverter unless its gate holds that polarity
+            let held = |lit: u32| (lit & 1 == 1) == nodes.get(&var(lit)).is_some_and(|n| n.2);
+            // x ^ y = XNOR(x, NOT y) = XNOR(NOT x, y) = NOT XNOR(x, y)
+            let node = match (held(x), held(y ^ 1)) {
+                (true, true) => (x, y ^ 1, false),
+                (false, false) => (x ^ 1, y, false),
+                (true, false) => (x, y, true),
+                (false, true) => (x ^ 1, y ^ 1, true),
+            };
+            nodes.insert(first_and + k, node);
+        }
+        nodes
+    }
+
+    // Lowers the graph into NOR/NOT gates: AND(a, b) = NOR(NOT a, NOT b), with
+    // an inverter only where a literal is read in the polarity its gate does
+    // not hold. XOR nodes become XNOR(a, b) = NOR(NOR(a, t), NOR(b, t)) with
+    // t = NOR(a, b): four NORs and no inverter, where their three ANDs need
+    // both polarities of a and b. Output k becomes signal n<k> (the
+    // OUT_BIAS-marked n<10000+k> of the text format); other gates are
+    // numbered after the outputs. Only logic some output depends on is built
+    pub fn to_circuit(&self, circuit: &mut Circuit) -> Result<()> {
+        ensure!(self.inputs <= MAX_PI, "{} primary inputs exceed the maximum of {}", self.inputs, MAX_PI);
+        let first_and = self.inputs + 1;
+        let vars = first_and + self.ands.len();
+        let var = |lit: u32| (lit >> 1) as usize;
+        let xnors = self.xnor_nodes();
+        // Polarity of each variable its gate holds: 1 for XOR nodes built as
+        // the XNOR of their complement
+        let held = |v: usize| xnors.get(&v).map_or(0, |n| n.2 as u32);
+
+        // Outputs take over the gate of the first node they read in the
+        // polarity it holds
+        let mut claimed: HashMap<usize, i32> = HashMap::new();
+        for (k, &lit) in self.outputs.iter().enumerate() {
+            if lit & 1 == held(var(lit)) && var(lit) >= first_and {
+                claimed.entry(var(lit)).or_insert(k as i32 + 1);
+            }
+        }
+
+        // Polarities each variable is read in, from the outputs backwards
+        let (mut need_pos, mut need_neg) = (vec![false; vars], vec![false; vars]);
+        let read = |lit: u32, pos: &mut [bool], neg: &mut [bool]| {
+            if lit & 1 == 1 { neg[var(lit)] = true } else { pos[var(lit)] = true }
+        };
+        for (k, &lit) in self.outputs.iter().enumerate() {
+            match claimed.get(&var(lit)) {
+                Some(&id) if id == k as i32 + 1 && lit & 1 == held(var(lit)) => read(lit, &mut need_pos, &mut need_neg),
+                // Other outputs are NOT(NOT lit)
+                _ => read(lit ^ 1, &mut need_pos, &mut need_neg),
+            }
+        }
+        for v in (first_and..vars).rev() {
+            if need_pos[v] || need_neg[v] {
+                // The XNOR template reads its literals as they are, and NOR
+                // each fanin in the opposite polarity
+                let reads = match xnors.get(&v) {
+                    Some(&(a, b, flipped)) => {
+                        if flipped { need_neg[v] = true } else { need_pos[v] = true }
+                        [a, b]
+                    }
+                    None => {
+                        need_pos[v] = true;
+                        let (a, b) = self.ands[v - first_and];
+                        [a ^ 1, b ^ 1]
+                    }
+                };
+                for lit in reads {
+                    read(lit, &mut need_pos, &mut need_neg);
+                }
+            }
+        }
+
+        let mut emitter = Emitter {
+            circuit,
+            next_id: self.outputs.len() as i32 + 1,
+            pos: HashMap::new(),
+            neg: HashMap::new(),
+        };
+        for k in 0..self.inputs {
+            emitter.pos.insert(k + 1, (MAX_GATES + k) as i32);
+            if need_neg[k + 1] {
+                emitter.invert(k + 1)?;
+            }
+        }
+        for v in (first_and..vars).filter(|&v| need_pos[v] || need_neg[v]) {
+            let inputs = match xnors.get(&v) {
+                Some(&(a, b, _)) => {
+                    let (a, b) = (emitter.literal(a)?, emitter.literal(b)?);
+                    let t = emitter.allocate()?;
+                    emitter.gate(t, &[a, b], false)?;
+                    let (ta, tb) = (emitter.allocate()?, emitter.allocate()?);
+                    emitter.gate(ta, &[a, t], false)?;
+                    emitter.gate(tb, &[b, t], false)?;
+                    [ta, tb]
+                }
+                None => {
+                    let (a, b) = self.ands[v - first_and];
+                    [emitter.literal(a ^ 1)?, emitter.literal(b ^ 1)?]
+                }
+            };
+            let out = match claimed.get(&v) {
+                Some(&id) => id,
+                None => emitter.allocate()?,
+            };
+            emitter.gate(out, &inputs, claimed.contains_key(&v))?;
+            if held(v) == 1 {
+                emitter.neg.insert(v, out);
+                if need_pos[v] {
+                    emitter.invert(v)?;
+                }
+            } else {
+                emitter.pos.insert(v, out);
+                if need_neg[v] {
+                    emitter.invert(v)?;
+                }
+            }
+        }
+
+        for (k, &lit) in self.outputs.iter().enumerate() {
+            let id = k as i32 + 1;
+            let v = var(lit);
+            if claimed.get(&v) == Some(&id) && lit & 1 == held(v) {
+                continue;
+            }
+            if v == 0 {
+                ensure!(self.inputs > 0, "Constant output {} needs a primary input to tie off from", id);
+                // NOR(x0, NOT x0) is false
+                let x0 = MAX_GATES as i32;
+                let not_x0 = match emitter.neg.get(&1) {
+                    Some(&s) => s,
+                    None => emitter.invert(1)?,
+                };
+                if lit == FALSE {
+                    emitter.gate(id, &[x0, not_x0], true)?;
+                } else {
+                    let tie = emitter.allocate()?;
+                    emitter.gate(tie, &[x0, not_x0], false)?;
+                    emitter.gate(id, &[tie], true)?;
+                }
+                continue;
+            }
+            let source = emitter.literal(lit ^ 1)?;
+            emitter.gate(id, &[source], true)?;
+        }
+
+        let circuit = emitter.circuit;
+        circuit.num_inputs = self.inputs;
+        circuit.num_outputs = self.outputs.len();
+        super::finish_netlist(circuit)
+    }
+}
+
+// Defined names reachable from `roots`, each after the names it reads, so
+// that readers can build them without recursing down long chains. `reads`
+// returns None for names without a definition (inputs, constants)
+pub(super) fn definition_order<K, F>(roots: &[K], reads: F) -> Result<Vec<K>>
+where
+    K: Clone + Eq + Hash + std::fmt::Display,
+    F: Fn(&K) -> Option<Vec<K>>,
+{
+    // false while a name is on the stack, true once it is ordered
+    let mut state: HashMap<K, bool> = HashMap::new();
+    let mut order = Vec::new();
+    for root in roots {
+        let Some(fanins) = reads(root) else { continue };
+        if state.contains_key(root) {
+            continue;
+        }
+        state.insert(root.clone(), false);
+        let mut stack = vec![(root.clone(), fanins, 0)];
+        while let Some((name, fanins, next)) = stack.last_mut() {
+            let Some(fanin) = fanins.get(*next).cloned() else {
+                state.insert(name.clone(), true);
+                order.push(name.clone());
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+            match state.get(&fanin) {
+                Some(true) => {}
+                Some(false) => bail!("Signal {} depends on itself", fanin),
+                None => if let Some(reads) = reads(&fanin) {
+                    state.insert(fanin.clone(), false);
+                    stack.push((fanin, reads, 0));
+                },
+            }
+        }
+    }
+    Ok(order)
+}
+
+struct Emitter<'a> {
+    circuit: &'a mut Circuit,
+    next_id: i32,
+    // Signals holding each variable and its complement
+    pos: HashMap<usize, i32>,
+    neg: HashMap<usize, i32>,
+}
+
+impl Emitter<'_> {
+    fn allocate(&mut self) -> Result<i32> {
+        ensure!(self.next_id < MAX_GATES as i32, "Netlist exceeds the maximum of {} gates", MAX_GATES);
+        self.next_id += 1;
+        Ok(self.next_id - 1)
+    }
+
+    fn gate(&mut self, out: i32, inputs: &[i32], is_output: bool) -> Result<()> {
+        ensure!(self.circuit.num_gates < MAX_GATES, "Netlist exceeds the maximum of {} gates", MAX_GATES);
+        let mut gate = TableGate { out, is_output, ..Default::default() };
+        gate.set_inputs(inputs);
+        self.circuit.gates.push(gate);
+        self.circuit.num_gates += 1;
+        Ok(())
+    }
+
+    // NOT gate for the polarity of a variable its gate does not hold
+    fn invert(&mut self, v: usize) -> Result<i32> {
+        let out = self.allocate()?;
+        let (source, to_pos) = match self.pos.get(&v) {
+            Some(&s) => (s, false),
+            None => (self.neg[&v], true),
+        };
+        self.gate(out, &[source], false)?;
+        if to_pos { self.pos.insert(v, out) } else { self.neg.insert(v, out) };
+        Ok(out)
+    }
+
+    // Signal of a literal whose polarity has already been built
+    fn literal(&self, lit: u32) -> Result<i32> {
+        let v = (lit >> 1) as usize;
+        let map = if lit & 1 == 0 { &self.pos } else { &self.neg };
+        match map.get(&v) {
+            Some(&s) => Ok(s),
+            None => bail!("Literal {} is read before it is built", lit),
+        }
+    }
+}
+
+// Reads an AIGER file, ASCII (aag) or binary (aig). Only combinational
+// graphs are accepted; symbol tables and comments are ignored
+pub fn parse_aiger(data: &[u8], circuit: &mut Circuit) -> Result<()> {
+    read_aiger(data, circuit, false).map(|_| ())
+}
+
+// The same with latches, cut open: latch k's output becomes the input after
+// the primary ones and its next state the output after the primary ones.
+// Returns the number of latches; their initial values are ignored
+pub fn parse_sequential_aiger(data: &[u8], circuit: &mut Circuit) -> Result<usize> {
+    read_aiger(data, circuit, true)
+}
+
+fn read_aiger(data: &[u8], circuit: &mut Circuit, sequential: bool) -> Result<usize> {
+    let header_end = data.iter().position(|&b| b == b'\n').unwrap_or(data.len());
+    let header = std::str::from_utf8(&data[..header_end])?;
+    let fields: Vec<&str> = header.split_whitespace().collect();
+    let binary = match fields.first() {
+        Some(&"aag") => false,
+        Some(&"aig") => true,
+        _ => bail!("Not an AIGER file (header '{}')", header),
+    };
+    let numbers: Vec<usize> = fields[1..].iter()
+        .map(|f| f.parse().map_err(|_| anyhow::anyhow!("Invalid AIGER header '{}'", header)))
+        .collect::<Result<_>>()?;
+    ensure!(numbers.len() >= 5, "AIGER header needs M I L O A, got '{}'", header);
+    let (max_var, inputs, latches, outputs, ands) = (numbers[0], numbers[1], numbers[2], numbers[3], numbers[4]);
+    ensure!(sequential || latches == 0, "AIGER file has {} latches; only combinational circuits are supported", latches);
+    ensure!(numbers[5..].iter().all(|&n| n == 0), "AIGER bad-state, constraint and fairness sections are not supported");
+
+    let mut lines = Lines { data, pos: (header_end + 1).min(data.len()) };
+    let mut aig = Aig::new(inputs + latches);
+    // AIGER variable -> literal in `aig`
+    let mut map: HashMap<usize, u32> = HashMap::new();
+    map.insert(0, FALSE);
+
+    if binary {
+        for k in 0..inputs {
+            map.insert(k + 1, aig.input(k));
+        }
+    } else {
+        for k in 0..inputs {
+            let lit = lines.number()?;
+            ensure!(lit % 2 == 0 && lit > 0, "Invalid AIGER input literal {}", lit);
+            map.insert(lit / 2, aig.input(k));
+        }
+    }
+    // Latch lines give the latch literal (implicit in binary files), its
+    // next state and optionally its initial value
+    let mut next_lits = Vec::with_capacity(latches);
+    for k in 0..latches {
+        let fields = lines.fields()?;
+        let (lit, rest) = if binary { (2 * (inputs + k + 1), &fields[..]) } else { (fields[0], &fields[1..]) };
+        ensure!(lit % 2 == 0 && lit > 0 && !rest.is_empty() && rest.len() <= 2, "Invalid AIGER latch line {}", k);
+        map.insert(lit / 2, aig.input(inputs + k));
+        next_lits.push(rest[0]);
+    }
+    let mut output_lits: Vec<usize> = (0..outputs).map(|_| lines.number()).collect::<Result<_>>()?;
+    if binary && outputs + latches > 0 {
+        lines.end_line();
+    }
+    output_lits.extend(next_lits);
+
+    let mut defs: HashMap<usize, (usize, usize)> = HashMap::new();
+    for i in 0..ands {
+        let (lhs, rhs0, rhs1) = if binary {
+            let lhs = 2 * (inputs + latches + i + 1);
+            let rhs0 = lhs.checked_sub(lines.delta()?);
+            let rhs1 = rhs0.and_then(|r| r.checked_sub(lines.delta().ok()?));
+            match (rhs0, rhs1) {
+                (Some(rhs0), Some(rhs1)) => (lhs, rhs0, rhs1),
+                _ => bail!("Invalid binary AIGER delta for AND {}", lhs),
+            }
+        } else {
+            let lhs = lines.number()?;
+            (lhs, lines.number()?, lines.number()?)
+        };
+        ensure!(lhs % 2 == 0 && lhs / 2 <= max_var, "Invalid AIGER AND literal {}", lhs);
+        ensure!(defs.insert(lhs / 2, (rhs0, rhs1)).is_none(), "AIGER variable {} is defined twice", lhs / 2);
+    }
+
+    let lookup = |map: &HashMap<usize, u32>, lit: usize| match map.get(&(lit / 2)) {
+        Some(&mapped) => Ok(mapped ^ (lit & 1) as u32),
+        None => Err(anyhow::anyhow!("AIGER literal {} is never defined", lit)),
+    };
+    let roots: Vec<usize> = output_lits.iter().map(|lit| lit / 2).collect();
+    for v in definition_order(&roots, |v| defs.get(v).map(|&(a, b)| vec![a / 2, b / 2]))? {
+        let (a, b) = defs[&v];
+        let and = aig.and(lookup(&map, a)?, lookup(&map, b)?);
+        map.insert(v, and);
+    }
+    for lit in output_lits {
+        let lit = lookup(&map, lit)?;
+        aig.output(lit);
+    }
+    aig.to_circuit(circuit)?;
+    Ok(latches)
+}
+
+// Cursor over the ASCII lines and binary tail of an AIGER file
+struct Lines<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Lines<'_> {
+    // Next whitespace-separated unsigned number
+    fn number(&mut self) -> Result<usize> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        ensure!(self.pos > start, "Truncated AIGER file at byte {}", start);
+        Ok(std::str::from_utf8(&self.data[start..self.pos])?.parse()?)
+    }
+
+    // The numbers of the next line that holds any
+    fn fields(&mut self) -> Result<Vec<usize>> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let end = self.data[self.pos..].iter().position(|&b| b == b'\n').map_or(self.data.len(), |n| self.pos + n);
+        let line = std::str::from_utf8(&self.data[self.pos..end])?;
+        ensure!(!line.trim().is_empty(), "Truncated AIGER file at byte {}", self.pos);
+        let fields = line.split_whitespace()
+            .map(|f| f.parse().map_err(|_| anyhow::anyhow!("Invalid AIGER line '{}'", line)))
+            .collect::<Result<_>>()?;
+        self.pos = end;
+        Ok(fields)
+    }
+
+    // Moves past the end of the current line
+    fn end_line(&mut self) {
+        while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+            self.pos += 1;
+        }
+        self.pos += 1;
+    }
+
+    // Variable-length 7-bit delta of the binary format
+    fn delta(&mut self) -> Result<usize> {
+        let (mut value, mut shift) = (0usize, 0);
+        loop {
+            let Some(&byte) = self.data.get(self.pos) else {
+                bail!("Truncated binary AIGER file");
+            };
+            self.pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+            ensure!(shift < 64, "Invalid binary AIGER delta");
+        }
+    }
+}
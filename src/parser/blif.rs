@@ -0,0 +1,196 @@
+//parser/blif.rs
+// Combinational BLIF: .inputs, .outputs and single-output .names covers.
+// Covers may appear in any order; latches and subcircuits are rejected. A file
+// may hold several models, each a circuit of its own. The sequential reader
+// takes a single model with latches
+use std::collections::{HashMap, HashSet};
+use anyhow::{Context, Result, bail, ensure};
+
+use super::aig::{definition_order, Aig, FALSE, TRUE};
+use crate::Circuit;
+
+struct Cover {
+    inputs: Vec<String>,
+    // Input patterns over '0', '1' and '-', and the value they set the output to
+    rows: Vec<(Vec<u8>, bool)>,
+}
+
+// A file of a single model
+pub fn parse_blif(text: &str, circuit: &mut Circuit) -> Result<()> {
+    let mut models = split_models(text)?;
+    let names: Vec<&str> = models.iter().map(|(name, _)| name.as_str()).collect();
+    ensure!(models.len() == 1, "BLIF file holds {} models ({})", models.len(), names.join(", "));
+    let (_, lines) = models.remove(0);
+    parse_model(&lines, circuit, false).map(|_| ())
+}
+
+// A single model with .latch lines, cut open as AIGER latches are: latch k's
+// output is the input after the primary ones and its input the output after
+// them. Returns the number of latches; their types and initial values are
+// ignored
+pub fn parse_sequential_blif(text: &str, circuit: &mut Circuit) -> Result<usize> {
+    let mut models = split_models(text)?;
+    ensure!(models.len() == 1, "A sequential BLIF file holds one model, not {}", models.len());
+    let (_, lines) = models.remove(0);
+    parse_model(&lines, circuit, true)
+}
+
+// Every model of a file with its name, in file order
+pub fn parse_blif_models(text: &str) -> Result<Vec<(String, Circuit)>> {
+    split_models(text)?.into_iter()
+        .map(|(name, lines)| {
+            let mut circuit = Circuit::new();
+            parse_model(&lines, &mut circuit, false).context(format!("In BLIF model {}", name))?;
+            Ok((name, circuit))
+        })
+        .collect()
+}
+
+// Lines of each model, '\'-continued lines joined and comments dropped. Lines
+// outside a .model block form an unnamed model, as in files that leave it out
+fn split_models(text: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let mut lines = Vec::new();
+    let mut pending = String::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        if let Some(head) = line.trim_end().strip_suffix('\\') {
+            pending.push_str(head);
+            pending.push(' ');
+            continue;
+        }
+        pending.push_str(line);
+        lines.push(std::mem::take(&mut pending));
+    }
+
+    let mut models: Vec<(String, Vec<String>)> = Vec::new();
+    let mut open = false;
+    for line in lines {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => {}
+            Some(".model") => {
+                models.push((words.next().unwrap_or("").to_string(), Vec::new()));
+                open = true;
+            }
+            Some(".end") => open = false,
+            Some(_) => {
+                if !open {
+                    models.push((String::new(), Vec::new()));
+                    open = true;
+                }
+                models.last_mut().expect("open model").1.push(line);
+            }
+        }
+    }
+    if models.is_empty() {
+        models.push((String::new(), Vec::new()));
+    }
+
+    // Unnamed models are numbered; names must tell the models apart
+    let mut seen = HashSet::new();
+    for (k, (name, _)) in models.iter_mut().enumerate() {
+        if name.is_empty() {
+            *name = format!("model{}", k);
+        }
+        ensure!(seen.insert(name.clone()), "BLIF model {} is defined twice", name);
+    }
+    Ok(models)
+}
+
+fn parse_model(lines: &[String], circuit: &mut Circuit, sequential: bool) -> Result<usize> {
+    let (mut inputs, mut outputs) = (Vec::new(), Vec::new());
+    // Input and output of each latch
+    let mut latches: Vec<(String, String)> = Vec::new();
+    let mut covers: HashMap<String, Cover> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in lines {
+        let mut words = line.split_whitespace();
+        let Some(first) = words.next() else { continue };
+        if !first.starts_with('.') {
+            let Some(name) = &current else {
+                bail!("Cover row '{}' outside a .names block", line.trim());
+            };
+            let cover = covers.get_mut(name).expect("current cover exists");
+            let (pattern, value) = match (cover.inputs.len(), words.next()) {
+                (0, None) => ("", first),
+                (_, Some(value)) => (first, value),
+                _ => bail!("Cover row '{}' of {} has no output value", line.trim(), name),
+            };
+            ensure!(pattern.len() == cover.inputs.len() && pattern.bytes().all(|b| matches!(b, b'0' | b'1' | b'-')),
+                "Cover row '{}' of {} does not match its {} inputs", line.trim(), name, cover.inputs.len());
+            let value = match value {
+                "1" => true,
+                "0" => false,
+                _ => bail!("Cover row '{}' of {} has output value '{}'", line.trim(), name, value),
+            };
+            cover.rows.push((pattern.as_bytes().to_vec(), value));
+            continue;
+        }
+
+        current = None;
+        match first {
+            ".inputs" => inputs.extend(words.map(String::from)),
+            ".outputs" => outputs.extend(words.map(String::from)),
+            ".names" => {
+                let mut signals: Vec<String> = words.map(String::from).collect();
+                let Some(name) = signals.pop() else { bail!(".names without signals") };
+                ensure!(!covers.contains_key(&name), "Signal {} is defined twice", name);
+                covers.insert(name.clone(), Cover { inputs: signals, rows: Vec::new() });
+                current = Some(name);
+            }
+            ".latch" => {
+                ensure!(sequential, "BLIF latches are not supported; only combinational circuits are");
+                match (words.next(), words.next()) {
+                    (Some(input), Some(output)) => latches.push((input.to_string(), output.to_string())),
+                    _ => bail!("Latch '{}' needs an input and an output", line.trim()),
+                }
+            }
+            other => bail!("Unsupported BLIF construct {}", other),
+        }
+    }
+
+    inputs.extend(latches.iter().map(|(_, output)| output.clone()));
+    outputs.extend(latches.iter().map(|(input, _)| input.clone()));
+    let mut aig = Aig::new(inputs.len());
+    let mut values: HashMap<String, u32> = HashMap::new();
+    for (k, name) in inputs.iter().enumerate() {
+        ensure!(values.insert(name.clone(), aig.input(k)).is_none(), "Input {} is listed twice", name);
+    }
+    let order = definition_order(&outputs, |name| covers.get(name).map(|c| c.inputs.clone()))?;
+    for name in order {
+        let lit = build_cover(&name, &covers[&name], &values, &mut aig)?;
+        values.insert(name, lit);
+    }
+    for name in &outputs {
+        match values.get(name) {
+            Some(&lit) => aig.output(lit),
+            None => bail!("Signal {} is never defined", name),
+        }
+    }
+    aig.to_circuit(circuit)?;
+    Ok(latches.len())
+}
+
+// Sum of the rows' products; rows with output 0 describe the off-set
+fn build_cover(name: &str, cover: &Cover, values: &HashMap<String, u32>, aig: &mut Aig) -> Result<u32> {
+    let fanins: Vec<u32> = cover.inputs.iter()
+        .map(|input| values.get(input).copied()
+            .ok_or_else(|| anyhow::anyhow!("Signal {} is never defined", input)))
+        .collect::<Result<_>>()?;
+
+    let on_set = cover.rows.first().is_none_or(|&(_, value)| value);
+    ensure!(cover.rows.iter().all(|&(_, value)| value == on_set), "Cover of {} mixes on-set and off-set rows", name);
+    let mut sum = FALSE;
+    for (pattern, _) in &cover.rows {
+        let mut product = TRUE;
+        for (&bit, &fanin) in pattern.iter().zip(&fanins) {
+            product = match bit {
+                b'1' => aig.and(product, fanin),
+                b'0' => aig.and(product, fanin ^ 1),
+                _ => product,
+            };
+        }
+        sum = aig.or(sum, product);
+    }
+    Ok(if on_set { sum } else { sum ^ 1 })
+}
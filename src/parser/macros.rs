@@ -0,0 +1,232 @@
+//parser/macros.rs
+// Word-level blocks a netlist line can instantiate, bit 0 first:
+//
+//   ADD<w>   s0 .. s<w-1> [cout] = a0 .. a<w-1> b0 .. b<w-1> [cin]
+//   MUX<k>   out = d0 .. d<k-1> s0 .. s<log2 k - 1>      (k a power of two)
+//   XOR_TREE out = i0 i1 ...
+//
+// Any operand list may use runs such as `x0..x7` or `n10008..n10001`.
+// Blocks expand into fixed NOR/NOT subcircuits: a 9-NOR full adder (6 NORs
+// for a half adder without cin), 3 NORs per 2:1 mux stage plus one NOT per
+// select bit, and a balanced tree of 4-NOR XNORs
+use anyhow::{Result, Context, bail, ensure};
+
+use super::variable_id;
+use crate::{TableGate, MAX_GATES, OUT_BIAS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Block {
+    Add(usize),
+    Mux(usize),
+    XorTree,
+}
+
+impl Block {
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "XOR_TREE" {
+            return Some(Block::XorTree);
+        }
+        let width = |digits: &str| digits.parse::<usize>().ok().filter(|&w| w > 0);
+        if let Some(w) = name.strip_prefix("ADD").and_then(width) {
+            return Some(Block::Add(w));
+        }
+        name.strip_prefix("MUX")
+            .and_then(width)
+            .filter(|k| k.is_power_of_two() && *k >= 2)
+            .map(Block::Mux)
+    }
+}
+
+// Gates of a macro line, or None if the line does not start with a block name
+pub fn expand_line(line: &str, temp_var: &mut i32) -> Result<Option<Vec<TableGate>>> {
+    let Some(name) = line.split_whitespace().next() else {
+        return Ok(None);
+    };
+    let Some(block) = Block::from_name(name) else {
+        return Ok(None);
+    };
+    let (lhs, rhs) = line[name.len()..].split_once('=')
+        .context("Macro line has no '='")?;
+
+    // Outputs carry the OUT_BIAS marker like ordinary lines
+    let mut outputs = Vec::new();
+    for id in signals(lhs)? {
+        let (id, marked) = if id >= OUT_BIAS as i32 { (id - OUT_BIAS as i32, true) } else { (id, false) };
+        ensure!(id < MAX_GATES as i32, "Primary input x{} cannot be driven by a macro", id - MAX_GATES as i32);
+        ensure!(!outputs.iter().any(|&(o, _)| o == id), "Signal n{} is driven twice", id);
+        outputs.push((id, marked));
+    }
+    let inputs: Vec<i32> = signals(rhs)?.into_iter()
+        .map(|id| if id >= OUT_BIAS as i32 { id - OUT_BIAS as i32 } else { id })
+        .collect();
+    let out: Vec<i32> = outputs.iter().map(|&(id, _)| id).collect();
+
+    let mut builder = Builder { gates: Vec::new(), temp_var };
+    match block {
+        Block::Add(w) => {
+            ensure!(out.len() == w || out.len() == w + 1,
+                "{} drives {} or {} signals, not {}", name, w, w + 1, out.len());
+            ensure!(inputs.len() == 2 * w || inputs.len() == 2 * w + 1,
+                "{} reads {} or {} signals, not {}", name, 2 * w, 2 * w + 1, inputs.len());
+            builder.adder(&inputs[..w], &inputs[w..2 * w], inputs.get(2 * w).copied(), &out[..w], out.get(w).copied())?;
+        }
+        Block::Mux(k) => {
+            let selects = k.trailing_zeros() as usize;
+            ensure!(out.len() == 1, "{} drives one signal, not {}", name, out.len());
+            ensure!(inputs.len() == k + selects,
+                "{} reads {} data and {} select signals, not {} signals", name, k, selects, inputs.len());
+            builder.mux(&inputs[..k], &inputs[k..], out[0])?;
+        }
+        Block::XorTree => {
+            ensure!(out.len() == 1, "{} drives one signal, not {}", name, out.len());
+            ensure!(inputs.len() >= 2, "{} needs at least two inputs", name);
+            builder.xor_tree(&inputs, out[0])?;
+        }
+    }
+
+    let mut gates = builder.gates;
+    for gate in &mut gates {
+        gate.is_output = outputs.iter().any(|&(id, marked)| marked && id == gate.out);
+    }
+    Ok(Some(gates))
+}
+
+// Signal ids of whitespace- or comma-separated variables and runs
+pub(crate) fn signals(text: &str) -> Result<Vec<i32>> {
+    let mut ids = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty()) {
+        let (first, last) = token.split_once("..").unwrap_or((token, token));
+        let (prefix, lo) = variable(first)?;
+        let (last_prefix, hi) = variable(last)?;
+        ensure!(prefix == last_prefix, "Run {} mixes signal kinds", token);
+        let run: Box<dyn Iterator<Item = usize>> = if lo <= hi {
+            Box::new(lo..=hi)
+        } else {
+            Box::new((hi..=lo).rev())
+        };
+        for index in run {
+            ids.push(variable_id(prefix, &index.to_string())?);
+        }
+    }
+    Ok(ids)
+}
+
+fn variable(token: &str) -> Result<(&'static str, usize)> {
+    let prefix = match token.as_bytes().first() {
+        Some(b'n') => "n",
+        Some(b'x') => "x",
+        _ => bail!("'{}' is not a signal", token),
+    };
+    let index = token[1..].parse()
+        .with_context(|| format!("'{}' is not a signal", token))?;
+    Ok((prefix, index))
+}
+
+struct Builder<'a> {
+    gates: Vec<TableGate>,
+    temp_var: &'a mut i32,
+}
+
+impl Builder<'_> {
+    // Adds a NOR (two inputs) or NOT (one input) driving `out`, or a new
+    // temporary when `out` is None
+    fn gate(&mut self, out: Option<i32>, inputs: &[i32]) -> Result<i32> {
+        let out = match out {
+            Some(out) => out,
+            None => {
+                ensure!(*self.temp_var < MAX_GATES as i32, "Too many temporary signals");
+                *self.temp_var += 1;
+                -(*self.temp_var - 1)
+            }
+        };
+        let mut gate = TableGate { out, ..Default::default() };
+        gate.set_inputs(inputs);
+        self.gates.push(gate);
+        Ok(out)
+    }
+
+    fn nor(&mut self, a: i32, b: i32) -> Result<i32> {
+        self.gate(None, &[a, b])
+    }
+
+    // XNOR(a, b) from four NORs; also returns NOR(a, b), which adders reuse
+    fn xnor(&mut self, a: i32, b: i32, out: Option<i32>) -> Result<(i32, i32)> {
+        let t1 = self.nor(a, b)?;
+        let t2 = self.nor(a, t1)?;
+        let t3 = self.nor(b, t1)?;
+        Ok((t1, self.gate(out, &[t2, t3])?))
+    }
+
+    // Ripple-carry adder: 9-NOR full adders, or a 6-NOR half adder at bit 0
+    // when there is no carry in
+    fn adder(&mut self, a: &[i32], b: &[i32], cin: Option<i32>, sum: &[i32], cout: Option<i32>) -> Result<()> {
+        let mut carry = cin;
+        for k in 0..a.len() {
+            let carry_out = if k + 1 == a.len() { cout } else { None };
+            let needs_carry = k + 1 < a.len() || cout.is_some();
+            let (t1, x) = self.xnor(a[k], b[k], None)?;
+            carry = match carry {
+                // sum = XNOR(XNOR(a, b), c); cout = NOR(NOR(a, b), NOR(XNOR(a, b), c))
+                Some(c) => {
+                    let u1 = self.nor(x, c)?;
+                    let u2 = self.nor(x, u1)?;
+                    let u3 = self.nor(c, u1)?;
+                    self.gate(Some(sum[k]), &[u2, u3])?;
+                    needs_carry.then(|| self.gate(carry_out, &[t1, u1])).transpose()?
+                }
+                // sum = NOR(NOR(a, b), XNOR(a, b)); cout = NOR(NOR(a, b), sum)
+                None => {
+                    let s = self.gate(Some(sum[k]), &[t1, x])?;
+                    needs_carry.then(|| self.gate(carry_out, &[t1, s])).transpose()?
+                }
+            };
+        }
+        Ok(())
+    }
+
+    // Tree of 2:1 muxes, select bit k choosing at stage k:
+    // NOR(NOR(d0, s), NOR(d1, NOT s))
+    fn mux(&mut self, data: &[i32], selects: &[i32], out: i32) -> Result<()> {
+        let mut level = data.to_vec();
+        for (k, &s) in selects.iter().enumerate() {
+            let ns = self.gate(None, &[s])?;
+            let last = k + 1 == selects.len();
+            let mut next = Vec::with_capacity(level.len() / 2);
+            for pair in level.chunks(2) {
+                let lo = self.nor(pair[0], s)?;
+                let hi = self.nor(pair[1], ns)?;
+                next.push(self.gate(last.then_some(out), &[lo, hi])?);
+            }
+            level = next;
+        }
+        Ok(())
+    }
+
+    // Balanced tree of XNORs; each node tracks whether it holds the parity of
+    // its leaves or its complement, and the root is an XNOR or an XOR
+    // (NOR(NOR(a, b), XNOR(a, b))) so that no inverter is needed
+    fn xor_tree(&mut self, inputs: &[i32], out: i32) -> Result<()> {
+        let mut level: Vec<(i32, bool)> = inputs.iter().map(|&i| (i, false)).collect();
+        while level.len() > 1 {
+            let root = level.len() == 2;
+            let mut next = Vec::with_capacity(level.len().div_ceil(2));
+            for pair in level.chunks(2) {
+                let &[(a, ia), (b, ib)] = pair else {
+                    next.push(pair[0]);
+                    continue;
+                };
+                // XNOR(a, b) holds the complement of a ^ b
+                let inverted = !(ia ^ ib);
+                if root && inverted {
+                    let (t1, x) = self.xnor(a, b, None)?;
+                    next.push((self.gate(Some(out), &[t1, x])?, false));
+                } else {
+                    let (_, x) = self.xnor(a, b, root.then_some(out))?;
+                    next.push((x, inverted));
+                }
+            }
+            level = next;
+        }
+        Ok(())
+    }
+}
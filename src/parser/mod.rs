@@ -1,142 +1,498 @@
 //parser/mod.rs
+mod aig;
+mod blif;
+mod macros;
 mod parsers;
+mod verilog;
 
+use std::collections::HashSet;
 use std::path::Path;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use anyhow::{Result, Context, bail};
-use regex::Regex;
+use std::io::BufRead;
+use anyhow::{Result, Context, bail, ensure};
+use memmap2::Mmap;
 
-use crate::{Circuit, TableGate, MAX_GATES, OUT_BIAS};
+use crate::gates::GateKind;
+use crate::passes::{Decompose, Pass};
+use crate::{Attributes, Circuit, ExtraOut, Origin, TableGate, MAX_FANIN, MAX_GATES, MAX_PI, OUT_BIAS};
 
+pub use self::aig::{parse_aiger, parse_sequential_aiger, Aig};
+pub use self::blif::{parse_blif, parse_blif_models, parse_sequential_blif};
+pub use self::macros::Block;
+pub(crate) use self::macros::signals;
 pub use self::parsers::*;
+pub use self::verilog::{parse_verilog, parse_verilog_modules};
+
+// Netlist file formats; everything but the native format is read through an
+// and-inverter graph and lowered to NOR/NOT gates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Native,
+    Aiger,
+    Blif,
+    Verilog,
+}
+
+impl Format {
+    // By extension; .v files are structural Verilog if they declare a module
+    // and native netlists otherwise
+    pub fn detect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let extension = path.as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        Ok(match extension.as_deref() {
+            Some("aag" | "aig") => Format::Aiger,
+            Some("blif") => Format::Blif,
+            Some("v") => {
+                let data = std::fs::read(path.as_ref())
+                    .context(format!("Failed to open file: {:?}", path.as_ref()))?;
+                if data.windows(6).any(|w| w == b"module") { Format::Verilog } else { Format::Native }
+            }
+            _ => Format::Native,
+        })
+    }
+
+    // Extension a file of the format is detected by
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Native => "txt",
+            Format::Aiger => "aig",
+            Format::Blif => "blif",
+            Format::Verilog => "v",
+        }
+    }
+
+    pub fn is_netlist_extension(extension: &str) -> bool {
+        matches!(extension.to_ascii_lowercase().as_str(), "txt" | "v" | "aag" | "aig" | "blif")
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Format::Native => "native",
+            Format::Aiger => "aiger",
+            Format::Blif => "blif",
+            Format::Verilog => "verilog",
+        })
+    }
+}
+
+// Parses a netlist of a single circuit in any supported format and finds its
+// primary inputs
+pub fn load_netlist<P: AsRef<Path>>(path: P, circuit: &mut Circuit) -> Result<Format> {
+    load_top(path, None, circuit)
+}
+
+// The one circuit of a netlist, or its module named `top`
+pub fn load_top<P: AsRef<Path>>(path: P, top: Option<&str>, circuit: &mut Circuit) -> Result<Format> {
+    let (format, mut circuits) = load_netlists(path.as_ref(), top)?;
+    let names: Vec<&str> = circuits.iter().map(|c| c.bench_name.as_str()).collect();
+    ensure!(circuits.len() == 1, "{:?} holds {} circuits ({}) and no top module was given",
+        path.as_ref(), circuits.len(), names.join(", "));
+    *circuit = circuits.remove(0);
+    Ok(format)
+}
+
+// Every top-level circuit of a netlist file with its primary inputs found, or
+// only the BLIF model or Verilog module named `top`. Circuits of a file that
+// holds several are named `<file>_<module>`, otherwise after the file.
+// Inputs a foreign format declares but never reads still count
+pub fn load_netlists<P: AsRef<Path>>(path: P, top: Option<&str>) -> Result<(Format, Vec<Circuit>)> {
+    let format = Format::detect(path.as_ref())?;
+    let circuits = load_netlists_as(path, format, top)?;
+    Ok((format, circuits))
+}
+
+// The same for a file known to be in `format`, whatever its extension
+pub fn load_netlists_as<P: AsRef<Path>>(path: P, format: Format, top: Option<&str>) -> Result<Vec<Circuit>> {
+    let path = path.as_ref();
+    // Formats without module names hold one circuit
+    let mut modules = vec![(String::new(), Circuit::new())];
+    if format == Format::Native {
+        parse_netlist(path, &mut modules[0].1)?;
+    } else {
+        let data = std::fs::read(path)
+            .context(format!("Failed to open file: {:?}", path))?;
+        match format {
+            Format::Aiger => parse_aiger(&data, &mut modules[0].1)?,
+            Format::Blif => modules = parse_blif_models(std::str::from_utf8(&data)?)?,
+            _ => modules = parse_verilog_modules(std::str::from_utf8(&data)?)?,
+        }
+    }
+
+    let several = modules.len() > 1;
+    if let Some(top) = top {
+        ensure!(matches!(format, Format::Blif | Format::Verilog),
+            "{:?} is a {} netlist of a single circuit; only BLIF models and Verilog modules are selected by name", path, format);
+        let names: Vec<String> = modules.iter().map(|(name, _)| name.clone()).collect();
+        modules.retain(|(name, _)| name == top);
+        ensure!(!modules.is_empty(), "{:?} has no module {} (it holds {})", path, top, names.join(", "));
+    }
+
+    let stem = extract_bench_name(path);
+    let circuits = modules.into_iter()
+        .map(|(name, mut circuit)| {
+            circuit.bench_name = if several { format!("{}_{}", stem, name) } else { stem.clone() };
+            settle_inputs(&mut circuit, format);
+            circuit
+        })
+        .collect();
+    Ok(circuits)
+}
+
+// A netlist with latches, cut open at them: the circuit's inputs are the
+// primary inputs, then the latch outputs, and its outputs the primary
+// outputs, then the latch inputs
+#[derive(Debug, Clone)]
+pub struct Sequential {
+    pub circuit: Circuit,
+    pub latches: usize,
+}
+
+impl Sequential {
+    pub fn primary_inputs(&self) -> usize {
+        self.circuit.num_inputs - self.latches
+    }
+
+    pub fn primary_outputs(&self) -> usize {
+        self.circuit.num_outputs - self.latches
+    }
+}
+
+// An AIGER or BLIF netlist that may hold latches, which the other loaders
+// refuse
+pub fn load_sequential<P: AsRef<Path>>(path: P) -> Result<Sequential> {
+    let path = path.as_ref();
+    let format = Format::detect(path)?;
+    let data = std::fs::read(path)
+        .context(format!("Failed to open file: {:?}", path))?;
+    let mut circuit = Circuit::new();
+    let latches = match format {
+        Format::Aiger => parse_sequential_aiger(&data, &mut circuit)?,
+        Format::Blif => parse_sequential_blif(std::str::from_utf8(&data)?, &mut circuit)?,
+        _ => bail!("{:?} is a {} netlist; latches are read from AIGER and BLIF files", path, format),
+    };
+    circuit.bench_name = extract_bench_name(path);
+    settle_inputs(&mut circuit, format);
+    Ok(Sequential { circuit, latches })
+}
+
+fn settle_inputs(circuit: &mut Circuit, format: Format) {
+    let declared = circuit.num_inputs;
+    find_primary_inputs(circuit);
+    if format != Format::Native {
+        for k in circuit.num_inputs..declared {
+            circuit.primary_inputs[k] = (MAX_GATES + k) as i32;
+        }
+        circuit.num_inputs = circuit.num_inputs.max(declared);
+    }
+    note_interface(circuit);
+}
+
+// Records inputs no gate reads and circuits without inputs or outputs
+fn note_interface(circuit: &mut Circuit) {
+    let read: HashSet<i32> = circuit.gates.iter()
+        .take(circuit.num_gates)
+        .flat_map(|g| g.inputs[..g.fanin].iter().copied())
+        .filter(|&s| s >= MAX_GATES as i32)
+        .collect();
+    if read.is_empty() {
+        circuit.diagnostics.warn("parser", "no-inputs", "No gate reads a primary input");
+    } else {
+        let unused: Vec<String> = (0..circuit.num_inputs)
+            .filter(|&k| !read.contains(&((MAX_GATES + k) as i32)))
+            .map(|k| format!("x{}", k))
+            .collect();
+        if !unused.is_empty() {
+            circuit.diagnostics.warn("parser", "unused-input",
+                format!("{} of {} inputs are never read: {}", unused.len(), circuit.num_inputs, unused.join(" ")));
+        }
+    }
+    if !circuit.gates.iter().take(circuit.num_gates).any(|g| g.primary_outputs().next().is_some()) {
+        circuit.diagnostics.warn("parser", "no-outputs", "No gate drives a primary output");
+    }
+}
 
 pub fn parse_netlist<P: AsRef<Path>>(path: P, circuit: &mut Circuit) -> Result<()> {
     let file = File::open(path.as_ref())
         .context(format!("Failed to open file: {:?}", path.as_ref()))?;
     
-    let reader = BufReader::new(file);
+    // Extract benchmark name from path
+    circuit.bench_name = extract_bench_name(path.as_ref());
+    
+    let len = file.metadata()
+        .context(format!("Failed to stat file: {:?}", path.as_ref()))?
+        .len();
+    if len == 0 {
+        return parse_netlist_bytes(&[], circuit);
+    }
+    
+    // Map the file instead of reading it line by line; lines are parsed in
+    // place without a String per line, which matters for 100+ MB netlists.
+    // SAFETY: the map is read-only and dropped before returning; as with any
+    // memory map, the file must not be truncated while it is being parsed.
+    let map = unsafe { Mmap::map(&file) }
+        .context(format!("Failed to map file: {:?}", path.as_ref()))?;
+    parse_netlist_bytes(&map, circuit)
+}
+
+// Parses netlist text that is already in memory
+pub fn parse_netlist_str(text: &str, circuit: &mut Circuit) -> Result<()> {
+    parse_netlist_bytes(text.as_bytes(), circuit)
+}
+
+// Parses netlist bytes in place, one line at a time
+pub fn parse_netlist_bytes(data: &[u8], circuit: &mut Circuit) -> Result<()> {
     let mut temp_var = 1;
     
-    // Extract benchmark name from path
-    let file_name = path.as_ref()
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("unknown");
-    
-    // Remove extension
-    let bench_name = if let Some(pos) = file_name.rfind('.') {
-        &file_name[..pos]
-    } else {
-        file_name
-    };
+    // Initialize the input count to 0
+    circuit.num_inputs = 0;
     
-    circuit.bench_name = bench_name.to_string();
+    for (line_no, line) in data.split(|&b| b == b'\n').enumerate() {
+        let line = std::str::from_utf8(line)
+            .with_context(|| format!("Line {} is not valid UTF-8", line_no + 1))?;
+        if !parse_line(line, line_no, &mut temp_var, circuit)? {
+            break;
+        }
+    }
+    
+    finish_netlist(circuit)
+}
+
+pub fn parse_netlist_from<R: BufRead>(mut reader: R, circuit: &mut Circuit) -> Result<()> {
+    let mut temp_var = 1;
     
     // Initialize the input count to 0
     circuit.num_inputs = 0;
     
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
-        
-        if line.starts_with('.') {
+    // One buffer reused for every line
+    let mut line = String::new();
+    let mut line_no = 0;
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)
+            .with_context(|| format!("Failed to read line {}", line_no + 1))?;
+        if read == 0 || !parse_line(&line, line_no, &mut temp_var, circuit)? {
             break;
         }
-        
-        let var_ids = extract_variables(&line)?;
-        
-        match var_ids.len() {
-            2 => {
-                // NOT gate
-                let mut gate = TableGate::default();
-                gate.fanin = 1;
-                gate.out = if var_ids[0] >= OUT_BIAS as i32 { var_ids[0] - OUT_BIAS as i32 } else { var_ids[0] };
-                gate.inputs[0] = if var_ids[1] >= OUT_BIAS as i32 { var_ids[1] - OUT_BIAS as i32 } else { var_ids[1] };
-                
-                if var_ids[0] >= OUT_BIAS as i32 {
-                    gate.is_output = true;
-                    circuit.num_outputs += 1;
-                }
-                
-                circuit.gates.push(gate);
-                circuit.num_gates += 1;
-            },
-            3 => {
-                // 2-input NOR
-                let mut gate = TableGate::default();
-                gate.fanin = 2;
-                gate.out = if var_ids[0] >= OUT_BIAS as i32 { var_ids[0] - OUT_BIAS as i32 } else { var_ids[0] };
-                gate.inputs[0] = if var_ids[1] >= OUT_BIAS as i32 { var_ids[1] - OUT_BIAS as i32 } else { var_ids[1] };
-                gate.inputs[1] = if var_ids[2] >= OUT_BIAS as i32 { var_ids[2] - OUT_BIAS as i32 } else { var_ids[2] };
-                
-                if var_ids[0] >= OUT_BIAS as i32 {
-                    gate.is_output = true;
-                    circuit.num_outputs += 1;
-                }
-                
-                circuit.gates.push(gate);
-                circuit.num_gates += 1;
-            },
-            4 => {
-                // Two 2-input NOR gates in cascade
-                let mut gate1 = TableGate::default();
-                gate1.fanin = 2;
-                gate1.out = -(temp_var);
-                gate1.inputs[0] = var_ids[2];
-                gate1.inputs[1] = var_ids[3];
-                
-                circuit.gates.push(gate1);
-                circuit.num_gates += 1;
-                
-                let mut gate2 = TableGate::default();
-                gate2.fanin = 2;
-                gate2.out = var_ids[0];
-                gate2.inputs[0] = var_ids[1];
-                gate2.inputs[1] = -(temp_var);
-                
-                circuit.gates.push(gate2);
-                circuit.num_gates += 1;
-                
-                temp_var += 1;
-            },
-            5 => {
-                // Three 2-input NOR gates in two levels
-                let mut gate1 = TableGate::default();
-                gate1.fanin = 2;
-                gate1.out = -(temp_var);
-                gate1.inputs[0] = var_ids[1];
-                gate1.inputs[1] = var_ids[2];
-                
-                circuit.gates.push(gate1);
-                circuit.num_gates += 1;
-                
-                let mut gate2 = TableGate::default();
-                gate2.fanin = 2;
-                gate2.out = -(temp_var + 1);
-                gate2.inputs[0] = var_ids[3];
-                gate2.inputs[1] = var_ids[4];
-                
-                circuit.gates.push(gate2);
-                circuit.num_gates += 1;
-                
-                let mut gate3 = TableGate::default();
-                gate3.fanin = 2;
-                gate3.out = var_ids[0];
-                gate3.inputs[0] = -(temp_var);
-                gate3.inputs[1] = -(temp_var + 1);
-                
-                circuit.gates.push(gate3);
-                circuit.num_gates += 1;
-                
-                temp_var += 2;
-            },
-            _ => {
-                bail!("Invalid number of variables in line: {}", line);
-            }
+        line_no += 1;
+    }
+    
+    finish_netlist(circuit)
+}
+
+// Adds the gates of one netlist line; returns false at the '.' terminator
+fn parse_line(line: &str, line_no: usize, temp_var: &mut i32, circuit: &mut Circuit) -> Result<bool> {
+    let line = line.trim();
+    
+    if line.starts_with('.') {
+        return Ok(false);
+    }
+    // Anything after '#' is a comment
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() {
+        return Ok(true);
+    }
+    // Annotations follow the operands and go to every gate of the line
+    let (line, attributes) = split_attributes(line)
+        .with_context(|| format!("Invalid annotation on line {}: {}", line_no + 1, line))?;
+    
+    // Word-level blocks (ADD8, MUX4, XOR_TREE, ...) expand into several gates
+    if let Some(mut gates) = macros::expand_line(line, temp_var)
+        .with_context(|| format!("Invalid macro line {}: {}", line_no + 1, line))?
+    {
+        if circuit.num_gates + gates.len() > MAX_GATES {
+            bail!("Netlist exceeds the maximum of {} gates at line {}", MAX_GATES, line_no + 1);
+        }
+        let origin = Origin { line: line_no + 1, text: line.trim().to_string() };
+        for gate in &mut gates {
+            gate.attributes = attributes.clone();
+            gate.origin = Some(origin.clone());
+        }
+        circuit.num_outputs += gates.iter().filter(|g| g.is_output).count();
+        circuit.num_gates += gates.len();
+        circuit.gates.extend(gates);
+        return Ok(true);
+    }
+    
+    let mut var_ids = extract_variables(line)
+        .with_context(|| format!("Invalid netlist line {}: {}", line_no + 1, line))?;
+    let single = single_gate(line)
+        .with_context(|| format!("Invalid netlist line {}: {}", line_no + 1, line))?;
+    let extra_outs = extra_outputs(line)
+        .with_context(|| format!("Invalid netlist line {}: {}", line_no + 1, line))?;
+    
+    // Expansion of 4/5-variable lines adds up to three gates
+    let new_gates = match var_ids.len() {
+        4 if !single => 2,
+        5 if !single => 3,
+        _ => 1,
+    };
+    if circuit.num_gates + new_gates > MAX_GATES {
+        bail!("Netlist exceeds the maximum of {} gates at line {}", MAX_GATES, line_no + 1);
+    }
+    if *temp_var + 2 > MAX_GATES as i32 {
+        bail!("Too many temporary signals at line {}", line_no + 1);
+    }
+    
+    // Primary outputs are marked by OUT_BIAS on any line shape; strip
+    // the bias from every variable and remember the marker
+    let is_output = var_ids.first().is_some_and(|&v| v >= OUT_BIAS as i32);
+    for v in var_ids.iter_mut().filter(|v| **v >= OUT_BIAS as i32) {
+        *v -= OUT_BIAS as i32;
+    }
+    if is_output {
+        circuit.num_outputs += 1;
+    }
+    
+    let first = circuit.gates.len();
+    match var_ids.len() {
+        2 => {
+            // NOT gate
+            let mut gate = TableGate {
+                fanin: 1,
+                out: var_ids[0],
+                is_output,
+                ..Default::default()
+            };
+            gate.inputs[0] = var_ids[1];
+            
+            circuit.gates.push(gate);
+            circuit.num_gates += 1;
+        },
+        3 => {
+            // 2-input NOR
+            let mut gate = TableGate {
+                fanin: 2,
+                out: var_ids[0],
+                is_output,
+                ..Default::default()
+            };
+            gate.inputs[0] = var_ids[1];
+            gate.inputs[1] = var_ids[2];
+            
+            circuit.gates.push(gate);
+            circuit.num_gates += 1;
+        },
+        n if n > 5 || (n > 3 && single) => {
+            // Wide NOR of every variable on the right-hand side
+            let mut gate = TableGate {
+                out: var_ids[0],
+                is_output,
+                ..Default::default()
+            };
+            gate.set_inputs(&var_ids[1..]);
+            
+            circuit.gates.push(gate);
+            circuit.num_gates += 1;
+        },
+        4 => {
+            // Two 2-input NOR gates in cascade
+            let mut gate1 = TableGate {
+                fanin: 2,
+                out: -(*temp_var),
+                ..Default::default()
+            };
+            gate1.inputs[0] = var_ids[2];
+            gate1.inputs[1] = var_ids[3];
+            
+            circuit.gates.push(gate1);
+            circuit.num_gates += 1;
+            
+            let mut gate2 = TableGate {
+                fanin: 2,
+                out: var_ids[0],
+                is_output,
+                ..Default::default()
+            };
+            gate2.inputs[0] = var_ids[1];
+            gate2.inputs[1] = -(*temp_var);
+            
+            circuit.gates.push(gate2);
+            circuit.num_gates += 1;
+            
+            *temp_var += 1;
+        },
+        5 => {
+            // Three 2-input NOR gates in two levels
+            let mut gate1 = TableGate {
+                fanin: 2,
+                out: -(*temp_var),
+                ..Default::default()
+            };
+            gate1.inputs[0] = var_ids[1];
+            gate1.inputs[1] = var_ids[2];
+            
+            circuit.gates.push(gate1);
+            circuit.num_gates += 1;
+            
+            let mut gate2 = TableGate {
+                fanin: 2,
+                out: -(*temp_var + 1),
+                ..Default::default()
+            };
+            gate2.inputs[0] = var_ids[3];
+            gate2.inputs[1] = var_ids[4];
+            
+            circuit.gates.push(gate2);
+            circuit.num_gates += 1;
+            
+            let mut gate3 = TableGate {
+                fanin: 2,
+                out: var_ids[0],
+                is_output,
+                ..Default::default()
+            };
+            gate3.inputs[0] = -(*temp_var);
+            gate3.inputs[1] = -(*temp_var + 1);
+            
+            circuit.gates.push(gate3);
+            circuit.num_gates += 1;
+            
+            *temp_var += 2;
+        },
+        _ => {
+            bail!("Invalid number of variables in line {}: {}", line_no + 1, line);
         }
     }
+    // The line's own gate is the last one, after any it expanded into
+    if !extra_outs.is_empty() {
+        circuit.num_outputs += extra_outs.iter().filter(|o| o.is_output).count();
+        circuit.gates.last_mut().unwrap().extra_outs = extra_outs;
+    }
+    let origin = Origin { line: line_no + 1, text: line.trim().to_string() };
+    for gate in &mut circuit.gates[first..] {
+        gate.attributes = attributes.clone();
+        gate.origin = Some(origin.clone());
+    }
     
+    Ok(true)
+}
+
+// Splits the annotations off a gate line, `n5 = x0 x1 @critical @owner=alu`:
+// each is `@name` or `@name=value`, and nothing but annotations follows the first
+fn split_attributes(line: &str) -> Result<(&str, Attributes)> {
+    let mut attributes = Attributes::default();
+    let Some(at) = line.find('@') else {
+        return Ok((line, attributes));
+    };
+    for token in line[at..].split_whitespace() {
+        let Some(annotation) = token.strip_prefix('@') else {
+            bail!("'{}' follows an annotation; annotations go after the operands", token);
+        };
+        let (name, value) = annotation.split_once('=').unwrap_or((annotation, ""));
+        ensure!(name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "'{}' is not an attribute name", name);
+        ensure!(value.is_empty() != annotation.contains('='), "@{} has no value after '='", name);
+        attributes.insert(name, value);
+    }
+    Ok((line[..at].trim_end(), attributes))
+}
+
+fn finish_netlist(circuit: &mut Circuit) -> Result<()> {
     // Initialize gate levels
     for gate in &mut circuit.gates {
         gate.asap_level = -1;
@@ -144,38 +500,126 @@ pub fn parse_netlist<P: AsRef<Path>>(path: P, circuit: &mut Circuit) -> Result<(
         gate.list_level = -1;
     }
     
+    // Wide lines are split until every gate fits one crossbar cell; the
+    // pipeline splits them further for its devices
+    if circuit.gates.iter().any(|g| g.fanin > MAX_FANIN) {
+        Decompose { max_fanin: MAX_FANIN }.run(circuit)?;
+        circuit.num_gates = circuit.gates.len();
+    }
+    circuit.index_signals();
     Ok(())
 }
 
+// Single pass over a netlist line: every `n<digits>` / `x<digits>` token in
+// order, with the left-hand side required to be an n-variable
 fn extract_variables(line: &str) -> Result<Vec<i32>> {
-    let mut var_ids = Vec::new();
+    let mut var_ids = Vec::with_capacity(MAX_LINE_VARIABLES);
 
     // Split line at '='
-    let (left, right) = match line.find('=') {
-        Some(eq) => (&line[..eq].trim(), &line[eq+1..].trim()),
-        None => return Ok(var_ids),
+    let Some(eq) = line.find('=') else {
+        return Ok(var_ids);
     };
 
     // Output variable (left)
-    let output_re = Regex::new(r"([nx])(\d+)").unwrap();
-    if let Some(cap) = output_re.captures(left) {
-        let prefix = &cap[1];
-        let id: i32 = cap[2].parse().unwrap();
-        let var_id = if prefix == "x" { MAX_GATES as i32 + id } else { id };
-        var_ids.push(var_id);
+    match variables(&line[..eq]).next() {
+        Some((b'n', digits)) => var_ids.push(variable_id("n", digits)?),
+        Some((_, digits)) => bail!("Primary input x{} cannot be driven by a gate", digits),
+        None => bail!("Missing output variable"),
     }
 
     // Input variables (right), preserve order!
-    for cap in output_re.captures_iter(right) {
-        let prefix = &cap[1];
-        let id: i32 = cap[2].parse().unwrap();
-        let var_id = if prefix == "x" { MAX_GATES as i32 + id } else { id };
-        var_ids.push(var_id);
+    for (prefix, digits) in variables(&line[eq + 1..]) {
+        let prefix = if prefix == b'n' { "n" } else { "x" };
+        var_ids.push(variable_id(prefix, digits)?);
     }
 
     Ok(var_ids)
 }
 
+// Further signals on the left-hand side, `n5 n6 n10002 = NOR(x0, x1)`: the
+// line's gate drives each of them with its value. Output-biased ones are
+// primary outputs
+fn extra_outputs(line: &str) -> Result<Vec<ExtraOut>> {
+    let Some((lhs, _)) = line.split_once('=') else {
+        return Ok(Vec::new());
+    };
+    // The first is the gate's `out`, checked against the others only
+    let mut outs: Vec<ExtraOut> = Vec::new();
+    for (prefix, digits) in variables(lhs) {
+        ensure!(prefix == b'n', "Primary input x{} cannot be driven by a gate", digits);
+        let id = variable_id("n", digits)?;
+        let out = match id >= OUT_BIAS as i32 {
+            true => ExtraOut { signal: id - OUT_BIAS as i32, is_output: true },
+            false => ExtraOut { signal: id, is_output: false },
+        };
+        ensure!(outs.iter().all(|o| o.signal != out.signal), "n{} is driven twice", digits);
+        outs.push(out);
+    }
+    Ok(outs.split_off(outs.len().min(1)))
+}
+
+// Whether a line spells out one gate, `n8 = NOR(n7, n4, x2)` or
+// `n9 = NOT(n8)`, as decomposed netlists do: its operands are then one NOR
+// of any width instead of a 4/5-variable cascade
+fn single_gate(line: &str) -> Result<bool> {
+    let Some((_, rhs)) = line.split_once('=') else {
+        return Ok(false);
+    };
+    let Some((function, _)) = rhs.split_once('(') else {
+        return Ok(false);
+    };
+    match GateKind::named(function.trim()) {
+        Some(kind) if kind.spec().native => Ok(true),
+        _ => bail!("{} gates cannot be read from a native netlist, only {}",
+            function.trim(), GateKind::native_names().join(" and ")),
+    }
+}
+
+// Most lines name at most five variables; wide NOR lines grow the buffer
+const MAX_LINE_VARIABLES: usize = 5;
+
+// Tokens of the form [nx][0-9]+ anywhere in the text, left to right
+fn variables(text: &str) -> impl Iterator<Item = (u8, &str)> {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while pos + 1 < bytes.len() {
+            let prefix = bytes[pos];
+            if (prefix == b'n' || prefix == b'x') && bytes[pos + 1].is_ascii_digit() {
+                let start = pos + 1;
+                let end = bytes[start..].iter()
+                    .position(|b| !b.is_ascii_digit())
+                    .map_or(bytes.len(), |len| start + len);
+                pos = end;
+                return Some((prefix, &text[start..end]));
+            }
+            pos += 1;
+        }
+        None
+    })
+}
+
+// Maps a variable to its signal id, rejecting indices that would alias the
+// primary-input or output-bias ranges
+fn variable_id(prefix: &str, digits: &str) -> Result<i32> {
+    let id: usize = digits.parse()
+        .ok()
+        .filter(|&id| id < OUT_BIAS + MAX_GATES)
+        .with_context(|| format!("Variable index {}{} is out of range", prefix, digits))?;
+
+    if prefix == "x" {
+        if id >= MAX_PI {
+            bail!("Primary input x{} exceeds the maximum of {} inputs", id, MAX_PI);
+        }
+        Ok((MAX_GATES + id) as i32)
+    } else {
+        if (MAX_GATES..OUT_BIAS).contains(&id) {
+            bail!("Signal n{} collides with the primary input range", id);
+        }
+        Ok(id as i32)
+    }
+}
+
 
 pub fn find_primary_inputs(circuit: &mut Circuit) {
     // Reset primary inputs
@@ -209,24 +653,72 @@ pub fn find_primary_inputs(circuit: &mut Circuit) {
     }
 }
 
+// Result of cross-checking declared primary outputs against the gates that
+// drive them. Output k is written as n<OUT_BIAS + k> in the netlist and becomes
+// port op_k in the generated Verilog.
+#[derive(Debug, Default, Clone)]
+pub struct OutputAudit {
+    pub declared: Vec<i32>,
+    pub undriven: Vec<i32>,
+    pub duplicates: Vec<i32>,
+    pub unmarked: Vec<i32>,
+}
+
+impl OutputAudit {
+    pub fn is_clean(&self) -> bool {
+        self.undriven.is_empty() && self.duplicates.is_empty() && self.unmarked.is_empty()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        let mut messages = Vec::new();
+        for &k in &self.undriven {
+            messages.push(format!("Output op_{} (n{}) is never driven", k, OUT_BIAS as i32 + k));
+        }
+        for &k in &self.duplicates {
+            messages.push(format!("Output op_{} (n{}) is driven by more than one line", k, OUT_BIAS as i32 + k));
+        }
+        for &k in &self.unmarked {
+            messages.push(format!("Signal n{} is numbered like output op_{} but not marked as an output", k, k));
+        }
+        messages
+    }
+}
+
+pub fn audit_primary_outputs(circuit: &Circuit) -> OutputAudit {
+    let mut audit = OutputAudit::default();
+    let mut seen = HashSet::new();
+
+    for output in circuit.gates.iter().take(circuit.num_gates).flat_map(|g| g.primary_outputs()) {
+        if !seen.insert(output) && !audit.duplicates.contains(&output) {
+            audit.duplicates.push(output);
+        }
+    }
+    audit.declared = seen.iter().copied().collect();
+    audit.declared.sort_unstable();
+
+    // Output ports are numbered contiguously from 1
+    let last = audit.declared.last().copied().unwrap_or(0);
+    audit.undriven = (1..=last).filter(|k| !seen.contains(k)).collect();
+    audit.unmarked = circuit.gates.iter()
+        .take(circuit.num_gates)
+        .flat_map(|g| std::iter::once((g.out, g.is_output)).chain(g.extra_outs.iter().map(|o| (o.signal, o.is_output))))
+        .filter(|&(out, marked)| !marked && out >= 1 && out <= last)
+        .map(|(out, _)| out)
+        .collect();
+    audit.unmarked.sort_unstable();
+    audit.undriven.retain(|k| !audit.unmarked.contains(k));
+    audit.duplicates.sort_unstable();
+
+    audit
+}
+
 pub fn find_gate_outputs(circuit: &mut Circuit) {
     for i in 0..circuit.num_gates {
-        for k in 0..circuit.num_gates {
-            if k == i {
-                continue;
-            }
-            
-            for j in 0..circuit.gates[k].fanin {
-                let current_input = circuit.gates[k].inputs[j];
-                
-                if circuit.gates[i].out == current_input {
-                    let output_count = circuit.gates[i].output_gates.iter()
-                        .position(|&x| x == 0)
-                        .unwrap_or(0);
-                    
-                    circuit.gates[i].output_gates[output_count] = circuit.gates[k].out;
-                }
-            }
-        }
+        let readers: Vec<i32> = circuit.gate_readers(i)
+            .filter(|&k| k != i)
+            .map(|k| circuit.gates[k].out)
+            .collect();
+        
+        circuit.gates[i].output_gates = readers;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,281 @@
+//parser/verilog.rs
+// Structural Verilog as published with the EPFL suite: modules of input,
+// output and wire declarations (scalar or [msb:lsb] vectors) and continuous
+// assignments over ~, &, ^ and | (in decreasing precedence), parentheses and
+// the constants 1'b0 / 1'b1. Vector ports are listed bit 0 first. Modules do
+// not instantiate each other; each is a circuit of its own
+use std::collections::{HashMap, HashSet};
+use anyhow::{Context, Result, bail, ensure};
+
+use super::aig::{definition_order, Aig, FALSE, TRUE};
+use crate::Circuit;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(String),
+    Symbol(char),
+}
+
+// A file of a single module
+pub fn parse_verilog(text: &str, circuit: &mut Circuit) -> Result<()> {
+    let tokens = tokenize(text)?;
+    let modules = split_modules(&tokens)?;
+    let names: Vec<&str> = modules.iter().map(|(name, _)| name.as_str()).collect();
+    ensure!(modules.len() == 1, "Verilog file holds {} modules ({})", modules.len(), names.join(", "));
+    parse_module(modules[0].1, circuit)
+}
+
+// Every module of a file with its name, in file order
+pub fn parse_verilog_modules(text: &str) -> Result<Vec<(String, Circuit)>> {
+    let tokens = tokenize(text)?;
+    split_modules(&tokens)?.into_iter()
+        .map(|(name, tokens)| {
+            let mut circuit = Circuit::new();
+            parse_module(tokens, &mut circuit).context(format!("In Verilog module {}", name))?;
+            Ok((name, circuit))
+        })
+        .collect()
+}
+
+// The tokens of each module up to its `endmodule`, which the last module may
+// leave out
+fn split_modules(tokens: &[Token]) -> Result<Vec<(String, &[Token])>> {
+    ensure!(!tokens.is_empty(), "Empty Verilog file");
+    let mut modules = Vec::new();
+    let mut seen = HashSet::new();
+    for module in tokens.split(|t| matches!(t, Token::Name(n) if n == "endmodule")) {
+        if module.is_empty() {
+            continue;
+        }
+        ensure!(module[0] == Token::Name("module".into()), "Verilog file does not start with a module");
+        let Some(Token::Name(name)) = module.get(1) else { bail!("Module without a name") };
+        ensure!(seen.insert(name.clone()), "Verilog module {} is defined twice", name);
+        modules.push((name.clone(), module));
+    }
+    ensure!(!modules.is_empty(), "Verilog file holds no module");
+    Ok(modules)
+}
+
+fn parse_module(tokens: &[Token], circuit: &mut Circuit) -> Result<()> {
+    let mut statements = tokens.split(|t| *t == Token::Symbol(';'));
+    statements.next();
+
+    let (mut inputs, mut outputs) = (Vec::new(), Vec::new());
+    let mut assigns: HashMap<String, &[Token]> = HashMap::new();
+    for statement in statements {
+        let Some(Token::Name(keyword)) = statement.first() else {
+            ensure!(statement.is_empty(), "Unexpected Verilog statement starting with {:?}", statement[0]);
+            continue;
+        };
+        match keyword.as_str() {
+            "input" => inputs.extend(declared(&statement[1..])?),
+            "output" => outputs.extend(declared(&statement[1..])?),
+            "wire" => {}
+            "assign" => {
+                let Some(eq) = statement.iter().position(|t| *t == Token::Symbol('=')) else {
+                    bail!("Assignment without '='");
+                };
+                let mut lhs = Parser { tokens: &statement[1..eq], pos: 0 };
+                let name = lhs.signal()?;
+                ensure!(lhs.pos == lhs.tokens.len(), "Assignment to {} has a malformed left-hand side", name);
+                ensure!(assigns.insert(name.clone(), &statement[eq + 1..]).is_none(), "Signal {} is assigned twice", name);
+            }
+            other => bail!("Unsupported Verilog statement '{}'", other),
+        }
+    }
+
+    let mut aig = Aig::new(inputs.len());
+    let mut values: HashMap<String, u32> = HashMap::new();
+    for (k, name) in inputs.iter().enumerate() {
+        ensure!(values.insert(name.clone(), aig.input(k)).is_none(), "Input {} is declared twice", name);
+    }
+    let order = definition_order(&outputs, |name| assigns.get(name).map(|tokens| reads(tokens)))?;
+    for name in order {
+        let tokens = assigns[&name];
+        let mut parser = Parser { tokens, pos: 0 };
+        let lit = parser.expression(0, &values, &mut aig)?;
+        ensure!(parser.pos == tokens.len(), "Trailing tokens in the assignment to {}", name);
+        values.insert(name, lit);
+    }
+    for name in &outputs {
+        match values.get(name) {
+            Some(&lit) => aig.output(lit),
+            None => bail!("Signal {} is never assigned", name),
+        }
+    }
+    aig.to_circuit(circuit)
+}
+
+// Signals an expression reads
+fn reads(tokens: &[Token]) -> Vec<String> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut names = Vec::new();
+    while parser.pos < tokens.len() {
+        match parser.signal() {
+            Ok(name) => names.push(name),
+            Err(_) => parser.pos += 1,
+        }
+    }
+    names
+}
+
+// Bit names of a declaration: `a, b` or `[7:0] a, b`
+fn declared(tokens: &[Token]) -> Result<Vec<String>> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let range = if parser.eat('[') {
+        let msb = parser.number()?;
+        ensure!(parser.eat(':'), "Malformed vector range");
+        let lsb = parser.number()?;
+        ensure!(parser.eat(']'), "Malformed vector range");
+        Some((msb.min(lsb), msb.max(lsb)))
+    } else {
+        None
+    };
+
+    let mut names = Vec::new();
+    while parser.pos < tokens.len() {
+        let Some(Token::Name(name)) = tokens.get(parser.pos) else {
+            bail!("Expected a signal name in declaration, found {:?}", tokens[parser.pos]);
+        };
+        parser.pos += 1;
+        match range {
+            Some((lo, hi)) => names.extend((lo..=hi).map(|i| format!("{}[{}]", name, i))),
+            None => names.push(name.clone()),
+        }
+        if !parser.eat(',') {
+            break;
+        }
+    }
+    ensure!(parser.pos == tokens.len(), "Malformed declaration");
+    Ok(names)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+// Binary operators from loosest to tightest
+const OPERATORS: [char; 3] = ['|', '^', '&'];
+
+impl Parser<'_> {
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.tokens.get(self.pos) == Some(&Token::Symbol(symbol));
+        self.pos += found as usize;
+        found
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(n.parse()?)
+            }
+            other => bail!("Expected a number, found {:?}", other),
+        }
+    }
+
+    // A name with an optional constant bit select
+    fn signal(&mut self) -> Result<String> {
+        let Some(Token::Name(name)) = self.tokens.get(self.pos) else {
+            bail!("Expected a signal, found {:?}", self.tokens.get(self.pos));
+        };
+        self.pos += 1;
+        if self.eat('[') {
+            let bit = self.number()?;
+            ensure!(self.eat(']'), "Malformed bit select of {}", name);
+            return Ok(format!("{}[{}]", name, bit));
+        }
+        Ok(name.clone())
+    }
+
+    // Operators at `level` and tighter
+    fn expression(&mut self, level: usize, values: &HashMap<String, u32>, aig: &mut Aig) -> Result<u32> {
+        if level == OPERATORS.len() {
+            return self.unary(values, aig);
+        }
+        let mut lit = self.expression(level + 1, values, aig)?;
+        while self.eat(OPERATORS[level]) {
+            let rhs = self.expression(level + 1, values, aig)?;
+            lit = match OPERATORS[level] {
+                '|' => aig.or(lit, rhs),
+                '^' => aig.xor(lit, rhs),
+                _ => aig.and(lit, rhs),
+            };
+        }
+        Ok(lit)
+    }
+
+    fn unary(&mut self, values: &HashMap<String, u32>, aig: &mut Aig) -> Result<u32> {
+        if self.eat('~') || self.eat('!') {
+            return Ok(self.unary(values, aig)? ^ 1);
+        }
+        if self.eat('(') {
+            let lit = self.expression(0, values, aig)?;
+            ensure!(self.eat(')'), "Unbalanced parentheses");
+            return Ok(lit);
+        }
+        if let Some(Token::Number(n)) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            return match n.as_str() {
+                "1'b0" | "1'h0" | "0" => Ok(FALSE),
+                "1'b1" | "1'h1" | "1" => Ok(TRUE),
+                _ => bail!("Unsupported constant {}", n),
+            };
+        }
+        let name = self.signal()?;
+        match values.get(&name) {
+            Some(&lit) => Ok(lit),
+            None => bail!("Signal {} is never assigned", name),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '/' {
+            chars.next();
+            match chars.next() {
+                Some('/') => while chars.next_if(|&c| c != '\n').is_some() {},
+                Some('*') => {
+                    let mut last = ' ';
+                    for c in chars.by_ref() {
+                        if last == '*' && c == '/' {
+                            break;
+                        }
+                        last = c;
+                    }
+                }
+                _ => bail!("Unexpected '/' in Verilog"),
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '\\' {
+            // Escaped identifiers run to the next whitespace
+            let escaped = c == '\\';
+            let mut name = String::new();
+            if escaped {
+                chars.next();
+            }
+            while let Some(c) = chars.next_if(|&c| if escaped { !c.is_whitespace() } else { c.is_ascii_alphanumeric() || c == '_' || c == '$' }) {
+                name.push(c);
+            }
+            tokens.push(Token::Name(name));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '\'') {
+                number.push(c);
+            }
+            tokens.push(Token::Number(number));
+        } else if "()[]:,;=~!&|^".contains(c) {
+            chars.next();
+            tokens.push(Token::Symbol(c));
+        } else {
+            bail!("Unexpected character '{}' in Verilog", c);
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,89 @@
+//passes/bind.rs
+use std::collections::HashSet;
+use anyhow::{Result, bail, ensure};
+
+use super::dont_care::bind_inputs;
+use super::{Pass, PassReport};
+use crate::{signal_id, signal_name, Circuit, MAX_GATES};
+
+// Fixes primary inputs at constants, e.g. the configuration pins of a design,
+// and propagates them. The inputs stay in the interface but no gate reads
+// them; gates left without readers are removed
+#[derive(Debug, Clone, Default)]
+pub struct Bind {
+    // (input, value) pairs, in the order given
+    pub bindings: Vec<(usize, bool)>,
+}
+
+impl Bind {
+    // Bindings written as xN=V, V being 0 or 1
+    pub fn parse<S: AsRef<str>>(bindings: &[S]) -> Result<Self> {
+        let mut bind = Self::default();
+        for binding in bindings {
+            let binding = binding.as_ref().trim();
+            let Some((name, value)) = binding.split_once('=') else {
+                bail!("Binding '{}' is not of the form xN=0 or xN=1", binding);
+            };
+            let input = match signal_id(name.trim()) {
+                Some(s) if s >= MAX_GATES as i32 => s as usize - MAX_GATES,
+                _ => bail!("Binding '{}' does not name a primary input", binding),
+            };
+            let value = match value.trim() {
+                "0" => false,
+                "1" => true,
+                _ => bail!("Binding '{}' fixes {} at neither 0 nor 1", binding, name.trim()),
+            };
+            ensure!(bind.bindings.iter().all(|&(i, _)| i != input), "Input {} is bound twice", signal_name((MAX_GATES + input) as i32));
+            bind.bindings.push((input, value));
+        }
+        Ok(bind)
+    }
+
+    // The bindings as given on the command line
+    pub fn describe(&self) -> String {
+        let bindings: Vec<String> = self.bindings.iter()
+            .map(|&(input, value)| format!("{}={}", signal_name((MAX_GATES + input) as i32), value as u8))
+            .collect();
+        bindings.join(",")
+    }
+}
+
+impl Pass for Bind {
+    fn name(&self) -> &'static str {
+        "bind"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        for &(input, _) in &self.bindings {
+            ensure!(input < circuit.num_inputs, "Cannot bind {}: the circuit has {} inputs",
+                signal_name((MAX_GATES + input) as i32), circuit.num_inputs);
+        }
+        let gates_before = circuit.num_gates;
+        let rewritten = bind_inputs(circuit, &self.bindings)?;
+
+        let kept: HashSet<i32> = rewritten.iter().map(|g| g.out).collect();
+        let existing: HashSet<i32> = circuit.gates.iter().map(|g| g.out).collect();
+        let mut removed: Vec<i32> = circuit.gates.iter()
+            .map(|g| g.out)
+            .filter(|out| !kept.contains(out))
+            .collect();
+        removed.sort_unstable();
+        // Outputs the bindings fix are tied off with new temporaries
+        let tied = kept.iter().filter(|s| !existing.contains(s)).count();
+
+        circuit.gates = rewritten;
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![
+                format!("{} bound inputs", self.bindings.len()),
+                format!("{} tie-off gates", tied),
+            ],
+            removed,
+        })
+    }
+}
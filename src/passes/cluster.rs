@@ -0,0 +1,182 @@
+//passes/cluster.rs
+use std::collections::{BTreeMap, HashMap, HashSet};
+use anyhow::Result;
+
+use super::commute::{estimate, mapping_order};
+use super::{Pass, PassReport};
+use crate::{Circuit, MAX_COL};
+
+// Gathers small reconvergent cones into one row of the compact mapping. A
+// cone is a gate and the gates between it and a signal that reaches it along
+// two or more paths, at most `max_size` gates in all. The compact mapper puts
+// a gate in its last operand's row, so each member is made to read another
+// member, or else the shared signal, last: the whole cone then lands in the
+// shared signal's row and none of its internal operands is copied. The cones
+// are recorded on the circuit, and the compact mappers reserve one run of
+// columns per cone when they place its first gate. A cone is kept only if the
+// mapper is estimated to make no more copies with it and to fit. `@dont_touch`
+// gates join no cone. Runs after the levels and operand order are final, like
+// `commute`
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub max_size: usize,
+}
+
+impl Default for Cluster {
+    fn default() -> Self {
+        Self { max_size: 4 }
+    }
+}
+
+impl Pass for Cluster {
+    fn name(&self) -> &'static str {
+        "cluster"
+    }
+
+    fn keeps_extra_outs(&self) -> bool {
+        true
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let order = mapping_order(circuit);
+        let before = estimate(circuit, &order);
+
+        // Each cone is kept if it costs no copies, counting the ones kept before
+        // it, and the mapping still fits in the crossbar's columns
+        let mut clusters = Vec::new();
+        let mut result = before;
+        for cone in find_clusters(circuit, &order, self.max_size) {
+            let original: Vec<_> = cone.members.iter().map(|&i| circuit.gates[i].inputs.clone()).collect();
+            gather(circuit, &cone);
+            let after = estimate(circuit, &order);
+            let fits = after.width <= MAX_COL || after.width <= result.width;
+            if after.copies <= result.copies && fits {
+                result = after;
+                clusters.push(cone);
+            } else {
+                for (&i, inputs) in cone.members.iter().zip(original) {
+                    circuit.gates[i].inputs = inputs;
+                }
+            }
+        }
+
+        let mut sizes: BTreeMap<usize, usize> = BTreeMap::new();
+        for cluster in &clusters {
+            *sizes.entry(cluster.members.len()).or_insert(0) += 1;
+        }
+        let sizes: Vec<String> = sizes.iter().map(|(size, count)| format!("{}x{}", count, size)).collect();
+        let mut details = vec![format!("{} clusters", clusters.len())];
+        if !sizes.is_empty() {
+            details.push(format!("sizes {}", sizes.join(" ")));
+        }
+        details.push(format!("estimated copies {} -> {}", before.copies, result.copies));
+        circuit.clusters = clusters.into_iter()
+            .map(|c| c.members.iter().map(|&i| circuit.gates[i].out).collect())
+            .collect();
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details,
+            removed: Vec::new(),
+        })
+    }
+}
+
+// A cone's gates in mapping order, root last, and the signal all its paths
+// start from
+struct Cone {
+    members: Vec<usize>,
+    source: i32,
+}
+
+// Cones rooted at each gate in mapping order, from gates no earlier cone took
+fn find_clusters(circuit: &Circuit, order: &[usize], max_size: usize) -> Vec<Cone> {
+    let position: HashMap<usize, usize> = order.iter().enumerate().map(|(p, &i)| (i, p)).collect();
+    let mut taken: HashSet<usize> = (0..circuit.num_gates).filter(|&i| circuit.gates[i].dont_touch()).collect();
+    let mut cones = Vec::new();
+    for &root in order {
+        if circuit.gates[root].fanin < 2 || taken.contains(&root) {
+            continue;
+        }
+        if let Some(cone) = cone_at(circuit, root, &position, &taken, max_size) {
+            taken.extend(cone.members.iter().copied());
+            cones.push(cone);
+        }
+    }
+    cones
+}
+
+// The largest cone of at most `max_size` gates rooted at `root`; ties go to
+// the source found first
+fn cone_at(circuit: &Circuit, root: usize, position: &HashMap<usize, usize>, taken: &HashSet<usize>, max_size: usize) -> Option<Cone> {
+    // Free gates within max_size - 1 levels of operands above the root
+    let mut cone = vec![root];
+    let mut frontier = vec![root];
+    for _ in 1..max_size {
+        let mut next = Vec::new();
+        for &i in &frontier {
+            let gate = &circuit.gates[i];
+            for &operand in &gate.inputs[..gate.fanin] {
+                if let Some(&j) = circuit.drivers.get(&operand) {
+                    if !taken.contains(&j) && !cone.contains(&j) {
+                        cone.push(j);
+                        next.push(j);
+                    }
+                }
+            }
+        }
+        frontier = next;
+    }
+    cone.sort_by_key(|i| position[i]);
+
+    let mut best: Option<Cone> = None;
+    let mut sources: Vec<i32> = Vec::new();
+    for &i in &cone {
+        let gate = &circuit.gates[i];
+        for &operand in &gate.inputs[..gate.fanin] {
+            if !sources.contains(&operand) {
+                sources.push(operand);
+            }
+        }
+    }
+    for source in sources {
+        // Gates of the cone with a path from the source, in mapping order
+        let mut members: Vec<usize> = Vec::new();
+        for &i in &cone {
+            let gate = &circuit.gates[i];
+            let reads = gate.inputs[..gate.fanin].iter()
+                .any(|&s| s == source || circuit.drivers.get(&s).is_some_and(|j| members.contains(j)));
+            if reads {
+                members.push(i);
+            }
+        }
+        // Reconvergent: two of the root's operands lead back to the source
+        let root = &circuit.gates[root];
+        let paths = root.inputs[..root.fanin].iter()
+            .filter(|&&s| s == source || circuit.drivers.get(&s).is_some_and(|j| members.contains(j)))
+            .count();
+        if paths < 2 || members.len() > max_size {
+            continue;
+        }
+        if best.as_ref().is_none_or(|b| members.len() > b.members.len()) {
+            best = Some(Cone { members, source });
+        }
+    }
+    best
+}
+
+// Makes every member read another member last, or else the source
+fn gather(circuit: &mut Circuit, cone: &Cone) {
+    let outputs: Vec<i32> = cone.members.iter().map(|&i| circuit.gates[i].out).collect();
+    for &i in &cone.members {
+        let gate = &mut circuit.gates[i];
+        let fanin = gate.fanin;
+        let pick = gate.inputs[..fanin].iter().position(|s| outputs.contains(s))
+            .or_else(|| gate.inputs[..fanin].iter().position(|&s| s == cone.source));
+        if let Some(k) = pick {
+            gate.inputs.swap(k, fanin - 1);
+        }
+    }
+}
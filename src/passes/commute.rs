@@ -0,0 +1,193 @@
+//passes/commute.rs
+use std::collections::HashMap;
+use anyhow::Result;
+
+use super::{Pass, PassReport};
+use crate::{scheduler, Circuit, TableGate, MAX_COL, MAX_GATES};
+
+// Orders the operands of two-input gates for the compact mapper, which places
+// a gate in its second operand's row and copies the first operand there when
+// the rows differ. Both orders cost that gate the same copy, but the row it
+// lands in decides whether its readers need copies of it. Gates are visited
+// in the mapper's order; each moves to its first operand's row only if more
+// of its readers' other, already placed operands sit there. The new order is
+// kept only if the mapper is estimated to make fewer copies and still fit in
+// the crossbar's columns. Gathering readers in their operands' rows makes
+// those rows longer, so rows as wide as the original mapping are avoided.
+// `@dont_touch` gates keep their order
+#[derive(Debug, Clone, Default)]
+pub struct Commute;
+
+// Copies and widest row of the compact mapping of a circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct Estimate {
+    pub(super) copies: usize,
+    pub(super) width: usize,
+}
+
+impl Pass for Commute {
+    fn name(&self) -> &'static str {
+        "commute"
+    }
+
+    fn keeps_extra_outs(&self) -> bool {
+        true
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let order = mapping_order(circuit);
+        let before = estimate(circuit, &order);
+
+        let original: Vec<[i32; 2]> = circuit.gates.iter().map(|g| [g.inputs[0], g.inputs[1]]).collect();
+        let swapped = reorder(circuit, &order, before.width);
+        let after = estimate(circuit, &order);
+        // Fewer copies may cost width, as long as the mapping still fits
+        let fits = after.width <= MAX_COL || after.width <= before.width;
+        let kept = swapped > 0 && after.copies < before.copies && fits;
+        if !kept {
+            for (gate, inputs) in circuit.gates.iter_mut().zip(&original) {
+                gate.inputs[..2].copy_from_slice(inputs);
+            }
+        }
+
+        let nors = circuit.gates.iter().filter(|g| g.fanin == 2).count();
+        let (swapped, result) = if kept { (swapped, after) } else { (0, before) };
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![
+                format!("swapped {} of {} operand pairs", swapped, nors),
+                format!("estimated copies {} -> {}", before.copies, result.copies),
+            ],
+            removed: Vec::new(),
+        })
+    }
+}
+
+// Gate indices in the order the compact mapper places them: by ASAP level,
+// ties in netlist order. Unscheduled circuits are scheduled on a copy
+pub(super) fn mapping_order(circuit: &Circuit) -> Vec<usize> {
+    let levels: Vec<i32> = if circuit.max_asap > 0 {
+        circuit.gates.iter().map(|g| g.asap_level).collect()
+    } else {
+        let mut scheduled = circuit.clone();
+        scheduler::compute_asap_schedule(&mut scheduled);
+        scheduled.gates.iter().map(|g| g.asap_level).collect()
+    };
+    let mut order: Vec<usize> = (0..circuit.num_gates).collect();
+    order.sort_by_key(|&i| levels[i]);
+    order
+}
+
+// Rows the compact mapper gives each placed signal, and the columns used per row
+struct Rows {
+    of: HashMap<i32, usize>,
+    used: HashMap<usize, usize>,
+}
+
+impl Rows {
+    fn new(circuit: &Circuit) -> Self {
+        let mut rows = Rows { of: HashMap::new(), used: HashMap::new() };
+        for n in 0..circuit.num_inputs {
+            rows.of.insert((MAX_GATES + n) as i32, n);
+            rows.used.insert(n, 1);
+        }
+        rows
+    }
+
+    fn row(&self, signal: i32) -> Option<usize> {
+        self.of.get(&signal).copied()
+    }
+
+    // Every signal of the gate is in its cell's row
+    fn place(&mut self, gate: &TableGate, row: usize, cells: usize) {
+        for out in gate.outs() {
+            self.of.insert(out, row);
+        }
+        *self.used.entry(row).or_insert(0) += cells;
+    }
+}
+
+pub(super) fn estimate(circuit: &Circuit, order: &[usize]) -> Estimate {
+    let (rows, copies) = place(circuit, order);
+    Estimate { copies, width: rows.used.values().copied().max().unwrap_or(0) }
+}
+
+// Row of every input and gate output in the compact mapping
+pub(super) fn signal_rows(circuit: &Circuit, order: &[usize]) -> HashMap<i32, usize> {
+    place(circuit, order).0.of
+}
+
+fn place(circuit: &Circuit, order: &[usize]) -> (Rows, usize) {
+    let mut rows = Rows::new(circuit);
+    let mut copies = 0;
+    for &i in order {
+        let gate = &circuit.gates[i];
+        let operands: Vec<usize> = gate.inputs[..gate.fanin].iter().map(|&s| rows.row(s).unwrap_or(0)).collect();
+        let Some((&row, others)) = operands.split_last() else { continue };
+        copies += others.iter().filter(|&&other| other != row).count();
+        // The mapper reserves a column per operand even when it needs no copy
+        rows.place(gate, row, gate.fanin);
+    }
+    (rows, copies)
+}
+
+// Picks each gate's row in mapping order and swaps its operands where the
+// other row wins; returns the number of gates swapped
+fn reorder(circuit: &mut Circuit, order: &[usize], width: usize) -> usize {
+    let mut rows = Rows::new(circuit);
+    let mut swapped = 0;
+    for &i in order {
+        let gate = &circuit.gates[i];
+        let [a, b] = [gate.inputs[0], gate.inputs[1]];
+        let first = rows.row(a).unwrap_or(0);
+        if gate.fanin == 1 {
+            rows.place(gate, first, 1);
+            continue;
+        }
+        // Wider gates keep their order, in their last operand's row
+        if gate.fanin > 2 || gate.dont_touch() {
+            let last = rows.row(gate.inputs[gate.fanin - 1]).unwrap_or(0);
+            rows.place(gate, last, gate.fanin);
+            continue;
+        }
+        let second = rows.row(b).unwrap_or(0);
+        if first == second {
+            rows.place(gate, second, 2);
+            continue;
+        }
+
+        // Readers whose other operand already sits in a row
+        let mut shared = [0usize; 2];
+        for reader in circuit.gate_readers(i) {
+            let reader = &circuit.gates[reader];
+            if reader.fanin != 2 {
+                continue;
+            }
+            let other = if gate.outs().any(|s| s == reader.inputs[0]) { reader.inputs[1] } else { reader.inputs[0] };
+            match rows.row(other) {
+                Some(row) if row == first => shared[0] += 1,
+                Some(row) if row == second => shared[1] += 1,
+                _ => {}
+            }
+        }
+        // Rows already as wide as the original mapping are avoided if possible
+        let used = |row: usize| rows.used.get(&row).copied().unwrap_or(0);
+        let row = match (used(first) + 2 <= width, used(second) + 2 <= width) {
+            (true, false) => first,
+            (false, true) => second,
+            (false, false) if used(first) < used(second) => first,
+            (false, false) => second,
+            _ if shared[0] > shared[1] => first,
+            _ => second,
+        };
+        if row == first {
+            circuit.gates[i].inputs.swap(0, 1);
+            swapped += 1;
+        }
+        rows.place(&circuit.gates[i], row, 2);
+    }
+    swapped
+}
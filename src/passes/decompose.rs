@@ -0,0 +1,101 @@
+//passes/decompose.rs
+use anyhow::{Result, ensure};
+
+use super::{Pass, PassReport};
+use crate::gates::{GateKind, Split};
+use crate::{signal_name, Circuit, TableGate, MAX_GATES};
+
+// Splits NORs wider than the devices can evaluate. By De Morgan,
+// NOR(a, b, c, d) = NOR(OR(a, b), OR(c, d)) with OR(a, b) = NOT(NOR(a, b)),
+// so the inputs are cut into at most `max_fanin` groups, each group is ORed
+// by a NOR and a NOT (a group of one is read directly) and the gate reads the
+// groups. Groups still too wide are split the same way. The same identities
+// hold for NAND circuits with AND in place of OR. The gate keeps its output
+// and new signals are temporaries carrying its attributes and origin
+#[derive(Debug, Clone)]
+pub struct Decompose {
+    pub max_fanin: usize,
+}
+
+impl Pass for Decompose {
+    fn name(&self) -> &'static str {
+        "decompose"
+    }
+
+    fn keeps_extra_outs(&self) -> bool {
+        true
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        ensure!(self.max_fanin >= 2, "Gates must take at least two inputs, not {}", self.max_fanin);
+        let gates_before = circuit.num_gates;
+        let mut next_temp = circuit.gates.iter()
+            .flat_map(|g| g.inputs[..g.fanin].iter().copied().chain(g.outs()))
+            .map(|s| -s)
+            .max()
+            .unwrap_or(0)
+            .max(0) + 1;
+
+        // New gates go right before the gate they feed, so drivers still
+        // come first in netlist order
+        let mut split = 0;
+        let primitive = circuit.primitive;
+        let mut gates = Vec::with_capacity(circuit.gates.len());
+        for mut gate in circuit.gates.drain(..) {
+            if gate.fanin > self.max_fanin {
+                let kind = GateKind::of(primitive, gate.fanin);
+                ensure!(kind.spec().split == Split::Regroup, "{} gates cannot be split, but {} has {} inputs",
+                    kind, signal_name(gate.out), gate.fanin);
+                let inputs = gate.inputs[..gate.fanin].to_vec();
+                let first = gates.len();
+                gate.set_inputs(&self.groups(&inputs, &mut next_temp, &mut gates)?);
+                for added in &mut gates[first..] {
+                    added.attributes = gate.attributes.clone();
+                    added.origin = gate.origin.clone();
+                }
+                split += 1;
+            }
+            gates.push(gate);
+        }
+        ensure!(gates.len() <= MAX_GATES,
+            "Splitting {} gates exceeds the maximum of {} gates", split, MAX_GATES);
+        circuit.gates = gates;
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.gates.len(),
+            details: vec![format!("split {} gates wider than {} inputs", split, self.max_fanin)],
+            removed: Vec::new(),
+        })
+    }
+}
+
+impl Decompose {
+    // Signals whose NOR equals the NOR of `inputs`, at most `max_fanin` of them
+    fn groups(&self, inputs: &[i32], next_temp: &mut i32, added: &mut Vec<TableGate>) -> Result<Vec<i32>> {
+        if inputs.len() <= self.max_fanin {
+            return Ok(inputs.to_vec());
+        }
+        let size = inputs.len().div_ceil(self.max_fanin);
+        inputs.chunks(size)
+            .map(|group| {
+                if group.len() == 1 {
+                    return Ok(group[0]);
+                }
+                let nor = self.gate(self.groups(group, next_temp, added)?, next_temp, added)?;
+                self.gate(vec![nor], next_temp, added)
+            })
+            .collect()
+    }
+
+    fn gate(&self, inputs: Vec<i32>, next_temp: &mut i32, added: &mut Vec<TableGate>) -> Result<i32> {
+        ensure!(*next_temp < MAX_GATES as i32, "Too many temporary signals");
+        let out = -*next_temp;
+        *next_temp += 1;
+        let mut gate = TableGate { out, ..Default::default() };
+        gate.set_inputs(&inputs);
+        added.push(gate);
+        Ok(out)
+    }
+}
@@ -0,0 +1,413 @@
+//passes/dont_care.rs
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, bail, ensure};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::minimize::leaf_pattern;
+use super::{Pass, PassReport};
+use crate::simulator::{output_signals, topological_order};
+use crate::{signal_name, Circuit, TableGate, MAX_GATES};
+
+// Facts are proven by enumerating the primary inputs a signal depends on
+const MAX_SUPPORT: usize = 14;
+// Random care vectors, 64 per batch, used to find candidate facts
+const SAMPLE_BATCHES: usize = 64;
+const SAMPLE_SEED: u64 = 0x5eed;
+
+// Input combinations that never occur, one cube per line: a bit string over
+// the primary inputs, x0 first, with '-' for inputs the cube does not fix.
+// '#' starts a comment; whitespace and '_' are ignored
+#[derive(Debug, Clone, Default)]
+pub struct DontCareSet {
+    width: usize,
+    // Fixed (input, value) pairs of each cube
+    cubes: Vec<Vec<(usize, bool)>>,
+}
+
+impl DontCareSet {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = fs::read_to_string(path.as_ref())
+            .context(format!("Failed to read don't-care file: {:?}", path.as_ref()))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut set = DontCareSet::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut cube = Vec::new();
+            let mut width = 0;
+            for ch in line.chars().filter(|c| !c.is_whitespace() && *c != '_') {
+                match ch {
+                    '0' => cube.push((width, false)),
+                    '1' => cube.push((width, true)),
+                    '-' => {}
+                    _ => bail!("Invalid character '{}' in don't-care cube on line {}", ch, n + 1),
+                }
+                width += 1;
+            }
+            ensure!(set.cubes.is_empty() || width == set.width,
+                "Don't-care cube on line {} has {} bits, earlier cubes have {}", n + 1, width, set.width);
+            set.width = width;
+            set.cubes.push(cube);
+        }
+        Ok(set)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cubes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cubes.is_empty()
+    }
+
+    // Lanes of `inputs` (one word per input) that fall outside every cube
+    fn care(&self, inputs: &[u64]) -> u64 {
+        !self.cubes.iter()
+            .map(|cube| cube.iter().fold(!0, |acc, &(i, v)| acc & if v { inputs[i] } else { !inputs[i] }))
+            .fold(0, |acc, covered| acc | covered)
+    }
+}
+
+// Simplifies the netlist under input combinations that never occur. Signals
+// that are constant on every other combination are propagated as constants,
+// and signals equal to an earlier one are merged into it; gates left without
+// readers are removed. Candidates come from random simulation and each is
+// proven by enumerating its primary-input support, so facts about signals
+// with more than MAX_SUPPORT inputs are not used
+#[derive(Debug, Clone)]
+pub struct DontCare {
+    pub set: DontCareSet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Constant(bool),
+    Signal(i32),
+}
+
+impl Pass for DontCare {
+    fn name(&self) -> &'static str {
+        "dont-care"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        ensure!(self.set.is_empty() || self.set.width == circuit.num_inputs,
+            "Don't-care cubes have {} bits but the circuit has {} inputs", self.set.width, circuit.num_inputs);
+        let gates_before = circuit.num_gates;
+        let netlist = Netlist::new(circuit)?;
+
+        // Candidates: constant on, or equal to an earlier signal on, sampled care vectors
+        let mut rng = StdRng::seed_from_u64(SAMPLE_SEED);
+        let mut signatures = vec![Vec::with_capacity(SAMPLE_BATCHES); netlist.slots()];
+        let (mut seen0, mut seen1) = (vec![0u64; netlist.slots()], vec![0u64; netlist.slots()]);
+        for _ in 0..SAMPLE_BATCHES {
+            let inputs: Vec<u64> = (0..circuit.num_inputs).map(|_| rng.gen()).collect();
+            let care = self.set.care(&inputs);
+            for (slot, word) in netlist.eval(&inputs).into_iter().enumerate() {
+                seen0[slot] |= !word & care;
+                seen1[slot] |= word & care;
+                signatures[slot].push(word & care);
+            }
+        }
+
+        let outputs = output_signals(circuit);
+        let mut facts: HashMap<i32, Value> = HashMap::new();
+        let mut classes: HashMap<&[u64], usize> = HashMap::new();
+        for slot in netlist.inputs..netlist.slots() {
+            let signal = netlist.signals[slot];
+            let Some(support) = &netlist.support[slot] else { continue };
+            if seen0[slot] == 0 || seen1[slot] == 0 {
+                let value = seen1[slot] != 0;
+                if netlist.prove(&self.set, &[slot], support, |w| if value { !w[0] } else { w[0] }) {
+                    facts.insert(signal, Value::Constant(value));
+                }
+                continue;
+            }
+
+            // Outputs keep their own gate, but may stand for later signals
+            let first = *classes.entry(&signatures[slot]).or_insert(slot);
+            if first == slot || outputs.contains(&signal) {
+                continue;
+            }
+            let Some(first_support) = &netlist.support[first] else { continue };
+            let mut union = support.clone();
+            union.extend(first_support.iter().filter(|i| !support.contains(i)));
+            if union.len() <= MAX_SUPPORT && netlist.prove(&self.set, &[slot, first], &union, |w| w[0] ^ w[1]) {
+                facts.insert(signal, Value::Signal(netlist.signals[first]));
+            }
+        }
+        let constants = facts.values().filter(|v| matches!(v, Value::Constant(_))).count();
+        let merged = facts.len() - constants;
+
+        let rewritten = rewrite(circuit, &netlist, &facts, &outputs);
+        let kept: HashSet<i32> = rewritten.iter().map(|g| g.out).collect();
+        let mut removed: Vec<i32> = circuit.gates.iter()
+            .map(|g| g.out)
+            .filter(|out| !kept.contains(out))
+            .collect();
+        removed.sort_unstable();
+
+        circuit.gates = rewritten;
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![
+                format!("{} don't-care cubes", self.set.len()),
+                format!("{} constant signals", constants),
+                format!("{} merged signals", merged),
+            ],
+            removed,
+        })
+    }
+}
+
+// The gates left when primary inputs are fixed at `values` and the constants
+// propagated, as by a don't-care set excluding every other combination
+pub(super) fn bind_inputs(circuit: &Circuit, values: &[(usize, bool)]) -> Result<Vec<TableGate>> {
+    let netlist = Netlist::new(circuit)?;
+    let facts: HashMap<i32, Value> = values.iter()
+        .map(|&(input, value)| ((MAX_GATES + input) as i32, Value::Constant(value)))
+        .collect();
+    Ok(rewrite(circuit, &netlist, &facts, &output_signals(circuit)))
+}
+
+// Rebuilds the gates in topological order with the proven facts applied and
+// constants propagated, then drops gates that no output depends on. Facts
+// about primary inputs hold from the start. `@dont_touch` gates stay as they
+// are and keep their readers, and the signals they read keep a gate of their
+// own, as outputs do. Rebuilt gates keep their attributes, and every gate
+// built for one keeps its origin
+fn rewrite(circuit: &Circuit, netlist: &Netlist, facts: &HashMap<i32, Value>, outputs: &[i32]) -> Vec<TableGate> {
+    let primitive = circuit.primitive;
+    // An input at this value fixes the gate's output to its complement; inputs
+    // at the other value can be dropped, and a gate with no inputs left is
+    // at this value (NOR(1, ..) = 0 and NOR() = 1)
+    let controlling = primitive.eval([!0, 0]) == 0;
+
+    let mut values: HashMap<i32, Value> = facts.iter()
+        .filter(|&(&s, _)| s >= MAX_GATES as i32)
+        .map(|(&s, &v)| (s, v))
+        .collect();
+    let mut gates = Vec::with_capacity(circuit.num_gates);
+    let mut next_temp = circuit.gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1;
+    let resolve = |values: &HashMap<i32, Value>, s: i32| values.get(&s).copied().unwrap_or(Value::Signal(s));
+    let pinned: HashSet<i32> = circuit.gates.iter()
+        .filter(|g| g.dont_touch())
+        .flat_map(|g| g.inputs[..g.fanin].iter().copied())
+        .collect();
+
+    for &i in &netlist.order {
+        let gate = &circuit.gates[i];
+        if gate.dont_touch() {
+            let mut kept = table_gate(gate.out, &gate.inputs[..gate.fanin], gate.is_output);
+            kept.attributes = gate.attributes.clone();
+            gates.push((i, kept));
+            continue;
+        }
+        // Whether the gate keeps its own signal
+        let is_output = outputs.contains(&gate.out) || pinned.contains(&gate.out);
+
+        let mut inputs = Vec::with_capacity(gate.fanin);
+        let mut value = None;
+        for &input in &gate.inputs[..gate.fanin] {
+            match resolve(&values, input) {
+                Value::Constant(c) if c == controlling => value = Some(Value::Constant(!controlling)),
+                Value::Constant(_) => {}
+                Value::Signal(s) => if !inputs.contains(&s) { inputs.push(s) },
+            }
+        }
+        if inputs.is_empty() && value.is_none() {
+            value = Some(Value::Constant(controlling));
+        }
+        if let Some(&fact) = facts.get(&gate.out) {
+            value = Some(match fact {
+                Value::Signal(s) => resolve(&values, s),
+                constant => constant,
+            });
+        }
+        // NOT(NOT(s)) is s
+        if let ([s], None, false) = (&inputs[..], value, is_output) {
+            if let Some(inner) = gates.iter().find(|(_, g): &&(usize, TableGate)| g.out == *s && g.fanin == 1) {
+                value = Some(Value::Signal(inner.1.inputs[0]));
+            }
+        }
+
+        match value {
+            Some(Value::Signal(s)) if !is_output => {
+                values.insert(gate.out, Value::Signal(s));
+            }
+            Some(Value::Constant(c)) if !is_output => {
+                values.insert(gate.out, Value::Constant(c));
+            }
+            // Outputs keep their signal: constants are tied off from x0 as
+            // PRIM(x0, NOT x0), which is the complement of the controlling value
+            Some(Value::Constant(c)) => {
+                let x0 = MAX_GATES as i32;
+                let mut temp = || {
+                    next_temp += 1;
+                    -(next_temp - 1)
+                };
+                let not_x0 = temp();
+                gates.push((i, table_gate(not_x0, &[x0], false)));
+                let mut tied = if c == controlling {
+                    let tie = temp();
+                    gates.push((i, table_gate(tie, &[x0, not_x0], false)));
+                    table_gate(gate.out, &[tie], gate.is_output)
+                } else {
+                    table_gate(gate.out, &[x0, not_x0], gate.is_output)
+                };
+                tied.attributes = gate.attributes.clone();
+                gates.push((i, tied));
+            }
+            _ => {
+                let mut rebuilt = table_gate(gate.out, &inputs, gate.is_output);
+                rebuilt.attributes = gate.attributes.clone();
+                gates.push((i, rebuilt));
+            }
+        }
+    }
+
+    // Only gates that some output depends on survive, in their original order
+    gates.sort_by_key(|&(i, _)| i);
+    for (i, gate) in &mut gates {
+        gate.origin = circuit.gates[*i].origin.clone();
+    }
+    let mut gates: Vec<TableGate> = gates.into_iter().map(|(_, g)| g).collect();
+    let drivers: HashMap<i32, usize> = gates.iter().enumerate().map(|(k, g)| (g.out, k)).collect();
+    let mut live = vec![false; gates.len()];
+    let mut stack: Vec<usize> = outputs.iter().filter_map(|o| drivers.get(o).copied()).collect();
+    stack.extend(gates.iter().enumerate().filter(|(_, g)| g.dont_touch()).map(|(k, _)| k));
+    while let Some(k) = stack.pop() {
+        if std::mem::replace(&mut live[k], true) {
+            continue;
+        }
+        stack.extend(gates[k].inputs[..gates[k].fanin].iter().filter_map(|s| drivers.get(s).copied()));
+    }
+    let mut k = 0;
+    gates.retain(|_| {
+        k += 1;
+        live[k - 1]
+    });
+    gates
+}
+
+fn table_gate(out: i32, inputs: &[i32], is_output: bool) -> TableGate {
+    let mut gate = TableGate { out, is_output, ..Default::default() };
+    gate.set_inputs(inputs);
+    gate
+}
+
+// Slot-indexed netlist like the simulator's, with the primary-input support
+// of every slot (None past MAX_SUPPORT inputs)
+struct Netlist {
+    primitive: crate::Primitive,
+    inputs: usize,
+    order: Vec<usize>,
+    signals: Vec<i32>,
+    ops: Vec<Vec<usize>>,
+    support: Vec<Option<Vec<usize>>>,
+}
+
+impl Netlist {
+    fn new(circuit: &Circuit) -> Result<Self> {
+        let order = topological_order(circuit)?;
+        let inputs = circuit.num_inputs;
+        let mut signals: Vec<i32> = (0..inputs).map(|i| (MAX_GATES + i) as i32).collect();
+        signals.extend(order.iter().map(|&i| circuit.gates[i].out));
+        let slot_of: HashMap<i32, usize> = signals.iter().enumerate().map(|(k, &s)| (s, k)).collect();
+
+        let mut ops = vec![Vec::new(); inputs];
+        let mut support: Vec<Option<Vec<usize>>> = (0..inputs).map(|i| Some(vec![i])).collect();
+        for &i in &order {
+            let gate = &circuit.gates[i];
+            let srcs: Vec<usize> = gate.inputs[..gate.fanin].iter()
+                .map(|s| slot_of.get(s).copied()
+                    .with_context(|| format!("Signal {} is outside the primary input range", signal_name(*s))))
+                .collect::<Result<_>>()?;
+            let mut merged: Option<Vec<usize>> = Some(Vec::new());
+            for &src in &srcs {
+                merged = match (merged, &support[src]) {
+                    (Some(mut acc), Some(s)) => {
+                        let new: Vec<usize> = s.iter().copied().filter(|i| !acc.contains(i)).collect();
+                        acc.extend(new);
+                        (acc.len() <= MAX_SUPPORT).then_some(acc)
+                    }
+                    _ => None,
+                };
+            }
+            support.push(merged);
+            ops.push(srcs);
+        }
+
+        Ok(Self { primitive: circuit.primitive, inputs, order, signals, ops, support })
+    }
+
+    fn slots(&self) -> usize {
+        self.signals.len()
+    }
+
+    fn eval(&self, inputs: &[u64]) -> Vec<u64> {
+        let mut words = inputs.to_vec();
+        for slot in self.inputs..self.slots() {
+            let word = self.primitive.eval(self.ops[slot].iter().map(|&s| words[s]));
+            words.push(word);
+        }
+        words
+    }
+
+    // True if `violations` (over the words of `targets`) is zero on every
+    // assignment of the `support` inputs that no cube within them excludes
+    fn prove(&self, set: &DontCareSet, targets: &[usize], support: &[usize], violations: impl Fn(&[u64]) -> u64) -> bool {
+        // Slots of the targets' cones, in evaluation order
+        let mut in_cone = vec![false; self.slots()];
+        let mut stack = targets.to_vec();
+        while let Some(slot) = stack.pop() {
+            if !std::mem::replace(&mut in_cone[slot], true) {
+                stack.extend(&self.ops[slot]);
+            }
+        }
+        let cone: Vec<usize> = (self.inputs..self.slots()).filter(|&s| in_cone[s]).collect();
+
+        // Cubes over other inputs may not exclude anything once those are free
+        let local = DontCareSet {
+            width: support.len(),
+            cubes: set.cubes.iter()
+                .filter_map(|cube| cube.iter()
+                    .map(|&(i, v)| support.iter().position(|&s| s == i).map(|j| (j, v)))
+                    .collect())
+                .collect(),
+        };
+
+        let lanes = if support.len() >= 6 { !0 } else { (1u64 << (1 << support.len())) - 1 };
+        let mut words = vec![0u64; self.slots()];
+        for batch in 0..1u64 << support.len().saturating_sub(6) {
+            let assignment: Vec<u64> = (0..support.len())
+                .map(|j| if j < 6 { leaf_pattern(j) } else if batch >> (j - 6) & 1 == 1 { !0 } else { 0 })
+                .collect();
+            for (j, &input) in support.iter().enumerate() {
+                words[input] = assignment[j];
+            }
+            for &slot in &cone {
+                words[slot] = self.primitive.eval(self.ops[slot].iter().map(|&s| words[s]));
+            }
+            let targets: Vec<u64> = targets.iter().map(|&t| words[t]).collect();
+            if violations(&targets) & local.care(&assignment) & lanes != 0 {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,156 @@
+//passes/duplicate.rs
+use std::collections::HashMap;
+use anyhow::Result;
+
+use super::commute::{estimate, mapping_order, signal_rows};
+use super::{Pass, PassReport};
+use crate::{signal_name, Circuit, MAX_COL, MAX_GATES};
+
+// Duplicated gates the report names, in mapping order
+const DUPLICATES_LISTED: usize = 8;
+
+// Recomputes high-fanout gates in a second row instead of copying their value
+// there. The compact mapper puts a gate in its last operand's row, and each
+// reader in another row copies the gate into its own. A duplicate with another
+// operand last lands in that operand's row, where the readers of that row read
+// it in place; the duplicate in turn copies its operands from other rows. A
+// gate is duplicated into the row where the copies saved outnumber the ones
+// the duplicate costs, and the duplicate is kept only if the mapper is
+// estimated to make fewer copies overall and still fit in the crossbar's
+// columns. `@dont_touch` gates are neither duplicated nor moved onto a
+// duplicate. Runs after the levels and operand order are final, like `commute`
+#[derive(Debug, Clone, Default)]
+pub struct Duplicate;
+
+// A duplicate worth trying: the operand to put last and the readers to move
+// onto the duplicate
+struct Candidate {
+    last: usize,
+    readers: Vec<usize>,
+}
+
+impl Pass for Duplicate {
+    fn name(&self) -> &'static str {
+        "duplicate"
+    }
+
+    fn keeps_extra_outs(&self) -> bool {
+        true
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let mut order = mapping_order(circuit);
+        let before = estimate(circuit, &order);
+        let mut result = before;
+        let mut rows = signal_rows(circuit, &order);
+        let mut next_temp = circuit.gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1;
+
+        let mut duplicated = Vec::new();
+        for i in order.clone() {
+            let Some(candidate) = candidate(circuit, i, &rows) else { continue };
+            if next_temp >= MAX_GATES as i32 {
+                break;
+            }
+            let original = circuit.gates[i].out;
+            let out = -next_temp;
+            let mut gate = circuit.gates[i].clone();
+            gate.out = out;
+            gate.is_output = false;
+            gate.extra_outs.clear();
+            gate.output_gates.clear();
+            let fanin = gate.fanin;
+            gate.inputs.swap(candidate.last, fanin - 1);
+            circuit.gates.push(gate);
+            circuit.num_gates += 1;
+            let moved = retarget(circuit, &candidate.readers, original, out);
+            circuit.index_signals();
+
+            let new_order = mapping_order(circuit);
+            let after = estimate(circuit, &new_order);
+            let fits = after.width <= MAX_COL || after.width <= result.width;
+            if after.copies < result.copies && fits {
+                result = after;
+                order = new_order;
+                rows = signal_rows(circuit, &order);
+                next_temp += 1;
+                duplicated.push(original);
+            } else {
+                retarget(circuit, &moved, out, original);
+                circuit.gates.pop();
+                circuit.num_gates -= 1;
+                circuit.index_signals();
+            }
+        }
+
+        let mut names: Vec<String> = duplicated.iter().take(DUPLICATES_LISTED).map(|&s| signal_name(s)).collect();
+        if duplicated.len() > DUPLICATES_LISTED {
+            names.push("...".to_string());
+        }
+        let mut details = vec![if names.is_empty() {
+            "0 duplicates".to_string()
+        } else {
+            format!("{} duplicates of {}", duplicated.len(), names.join(" "))
+        }];
+        details.push(format!("estimated copies {} -> {}", before.copies, result.copies));
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details,
+            removed: Vec::new(),
+        })
+    }
+}
+
+// The row of one of the gate's operands where moving readers onto a
+// duplicate saves the most copies beyond the duplicate's own, if any does
+fn candidate(circuit: &Circuit, i: usize, rows: &HashMap<i32, usize>) -> Option<Candidate> {
+    let gate = &circuit.gates[i];
+    if gate.fanin < 2 || gate.dont_touch() {
+        return None;
+    }
+    let row_of = |s: i32| rows.get(&s).copied().unwrap_or(0);
+    let home = row_of(gate.out);
+
+    // Readers that copy the gate, by the row they sit in
+    let mut copying: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &reader in circuit.readers(gate.out) {
+        let r = &circuit.gates[reader];
+        let row = row_of(r.out);
+        if r.fanin >= 2 && !r.dont_touch() && r.inputs[r.fanin - 1] != gate.out && row != home {
+            copying.entry(row).or_default().push(reader);
+        }
+    }
+
+    let operands = &gate.inputs[..gate.fanin];
+    let mut best: Option<(usize, Candidate)> = None;
+    for (k, &operand) in operands.iter().enumerate() {
+        let row = row_of(operand);
+        let Some(readers) = copying.get(&row) else { continue };
+        let cost = operands.iter().filter(|&&s| row_of(s) != row).count();
+        if readers.len() > cost && best.as_ref().is_none_or(|(gain, _)| readers.len() - cost > *gain) {
+            best = Some((readers.len() - cost, Candidate { last: k, readers: readers.clone() }));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+// Points the readers' operands `from` at `to`, except a last operand, which
+// decides the reader's row; returns the readers changed
+fn retarget(circuit: &mut Circuit, readers: &[usize], from: i32, to: i32) -> Vec<usize> {
+    let mut moved = Vec::new();
+    for &reader in readers {
+        let gate = &mut circuit.gates[reader];
+        let fanin = gate.fanin;
+        let mut changed = false;
+        for operand in gate.inputs[..fanin - 1].iter_mut().filter(|s| **s == from) {
+            *operand = to;
+            changed = true;
+        }
+        if changed {
+            moved.push(reader);
+        }
+    }
+    moved
+}
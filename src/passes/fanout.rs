@@ -0,0 +1,199 @@
+//passes/fanout.rs
+use std::cmp::Reverse;
+use anyhow::Result;
+
+use super::{Pass, PassReport};
+use crate::scheduler::{self, Schedule};
+use crate::{signal_name, Circuit, TableGate, MAX_GATES};
+
+// Split signals the report names, most read first
+const SPLITS_LISTED: usize = 8;
+
+// Splits signals read by more than `max_fanout` gates into balanced trees of
+// NOTs, so that no gate or input is read more than `max_fanout` times. Each
+// stage of the tree reads the one before it, at most `max_fanout` nodes to a
+// parent, and the readers are shared out over the last stage in netlist order.
+// A tree has an even number of stages so the last one carries the signal
+// itself; where the shortest tree would invert it, one NOT heads the tree.
+// A reader in another row then copies its leaf rather than the signal, so the
+// copies of one value are split over the leaves, at the cost of the tree's
+// gates and levels. `@dont_touch` gates are not split, and `@dont_touch`
+// readers keep reading the signal
+#[derive(Debug, Clone)]
+pub struct FanoutTrees {
+    pub max_fanout: usize,
+}
+
+impl Default for FanoutTrees {
+    fn default() -> Self {
+        Self { max_fanout: 16 }
+    }
+}
+
+impl Pass for FanoutTrees {
+    fn name(&self) -> &'static str {
+        "fanout"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let mut next_temp = circuit.gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1;
+
+        let mut split = Vec::new();
+        let mut deepest = 0;
+        for (signal, readers) in overloaded(circuit, self.max_fanout) {
+            let stages = stage_sizes(readers.len(), self.max_fanout);
+            let added: usize = stages.iter().sum();
+            if next_temp as usize + added > MAX_GATES {
+                break;
+            }
+            let origin = circuit.driver(signal).and_then(|g| g.origin.clone());
+            // Each stage's nodes read the previous stage's, in balanced shares
+            let mut parents = vec![signal];
+            for &size in &stages {
+                let nodes: Vec<i32> = (0..size as i32).map(|k| -(next_temp + k)).collect();
+                next_temp += size as i32;
+                for (share, &parent) in shares(&nodes, parents.len()).zip(&parents) {
+                    for &out in share {
+                        let mut gate = TableGate { out, origin: origin.clone(), ..Default::default() };
+                        gate.set_inputs(&[parent]);
+                        circuit.gates.push(gate);
+                    }
+                }
+                parents = nodes;
+            }
+            for (share, &leaf) in shares(&readers, parents.len()).zip(&parents) {
+                for &reader in share {
+                    let gate = &mut circuit.gates[reader];
+                    let fanin = gate.fanin;
+                    for operand in gate.inputs[..fanin].iter_mut().filter(|s| **s == signal) {
+                        *operand = leaf;
+                    }
+                }
+            }
+            deepest = deepest.max(stages.len());
+            split.push((signal, readers.len()));
+        }
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+
+        let mut names: Vec<String> = split.iter().take(SPLITS_LISTED)
+            .map(|&(s, readers)| format!("{} ({})", signal_name(s), readers))
+            .collect();
+        if split.len() > SPLITS_LISTED {
+            names.push("...".to_string());
+        }
+        let mut details = vec![if names.is_empty() {
+            format!("0 signals read more than {} times", self.max_fanout)
+        } else {
+            format!("{} trees for {}", split.len(), names.join(" "))
+        }];
+        if deepest > 0 {
+            details.push(format!("{} NOTs, at most {} stages", circuit.num_gates - gates_before, deepest));
+        }
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details,
+            removed: Vec::new(),
+        })
+    }
+}
+
+// Signals read by more than `max_fanout` gates and the readers that may be
+// moved onto a tree, most read first; a reader reading a signal twice counts
+// once
+fn overloaded(circuit: &Circuit, max_fanout: usize) -> Vec<(i32, Vec<usize>)> {
+    let mut signals: Vec<(i32, Vec<usize>)> = fanouts(circuit)
+        .filter(|&(signal, readers)| readers > max_fanout && !circuit.driver(signal).is_some_and(|g| g.dont_touch()))
+        .map(|(signal, _)| {
+            let mut readers: Vec<usize> = circuit.readers(signal).iter().copied()
+                .filter(|&i| !circuit.gates[i].dont_touch())
+                .collect();
+            readers.dedup();
+            (signal, readers)
+        })
+        .filter(|(_, readers)| readers.len() > max_fanout)
+        .collect();
+    signals.sort_by_key(|(signal, readers)| (Reverse(readers.len()), *signal));
+    signals
+}
+
+// Every signal read by a gate and its number of reading gates
+fn fanouts(circuit: &Circuit) -> impl Iterator<Item = (i32, usize)> + '_ {
+    let inputs = (0..circuit.num_inputs).map(|k| (MAX_GATES + k) as i32);
+    let gates = circuit.gates[..circuit.num_gates].iter().map(|g| g.out);
+    inputs.chain(gates).filter_map(|signal| {
+        let mut readers = circuit.readers(signal).to_vec();
+        readers.dedup();
+        (!readers.is_empty()).then_some((signal, readers.len()))
+    })
+}
+
+// Nodes in each stage of the tree for `readers` readers, first stage first:
+// the last stage feeds the readers, and each stage before it feeds the next,
+// `max_fanout` at most per node. An odd count of stages gets a single NOT at
+// the head
+fn stage_sizes(readers: usize, max_fanout: usize) -> Vec<usize> {
+    let mut stages = Vec::new();
+    let mut fed = readers;
+    while fed > max_fanout {
+        fed = fed.div_ceil(max_fanout);
+        stages.push(fed);
+    }
+    if stages.len() % 2 == 1 {
+        stages.push(1);
+    }
+    stages.reverse();
+    stages
+}
+
+// `items` cut into `parts` runs whose lengths differ by one at most
+fn shares<T>(items: &[T], parts: usize) -> impl Iterator<Item = &[T]> {
+    let (size, extra) = (items.len() / parts, items.len() % parts);
+    (0..parts).scan(0, move |start, k| {
+        let end = *start + size + usize::from(k < extra);
+        let share = &items[*start..end];
+        *start = end;
+        Some(share)
+    })
+}
+
+// What the fanout trees spread out, measured on an ASAP schedule of a copy of
+// the circuit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanoutProfile {
+    pub gates: usize,
+    pub levels: i32,
+    // Most gates reading one signal
+    pub max_fanout: usize,
+    // Most gates in one level
+    pub peak_level_gates: usize,
+    // Operands the compact mapper copies into another row, in all and most
+    // of one signal in one level
+    pub copies: usize,
+    pub peak_copies: usize,
+    // Signals read by more than the limit measured against, most read first
+    pub overloaded: Vec<(i32, usize)>,
+}
+
+impl FanoutProfile {
+    pub fn measure(circuit: &Circuit, max_fanout: usize) -> Self {
+        let mut circuit = circuit.clone();
+        scheduler::compute_asap_schedule(&mut circuit);
+        let schedule = Schedule::asap(&circuit);
+        let pressure = scheduler::copy_pressure(&circuit);
+        let mut overloaded: Vec<(i32, usize)> = fanouts(&circuit).filter(|&(_, readers)| readers > max_fanout).collect();
+        overloaded.sort_by_key(|&(signal, readers)| (Reverse(readers), signal));
+        Self {
+            gates: circuit.num_gates,
+            levels: circuit.max_asap,
+            max_fanout: fanouts(&circuit).map(|(_, readers)| readers).max().unwrap_or(0),
+            peak_level_gates: schedule.levels().map(|(_, gates)| gates.len()).max().unwrap_or(0),
+            copies: pressure.iter().map(|p| p.copies).sum(),
+            peak_copies: pressure.iter().map(|p| p.peak).max().unwrap_or(0),
+            overloaded,
+        }
+    }
+}
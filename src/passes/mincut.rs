@@ -0,0 +1,280 @@
+//passes/mincut.rs
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use anyhow::Result;
+
+use super::commute::mapping_order;
+use super::{Pass, PassReport};
+use crate::{Circuit, MAX_GATES};
+
+// Picks the compact mapping's row of every gate by partitioning the gate
+// graph across the input rows. An operand in another row than its reader is
+// copied there, so the copies are the cut edges, and Fiduccia-Mattheyses
+// passes move gates between rows to cut fewer of them. The passes start from
+// the rows the mapper would give (each gate in its last operand's row) and no
+// row may grow wider than the widest one of that start, so the crossbar keeps
+// its width. The rows are recorded on the circuit for the compact mapper to
+// follow. Runs after the levels and operand order are final, like `commute`
+#[derive(Debug, Clone)]
+pub struct MinCut {
+    // Refinement passes at most; each stops paying off sooner or later
+    pub passes: usize,
+}
+
+impl Default for MinCut {
+    fn default() -> Self {
+        Self { passes: 8 }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    // A primary input, fixed in its row
+    Input(usize),
+    Gate(usize),
+}
+
+// Rows of the gates as the passes move them, with the columns each row uses
+struct Partition {
+    operands: Vec<Vec<Source>>,
+    // One entry per operand slot reading the gate
+    readers: Vec<Vec<usize>>,
+    fanin: Vec<usize>,
+    row: Vec<usize>,
+    load: Vec<usize>,
+    rows: usize,
+    cap: usize,
+}
+
+impl Pass for MinCut {
+    fn name(&self) -> &'static str {
+        "mincut"
+    }
+
+    fn keeps_extra_outs(&self) -> bool {
+        true
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let mut partition = Partition::new(circuit);
+        let (cut_before, width_before) = (partition.cut(), partition.width());
+        let mut passes = 0;
+        while passes < self.passes && partition.refine() > 0 {
+            passes += 1;
+        }
+
+        let initial = partition.initial(circuit);
+        let moved = (0..initial.len()).filter(|&i| partition.row[i] != initial[i]).count();
+        circuit.planned_rows = circuit.gates[..circuit.num_gates].iter().zip(&partition.row)
+            .map(|(gate, &row)| (gate.out, row))
+            .collect();
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before: circuit.num_gates,
+            gates_after: circuit.num_gates,
+            details: vec![
+                format!("{} passes", passes),
+                format!("{} gates moved", moved),
+                format!("estimated copies {} -> {}", cut_before, partition.cut()),
+                format!("widest row {} -> {}", width_before, partition.width()),
+            ],
+            removed: Vec::new(),
+        })
+    }
+}
+
+impl Partition {
+    fn new(circuit: &Circuit) -> Self {
+        let n = circuit.num_gates;
+        let index: HashMap<i32, usize> = circuit.gates[..n].iter().enumerate()
+            .flat_map(|(i, g)| g.outs().map(move |out| (out, i)))
+            .collect();
+        let mut operands = vec![Vec::new(); n];
+        let mut readers = vec![Vec::new(); n];
+        for (i, gate) in circuit.gates[..n].iter().enumerate() {
+            for &signal in &gate.inputs[..gate.fanin] {
+                let input = signal - MAX_GATES as i32;
+                if input >= 0 && (input as usize) < circuit.num_inputs {
+                    operands[i].push(Source::Input(circuit.input_line(input as usize)));
+                } else if let Some(&j) = index.get(&signal) {
+                    operands[i].push(Source::Gate(j));
+                    readers[j].push(i);
+                }
+            }
+        }
+
+        let rows = circuit.input_line_count().max(1);
+        let mut partition = Self {
+            operands,
+            readers,
+            fanin: circuit.gates[..n].iter().map(|g| g.fanin).collect(),
+            row: vec![0; n],
+            load: vec![0; rows],
+            rows,
+            cap: 0,
+        };
+        partition.row = partition.initial(circuit);
+        for n in circuit.mapped_inputs() {
+            partition.load[circuit.input_line(n)] += 1;
+        }
+        for i in 0..n {
+            partition.load[partition.row[i]] += partition.cells(i);
+        }
+        partition.cap = partition.width();
+        partition
+    }
+
+    // The mapper's own rows, in mapping order: each gate in its last operand's
+    fn initial(&self, circuit: &Circuit) -> Vec<usize> {
+        let mut rows = vec![0; self.row.len()];
+        for i in mapping_order(circuit) {
+            rows[i] = match self.operands[i].last() {
+                Some(&Source::Input(line)) => line,
+                Some(&Source::Gate(j)) => rows[j],
+                None => 0,
+            };
+        }
+        rows
+    }
+
+    fn source_row(&self, source: Source) -> usize {
+        match source {
+            Source::Input(line) => line,
+            Source::Gate(j) => self.row[j],
+        }
+    }
+
+    // Operands of gate `i` that would be copied were it in `row`
+    fn copies(&self, i: usize, row: usize) -> usize {
+        self.operands[i].iter().filter(|&&s| self.source_row(s) != row).count()
+    }
+
+    // Columns the gate takes in its row: its copies and itself, or a column
+    // per operand, whichever is more
+    fn cells(&self, i: usize) -> usize {
+        (self.copies(i, self.row[i]) + 1).max(self.fanin[i])
+    }
+
+    fn cut(&self) -> usize {
+        (0..self.row.len()).map(|i| self.copies(i, self.row[i])).sum()
+    }
+
+    fn width(&self) -> usize {
+        self.load.iter().copied().max().unwrap_or(0)
+    }
+
+    // Copies saved by moving gate `i` to `row`: its own operands and its
+    // readers' operand slots
+    fn gain(&self, i: usize, row: usize) -> i64 {
+        let from = self.row[i];
+        let own = self.copies(i, from) as i64 - self.copies(i, row) as i64;
+        let readers: i64 = self.readers[i].iter()
+            .map(|&r| (self.row[r] == row) as i64 - (self.row[r] == from) as i64)
+            .sum();
+        own + readers
+    }
+
+    // Moves gate `i` to `row`, keeping the loads of every row it touches
+    fn shift(&mut self, i: usize, row: usize) {
+        let mut touched: Vec<usize> = self.readers[i].clone();
+        touched.push(i);
+        touched.sort_unstable();
+        touched.dedup();
+        for &g in &touched {
+            self.load[self.row[g]] -= self.cells(g);
+        }
+        self.row[i] = row;
+        for &g in &touched {
+            self.load[self.row[g]] += self.cells(g);
+        }
+    }
+
+    // Whether a move keeps every row within the width cap; a row already
+    // past it may not grow
+    fn fits(&mut self, i: usize, row: usize) -> bool {
+        let mut lines: Vec<usize> = self.readers[i].iter().map(|&r| self.row[r]).chain([self.row[i], row]).collect();
+        lines.sort_unstable();
+        lines.dedup();
+        let before: Vec<usize> = lines.iter().map(|&l| self.load[l]).collect();
+        let from = self.row[i];
+        self.shift(i, row);
+        let fits = lines.iter().zip(&before).all(|(&l, &old)| self.load[l] <= self.cap.max(old));
+        self.shift(i, from);
+        fits
+    }
+
+    // The best move of gate `i`: among the rows its operands and readers sit
+    // in, the highest gain, then the least loaded row
+    fn best_move(&mut self, i: usize) -> Option<(i64, usize)> {
+        let candidates: BTreeSet<usize> = self.operands[i].iter().map(|&s| self.source_row(s))
+            .chain(self.readers[i].iter().map(|&r| self.row[r]))
+            .filter(|&row| row != self.row[i] && row < self.rows)
+            .collect();
+        let mut best = None;
+        for row in candidates {
+            let gain = self.gain(i, row);
+            if best.is_some_and(|(g, r): (i64, usize)| (g, Reverse(self.load[r])) >= (gain, Reverse(self.load[row]))) {
+                continue;
+            }
+            if self.fits(i, row) {
+                best = Some((gain, row));
+            }
+        }
+        best
+    }
+
+    // One pass: every gate moves once, best gain first, even at a loss, then
+    // the moves past the best running total are undone. Returns the copies saved
+    fn refine(&mut self) -> i64 {
+        let n = self.row.len();
+        let mut locked = vec![false; n];
+        let mut version = vec![0u32; n];
+        let mut heap = BinaryHeap::new();
+        for i in 0..n {
+            if let Some((gain, row)) = self.best_move(i) {
+                heap.push((gain, Reverse(i), row, 0));
+            }
+        }
+
+        let mut moves: Vec<(usize, usize)> = Vec::new();
+        let (mut total, mut best, mut kept) = (0, 0, 0);
+        while let Some((gain, Reverse(i), _, v)) = heap.pop() {
+            if locked[i] || v != version[i] {
+                continue;
+            }
+            // Loads may have changed since the move was queued
+            let Some((now, row)) = self.best_move(i) else { continue };
+            if now != gain {
+                version[i] += 1;
+                heap.push((now, Reverse(i), row, version[i]));
+                continue;
+            }
+            moves.push((i, self.row[i]));
+            self.shift(i, row);
+            locked[i] = true;
+            total += gain;
+            if total > best {
+                (best, kept) = (total, moves.len());
+            }
+
+            let neighbours: Vec<usize> = self.operands[i].iter()
+                .filter_map(|&s| match s { Source::Gate(j) => Some(j), Source::Input(_) => None })
+                .chain(self.readers[i].iter().copied())
+                .collect();
+            for j in neighbours {
+                if locked[j] {
+                    continue;
+                }
+                version[j] += 1;
+                if let Some((gain, row)) = self.best_move(j) {
+                    heap.push((gain, Reverse(j), row, version[j]));
+                }
+            }
+        }
+
+        for &(i, row) in moves[kept..].iter().rev() {
+            self.shift(i, row);
+        }
+        best
+    }
+}
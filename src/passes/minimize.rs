@@ -0,0 +1,326 @@
+//passes/minimize.rs
+use std::collections::{BTreeSet, HashMap, HashSet};
+use anyhow::{Result, ensure};
+
+use super::{Pass, PassReport};
+use crate::simulator::output_signals;
+use crate::{Circuit, Primitive, TableGate, MAX_GATES};
+
+// Truth tables of cones are held in one u64
+const MAX_CONE_INPUTS: usize = 6;
+
+// Two-level minimization of fanout-free cones. Each cone whose leaves number
+// at most `max_inputs` is collapsed into its truth table, minimized to a
+// sum of products with Quine-McCluskey (greedy cover after the essential
+// primes), and rebuilt from NOR/NOT gates if that takes fewer gates. Cones
+// holding a `@dont_touch` gate are left alone; the root's attributes go to
+// the gate that takes over its signal, and its origin to every rebuilt gate
+#[derive(Debug, Clone)]
+pub struct Minimize {
+    pub max_inputs: usize,
+}
+
+impl Default for Minimize {
+    fn default() -> Self {
+        Self { max_inputs: MAX_CONE_INPUTS }
+    }
+}
+
+impl Pass for Minimize {
+    fn name(&self) -> &'static str {
+        "minimize"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        ensure!(circuit.primitive == Primitive::Nor, "Minimization needs a NOR/NOT circuit");
+        let max_inputs = self.max_inputs.min(MAX_CONE_INPUTS);
+        let gates_before = circuit.num_gates;
+
+        // A cone ends at every output and every signal not read by exactly one gate
+        let outputs: HashSet<i32> = output_signals(circuit).into_iter().collect();
+        let is_root = |i: usize| {
+            let out = circuit.gates[i].out;
+            outputs.contains(&out) || circuit.readers(out).len() != 1
+        };
+
+        let mut next_temp = circuit.gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1;
+        let mut removed = HashSet::new();
+        let mut removed_signals = Vec::new();
+        let mut added = Vec::new();
+        let (mut cones, mut rewritten) = (0, 0);
+
+        for root in (0..circuit.num_gates).filter(|&i| is_root(i)) {
+            let Some(cone) = Cone::collect(circuit, root, max_inputs, &is_root) else { continue };
+            if cone.gates.len() < 2 || cone.gates.iter().any(|&i| circuit.gates[i].dont_touch()) {
+                continue;
+            }
+            cones += 1;
+
+            let full = if cone.leaves.len() == MAX_CONE_INPUTS { u64::MAX } else { (1 << (1 << cone.leaves.len())) - 1 };
+            let on = cone.truth_table(circuit) & full;
+            if on == 0 || on == full {
+                continue;
+            }
+
+            let Some(mut network) = synthesize(&cone.leaves, on, full, next_temp) else { continue };
+            if network.gates.len() >= cone.gates.len() {
+                continue;
+            }
+
+            // The top gate takes over the root's signal and output marker
+            let root_gate = &circuit.gates[root];
+            let top = network.top;
+            for gate in &mut network.gates {
+                gate.origin = root_gate.origin.clone();
+                if gate.out == top {
+                    gate.out = root_gate.out;
+                    gate.is_output = root_gate.is_output;
+                    gate.attributes = root_gate.attributes.clone();
+                }
+                for input in gate.inputs.iter_mut().take(gate.fanin) {
+                    if *input == top {
+                        *input = root_gate.out;
+                    }
+                }
+            }
+            ensure!(network.next_temp < MAX_GATES as i32, "Too many temporary signals");
+            next_temp = network.next_temp;
+            removed_signals.extend(cone.gates.iter().map(|&i| circuit.gates[i].out).filter(|&out| out != root_gate.out));
+            removed.extend(cone.gates);
+            added.extend(network.gates);
+            rewritten += 1;
+        }
+
+        let mut index = 0;
+        circuit.gates.retain(|_| {
+            index += 1;
+            !removed.contains(&(index - 1))
+        });
+        circuit.gates.extend(added);
+        removed_signals.sort_unstable();
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![format!("{} of {} cones rewritten", rewritten, cones)],
+            removed: removed_signals,
+        })
+    }
+}
+
+// Gates of a fanout-free cone in topological order, and its leaf signals in
+// the order they were reached
+struct Cone {
+    gates: Vec<usize>,
+    leaves: Vec<i32>,
+}
+
+impl Cone {
+    fn collect(circuit: &Circuit, root: usize, max_inputs: usize, is_root: &dyn Fn(usize) -> bool) -> Option<Self> {
+        let mut cone = Cone { gates: Vec::new(), leaves: Vec::new() };
+        // Post-order walk: (gate, next input to visit)
+        let mut stack = vec![(root, 0)];
+        while let Some((i, k)) = stack.pop() {
+            let gate = &circuit.gates[i];
+            if k == gate.fanin {
+                cone.gates.push(i);
+                continue;
+            }
+            stack.push((i, k + 1));
+            let input = gate.inputs[k];
+            match circuit.driver_index(input) {
+                Some(d) if !is_root(d) => stack.push((d, 0)),
+                _ => {
+                    if !cone.leaves.contains(&input) {
+                        cone.leaves.push(input);
+                        if cone.leaves.len() > max_inputs {
+                            return None;
+                        }
+                    }
+                }
+            }
+        }
+        Some(cone)
+    }
+
+    // Bit m holds the cone's output when leaf j is bit j of m
+    fn truth_table(&self, circuit: &Circuit) -> u64 {
+        let mut values: HashMap<i32, u64> = self.leaves.iter()
+            .enumerate()
+            .map(|(j, &leaf)| (leaf, leaf_pattern(j)))
+            .collect();
+        let mut last = 0;
+        for &i in &self.gates {
+            let gate = &circuit.gates[i];
+            last = Primitive::Nor.eval(gate.inputs[..gate.fanin].iter().map(|s| values[s]));
+            values.insert(gate.out, last);
+        }
+        last
+    }
+}
+
+// Truth table column of variable j over the 64 assignments of six variables
+pub(super) fn leaf_pattern(j: usize) -> u64 {
+    (0..64u64).filter(|m| m >> j & 1 == 1).fold(0, |acc, m| acc | 1 << m)
+}
+
+// A product term: variables in `care` must equal their bit in `bits`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Cube {
+    care: u8,
+    bits: u8,
+}
+
+impl Cube {
+    fn covers(&self, m: u8) -> bool {
+        m & self.care == self.bits
+    }
+
+    fn literals(&self) -> u32 {
+        self.care.count_ones()
+    }
+}
+
+// Quine-McCluskey over `vars` variables: all prime implicants, then the
+// essential ones, then greedily the prime covering most remaining minterms
+fn minimize_sop(on: u64, vars: usize) -> Vec<Cube> {
+    let full_care = ((1u16 << vars) - 1) as u8;
+    let minterms: Vec<u8> = (0..64u8).filter(|&m| on >> m & 1 == 1).collect();
+
+    let mut primes = BTreeSet::new();
+    let mut current: BTreeSet<Cube> = minterms.iter().map(|&m| Cube { care: full_care, bits: m }).collect();
+    while !current.is_empty() {
+        let mut next = BTreeSet::new();
+        let mut merged = HashSet::new();
+        let cubes: Vec<Cube> = current.iter().copied().collect();
+        for (a_idx, a) in cubes.iter().enumerate() {
+            for b in &cubes[a_idx + 1..] {
+                let diff = a.bits ^ b.bits;
+                if a.care == b.care && diff.count_ones() == 1 {
+                    next.insert(Cube { care: a.care & !diff, bits: a.bits & !diff });
+                    merged.insert(*a);
+                    merged.insert(*b);
+                }
+            }
+        }
+        primes.extend(cubes.into_iter().filter(|c| !merged.contains(c)));
+        current = next;
+    }
+
+    let mut uncovered: BTreeSet<u8> = minterms.into_iter().collect();
+    let mut cover = Vec::new();
+    for &m in &uncovered {
+        let mut covering = primes.iter().filter(|p| p.covers(m));
+        if let (Some(&only), None) = (covering.next(), covering.next()) {
+            if !cover.contains(&only) {
+                cover.push(only);
+            }
+        }
+    }
+    uncovered.retain(|&m| !cover.iter().any(|p| p.covers(m)));
+    while !uncovered.is_empty() {
+        let best = *primes.iter()
+            .max_by_key(|p| (uncovered.iter().filter(|&&m| p.covers(m)).count(), std::cmp::Reverse(p.literals())))
+            .expect("every minterm lies in a prime implicant");
+        cover.push(best);
+        uncovered.retain(|&m| !best.covers(m));
+    }
+    cover
+}
+
+// NOR/NOT network computing a cone, with its top signal
+struct Network {
+    gates: Vec<TableGate>,
+    top: i32,
+    next_temp: i32,
+}
+
+// Builds both f = OR(products of f) and f = NOR(products of NOT f) and keeps
+// the one with fewer gates; None if neither ends in a gate of its own
+fn synthesize(leaves: &[i32], on: u64, full: u64, next_temp: i32) -> Option<Network> {
+    let vars = leaves.len();
+    let candidates = [
+        (minimize_sop(on, vars), false),
+        (minimize_sop(!on & full, vars), true),
+    ];
+
+    candidates.into_iter()
+        .filter_map(|(cover, complemented)| {
+            let mut builder = Builder { leaves, gates: Vec::new(), hashed: HashMap::new(), next_temp };
+            let products: Vec<i32> = cover.iter().map(|c| builder.product(c)).collect();
+            let top = if complemented { builder.or_neg(&products) } else { builder.or_pos(&products) };
+            builder.gates.iter().any(|g| g.out == top).then_some(Network {
+                top,
+                next_temp: builder.next_temp,
+                gates: builder.gates,
+            })
+        })
+        .min_by_key(|n| n.gates.len())
+}
+
+struct Builder<'a> {
+    leaves: &'a [i32],
+    gates: Vec<TableGate>,
+    // Structural hashing: (inputs) -> output, NOT gates with one input
+    hashed: HashMap<Vec<i32>, i32>,
+    next_temp: i32,
+}
+
+impl Builder<'_> {
+    fn gate(&mut self, mut inputs: Vec<i32>) -> i32 {
+        inputs.sort_unstable();
+        if let Some(&out) = self.hashed.get(&inputs) {
+            return out;
+        }
+        let out = -self.next_temp;
+        self.next_temp += 1;
+        let mut gate = TableGate { out, ..Default::default() };
+        gate.set_inputs(&inputs);
+        self.gates.push(gate);
+        self.hashed.insert(inputs, out);
+        out
+    }
+
+    fn literal(&mut self, var: usize, positive: bool) -> i32 {
+        let leaf = self.leaves[var];
+        if positive { leaf } else { self.gate(vec![leaf]) }
+    }
+
+    // AND of literals is the NOR of their complements
+    fn product(&mut self, cube: &Cube) -> i32 {
+        let vars: Vec<usize> = (0..self.leaves.len()).filter(|&j| cube.care >> j & 1 == 1).collect();
+        if let [j] = vars[..] {
+            return self.literal(j, cube.bits >> j & 1 == 1);
+        }
+        let complements: Vec<i32> = vars.iter()
+            .map(|&j| self.literal(j, cube.bits >> j & 1 == 0))
+            .collect();
+        self.or_neg(&complements)
+    }
+
+    // NOT(s1 | s2 | ...), as a balanced tree of NORs
+    fn or_neg(&mut self, signals: &[i32]) -> i32 {
+        match signals {
+            [s] => self.gate(vec![*s]),
+            [a, b] => self.gate(vec![*a, *b]),
+            _ => {
+                let (left, right) = signals.split_at(signals.len() / 2);
+                let (left, right) = (self.or_pos(left), self.or_pos(right));
+                self.gate(vec![left, right])
+            }
+        }
+    }
+
+    fn or_pos(&mut self, signals: &[i32]) -> i32 {
+        match signals {
+            [s] => *s,
+            _ => {
+                let negated = self.or_neg(signals);
+                self.gate(vec![negated])
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//passes/mod.rs
+mod bind;
+mod cluster;
+mod commute;
+mod decompose;
+mod dont_care;
+mod duplicate;
+mod fanout;
+mod mincut;
+mod minimize;
+mod polarity;
+mod readout;
+mod xor;
+
+use std::fmt;
+use anyhow::{Result, Context, bail};
+use clap::ValueEnum;
+
+use crate::{signal_name, verify, Circuit};
+
+pub use self::bind::Bind;
+pub use self::cluster::Cluster;
+pub use self::commute::Commute;
+pub use self::decompose::Decompose;
+pub use self::dont_care::{DontCare, DontCareSet};
+pub use self::duplicate::Duplicate;
+pub use self::fanout::{FanoutProfile, FanoutTrees};
+pub use self::mincut::MinCut;
+pub use self::minimize::Minimize;
+pub use self::polarity::ActiveLowInputs;
+pub use self::readout::InvertedReadout;
+pub use self::xor::Xor;
+
+// A netlist-to-netlist transformation run after parsing and before scheduling.
+// Passes must keep the circuit's function and its primary inputs and outputs
+pub trait Pass {
+    fn name(&self) -> &'static str;
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport>;
+
+    // Whether the pass keeps the further signals of multi-output gates;
+    // passes that rebuild gates from `out` alone do not, and are not run on
+    // circuits that have any
+    fn keeps_extra_outs(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    pub pass: &'static str,
+    pub gates_before: usize,
+    pub gates_after: usize,
+    pub details: Vec<String>,
+    // Signals whose gates the pass removed, sorted by id
+    pub removed: Vec<i32>,
+}
+
+impl fmt::Display for PassReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {} gates", self.pass, self.gates_before, self.gates_after)?;
+        if !self.details.is_empty() {
+            write!(f, " ({})", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Optional passes selectable from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PassName {
+    /// Two-level (Quine-McCluskey) minimization of small fanout-free cones
+    Minimize,
+    /// Operand order of two-input gates chosen to save compact-mapping copies
+    Commute,
+    /// XORs written as five NOR/NOT gates rebuilt from the four-NOR template
+    Xor,
+}
+
+impl PassName {
+    pub fn create(self) -> Box<dyn Pass> {
+        match self {
+            PassName::Minimize => Box::new(Minimize::default()),
+            PassName::Commute => Box::new(Commute),
+            PassName::Xor => Box::new(Xor),
+        }
+    }
+}
+
+// Runs passes in the order they were added. The circuit's signal indices are
+// rebuilt after every pass, and with invariant checks enabled the netlist is
+// re-verified so a broken pass is caught where it ran
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_names(names: &[PassName]) -> Self {
+        let mut manager = Self::new();
+        for &name in names {
+            manager.add(name.create());
+        }
+        manager
+    }
+
+    pub fn add(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub fn run(&self, circuit: &mut Circuit) -> Result<Vec<PassReport>> {
+        let mut reports = Vec::with_capacity(self.passes.len());
+        for pass in &self.passes {
+            if !pass.keeps_extra_outs() {
+                if let Some(gate) = circuit.gates[..circuit.num_gates].iter().find(|g| g.is_multi_output()) {
+                    let outs: Vec<String> = gate.outs().map(signal_name).collect();
+                    bail!("Pass '{}' does not take multi-output gates, and {} drives {}",
+                        pass.name(), signal_name(gate.out), outs.join(" "));
+                }
+            }
+            let report = pass.run(circuit)
+                .with_context(|| format!("Pass '{}' failed", pass.name()))?;
+            circuit.num_gates = circuit.gates.len();
+            circuit.index_signals();
+            if verify::checks_enabled() {
+                verify::verify_netlist(circuit)
+                    .with_context(|| format!("Pass '{}' produced an invalid netlist", pass.name()))?;
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+}
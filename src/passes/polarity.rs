@@ -0,0 +1,106 @@
+//passes/polarity.rs
+use std::collections::HashMap;
+use anyhow::Result;
+
+use super::{Pass, PassReport};
+use crate::{Circuit, TableGate, MAX_GATES};
+
+// Loads primary inputs as their complement where that saves inverters, for
+// controllers that write operands active-low. The NOTs of such an input read
+// its cell directly; if any gate needs the true value, one NOT of the stored
+// value serves all of them, and the NOTs of its NOTs as well. An input is
+// complemented only when more NOTs go than that NOT costs. NOTs that are
+// outputs keep reading the true value. An input stays as it is where a
+// `@dont_touch` gate would be removed or rewired
+#[derive(Debug, Clone, Default)]
+pub struct ActiveLowInputs;
+
+impl Pass for ActiveLowInputs {
+    fn name(&self) -> &'static str {
+        "polarity"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let mut next_temp = circuit.gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1;
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        // Signal -> the signal its readers read instead
+        let mut rewired = HashMap::new();
+
+        for input in 0..circuit.num_inputs {
+            if circuit.complemented_inputs.contains(&input) {
+                continue;
+            }
+            let x = (MAX_GATES + input) as i32;
+            let (nots, direct): (Vec<usize>, Vec<usize>) = circuit.readers(x).iter()
+                .partition(|&&i| circuit.gates[i].fanin == 1 && !circuit.gates[i].is_output);
+            // NOTs of those NOTs compute x itself, as the true-value NOT does
+            let doubles: Vec<usize> = nots.iter()
+                .flat_map(|&i| circuit.readers(circuit.gates[i].out).iter().copied())
+                .filter(|&i| circuit.gates[i].fanin == 1 && !circuit.gates[i].is_output)
+                .collect();
+            // The NOTs go, and their readers and the direct readers are rewired
+            let touched = nots.iter().chain(&direct)
+                .chain(nots.iter().flat_map(|&i| circuit.readers(circuit.gates[i].out)));
+            if touched.copied().any(|i| circuit.gates[i].dont_touch()) {
+                continue;
+            }
+            let needs_true = !direct.is_empty() || !doubles.is_empty();
+            if nots.len() + doubles.len() <= usize::from(needs_true) {
+                continue;
+            }
+
+            circuit.complemented_inputs.insert(input);
+            for &i in &nots {
+                rewired.insert(circuit.gates[i].out, x);
+                removed.push(circuit.gates[i].out);
+            }
+            if needs_true {
+                let t = -next_temp;
+                next_temp += 1;
+                // It stands for the NOTs of NOTs, where there are any
+                let origin = doubles.first().and_then(|&i| circuit.gates[i].origin.clone());
+                let mut gate = TableGate { out: t, origin, ..Default::default() };
+                gate.set_inputs(&[x]);
+                added.push(gate);
+                for &i in &doubles {
+                    rewired.insert(circuit.gates[i].out, t);
+                    removed.push(circuit.gates[i].out);
+                }
+                for &i in &direct {
+                    let gate = &mut circuit.gates[i];
+                    for operand in gate.inputs[..gate.fanin].iter_mut().filter(|s| **s == x) {
+                        *operand = t;
+                    }
+                }
+            }
+        }
+
+        circuit.gates.retain(|g| !rewired.contains_key(&g.out));
+        for gate in &mut circuit.gates {
+            let fanin = gate.fanin;
+            for operand in &mut gate.inputs[..fanin] {
+                if let Some(&s) = rewired.get(operand) {
+                    *operand = s;
+                }
+            }
+        }
+        let inverters = added.len();
+        circuit.gates.splice(0..0, added);
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+        removed.sort_unstable();
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![
+                format!("{} active-low inputs", circuit.complemented_inputs.len()),
+                format!("{} inverters of stored inputs", inverters),
+            ],
+            removed,
+        })
+    }
+}
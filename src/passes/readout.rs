@@ -0,0 +1,46 @@
+//passes/readout.rs
+use anyhow::Result;
+
+use super::{Pass, PassReport};
+use crate::simulator::output_signals;
+use crate::Circuit;
+
+// Drops the NOT gates that only invert an output and reads the signal they
+// invert in their place, with the output's polarity flipped at read-out. Each
+// saves the NOT's cell and evaluation; an output another gate reads keeps
+// its NOT, and so does a `@dont_touch` NOT
+#[derive(Debug, Clone, Default)]
+pub struct InvertedReadout;
+
+impl Pass for InvertedReadout {
+    fn name(&self) -> &'static str {
+        "readout"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let mut removed = Vec::new();
+        for output in output_signals(circuit) {
+            let Some(gate) = circuit.driver(output) else {
+                continue;
+            };
+            if gate.fanin != 1 || !gate.is_output || gate.dont_touch() || !circuit.readers(output).is_empty() {
+                continue;
+            }
+            circuit.inverted_outputs.insert(output, gate.inputs[0]);
+            removed.push(output);
+        }
+
+        circuit.gates.retain(|g| !removed.contains(&g.out));
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![format!("{} outputs read out inverted", removed.len())],
+            removed,
+        })
+    }
+}
@@ -0,0 +1,262 @@
+//passes/xor.rs
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+
+use super::{Pass, PassReport};
+use crate::simulator::output_signals;
+use crate::{Circuit, TableGate};
+
+// Rebuilds XORs written as x = NOR(NOR(a, b), NOR(NOT a, NOT b)), the shape
+// a sum-of-products XOR lowers to, from the four-NOR template
+// XNOR(a, b) = NOR(NOR(a, t), NOR(b, t)) with t = NOR(a, b), which needs no
+// inverters. x is XNOR(a, NOT b) and its complement XNOR(a, b); the
+// template builds whichever reads fewer inverters, and the complement only
+// when an inverter of x can be replaced by it, as in XOR chains. Inner gates
+// and inverters are dropped once nothing else reads them, and an XOR is
+// rebuilt only if that saves gates. NAND netlists have the same shape, where
+// the template is an XOR. `@dont_touch` gates and the signals they read are
+// neither rebuilt nor dropped. The template's gates keep the XOR's origin
+#[derive(Debug, Clone, Default)]
+pub struct Xor;
+
+impl Pass for Xor {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn run(&self, circuit: &mut Circuit) -> Result<PassReport> {
+        let gates_before = circuit.num_gates;
+        let mut rewriter = Rewriter::new(circuit);
+        let mut rebuilt = 0;
+        for i in 0..rewriter.gates.len() {
+            if let Some((reads, complement)) = rewriter.candidate(i) {
+                rewriter.rebuild(i, reads, complement);
+                rebuilt += 1;
+            }
+        }
+
+        let mut removed: Vec<i32> = rewriter.removed.iter().copied().collect();
+        removed.sort_unstable();
+        circuit.gates = rewriter.finish();
+        circuit.num_gates = circuit.gates.len();
+        circuit.index_signals();
+
+        Ok(PassReport {
+            pass: self.name(),
+            gates_before,
+            gates_after: circuit.num_gates,
+            details: vec![format!("{} XORs rebuilt", rebuilt)],
+            removed,
+        })
+    }
+}
+
+// The netlist as XORs are rebuilt in gate order, with readers counted
+struct Rewriter {
+    gates: Vec<TableGate>,
+    outputs: HashSet<i32>,
+    drivers: HashMap<i32, usize>,
+    reads: HashMap<i32, usize>,
+    // Outputs and operands of `@dont_touch` gates
+    pinned: HashSet<i32>,
+    // Inverter -> the signal it inverts
+    inverted: HashMap<i32, i32>,
+    // Gates built ahead of each rebuilt XOR
+    built: HashMap<usize, Vec<TableGate>>,
+    // Dropped inverters -> the template output that replaces them
+    alias: HashMap<i32, i32>,
+    removed: HashSet<i32>,
+    next_temp: i32,
+}
+
+impl Rewriter {
+    fn new(circuit: &Circuit) -> Self {
+        let gates = circuit.gates[..circuit.num_gates].to_vec();
+        let mut reads: HashMap<i32, usize> = HashMap::new();
+        for gate in &gates {
+            for &input in &gate.inputs[..gate.fanin] {
+                *reads.entry(input).or_default() += 1;
+            }
+        }
+        let pinned = gates.iter()
+            .filter(|g| g.dont_touch())
+            .flat_map(|g| g.inputs[..g.fanin].iter().copied().chain([g.out]))
+            .collect();
+        Self {
+            outputs: output_signals(circuit).into_iter().collect(),
+            pinned,
+            drivers: gates.iter().enumerate().map(|(i, g)| (g.out, i)).collect(),
+            reads,
+            inverted: gates.iter().filter(|g| g.fanin == 1).map(|g| (g.out, g.inputs[0])).collect(),
+            built: HashMap::new(),
+            alias: HashMap::new(),
+            removed: HashSet::new(),
+            next_temp: gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1,
+            gates,
+        }
+    }
+
+    fn resolve(&self, s: i32) -> i32 {
+        self.alias.get(&s).copied().unwrap_or(s)
+    }
+
+    fn live(&self, s: i32) -> Option<&TableGate> {
+        self.drivers.get(&s).map(|&i| &self.gates[i]).filter(|g| !self.removed.contains(&g.out))
+    }
+
+    fn operands(&self, gate: &TableGate) -> [i32; 2] {
+        [self.resolve(gate.inputs[0]), self.resolve(gate.inputs[1])]
+    }
+
+    // A live gate's signal that no output or `@dont_touch` gate needs
+    fn removable(&self, s: i32) -> bool {
+        !self.outputs.contains(&s) && !self.pinned.contains(&s) && self.live(s).is_some()
+    }
+
+    // Read once, by the XOR being rebuilt
+    fn private(&self, s: i32) -> bool {
+        self.reads.get(&s) == Some(&1) && self.removable(s)
+    }
+
+    fn complements(&self, a: i32, b: i32) -> bool {
+        self.inverted.get(&a) == Some(&b) || self.inverted.get(&b) == Some(&a)
+    }
+
+    // The signals to build gate i's XOR from, and whether the template holds
+    // its complement, if rebuilding it saves gates
+    fn candidate(&self, i: usize) -> Option<([i32; 2], bool)> {
+        let gate = &self.gates[i];
+        if gate.fanin != 2 || gate.dont_touch() || self.removed.contains(&gate.out) {
+            return None;
+        }
+        let [p, q] = self.operands(gate);
+        let (pg, qg) = (self.live(p)?, self.live(q)?);
+        if p == q || pg.fanin != 2 || qg.fanin != 2 || !self.private(p) || !self.private(q) {
+            return None;
+        }
+        let u = self.operands(pg);
+        let mut w = self.operands(qg);
+        if !(self.complements(u[0], w[0]) && self.complements(u[1], w[1])) {
+            w.swap(0, 1);
+        }
+        if !(self.complements(u[0], w[0]) && self.complements(u[1], w[1])) || u[0] == u[1] {
+            return None;
+        }
+
+        // Inverters of x whose readers can read the complement instead
+        let inverters = self.inverters_of(gate.out).len();
+        let x_goes = inverters > 0 && self.reads.get(&gate.out) == Some(&inverters) && !self.outputs.contains(&gate.out)
+            && !self.pinned.contains(&gate.out);
+        // x = u0 ^ u1 = XNOR(u0, w1) = XNOR(w0, u1) = NOT XNOR(u0, u1) = NOT XNOR(w0, w1)
+        let options = [([u[0], w[1]], false), ([w[0], u[1]], false), ([u[0], u[1]], true), ([w[0], w[1]], true)];
+        options.into_iter()
+            .filter(|&(_, complement)| !complement || inverters > 0)
+            .map(|(reads, complement)| {
+                // Operands the template does not read go with the inner gates
+                let freed = u.iter().chain(&w)
+                    .filter(|s| !reads.contains(s) && self.inverted.contains_key(s) && self.private(**s))
+                    .count() as i64;
+                let saved = if complement {
+                    freed + inverters as i64 + x_goes as i64 - 2
+                } else {
+                    freed - 1
+                };
+                (saved, reads, complement)
+            })
+            .filter(|&(saved, _, _)| saved > 0)
+            .max_by_key(|&(saved, _, _)| saved)
+            .map(|(_, reads, complement)| (reads, complement))
+    }
+
+    fn inverters_of(&self, s: i32) -> Vec<i32> {
+        let mut inverters: Vec<i32> = self.inverted.iter()
+            .filter(|&(&n, &src)| src == s && self.removable(n))
+            .map(|(&n, _)| n)
+            .collect();
+        inverters.sort_unstable();
+        inverters
+    }
+
+    fn temp(&mut self) -> i32 {
+        self.next_temp += 1;
+        -(self.next_temp - 1)
+    }
+
+    fn add(&mut self, i: usize, out: i32, inputs: &[i32]) {
+        let mut gate = TableGate { out, origin: self.gates[i].origin.clone(), ..Default::default() };
+        gate.set_inputs(inputs);
+        for &input in inputs {
+            *self.reads.entry(input).or_default() += 1;
+        }
+        self.built.entry(i).or_default().push(gate);
+    }
+
+    fn drop_gate(&mut self, s: i32) {
+        let i = self.drivers[&s];
+        let inputs: Vec<i32> = self.gates[i].inputs[..self.gates[i].fanin].iter().map(|&s| self.resolve(s)).collect();
+        for input in inputs {
+            *self.reads.entry(input).or_default() -= 1;
+        }
+        self.inverted.remove(&s);
+        self.removed.insert(s);
+    }
+
+    fn rebuild(&mut self, i: usize, [a, b]: [i32; 2], complement: bool) {
+        let x = self.gates[i].out;
+        let [p, q] = self.operands(&self.gates[i]);
+        let operands: Vec<i32> = [p, q].iter().flat_map(|&s| self.operands(self.live(s).expect("inner gate"))).collect();
+        let (t, ta, tb) = (self.temp(), self.temp(), self.temp());
+        self.add(i, t, &[a, b]);
+        self.add(i, ta, &[a, t]);
+        self.add(i, tb, &[b, t]);
+        // x stops reading p and q, which only it read
+        for s in [p, q] {
+            self.reads.insert(s, 0);
+        }
+        self.drop_gate(p);
+        self.drop_gate(q);
+        for s in operands {
+            if self.inverted.contains_key(&s) && self.reads.get(&s) == Some(&0) && self.removable(s) {
+                self.drop_gate(s);
+            }
+        }
+
+        if !complement {
+            self.gates[i].set_inputs(&[ta, tb]);
+            *self.reads.entry(ta).or_default() += 1;
+            *self.reads.entry(tb).or_default() += 1;
+            return;
+        }
+        // The template's output y replaces the inverters of x, and x = NOT y
+        let y = self.temp();
+        self.add(i, y, &[ta, tb]);
+        for n in self.inverters_of(x) {
+            let readers = self.reads.remove(&n).unwrap_or(0);
+            *self.reads.entry(y).or_default() += readers;
+            self.drop_gate(n);
+            self.alias.insert(n, y);
+        }
+        self.gates[i].set_inputs(&[y]);
+        *self.reads.entry(y).or_default() += 1;
+        self.inverted.insert(x, y);
+        if self.reads.get(&x).copied().unwrap_or(0) == 0 && !self.outputs.contains(&x) && !self.pinned.contains(&x) {
+            self.drop_gate(x);
+        }
+    }
+
+    // Gates in their original order, each XOR's template ahead of it
+    fn finish(mut self) -> Vec<TableGate> {
+        let mut gates = Vec::with_capacity(self.gates.len());
+        for (i, gate) in std::mem::take(&mut self.gates).into_iter().enumerate() {
+            gates.extend(self.built.remove(&i).unwrap_or_default());
+            if self.removed.contains(&gate.out) {
+                continue;
+            }
+            let inputs: Vec<i32> = gate.inputs[..gate.fanin].iter().map(|&s| self.resolve(s)).collect();
+            let mut gate = gate;
+            gate.set_inputs(&inputs);
+            gates.push(gate);
+        }
+        gates
+    }
+}
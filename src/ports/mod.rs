@@ -0,0 +1,192 @@
+//ports/mod.rs
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, bail, ensure};
+
+use crate::parser::signals;
+use crate::{signal_name, Circuit, MAX_GATES};
+
+// A word-level input port: bits of an operand that a word-parallel write
+// driver loads together, so the mappers keep them on adjacent lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortGroup {
+    pub name: String,
+    // Bit range as written, e.g. (7, 0) for a[7:0]; None for a port written
+    // without one, which is a single bit unless it lists its inputs
+    pub range: Option<(usize, usize)>,
+    // Primary inputs by index, lowest bit first
+    pub inputs: Vec<usize>,
+}
+
+impl PortGroup {
+    pub fn width(&self) -> usize {
+        self.inputs.len()
+    }
+}
+
+// As in the grouping file, with the bits spelled out: a[3:0]=x4..x7
+impl fmt::Display for PortGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some((msb, lsb)) = self.range {
+            write!(f, "[{}:{}]", msb, lsb)?;
+        }
+        let name = |n: usize| signal_name((MAX_GATES + n) as i32);
+        // Runs of three or more consecutive inputs, rising or falling, as first..last
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for &input in &self.inputs {
+            match runs.last_mut() {
+                Some((first, last)) if input.abs_diff(*last) == 1 && (first == last || (input > *last) == (*last > *first)) => *last = input,
+                _ => runs.push((input, input)),
+            }
+        }
+        let runs: Vec<String> = runs.into_iter()
+            .map(|(first, last)| match first.abs_diff(last) {
+                0 => name(first),
+                1 => format!("{} {}", name(first), name(last)),
+                _ => format!("{}..{}", name(first), name(last)),
+            })
+            .collect();
+        write!(f, "={}", runs.join(" "))
+    }
+}
+
+// The ports of a grouping file, one per line:
+//   a[7:0]              the next 8 inputs after the previous group (x0 first)
+//   b[7:0] = x8..x15    the inputs listed, lowest bit first
+//   en = x20            a single bit
+//   flags = x21 x23     bits without a range
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortGroups {
+    pub groups: Vec<PortGroup>,
+}
+
+impl PortGroups {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = fs::read_to_string(path.as_ref())
+            .context(format!("Failed to read port grouping file: {:?}", path.as_ref()))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut groups: Vec<PortGroup> = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let next = groups.last().and_then(|g| g.inputs.iter().max()).map_or(0, |&i| i + 1);
+            let group = parse_group(line, next).with_context(|| format!("Port group on line {}", n + 1))?;
+            for other in &groups {
+                ensure!(other.name != group.name, "Port {} on line {} is grouped twice", group.name, n + 1);
+                if let Some(&shared) = group.inputs.iter().find(|i| other.inputs.contains(i)) {
+                    bail!("Input {} on line {} is already in port {}", signal_name((MAX_GATES + shared) as i32), n + 1, other.name);
+                }
+            }
+            groups.push(group);
+        }
+        Ok(Self { groups })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // Line of each input (its compact row, or naive column): the groups in
+    // file order, each from its lowest bit, then the ungrouped inputs in order
+    pub fn lines(&self, num_inputs: usize) -> Vec<usize> {
+        let grouped = self.groups.iter().flat_map(|g| g.inputs.iter().copied());
+        let rest = (0..num_inputs).filter(|i| !self.groups.iter().any(|g| g.inputs.contains(i)));
+        let mut lines = vec![0; num_inputs];
+        for (line, input) in grouped.chain(rest).enumerate() {
+            lines[input] = line;
+        }
+        lines
+    }
+
+    pub fn apply(&self, circuit: &mut Circuit) -> Result<()> {
+        for group in &self.groups {
+            if let Some(&input) = group.inputs.iter().find(|&&i| i >= circuit.num_inputs) {
+                bail!("Port {} groups {}, but the circuit has {} inputs",
+                    group.name, signal_name((MAX_GATES + input) as i32), circuit.num_inputs);
+            }
+        }
+        circuit.input_lines = self.lines(circuit.num_inputs);
+        circuit.input_groups = self.groups.clone();
+        Ok(())
+    }
+}
+
+// Groups as artifact headers report them
+pub fn describe(groups: &[PortGroup]) -> String {
+    let groups: Vec<String> = groups.iter().map(|g| g.to_string()).collect();
+    groups.join(" ")
+}
+
+// Primary inputs no gate reads, by index. Empty when no gate reads any, so a
+// mapping always keeps an input line
+pub fn unused_inputs(circuit: &Circuit) -> Vec<usize> {
+    let read: HashSet<usize> = circuit.gates.iter()
+        .take(circuit.num_gates)
+        .flat_map(|g| g.inputs[..g.fanin].iter().copied())
+        .filter(|&s| s >= MAX_GATES as i32)
+        .map(|s| (s - MAX_GATES as i32) as usize)
+        .collect();
+    if read.is_empty() {
+        return Vec::new();
+    }
+    (0..circuit.num_inputs).filter(|n| !read.contains(n)).collect()
+}
+
+// Takes the inputs off the crossbar: the others close up on the first lines
+// in the order they had, so grouped ports stay together
+pub fn drop_inputs(circuit: &mut Circuit, unused: &[usize]) {
+    circuit.unused_inputs.extend(unused.iter().copied());
+    let mut order: Vec<usize> = (0..circuit.num_inputs).collect();
+    order.sort_by_key(|&n| (circuit.unused_inputs.contains(&n), circuit.input_line(n)));
+    let mut lines = vec![0; circuit.num_inputs];
+    for (line, input) in order.into_iter().enumerate() {
+        lines[input] = line;
+    }
+    circuit.input_lines = lines;
+}
+
+// A group without a list of inputs takes the ones from `next` on
+fn parse_group(line: &str, next: usize) -> Result<PortGroup> {
+    let (port, bits) = match line.split_once('=') {
+        Some((port, bits)) => (port.trim(), Some(bits)),
+        None => (line, None),
+    };
+    let (name, range) = match port.split_once('[') {
+        Some((name, range)) => {
+            let range = range.strip_suffix(']').context(format!("'{}' has no closing ]", port))?;
+            let (msb, lsb) = range.split_once(':').unwrap_or((range, range));
+            let bit = |b: &str| b.trim().parse::<usize>().with_context(|| format!("'{}' is not a bit number", b.trim()));
+            (name.trim(), Some((bit(msb)?, bit(lsb)?)))
+        }
+        None => (port, None),
+    };
+    ensure!(!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'),
+        "'{}' is not a port name", name);
+    let width = range.map_or(1, |(msb, lsb)| msb.abs_diff(lsb) + 1);
+
+    let inputs = match bits {
+        None => (next..next + width).collect(),
+        Some(bits) => {
+            let mut inputs = Vec::new();
+            for signal in signals(bits)? {
+                ensure!(signal >= MAX_GATES as i32, "{} is not a primary input", signal_name(signal));
+                inputs.push(signal as usize - MAX_GATES);
+            }
+            ensure!(range.is_none() || inputs.len() == width,
+                "{} lists {} inputs for {} bits", port, inputs.len(), width);
+            let mut seen = inputs.clone();
+            seen.sort_unstable();
+            ensure!(seen.windows(2).all(|w| w[0] != w[1]), "{} lists an input twice", port);
+            inputs
+        }
+    };
+    Ok(PortGroup { name: name.to_string(), range, inputs })
+}
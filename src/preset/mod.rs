@@ -0,0 +1,117 @@
+//preset/mod.rs
+use std::fmt;
+use clap::ValueEnum;
+
+use crate::mapper::RowPolicy;
+use crate::passes::{MinCut, PassName};
+
+// Bundles of pipeline options for `--preset`, from the quickest run to the
+// smallest program. Options given on the command line win over the preset's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Netlist operand order, no optimization passes, parallel mapping of large circuits
+    Fast,
+    /// Operand order chosen after scheduling, XOR rebuilding and copy reuse
+    Balanced,
+    /// Every pass, duplication, clustering, min-cut placement with more refinement
+    /// passes, and copy reuse
+    Quality,
+}
+
+// What a preset selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    // Run before scheduling, in order
+    pub passes: Vec<PassName>,
+    // Whether `commute` picks the operand order after scheduling
+    pub commute: bool,
+    pub duplicate: bool,
+    pub cluster: Option<usize>,
+    pub placement: RowPolicy,
+    // Refinement passes of min-cut placement at most
+    pub mincut_passes: usize,
+    pub reuse_copies: bool,
+    pub parallel: bool,
+}
+
+impl Preset {
+    pub fn settings(self) -> Settings {
+        let mincut_passes = MinCut::default().passes;
+        match self {
+            Preset::Fast => Settings {
+                passes: Vec::new(),
+                commute: false,
+                duplicate: false,
+                cluster: None,
+                placement: RowPolicy::LastOperand,
+                mincut_passes,
+                reuse_copies: false,
+                parallel: true,
+            },
+            Preset::Balanced => Settings {
+                passes: vec![PassName::Xor],
+                commute: true,
+                duplicate: false,
+                cluster: None,
+                placement: RowPolicy::LastOperand,
+                mincut_passes,
+                reuse_copies: true,
+                parallel: false,
+            },
+            Preset::Quality => Settings {
+                passes: vec![PassName::Minimize, PassName::Xor],
+                commute: true,
+                duplicate: true,
+                cluster: Some(4),
+                placement: RowPolicy::Mincut,
+                mincut_passes: 2 * mincut_passes,
+                reuse_copies: true,
+                parallel: false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Preset::Fast => "fast",
+            Preset::Balanced => "balanced",
+            Preset::Quality => "quality",
+        };
+        f.pad(name)
+    }
+}
+
+// The options of a run, as `[PRESET]` lines and reports list them
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.passes.is_empty() {
+            parts.push("no passes".to_string());
+        } else {
+            let names: Vec<String> = self.passes.iter()
+                .filter_map(|p| p.to_possible_value().map(|v| v.get_name().to_string()))
+                .collect();
+            parts.push(format!("passes {}", names.join(" ")));
+        }
+        parts.push(if self.commute { "operand order chosen" } else { "netlist operand order" }.to_string());
+        if self.duplicate {
+            parts.push("duplication".to_string());
+        }
+        if let Some(size) = self.cluster {
+            parts.push(format!("clusters of {}", size));
+        }
+        parts.push(match self.placement {
+            RowPolicy::LastOperand => "last-operand placement".to_string(),
+            RowPolicy::Mincut => format!("min-cut placement ({} passes)", self.mincut_passes),
+        });
+        if self.reuse_copies {
+            parts.push("copy reuse".to_string());
+        }
+        if self.parallel {
+            parts.push("parallel".to_string());
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
@@ -0,0 +1,99 @@
+//presynth/mod.rs
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::parser::{self, Format};
+use crate::Circuit;
+
+pub const INPUT_PLACEHOLDER: &str = "{input}";
+pub const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+// Numbers the scratch directories of one process, which may run several
+// commands at once
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+// An external synthesizer run on each input before parsing, e.g.
+// `yosys -q -p 'read_verilog {input}; synth -top top; abc -g NOR; write_blif {output}'`.
+// The command runs through the shell with {input} replaced by the input file
+// and {output} by a scratch file named after the input, with the extension
+// of `format`. The scratch file is read like any netlist and then removed
+#[derive(Debug, Clone)]
+pub struct Presynth {
+    pub command: String,
+    pub format: Format,
+}
+
+// What a synthesizer run wrote, read back
+#[derive(Debug)]
+pub struct Synthesized {
+    pub circuits: Vec<Circuit>,
+    // The command as it ran, placeholders filled in
+    pub command: String,
+}
+
+impl Presynth {
+    pub fn new(command: &str, format: Format) -> Result<Self> {
+        ensure!(command.contains(OUTPUT_PLACEHOLDER),
+            "--presynth command '{}' never names its output; write the netlist to {}", command, OUTPUT_PLACEHOLDER);
+        Ok(Self { command: command.to_string(), format })
+    }
+
+    pub fn command_line(&self, input: &Path, output: &Path) -> String {
+        self.command
+            .replace(INPUT_PLACEHOLDER, &input.display().to_string())
+            .replace(OUTPUT_PLACEHOLDER, &output.display().to_string())
+    }
+
+    // Runs the command on `input` and loads the netlist it wrote, or only the
+    // module `top` of it
+    pub fn run(&self, input: &Path, top: Option<&str>) -> Result<Synthesized> {
+        ensure!(input.is_file(), "Input file not found: {:?}", input);
+        let run = RUNS.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("delphi-presynth-{}-{}", std::process::id(), run));
+        fs::create_dir_all(&dir).context(format!("Failed to create scratch directory: {:?}", dir))?;
+        let result = self.run_in(&dir, input, top);
+        fs::remove_dir_all(&dir).context(format!("Failed to remove scratch directory: {:?}", dir))?;
+        result
+    }
+
+    fn run_in(&self, dir: &Path, input: &Path, top: Option<&str>) -> Result<Synthesized> {
+        let output = scratch_path(dir, input, self.format);
+        let command = self.command_line(input, &output);
+        let run = shell(&command)
+            .output()
+            .context(format!("Failed to run --presynth command: {}", command))?;
+        if !run.status.success() {
+            let status = run.status.code().map_or("a signal".to_string(), |c| format!("status {}", c));
+            bail!("--presynth command failed with {}: {}\n{}{}", status, command,
+                String::from_utf8_lossy(&run.stderr).trim_end(), String::from_utf8_lossy(&run.stdout).trim_end());
+        }
+        ensure!(output.is_file(), "--presynth command wrote no netlist to {:?}: {}", output, command);
+
+        let circuits = parser::load_netlists_as(&output, self.format, top)
+            .context(format!("Failed to parse the {} netlist --presynth wrote", self.format))?;
+        Ok(Synthesized { circuits, command })
+    }
+}
+
+// Named after the input, so circuits and artifacts are too
+fn scratch_path(dir: &Path, input: &Path, format: Format) -> PathBuf {
+    dir.join(format!("{}.{}", parser::extract_bench_name(input), format.extension()))
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
@@ -0,0 +1,1063 @@
+//program/mod.rs
+mod python;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use anyhow::{Result, Context, bail, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::gates::GateKind;
+use crate::mapper::{CompactPlan, Placement};
+use crate::output;
+use crate::schema::{self, Versioned};
+use crate::simulator::output_signals;
+use crate::tiling::COPY_CYCLES;
+use crate::{invariant, signal_name, verify, Circuit, CrossbarMapping, MemristiveGate, Primitive, MAX_FANIN, MAX_GATES};
+
+// Machine-readable encodings of a program, next to the text listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgramFormat {
+    /// One JSON object with the steps and their ops
+    Json,
+    /// Compact little-endian records (see `Program::write_binary`)
+    Binary,
+    /// Standalone Python script replaying the program on a NumPy array
+    Python,
+}
+
+impl ProgramFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ProgramFormat::Json => "json",
+            ProgramFormat::Binary => "bin",
+            ProgramFormat::Python => "py",
+        }
+    }
+}
+
+// How a program holds the primary inputs in their cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Encoding {
+    /// Every input as its value
+    #[default]
+    ActiveHigh,
+    /// Inputs read mostly through inverters as their complement, saving the inverters
+    ActiveLow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Cell {
+    pub row: u32,
+    pub col: u32,
+}
+
+impl Cell {
+    pub(crate) fn of(gate: &MemristiveGate) -> Self {
+        Self { row: gate.idx.max(0) as u32, col: gate.jdx.max(0) as u32 }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.row, self.col)
+    }
+}
+
+// One operation of a crossbar program. Operands name the cells read, which
+// hold a primary input, a copy or an earlier gate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum MicroOp {
+    // Writes primary input `input` into its cell before the first level; a
+    // complemented load writes its complement, for active-low inputs
+    InputLoad {
+        input: usize,
+        cell: Cell,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        complemented: bool,
+    },
+    // A cell holding `signal` before the first level, a primary input or gate
+    // that an earlier program on the same input vector left there. No op
+    // writes it
+    Held { signal: i32, cell: Cell },
+    // A cell already at logic 1 before the first level, as an Init leaves it,
+    // for the gate evaluating into it
+    Preset { cell: Cell },
+    // Sets cells along one row or column to logic 1 ahead of an evaluation
+    Init { cells: Vec<Cell> },
+    // Duplicates a cell into the row of the gate reading it
+    Copy { from: Cell, cell: Cell },
+    // Gate of two or more inputs; NAND when the program's primitive is NAND
+    ExecNor { inputs: Vec<Cell>, cell: Cell },
+    ExecNot { input: Cell, cell: Cell },
+    // Senses an output signal once the last level has evaluated and stores
+    // it in output buffer `slot`. An inverted read stores the complement of
+    // the cell, for an output whose trailing NOT was dropped
+    Read {
+        signal: i32,
+        cell: Cell,
+        slot: usize,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        inverted: bool,
+    },
+}
+
+impl MicroOp {
+    fn opcode(&self) -> u8 {
+        match self {
+            MicroOp::InputLoad { complemented: false, .. } => 0,
+            MicroOp::InputLoad { complemented: true, .. } => 7,
+            MicroOp::Init { .. } => 1,
+            MicroOp::Copy { .. } => 2,
+            MicroOp::ExecNor { .. } => 3,
+            MicroOp::ExecNot { .. } => 4,
+            MicroOp::Read { inverted: false, .. } => 5,
+            MicroOp::Read { inverted: true, .. } => 6,
+            MicroOp::Held { .. } => 8,
+            MicroOp::Preset { .. } => 9,
+        }
+    }
+}
+
+// The ops of one level. Steps at level 0 move data in and out: the first
+// loads the inputs and the last, the read phase, reads the outputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub level: i32,
+    pub ops: Vec<MicroOp>,
+}
+
+// A crossbar program as every emitter writes it and the simulator runs it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Program {
+    pub primitive: Primitive,
+    pub num_inputs: usize,
+    pub rows: usize,
+    pub cols: usize,
+    pub steps: Vec<Step>,
+}
+
+// A slice of a program small enough for a controller's instruction memory,
+// with the state it resumes from and leaves to the next part
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    // Positions in `Program::ops`
+    pub ops: Range<usize>,
+    pub levels: RangeInclusive<i32>,
+    // Cells written by earlier parts that this part or a later one reads
+    pub resume: Vec<Cell>,
+    // Cells an earlier part initialized that this part evaluates
+    pub pending: Vec<Cell>,
+    // Cells the next part resumes from
+    pub checkpoint: Vec<Cell>,
+}
+
+// What the crossbar holds and does in one cycle of a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleUsage {
+    pub cycle: usize,
+    // 0 for the input write
+    pub level: i32,
+    // "input", "reset", "copy", "read", "write" or "evaluate"
+    pub phase: &'static str,
+    // Cells the cycle writes or senses
+    pub active: usize,
+    // Cells written or initialized so far
+    pub occupied: usize,
+    // Cells holding a value that is still to be read
+    pub live: usize,
+}
+
+// Copy overhead, in percent, past which a program is reported as spending
+// more cycles on copies than on its gates
+pub const HIGH_COPY_OVERHEAD: f64 = 100.0;
+
+// Cycles of a program, counted from its ops: one cycle writes the primary
+// inputs, then each level takes a cycle per Init to set its output cells to
+// logic 1, a read and an evaluation. A serialized program evaluates its
+// gates one at a time, so each gate rather than each level takes the read,
+// the write and the evaluation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramCycles {
+    pub levels: usize,
+    pub resets: usize,
+    // Copies each level writes ahead of its gates
+    pub copies: Vec<usize>,
+    // Gates of a serialized program
+    pub serialized: Option<usize>,
+    // Whether the reads and writes count the copies, each a read of its
+    // source and a write of its cell, as the crossbar performs them.
+    // Otherwise the copies are counted apart, in `copy_cycles`
+    pub folded: bool,
+}
+
+impl ProgramCycles {
+    // Evaluation steps: one per level, or one per gate when serialized
+    pub fn steps(&self) -> usize {
+        self.serialized.unwrap_or(self.levels)
+    }
+
+    pub fn reads(&self) -> usize {
+        self.steps() + self.folded_copies()
+    }
+
+    pub fn writes(&self) -> usize {
+        1 + self.resets + self.steps() + self.folded_copies()
+    }
+
+    pub fn evaluations(&self) -> usize {
+        self.steps()
+    }
+
+    pub fn total(&self) -> usize {
+        self.reads() + self.writes() + self.evaluations()
+    }
+
+    pub fn copy_cycles(&self) -> usize {
+        COPY_CYCLES * self.copies.iter().sum::<usize>()
+    }
+
+    // Cycles the copies add, in percent of the cycles of the gates
+    pub fn copy_overhead(&self) -> f64 {
+        let gates = self.total() - 2 * self.folded_copies();
+        100.0 * self.copy_cycles() as f64 / gates as f64
+    }
+
+    pub fn folding(self, folded: bool) -> Self {
+        Self { folded, ..self }
+    }
+
+    // Copies counted in the reads and writes
+    pub fn folded_copies(&self) -> usize {
+        if self.folded { self.copies.iter().sum() } else { 0 }
+    }
+}
+
+impl Program {
+    // The program a crossbar mapping runs, level by level in row-major order
+    pub fn from_mapping(circuit: &Circuit, mapping: &CrossbarMapping) -> Result<Self> {
+        let inputs = mapping.occupied_cells()
+            .filter(|c| (c.value >= MAX_GATES as i32 || c.held) && !c.is_copy)
+            .cloned()
+            .collect();
+        let levels = mapping.cells_by_level()
+            .filter(|&(l, _)| l > 0)
+            .map(|(l, cells)| {
+                for &(i, j) in &cells {
+                    check_operand_levels(mapping, i, j);
+                }
+                (l, cells.into_iter().map(|(i, j)| mapping.cell(i, j).clone()).collect())
+            });
+        Self::build(circuit, (mapping.max_idx, mapping.max_jdx), inputs, levels)
+    }
+
+    // The same program as the compact mapping's, built from a plan without
+    // materializing the crossbar
+    pub fn from_plan(circuit: &Circuit, plan: &CompactPlan, placement: Option<&Placement>) -> Result<Self> {
+        let place = |cell: MemristiveGate| match placement {
+            Some(placement) => placement.cell(&cell),
+            None => cell,
+        };
+        let (mut max_idx, mut max_jdx) = plan.dimensions();
+        if let Some(placement) = placement {
+            placement.check_fits(max_idx, max_jdx).context("Compact mapping does not fit the crossbar")?;
+            (max_idx, max_jdx) = (placement.row(max_idx), placement.col(max_jdx));
+        }
+        let inputs = circuit.mapped_inputs().map(|n| place(plan.input_cell(n))).collect();
+        let levels = plan.levels()
+            .filter(|&(l, _)| l > 0)
+            .map(|(l, gates)| (l, gates.into_iter().map(|i| place(plan.gate_cell(i))).collect()));
+        Self::build(circuit, (max_idx, max_jdx), inputs, levels)
+    }
+
+    fn build<I>(circuit: &Circuit, (max_idx, max_jdx): (i32, i32), inputs: Vec<MemristiveGate>, levels: I) -> Result<Self>
+    where
+        I: Iterator<Item = (i32, Vec<MemristiveGate>)>,
+    {
+        let mut computed: HashMap<i32, Cell> = HashMap::new();
+        let mut loads = Vec::with_capacity(inputs.len());
+        for cell in &inputs {
+            if cell.held {
+                loads.push(MicroOp::Held { signal: cell.value, cell: Cell::of(cell) });
+            } else {
+                let input = (cell.value - MAX_GATES as i32) as usize;
+                let complemented = circuit.complemented_inputs.contains(&input);
+                loads.push(MicroOp::InputLoad { input, cell: Cell::of(cell), complemented });
+            }
+            computed.insert(cell.value, Cell::of(cell));
+        }
+        let mut steps = vec![Step { level: 0, ops: loads }];
+        // Gate cells already at logic 1 skip their Init
+        let mut presets = Vec::new();
+
+        // A copy read by several gates is written for the first of them
+        let mut copied = HashSet::new();
+        for (level, cells) in levels {
+            let targets: Vec<Cell> = cells.iter().filter(|c| c.state != 1).map(Cell::of).collect();
+            presets.extend(cells.iter().filter(|c| c.state == 1).map(|c| MicroOp::Preset { cell: Cell::of(c) }));
+            let mut ops: Vec<MicroOp> = reset_groups(&targets).into_iter().map(|cells| MicroOp::Init { cells }).collect();
+            let mut execs = Vec::with_capacity(cells.len());
+            for cell in &cells {
+                let operand = |k: usize| cell.inputs[k].as_deref().with_context(|| {
+                    format!("Cell {} ({}) has unconnected input {}", Cell::of(cell), signal_name(cell.value), k)
+                });
+                let kind = GateKind::of(circuit.primitive, cell.fanin);
+                ensure!(kind.spec().fanin.contains(&cell.fanin),
+                    "Cell {} ({}) has unsupported fanin {}", Cell::of(cell), signal_name(cell.value), cell.fanin);
+                let operands = (0..cell.fanin).map(operand).collect::<Result<Vec<_>>>()?;
+                for copy in operands.iter().filter(|c| c.is_copy) {
+                    if copied.insert(Cell::of(copy)) {
+                        let from = copy.inputs[0].as_deref()
+                            .with_context(|| format!("Copy cell {} has no source", Cell::of(copy)))?;
+                        ops.push(MicroOp::Copy { from: Cell::of(from), cell: Cell::of(copy) });
+                    }
+                }
+                let inputs: Vec<Cell> = operands.iter().map(|c| Cell::of(c)).collect();
+                execs.push(kind.emit(&inputs, Cell::of(cell)));
+                computed.insert(cell.value, Cell::of(cell));
+            }
+            ops.extend(execs);
+            steps.push(Step { level, ops });
+        }
+        steps[0].ops.extend(presets);
+
+        let mut reads = Vec::new();
+        for (slot, signal) in output_signals(circuit).into_iter().enumerate() {
+            let source = circuit.inverted_outputs.get(&signal);
+            let cell = *computed.get(&circuit.cell_signal(*source.unwrap_or(&signal)))
+                .with_context(|| format!("Output {} is not mapped", signal_name(signal)))?;
+            reads.push(MicroOp::Read { signal, cell, slot, inverted: source.is_some() });
+        }
+        steps.push(Step { level: 0, ops: reads });
+
+        Ok(Self {
+            primitive: circuit.primitive,
+            num_inputs: circuit.num_inputs,
+            rows: max_idx.max(0) as usize + 1,
+            cols: max_jdx.max(0) as usize + 1,
+            steps,
+        })
+    }
+
+    // Steps that evaluate gates, i.e. all but the input loads and the read phase
+    pub fn levels(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter().filter(|s| s.level > 0)
+    }
+
+    // The output reads in buffer order
+    pub fn outputs(&self) -> impl Iterator<Item = (usize, i32, Cell)> + '_ {
+        let mut reads: Vec<(usize, i32, Cell)> = self.ops()
+            .filter_map(|(_, op)| match op {
+                MicroOp::Read { signal, cell, slot, .. } => Some((*slot, *signal, *cell)),
+                _ => None,
+            })
+            .collect();
+        reads.sort_unstable_by_key(|&(slot, _, _)| slot);
+        reads.into_iter()
+    }
+
+    // Primary inputs the program loads; unused inputs may have no cell
+    pub fn loaded_inputs(&self) -> usize {
+        self.ops().filter(|(_, op)| matches!(op, MicroOp::InputLoad { .. })).count()
+    }
+
+    // Primary inputs loaded as their complement
+    pub fn complemented_inputs(&self) -> Vec<usize> {
+        let mut inputs: Vec<usize> = self.ops()
+            .filter_map(|(_, op)| match op {
+                MicroOp::InputLoad { input, complemented: true, .. } => Some(*input),
+                _ => None,
+            })
+            .collect();
+        inputs.sort_unstable();
+        inputs
+    }
+
+    // Buffer slots whose reads store the complement of their cell
+    pub fn inverted_slots(&self) -> Vec<usize> {
+        let mut slots: Vec<usize> = self.ops()
+            .filter_map(|(_, op)| match op {
+                MicroOp::Read { slot, inverted: true, .. } => Some(*slot),
+                _ => None,
+            })
+            .collect();
+        slots.sort_unstable();
+        slots
+    }
+
+    // Drops the read phase, for programs that hand their outputs on instead
+    pub fn without_read_phase(mut self) -> Self {
+        if self.steps.len() > 1 && self.steps.last().is_some_and(|s| s.level == 0) {
+            self.steps.pop();
+        }
+        self
+    }
+
+    pub fn cycles(&self) -> ProgramCycles {
+        let count = |step: &Step, f: fn(&MicroOp) -> bool| step.ops.iter().filter(|&op| f(op)).count();
+        let mut cycles = ProgramCycles::default();
+        for step in self.levels() {
+            cycles.levels += 1;
+            cycles.resets += count(step, |op| matches!(op, MicroOp::Init { .. }));
+            cycles.copies.push(count(step, |op| matches!(op, MicroOp::Copy { .. })));
+        }
+        cycles
+    }
+
+    // Cycles of the program run one gate at a time, as the naive mapping's
+    // must be: its gates all share row 0, so no two evaluate at once
+    pub fn serialized_cycles(&self) -> ProgramCycles {
+        let gates = self.levels()
+            .flat_map(|s| &s.ops)
+            .filter(|op| matches!(op, MicroOp::ExecNor { .. } | MicroOp::ExecNot { .. }))
+            .count();
+        ProgramCycles { serialized: Some(gates), ..self.cycles() }
+    }
+
+    // Cells the program writes: its inputs, copies and gate outputs
+    pub fn cells(&self) -> HashSet<Cell> {
+        self.ops()
+            .filter_map(|(_, op)| match op {
+                MicroOp::InputLoad { cell, .. } | MicroOp::Copy { cell, .. }
+                    | MicroOp::ExecNor { cell, .. } | MicroOp::ExecNot { cell, .. } => Some(*cell),
+                MicroOp::Held { .. } | MicroOp::Preset { .. } | MicroOp::Init { .. } | MicroOp::Read { .. } => None,
+            })
+            .collect()
+    }
+
+    // The listing in the C implementation's micro-op format: per level its
+    // RESET lines and gate evaluations, then a READ line per output with its
+    // buffer slot. Input loads and copies stay implicit: an operand read from
+    // a copy is named after the copy's source. Levels are numbered from
+    // `first_level`
+    pub fn write_text<W: Write>(&self, file: &mut W, first_level: i32) -> Result<()> {
+        TextListing::new(self, first_level).write_until(file, usize::MAX)
+    }
+
+    // Every op with its level, in program order
+    pub fn ops(&self) -> impl Iterator<Item = (i32, &MicroOp)> {
+        self.steps.iter().flat_map(|s| s.ops.iter().map(move |op| (s.level, op)))
+    }
+
+    // Splits the program into parts of at most `max_ops` ops, cutting at the
+    // last level boundary that fits and inside a level only when one level
+    // alone exceeds the budget
+    pub fn partition(&self, max_ops: usize) -> Result<Vec<Partition>> {
+        ensure!(max_ops > 0, "A part needs room for at least one micro-op");
+        let total: usize = self.steps.iter().map(|s| s.ops.len()).sum();
+        let mut boundaries = Vec::with_capacity(self.steps.len());
+        let mut at = 0;
+        for step in &self.steps {
+            at += step.ops.len();
+            boundaries.push(at);
+        }
+
+        let mut cuts = vec![0];
+        while let Some(&start) = cuts.last().filter(|&&s| s < total) {
+            let limit = (start + max_ops).min(total);
+            let fitting = boundaries.partition_point(|&b| b <= limit);
+            let level_end = boundaries[..fitting].last().filter(|&&b| b > start);
+            cuts.push(level_end.copied().unwrap_or(limit));
+        }
+
+        // Each value lives from its write to its last read, and each
+        // initialized cell from its Init to its evaluation. The listing names
+        // an operand read from a copy after the copy's source, so that read
+        // keeps the source alive too
+        let mut written: HashMap<Cell, usize> = HashMap::new();
+        let mut initialized: HashMap<Cell, usize> = HashMap::new();
+        let mut origin: HashMap<Cell, Cell> = HashMap::new();
+        // Last read of each written value, keyed by cell and write
+        let mut last_read: HashMap<(Cell, usize), usize> = HashMap::new();
+        let mut pending: Vec<(Cell, usize, usize)> = Vec::new();
+        for (i, (_, op)) in self.ops().enumerate() {
+            let mut reads: Vec<Cell> = Vec::new();
+            let mut target = None;
+            match op {
+                MicroOp::InputLoad { cell, .. } | MicroOp::Held { cell, .. } => target = Some(*cell),
+                MicroOp::Preset { cell } => {
+                    initialized.insert(*cell, i);
+                }
+                MicroOp::Init { cells } => {
+                    for &cell in cells {
+                        initialized.insert(cell, i);
+                    }
+                }
+                MicroOp::Copy { from, cell } => {
+                    reads.push(*from);
+                    let source = origin.get(from).copied().unwrap_or(*from);
+                    origin.insert(*cell, source);
+                    target = Some(*cell);
+                }
+                MicroOp::ExecNor { inputs, cell } => {
+                    reads.extend(inputs.iter().flat_map(|c| [Some(*c), origin.get(c).copied()]).flatten());
+                    target = Some(*cell);
+                }
+                MicroOp::ExecNot { input, cell } => {
+                    reads.extend([Some(*input), origin.get(input).copied()].into_iter().flatten());
+                    target = Some(*cell);
+                }
+                MicroOp::Read { cell, .. } => reads.push(*cell),
+            }
+            for cell in reads {
+                if let Some(&w) = written.get(&cell) {
+                    last_read.insert((cell, w), i);
+                }
+            }
+            if let Some(cell) = target {
+                if let Some(init) = initialized.remove(&cell) {
+                    pending.push((cell, init, i));
+                }
+                written.insert(cell, i);
+            }
+        }
+        // Cells held across each cut: a span from `from` to `to` crosses the
+        // cuts `b` with from < b <= to
+        let across = |spans: &[(Cell, usize, usize)]| -> Vec<Vec<Cell>> {
+            let mut held = vec![Vec::new(); cuts.len()];
+            for &(cell, from, to) in spans {
+                let first = cuts.partition_point(|&b| b <= from);
+                for cells in &mut held[first..cuts.partition_point(|&b| b <= to)] {
+                    cells.push(cell);
+                }
+            }
+            for cells in &mut held {
+                cells.sort_unstable();
+                cells.dedup();
+            }
+            held
+        };
+        let live: Vec<(Cell, usize, usize)> = last_read.into_iter().map(|((cell, w), r)| (cell, w, r)).collect();
+        let (live, pending) = (across(&live), across(&pending));
+
+        // The read phase counts as part of the last level
+        let mut levels = Vec::with_capacity(total);
+        for step in &self.steps {
+            let level = levels.last().map_or(step.level, |&l: &i32| l.max(step.level));
+            levels.extend(std::iter::repeat_n(level, step.ops.len()));
+        }
+        Ok(cuts.windows(2)
+            .enumerate()
+            .map(|(k, w)| Partition {
+                ops: w[0]..w[1],
+                levels: levels[w[0]]..=levels[w[1] - 1],
+                resume: live[k].clone(),
+                pending: pending[k].clone(),
+                checkpoint: live[k + 1].clone(),
+            })
+            .collect())
+    }
+
+    // The program cycle by cycle, as `cycles` counts them plus two cycles per
+    // copy: the input write, then per level its resets, copies, a read, an
+    // evaluation write and an evaluation, which senses the operands and
+    // writes the results. A value is live from the cycle writing it to the
+    // last cycle reading it; outputs stay live to the end
+    pub fn timeline(&self) -> Vec<CycleUsage> {
+        let mut cycles: Vec<(i32, &'static str, usize)> = Vec::new();
+        // Cycle each value is written at, and the last cycle reading it; a
+        // cell written again closes the span of its old value
+        let mut spans: HashMap<Cell, (usize, Option<usize>)> = HashMap::new();
+        let mut closed: Vec<(usize, Option<usize>)> = Vec::new();
+        let mut touched: HashMap<Cell, usize> = HashMap::new();
+        let mut outputs = Vec::new();
+        let read = |spans: &mut HashMap<Cell, (usize, Option<usize>)>, cell: &Cell, at: usize| {
+            if let Some(span) = spans.get_mut(cell) {
+                span.1 = Some(span.1.map_or(at, |to| to.max(at)));
+            }
+        };
+
+        for step in &self.steps {
+            let execs: Vec<(&[Cell], Cell)> = step.ops.iter()
+                .filter_map(|op| match op {
+                    MicroOp::ExecNor { inputs, cell } => Some((&inputs[..], *cell)),
+                    MicroOp::ExecNot { input, cell } => Some((std::slice::from_ref(input), *cell)),
+                    _ => None,
+                })
+                .collect();
+            for op in &step.ops {
+                match op {
+                    MicroOp::InputLoad { cell, .. } => {
+                        if cycles.is_empty() {
+                            cycles.push((0, "input", 0));
+                        }
+                        cycles[0].2 += 1;
+                        closed.extend(spans.insert(*cell, (0, None)));
+                        touched.entry(*cell).or_insert(0);
+                    }
+                    // Held and preset cells take no cycle
+                    MicroOp::Held { cell, .. } => {
+                        closed.extend(spans.insert(*cell, (0, None)));
+                        touched.entry(*cell).or_insert(0);
+                    }
+                    MicroOp::Preset { cell } => {
+                        touched.entry(*cell).or_insert(0);
+                    }
+                    MicroOp::Init { cells } => {
+                        let at = cycles.len();
+                        cycles.push((step.level, "reset", cells.len()));
+                        for cell in cells {
+                            touched.entry(*cell).or_insert(at);
+                        }
+                    }
+                    MicroOp::Copy { from, cell } => {
+                        let at = cycles.len();
+                        cycles.push((step.level, "copy", 1));
+                        cycles.push((step.level, "copy", 1));
+                        read(&mut spans, from, at);
+                        closed.extend(spans.insert(*cell, (at + 1, None)));
+                        touched.entry(*cell).or_insert(at + 1);
+                    }
+                    MicroOp::Read { cell, .. } => outputs.push(*cell),
+                    MicroOp::ExecNor { .. } | MicroOp::ExecNot { .. } => {}
+                }
+            }
+            if !execs.is_empty() {
+                let at = cycles.len();
+                for phase in ["read", "write", "evaluate"] {
+                    cycles.push((step.level, phase, execs.len()));
+                }
+                for (inputs, _) in &execs {
+                    for input in *inputs {
+                        read(&mut spans, input, at + 2);
+                    }
+                }
+                for (_, cell) in &execs {
+                    closed.extend(spans.insert(*cell, (at + 2, None)));
+                    touched.entry(*cell).or_insert(at + 2);
+                }
+            }
+        }
+        let last = cycles.len().saturating_sub(1);
+        for cell in &outputs {
+            read(&mut spans, cell, last);
+        }
+
+        // Counts over the cycles by adding each span at its start and
+        // removing it after its end
+        let mut live = vec![0i64; cycles.len() + 1];
+        for &(from, to) in spans.values().chain(&closed) {
+            if let Some(to) = to {
+                live[from] += 1;
+                live[to + 1] -= 1;
+            }
+        }
+        let mut occupied = vec![0usize; cycles.len() + 1];
+        for &at in touched.values() {
+            occupied[at] += 1;
+        }
+        let (mut held, mut used) = (0i64, 0usize);
+        cycles.into_iter()
+            .enumerate()
+            .map(|(cycle, (level, phase, active))| {
+                held += live[cycle];
+                used += occupied[cycle];
+                CycleUsage { cycle, level, phase, active, occupied: used, live: held as usize }
+            })
+            .collect()
+    }
+
+    pub fn write_json<W: Write>(&self, file: &mut W) -> Result<()> {
+        serde_json::to_writer(&mut *file, &Versioned::new(self)).context("Failed to write JSON program")?;
+        writeln!(file)?;
+        Ok(())
+    }
+
+    pub fn read_json(text: &str) -> Result<Self> {
+        schema::from_str(text, "program")
+    }
+
+    // A program written with `--emit`, in the format its extension names
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).context(format!("Failed to read program: {:?}", path))?;
+        let program = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::read_json(&String::from_utf8_lossy(&bytes)),
+            Some("bin") => Self::read_binary(&bytes),
+            _ => bail!("{:?} is neither a .json nor a .bin program", path),
+        };
+        program.context(format!("Failed to load program: {:?}", path))
+    }
+
+    // Little-endian records: the header, then per step its level and op
+    // count, and per op its opcode and fields
+    pub fn write_binary<W: Write>(&self, file: &mut W) -> Result<()> {
+        file.write_all(BINARY_MAGIC)?;
+        file.write_all(&[BINARY_VERSION, self.primitive as u8])?;
+        for n in [self.num_inputs, self.rows, self.cols, self.steps.len()] {
+            file.write_all(&(n as u32).to_le_bytes())?;
+        }
+        for step in &self.steps {
+            file.write_all(&step.level.to_le_bytes())?;
+            file.write_all(&(step.ops.len() as u32).to_le_bytes())?;
+            for op in &step.ops {
+                file.write_all(&[op.opcode()])?;
+                let mut fields: Vec<u32> = Vec::new();
+                match op {
+                    MicroOp::InputLoad { input, cell, .. } => fields.extend([*input as u32, cell.row, cell.col]),
+                    MicroOp::Init { cells } => {
+                        fields.push(cells.len() as u32);
+                        fields.extend(cells.iter().flat_map(|c| [c.row, c.col]));
+                    }
+                    MicroOp::Copy { from, cell } => fields.extend([from.row, from.col, cell.row, cell.col]),
+                    MicroOp::ExecNor { inputs, cell } => {
+                        fields.push(inputs.len() as u32);
+                        fields.extend(inputs.iter().chain([cell]).flat_map(|c| [c.row, c.col]));
+                    }
+                    MicroOp::ExecNot { input, cell } => fields.extend([input.row, input.col, cell.row, cell.col]),
+                    MicroOp::Read { signal, cell, slot, .. } => fields.extend([*signal as u32, cell.row, cell.col, *slot as u32]),
+                    MicroOp::Held { signal, cell } => fields.extend([*signal as u32, cell.row, cell.col]),
+                    MicroOp::Preset { cell } => fields.extend([cell.row, cell.col]),
+                }
+                for field in fields {
+                    file.write_all(&field.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_binary(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.starts_with(BINARY_MAGIC), "Not a binary micro-op program");
+        let mut reader = Reader { bytes, at: BINARY_MAGIC.len() };
+        let version = reader.byte()?;
+        ensure!(version == BINARY_VERSION, "Unsupported binary program version {}", version);
+        let primitive = match reader.byte()? {
+            0 => Primitive::Nor,
+            1 => Primitive::Nand,
+            other => bail!("Unknown primitive {} in binary program", other),
+        };
+        let (num_inputs, rows, cols) = (reader.word()? as usize, reader.word()? as usize, reader.word()? as usize);
+        let mut steps = Vec::new();
+        for _ in 0..reader.word()? {
+            let level = reader.word()? as i32;
+            let mut ops = Vec::new();
+            for _ in 0..reader.word()? {
+                ops.push(match reader.byte()? {
+                    opcode @ (0 | 7) => MicroOp::InputLoad {
+                        input: reader.word()? as usize,
+                        cell: reader.cell()?,
+                        complemented: opcode == 7,
+                    },
+                    1 => {
+                        let count = reader.word()?;
+                        MicroOp::Init { cells: (0..count).map(|_| reader.cell()).collect::<Result<_>>()? }
+                    }
+                    2 => MicroOp::Copy { from: reader.cell()?, cell: reader.cell()? },
+                    3 => {
+                        let count = reader.word()? as usize;
+                        ensure!((2..=MAX_FANIN).contains(&count), "Gate of {} inputs at byte {}", count, reader.at - 4);
+                        MicroOp::ExecNor { inputs: (0..count).map(|_| reader.cell()).collect::<Result<_>>()?, cell: reader.cell()? }
+                    }
+                    4 => MicroOp::ExecNot { input: reader.cell()?, cell: reader.cell()? },
+                    opcode @ (5 | 6) => MicroOp::Read {
+                        signal: reader.word()? as i32,
+                        cell: reader.cell()?,
+                        slot: reader.word()? as usize,
+                        inverted: opcode == 6,
+                    },
+                    8 => MicroOp::Held { signal: reader.word()? as i32, cell: reader.cell()? },
+                    9 => MicroOp::Preset { cell: reader.cell()? },
+                    other => bail!("Unknown opcode {} at byte {}", other, reader.at - 1),
+                });
+            }
+            steps.push(Step { level, ops });
+        }
+        ensure!(reader.at == bytes.len(), "Trailing bytes after the binary program");
+        Ok(Self { primitive, num_inputs, rows, cols, steps })
+    }
+}
+
+pub(crate) const BINARY_MAGIC: &[u8] = b"DLPH";
+const BINARY_VERSION: u8 = 3;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let chunk = self.bytes.get(self.at..self.at + N).context("Truncated binary program")?;
+        self.at += N;
+        Ok(chunk.try_into()?)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn word(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn cell(&mut self) -> Result<Cell> {
+        Ok(Cell { row: self.word()?, col: self.word()? })
+    }
+}
+
+// Writes a program's text listing in consecutive slices of its ops, as the
+// parts of a partitioned program list them. Operands keep the names earlier
+// ops gave them
+pub struct TextListing<'a> {
+    program: &'a Program,
+    first_level: i32,
+    names: HashMap<Cell, String>,
+    // Signal each cell holds, for listings annotated with a legend and a
+    // comment per op
+    signals: Option<&'a HashMap<Cell, i32>>,
+    // The C implementation's layout: no RESET lines, no read phase, and the
+    // last level left out, as that implementation never listed it
+    legacy: bool,
+    // Next step and op to write, and its position in `Program::ops`
+    step: usize,
+    op: usize,
+    at: usize,
+}
+
+impl<'a> TextListing<'a> {
+    pub fn new(program: &'a Program, first_level: i32) -> Self {
+        Self { program, first_level, names: HashMap::new(), signals: None, legacy: false, step: 0, op: 0, at: 0 }
+    }
+
+    pub fn legacy(mut self) -> Self {
+        self.legacy = true;
+        self
+    }
+
+    pub fn annotated(mut self, signals: &'a HashMap<Cell, i32>) -> Self {
+        self.signals = Some(signals);
+        self
+    }
+
+    // Symbolic name of the signal in a cell, or the cell if it is not known
+    fn signal(&self, cell: &Cell) -> String {
+        self.signals.and_then(|s| s.get(cell)).map_or_else(|| cell.to_string(), |&s| signal_name(s))
+    }
+
+    // "  # n10 = NOR(x2, n5)"
+    fn exec_comment(&self, cell: &Cell, operands: &[Cell]) -> String {
+        if self.signals.is_none() {
+            return String::new();
+        }
+        let gate = GateKind::of(self.program.primitive, operands.len());
+        let operands: Vec<String> = operands.iter().map(|c| self.signal(c)).collect();
+        format!("  # {} = {}({})", self.signal(cell), gate, operands.join(", "))
+    }
+
+    // Writes the ops up to position `end`, with a level header for each
+    // level the slice enters
+    pub fn write_until<W: Write>(&mut self, file: &mut W, end: usize) -> Result<()> {
+        if self.signals.is_some() && self.at == 0 && end > 0 {
+            file.write_all(LEGEND.as_bytes())?;
+        }
+        let mut entered = false;
+        let last = self.program.levels().last().map(|s| s.level);
+        while let Some(step) = self.program.steps.get(self.step) {
+            if self.at >= end || (self.legacy && self.step > 0 && (step.level == 0 || Some(step.level) == last)) {
+                break;
+            }
+            let Some(op) = step.ops.get(self.op) else {
+                self.step += 1;
+                self.op = 0;
+                continue;
+            };
+            if self.op == 0 || !entered {
+                if step.level > 0 {
+                    // The C implementation's listings number levels from 1
+                    let level = step.level + self.first_level - 1;
+                    let level = if self.legacy { level } else { output::level_number(level) };
+                    writeln!(file, "# Level: {:2} _____________________________________", level)?;
+                } else if self.step > 0 {
+                    writeln!(file, "# Read phase _____________________________________")?;
+                }
+            }
+            match op {
+                MicroOp::InputLoad { input, cell, .. } => {
+                    self.names.insert(*cell, format!("/{}", input));
+                }
+                MicroOp::Held { .. } | MicroOp::Preset { .. } | MicroOp::Init { .. } if self.legacy => {}
+                MicroOp::Held { cell, .. } => {
+                    let comment = match self.signals {
+                        Some(_) => format!("  # held {}", self.signal(cell)),
+                        None => String::new(),
+                    };
+                    writeln!(file, "RESUME {}{}", cell, comment)?;
+                }
+                MicroOp::Preset { cell } => writeln!(file, "PENDING {}", cell)?,
+                MicroOp::Init { cells } => {
+                    let names: Vec<String> = cells.iter().map(|c| c.to_string()).collect();
+                    let comment = match self.signals {
+                        Some(_) => format!("  # init {}", cells.iter().map(|c| self.signal(c)).collect::<Vec<_>>().join(" ")),
+                        None => String::new(),
+                    };
+                    writeln!(file, "RESET {}{}", names.join(" "), comment)?;
+                }
+                MicroOp::Copy { from, cell } => {
+                    let name = self.names.get(from).cloned().unwrap_or_else(|| from.to_string());
+                    if !self.legacy {
+                        let comment = match self.signals {
+                            Some(_) => format!("  # copy {}", self.signal(from)),
+                            None => String::new(),
+                        };
+                        writeln!(file, "COPY {:>9} {:>9}{}", name, cell.to_string(), comment)?;
+                    }
+                    self.names.insert(*cell, name);
+                }
+                MicroOp::ExecNor { inputs, cell } => {
+                    write_exec(file, *cell, inputs, &self.names)?;
+                    writeln!(file, "{}", self.exec_comment(cell, inputs))?;
+                    self.names.insert(*cell, cell.to_string());
+                }
+                MicroOp::ExecNot { input, cell } => {
+                    write_exec(file, *cell, &[*input], &self.names)?;
+                    writeln!(file, "{}", self.exec_comment(cell, std::slice::from_ref(input)))?;
+                    self.names.insert(*cell, cell.to_string());
+                }
+                MicroOp::Read { signal, cell, slot, inverted } => {
+                    let polarity = if *inverted { " inverted" } else { "" };
+                    writeln!(file, "READ {:>9} {:4} {}{}", cell.to_string(), slot, signal_name(*signal), polarity)?;
+                }
+            }
+            self.op += 1;
+            self.at += 1;
+            entered = true;
+        }
+        Ok(())
+    }
+}
+
+// One Init sets cells along a single row or column; the cells are split by
+// whichever of the two needs fewer
+pub fn reset_groups(cells: &[Cell]) -> Vec<Vec<Cell>> {
+    let group = |key: fn(&Cell) -> u32| {
+        let mut groups: BTreeMap<u32, Vec<Cell>> = BTreeMap::new();
+        for cell in cells {
+            groups.entry(key(cell)).or_default().push(*cell);
+        }
+        groups.into_values().collect::<Vec<_>>()
+    };
+    let (by_col, by_row) = (group(|c| c.col), group(|c| c.row));
+    if by_row.len() < by_col.len() { by_row } else { by_col }
+}
+
+// Opens an annotated listing
+const LEGEND: &str = "\
+# Legend _____________________________________
+# RESUME <cell>          a cell holding its value before level 1
+# PENDING <cell>         a cell already at logic 1, which needs no RESET
+# RESET <cells>          sets the cells a level writes to logic 1
+# COPY <source> <cell>   reads <source> and writes it into <cell>, in the row
+#                        of a gate of the level, ahead of the gates
+# <row> False <col> <operand> ... <col> True
+#                        a gate in <row>: the operands it reads, each with its
+#                        column, then the column of the cell it switches
+# /k                     primary input k, loaded into its cell before level 1
+#                        (complemented if the footer lists it active-low)
+# RxC                    the gate or copy in row R, column C; an operand read
+#                        from a copy is named after the copy's source
+# READ <cell> <slot> <name> [inverted]
+#                        senses an output into slot <slot> of the output
+#                        buffer; an inverted read stores the cell's complement
+# <op>  # <signal> = <gate>(<operands>)
+#                        the signal an op writes, its gate and the signals it
+#                        reads, e.g. n5 = NOR(n4, n3)
+";
+
+// "<row> False <col1> <name1> <col2><name2> <col> True", as the C
+// implementation prints a gate, without the line's end
+fn write_exec<W: Write>(file: &mut W, cell: Cell, operands: &[Cell], names: &HashMap<Cell, String>) -> Result<()> {
+    let name = |c: &Cell| names.get(c).cloned().unwrap_or_else(|| c.to_string());
+    write!(file, "{:4} {:5} ", cell.row, "False")?;
+    write!(file, "{:4} ", operands[0].col)?;
+    write!(file, "{:9} ", name(&operands[0]))?;
+    match operands.get(1) {
+        Some(second) => {
+            write!(file, "{:4}", second.col)?;
+            write!(file, "{:9} ", name(second))?;
+        }
+        None => write!(file, "{:14}", " ")?,
+    }
+    // Operands past the C implementation's two follow in the same layout
+    for operand in operands.iter().skip(2) {
+        write!(file, "{:4} {:9} ", operand.col, name(operand))?;
+    }
+    write!(file, "{:4} True", cell.col)?;
+    Ok(())
+}
+
+// The signal each cell of a mapping holds, to annotate its listing
+pub fn cell_signals(mapping: &CrossbarMapping) -> HashMap<Cell, i32> {
+    mapping.occupied_cells().map(|c| (Cell::of(c), c.value)).collect()
+}
+
+// Where a program evaluates a gate: its cell, its level and how many of its
+// operands are copies brought into its row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GateSite {
+    pub cell: Cell,
+    pub level: i32,
+    pub copies: usize,
+}
+
+// The site of every gate the program evaluates, by the signal `signals`
+// names its cell with
+pub fn gate_sites(program: &Program, signals: &HashMap<Cell, i32>) -> HashMap<i32, GateSite> {
+    // Cells holding a copy until something else is written into them
+    let mut copies = HashSet::new();
+    let mut sites = HashMap::new();
+    for (level, op) in program.ops() {
+        let (operands, cell) = match op {
+            MicroOp::Copy { cell, .. } => {
+                copies.insert(*cell);
+                continue;
+            }
+            MicroOp::ExecNor { inputs, cell } => (inputs.as_slice(), cell),
+            MicroOp::ExecNot { input, cell } => (std::slice::from_ref(input), cell),
+            MicroOp::InputLoad { cell, .. } | MicroOp::Held { cell, .. } => {
+                copies.remove(cell);
+                continue;
+            }
+            _ => continue,
+        };
+        let copied = operands.iter().filter(|c| copies.contains(c)).count();
+        copies.remove(cell);
+        if let Some(&signal) = signals.get(cell) {
+            sites.insert(signal, GateSite { cell: *cell, level, copies: copied });
+        }
+    }
+    sites
+}
+
+// The same for the cells of a compact plan, where the placement puts them
+pub fn plan_signals(circuit: &Circuit, plan: &CompactPlan, placement: Option<&Placement>) -> HashMap<Cell, i32> {
+    let place = |cell: MemristiveGate| match placement {
+        Some(placement) => placement.cell(&cell),
+        None => cell,
+    };
+    let inputs = circuit.mapped_inputs().map(|n| plan.input_cell(n));
+    let gates = (0..circuit.num_gates).flat_map(|i| plan.copy_cell(i).into_iter().chain([plan.gate_cell(i)]));
+    inputs.chain(gates).map(place).map(|c| (Cell::of(&c), c.value)).collect()
+}
+
+// Every gate cell operand is a primary input, a copy, or a gate cell from an
+// earlier level, so the program never reads a cell before writing it
+fn check_operand_levels(mapping: &CrossbarMapping, i: usize, j: usize) {
+    if !verify::checks_enabled() {
+        return;
+    }
+    let cell = mapping.cell(i, j);
+    for operand in cell.inputs.iter().take(cell.fanin).flatten() {
+        let source = mapping.cell(operand.idx as usize, operand.jdx as usize);
+        if source.is_copy || source.value >= MAX_GATES as i32 {
+            continue;
+        }
+        invariant!(source.asap_level < cell.asap_level,
+            "cell {}x{} at level {} reads {}x{} at level {}",
+            i, j, cell.asap_level, operand.idx, operand.jdx, source.asap_level);
+    }
+}
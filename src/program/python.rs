@@ -0,0 +1,227 @@
+//program/python.rs
+use std::io::Write;
+use anyhow::Result;
+
+use super::{Cell, MicroOp, Program};
+use crate::signal_name;
+
+impl Program {
+    // A standalone Python script that replays the program on a NumPy array,
+    // every input vector at once, and prints the outputs as `simulate` does
+    pub fn write_python<W: Write>(&self, file: &mut W) -> Result<()> {
+        writeln!(file, "#!/usr/bin/env python3")?;
+        writeln!(file, "{}", PYTHON_USAGE.trim_end())?;
+        writeln!(file, "import argparse")?;
+        writeln!(file, "import sys")?;
+        writeln!(file)?;
+        writeln!(file, "import numpy as np")?;
+        writeln!(file)?;
+        writeln!(file, "PRIMITIVE = \"{}\"", self.primitive.kind().name())?;
+        writeln!(file, "NUM_INPUTS = {}", self.num_inputs)?;
+        writeln!(file, "ROWS, COLS = {}, {}", self.rows, self.cols)?;
+        let outputs: Vec<String> = self.outputs().map(|(_, signal, _)| format!("\"{}\"", signal_name(signal))).collect();
+        writeln!(file, "# Output signals by read slot")?;
+        writeln!(file, "OUTPUTS = [{}]", outputs.join(", "))?;
+        writeln!(file)?;
+
+        writeln!(file, "{}", PYTHON_OPS.trim_end())?;
+        writeln!(file, "STEPS = [")?;
+        for step in &self.steps {
+            writeln!(file, "    ({}, [", step.level)?;
+            for op in &step.ops {
+                writeln!(file, "        {},", python_op(op))?;
+            }
+            writeln!(file, "    ]),")?;
+        }
+        writeln!(file, "]")?;
+        writeln!(file)?;
+        writeln!(file)?;
+        write!(file, "{}", PYTHON_RUNTIME.trim_start())?;
+        Ok(())
+    }
+}
+
+fn python_op(op: &MicroOp) -> String {
+    let flag = |b: bool| if b { "True" } else { "False" };
+    match op {
+        MicroOp::InputLoad { input, cell, complemented } =>
+            format!("(\"load\", {}, {}, {})", input, python_cell(cell), flag(*complemented)),
+        MicroOp::Held { signal, cell } => format!("(\"held\", \"{}\", {})", signal_name(*signal), python_cell(cell)),
+        MicroOp::Preset { cell } => format!("(\"preset\", {})", python_cell(cell)),
+        MicroOp::Init { cells } => {
+            let cells: Vec<String> = cells.iter().map(python_cell).collect();
+            format!("(\"init\", [{}])", cells.join(", "))
+        }
+        MicroOp::Copy { from, cell } => format!("(\"copy\", {}, {})", python_cell(from), python_cell(cell)),
+        MicroOp::ExecNor { inputs, cell } => {
+            let inputs: Vec<String> = inputs.iter().map(python_cell).collect();
+            format!("(\"gate\", [{}], {})", inputs.join(", "), python_cell(cell))
+        }
+        MicroOp::ExecNot { input, cell } => format!("(\"not\", {}, {})", python_cell(input), python_cell(cell)),
+        MicroOp::Read { cell, slot, inverted, .. } =>
+            format!("(\"read\", {}, {}, {})", slot, python_cell(cell), flag(*inverted)),
+    }
+}
+
+fn python_cell(cell: &Cell) -> String {
+    format!("({}, {})", cell.row, cell.col)
+}
+
+const PYTHON_USAGE: &str = r##"
+"""Crossbar program of delphi, replayed on a NumPy array.
+
+Each cell of the crossbar holds one bit per input vector, so one run
+evaluates every vector. The checks match `delphi simulate`: a cell may not be
+read before it is written or while it waits for its evaluation, and a gate
+only evaluates into a cell initialized to 1.
+
+    python3 program.py                        # every vector, up to 20 inputs
+    python3 program.py 10110 01101            # bit strings, x0 first
+    python3 program.py --vectors vectors.txt  # one bit string per line
+    python3 program.py --random 1000 --seed 7
+    python3 program.py 10110 --trace          # the crossbar after each level
+
+From Python, `run(vectors, trace)` returns the outputs as an array with a
+row per vector and calls `trace(level, cells, written)` after each level.
+"""
+"##;
+
+const PYTHON_OPS: &str = r##"
+# Steps as (level, ops), cells as (row, col). The ops:
+#   ("load", input, cell, complemented)   write a primary input, or its complement
+#   ("held", signal, cell)                a cell already holding a signal; only
+#                                         held primary inputs can be replayed
+#   ("preset", cell)                      a cell already at 1, ready to evaluate
+#   ("init", [cells])                     set cells to 1 ahead of an evaluation
+#   ("copy", source, cell)                duplicate a cell into another row
+#   ("gate", [operands], cell)            PRIMITIVE of the operands
+#   ("not", operand, cell)
+#   ("read", slot, cell, inverted)        sense an output into its slot
+"##;
+
+const PYTHON_RUNTIME: &str = r##"
+class ProgramError(Exception):
+    pass
+
+
+def run(vectors, trace=None):
+    """Outputs of the program, one row of bools per input vector."""
+    vectors = np.asarray(vectors, dtype=bool).reshape(-1, NUM_INPUTS)
+    count = vectors.shape[0]
+    cells = np.zeros((ROWS, COLS, count), dtype=bool)
+    written = set()
+    armed = set()
+    outputs = np.zeros((count, len(OUTPUTS)), dtype=bool)
+    filled = set()
+
+    for level, ops in STEPS:
+        def read(cell):
+            if cell in armed:
+                raise ProgramError(f"cell {cell[0]}x{cell[1]} is read at level {level} before it evaluates")
+            if cell not in written:
+                raise ProgramError(f"cell {cell[0]}x{cell[1]} is read at level {level} but never written")
+            return cells[cell]
+
+        def evaluate(cell, value):
+            if cell not in armed:
+                raise ProgramError(f"cell {cell[0]}x{cell[1]} evaluates at level {level} without being initialized")
+            armed.discard(cell)
+            cells[cell] = value
+
+        for op in ops:
+            kind = op[0]
+            if kind == "load":
+                _, index, cell, complemented = op
+                cells[cell] = vectors[:, index] != complemented
+                written.add(cell)
+            elif kind == "held":
+                _, signal, cell = op
+                if not signal.startswith("x"):
+                    raise ProgramError(f"cell {cell[0]}x{cell[1]} holds {signal}, which an earlier program computed")
+                cells[cell] = vectors[:, int(signal[1:])]
+                written.add(cell)
+            elif kind == "preset":
+                cells[op[1]] = True
+                armed.add(op[1])
+                written.add(op[1])
+            elif kind == "init":
+                for cell in op[1]:
+                    cells[cell] = True
+                    armed.add(cell)
+                    written.add(cell)
+            elif kind == "copy":
+                cells[op[2]] = read(op[1])
+                written.add(op[2])
+            elif kind == "gate":
+                operands = np.stack([read(cell) for cell in op[1]])
+                if PRIMITIVE == "NAND":
+                    evaluate(op[2], ~operands.all(axis=0))
+                else:
+                    evaluate(op[2], ~operands.any(axis=0))
+            elif kind == "not":
+                evaluate(op[2], ~read(op[1]))
+            elif kind == "read":
+                _, slot, cell, inverted = op
+                if slot in filled:
+                    raise ProgramError(f"output slot {slot} is read twice")
+                filled.add(slot)
+                outputs[:, slot] = read(cell) != inverted
+        if trace is not None:
+            trace(level, cells, written)
+    return outputs
+
+
+def print_cells(level, cells, written):
+    """The crossbar for the first vector: 0 or 1 per written cell, . elsewhere."""
+    print(f"# level {level}")
+    for row in range(ROWS):
+        line = "".join(str(int(cells[row, col, 0])) if (row, col) in written else "." for col in range(COLS))
+        print(line.rstrip("."))
+
+
+def parse_bits(text):
+    text = text.strip()
+    if len(text) != NUM_INPUTS or any(c not in "01" for c in text):
+        sys.exit(f"error: '{text}' is not a vector of {NUM_INPUTS} bits")
+    return [c == "1" for c in text]
+
+
+def bits(values):
+    return "".join("1" if v else "0" for v in values)
+
+
+def main():
+    parser = argparse.ArgumentParser(description="Replay the crossbar program on input vectors.")
+    parser.add_argument("bits", nargs="*", help="input vectors as bit strings, x0 first")
+    parser.add_argument("--vectors", metavar="FILE", help="file with one bit string per line; # starts a comment")
+    parser.add_argument("--random", type=int, metavar="N", help="number of random vectors")
+    parser.add_argument("--seed", type=int, default=1, help="seed of the random vectors")
+    parser.add_argument("--trace", action="store_true", help="print the crossbar after each level, for the first vector")
+    args = parser.parse_args()
+
+    if args.bits:
+        vectors = np.array([parse_bits(b) for b in args.bits], dtype=bool)
+    elif args.vectors:
+        with open(args.vectors) as lines:
+            texts = [line.split("#")[0].strip() for line in lines]
+        vectors = np.array([parse_bits(t) for t in texts if t], dtype=bool)
+    elif args.random is not None:
+        vectors = np.random.default_rng(args.seed).random((args.random, NUM_INPUTS)) < 0.5
+    elif NUM_INPUTS <= 20:
+        numbers = np.arange(1 << NUM_INPUTS)
+        vectors = (numbers[:, None] >> np.arange(NUM_INPUTS)) & 1 == 1
+    else:
+        parser.error(f"{NUM_INPUTS} inputs are too many to enumerate; give vectors, --vectors or --random")
+
+    try:
+        outputs = run(vectors, print_cells if args.trace else None)
+    except ProgramError as error:
+        sys.exit(f"error: {error}")
+    print(f"# inputs: x0..x{NUM_INPUTS - 1}  outputs: {' '.join(OUTPUTS)}")
+    for vector, output in zip(vectors, outputs):
+        print(bits(vector), "->", bits(output))
+
+
+if __name__ == "__main__":
+    main()
+"##;
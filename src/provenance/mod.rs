@@ -0,0 +1,199 @@
+//provenance/mod.rs
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{self, Versioned};
+use crate::{ports, signal_name, Circuit, CrossbarMapping, Origin};
+
+// Summary of the invocation that wrote an artifact, stamped at its top so
+// results scattered across an output directory stay traceable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactHeader {
+    pub tool: String,
+    pub input: String,
+    // FNV-1a hash of the input file's bytes
+    pub input_hash: String,
+    pub options: String,
+    pub gates: usize,
+    pub inputs: usize,
+    pub outputs: usize,
+    // Word-level input ports the mappers kept together, if any were grouped
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub input_ports: String,
+    // UTC, to the second; SOURCE_DATE_EPOCH overrides the clock for
+    // reproducible builds
+    pub timestamp: String,
+}
+
+impl ArtifactHeader {
+    pub fn new<P: AsRef<Path>>(input: P, options: &[String], circuit: &Circuit) -> Result<Self> {
+        let input = input.as_ref();
+        let bytes = fs::read(input)
+            .context(format!("Failed to read netlist: {:?}", input))?;
+        Ok(Self {
+            tool: tool(),
+            input: input.display().to_string(),
+            input_hash: content_hash(&bytes),
+            options: options.join(" "),
+            gates: circuit.num_gates,
+            inputs: circuit.num_inputs,
+            outputs: circuit.num_outputs,
+            input_ports: ports::describe(&circuit.input_groups),
+            timestamp: humantime::format_rfc3339_seconds(generated_at()).to_string(),
+        })
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Tool      : {}", self.tool),
+            format!("Input     : {}", self.input),
+            format!("Input Hash: {}", self.input_hash),
+            format!("Options   : {}", self.options),
+            format!("Circuit   : {} gates, {} inputs, {} outputs", self.gates, self.inputs, self.outputs),
+            format!("Generated : {}", self.timestamp),
+        ];
+        if !self.input_ports.is_empty() {
+            lines.insert(5, format!("Ports     : {}", self.input_ports));
+        }
+        lines
+    }
+
+    // Puts the header at the top of an artifact already on disk: as comments
+    // in text, Verilog and Python files, after a script's #! line, and as a
+    // "header" object in JSON programs.
+    // Binary programs and CSV tables have no place for it and are left alone
+    pub fn stamp<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let comment = match path.extension().and_then(|e| e.to_str()) {
+            Some("v") => "//",
+            Some("json") => return self.stamp_json(path),
+            Some("txt") | Some("py") => "#",
+            _ => return Ok(()),
+        };
+        let text = fs::read_to_string(path)
+            .context(format!("Failed to read artifact: {:?}", path))?;
+        let mut stamped = String::with_capacity(text.len() + 512);
+        let text = match text.strip_prefix("#!").and_then(|rest| rest.split_once('\n')) {
+            Some((interpreter, rest)) => {
+                stamped.push_str(&format!("#!{}\n", interpreter));
+                rest
+            }
+            None => &text,
+        };
+        for line in self.lines() {
+            stamped.push_str(&format!("{} {}\n", comment, line));
+        }
+        stamped.push_str(text);
+        fs::write(path, stamped).context(format!("Failed to stamp artifact: {:?}", path))
+    }
+
+    fn stamp_json(&self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)
+            .context(format!("Failed to read artifact: {:?}", path))?;
+        let body = text.trim_start().strip_prefix('{')
+            .context(format!("{:?} is not a JSON object", path))?;
+        let separator = if body.trim_start().starts_with('}') { "" } else { "," };
+        let header = serde_json::to_string(self)?;
+        fs::write(path, format!("{{\"header\":{}{}{}", header, separator, body))
+            .context(format!("Failed to stamp artifact: {:?}", path))
+    }
+}
+
+// The netlist line behind every gate and every mapped cell, as
+// `--provenance-json` writes it, so a gate or cell a check flags can be
+// traced to the source. Primary inputs and gates built from none (input
+// inverters of NAND circuits, for one) have no origin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenanceReport {
+    pub circuit: String,
+    pub gates: Vec<GateOrigin>,
+    pub naive: Vec<CellOrigin>,
+    pub compact: Vec<CellOrigin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GateOrigin {
+    pub signal: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+}
+
+// A copy has the origin of the gate whose value it holds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellOrigin {
+    pub row: u32,
+    pub col: u32,
+    pub signal: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub copy: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+}
+
+impl ProvenanceReport {
+    pub fn new(circuit: &Circuit, naive: &CrossbarMapping, compact: &CrossbarMapping) -> Self {
+        Self {
+            circuit: circuit.bench_name.clone(),
+            gates: gate_origins(circuit),
+            naive: cell_origins(circuit, naive),
+            compact: cell_origins(circuit, compact),
+        }
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string(&Versioned::new(self))? + "\n")
+            .context(format!("Failed to write provenance: {:?}", path))
+    }
+
+    pub fn read_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read provenance: {:?}", path))?;
+        schema::from_str(&text, "provenance").context(format!("Failed to load provenance: {:?}", path))
+    }
+}
+
+// In netlist order
+pub fn gate_origins(circuit: &Circuit) -> Vec<GateOrigin> {
+    circuit.gates_in_original_order().into_iter()
+        .map(|i| &circuit.gates[i])
+        .map(|g| GateOrigin { signal: signal_name(g.out), origin: g.origin.clone() })
+        .collect()
+}
+
+// Row by row, as the mapping's programs address the cells
+pub fn cell_origins(circuit: &Circuit, mapping: &CrossbarMapping) -> Vec<CellOrigin> {
+    mapping.occupied_cells()
+        .map(|cell| CellOrigin {
+            row: cell.idx as u32,
+            col: cell.jdx as u32,
+            signal: signal_name(cell.value),
+            copy: cell.is_copy,
+            origin: circuit.origin(cell.value).cloned(),
+        })
+        .collect()
+}
+
+pub fn tool() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+// The time artifacts are stamped with: SOURCE_DATE_EPOCH if set, else now
+pub fn generated_at() -> SystemTime {
+    std::env::var("SOURCE_DATE_EPOCH").ok()
+        .and_then(|s| s.trim().parse().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap_or_else(SystemTime::now)
+}
+
+// FNV-1a of a file's bytes, as `fnv1a64:<hex>`
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("fnv1a64:{:016x}", fnv1a(bytes))
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,88 @@
+//replicate/mod.rs
+use std::ops::Range;
+use anyhow::{Result, ensure};
+
+use crate::batch::{self, InitWrite};
+use crate::tiling::LEVEL_CYCLES;
+use crate::{Circuit, CrossbarMapping, MAX_COL, MAX_GATES};
+
+// N bit-sliced copies of a mapping side by side, one group of columns per
+// bit. Every micro-op of the 1-bit program is issued once and acts on its
+// columns in every group, so an N-bit word is computed in the cycles one
+// bit takes, plus whatever extra input writes the slices need
+#[derive(Debug, Clone)]
+pub struct Replication {
+    pub bits: usize,
+    pub rows: usize,
+    // Columns of one copy of the mapping
+    pub cols_per_bit: usize,
+    // Cell of each primary input in bit 0; None for the unused inputs the
+    // mapping leaves out
+    pub inputs: Vec<Option<(usize, usize)>>,
+    pub levels: usize,
+}
+
+impl Replication {
+    pub fn new(circuit: &Circuit, mapping: &CrossbarMapping, bits: usize) -> Result<Self> {
+        ensure!(bits > 0, "A replication needs at least one bit");
+        let cols_per_bit = mapping.max_jdx.max(0) as usize + 1;
+        let max_bits = Self::max_bits(mapping);
+        ensure!(bits <= max_bits,
+            "{} bits need {} columns; the mapping takes {} per bit, so at most {} fit in {}",
+            bits, bits * cols_per_bit, cols_per_bit, max_bits, MAX_COL);
+
+        let mut inputs = vec![None; circuit.num_inputs];
+        for cell in mapping.occupied_cells() {
+            let n = cell.value - MAX_GATES as i32;
+            if n >= 0 && (n as usize) < inputs.len() && !cell.is_copy {
+                inputs[n as usize] = Some((cell.idx as usize, cell.jdx as usize));
+            }
+        }
+
+        Ok(Self {
+            bits,
+            rows: mapping.max_idx.max(0) as usize + 1,
+            cols_per_bit,
+            inputs,
+            levels: circuit.max_asap.max(0) as usize,
+        })
+    }
+
+    // Bits whose copies of the mapping fit in the crossbar's columns
+    pub fn max_bits(mapping: &CrossbarMapping) -> usize {
+        MAX_COL / (mapping.max_jdx.max(0) as usize + 1)
+    }
+
+    // Columns holding bit `bit`
+    pub fn columns(&self, bit: usize) -> Range<usize> {
+        bit * self.cols_per_bit..(bit + 1) * self.cols_per_bit
+    }
+
+    // Cell receiving input `input` of bit `bit`
+    pub fn cell(&self, bit: usize, input: usize) -> (usize, usize) {
+        let (row, col) = self.inputs[input].unwrap_or_else(|| panic!("Input x{} has no cell in the mapping", input));
+        (row, bit * self.cols_per_bit + col)
+    }
+
+    // Input data writes along whole rows or columns; the naive mapping's
+    // single input row loads every bit in one write
+    pub fn init_writes(&self) -> Vec<InitWrite> {
+        batch::line_writes((0..self.bits).flat_map(|bit| {
+            (0..self.inputs.len()).filter(|&n| self.inputs[n].is_some()).map(move |input| {
+                let (row, col) = self.cell(bit, input);
+                (row, col, bit, input)
+            })
+        }))
+    }
+
+    // The shared micro-ops of one word, the input writes replacing the
+    // single input write
+    pub fn cycles(&self) -> usize {
+        LEVEL_CYCLES * self.levels + self.init_writes().len()
+    }
+
+    // Bits per cycle over the whole word
+    pub fn throughput(&self) -> f64 {
+        self.bits as f64 / self.cycles() as f64
+    }
+}
@@ -0,0 +1,190 @@
+//repro/mod.rs
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail, ensure};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::provenance;
+
+// Name of the manifest at the root of every bundle
+pub const MANIFEST: &str = "MANIFEST.toml";
+
+// What a bundled file is to the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // The netlist
+    Input,
+    // Technology, parasitics and don't-care files
+    Config,
+    // Everything the run wrote
+    Artifact,
+    // The run's console output
+    Log,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledFile {
+    // Relative to the bundle root, '/'-separated
+    pub path: String,
+    pub role: Role,
+    pub bytes: u64,
+    pub hash: String,
+}
+
+// How a bundled run was made. `command` run from the unpacked bundle with
+// SOURCE_DATE_EPOCH set to `source_date_epoch` writes the same artifacts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReproManifest {
+    pub tool: String,
+    pub generated: String,
+    pub source_date_epoch: u64,
+    pub command: Vec<String>,
+    // The process options as given, before their paths were moved into the bundle
+    pub options: Vec<String>,
+    pub files: Vec<BundledFile>,
+}
+
+// A bundle assembled in a staging directory, then packed into one gzipped
+// tarball. Entries are sorted and carry fixed owners, modes and times, so a
+// bundle of the same run is the same file
+pub struct Bundle {
+    root: PathBuf,
+    files: Vec<BundledFile>,
+    // Time of the run, in seconds since the epoch
+    epoch: u64,
+}
+
+impl Bundle {
+    // Starts from an empty staging directory; the run is dated now, or at
+    // SOURCE_DATE_EPOCH if set
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        if root.exists() {
+            fs::remove_dir_all(&root).context(format!("Failed to clear staging directory: {:?}", root))?;
+        }
+        fs::create_dir_all(&root).context(format!("Failed to create staging directory: {:?}", root))?;
+        let epoch = provenance::generated_at().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Ok(Self { root, files: Vec::new(), epoch })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // The SOURCE_DATE_EPOCH the bundled run is made with
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    // Copies a file into the bundle at `path`
+    pub fn add_file(&mut self, source: &Path, path: &str, role: Role) -> Result<()> {
+        let bytes = fs::read(source).context(format!("Failed to read {:?}", source))?;
+        self.add_bytes(path, &bytes, role)
+    }
+
+    pub fn add_bytes(&mut self, path: &str, bytes: &[u8], role: Role) -> Result<()> {
+        let target = self.root.join(path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir).context(format!("Failed to create directory: {:?}", dir))?;
+        }
+        fs::write(&target, bytes).context(format!("Failed to write {:?}", target))?;
+        self.record(path, bytes, role)
+    }
+
+    // Records the files already written under `dir` of the staging directory
+    pub fn add_dir(&mut self, dir: &str, role: Role) -> Result<()> {
+        let mut paths = Vec::new();
+        collect_files(&self.root.join(dir), &mut paths)?;
+        paths.sort();
+        for path in paths {
+            let relative = path.strip_prefix(&self.root).expect("file under the staging root");
+            let name: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+            let bytes = fs::read(&path).context(format!("Failed to read {:?}", path))?;
+            self.record(&name.join("/"), &bytes, role)?;
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, path: &str, bytes: &[u8], role: Role) -> Result<()> {
+        ensure!(path != MANIFEST && self.files.iter().all(|f| f.path != path), "{} is bundled twice", path);
+        self.files.push(BundledFile {
+            path: path.to_string(),
+            role,
+            bytes: bytes.len() as u64,
+            hash: provenance::content_hash(bytes),
+        });
+        Ok(())
+    }
+
+    // Writes the manifest and packs it with every file into `path`; the
+    // staging directory is removed
+    pub fn finish<P: AsRef<Path>>(self, command: Vec<String>, options: Vec<String>, path: P) -> Result<ReproManifest> {
+        let epoch = self.epoch;
+        let manifest = ReproManifest {
+            tool: provenance::tool(),
+            generated: humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(epoch)).to_string(),
+            source_date_epoch: epoch,
+            command,
+            options,
+            files: self.files,
+        };
+
+        let path = path.as_ref();
+        let file = File::create(path).context(format!("Failed to create bundle: {:?}", path))?;
+        let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        let mut append = |name: &str, data: &[u8]| -> Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(epoch);
+            header.set_cksum();
+            tar.append_data(&mut header, name, data)
+                .context(format!("Failed to add {} to the bundle", name))
+        };
+        append(MANIFEST, toml::to_string(&manifest)?.as_bytes())?;
+        for entry in &manifest.files {
+            let data = fs::read(self.root.join(&entry.path))
+                .context(format!("Failed to read {:?}", entry.path))?;
+            append(&entry.path, &data)?;
+        }
+        tar.into_inner()?.finish()?;
+
+        fs::remove_dir_all(&self.root).context(format!("Failed to remove staging directory: {:?}", self.root))?;
+        Ok(manifest)
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).context(format!("Failed to read directory: {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// The manifest of a bundle on disk
+pub fn read_manifest<P: AsRef<Path>>(bundle: P) -> Result<ReproManifest> {
+    let path = bundle.as_ref();
+    let file = File::open(path).context(format!("Failed to open bundle: {:?}", path))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST {
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            return toml::from_str(&text).context(format!("Malformed manifest in {:?}", path));
+        }
+    }
+    bail!("{:?} has no {}", path, MANIFEST)
+}
@@ -0,0 +1,192 @@
+//resynth/mod.rs
+use std::collections::{HashMap, HashSet};
+use anyhow::{Result, bail, ensure};
+
+use crate::program::Program;
+use crate::simulator::{output_signals, topological_order};
+use crate::{mapper, scheduler, signal_name, Circuit, Origin, Primitive, TableGate, MAX_GATES};
+
+// Rewrites a NOR/NOT circuit into NAND/NOT form. By De Morgan,
+// NOR(a, b, ...) = NOT(NAND(NOT a, NOT b, ...)); inverters are cancelled by
+// tracking both polarities of every signal and only building the ones a
+// reader needs. Primary inputs and outputs keep their ids, a NOR that is
+// needed in true polarity keeps its id, and new signals are temporaries
+pub fn to_nand(circuit: &Circuit) -> Result<Circuit> {
+    ensure!(circuit.primitive == Primitive::Nor, "Circuit is already in {} form", circuit.primitive);
+    ensure!(!circuit.gates[..circuit.num_gates].iter().any(|g| g.is_multi_output()),
+        "Circuit {} has multi-output gates, which NAND re-synthesis cannot rebuild", circuit.bench_name);
+    let order = topological_order(circuit)?;
+    let outputs: HashSet<i32> = output_signals(circuit).into_iter().collect();
+
+    // Polarities each signal is read in, found from the outputs backwards
+    let mut need_pos: HashSet<i32> = outputs.clone();
+    let mut need_neg: HashSet<i32> = HashSet::new();
+    for &i in order.iter().rev() {
+        let gate = &circuit.gates[i];
+        let (pos, neg) = (need_pos.contains(&gate.out), need_neg.contains(&gate.out));
+        if gate.fanin == 1 {
+            let input = gate.inputs[0];
+            if outputs.contains(&gate.out) {
+                match nor_inputs(circuit, input) {
+                    Some(inputs) => need_neg.extend(inputs),
+                    None => { need_pos.insert(input); }
+                }
+            } else if pos {
+                need_neg.insert(input);
+            }
+            if neg {
+                need_pos.insert(input);
+            }
+        } else if pos || neg {
+            need_neg.extend(&gate.inputs[..gate.fanin]);
+        }
+    }
+
+    let mut nand = Circuit::new();
+    nand.primitive = Primitive::Nand;
+    nand.num_inputs = circuit.num_inputs;
+    nand.primary_inputs = circuit.primary_inputs.clone();
+    nand.num_outputs = circuit.num_outputs;
+    nand.bench_name = circuit.bench_name.clone();
+
+    let mut builder = Builder {
+        circuit: nand,
+        pos: HashMap::new(),
+        neg: HashMap::new(),
+        next_temp: circuit.gates.iter().map(|g| -g.out).max().unwrap_or(0).max(0) + 1,
+        origin: None,
+    };
+
+    for k in 0..circuit.num_inputs {
+        let input = (MAX_GATES + k) as i32;
+        builder.pos.insert(input, input);
+        if need_neg.contains(&input) {
+            let inverted = builder.temp()?;
+            builder.emit(inverted, &[input], false);
+            builder.neg.insert(input, inverted);
+        }
+    }
+
+    for &i in &order {
+        let gate = &circuit.gates[i];
+        let g = gate.out;
+        builder.origin = gate.origin.clone();
+        if gate.fanin == 1 {
+            let input = gate.inputs[0];
+            if outputs.contains(&g) {
+                // An output inverting a NOR is an OR, which is a single NAND
+                let sources = match nor_inputs(circuit, input) {
+                    Some(inputs) => inputs.iter().map(|&s| builder.neg(s)).collect::<Result<Vec<_>>>()?,
+                    None => vec![builder.pos(input)?],
+                };
+                builder.emit(g, &sources, gate.is_output);
+                builder.pos.insert(g, g);
+            } else if need_pos.contains(&g) {
+                let source = builder.neg(input)?;
+                builder.pos.insert(g, source);
+            }
+            if need_neg.contains(&g) {
+                let source = builder.pos(input)?;
+                builder.neg.insert(g, source);
+            }
+        } else if gate.fanin >= 2 {
+            if !(need_pos.contains(&g) || need_neg.contains(&g)) {
+                continue;
+            }
+            let inputs = gate.inputs[..gate.fanin].iter().map(|&s| builder.neg(s)).collect::<Result<Vec<_>>>()?;
+            let inverted = builder.temp()?;
+            builder.emit(inverted, &inputs, false);
+            builder.neg.insert(g, inverted);
+            if need_pos.contains(&g) {
+                builder.emit(g, &[inverted], gate.is_output);
+                builder.pos.insert(g, g);
+            }
+        } else {
+            bail!("Gate n{} has unsupported fanin {}", g, gate.fanin);
+        }
+    }
+
+    let mut nand = builder.circuit;
+    nand.num_gates = nand.gates.len();
+    nand.index_signals();
+    Ok(nand)
+}
+
+// Inputs of the NOR driving a signal, if a NOR drives it
+fn nor_inputs(circuit: &Circuit, signal: i32) -> Option<&[i32]> {
+    circuit.driver(signal)
+        .filter(|d| d.fanin >= 2)
+        .map(|d| &d.inputs[..d.fanin])
+}
+
+struct Builder {
+    circuit: Circuit,
+    // Signal of the source circuit -> signal holding it, or its complement
+    pos: HashMap<i32, i32>,
+    neg: HashMap<i32, i32>,
+    next_temp: i32,
+    // Of the source gate being rebuilt, for the gates built for it
+    origin: Option<Origin>,
+}
+
+impl Builder {
+    fn temp(&mut self) -> Result<i32> {
+        ensure!(self.next_temp < MAX_GATES as i32, "Too many temporary signals in NAND form");
+        self.next_temp += 1;
+        Ok(-(self.next_temp - 1))
+    }
+
+    fn emit(&mut self, out: i32, inputs: &[i32], is_output: bool) {
+        let mut gate = TableGate { out, is_output, origin: self.origin.clone(), ..Default::default() };
+        gate.set_inputs(inputs);
+        self.circuit.gates.push(gate);
+    }
+
+    fn pos(&self, signal: i32) -> Result<i32> {
+        match self.pos.get(&signal) {
+            Some(&s) => Ok(s),
+            None => bail!("Signal {} is read before it is built", signal_name(signal)),
+        }
+    }
+
+    fn neg(&self, signal: i32) -> Result<i32> {
+        match self.neg.get(&signal) {
+            Some(&s) => Ok(s),
+            None => bail!("Complement of {} is read before it is built", signal_name(signal)),
+        }
+    }
+}
+
+// Size and depth of a circuit and of its compact mapping, for comparing the
+// NOR and NAND flows or a circuit before and after binding inputs
+#[derive(Debug, Clone)]
+pub struct FlowMetrics {
+    pub primitive: Primitive,
+    pub gates: usize,
+    pub not_gates: usize,
+    pub levels: i32,
+    pub rows: i32,
+    pub cols: i32,
+    pub cells: usize,
+    // Of the compact program, copies included; 0 if it cannot be built
+    pub cycles: usize,
+}
+
+impl FlowMetrics {
+    // Schedules and maps a copy of the circuit
+    pub fn measure(circuit: &Circuit) -> Self {
+        let mut circuit = circuit.clone();
+        scheduler::compute_asap_schedule(&mut circuit);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        Self {
+            primitive: circuit.primitive,
+            gates: circuit.num_gates,
+            not_gates: circuit.gates.iter().take(circuit.num_gates).filter(|g| g.fanin == 1).count(),
+            levels: circuit.max_asap,
+            rows: mapping.max_idx + 1,
+            cols: mapping.max_jdx + 1,
+            cells: mapping.occupied_cells().count(),
+            cycles: Program::from_mapping(&circuit, &mapping).map_or(0, |p| p.cycles().total()),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//retime/mod.rs
+// Retiming of a netlist cut open at its latches. Every gate takes one
+// crossbar level, so the clock period is the deepest combinational cloud
+// between registers. Registers are moved across gates (Leiserson-Saxe, with
+// FEAS and a binary search on the period) so that the deepest cloud is as
+// shallow as it can be. Every path from an input to an output keeps its
+// number of registers, so latency is kept; initial register values are not
+// recomputed
+use std::collections::HashMap;
+use std::fmt;
+use anyhow::{Result, ensure};
+
+use crate::chain::compact_cycles;
+use crate::parser::Sequential;
+use crate::simulator::output_signals;
+use crate::{Circuit, TableGate, MAX_GATES};
+
+// The environment: primary inputs come from it and primary outputs go to it
+const HOST: usize = usize::MAX;
+
+// A wire from the gate (or HOST) driving `signal` to input `input` of gate
+// `to` (or to HOST), through `weight` registers
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    from: usize,
+    to: usize,
+    input: usize,
+    signal: i32,
+    weight: i64,
+}
+
+// A combinational cloud: gates joined by wires without registers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cloud {
+    pub gates: usize,
+    pub depth: usize,
+    // Cycles of its compact program
+    pub cycles: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Retiming {
+    pub name: String,
+    // Registers each gate moved from its outputs to its inputs
+    pub lags: Vec<i64>,
+    pub before: Vec<Cloud>,
+    pub after: Vec<Cloud>,
+    pub registers: (usize, usize),
+}
+
+impl Retiming {
+    pub fn new(sequential: &Sequential) -> Result<Self> {
+        let graph = Graph::new(sequential)?;
+        let still = vec![0; graph.gates];
+        let period = graph.arrivals(&still).map(|a| a.into_iter().max().unwrap_or(0))
+            .expect("netlists without latches have no combinational loops");
+
+        // The current period is met without moving anything
+        let (mut low, mut high, mut lags) = (1, period, still.clone());
+        while low < high {
+            let mid = (low + high) / 2;
+            match graph.feasible(mid) {
+                Some(found) => (high, lags) = (mid, found),
+                None => low = mid + 1,
+            }
+        }
+        Ok(Self {
+            name: sequential.circuit.bench_name.clone(),
+            before: graph.clouds(&sequential.circuit, &still)?,
+            after: graph.clouds(&sequential.circuit, &lags)?,
+            registers: (graph.registers(&still), graph.registers(&lags)),
+            lags,
+        })
+    }
+
+    // Levels of the deepest cloud
+    pub fn period(clouds: &[Cloud]) -> usize {
+        clouds.iter().map(|c| c.depth).max().unwrap_or(0)
+    }
+
+    // Cycles of the slowest cloud's program, which bounds how often the
+    // design takes a new input
+    pub fn slowest(clouds: &[Cloud]) -> usize {
+        clouds.iter().map(|c| c.cycles).max().unwrap_or(0)
+    }
+}
+
+// `s27: period 6 -> 3 levels, slowest cloud 41 -> 23 cycles, 3 -> 5 registers`
+impl fmt::Display for Retiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: period {} -> {} levels, slowest cloud {} -> {} cycles, {} -> {} registers",
+            self.name, Self::period(&self.before), Self::period(&self.after),
+            Self::slowest(&self.before), Self::slowest(&self.after), self.registers.0, self.registers.1)
+    }
+}
+
+// `12 gates, 6 levels, 41 cycles`
+impl fmt::Display for Cloud {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} gates, {} levels, {} cycles", self.gates, self.depth, self.cycles)
+    }
+}
+
+struct Graph {
+    gates: usize,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn new(sequential: &Sequential) -> Result<Self> {
+        let circuit = &sequential.circuit;
+        ensure!(!circuit.gates[..circuit.num_gates].iter().any(|g| g.is_multi_output()),
+            "{} has multi-output gates, which cannot be retimed", circuit.bench_name);
+        let (inputs, outputs) = (sequential.primary_inputs(), sequential.primary_outputs());
+        let ports = output_signals(circuit);
+        ensure!(ports.len() == circuit.num_outputs, "{} drives {} of its {} outputs",
+            circuit.bench_name, ports.len(), circuit.num_outputs);
+        let driver = |signal: i32| circuit.drivers[&signal];
+
+        let mut edges = Vec::new();
+        for (k, &signal) in ports[..outputs].iter().enumerate() {
+            edges.push(Edge { from: driver(signal), to: HOST, input: k, signal, weight: 0 });
+        }
+        for (v, gate) in circuit.gates[..circuit.num_gates].iter().enumerate() {
+            for (input, &signal) in gate.inputs[..gate.fanin].iter().enumerate() {
+                let edge = match signal - MAX_GATES as i32 {
+                    k if k < 0 => Edge { from: driver(signal), to: v, input, signal, weight: 0 },
+                    k if (k as usize) < inputs => Edge { from: HOST, to: v, input, signal, weight: 0 },
+                    // A latch output reads the latch's input a register later
+                    k => {
+                        let d = ports[outputs + k as usize - inputs];
+                        Edge { from: driver(d), to: v, input, signal: d, weight: 1 }
+                    }
+                };
+                edges.push(edge);
+            }
+        }
+        Ok(Self { gates: circuit.num_gates, edges })
+    }
+
+    fn lag(lags: &[i64], v: usize) -> i64 {
+        if v == HOST { 0 } else { lags[v] }
+    }
+
+    fn weight(lags: &[i64], edge: &Edge) -> i64 {
+        edge.weight + Self::lag(lags, edge.to) - Self::lag(lags, edge.from)
+    }
+
+    // Levels from the last register to the end of each gate, or None if the
+    // wires without registers close a loop
+    fn arrivals(&self, lags: &[i64]) -> Option<Vec<usize>> {
+        let mut pending = vec![0usize; self.gates];
+        let mut readers = vec![Vec::new(); self.gates];
+        for edge in self.edges.iter().filter(|e| e.from != HOST && e.to != HOST && Self::weight(lags, e) == 0) {
+            pending[edge.to] += 1;
+            readers[edge.from].push(edge.to);
+        }
+        let mut arrival = vec![1usize; self.gates];
+        let mut ready: Vec<usize> = (0..self.gates).filter(|&v| pending[v] == 0).collect();
+        let mut done = 0;
+        while let Some(u) = ready.pop() {
+            done += 1;
+            for &v in &readers[u] {
+                arrival[v] = arrival[v].max(arrival[u] + 1);
+                pending[v] -= 1;
+                if pending[v] == 0 {
+                    ready.push(v);
+                }
+            }
+        }
+        (done == self.gates).then_some(arrival)
+    }
+
+    // Lags that bring every cloud down to `period` levels, if any do: each
+    // round moves a register back over every gate that ends too late
+    fn feasible(&self, period: usize) -> Option<Vec<i64>> {
+        let mut lags = vec![0; self.gates];
+        for _ in 0..=self.gates {
+            let arrival = self.arrivals(&lags)?;
+            if arrival.iter().all(|&a| a <= period) {
+                return self.edges.iter().all(|e| Self::weight(&lags, e) >= 0).then_some(lags);
+            }
+            for (v, &a) in arrival.iter().enumerate() {
+                if a > period {
+                    lags[v] += 1;
+                }
+            }
+            // A register moved back over a gate driving a primary output
+            // would delay the output
+            if self.edges.iter().any(|e| e.to == HOST && Self::weight(&lags, e) < 0) {
+                return None;
+            }
+        }
+        None
+    }
+
+    // Registers after retiming; those on the wires from one driver are shared
+    fn registers(&self, lags: &[i64]) -> usize {
+        let mut most: HashMap<(usize, i32), i64> = HashMap::new();
+        for edge in &self.edges {
+            let weight = most.entry((edge.from, edge.signal)).or_default();
+            *weight = (*weight).max(Self::weight(lags, edge));
+        }
+        most.values().sum::<i64>() as usize
+    }
+
+    // Each cloud as a circuit of its own: what it reads through registers or
+    // from the host are its inputs, and gates read through registers or by
+    // the host, or by nothing, are its outputs
+    fn clouds(&self, circuit: &Circuit, lags: &[i64]) -> Result<Vec<Cloud>> {
+        let arrival = self.arrivals(lags).expect("retimed clouds are acyclic");
+        let mut parent: Vec<usize> = (0..self.gates).collect();
+        fn root(parent: &mut [usize], mut v: usize) -> usize {
+            while parent[v] != v {
+                parent[v] = parent[parent[v]];
+                v = parent[v];
+            }
+            v
+        }
+        // Each gate's inputs: the signal, and the registers on the way if it
+        // comes from outside the cloud
+        let mut inputs = vec![Vec::new(); self.gates];
+        let (mut outputs, mut read) = (vec![false; self.gates], vec![false; self.gates]);
+        for edge in &self.edges {
+            let weight = Self::weight(lags, edge);
+            let inside = edge.from != HOST && edge.to != HOST && weight == 0;
+            if inside {
+                let (a, b) = (root(&mut parent, edge.from), root(&mut parent, edge.to));
+                parent[a] = b;
+            }
+            if edge.to != HOST {
+                inputs[edge.to].push((edge.input, edge.signal, (!inside).then_some(weight)));
+            }
+            if edge.from != HOST {
+                read[edge.from] = true;
+                outputs[edge.from] |= !inside;
+            }
+        }
+        // Gates read by nothing end their cloud too
+        for v in 0..self.gates {
+            outputs[v] |= !read[v];
+        }
+
+        let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+        for v in 0..self.gates {
+            members.entry(root(&mut parent, v)).or_default().push(v);
+        }
+        let mut clouds: Vec<Vec<usize>> = members.into_values().collect();
+        clouds.sort_by_key(|gates| gates[0]);
+
+        clouds.into_iter().enumerate().map(|(k, mut gates)| {
+            gates.sort_by_key(|&v| (arrival[v], v));
+            let mut cloud = Circuit::new();
+            cloud.bench_name = format!("{}_cloud{}", circuit.bench_name, k);
+            cloud.primitive = circuit.primitive;
+            // Signals from outside, by driver and registers on the way
+            let mut ports: HashMap<(i32, i64), i32> = HashMap::new();
+            for &v in &gates {
+                let gate = &circuit.gates[v];
+                let mut read = gate.inputs[..gate.fanin].to_vec();
+                for &(input, signal, weight) in &inputs[v] {
+                    if let Some(weight) = weight {
+                        let next = (MAX_GATES + ports.len()) as i32;
+                        read[input] = *ports.entry((signal, weight)).or_insert(next);
+                    } else {
+                        read[input] = signal;
+                    }
+                }
+                let mut cloud_gate = TableGate {
+                    out: gate.out,
+                    is_output: outputs[v],
+                    attributes: gate.attributes.clone(),
+                    origin: gate.origin.clone(),
+                    ..Default::default()
+                };
+                cloud_gate.set_inputs(&read);
+                cloud.gates.push(cloud_gate);
+            }
+            cloud.num_gates = gates.len();
+            cloud.num_inputs = ports.len();
+            cloud.primary_inputs = (0..ports.len()).map(|k| (MAX_GATES + k) as i32).collect();
+            cloud.num_outputs = gates.iter().filter(|&&v| outputs[v]).count();
+            cloud.index_signals();
+            Ok(Cloud {
+                gates: gates.len(),
+                depth: gates.iter().map(|&v| arrival[v]).max().unwrap_or(0),
+                cycles: compact_cycles(&cloud)?,
+            })
+        }).collect()
+    }
+}
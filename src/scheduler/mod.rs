@@ -1,251 +1,626 @@
 //scheduler/mod.rs
-use crate::Circuit;
-// use crate::TableGate;
-// use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+use crate::{signal_name, Circuit, CrossbarMapping, TableGate, MAX_GATES, MAX_ROW};
+use crate::analysis::{self, ActivationLimits};
+use crate::schema::{self, Versioned};
+use crate::tech::Technology;
 
-// Scheduling algorithms
-pub fn compute_asap_schedule(circuit: &mut Circuit) {
-    let mut count = 0;
-    
-    while count < circuit.num_gates {
-        for i in 0..circuit.num_gates {
-            if circuit.gates[i].fanin == 1 {
-                // NOT gate
-                let input_level = get_asap_level(circuit, circuit.gates[i].inputs[0]);
-                if input_level != -1 {
-                    circuit.gates[i].asap_level = input_level + 1;
-                    if input_level + 1 > circuit.max_asap {
-                        circuit.max_asap = input_level + 1;
-                    }
-                    count += 1;
-                }
-            } else if circuit.gates[i].fanin == 2 {
-                // NOR gate
-                let input1_level = get_asap_level(circuit, circuit.gates[i].inputs[0]);
-                let input2_level = get_asap_level(circuit, circuit.gates[i].inputs[1]);
-                
-                let max_level = input1_level.max(input2_level);
-                
-                if input1_level != -1 && input2_level != -1 {
-                    circuit.gates[i].asap_level = max_level + 1;
-                    if max_level + 1 > circuit.max_asap {
-                        circuit.max_asap = max_level + 1;
-                    }
-                    count += 1;
-                }
+// A schedule level, counted from 1
+pub type Level = i32;
+// Position of a gate in `circuit.gates`
+pub type GateId = usize;
+
+// Gates of each level under one of a circuit's schedules, gathered in one
+// pass. Gates keep their order in the circuit within a level, and gates not
+// (yet) scheduled, at levels below 1, belong to none. Like the circuit's
+// signal indices, it holds positions: rebuild it after the gates are reordered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schedule {
+    levels: BTreeMap<Level, Vec<GateId>>,
+    level_of: Vec<Option<Level>>,
+}
+
+impl Schedule {
+    pub fn new(circuit: &Circuit, level: impl Fn(&TableGate) -> Level) -> Self {
+        let mut levels: BTreeMap<Level, Vec<GateId>> = BTreeMap::new();
+        let mut level_of = Vec::with_capacity(circuit.num_gates);
+        for (i, gate) in circuit.gates.iter().take(circuit.num_gates).enumerate() {
+            let l = level(gate);
+            if l >= 1 {
+                levels.entry(l).or_default().push(i);
             }
+            level_of.push(Some(l).filter(|&l| l >= 1));
         }
+        Self { levels, level_of }
+    }
+
+    pub fn asap(circuit: &Circuit) -> Self {
+        Self::new(circuit, |g| g.asap_level)
+    }
+
+    pub fn alap(circuit: &Circuit) -> Self {
+        Self::new(circuit, |g| g.alap_level)
+    }
+
+    pub fn list(circuit: &Circuit) -> Self {
+        Self::new(circuit, |g| g.list_level)
+    }
+
+    // Levels holding at least one gate, in increasing order
+    pub fn levels(&self) -> impl Iterator<Item = (Level, &[GateId])> {
+        self.levels.iter().map(|(&l, gates)| (l, gates.as_slice()))
+    }
+
+    pub fn gates(&self, level: Level) -> &[GateId] {
+        self.levels.get(&level).map_or(&[], |g| g.as_slice())
+    }
+
+    pub fn level_of(&self, gate: GateId) -> Option<Level> {
+        self.level_of.get(gate).copied().flatten()
+    }
+
+    // Last level holding a gate; 0 for an empty schedule
+    pub fn depth(&self) -> Level {
+        self.levels.keys().next_back().copied().unwrap_or(0)
     }
 }
 
-pub fn compute_alap_schedule(circuit: &mut Circuit) {
-    // Initialize PO levels
-    for i in 0..circuit.num_gates {
-        if is_po(circuit, circuit.gates[i].out) {
-            circuit.gates[i].alap_level = 1;
+// The levels of every gate under each schedule, as `--schedule-json` writes
+// them once the schedules and the operand order are final
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleReport {
+    pub circuit: String,
+    pub asap_levels: Level,
+    pub alap_levels: Level,
+    pub list_levels: Level,
+    pub gates: Vec<ScheduledGate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledGate {
+    pub signal: String,
+    pub operands: Vec<String>,
+    pub asap: Level,
+    pub alap: Level,
+    // Unset until a list schedule ran
+    pub list: Option<Level>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub output: bool,
+}
+
+impl ScheduleReport {
+    pub fn new(circuit: &Circuit) -> Self {
+        let gates = circuit.gates_in_original_order().into_iter()
+            .map(|i| &circuit.gates[i])
+            .map(|g| ScheduledGate {
+                signal: signal_name(g.out),
+                operands: g.inputs[..g.fanin].iter().map(|&s| signal_name(s)).collect(),
+                asap: g.asap_level,
+                alap: g.alap_level,
+                list: Some(g.list_level).filter(|&l| l != -1),
+                output: g.is_output,
+            })
+            .collect();
+        Self {
+            circuit: circuit.bench_name.clone(),
+            asap_levels: circuit.max_asap.max(0),
+            alap_levels: circuit.max_alap.max(0),
+            list_levels: circuit.max_list.max(0),
+            gates,
         }
     }
-    
-    // Iterate until all gates have been labeled
-    while !all_alap_labeled(circuit) {
-        for i in 0..circuit.num_gates {
-            update_alap(circuit, i, circuit.gates[i].out);
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string(&Versioned::new(self))? + "\n")
+            .context(format!("Failed to write schedule: {:?}", path))
+    }
+
+    pub fn read_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read schedule: {:?}", path))?;
+        schema::from_str(&text, "schedule").context(format!("Failed to load schedule: {:?}", path))
+    }
+}
+
+// Scheduling algorithms
+// Labels each gate one level past its latest operand, level by level. Gates
+// on or behind a combinational loop never become ready: they keep level -1
+// and an error diagnostic names them and the signals they wait for
+pub fn compute_asap_schedule(circuit: &mut Circuit) {
+    let n = circuit.num_gates;
+    let steps: Vec<ScheduleStep> = Scheduler::Asap.steps(circuit).collect();
+    for gate in circuit.gates.iter_mut().take(n) {
+        gate.asap_level = -1;
+    }
+    circuit.max_asap = 0;
+
+    let mut unlabeled = n;
+    for step in steps {
+        for &i in &step.gates {
+            circuit.gates[i].asap_level = step.level;
         }
+        circuit.max_asap = step.level;
+        unlabeled -= step.gates.len();
     }
-    
-    // Additional iterations to ensure convergence
-    for _ in 0..10 {
-        for i in 0..circuit.num_gates {
-            update_alap(circuit, i, circuit.gates[i].out);
+
+    if unlabeled > 0 {
+        let message = describe_unready(circuit);
+        circuit.diagnostics.error("scheduler", "unready", message);
+    }
+}
+
+// Gates the ASAP schedule left unlabeled
+pub fn unready_gates(circuit: &Circuit) -> Vec<GateId> {
+    (0..circuit.num_gates).filter(|&i| circuit.gates[i].asap_level < 1).collect()
+}
+
+// Fails with the gates ASAP could not schedule, for callers that cannot go
+// on with a partial schedule
+pub fn ensure_scheduled(circuit: &Circuit) -> Result<()> {
+    ensure!(unready_gates(circuit).is_empty(), "{}", describe_unready(circuit));
+    Ok(())
+}
+
+// Gates listed when ASAP leaves some unready, each with its unlabeled operands
+const UNREADY_LISTED: usize = 8;
+
+// "3 gates never became ready, on or behind a combinational loop: n1 (waits
+// for n2), ...; missing signals n1 n2"
+fn describe_unready(circuit: &Circuit) -> String {
+    let unready = unready_gates(circuit);
+    let mut missing: Vec<i32> = Vec::new();
+    let mut listed = Vec::new();
+    for &i in &unready {
+        let gate = &circuit.gates[i];
+        let waits: Vec<i32> = gate.inputs[..gate.fanin].iter()
+            .copied()
+            .filter(|&input| get_asap_level(circuit, input) == -1)
+            .collect();
+        for &signal in &waits {
+            if !missing.contains(&signal) {
+                missing.push(signal);
+            }
+        }
+        if listed.len() < UNREADY_LISTED {
+            let waits: Vec<String> = waits.iter().map(|&s| signal_name(s)).collect();
+            listed.push(format!("{} (waits for {})", signal_name(gate.out), waits.join(", ")));
         }
     }
-    
-    // Correct ALAP levels
-    let mut max_level = 0;
-    for i in 0..circuit.num_gates {
-        if circuit.gates[i].alap_level > max_level {
-            max_level = circuit.gates[i].alap_level;
+    if unready.len() > UNREADY_LISTED {
+        listed.push(format!("{} more", unready.len() - UNREADY_LISTED));
+    }
+    missing.sort_unstable();
+    let mut names: Vec<String> = missing.iter().take(UNREADY_LISTED).map(|&s| signal_name(s)).collect();
+    if missing.len() > UNREADY_LISTED {
+        names.push("...".to_string());
+    }
+    format!("{} of {} gates never became ready, on or behind a combinational loop: {}; missing signals {}",
+        unready.len(), circuit.num_gates, listed.join(", "), names.join(" "))
+}
+
+// ALAP levels in one backward pass over the fanout lists: a gate is labeled
+// once every gate reading it has been, one level before the earliest of them
+// (counted from the outputs, then turned around). Gates on a cycle, which no
+// netlist should have, stay unlabeled
+pub fn compute_alap_schedule(circuit: &mut Circuit) {
+    let n = circuit.num_gates;
+    let mut drivers: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (i, gate) in circuit.gates.iter().take(n).enumerate() {
+        for out in gate.outs() {
+            drivers.entry(out).or_default().push(i);
         }
     }
-    
-    for i in 0..circuit.num_gates {
-        circuit.gates[i].alap_level = max_level - circuit.gates[i].alap_level + 1;
+
+    // Distance from the outputs: 1 for a gate nothing reads, through any of
+    // the signals it drives
+    let mut unlabeled_readers: Vec<usize> = (0..n)
+        .map(|i| circuit.gate_readers(i).count())
+        .collect();
+    let mut ready: Vec<usize> = (0..n).filter(|&i| unlabeled_readers[i] == 0).collect();
+    let mut distance = vec![-1; n];
+    while let Some(i) = ready.pop() {
+        let gate = &circuit.gates[i];
+        distance[i] = 1 + circuit.gate_readers(i).map(|r| distance[r]).max().unwrap_or(0);
+        for input in &gate.inputs[..gate.fanin] {
+            for &d in drivers.get(input).into_iter().flatten() {
+                unlabeled_readers[d] -= 1;
+                if unlabeled_readers[d] == 0 {
+                    ready.push(d);
+                }
+            }
+        }
+    }
+
+    let max_level = distance.iter().copied().max().unwrap_or(0).max(0);
+    for (gate, d) in circuit.gates.iter_mut().zip(distance) {
+        gate.alap_level = if d > 0 { max_level - d + 1 } else { -1 };
     }
-    
     circuit.max_alap = max_level;
 }
 
 pub fn compute_list_schedule(circuit: &mut Circuit) {
-    // Compute mobilities
-    let mut max_level = 0;
+    compute_list_schedule_for(circuit, None);
+}
+
+// Gates per level a list schedule may evaluate on the target array: one row
+// per gate, within the rows a technology can activate at once and the rows
+// its array has free to compute in. Without a technology, the crossbar's rows
+pub fn list_gate_limit(tech: Option<&Technology>) -> usize {
+    match tech {
+        Some(tech) => {
+            let periphery = &tech.periphery;
+            let rows = periphery.rows.unwrap_or(MAX_ROW)
+                .saturating_sub(periphery.reserved_rows.len() + periphery.storage_rows.len());
+            periphery.max_row_activations.min(rows).max(1)
+        }
+        None => MAX_ROW,
+    }
+}
+
+// The largest bound on gates per level that fits the target array. Bounds
+// past the widest ASAP level schedule the same, so the bound stops there
+pub fn list_bound(circuit: &Circuit, tech: Option<&Technology>) -> usize {
+    let widest = Schedule::asap(circuit).levels().map(|(_, gates)| gates.len()).max().unwrap_or(1);
+    list_gate_limit(tech).min(widest)
+}
+
+// One point of the trade-off between gates per level and schedule depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListPoint {
+    pub bound: usize,
+    pub levels: Level,
+    pub max_gates: usize,
+}
+
+// The list schedule at each of `bounds`, leaving the circuit as it is
+pub fn list_tradeoff(circuit: &Circuit, bounds: impl IntoIterator<Item = usize>) -> Vec<ListPoint> {
+    bounds.into_iter()
+        .map(|bound| {
+            let levels = list_levels(circuit, bound);
+            let mut widths: HashMap<Level, usize> = HashMap::new();
+            for &level in &levels {
+                *widths.entry(level).or_default() += 1;
+            }
+            ListPoint {
+                bound,
+                levels: levels.iter().copied().max().unwrap_or(0),
+                max_gates: widths.values().copied().max().unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+// List scheduling with the largest bound on gates per level that fits the
+// target array. Gates are ordered by mobility first, as the mappers expect
+pub fn compute_list_schedule_for(circuit: &mut Circuit, tech: Option<&Technology>) {
     for i in 0..circuit.num_gates {
         circuit.gates[i].mobility = circuit.gates[i].alap_level - circuit.gates[i].asap_level;
-        
-        if circuit.gates[i].asap_level > max_level {
-            max_level = circuit.gates[i].asap_level;
-        }
-    }
-    
-    // Sort gates by mobility (smallest first)
-    circuit.gates.sort_by(|a, b| a.mobility.cmp(&b.mobility));
-    
-    // Find minimal number of gates per level
-    for max_gates in 2..20 {
-        // Reinitialize list levels
-        for i in 0..circuit.num_gates {
-            circuit.gates[i].list_level = -1;
+    }
+    circuit.sort_gates_by_key(|g| g.mobility);
+
+    let bound = list_bound(circuit, tech);
+    let levels = list_levels(circuit, bound);
+    circuit.max_list = levels.iter().copied().max().unwrap_or(0);
+    for (gate, level) in circuit.gates.iter_mut().zip(levels) {
+        gate.list_level = level;
+    }
+    if let Some(tech) = tech.filter(|_| circuit.max_list > circuit.max_asap) {
+        circuit.diagnostics.info("scheduler", "list-bound",
+            format!("{} allows {} gates per level; the list schedule takes {} levels instead of {}",
+                tech.name, bound, circuit.max_list, circuit.max_asap));
+    }
+}
+
+// Splits ASAP levels whose gates, as placed in `mapping`, activate more
+// devices on a wordline or bitline than the limits allow. Gates keep their
+// order (the one the mapper sorted them into) and each level is cut into
+// consecutive runs that fit, so remapping reproduces the same placement with
+// the new levels. A single gate over the limits cannot be split further.
+// ALAP levels move with the added depth. Returns the number of levels added
+pub fn split_overloaded_levels(circuit: &mut Circuit, mapping: &CrossbarMapping, limits: &ActivationLimits) -> i32 {
+    let mut added = 0;
+    let mut level = -1;
+    let mut devices = HashSet::new();
+    let mut rows: HashMap<usize, usize> = HashMap::new();
+    let mut cols: HashMap<usize, usize> = HashMap::new();
+
+    for i in 0..circuit.num_gates {
+        let gate = &circuit.gates[i];
+        let Some(ref placed) = gate.gate_map else { continue };
+        if gate.asap_level != level {
+            level = gate.asap_level;
+            devices.clear();
+            rows.clear();
+            cols.clear();
         }
-        
-        if list_schedule_possible(circuit, max_level, max_gates) {
-            circuit.max_list = max_level;
-            break;
+
+        let cell = mapping.cell(placed.idx as usize, placed.jdx as usize);
+        let mut new: Vec<(usize, usize)> = analysis::activated_devices(cell)
+            .filter(|d| !devices.contains(d))
+            .collect();
+        new.sort_unstable();
+        new.dedup();
+        let fits = |counts: &HashMap<usize, usize>, limit: Option<usize>, line: fn(&(usize, usize)) -> usize| {
+            let Some(limit) = limit else { return true };
+            let mut extra: HashMap<usize, usize> = HashMap::new();
+            for d in &new {
+                *extra.entry(line(d)).or_insert(0) += 1;
+            }
+            extra.iter().all(|(k, n)| counts.get(k).unwrap_or(&0) + n <= limit)
+        };
+        if !devices.is_empty()
+            && (!fits(&rows, limits.wordline, |d| d.0) || !fits(&cols, limits.bitline, |d| d.1))
+        {
+            added += 1;
+            new = analysis::activated_devices(cell).collect();
+            new.sort_unstable();
+            new.dedup();
+            devices.clear();
+            rows.clear();
+            cols.clear();
+        }
+
+        for d in new {
+            *rows.entry(d.0).or_insert(0) += 1;
+            *cols.entry(d.1).or_insert(0) += 1;
+            devices.insert(d);
+        }
+        circuit.gates[i].asap_level += added;
+    }
+
+    if added > 0 {
+        for i in 0..circuit.num_gates {
+            circuit.gates[i].alap_level += added;
         }
+        circuit.max_asap += added;
+        circuit.max_alap += added;
     }
+    added
 }
 
-// Helper functions
-fn get_asap_level(circuit: &Circuit, line_id: i32) -> i32 {
-    if is_pi(circuit, line_id) {
-        return 0;
+// Copies the compact mapping makes of one signal. A NOR sits in its last
+// operand's row, so each NOR that reads the signal as an earlier operand from
+// another row writes a copy of it there, in the NOR's level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyPressure {
+    pub signal: i32,
+    pub fanout: usize,
+    pub copies: usize,
+    // Most copies made in one level, and the first level making that many
+    pub peak: usize,
+    pub peak_level: i32,
+}
+
+// Signals the compact mapping copies under the current ASAP levels, most
+// copied first
+pub fn copy_pressure(circuit: &Circuit) -> Vec<CopyPressure> {
+    let mut levels: HashMap<i32, BTreeMap<i32, usize>> = HashMap::new();
+    for (i, signal) in copied_operands(circuit) {
+        *levels.entry(signal).or_default().entry(circuit.gates[i].asap_level).or_insert(0) += 1;
     }
-    
-    for i in 0..circuit.num_gates {
-        if circuit.gates[i].out == line_id {
-            return circuit.gates[i].asap_level;
-        }
-    }
-    
-    // Error case
-    -1
-}
-
-// fn get_alap_level(circuit: &Circuit, line_id: i32) -> i32 {
-//     if is_po(circuit, line_id) {
-//         return 0;
-//     }
-    
-//     for i in 0..circuit.num_gates {
-//         if circuit.gates[i].out == line_id {
-//             return circuit.gates[i].alap_level;
-//         }
-//     }
-    
-//     // Error case
-//     -1
-// }
-
-fn get_list_level(circuit: &Circuit, line_id: i32) -> i32 {
-    if is_pi(circuit, line_id) {
-        return 0;
-    }
-    
-    for i in 0..circuit.num_gates {
-        if circuit.gates[i].out == line_id {
-            return circuit.gates[i].list_level;
+    let mut pressure: Vec<CopyPressure> = levels.into_iter()
+        .map(|(signal, by_level)| {
+            let (&peak_level, &peak) = by_level.iter()
+                .max_by_key(|&(&level, &n)| (n, Reverse(level)))
+                .unwrap_or((&0, &0));
+            CopyPressure {
+                signal,
+                fanout: circuit.readers(signal).len(),
+                copies: by_level.values().sum(),
+                peak,
+                peak_level,
+            }
+        })
+        .collect();
+    pressure.sort_by_key(|p| (Reverse(p.copies), Reverse(p.peak), p.signal));
+    pressure
+}
+
+// Copies the compact mapping writes in each level, level 1 first
+pub fn copies_per_level(circuit: &Circuit) -> Vec<usize> {
+    let mut copies = vec![0; circuit.max_asap.max(0) as usize];
+    for (i, _) in copied_operands(circuit) {
+        let level = circuit.gates[i].asap_level;
+        if level >= 1 && level <= circuit.max_asap {
+            copies[(level - 1) as usize] += 1;
         }
     }
-    
-    // Error case
-    -1
+    copies
 }
 
-fn is_pi(circuit: &Circuit, line_id: i32) -> bool {
-    for i in 0..circuit.num_gates {
-        if circuit.gates[i].out == line_id {
-            return false;
+// Delays two-input gates within their slack so that no signal is copied more
+// than `budget` times in one level. A gate may move up to the level before its
+// earliest reader, or to the last level if nothing reads it, so the depth
+// stays the same. Rows do not depend on levels: the copies are the same ones,
+// written in other levels. Returns the number of gates delayed
+pub fn smooth_copies(circuit: &mut Circuit, budget: usize) -> usize {
+    if budget == 0 {
+        circuit.diagnostics.info("scheduler", "clamped", "Copy budget 0 raised to 1 copy per signal and level");
+    }
+    let budget = budget.max(1);
+    let mut gates = copied_operands(circuit);
+    gates.retain(|&(i, _)| circuit.gates[i].fanin == 2);
+    let mut copies: HashMap<(i32, i32), usize> = HashMap::new();
+    for &(i, signal) in &gates {
+        *copies.entry((signal, circuit.gates[i].asap_level)).or_insert(0) += 1;
+    }
+
+    // Latest gates first, so readers have settled before their drivers move
+    gates.sort_by_key(|&(i, _)| (Reverse(circuit.gates[i].asap_level), i));
+    let mut delayed = 0;
+    for (i, signal) in gates {
+        let level = circuit.gates[i].asap_level;
+        if copies[&(signal, level)] <= budget {
+            continue;
         }
+        let latest = circuit.gate_readers(i)
+            .map(|r| circuit.gates[r].asap_level - 1)
+            .min()
+            .unwrap_or(circuit.max_asap);
+        let Some(target) = (level + 1..=latest).find(|&l| copies.get(&(signal, l)).copied().unwrap_or(0) < budget) else {
+            continue;
+        };
+        *copies.get_mut(&(signal, level)).unwrap() -= 1;
+        *copies.entry((signal, target)).or_insert(0) += 1;
+        circuit.gates[i].asap_level = target;
+        delayed += 1;
     }
-    
-    true
+    delayed
 }
 
-fn is_po(circuit: &Circuit, line_id: i32) -> bool {
-    for i in 0..circuit.num_gates {
-        for j in 0..circuit.gates[i].fanin {
-            if circuit.gates[i].inputs[j] == line_id {
-                return false;
-            }
+// NORs with an operand the compact mapping copies, with that operand; a gate
+// appears once per copied operand
+fn copied_operands(circuit: &Circuit) -> Vec<(usize, i32)> {
+    let rows = signal_rows(circuit);
+    let row = |signal: i32| rows.get(&signal).copied().unwrap_or(0);
+    (0..circuit.num_gates)
+        .filter(|&i| circuit.gates[i].fanin >= 2)
+        .flat_map(|i| {
+            let (last, others) = circuit.gates[i].inputs[..circuit.gates[i].fanin].split_last().unwrap();
+            others.iter()
+                .filter(|&&signal| row(signal) != row(*last))
+                .map(move |&signal| (i, signal))
+        })
+        .collect()
+}
+
+// Row of each signal in the compact mapping, by the mapper's rules: input k
+// in row k, a NOT in its operand's row and a NOR in its last operand's row.
+// Anything else falls back to row 0 as in the mapper
+fn signal_rows(circuit: &Circuit) -> HashMap<i32, usize> {
+    let mut rows: HashMap<i32, usize> = (0..circuit.num_inputs)
+        .map(|k| ((MAX_GATES + k) as i32, k))
+        .collect();
+    let schedule = Schedule::asap(circuit);
+    for &i in schedule.levels().flat_map(|(_, gates)| gates) {
+        let gate = &circuit.gates[i];
+        let operand = gate.inputs[gate.fanin.max(1) - 1];
+        let row = rows.get(&operand).copied().unwrap_or(0);
+        // The first driver of a signal is the one the mapper reads
+        for out in gate.outs().filter(|&s| circuit.driver_index(s) == Some(i)) {
+            rows.insert(out, row);
         }
     }
-    
-    true
+    rows
 }
 
-fn all_alap_labeled(circuit: &Circuit) -> bool {
-    for i in 0..circuit.num_gates {
-        if circuit.gates[i].alap_level == -1 {
-            return false;
+// Helper functions
+// Level of a signal's driver; primary inputs (signals without a driver) are at level 0
+fn get_asap_level(circuit: &Circuit, line_id: i32) -> i32 {
+    circuit.driver(line_id).map_or(0, |g| g.asap_level)
+}
+
+// Level of every gate when each level takes at most `bound` of the gates
+// whose operands are ready, the ones with the earliest ALAP level first
+fn list_levels(circuit: &Circuit, bound: usize) -> Vec<Level> {
+    let mut levels = vec![-1; circuit.num_gates];
+    for step in Scheduler::List(bound).steps(circuit) {
+        for i in step.gates {
+            levels[i] = step.level;
         }
     }
-    
-    true
+    levels
+}
+
+// The schedulers, run a level at a time for callers that show or animate
+// the schedule as it grows. ASAP takes every gate whose operands are ready;
+// List(bound) takes at most `bound` of them, the earliest ALAP level first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheduler {
+    Asap,
+    List(usize),
+}
+
+// One level of a schedule: the gates it takes, by index in `circuit.gates`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleStep {
+    pub level: Level,
+    pub gates: Vec<GateId>,
+}
+
+// A schedule in progress over a circuit's gates in their current order. The
+// circuit's levels are left alone; the steps only read the ALAP levels
+pub struct ScheduleSteps<'a> {
+    circuit: &'a Circuit,
+    scheduler: Scheduler,
+    // Operands of each gate not scheduled yet
+    waiting: Vec<usize>,
+    readers: Vec<Vec<GateId>>,
+    ready: BTreeSet<(Level, GateId)>,
+    level: Level,
 }
 
-fn update_alap(circuit: &mut Circuit, index: usize, line_id: i32) {
-    for j in 0..circuit.num_gates {
-        for k in 0..circuit.gates[j].fanin {
-            if line_id == circuit.gates[j].inputs[k] && circuit.gates[j].alap_level != -1 {
-                if circuit.gates[index].alap_level <= circuit.gates[j].alap_level {
-                    circuit.gates[index].alap_level = circuit.gates[j].alap_level + 1;
+impl Scheduler {
+    pub fn steps(self, circuit: &Circuit) -> ScheduleSteps<'_> {
+        let gates = &circuit.gates[..circuit.num_gates];
+        let mut waiting = vec![0usize; gates.len()];
+        let mut readers: Vec<Vec<GateId>> = vec![Vec::new(); gates.len()];
+        for (i, gate) in gates.iter().enumerate() {
+            for &input in &gate.inputs[..gate.fanin] {
+                if let Some(driver) = circuit.driver_index(input) {
+                    waiting[i] += 1;
+                    readers[driver].push(i);
                 }
             }
         }
+        let mut steps = ScheduleSteps { circuit, scheduler: self, waiting, readers, ready: BTreeSet::new(), level: 0 };
+        // ASAP leaves a gate without operands unlabeled
+        steps.ready = (0..gates.len())
+            .filter(|&i| steps.waiting[i] == 0 && (gates[i].fanin > 0 || self != Scheduler::Asap))
+            .map(|i| steps.key(i))
+            .collect();
+        steps
     }
 }
 
-fn list_schedule_possible(circuit: &mut Circuit, max_level: i32, max_gates: i32) -> bool {
-    let mut ngates = 0;
-    let mut max_level_assigned = 0;
-    
-    while ngates < circuit.num_gates {
-        let mut gates_in_level = 0;
-        let mut flag = false;
-        
-        for i in 0..circuit.num_gates {
-            if circuit.gates[i].fanin == 1 {
-                // NOT gate
-                let input_level = get_list_level(circuit, circuit.gates[i].inputs[0]);
-                
-                if input_level != -1 {
-                    circuit.gates[i].list_level = input_level + 1;
-                    if max_level_assigned < input_level + 1 {
-                        max_level_assigned = input_level + 1;
-                    }
-                    gates_in_level += 1;
-                    ngates += 1;
-                    flag = true;
-                    
-                    if gates_in_level == max_gates {
-                        break; // Current level filled up
-                    }
-                }
-            } else if circuit.gates[i].fanin == 2 {
-                // NOR gate
-                let input1_level = get_list_level(circuit, circuit.gates[i].inputs[0]);
-                let input2_level = get_list_level(circuit, circuit.gates[i].inputs[1]);
-                
-                let max_input_level = input1_level.max(input2_level);
-                
-                if input1_level != -1 && input2_level != -1 {
-                    circuit.gates[i].list_level = max_input_level + 1;
-                    if max_level_assigned < max_input_level + 1 {
-                        max_level_assigned = max_input_level + 1;
-                    }
-                    gates_in_level += 1;
-                    ngates += 1;
-                    flag = true;
-                    
-                    if gates_in_level == max_gates {
-                        break; // Current level filled up
-                    }
+impl ScheduleSteps<'_> {
+    // Levels scheduled so far
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    // Gates whose operands are all scheduled, waiting for a level
+    pub fn ready(&self) -> impl Iterator<Item = GateId> + '_ {
+        self.ready.iter().map(|&(_, i)| i)
+    }
+
+    fn key(&self, i: GateId) -> (Level, GateId) {
+        match self.scheduler {
+            Scheduler::Asap => (0, i),
+            Scheduler::List(_) => (self.circuit.gates[i].alap_level, i),
+        }
+    }
+}
+
+impl Iterator for ScheduleSteps<'_> {
+    type Item = ScheduleStep;
+
+    fn next(&mut self) -> Option<ScheduleStep> {
+        if self.ready.is_empty() {
+            return None;
+        }
+        self.level += 1;
+        let bound = match self.scheduler {
+            Scheduler::Asap => usize::MAX,
+            Scheduler::List(bound) => bound.max(1),
+        };
+        let gates: Vec<GateId> = (0..bound).map_while(|_| self.ready.pop_first()).map(|(_, i)| i).collect();
+        for &i in &gates {
+            for k in 0..self.readers[i].len() {
+                let reader = self.readers[i][k];
+                self.waiting[reader] -= 1;
+                if self.waiting[reader] == 0 {
+                    let key = self.key(reader);
+                    self.ready.insert(key);
                 }
             }
         }
-        
-        if !flag {
-            return false; // List schedule could not be formed
-        }
+        Some(ScheduleStep { level: self.level, gates })
     }
-    
-    max_level_assigned == max_level
-}
\ No newline at end of file
+}
@@ -0,0 +1,51 @@
+//schema/mod.rs
+use anyhow::{Context, Result, ensure};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+// Layout version of every JSON artifact: programs, metrics, schedules,
+// mappings, provenance, diagnostics and the results stream. It goes up when
+// a field is renamed, removed or changes meaning; added fields leave it alone
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Artifacts written before the field existed. Their layout is version 1's
+pub const UNVERSIONED: u32 = 0;
+
+// An artifact with `schema_version` as its first field
+#[derive(Debug, Serialize)]
+pub struct Versioned<'a, T: Serialize> {
+    schema_version: u32,
+    #[serde(flatten)]
+    artifact: &'a T,
+}
+
+impl<'a, T: Serialize> Versioned<'a, T> {
+    pub fn new(artifact: &'a T) -> Self {
+        Self { schema_version: SCHEMA_VERSION, artifact }
+    }
+}
+
+// The schema version of a JSON artifact, UNVERSIONED if it has none
+pub fn version_of(value: &Value) -> Result<u32> {
+    match value.get("schema_version") {
+        None => Ok(UNVERSIONED),
+        Some(version) => version.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .with_context(|| format!("schema_version {} is not a version number", version)),
+    }
+}
+
+// Reads a JSON artifact of any version up to SCHEMA_VERSION into the current
+// layout; `kind` names it in errors. Unversioned artifacts read as version 1.
+// A layout change converts the older versions here, before deserializing
+pub fn from_str<T: DeserializeOwned>(text: &str, kind: &str) -> Result<T> {
+    let mut value: Value = serde_json::from_str(text).context(format!("Malformed JSON {}", kind))?;
+    let version = version_of(&value).context(format!("Malformed JSON {}", kind))?;
+    ensure!(version <= SCHEMA_VERSION,
+        "JSON {} has schema version {}, but this delphi reads up to version {}", kind, version, SCHEMA_VERSION);
+    if let Some(object) = value.as_object_mut() {
+        object.remove("schema_version");
+    }
+    serde_json::from_value(value).context(format!("Malformed JSON {} (schema version {})", kind, version))
+}
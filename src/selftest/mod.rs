@@ -0,0 +1,194 @@
+//selftest/mod.rs
+use std::fmt;
+use anyhow::{Context, Result};
+
+use crate::mapper::{self, CompactPlan};
+use crate::program::{Cell, Program};
+use crate::simulator::{self, InputVector};
+use crate::{parallel, scheduler, signal_name, Circuit, CrossbarMapping, MAX_COL};
+
+// What one path built, one line per level, cell or op, so that two paths
+// can be told apart by their first differing line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot(pub Vec<String>);
+
+impl Snapshot {
+    // `n5 asap 2 alap 3 list 2`, per gate in netlist order
+    pub fn schedule(circuit: &Circuit) -> Self {
+        Self(circuit.gates[..circuit.num_gates].iter()
+            .map(|g| format!("{} asap {} alap {} list {}", signal_name(g.out), g.asap_level, g.alap_level, g.list_level))
+            .collect())
+    }
+
+    // The occupied cells with their operands, then the cell of every gate
+    pub fn mapping(circuit: &Circuit, mapping: &CrossbarMapping) -> Self {
+        let mut lines = vec![format!("size {}x{}", mapping.max_idx + 1, mapping.max_jdx + 1)];
+        lines.extend(mapping.occupied_cells().map(|c| {
+            let operands: Vec<String> = c.inputs.iter().flatten().map(|o| Cell::of(o).to_string()).collect();
+            format!("{} {}{} <- {}", Cell::of(c), signal_name(c.value), if c.is_copy { " copy" } else { "" }, operands.join(" "))
+        }));
+        lines.extend(circuit.gates[..circuit.num_gates].iter().map(|g| match &g.gate_map {
+            Some(cell) => format!("{} at {}", signal_name(g.out), Cell::of(cell)),
+            None => format!("{} unplaced", signal_name(g.out)),
+        }));
+        Self(lines)
+    }
+
+    pub fn program(program: &Program) -> Self {
+        let mut lines = vec![format!("{} {}x{}", program.primitive, program.rows, program.cols)];
+        lines.extend(program.ops().map(|(level, op)| format!("{} {:?}", level, op)));
+        Self(lines)
+    }
+
+    // Where `other` first departs from this one
+    pub fn difference(&self, other: &Snapshot) -> Option<String> {
+        match self.0.iter().zip(&other.0).position(|(a, b)| a != b) {
+            Some(line) => Some(format!("line {}: `{}` against `{}`", line + 1, self.0[line], other.0[line])),
+            None if self.0.len() != other.0.len() => Some(format!("{} lines against {}", self.0.len(), other.0.len())),
+            None => None,
+        }
+    }
+}
+
+// One comparison or simulation and what went wrong, if anything
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub failure: Option<String>,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failure {
+            Some(failure) => write!(f, "{} differs at {}", self.name, failure),
+            None => write!(f, "{} matches", self.name),
+        }
+    }
+}
+
+// The sequential flow against the parallel one on pools of each size: the
+// compact mapping and the streamed plan program, and the schedule for good
+// measure, which has only the sequential implementation. Every program is
+// then run on the same vectors as the netlist
+#[derive(Debug, Clone)]
+pub struct SelfTest {
+    pub checks: Vec<Check>,
+    // Whether the parallel mapper had a plan to build from; without one it
+    // falls back to the sequential mapper
+    pub planned: bool,
+    // Whether the naive mapping fits the crossbar, and so was simulated
+    pub naive: bool,
+    pub programs: usize,
+    pub vectors: usize,
+}
+
+impl SelfTest {
+    pub fn run(circuit: &Circuit, threads: &[usize], vectors: usize, seed: u64) -> Result<Self> {
+        let vectors = simulator::random_vectors(circuit.num_inputs, vectors, seed);
+        let expected = simulator::simulate_netlist(circuit, &vectors)?;
+        let mut test = SelfTest { checks: Vec::new(), planned: false, naive: false, programs: 0, vectors: vectors.len() };
+
+        let mut scheduled = circuit.clone();
+        schedule(&mut scheduled)?;
+        let schedule_reference = Snapshot::schedule(&scheduled);
+        // One column per gate after the inputs
+        test.naive = scheduled.input_line_count() + scheduled.num_gates <= MAX_COL;
+        if test.naive {
+            let mut naive = scheduled.clone();
+            let mapping = mapper::create_naive_mapping(&mut naive);
+            let program = Program::from_mapping(&naive, &mapping).context("No program for the naive mapping")?;
+            test.simulate("naive program", &program, &vectors, &expected)?;
+        }
+        let mut sequential = scheduled.clone();
+        let mapping = mapper::create_compact_mapping(&mut sequential);
+        let mapping_reference = Snapshot::mapping(&sequential, &mapping);
+        let compact = Program::from_mapping(&sequential, &mapping).context("No program for the compact mapping")?;
+        let program_reference = Snapshot::program(&compact);
+        test.simulate("compact program", &compact, &vectors, &expected)?;
+        test.planned = CompactPlan::new(&sequential).is_some();
+
+        for &count in threads {
+            let pool = parallel::thread_pool(count)?;
+            let on = |what: &str| format!("{} on {} threads", what, count);
+
+            let mut rescheduled = circuit.clone();
+            pool.install(|| schedule(&mut rescheduled))?;
+            test.compare(on("schedule"), &schedule_reference, &Snapshot::schedule(&rescheduled));
+
+            let mut parallel = scheduled.clone();
+            let mapping = parallel::create_compact_mapping_in(&pool, &mut parallel);
+            test.compare(on("compact mapping"), &mapping_reference, &Snapshot::mapping(&parallel, &mapping));
+            let program = Program::from_mapping(&parallel, &mapping).context("No program for the parallel compact mapping")?;
+            test.compare(on("compact program"), &program_reference, &Snapshot::program(&program));
+            test.simulate(&on("compact program"), &program, &vectors, &expected)?;
+
+            // What `--stream` writes, without the file
+            let mut streamed = scheduled.clone();
+            let program = pool.install(|| stream_program(&mut streamed))?;
+            test.compare(on("streamed program"), &program_reference, &Snapshot::program(&program));
+            test.simulate(&on("streamed program"), &program, &vectors, &expected)?;
+        }
+        Ok(test)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|c| c.failure.is_some())
+    }
+
+    fn compare(&mut self, name: String, reference: &Snapshot, snapshot: &Snapshot) {
+        self.checks.push(Check { name, failure: reference.difference(snapshot) });
+    }
+
+    fn simulate(&mut self, name: &str, program: &Program, vectors: &[InputVector], expected: &[Vec<bool>]) -> Result<()> {
+        let outputs = simulator::simulate_program(program, vectors)?;
+        let failure = outputs.iter().zip(expected).position(|(got, want)| got != want).map(|v| {
+            let bits = |bits: &[bool]| bits.iter().map(|&b| if b { '1' } else { '0' }).collect::<String>();
+            format!("vector {} ({}): {} against the netlist's {}", v, bits(&vectors[v]), bits(&outputs[v]), bits(&expected[v]))
+        });
+        self.checks.push(Check { name: format!("simulation of the {}", name), failure });
+        self.programs += 1;
+        Ok(())
+    }
+}
+
+// `12 checks passed, 64 vectors through 10 programs`
+impl fmt::Display for SelfTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed = self.failures().count();
+        if failed == 0 {
+            write!(f, "{} checks passed", self.checks.len())?;
+        } else {
+            write!(f, "{} of {} checks failed", failed, self.checks.len())?;
+        }
+        write!(f, ", {} vectors through {} programs", self.vectors, self.programs)?;
+        if !self.naive {
+            write!(f, "; the naive mapping does not fit the crossbar")?;
+        }
+        if !self.planned {
+            write!(f, "; no compact plan, so the parallel mapper ran the sequential one")?;
+        }
+        Ok(())
+    }
+}
+
+fn schedule(circuit: &mut Circuit) -> Result<()> {
+    scheduler::compute_asap_schedule(circuit);
+    scheduler::ensure_scheduled(circuit).with_context(|| format!("Failed to schedule {}", circuit.bench_name))?;
+    scheduler::compute_alap_schedule(circuit);
+    scheduler::compute_list_schedule(circuit);
+    Ok(())
+}
+
+fn stream_program(circuit: &mut Circuit) -> Result<Program> {
+    for gate in &mut circuit.gates[..circuit.num_gates] {
+        gate.gate_map = None;
+    }
+    circuit.sort_gates_by_key(|g| g.asap_level);
+    match CompactPlan::new(circuit) {
+        Some(plan) => Program::from_plan(circuit, &plan, None),
+        None => {
+            let mapping = mapper::create_compact_mapping(circuit);
+            Program::from_mapping(circuit, &mapping)
+        }
+    }
+}
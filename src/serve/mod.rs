@@ -0,0 +1,462 @@
+//serve/mod.rs
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::RunMetrics;
+use crate::schema::Versioned;
+
+// Largest request body taken, netlist included
+pub const MAX_BODY: usize = 64 << 20;
+
+// Largest request line and headers taken, together
+pub const MAX_HEAD: usize = 16 << 10;
+
+// Options of `process` a job may give. They change how the netlist is
+// processed and what is written, and name no command to run or file to read
+// outside the job; the runner keeps --bundle under the job's directory
+pub const JOB_OPTIONS: &[&str] = &[
+    "--preset", "--parallel", "--stream", "--cone", "--top", "--pass", "--max-fanout",
+    "--keep-operand-order", "--cluster", "--duplicate", "--placement", "--max-span",
+    "--keep-unused-inputs", "--bind", "--primitive", "--inverted-readout", "--encoding",
+    "--copy-budget", "--reuse-copies", "--max-copy-overhead", "--fold-copies", "--hotspots",
+    "--annotate", "--legacy-compat", "--emit", "--max-ops", "--timeline", "--overlay",
+    "--decomposed", "--pipeline-tiles", "--batch", "--replicate", "--rotations",
+    "--wordline-limit", "--bitline-limit", "--diagnostics-json", "--metrics-json", "--cost",
+    "--schedule-json", "--mapping-json", "--provenance-json", "--layout", "--overwrite",
+    "--no-clobber", "--bundle",
+];
+
+// Refuses every option of a job that JOB_OPTIONS leaves out, by its name
+// before any '='. Values are skipped, unless they read as an option
+pub fn check_options(options: &[String]) -> Result<()> {
+    for option in options.iter().filter(|o| o.starts_with('-')) {
+        let name = option.split_once('=').map_or(option.as_str(), |(name, _)| name);
+        ensure!(JOB_OPTIONS.contains(&name), "A job cannot give '{}'", name);
+    }
+    Ok(())
+}
+
+// Runs the pipeline for the service. `check` rejects options the pipeline
+// would not take before a job is queued; `run` processes the netlist with
+// its artifacts under `dir` and returns what each circuit measured
+pub trait Runner: Send + Sync + 'static {
+    fn check(&self, options: &[String]) -> Result<()>;
+    fn run(&self, netlist: &Path, options: &[String], dir: &Path) -> Result<Vec<RunMetrics>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+// A submitted netlist as the API reports it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub name: String,
+    pub options: Vec<String>,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // Paths under the job's artifact directory, once it ran
+    pub artifacts: Vec<String>,
+    #[serde(skip)]
+    pub metrics: Vec<RunMetrics>,
+}
+
+// The body of `POST /jobs`: the netlist's file name, whose extension picks
+// its format, its text and the options `process` takes besides -o
+#[derive(Debug, Clone, Deserialize)]
+pub struct Submission {
+    pub name: String,
+    pub netlist: String,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    // Only a JSON submission is taken: a browser sends one across origins
+    // only after a preflight the server never answers
+    fn is_json(&self) -> bool {
+        self.content_type.as_deref()
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|media| media.trim().eq_ignore_ascii_case("application/json"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        let body = serde_json::to_vec_pretty(&Versioned::new(value)).unwrap_or_default();
+        Self { status, content_type: "application/json", body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        #[derive(Serialize)]
+        struct Error {
+            error: String,
+        }
+        Self::json(status, &Error { error: message.into() })
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+// Jobs of one server under `root`, each in `job<id>/` with the netlist in
+// `input/` and the artifacts in `artifacts/`. A single worker runs them in
+// the order they came
+pub struct Service<R: Runner> {
+    root: PathBuf,
+    runner: R,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    queue: Mutex<Sender<u64>>,
+}
+
+impl<R: Runner> Service<R> {
+    pub fn start(root: &Path, runner: R) -> Result<Arc<Self>> {
+        fs::create_dir_all(root).context(format!("Failed to create directory: {:?}", root))?;
+        let (queue, pending) = mpsc::channel::<u64>();
+        let service = Arc::new(Self { root: root.to_path_buf(), runner, jobs: Mutex::new(BTreeMap::new()), queue: Mutex::new(queue) });
+        let worker = Arc::clone(&service);
+        thread::spawn(move || {
+            for id in pending {
+                worker.run(id);
+            }
+        });
+        Ok(service)
+    }
+
+    fn job_dir(&self, id: u64) -> PathBuf {
+        self.root.join(format!("job{}", id))
+    }
+
+    pub fn job(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn submit(&self, submission: Submission) -> Result<Job> {
+        ensure!(is_file_name(&submission.name), "Netlist name '{}' is not a plain file name", submission.name);
+        self.runner.check(&submission.options)?;
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = jobs.keys().next_back().map_or(1, |last| last + 1);
+        let dir = self.job_dir(id);
+        if dir.exists() {
+            fs::remove_dir_all(&dir).context(format!("Failed to clear {:?}", dir))?;
+        }
+        fs::create_dir_all(dir.join("input")).context(format!("Failed to create directory: {:?}", dir))?;
+        fs::write(dir.join("input").join(&submission.name), &submission.netlist)
+            .context(format!("Failed to write the netlist of job {}", id))?;
+        let job = Job {
+            id,
+            name: submission.name,
+            options: submission.options,
+            status: JobStatus::Queued,
+            error: None,
+            artifacts: Vec::new(),
+            metrics: Vec::new(),
+        };
+        jobs.insert(id, job.clone());
+        self.queue.lock().unwrap().send(id).context("The job worker has stopped")?;
+        Ok(job)
+    }
+
+    fn update<F: FnOnce(&mut Job)>(&self, id: u64, change: F) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            change(job);
+        }
+    }
+
+    // A run that fails or panics fails its job and leaves the worker running
+    fn run(&self, id: u64) {
+        let Some(job) = self.job(id) else { return };
+        self.update(id, |job| job.status = JobStatus::Running);
+        let dir = self.job_dir(id);
+        let artifacts = dir.join("artifacts");
+        let netlist = dir.join("input").join(&job.name);
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| self.runner.run(&netlist, &job.options, &artifacts)))
+            .unwrap_or_else(|panic| {
+                let message = panic.downcast_ref::<String>().cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                Err(anyhow::anyhow!("The run panicked: {}", message))
+            });
+        let mut listed = Vec::new();
+        list_files(&artifacts, &artifacts, &mut listed);
+        listed.sort();
+        self.update(id, |job| {
+            job.artifacts = listed;
+            match outcome {
+                Ok(metrics) => (job.status, job.metrics) = (JobStatus::Done, metrics),
+                Err(e) => (job.status, job.error) = (JobStatus::Failed, Some(format!("{:#}", e))),
+            }
+        });
+    }
+
+    // Routes a request:
+    //   GET  /health                   the server is up
+    //   POST /jobs                     queue a Submission
+    //   GET  /jobs                     every job
+    //   GET  /jobs/<id>                one job
+    //   GET  /jobs/<id>/metrics        what each circuit measured, once done
+    //   GET  /jobs/<id>/artifacts/<p>  one of the job's artifacts
+    pub fn handle(&self, request: &Request) -> Response {
+        let path = request.path.split('?').next().unwrap_or("");
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["health"]) => {
+                #[derive(Serialize)]
+                struct Health {
+                    status: &'static str,
+                    version: &'static str,
+                }
+                Response::json(200, &Health { status: "ok", version: env!("CARGO_PKG_VERSION") })
+            }
+            ("POST", ["jobs"]) if !request.is_json() => {
+                Response::error(415, "A submission is sent with Content-Type: application/json")
+            }
+            ("POST", ["jobs"]) => match serde_json::from_slice::<Submission>(&request.body) {
+                Err(e) => Response::error(400, format!("Malformed submission: {}", e)),
+                Ok(submission) => match self.submit(submission) {
+                    Ok(job) => Response::json(202, &job),
+                    Err(e) => Response::error(400, format!("{:#}", e)),
+                },
+            },
+            ("GET", ["jobs"]) => {
+                #[derive(Serialize)]
+                struct Jobs {
+                    jobs: Vec<Job>,
+                }
+                let jobs = self.jobs.lock().unwrap().values().cloned().collect();
+                Response::json(200, &Jobs { jobs })
+            }
+            ("GET", ["jobs", id, rest @ ..]) => {
+                let Some(job) = id.parse().ok().and_then(|id| self.job(id)) else {
+                    return Response::error(404, format!("No job {}", id));
+                };
+                match rest {
+                    [] => Response::json(200, &job),
+                    ["metrics"] if job.status != JobStatus::Done => {
+                        Response::error(409, format!("Job {} is {}, not done", job.id, job.status.name()))
+                    }
+                    ["metrics"] => {
+                        // A list has no fields to sit beside schema_version
+                        #[derive(Serialize)]
+                        struct Metrics<'a> {
+                            circuits: &'a [RunMetrics],
+                        }
+                        Response::json(200, &Metrics { circuits: &job.metrics })
+                    }
+                    ["artifacts", artifact @ ..] => {
+                        let artifact = artifact.join("/");
+                        if !job.artifacts.contains(&artifact) {
+                            return Response::error(404, format!("Job {} has no artifact {}", job.id, artifact));
+                        }
+                        match fs::read(self.job_dir(job.id).join("artifacts").join(&artifact)) {
+                            Ok(body) => Response { status: 200, content_type: content_type(&artifact), body },
+                            Err(e) => Response::error(500, format!("Failed to read {}: {}", artifact, e)),
+                        }
+                    }
+                    _ => Response::error(404, format!("No route {}", path)),
+                }
+            }
+            (_, ["health"] | ["jobs", ..]) => Response::error(405, format!("{} is not allowed on {}", request.method, path)),
+            _ => Response::error(404, format!("No route {}", path)),
+        }
+    }
+}
+
+// `path` under a job's directory `dir`. Only plain names are taken, so it
+// cannot leave `dir` through '..', a root or a drive
+pub fn job_path(dir: &Path, path: &Path) -> Result<PathBuf> {
+    let mut joined = dir.to_path_buf();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => joined.push(name),
+            Component::CurDir => {}
+            _ => anyhow::bail!("{:?} leaves the job's directory; give a relative path without '..'", path),
+        }
+    }
+    ensure!(joined.starts_with(dir) && joined != dir, "{:?} names no file in the job's directory", path);
+    Ok(joined)
+}
+
+fn is_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
+// Files under `dir`, as paths relative to `base` with '/' separators
+fn list_files(base: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            list_files(base, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+            files.push(parts.join("/"));
+        }
+    }
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit('.').next() {
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("bin") | Some("gz") => "application/octet-stream",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+// Why a request could not be read, with the status it is answered with
+#[derive(Debug, thiserror::Error)]
+pub enum RequestError {
+    #[error("A body of {0} bytes is over the {MAX_BODY} byte limit")]
+    BodyTooLarge(usize),
+    #[error("The request line and headers are over the {MAX_HEAD} byte limit")]
+    HeadTooLarge,
+    #[error("{0:#}")]
+    Malformed(anyhow::Error),
+}
+
+impl RequestError {
+    pub fn status(&self) -> u16 {
+        match self {
+            RequestError::BodyTooLarge(_) => 413,
+            RequestError::HeadTooLarge => 431,
+            RequestError::Malformed(_) => 400,
+        }
+    }
+}
+
+impl From<anyhow::Error> for RequestError {
+    fn from(e: anyhow::Error) -> Self {
+        RequestError::Malformed(e)
+    }
+}
+
+// Reads one HTTP/1.1 request; the body is taken by Content-Length
+pub fn read_request<R: Read>(stream: R) -> Result<Request, RequestError> {
+    let mut reader = BufReader::new(stream);
+    let mut head = MAX_HEAD;
+    let mut line = String::new();
+    read_head_line(&mut reader, &mut line, &mut head, "request line")?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(anyhow::anyhow!("Malformed request line '{}'", line.trim_end()).into());
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let (mut length, mut content_type) = (0, None);
+    loop {
+        line.clear();
+        read_head_line(&mut reader, &mut line, &mut head, "request headers")?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().context(format!("Malformed Content-Length '{}'", value.trim()))?;
+            } else if name.trim().eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_string());
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(RequestError::BodyTooLarge(length));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).context("Failed to read the request body")?;
+    Ok(Request { method, path, content_type, body })
+}
+
+// Reads one line of the request's head out of the `left` bytes it may still take
+fn read_head_line<R: BufRead>(reader: &mut R, line: &mut String, left: &mut usize, what: &str) -> Result<(), RequestError> {
+    let read = reader.take(*left as u64).read_line(line).context(format!("Failed to read the {}", what))?;
+    *left -= read;
+    if *left == 0 && !line.ends_with('\n') {
+        return Err(RequestError::HeadTooLarge);
+    }
+    Ok(())
+}
+
+pub fn write_response<W: Write>(mut stream: W, response: &Response) -> Result<()> {
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status, response.reason(), response.content_type, response.body.len())?;
+    stream.write_all(&response.body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+// Answers connections on `listener` until the process ends, one thread per
+// connection and one request per connection
+pub fn serve<R: Runner>(listener: TcpListener, root: &Path, runner: R) -> Result<()> {
+    let service = Service::start(root, runner)?;
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let service = Arc::clone(&service);
+        thread::spawn(move || answer(&service, stream));
+    }
+    Ok(())
+}
+
+fn answer<R: Runner>(service: &Service<R>, stream: TcpStream) {
+    let response = match read_request(&stream) {
+        Ok(request) => service.handle(&request),
+        Err(e) => Response::error(e.status(), e.to_string()),
+    };
+    // The client may have gone; nothing is left to tell it
+    let _ = write_response(&stream, &response);
+}
@@ -0,0 +1,614 @@
+//simulator/mod.rs
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, bail, ensure};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::program::{Cell, MicroOp, Program};
+use crate::{signal_name, Circuit, CrossbarMapping, Primitive, MAX_GATES};
+
+// Circuits with more inputs than this must be simulated with random vectors
+pub const MAX_EXHAUSTIVE_INPUTS: usize = 20;
+
+// One bit per primary input, x0 first
+pub type InputVector = Vec<bool>;
+// One bit per primary output, in the order returned by `output_signals`
+pub type OutputVector = Vec<bool>;
+
+// Primary output signals of the circuit, sorted by signal id
+pub fn output_signals(circuit: &Circuit) -> Vec<i32> {
+    let mut outputs: Vec<i32> = circuit.gates.iter()
+        .take(circuit.num_gates)
+        .flat_map(|g| g.primary_outputs())
+        .chain(circuit.inverted_outputs.keys().copied())
+        .collect();
+
+    // Netlists without OUT_BIAS markers: fall back to signals nobody consumes
+    if outputs.is_empty() {
+        outputs = circuit.gates.iter()
+            .take(circuit.num_gates)
+            .flat_map(|g| g.outs())
+            .filter(|&out| circuit.readers(out).is_empty())
+            .collect();
+    }
+
+    outputs.sort_unstable();
+    outputs
+}
+
+// Gate indices in an order where every gate comes after its drivers
+pub fn topological_order(circuit: &Circuit) -> Result<Vec<usize>> {
+    let mut pending = vec![0usize; circuit.num_gates];
+    let mut consumers: Vec<Vec<usize>> = vec![Vec::new(); circuit.num_gates];
+    for (i, gate) in circuit.gates.iter().take(circuit.num_gates).enumerate() {
+        for &input in &gate.inputs[..gate.fanin] {
+            if input >= MAX_GATES as i32 {
+                continue;
+            }
+            match circuit.drivers.get(&input) {
+                Some(&d) => {
+                    pending[i] += 1;
+                    consumers[d].push(i);
+                }
+                None => bail!("Signal {} used by {} is never driven",
+                    signal_name(input), signal_name(gate.out)),
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..circuit.num_gates).filter(|&i| pending[i] == 0).collect();
+    let mut head = 0;
+    while head < order.len() {
+        let i = order[head];
+        head += 1;
+        for &c in &consumers[i] {
+            pending[c] -= 1;
+            if pending[c] == 0 {
+                order.push(c);
+            }
+        }
+    }
+
+    if order.len() != circuit.num_gates {
+        bail!("Netlist contains a combinational loop ({} gates unreachable)",
+            circuit.num_gates - order.len());
+    }
+
+    Ok(order)
+}
+
+// Number of vectors evaluated per pass, one per bit of a machine word
+pub const LANES: usize = 64;
+
+// Netlist flattened into slot-indexed operations for word-level evaluation.
+// Slots 0..num_inputs hold primary inputs, the rest hold gate outputs.
+pub struct CompiledNetlist {
+    ops: Vec<(usize, Vec<usize>)>,
+    primitive: Primitive,
+    num_inputs: usize,
+    num_slots: usize,
+    signals: Vec<i32>,
+    outputs: Vec<usize>,
+    // Inputs the gates read as their complement
+    complemented: Vec<usize>,
+}
+
+impl CompiledNetlist {
+    pub fn new(circuit: &Circuit) -> Result<Self> {
+        let order = topological_order(circuit)?;
+
+        let mut slot_of = HashMap::with_capacity(circuit.num_gates);
+        let mut signals: Vec<i32> = (0..circuit.num_inputs)
+            .map(|i| (MAX_GATES + i) as i32)
+            .collect();
+        // A multi-output gate's signals share its slot
+        for &i in &order {
+            for out in circuit.gates[i].outs() {
+                slot_of.insert(out, signals.len());
+            }
+            signals.push(circuit.gates[i].out);
+        }
+
+        let mut ops = Vec::with_capacity(order.len());
+        for &i in &order {
+            let gate = &circuit.gates[i];
+            let mut srcs = Vec::with_capacity(gate.fanin);
+            for &input in &gate.inputs[..gate.fanin] {
+                if input >= MAX_GATES as i32 {
+                    let idx = (input - MAX_GATES as i32) as usize;
+                    if idx >= circuit.num_inputs {
+                        bail!("Input {} is outside the primary input range", signal_name(input));
+                    }
+                    srcs.push(idx);
+                } else {
+                    srcs.push(slot_of[&input]);
+                }
+            }
+            ops.push((slot_of[&gate.out], srcs));
+        }
+        // Outputs read out inverted evaluate as the NOT they replace
+        for (&output, &source) in &circuit.inverted_outputs {
+            let src = if source >= MAX_GATES as i32 { (source - MAX_GATES as i32) as usize } else { slot_of[&source] };
+            slot_of.insert(output, signals.len());
+            signals.push(output);
+            ops.push((slot_of[&output], vec![src]));
+        }
+
+        let outputs = output_signals(circuit).iter().map(|o| slot_of[o]).collect();
+
+        Ok(Self {
+            ops,
+            primitive: circuit.primitive,
+            num_inputs: circuit.num_inputs,
+            num_slots: signals.len(),
+            signals,
+            outputs,
+            complemented: circuit.complemented_inputs.iter().copied().collect(),
+        })
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    // Signal id held in each slot
+    pub fn signals(&self) -> &[i32] {
+        &self.signals
+    }
+
+    // Evaluates 64 vectors at once; `inputs` holds one word per primary input
+    // and the returned words hold every signal, indexed like `signals()`
+    pub fn eval_words(&self, inputs: &[u64]) -> Vec<u64> {
+        let mut slots = vec![0u64; self.num_slots];
+        slots[..self.num_inputs].copy_from_slice(&inputs[..self.num_inputs]);
+        for &i in &self.complemented {
+            slots[i] = !slots[i];
+        }
+        for (dst, srcs) in &self.ops {
+            slots[*dst] = self.primitive.eval(srcs.iter().map(|&s| slots[s]));
+        }
+        slots
+    }
+
+    // Every gate output by signal
+    fn held_words(&self, inputs: &[u64]) -> HashMap<i32, u64> {
+        self.signals.iter().copied().zip(self.eval_words(inputs))
+            .filter(|&(signal, _)| signal < MAX_GATES as i32)
+            .collect()
+    }
+
+    // One word per primary output
+    pub fn eval_outputs(&self, inputs: &[u64]) -> Vec<u64> {
+        let slots = self.eval_words(inputs);
+        self.outputs.iter().map(|&o| slots[o]).collect()
+    }
+}
+
+pub fn simulate_netlist(circuit: &Circuit, vectors: &[InputVector]) -> Result<Vec<OutputVector>> {
+    let netlist = CompiledNetlist::new(circuit)?;
+    check_widths(circuit, vectors)?;
+
+    let mut results = Vec::with_capacity(vectors.len());
+    for chunk in vectors.chunks(LANES) {
+        let words = netlist.eval_outputs(&pack_vectors(chunk, circuit.num_inputs));
+        results.extend(unpack_vectors(&words, chunk.len()));
+    }
+
+    Ok(results)
+}
+
+fn check_widths(circuit: &Circuit, vectors: &[InputVector]) -> Result<()> {
+    if let Some(vector) = vectors.iter().find(|v| v.len() != circuit.num_inputs) {
+        bail!("Input vector has {} bits but circuit has {} inputs",
+            vector.len(), circuit.num_inputs);
+    }
+    Ok(())
+}
+
+// Packs up to 64 vectors into one word per input, vector k in bit k
+pub fn pack_vectors(vectors: &[InputVector], width: usize) -> Vec<u64> {
+    let mut words = vec![0u64; width];
+    for (lane, vector) in vectors.iter().take(LANES).enumerate() {
+        for (bit, &value) in vector.iter().enumerate() {
+            if value {
+                words[bit] |= 1 << lane;
+            }
+        }
+    }
+    words
+}
+
+pub fn unpack_vectors(words: &[u64], count: usize) -> Vec<Vec<bool>> {
+    (0..count.min(LANES))
+        .map(|lane| words.iter().map(|w| w >> lane & 1 == 1).collect())
+        .collect()
+}
+
+// Fraction of random vectors for which each signal is high, and the fraction
+// of consecutive vector pairs across which it toggles
+pub fn switching_activity(circuit: &Circuit, passes: usize, seed: u64) -> Result<Vec<(i32, f64, f64)>> {
+    let netlist = CompiledNetlist::new(circuit)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut ones = vec![0u64; netlist.num_slots];
+    let mut toggles = vec![0u64; netlist.num_slots];
+    let mut last_bit = vec![None; netlist.num_slots];
+    for _ in 0..passes {
+        let inputs: Vec<u64> = (0..netlist.num_inputs).map(|_| rng.gen()).collect();
+        let slots = netlist.eval_words(&inputs);
+        for (k, &w) in slots.iter().enumerate() {
+            ones[k] += w.count_ones() as u64;
+            // Bit i toggles if it differs from bit i-1; lane 0 compares with the previous pass
+            toggles[k] += ((w ^ (w << 1)) & !1).count_ones() as u64;
+            if let Some(prev) = last_bit[k] {
+                toggles[k] += prev ^ (w & 1);
+            }
+            last_bit[k] = Some(w >> 63);
+        }
+    }
+
+    let samples = (passes * LANES) as f64;
+    let transitions = (passes * LANES).saturating_sub(1).max(1) as f64;
+    Ok(netlist.signals.iter().enumerate()
+        .skip(netlist.num_inputs)
+        .map(|(k, &sig)| (sig, ones[k] as f64 / samples, toggles[k] as f64 / transitions))
+        .collect())
+}
+
+// Evaluates a crossbar mapping cell by cell, following the recorded input
+// coordinates, so wiring mistakes in the mapper show up as output mismatches
+pub fn simulate_mapping(
+    circuit: &Circuit,
+    mapping: &CrossbarMapping,
+    vectors: &[InputVector],
+) -> Result<Vec<OutputVector>> {
+    check_widths(circuit, vectors)?;
+    let outputs = output_signals(circuit);
+
+    // Locate the cell computing each output, or the cell an inverted output
+    // is read from
+    let mut output_cells = Vec::with_capacity(outputs.len());
+    for &out in &outputs {
+        let (signal, inverted) = match circuit.inverted_outputs.get(&out) {
+            Some(&source) => (source, true),
+            None => (out, false),
+        };
+        let signal = circuit.cell_signal(signal);
+        let cell = mapping.occupied_cells()
+            .find(|c| c.value == signal && !c.is_copy)
+            .with_context(|| format!("Output {} is not mapped", signal_name(out)))?;
+        output_cells.push((cell.idx, cell.jdx, inverted));
+    }
+
+    // Gates held from an init state take their values from the netlist
+    let held: Vec<(i32, i32, i32)> = mapping.occupied_cells()
+        .filter(|c| c.held && c.value < MAX_GATES as i32)
+        .map(|c| (c.idx, c.jdx, c.value))
+        .collect();
+    let netlist = if held.is_empty() { None } else { Some(CompiledNetlist::new(circuit)?) };
+
+    let mut results = Vec::with_capacity(vectors.len());
+    let mut values = HashMap::new();
+    for chunk in vectors.chunks(LANES) {
+        let packed = pack_vectors(chunk, circuit.num_inputs);
+        let mut inputs = packed.clone();
+        for &i in &circuit.complemented_inputs {
+            inputs[i] = !inputs[i];
+        }
+        values.clear();
+        if let Some(netlist) = &netlist {
+            let words = netlist.held_words(&packed);
+            for &(i, j, signal) in &held {
+                values.insert((i, j), words[&signal]);
+            }
+        }
+        let mut words = Vec::with_capacity(output_cells.len());
+        for &(i, j, inverted) in &output_cells {
+            let word = cell_value(mapping, circuit.primitive, i, j, &inputs, &mut values, 0)?;
+            words.push(if inverted { !word } else { word });
+        }
+        results.extend(unpack_vectors(&words, chunk.len()));
+    }
+
+    Ok(results)
+}
+
+fn cell_value(
+    mapping: &CrossbarMapping,
+    primitive: Primitive,
+    i: i32,
+    j: i32,
+    inputs: &[u64],
+    values: &mut HashMap<(i32, i32), u64>,
+    depth: usize,
+) -> Result<u64> {
+    if let Some(&v) = values.get(&(i, j)) {
+        return Ok(v);
+    }
+    if depth > MAX_GATES {
+        bail!("Cycle detected through cell {}x{}", i, j);
+    }
+
+    let cell = mapping.cell(i as usize, j as usize);
+    if !cell.is_occupied() {
+        bail!("Cell {}x{} is read but never written", i, j);
+    }
+    let value = if cell.value >= MAX_GATES as i32 {
+        inputs[(cell.value - MAX_GATES as i32) as usize]
+    } else if cell.is_copy {
+        let src = cell.inputs[0].as_ref()
+            .with_context(|| format!("Copy cell {}x{} has no source", i, j))?;
+        cell_value(mapping, primitive, src.idx, src.jdx, inputs, values, depth + 1)?
+    } else {
+        let mut operands = Vec::with_capacity(cell.fanin);
+        for k in 0..cell.fanin {
+            let src = cell.inputs[k].as_ref()
+                .with_context(|| format!("Cell {}x{} ({}) has unconnected input {}",
+                    i, j, signal_name(cell.value), k))?;
+            operands.push(cell_value(mapping, primitive, src.idx, src.jdx, inputs, values, depth + 1)?);
+        }
+        primitive.eval(operands)
+    };
+
+    values.insert((i, j), value);
+    Ok(value)
+}
+
+// Runs a program op by op on a model of the crossbar: a gate must find its
+// cell initialized in the same level, and no cell may be read before it is
+// written or while it waits to evaluate. Outputs come in buffer slot order
+pub fn simulate_program(program: &Program, vectors: &[InputVector]) -> Result<Vec<OutputVector>> {
+    run_program(program, vectors, None)
+}
+
+// Like `simulate_program`, for programs whose cells hold gate outputs of an
+// earlier program: their values come from evaluating the circuit's netlist
+pub fn simulate_program_on(circuit: &Circuit, program: &Program, vectors: &[InputVector]) -> Result<Vec<OutputVector>> {
+    let netlist = CompiledNetlist::new(circuit)?;
+    run_program(program, vectors, Some(&netlist))
+}
+
+fn run_program(program: &Program, vectors: &[InputVector], netlist: Option<&CompiledNetlist>) -> Result<Vec<OutputVector>> {
+    if let Some(vector) = vectors.iter().find(|v| v.len() != program.num_inputs) {
+        bail!("Input vector has {} bits but the program has {} inputs", vector.len(), program.num_inputs);
+    }
+
+    let slots = program.outputs().count();
+    let mut results = Vec::with_capacity(vectors.len());
+    let mut crossbar = Crossbar::new(slots);
+    for chunk in vectors.chunks(LANES) {
+        let inputs = pack_vectors(chunk, program.num_inputs);
+        crossbar.clear();
+        if let Some(netlist) = netlist {
+            crossbar.held = netlist.held_words(&inputs);
+        }
+        for step in &program.steps {
+            for op in &step.ops {
+                crossbar.apply(program, step.level, op, &inputs)?;
+            }
+        }
+        // Slots are numbered densely, so each read fills one
+        let words: Vec<u64> = crossbar.words.iter().flatten().copied().collect();
+        results.extend(unpack_vectors(&words, chunk.len()));
+    }
+
+    Ok(results)
+}
+
+// The cells of a crossbar as a program runs, one bit per vector
+struct Crossbar {
+    values: HashMap<Cell, u64>,
+    // Cells initialized and waiting to evaluate
+    armed: HashSet<Cell>,
+    // The output buffer
+    words: Vec<Option<u64>>,
+    // Values of the gates held cells hold, when known
+    held: HashMap<i32, u64>,
+}
+
+impl Crossbar {
+    fn new(slots: usize) -> Self {
+        Self { values: HashMap::new(), armed: HashSet::new(), words: vec![None; slots], held: HashMap::new() }
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.armed.clear();
+        self.words.iter_mut().for_each(|w| *w = None);
+    }
+
+    fn read(&self, cell: &Cell, level: i32) -> Result<u64> {
+        if self.armed.contains(cell) {
+            bail!("Cell {} is read at level {} before it evaluates", cell, level);
+        }
+        self.values.get(cell).copied()
+            .with_context(|| format!("Cell {} is read at level {} but never written", cell, level))
+    }
+
+    fn apply(&mut self, program: &Program, level: i32, op: &MicroOp, inputs: &[u64]) -> Result<()> {
+        let (cell, value) = match op {
+            MicroOp::InputLoad { input, cell, complemented } => {
+                let word = inputs.get(*input)
+                    .with_context(|| format!("Input {} beyond the {} program inputs", input, program.num_inputs))?;
+                (cell, if *complemented { !word } else { *word })
+            }
+            MicroOp::Held { signal, cell } if *signal >= MAX_GATES as i32 => {
+                let input = (*signal - MAX_GATES as i32) as usize;
+                let word = inputs.get(input)
+                    .with_context(|| format!("Input {} beyond the {} program inputs", input, program.num_inputs))?;
+                (cell, *word)
+            }
+            MicroOp::Held { signal, cell } => {
+                let word = self.held.get(signal).with_context(|| format!(
+                    "Cell {} holds {}, which only the netlist of the program can evaluate", cell, signal_name(*signal)))?;
+                (cell, *word)
+            }
+            MicroOp::Preset { cell } => {
+                self.armed.insert(*cell);
+                self.values.insert(*cell, !0);
+                return Ok(());
+            }
+            MicroOp::Init { cells } => {
+                for cell in cells {
+                    self.armed.insert(*cell);
+                    self.values.insert(*cell, !0);
+                }
+                return Ok(());
+            }
+            MicroOp::Copy { from, cell } => (cell, self.read(from, level)?),
+            MicroOp::ExecNor { inputs, cell } => {
+                let operands = inputs.iter().map(|input| self.read(input, level)).collect::<Result<Vec<_>>>()?;
+                (cell, program.primitive.eval(operands))
+            }
+            MicroOp::ExecNot { input, cell } => (cell, !self.read(input, level)?),
+            MicroOp::Read { cell, slot, inverted, .. } => {
+                let value = self.read(cell, level)?;
+                let slots = self.words.len();
+                let word = self.words.get_mut(*slot)
+                    .with_context(|| format!("Output slot {} beyond the {} outputs", slot, slots))?;
+                ensure!(word.is_none(), "Output slot {} is read twice", slot);
+                *word = Some(if *inverted { !value } else { value });
+                return Ok(());
+            }
+        };
+        if matches!(op, MicroOp::ExecNor { .. } | MicroOp::ExecNot { .. }) && !self.armed.remove(cell) {
+            bail!("Cell {} evaluates at level {} without being initialized", cell, level);
+        }
+        self.values.insert(*cell, value);
+        Ok(())
+    }
+}
+
+// The crossbar after one frame of a traced program: the input write, one
+// Init, one copy, one level's evaluation or the output reads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFrame {
+    // First cycle of the frame, as `Program::timeline` numbers them
+    pub cycle: usize,
+    // Cycles the frame takes: a copy takes two and an evaluation three, its
+    // read, write and evaluation; the reads happen after the last cycle
+    pub cycles: usize,
+    pub level: i32,
+    // "input", "reset", "copy", "evaluate" or "read"
+    pub phase: &'static str,
+    // Cells the frame senses and the cells it writes or initializes
+    pub reads: Vec<Cell>,
+    pub writes: Vec<Cell>,
+    // Every cell written so far with its bit, or None while it is
+    // initialized and waits to evaluate
+    pub cells: BTreeMap<Cell, Option<bool>>,
+    // Output buffer slots filled so far
+    pub outputs: Vec<Option<bool>>,
+}
+
+// Runs a program on one input vector like `simulate_program` and records
+// the crossbar after each frame, for animations
+pub fn trace_program(program: &Program, vector: &[bool]) -> Result<Vec<TraceFrame>> {
+    ensure!(vector.len() == program.num_inputs,
+        "Input vector has {} bits but the program has {} inputs", vector.len(), program.num_inputs);
+    let inputs = pack_vectors(&[vector.to_vec()], program.num_inputs);
+    let mut crossbar = Crossbar::new(program.outputs().count());
+    let mut frames: Vec<TraceFrame> = Vec::new();
+    let mut cycle = 0;
+    for step in &program.steps {
+        for op in &step.ops {
+            let (phase, cycles, reads, writes) = match op {
+                MicroOp::InputLoad { cell, .. } => ("input", 1, vec![], vec![*cell]),
+                // Held and preset cells are in place before the first cycle
+                MicroOp::Held { cell, .. } | MicroOp::Preset { cell } => ("input", 0, vec![], vec![*cell]),
+                MicroOp::Init { cells } => ("reset", 1, vec![], cells.clone()),
+                MicroOp::Copy { from, cell } => ("copy", 2, vec![*from], vec![*cell]),
+                MicroOp::ExecNor { inputs, cell } => ("evaluate", 3, inputs.clone(), vec![*cell]),
+                MicroOp::ExecNot { input, cell } => ("evaluate", 3, vec![*input], vec![*cell]),
+                MicroOp::Read { cell, .. } => ("read", 0, vec![*cell], vec![]),
+            };
+            // The loads, a level's gates and the reads each share a frame
+            let joins = matches!(phase, "input" | "evaluate" | "read");
+            match frames.last_mut() {
+                Some(frame) if joins && frame.phase == phase && frame.level == step.level => {
+                    // A frame of held cells only takes a cycle once it loads an input
+                    if frame.cycles < cycles {
+                        cycle += cycles - frame.cycles;
+                        frame.cycles = cycles;
+                    }
+                    frame.reads.extend(reads);
+                    frame.writes.extend(writes);
+                }
+                _ => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.record(&crossbar);
+                    }
+                    frames.push(TraceFrame {
+                        cycle, cycles, level: step.level, phase, reads, writes,
+                        cells: BTreeMap::new(), outputs: Vec::new(),
+                    });
+                    cycle += cycles;
+                }
+            }
+            crossbar.apply(program, step.level, op, &inputs)?;
+        }
+    }
+    if let Some(frame) = frames.last_mut() {
+        frame.record(&crossbar);
+    }
+    Ok(frames)
+}
+
+impl TraceFrame {
+    fn record(&mut self, crossbar: &Crossbar) {
+        self.cells = crossbar.values.iter()
+            .map(|(cell, &value)| (*cell, (!crossbar.armed.contains(cell)).then_some(value & 1 == 1)))
+            .collect();
+        self.outputs = crossbar.words.iter().map(|w| w.map(|w| w & 1 == 1)).collect();
+    }
+}
+
+pub fn exhaustive_vectors(num_inputs: usize) -> Result<Vec<InputVector>> {
+    if num_inputs > MAX_EXHAUSTIVE_INPUTS {
+        bail!("Exhaustive simulation limited to {} inputs (circuit has {})",
+            MAX_EXHAUSTIVE_INPUTS, num_inputs);
+    }
+
+    Ok((0..1u64 << num_inputs)
+        .map(|n| (0..num_inputs).map(|b| n >> b & 1 == 1).collect())
+        .collect())
+}
+
+pub fn random_vectors(num_inputs: usize, count: usize, seed: u64) -> Vec<InputVector> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| (0..num_inputs).map(|_| rng.gen()).collect())
+        .collect()
+}
+
+// Reads vectors written as one bit string per line, x0 first; '#' starts a comment
+pub fn read_vectors<P: AsRef<Path>>(path: P) -> Result<Vec<InputVector>> {
+    let text = fs::read_to_string(path.as_ref())
+        .context(format!("Failed to read vector file: {:?}", path.as_ref()))?;
+
+    let mut vectors = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut vector = Vec::with_capacity(line.len());
+        for ch in line.chars().filter(|c| !c.is_whitespace() && *c != '_') {
+            match ch {
+                '0' => vector.push(false),
+                '1' => vector.push(true),
+                _ => bail!("Invalid character '{}' in vector on line {}", ch, n + 1),
+            }
+        }
+        vectors.push(vector);
+    }
+
+    Ok(vectors)
+}
+
+pub fn format_vector(vector: &[bool]) -> String {
+    vector.iter().map(|&b| if b { '1' } else { '0' }).collect()
+}
@@ -0,0 +1,386 @@
+//suite/mod.rs
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::parser::Format;
+use crate::metrics::RunMetrics;
+use crate::schema::{self, Versioned};
+use crate::Circuit;
+
+// Benchmark suites with a built-in manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Suite {
+    /// EPFL combinational benchmarks (arithmetic and random/control)
+    Epfl,
+}
+
+impl Suite {
+    pub fn manifest(self) -> Manifest {
+        let text = match self {
+            Suite::Epfl => include_str!("../../suites/epfl.toml"),
+        };
+        Manifest::parse(text).expect("built-in manifests are valid")
+    }
+}
+
+// Benchmarks of a suite, in the order they are run and reported:
+//
+//   name = "epfl"
+//
+//   [[benchmark]]
+//   name = "adder"
+//   category = "arithmetic"
+//   inputs = 256          (optional, checked after loading)
+//   outputs = 129         (optional)
+//   file = "arith/adder.aig"   (optional, relative to the suite directory)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(rename = "benchmark", default)]
+    pub benchmarks: Vec<Benchmark>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Benchmark {
+    pub name: String,
+    #[serde(default)]
+    pub category: String,
+    pub inputs: Option<usize>,
+    pub outputs: Option<usize>,
+    pub file: Option<PathBuf>,
+}
+
+// Extensions tried when a benchmark names no file, most direct format first
+const PREFERRED_EXTENSIONS: [&str; 4] = ["aig", "aag", "blif", "v"];
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = fs::read_to_string(path.as_ref())
+            .context(format!("Failed to read manifest: {:?}", path.as_ref()))?;
+        Self::parse(&text).context(format!("Invalid manifest: {:?}", path.as_ref()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    // File of each benchmark under `dir`, searched recursively by name
+    pub fn locate(&self, dir: &Path) -> Result<Vec<(Benchmark, Option<PathBuf>)>> {
+        let mut files = Vec::new();
+        collect_files(dir, &mut files)?;
+        files.sort();
+
+        Ok(self.benchmarks.iter().map(|bench| {
+            let path = match &bench.file {
+                Some(file) => Some(dir.join(file)).filter(|p| p.is_file()),
+                None => PREFERRED_EXTENSIONS.iter().find_map(|ext| {
+                    files.iter().find(|f| {
+                        f.file_stem().is_some_and(|s| s == bench.name.as_str())
+                            && f.extension().is_some_and(|e| e.eq_ignore_ascii_case(ext))
+                    }).cloned()
+                }),
+            };
+            (bench.clone(), path)
+        }).collect())
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).context(format!("Failed to read directory: {:?}", dir))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Benchmark {
+    // Mismatches between the manifest and the loaded circuit
+    pub fn check_interface(&self, circuit: &Circuit) -> Vec<String> {
+        let mut messages = Vec::new();
+        if let Some(inputs) = self.inputs.filter(|&n| n != circuit.num_inputs) {
+            messages.push(format!("expected {} inputs, found {}", inputs, circuit.num_inputs));
+        }
+        if let Some(outputs) = self.outputs.filter(|&n| n != circuit.num_outputs) {
+            messages.push(format!("expected {} outputs, found {}", outputs, circuit.num_outputs));
+        }
+        messages
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Status {
+    Done(Box<RunMetrics>),
+    Missing,
+    Skipped,
+    Failed(String),
+}
+
+impl Status {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Done(_) => "done",
+            Status::Missing => "missing",
+            Status::Skipped => "skipped",
+            Status::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SuiteRow {
+    pub benchmark: Benchmark,
+    pub format: Option<Format>,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub status: Status,
+}
+
+const COLUMNS: [&str; 13] = [
+    "Benchmark", "Category", "Format", "PI", "PO", "Gates", "NOT", "Levels",
+    "Crossbar", "Memristors", "Cycles", "Time (s)", "Status",
+];
+
+impl SuiteRow {
+    fn cells(&self) -> Vec<String> {
+        let format = self.format.map_or("-".to_string(), |f| f.to_string());
+        let mut cells = vec![self.benchmark.name.clone(), self.benchmark.category.clone(), format];
+        match &self.status {
+            Status::Done(metrics) => {
+                cells.extend([
+                    self.inputs.to_string(),
+                    self.outputs.to_string(),
+                    metrics.gates.to_string(),
+                    metrics.not_gates.to_string(),
+                    metrics.levels.to_string(),
+                    format!("{}x{}", metrics.compact.rows, metrics.compact.cols),
+                    metrics.compact.cells.to_string(),
+                    metrics.compact.cycles.to_string(),
+                    format!("{:.2}", metrics.times.total_ms / 1000.0),
+                    "ok".to_string(),
+                ]);
+            }
+            Status::Missing => {
+                cells.extend(std::iter::repeat_n("-".to_string(), 9));
+                cells.push("missing".to_string());
+            }
+            Status::Skipped => {
+                cells.extend(std::iter::repeat_n("-".to_string(), 9));
+                cells.push("skipped".to_string());
+            }
+            Status::Failed(reason) => {
+                cells.extend(std::iter::repeat_n("-".to_string(), 9));
+                cells.push(format!("failed: {}", reason));
+            }
+        }
+        cells
+    }
+}
+
+pub(crate) fn geometric_mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(s, n), v| (s + v.max(1.0).ln(), n + 1));
+    (count > 0).then(|| (sum / count as f64).exp())
+}
+
+// Writes `<name>_summary.txt`, a fixed-width table with totals and geometric
+// means over the completed benchmarks, and `<name>_summary.csv` with the same
+// rows, into `dir`; returns the paths
+pub fn write_summary(dir: &Path, name: &str, rows: &[SuiteRow]) -> Result<(PathBuf, PathBuf)> {
+    fs::create_dir_all(dir).context(format!("Failed to create directory: {:?}", dir))?;
+    let cells: Vec<Vec<String>> = rows.iter().map(SuiteRow::cells).collect();
+    let widths: Vec<usize> = (0..COLUMNS.len())
+        .map(|c| cells.iter().map(|r| r[c].len()).chain([COLUMNS[c].len()]).max().unwrap_or(0))
+        .collect();
+
+    let mut text = String::new();
+    writeln!(text, "{} SUITE SUMMARY:", name.to_uppercase())?;
+    writeln!(text, "=============")?;
+    let line = |row: &[String]| -> String {
+        let padded: Vec<String> = row.iter().zip(&widths).enumerate()
+            .map(|(c, (cell, &w))| if (3..12).contains(&c) { format!("{:>w$}", cell) } else { format!("{:<w$}", cell) })
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+    let header: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+    writeln!(text, "{}", line(&header))?;
+    for row in &cells {
+        writeln!(text, "{}", line(row))?;
+    }
+
+    let done: Vec<&RunMetrics> = rows.iter()
+        .filter_map(|r| match &r.status {
+            Status::Done(metrics) => Some(metrics.as_ref()),
+            _ => None,
+        })
+        .collect();
+    let count = |f: fn(&Status) -> bool| rows.iter().filter(|r| f(&r.status)).count();
+    writeln!(text)?;
+    writeln!(text, "Completed         : {} of {} ({} missing, {} skipped, {} failed)",
+        done.len(), rows.len(),
+        count(|s| matches!(s, Status::Missing)),
+        count(|s| matches!(s, Status::Skipped)),
+        count(|s| matches!(s, Status::Failed(_))))?;
+    writeln!(text, "Total gates       : {}", done.iter().map(|m| m.gates).sum::<usize>())?;
+    writeln!(text, "Total memristors  : {}", done.iter().map(|m| m.compact.cells).sum::<usize>())?;
+    writeln!(text, "Total cycles      : {}", done.iter().map(|m| m.compact.cycles).sum::<usize>())?;
+    if let Some(gates) = geometric_mean(done.iter().map(|m| m.gates as f64)) {
+        writeln!(text, "Geomean gates     : {:.1}", gates)?;
+        writeln!(text, "Geomean levels    : {:.1}", geometric_mean(done.iter().map(|m| m.levels as f64)).unwrap_or(0.0))?;
+        writeln!(text, "Geomean memristors: {:.1}", geometric_mean(done.iter().map(|m| m.compact.cells as f64)).unwrap_or(0.0))?;
+        writeln!(text, "Geomean cycles    : {:.1}", geometric_mean(done.iter().map(|m| m.compact.cycles as f64)).unwrap_or(0.0))?;
+    }
+
+    let mut csv = COLUMNS.join(",") + "\n";
+    for row in &cells {
+        let quoted: Vec<String> = row.iter()
+            .map(|c| if c.contains(',') { format!("\"{}\"", c.replace('"', "\"\"")) } else { c.clone() })
+            .collect();
+        csv += &(quoted.join(",") + "\n");
+    }
+
+    let text_path = dir.join(format!("{}_summary.txt", name));
+    let csv_path = dir.join(format!("{}_summary.csv", name));
+    fs::write(&text_path, text).context(format!("Failed to write {:?}", text_path))?;
+    fs::write(&csv_path, csv).context(format!("Failed to write {:?}", csv_path))?;
+    Ok((text_path, csv_path))
+}
+
+// What each circuit of a run ended with, as `<name>_summary.json` holds it
+// and `compare-runs` reads it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub name: String,
+    pub circuits: Vec<CircuitResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitResult {
+    pub circuit: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub category: String,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<RunMetrics>,
+}
+
+impl RunSummary {
+    pub fn new(name: &str, rows: &[SuiteRow]) -> Self {
+        let circuits = rows.iter()
+            .map(|row| {
+                let record = ResultRecord::new(&row.benchmark.name, None, &row.status, 0.0);
+                CircuitResult {
+                    circuit: row.benchmark.name.clone(),
+                    category: row.benchmark.category.clone(),
+                    status: record.status.to_string(),
+                    error: record.error.map(String::from),
+                    metrics: record.metrics.cloned(),
+                }
+            })
+            .collect();
+        Self { name: name.to_string(), circuits }
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(&Versioned::new(self))? + "\n")
+            .context(format!("Failed to write run summary: {:?}", path))
+    }
+
+    // A summary, or a results stream (`.jsonl`) whose last line for each
+    // circuit stands for it, since reruns append to the stream
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).context(format!("Failed to read run summary: {:?}", path))?;
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+            return schema::from_str(&text, "run summary").context(format!("Failed to load run summary: {:?}", path));
+        }
+
+        let mut circuits: Vec<CircuitResult> = Vec::new();
+        for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let result: CircuitResult = schema::from_str(line, "result")
+                .context(format!("Failed to load line {} of results stream: {:?}", n + 1, path))?;
+            match circuits.iter_mut().find(|c| c.circuit == result.circuit) {
+                Some(earlier) => *earlier = result,
+                None => circuits.push(result),
+            }
+        }
+        let name = path.parent().and_then(|d| d.file_name()).and_then(|n| n.to_str()).unwrap_or("results").to_string();
+        Ok(Self { name, circuits })
+    }
+}
+
+// Writes `<name>_summary.json` into `dir`, next to the text and CSV summaries
+pub fn write_summary_json(dir: &Path, name: &str, rows: &[SuiteRow]) -> Result<PathBuf> {
+    let path = dir.join(format!("{}_summary.json", name));
+    RunSummary::new(name, rows).write_json(&path)?;
+    Ok(path)
+}
+
+// Name of the results stream in the output directory
+pub const RESULTS_STREAM: &str = "results.jsonl";
+
+// One line of the results stream: how a circuit of a bench run ended
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultRecord<'a> {
+    pub circuit: &'a str,
+    pub file: Option<&'a Path>,
+    pub status: &'static str,
+    // Wall-clock time from parsing the file to the circuit's last artifact
+    pub duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<&'a RunMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+}
+
+impl<'a> ResultRecord<'a> {
+    pub fn new(circuit: &'a str, file: Option<&'a Path>, status: &'a Status, duration_ms: f64) -> Self {
+        let (metrics, error) = match status {
+            Status::Done(metrics) => (Some(metrics.as_ref()), None),
+            Status::Failed(reason) => (None, Some(reason.as_str())),
+            Status::Missing | Status::Skipped => (None, None),
+        };
+        Self { circuit, file, status: status.label(), duration_ms, metrics, error }
+    }
+}
+
+// `results.jsonl` in the output directory, a JSON line per circuit written as
+// soon as the circuit is done, so a long bench run can be watched or resumed
+// before its summary exists. Runs append to the stream of earlier ones
+pub struct ResultStream {
+    file: File,
+    path: PathBuf,
+}
+
+impl ResultStream {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).context(format!("Failed to create directory: {:?}", dir))?;
+        let path = dir.join(RESULTS_STREAM);
+        let file = OpenOptions::new().create(true).append(true).open(&path)
+            .context(format!("Failed to open results stream: {:?}", path))?;
+        Ok(Self { file, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, record: &ResultRecord) -> Result<()> {
+        let line = serde_json::to_string(&Versioned::new(record))? + "\n";
+        self.file.write_all(line.as_bytes())
+            .and_then(|_| self.file.flush())
+            .context(format!("Failed to write results stream: {:?}", self.path))
+    }
+}
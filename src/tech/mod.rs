@@ -0,0 +1,259 @@
+//tech/mod.rs
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, bail, ensure};
+use serde::Deserialize;
+
+use crate::{MAX_COL, MAX_FANIN, MAX_ROW};
+
+// Gate inputs of a device without a `max_device_fanin`
+pub const DEFAULT_DEVICE_FANIN: usize = 2;
+
+// A technology file holds one or more named device profiles:
+//
+//   default = "hfo2"
+//
+//   [profile.hfo2.device]
+//   read_ns = 1.0
+//   ...
+//   max_device_fanin = 3       (optional; inputs per evaluation, 2 by default)
+//   [profile.hfo2.crossbar]
+//   max_row_activations = 64
+//   drivers = 128
+//   rows = 256                 (optional array size; mappings must fit)
+//   cols = 512
+//   reserved_rows = [0, 128]   (optional; lines the mappers leave alone)
+//   reserved_cols = [511]
+//   storage_rows = [0, 1]      (optional; lines that store values but
+//   storage_cols = [480, 481]   evaluate no gates)
+//
+//   [profile.hfo2.variation]     (optional)
+//   switch_sigma = 0.1
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TechFile {
+    default: Option<String>,
+    profile: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    device: Device,
+    crossbar: Periphery,
+    variation: Option<Variation>,
+}
+
+// Timing, energy and endurance of a single memristive device
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Device {
+    pub read_ns: f64,
+    pub write_ns: f64,
+    // Duration of one MAGIC evaluation (the output device switching)
+    pub switch_ns: f64,
+    pub read_pj: f64,
+    pub write_pj: f64,
+    pub switch_pj: f64,
+    // Switching cycles a device survives
+    pub endurance: u64,
+    // Defaults to the slowest of the three operations
+    pub clock_ns: Option<f64>,
+    // Inputs one evaluation can take; wider gates are decomposed. Defaults
+    // to two-input gates
+    pub max_device_fanin: Option<usize>,
+}
+
+// Limits of the crossbar periphery
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Periphery {
+    // Rows one operation may activate at once (sneak current, wordline drive)
+    pub max_row_activations: usize,
+    // Bitline drivers, i.e. columns that can be driven at once
+    pub drivers: usize,
+    // Size of the physical array; unset dimensions are only bounded by the
+    // mappers' own limits
+    pub rows: Option<usize>,
+    pub cols: Option<usize>,
+    // Lines taken by reference cells, redundancy and the like
+    #[serde(default)]
+    pub reserved_rows: Vec<usize>,
+    #[serde(default)]
+    pub reserved_cols: Vec<usize>,
+    // The region map: lines of storage-only cells, without the drivers an
+    // evaluation needs. Primary inputs and copies may sit there, gates may
+    // not; a cell computes only where both its row and column do
+    #[serde(default)]
+    pub storage_rows: Vec<usize>,
+    #[serde(default)]
+    pub storage_cols: Vec<usize>,
+}
+
+impl Periphery {
+    // Whether mappings must be checked against or moved around this array
+    pub fn constrains_placement(&self) -> bool {
+        self.rows.is_some() || self.cols.is_some()
+            || !self.reserved_rows.is_empty() || !self.reserved_cols.is_empty()
+            || self.has_storage()
+    }
+
+    // Whether part of the array only stores values
+    pub fn has_storage(&self) -> bool {
+        !self.storage_rows.is_empty() || !self.storage_cols.is_empty()
+    }
+}
+
+// Device-to-device spread, for Monte-Carlo analysis
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variation {
+    // Standard deviation of ln(switching delay); the median stays switch_ns
+    pub switch_sigma: f64,
+}
+
+// Wire load of the crossbar lines, from a separate annotation file: the RC
+// delay an evaluation adds for the distance, in cells along rows and
+// columns, between its output cell and its farthest operand cell
+//
+//   distance = [0, 16, 64]
+//   delay_ns = [0.0, 0.05, 0.3]
+//
+// Delays are linear between the points, held below the first one and
+// extrapolated from the last two beyond the last
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WireModel {
+    pub distance: Vec<usize>,
+    pub delay_ns: Vec<f64>,
+}
+
+impl WireModel {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = fs::read_to_string(path.as_ref())
+            .context(format!("Failed to read wire annotation file: {:?}", path.as_ref()))?;
+        Self::from_toml(&text)
+            .context(format!("Invalid wire annotation file: {:?}", path.as_ref()))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let model: WireModel = toml::from_str(text)?;
+        ensure!(!model.distance.is_empty(), "At least one distance is required");
+        ensure!(model.distance.len() == model.delay_ns.len(),
+            "{} distances but {} delays", model.distance.len(), model.delay_ns.len());
+        ensure!(model.distance.windows(2).all(|w| w[0] < w[1]), "Distances must increase");
+        ensure!(model.delay_ns.iter().all(|&d| d >= 0.0), "Delays must not be negative");
+        Ok(model)
+    }
+
+    pub fn delay_ns(&self, distance: usize) -> f64 {
+        let (points, delays) = (&self.distance, &self.delay_ns);
+        let k = points.partition_point(|&p| p <= distance);
+        if k == 0 || points.len() == 1 {
+            return delays[0];
+        }
+        // The segment holding the distance, or the last one past the end
+        let k = k.min(points.len() - 1);
+        let (d0, d1) = (points[k - 1] as f64, points[k] as f64);
+        let slope = (delays[k] - delays[k - 1]) / (d1 - d0);
+        (delays[k - 1] + slope * (distance as f64 - d0)).max(0.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Technology {
+    pub name: String,
+    pub device: Device,
+    pub periphery: Periphery,
+    pub variation: Option<Variation>,
+    // Attached from an annotation file; the profile itself has no wires
+    pub wires: Option<WireModel>,
+}
+
+impl Technology {
+    // Loads `profile` from a technology file, or its default profile (the only
+    // one, or the one named by `default`)
+    pub fn load<P: AsRef<Path>>(path: P, profile: Option<&str>) -> Result<Self> {
+        let text = fs::read_to_string(path.as_ref())
+            .context(format!("Failed to read technology file: {:?}", path.as_ref()))?;
+        Self::from_toml(&text, profile)
+            .context(format!("Invalid technology file: {:?}", path.as_ref()))
+    }
+
+    pub fn from_toml(text: &str, profile: Option<&str>) -> Result<Self> {
+        let mut file: TechFile = toml::from_str(text)?;
+        let names = file.profile.keys().cloned().collect::<Vec<_>>().join(", ");
+
+        let name = match (profile, file.default.as_deref()) {
+            (Some(name), _) | (None, Some(name)) => name.to_string(),
+            (None, None) if file.profile.len() == 1 => file.profile.keys().next().unwrap().clone(),
+            (None, None) => bail!("No profile selected and no default; available: {}", names),
+        };
+        let Some(Profile { device, crossbar, variation }) = file.profile.remove(&name) else {
+            bail!("Unknown profile '{}'; available: {}", name, names);
+        };
+
+        for (field, value) in [("read_ns", device.read_ns), ("write_ns", device.write_ns),
+                               ("switch_ns", device.switch_ns), ("clock_ns", device.clock_ns.unwrap_or(1.0))] {
+            ensure!(value > 0.0, "Profile '{}': {} must be positive", name, field);
+        }
+        for (field, value) in [("read_pj", device.read_pj), ("write_pj", device.write_pj),
+                               ("switch_pj", device.switch_pj)] {
+            ensure!(value >= 0.0, "Profile '{}': {} must not be negative", name, field);
+        }
+        ensure!(device.endurance > 0, "Profile '{}': endurance must be positive", name);
+        if let Some(fanin) = device.max_device_fanin {
+            ensure!((2..=MAX_FANIN).contains(&fanin),
+                "Profile '{}': max_device_fanin must be between 2 and {}", name, MAX_FANIN);
+        }
+        ensure!(crossbar.max_row_activations > 0 && crossbar.drivers > 0,
+            "Profile '{}': max_row_activations and drivers must be positive", name);
+        for (field, size, reserved, storage, limit) in
+            [("rows", crossbar.rows, &crossbar.reserved_rows, &crossbar.storage_rows, MAX_ROW),
+             ("cols", crossbar.cols, &crossbar.reserved_cols, &crossbar.storage_cols, MAX_COL)] {
+            let size = size.unwrap_or(limit);
+            ensure!(size > 0 && size <= limit, "Profile '{}': {} must be between 1 and {}", name, field, limit);
+            if let Some(&line) = reserved.iter().find(|&&line| line >= size) {
+                bail!("Profile '{}': reserved {} {} is outside the {} {}", name, field, line, size, field);
+            }
+            if let Some(&line) = storage.iter().find(|&&line| line >= size) {
+                bail!("Profile '{}': storage {} {} is outside the {} {}", name, field, line, size, field);
+            }
+            if let Some(line) = storage.iter().find(|line| reserved.contains(line)) {
+                bail!("Profile '{}': {} {} is both reserved and storage", name, field, line);
+            }
+            ensure!((0..size).any(|line| !reserved.contains(&line) && !storage.contains(&line)),
+                "Profile '{}': no {} are left to compute in", name, field);
+        }
+        if let Some(ref v) = variation {
+            ensure!(v.switch_sigma >= 0.0, "Profile '{}': switch_sigma must not be negative", name);
+        }
+
+        Ok(Self { name, device, periphery: crossbar, variation, wires: None })
+    }
+
+    pub fn clock_ns(&self) -> f64 {
+        self.device.clock_ns.unwrap_or_else(|| {
+            self.device.read_ns.max(self.device.write_ns).max(self.device.switch_ns)
+        })
+    }
+
+    pub fn max_device_fanin(&self) -> usize {
+        self.device.max_device_fanin.unwrap_or(DEFAULT_DEVICE_FANIN)
+    }
+
+    // Clock cycles needed to evaluate operations spread over `rows` rows and
+    // `cols` columns that an abstract schedule counts as one step
+    pub fn step_cycles(&self, rows: usize, cols: usize) -> usize {
+        rows.div_ceil(self.periphery.max_row_activations)
+            .max(cols.div_ceil(self.periphery.drivers))
+            .max(1)
+    }
+
+    // Program runs until the most-written device wears out, when each device
+    // is written `writes_per_run` times per run
+    pub fn lifetime_runs(&self, writes_per_run: u64) -> u64 {
+        self.device.endurance / writes_per_run.max(1)
+    }
+}
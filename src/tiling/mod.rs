@@ -0,0 +1,230 @@
+//tiling/mod.rs
+use std::collections::{HashMap, HashSet};
+use anyhow::{Result, bail, ensure};
+
+use crate::scheduler::Schedule;
+use crate::simulator::{self, CompiledNetlist, InputVector, OutputVector, LANES};
+use crate::{signal_name, Circuit, TableGate, MAX_GATES, MAX_PI, MAX_ROW};
+
+// Cycles of one evaluation level, as counted in the micro-op metrics: a read,
+// two writes and the evaluation itself
+pub const LEVEL_CYCLES: usize = 4;
+
+// Cycles a copy of the compact mapping adds: reading its source and writing
+// the copy ahead of the gate that reads it
+pub const COPY_CYCLES: usize = 2;
+
+// One band of consecutive ASAP levels mapped onto its own crossbar. The band's
+// gates form a circuit of their own whose primary input k is `live_in[k]`
+#[derive(Debug, Clone)]
+pub struct Tile {
+    pub first_level: i32,
+    pub last_level: i32,
+    // Signals received from the previous tile (or the primary inputs), one per
+    // input row
+    pub live_in: Vec<i32>,
+    // Signals handed to the next tile: values computed here that later levels
+    // read, values passed through unchanged, and primary outputs
+    pub live_out: Vec<i32>,
+    pub circuit: Circuit,
+}
+
+impl Tile {
+    pub fn levels(&self) -> usize {
+        (self.last_level - self.first_level + 1) as usize
+    }
+
+    // Receiving writes every input row at once, sending reads the live-out
+    // cells once, and each level costs what it does on a single crossbar
+    pub fn stage_cycles(&self) -> usize {
+        LEVEL_CYCLES * self.levels() + 2
+    }
+
+    // Live-out signals forwarded without being computed in this tile
+    pub fn pass_through(&self) -> impl Iterator<Item = i32> + '_ {
+        let received: HashSet<i32> = self.live_in.iter().copied().collect();
+        self.live_out.iter().copied().filter(move |s| received.contains(s))
+    }
+}
+
+// Tiles of a circuit executed as a dataflow pipeline: while tile k evaluates
+// its levels for vector v, tile k+1 works on vector v-1. All tiles advance
+// together at a barrier once per initiation interval
+#[derive(Debug, Clone)]
+pub struct TiledPipeline {
+    pub tiles: Vec<Tile>,
+    // Levels of the whole circuit
+    pub depth: i32,
+    // Primary inputs of the whole circuit
+    pub num_inputs: usize,
+}
+
+impl TiledPipeline {
+    // Splits the ASAP schedule into `tiles` bands of (nearly) equal level
+    // count, which balances the stages; capped at one level per tile
+    pub fn new(circuit: &Circuit, tiles: usize) -> Result<Self> {
+        ensure!(tiles > 0, "A pipeline needs at least one tile");
+        let gates = &circuit.gates[..circuit.num_gates];
+        ensure!(!gates.is_empty(), "Circuit {} has no gates to tile", circuit.bench_name);
+        // Tiles hand signals on as values, with no load or read-out to invert them
+        ensure!(circuit.inverted_outputs.is_empty(), "Circuit {} reads outputs out inverted, which tiles cannot", circuit.bench_name);
+        ensure!(circuit.complemented_inputs.is_empty(), "Circuit {} loads inputs complemented, which tiles cannot", circuit.bench_name);
+        ensure!(!gates.iter().any(|g| g.is_multi_output()), "Circuit {} has multi-output gates, which tiles cannot hand on", circuit.bench_name);
+        if let Some(gate) = gates.iter().find(|g| g.asap_level < 1 || !(1..=2).contains(&g.fanin)) {
+            bail!("Gate {} is not scheduled as a NOT or two-input gate", signal_name(gate.out));
+        }
+
+        let depth = gates.iter().map(|g| g.asap_level).max().unwrap_or(0);
+        let count = tiles.min(depth as usize) as i32;
+        let bounds: Vec<i32> = (0..=count).map(|k| k * depth / count).collect();
+
+        // Level each signal is produced at; primary inputs at level 0
+        let mut level: HashMap<i32, i32> = HashMap::new();
+        for gate in gates {
+            level.insert(gate.out, gate.asap_level);
+        }
+        let produced = |s: i32| if s >= MAX_GATES as i32 { 0 } else { level.get(&s).copied().unwrap_or(0) };
+
+        // Last level each signal is read at; outputs stay live to the end
+        let mut last_read: HashMap<i32, i32> = HashMap::new();
+        for gate in gates {
+            for &input in &gate.inputs[..gate.fanin] {
+                let entry = last_read.entry(input).or_insert(0);
+                *entry = (*entry).max(gate.asap_level);
+            }
+        }
+        let outputs = simulator::output_signals(circuit);
+        for &out in &outputs {
+            last_read.insert(out, depth + 1);
+        }
+
+        // Signals crossing the boundary after `b`
+        let live = |b: i32| -> Vec<i32> {
+            let mut signals: Vec<i32> = if b == depth {
+                outputs.clone()
+            } else {
+                last_read.iter()
+                    .filter(|&(&s, &read)| produced(s) <= b && read > b)
+                    .map(|(&s, _)| s)
+                    .collect()
+            };
+            signals.sort_unstable();
+            signals
+        };
+
+        let schedule = Schedule::asap(circuit);
+        let mut result = Vec::with_capacity(count as usize);
+        for k in 0..count as usize {
+            let (first, last) = (bounds[k] + 1, bounds[k + 1]);
+            let live_in = live(bounds[k]);
+            let live_out = live(last);
+            ensure!(live_in.len() <= MAX_ROW.min(MAX_PI),
+                "Tile {} receives {} signals, more than the {} crossbar rows", k, live_in.len(), MAX_ROW.min(MAX_PI));
+            let band = (first..=last).flat_map(|l| schedule.gates(l)).map(|&i| &gates[i]);
+            let circuit = tile_circuit(circuit, k, band, first, last, &live_in, &live_out);
+            result.push(Tile { first_level: first, last_level: last, live_in, live_out, circuit });
+        }
+
+        Ok(Self { tiles: result, depth, num_inputs: circuit.num_inputs })
+    }
+
+    // Cycles between successive vectors entering the pipeline: the slowest stage
+    pub fn initiation_interval(&self) -> usize {
+        self.tiles.iter().map(Tile::stage_cycles).max().unwrap_or(0)
+    }
+
+    // Cycles from a vector entering tile 0 to its outputs leaving the last tile
+    pub fn latency(&self) -> usize {
+        self.tiles.len() * self.initiation_interval()
+    }
+
+    pub fn throughput(&self) -> f64 {
+        1.0 / self.initiation_interval().max(1) as f64
+    }
+
+    // The whole schedule on one crossbar, one vector at a time
+    pub fn unpipelined_cycles(&self) -> usize {
+        LEVEL_CYCLES * self.depth as usize + 1
+    }
+
+    // Evaluates the tiles one after another, each on the values the previous
+    // one sends; outputs come in `output_signals` order like `simulate_netlist`
+    pub fn simulate(&self, vectors: &[InputVector]) -> Result<Vec<OutputVector>> {
+        let compiled: Vec<CompiledNetlist> = self.tiles.iter()
+            .map(|t| CompiledNetlist::new(&t.circuit))
+            .collect::<Result<_>>()?;
+        if let Some(v) = vectors.iter().find(|v| v.len() != self.num_inputs) {
+            bail!("Input vector has {} bits, circuit has {} inputs", v.len(), self.num_inputs);
+        }
+
+        let mut results = Vec::with_capacity(vectors.len());
+        for chunk in vectors.chunks(LANES) {
+            let inputs = simulator::pack_vectors(chunk, self.num_inputs);
+            let mut values: HashMap<i32, u64> = (0..self.num_inputs)
+                .map(|n| ((MAX_GATES + n) as i32, inputs[n]))
+                .collect();
+            for (tile, netlist) in self.tiles.iter().zip(&compiled) {
+                let received: Vec<u64> = tile.live_in.iter().map(|s| values[s]).collect();
+                let slots = netlist.eval_words(&received);
+                let mut computed: HashMap<i32, u64> = HashMap::with_capacity(slots.len());
+                for (&signal, &word) in netlist.signals().iter().zip(&slots) {
+                    let signal = if signal >= MAX_GATES as i32 {
+                        tile.live_in[(signal - MAX_GATES as i32) as usize]
+                    } else {
+                        signal
+                    };
+                    computed.insert(signal, word);
+                }
+                values = tile.live_out.iter().map(|&s| (s, computed[&s])).collect();
+            }
+            let last = self.tiles.last().map_or(&[][..], |t| &t.live_out[..]);
+            let words: Vec<u64> = last.iter().map(|s| values[s]).collect();
+            results.extend(simulator::unpack_vectors(&words, chunk.len()));
+        }
+        Ok(results)
+    }
+}
+
+// Gates of one band with received signals renamed to the tile's inputs and
+// levels counted from the band's first level
+fn tile_circuit<'a>(
+    circuit: &Circuit,
+    k: usize,
+    band: impl Iterator<Item = &'a TableGate>,
+    first: i32,
+    last: i32,
+    live_in: &[i32],
+    live_out: &[i32],
+) -> Circuit {
+    let rows: HashMap<i32, i32> = live_in.iter().enumerate()
+        .map(|(n, &s)| (s, (MAX_GATES + n) as i32))
+        .collect();
+    let sent: HashSet<i32> = live_out.iter().copied().collect();
+
+    let mut tile = Circuit::new();
+    tile.bench_name = format!("{}_tile{}", circuit.bench_name, k);
+    tile.primitive = circuit.primitive;
+    for gate in band {
+        let mut inputs = gate.inputs.clone();
+        for input in &mut inputs[..gate.fanin] {
+            *input = rows.get(input).copied().unwrap_or(*input);
+        }
+        tile.gates.push(TableGate {
+            fanin: gate.fanin,
+            inputs,
+            out: gate.out,
+            asap_level: gate.asap_level - first + 1,
+            is_output: sent.contains(&gate.out),
+            ..Default::default()
+        });
+    }
+    tile.num_gates = tile.gates.len();
+    tile.num_inputs = live_in.len();
+    tile.num_outputs = tile.gates.iter().filter(|g| g.is_output).count();
+    tile.max_asap = last - first + 1;
+    for n in 0..live_in.len() {
+        tile.primary_inputs[n] = (MAX_GATES + n) as i32;
+    }
+    tile.index_signals();
+    tile
+}
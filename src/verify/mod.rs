@@ -0,0 +1,221 @@
+//verify/mod.rs
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::simulator::topological_order;
+use crate::{signal_name, Circuit, CrossbarMapping, MemristiveGate, TableGate, MAX_COL, MAX_FANIN, MAX_GATES, MAX_ROW};
+
+// Runtime switch for invariant checks in release builds (--paranoid)
+static PARANOID: AtomicBool = AtomicBool::new(false);
+
+pub fn set_paranoid(enabled: bool) {
+    PARANOID.store(enabled, Ordering::Relaxed);
+}
+
+// Invariant checks always run in debug builds and on request in release builds
+pub fn checks_enabled() -> bool {
+    cfg!(debug_assertions) || PARANOID.load(Ordering::Relaxed)
+}
+
+// Like debug_assert!, but also active in release builds run with --paranoid
+#[macro_export]
+macro_rules! invariant {
+    ($cond:expr, $($arg:tt)+) => {
+        if $crate::verify::checks_enabled() && !$cond {
+            panic!("invariant violated: {}", format_args!($($arg)+));
+        }
+    };
+}
+
+// Structural validity of a parsed netlist: supported gate types, single
+// drivers, no undriven signals and no combinational loops
+pub fn verify_netlist(circuit: &Circuit) -> Result<()> {
+    ensure!(circuit.num_gates == circuit.gates.len(),
+        "num_gates is {} but {} gates are stored", circuit.num_gates, circuit.gates.len());
+
+    let mut drivers = HashSet::new();
+    for gate in gates(circuit) {
+        ensure!((1..=MAX_FANIN).contains(&gate.fanin) && gate.fanin <= gate.inputs.len(),
+            "Gate {} has unsupported fanin {}", signal_name(gate.out), gate.fanin);
+        for out in gate.outs() {
+            ensure!(out < MAX_GATES as i32,
+                "Gate output {} lies in the primary input range", signal_name(out));
+            ensure!(drivers.insert(out),
+                "Signal {} has more than one driver", signal_name(out));
+        }
+    }
+
+    for gate in gates(circuit) {
+        for &input in &gate.inputs[..gate.fanin] {
+            if input >= MAX_GATES as i32 {
+                let idx = (input - MAX_GATES as i32) as usize;
+                ensure!(idx < circuit.num_inputs,
+                    "Gate {} reads {} beyond the {} primary inputs",
+                    signal_name(gate.out), signal_name(input), circuit.num_inputs);
+            }
+        }
+    }
+
+    // Reports undriven signals and loops
+    topological_order(circuit)?;
+    Ok(())
+}
+
+// Every gate is scheduled strictly after its drivers in the ASAP, ALAP and
+// list schedules, and ALAP never precedes ASAP
+pub fn verify_schedule(circuit: &Circuit) -> Result<()> {
+    let drivers = &circuit.drivers;
+
+    for gate in gates(circuit) {
+        let name = signal_name(gate.out);
+        ensure!(gate.asap_level >= 1, "Gate {} has no ASAP level", name);
+        ensure!(gate.asap_level <= circuit.max_asap,
+            "Gate {} ASAP level {} exceeds max_asap {}", name, gate.asap_level, circuit.max_asap);
+        ensure!(gate.alap_level >= gate.asap_level,
+            "Gate {} ALAP level {} precedes ASAP level {}", name, gate.alap_level, gate.asap_level);
+        ensure!(gate.alap_level <= circuit.max_alap,
+            "Gate {} ALAP level {} exceeds max_alap {}", name, gate.alap_level, circuit.max_alap);
+
+        for &input in &gate.inputs[..gate.fanin] {
+            let Some(&d) = drivers.get(&input) else { continue };
+            let driver = &circuit.gates[d];
+            ensure!(driver.asap_level < gate.asap_level,
+                "ASAP: {} (level {}) does not follow its input {} (level {})",
+                name, gate.asap_level, signal_name(input), driver.asap_level);
+            ensure!(driver.alap_level < gate.alap_level,
+                "ALAP: {} (level {}) does not follow its input {} (level {})",
+                name, gate.alap_level, signal_name(input), driver.alap_level);
+            if gate.list_level != -1 {
+                ensure!(driver.list_level != -1 && driver.list_level < gate.list_level,
+                    "LIST: {} (level {}) does not follow its input {} (level {})",
+                    name, gate.list_level, signal_name(input), driver.list_level);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Every gate occupies exactly one in-bounds cell holding its signal, and each
+// operand of that cell holds the driving signal (directly or via a copy)
+pub fn verify_mapping(circuit: &Circuit, mapping: &CrossbarMapping) -> Result<()> {
+    let mut occupied = HashMap::new();
+
+    // Gates an init state spares need no cell
+    let unevaluated = circuit.init_state.unevaluated(circuit);
+    for (i, gate) in gates(circuit).enumerate() {
+        if gate.gate_map.is_none() && unevaluated.contains(&gate.out) {
+            continue;
+        }
+        check_gate_cell(circuit, mapping, gate, &mut occupied)
+            .with_context(|| format!("Mapping of gate {} is inconsistent", circuit.describe_gate(i)))?;
+    }
+
+    // Conversely, every non-copy cell that is not a primary input holds a gate
+    let gate_cells = gate_cell_count(mapping);
+    ensure!(gate_cells == occupied.len(),
+        "Crossbar holds {} gate cells but {} gates are mapped", gate_cells, occupied.len());
+
+    Ok(())
+}
+
+// The cell of one gate, which no other gate shares
+fn check_gate_cell(circuit: &Circuit, mapping: &CrossbarMapping, gate: &TableGate, occupied: &mut HashMap<(i32, i32), i32>) -> Result<()> {
+    let name = signal_name(gate.out);
+    let Some(ref placed) = gate.gate_map else {
+        bail!("Gate {} is not mapped", name);
+    };
+    ensure!(placed.idx >= 0 && placed.idx <= mapping.max_idx
+        && placed.jdx >= 0 && placed.jdx <= mapping.max_jdx,
+        "Gate {} mapped to {}x{} outside the {}x{} crossbar",
+        name, placed.idx, placed.jdx, mapping.max_idx + 1, mapping.max_jdx + 1);
+    if let Some(other) = occupied.insert((placed.idx, placed.jdx), gate.out) {
+        bail!("Gates {} and {} share cell {}x{}", signal_name(other), name, placed.idx, placed.jdx);
+    }
+
+    let cell = mapping.cell(placed.idx as usize, placed.jdx as usize);
+    ensure!(cell.value == gate.out && !cell.is_copy,
+        "Cell {}x{} holds {} instead of gate {}", placed.idx, placed.jdx, signal_name(cell.value), name);
+    // A held cell is never evaluated, so it reads nothing
+    if cell.held {
+        return Ok(());
+    }
+    ensure!(cell.fanin == gate.fanin,
+        "Cell {}x{} has fanin {} but gate {} has fanin {}",
+        placed.idx, placed.jdx, cell.fanin, name, gate.fanin);
+    ensure!(cell.asap_level == gate.asap_level,
+        "Cell {}x{} is at level {} but gate {} is at level {}",
+        placed.idx, placed.jdx, cell.asap_level, name, gate.asap_level);
+
+    for k in 0..gate.fanin {
+        let Some(ref operand) = cell.inputs[k] else {
+            bail!("Input {} of gate {} is not connected", k, name);
+        };
+        // Cells hold a multi-output gate under its first signal
+        let source = resolve_source(mapping, operand)?;
+        ensure!(source == circuit.cell_signal(gate.inputs[k]),
+            "Input {} of gate {} reads {} instead of {}",
+            k, name, signal_name(source), signal_name(gate.inputs[k]));
+    }
+    Ok(())
+}
+
+// Placement invariants checked by the mappers themselves when invariant
+// checks are enabled: each gate sits in its own cell holding its signal, and
+// no other non-copy cells exist
+pub fn check_placement(circuit: &Circuit, mapping: &CrossbarMapping) {
+    if !checks_enabled() || circuit.num_inputs == 0 {
+        return;
+    }
+
+    let mut cells = HashSet::new();
+    let unevaluated = circuit.init_state.unevaluated(circuit);
+    for gate in gates(circuit) {
+        let placed = gate.gate_map.as_ref();
+        if placed.is_none() && unevaluated.contains(&gate.out) {
+            continue;
+        }
+        invariant!(placed.is_some(), "gate {} was not placed", signal_name(gate.out));
+        let Some(placed) = placed else { continue };
+        let cell = mapping.cell(placed.idx as usize, placed.jdx as usize);
+        invariant!(cell.value == gate.out && !cell.is_copy,
+            "cell {}x{} holds {} instead of gate {}",
+            placed.idx, placed.jdx, signal_name(cell.value), signal_name(gate.out));
+        invariant!(cells.insert((placed.idx, placed.jdx)),
+            "cell {}x{} holds more than one gate", placed.idx, placed.jdx);
+    }
+
+    let gate_cells = gate_cell_count(mapping);
+    invariant!(gate_cells == cells.len(),
+        "crossbar holds {} gate cells for {} gates", gate_cells, cells.len());
+}
+
+fn gate_cell_count(mapping: &CrossbarMapping) -> usize {
+    mapping.occupied_cells()
+        .filter(|c| c.value < MAX_GATES as i32 && !c.is_copy)
+        .count()
+}
+
+// Signal held by the cell an operand points at, following copies
+fn resolve_source(mapping: &CrossbarMapping, operand: &MemristiveGate) -> Result<i32> {
+    let (mut i, mut j) = (operand.idx, operand.jdx);
+    for _ in 0..MAX_GATES {
+        ensure!(i >= 0 && j >= 0 && (i as usize) < MAX_ROW && (j as usize) < MAX_COL,
+            "Operand points outside the crossbar at {}x{}", i, j);
+        let cell = mapping.cell(i as usize, j as usize);
+        ensure!(cell.is_occupied(), "Operand points at empty cell {}x{}", i, j);
+        if !cell.is_copy {
+            return Ok(cell.value);
+        }
+        let Some(ref src) = cell.inputs[0] else {
+            bail!("Copy cell {}x{} has no source", i, j);
+        };
+        (i, j) = (src.idx, src.jdx);
+    }
+    bail!("Copy chain starting at {}x{} does not terminate", operand.idx, operand.jdx)
+}
+
+fn gates(circuit: &Circuit) -> impl Iterator<Item = &TableGate> {
+    circuit.gates.iter().take(circuit.num_gates)
+}
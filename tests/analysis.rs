@@ -0,0 +1,144 @@
+//tests/analysis.rs
+use delphi::tech::Technology;
+use delphi::analysis::{ActivationLimits, Line};
+use delphi::{analysis, mapper, parser, scheduler, verify, Circuit, CrossbarMapping};
+
+// Outputs n1000k share ids with nk, so internal signals start at n2
+const NETLIST: &str = "\
+n2 = x0 x1
+n3 = x1 x2
+n4 = n2 n3
+n5 = n4
+n10001 = n5 x0
+.end
+";
+
+fn profile(switch_ns: f64, clock_ns: f64, sigma: Option<f64>) -> Technology {
+    let variation = sigma
+        .map(|s| format!("[profile.p.variation]\nswitch_sigma = {}\n", s))
+        .unwrap_or_default();
+    Technology::from_toml(&format!("
+[profile.p.device]
+read_ns = 1.0
+write_ns = 1.0
+switch_ns = {}
+read_pj = 0.0
+write_pj = 0.0
+switch_pj = 0.0
+endurance = 1
+clock_ns = {}
+
+[profile.p.crossbar]
+max_row_activations = 8
+drivers = 8
+
+{}", switch_ns, clock_ns, variation), None).unwrap()
+}
+
+fn scheduled() -> Circuit {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist_str(NETLIST, &mut circuit).unwrap();
+    parser::find_primary_inputs(&mut circuit);
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule(&mut circuit);
+    circuit
+}
+
+fn mapped() -> CrossbarMapping {
+    mapper::create_compact_mapping(&mut scheduled())
+}
+
+#[test]
+fn without_spread_yield_follows_the_clock() {
+    let mapping = mapped();
+    let fast = analysis::analyze_variability(&mapping, &profile(1.0, 2.0, Some(0.0)), 50, 1).unwrap();
+    assert_eq!(fast.program_yield, 1.0);
+    assert!(fast.levels.iter().all(|l| l.timing_yield == 1.0 && l.p99_ns == 1.0));
+    assert_eq!(fast.levels.iter().map(|l| l.ops).sum::<usize>(), 5);
+
+    let slow = analysis::analyze_variability(&mapping, &profile(3.0, 2.0, Some(0.0)), 50, 1).unwrap();
+    assert_eq!(slow.program_yield, 0.0);
+}
+
+#[test]
+fn spread_lowers_yield_reproducibly() {
+    let mapping = mapped();
+    let tech = profile(1.0, 1.2, Some(0.3));
+    let a = analysis::analyze_variability(&mapping, &tech, 2000, 7).unwrap();
+    let b = analysis::analyze_variability(&mapping, &tech, 2000, 7).unwrap();
+    assert!(a.program_yield > 0.0 && a.program_yield < 1.0);
+    assert_eq!(a.program_yield, b.program_yield);
+
+    // One device per level at the last level: P(ln d < ln 1.2) with sigma 0.3
+    let last = a.levels.last().unwrap();
+    assert_eq!(last.ops, 1);
+    assert!((last.timing_yield - 0.729).abs() < 0.04, "yield {}", last.timing_yield);
+}
+
+#[test]
+fn runs_keep_the_best_seed() {
+    let mapping = mapped();
+    let tech = profile(1.0, 1.2, Some(0.3));
+    let runs = analysis::analyze_variability_runs(&mapping, &tech, 300, 4, 5).unwrap();
+    let yields: Vec<f64> = (4..9)
+        .map(|seed| analysis::analyze_variability(&mapping, &tech, 300, seed).unwrap().program_yield)
+        .collect();
+    let spread = runs.program_yield;
+    assert_eq!(spread.runs, 5);
+    assert_eq!(spread.best.1, yields.iter().copied().fold(0.0, f64::max));
+    assert_eq!(spread.worst.1, yields.iter().copied().fold(1.0, f64::min));
+    assert!(spread.worst.1 <= spread.median && spread.median <= spread.best.1);
+    assert_eq!((runs.best_seed, runs.best.program_yield), spread.best);
+    assert!(runs.p99_ns.best.1 <= runs.p99_ns.worst.1);
+
+    // Ties go to the lower seed, and even counts take the middle pair
+    let spread = analysis::RunSpread::of(&[(3, 0.5), (1, 0.9), (2, 0.9), (4, 0.1)], true).unwrap();
+    assert_eq!((spread.best, spread.median, spread.worst), ((1, 0.9), 0.7, (4, 0.1)));
+    assert!(analysis::RunSpread::of(&[], true).is_none());
+    assert!(analysis::analyze_variability_runs(&mapping, &tech, 300, 4, 0).is_err());
+}
+
+#[test]
+fn profiles_without_variation_are_rejected() {
+    assert!(analysis::analyze_variability(&mapped(), &profile(1.0, 2.0, None), 10, 1).is_err());
+}
+
+#[test]
+fn activation_limits_flag_crowded_lines() {
+    let mut circuit = scheduled();
+    let mapping = mapper::create_naive_mapping(&mut circuit);
+
+    // Level 1 of the naive mapping evaluates n2 and n3 on row 0 and
+    // touches x0, x1, x2, n2 and n3
+    let limits = ActivationLimits { wordline: Some(4), bitline: None };
+    let violations = analysis::check_activation(&mapping, &limits);
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].level, violations[0].line, violations[0].index, violations[0].devices),
+        (1, Line::Wordline, 0, 5));
+
+    assert!(analysis::check_activation(&mapping, &ActivationLimits::default()).is_empty());
+    assert!(analysis::check_activation(&mapping, &ActivationLimits { wordline: Some(5), bitline: Some(1) }).is_empty());
+}
+
+#[test]
+fn splitting_levels_keeps_the_placement() {
+    let limits = ActivationLimits { wordline: Some(4), bitline: None };
+    let mut circuit = scheduled();
+    let before = mapper::create_naive_mapping(&mut circuit);
+    let positions = |c: &Circuit| c.gates.iter()
+        .map(|g| g.gate_map.as_ref().map(|m| (g.out, m.idx, m.jdx)))
+        .collect::<Vec<_>>();
+    let placed = positions(&circuit);
+
+    assert_eq!(scheduler::split_overloaded_levels(&mut circuit, &before, &limits), 1);
+    assert_eq!(circuit.max_asap, 5);
+    let after = mapper::create_naive_mapping(&mut circuit);
+    assert_eq!(positions(&circuit), placed);
+    assert!(analysis::check_activation(&after, &limits).is_empty());
+    verify::verify_schedule(&circuit).unwrap();
+    verify::verify_mapping(&circuit, &after).unwrap();
+
+    // Nothing left to split
+    assert_eq!(scheduler::split_overloaded_levels(&mut circuit, &after, &limits), 0);
+}
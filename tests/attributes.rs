@@ -0,0 +1,132 @@
+//tests/attributes.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::passes::{ActiveLowInputs, Bind, Cluster, Commute, DontCare, DontCareSet, Duplicate, InvertedReadout, Minimize, Pass, Xor};
+use delphi::{generator, parser, simulator, Circuit, DONT_TOUCH};
+
+// Every gate line of a netlist annotated `@dont_touch`
+fn pinned(text: &str) -> String {
+    text.lines()
+        .map(|line| if line.starts_with('n') { format!("{} @dont_touch\n", line) } else { format!("{}\n", line) })
+        .collect()
+}
+
+// Each gate's signal and operands, in order
+fn gates(circuit: &Circuit) -> Vec<(i32, Vec<i32>)> {
+    circuit.gates[..circuit.num_gates].iter().map(|g| (g.out, g.inputs[..g.fanin].to_vec())).collect()
+}
+
+#[test]
+fn annotations_reach_every_gate_of_the_line() {
+    let text = "\
+# a full adder's carry, annotated
+n1 = x0 x1 @critical @owner=alu   # comment after the annotations
+n2 = x0 x1 x2 x3 @owner=alu
+n10001 = NOR(n1, n2) @dont_touch
+ADD2 n10002 n10003 = x0 x1 x2 x3 @block=adder
+.end
+";
+    let circuit = common::parsed(text);
+    let first = &circuit.gates[0];
+    assert_eq!((first.attributes.get("critical"), first.attributes.get("owner")), (Some(""), Some("alu")));
+    assert_eq!(first.attributes.to_string(), "@critical @owner=alu");
+    // The 4-variable cascade and the adder's gates all carry their line's annotations
+    let usage = circuit.attribute_usage();
+    let cascade = circuit.gates.iter().filter(|g| g.attributes.get("owner") == Some("alu")).count();
+    assert!(cascade > 2, "{:?}", usage);
+    assert_eq!(usage["owner=alu"].len(), cascade);
+    assert_eq!(usage[DONT_TOUCH].len(), 1);
+    assert!(circuit.gates.iter().filter(|g| g.dont_touch()).all(|g| g.is_output && g.fanin == 2));
+    assert_eq!(usage["block=adder"].len(), circuit.gates.iter().filter(|g| g.attributes.contains("block")).count());
+    assert!(!usage.contains_key("block"));
+
+    for bad in ["n1 = x0 @critical x1\n", "n1 = x0 x1 @2fast\n", "n1 = x0 x1 @owner=\n", "n1 = x0 x1 @\n"] {
+        let mut circuit = Circuit::new();
+        let error = parser::parse_netlist_str(bad, &mut circuit).unwrap_err();
+        assert!(format!("{:#}", error).contains("Invalid annotation on line 1"), "{}: {:#}", bad, error);
+    }
+}
+
+#[test]
+fn decomposed_netlists_keep_the_annotations() {
+    let dir = env::temp_dir().join(format!("delphi-attributes-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let text = "n1 = x0 x1 @critical\nn2 = x0 x1 x2 x3 @owner=alu\nn10001 = n1 n2 @dont_touch\n.end\n";
+    let circuit = common::scheduled(text);
+    let path = dir.join("annotated_decomposed.txt");
+    generator::generate_decomposed_netlist(&circuit, &path).unwrap();
+    let read = common::scheduled(&fs::read_to_string(&path).unwrap());
+    assert_eq!(read.attribute_usage().iter().map(|(a, s)| (a.clone(), s.len())).collect::<Vec<_>>(),
+        circuit.attribute_usage().iter().map(|(a, s)| (a.clone(), s.len())).collect::<Vec<_>>());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn passes_leave_dont_touch_gates_as_written() {
+    let passes: Vec<Box<dyn Pass>> = vec![
+        Box::new(Minimize::default()),
+        Box::new(Xor),
+        Box::new(InvertedReadout),
+        Box::new(ActiveLowInputs),
+        Box::new(Commute),
+        Box::new(Duplicate),
+        Box::new(Cluster::default()),
+        Box::new(Bind::parse(&["x0=1", "x2=0"]).unwrap()),
+        Box::new(DontCare { set: DontCareSet::parse("0-1--\n").unwrap() }),
+    ];
+    for pass in &passes {
+        // Every pass but xor changes one of these as written; none changes them pinned
+        let mut changed = false;
+        for text in [common::bundled("c17"), common::synthetic_netlist(5, 300, 7)] {
+            let mut free = common::scheduled(&text);
+            let before = gates(&free);
+            pass.run(&mut free).unwrap();
+            changed |= gates(&free) != before || !free.clusters.is_empty();
+
+            let mut circuit = common::scheduled(&pinned(&text));
+            let before = gates(&circuit);
+            let report = pass.run(&mut circuit).unwrap();
+            assert_eq!(gates(&circuit), before, "{}: {}", pass.name(), report);
+            assert!(circuit.clusters.is_empty() && circuit.inverted_outputs.is_empty() && circuit.complemented_inputs.is_empty(),
+                "{}: {}", pass.name(), report);
+        }
+        assert!(changed || pass.name() == "xor", "{}", pass.name());
+    }
+
+    // The XORs that read or feed a pinned XOR of a parity chain keep their
+    // templates too, and the last one drives the output inverter
+    let text = "\
+n1 = x0
+n2 = x1
+n3 = x0 x1
+n4 = n1 n2
+n5 = n3 n4
+n6 = n5
+n7 = x2
+n8 = n5 x2
+n9 = n6 n7
+n10 = n8 n9 @dont_touch
+n11 = n10
+n12 = x3
+n13 = n10 x3
+n14 = n11 n12
+n15 = n13 n14
+n10001 = n15
+.end
+";
+    let original = common::parsed(text);
+    let mut circuit = original.clone();
+    let report = Xor.run(&mut circuit).unwrap();
+    assert_eq!(report.details, ["0 XORs rebuilt"]);
+    let free = Xor.run(&mut common::parsed(&text.replace(" @dont_touch", ""))).unwrap();
+    assert_eq!(free.details, ["2 XORs rebuilt"]);
+    let pinned = circuit.gates.iter().find(|g| g.dont_touch()).unwrap();
+    assert_eq!(pinned.inputs[..pinned.fanin], [8, 9]);
+    assert!(circuit.driver(8).is_some() && circuit.driver(9).is_some());
+    let vectors = simulator::exhaustive_vectors(4).unwrap();
+    assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+        simulator::simulate_netlist(&original, &vectors).unwrap());
+}
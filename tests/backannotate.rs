@@ -0,0 +1,60 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::program::{self, Cell, Program};
+use delphi::{generator, mapper};
+
+#[test]
+fn every_gate_sits_where_the_mapping_put_it() {
+    for (name, text) in common::circuits().into_iter().take(3) {
+        let mut circuit = common::scheduled(&text);
+        let mut mapping = mapper::create_compact_mapping(&mut circuit);
+        mapper::reuse_copies(&mut circuit, &mut mapping);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        let sites = program::gate_sites(&program, &program::cell_signals(&mapping));
+
+        assert_eq!(sites.len(), circuit.num_gates, "{}", name);
+        for gate in &circuit.gates[..circuit.num_gates] {
+            let placed = gate.gate_map.as_ref().unwrap();
+            let site = sites[&gate.out];
+            assert_eq!(site.cell, Cell { row: placed.idx as u32, col: placed.jdx as u32 }, "{}", name);
+            assert_eq!(site.level, gate.asap_level, "{}", name);
+            let cell = mapping.cell(placed.idx as usize, placed.jdx as usize);
+            let copies = cell.inputs[..cell.fanin].iter().flatten()
+                .filter(|o| mapping.cell(o.idx as usize, o.jdx as usize).is_copy)
+                .count();
+            assert_eq!(site.copies, copies, "{}: {}", name, delphi::signal_name(gate.out));
+        }
+    }
+}
+
+#[test]
+fn the_verilog_annotates_each_instance() {
+    let dir = env::temp_dir().join(format!("delphi-backannotate-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let sites = program::gate_sites(&program, &program::cell_signals(&mapping));
+
+    let (plain, annotated) = (dir.join("plain.v"), dir.join("annotated.v"));
+    generator::generate_magic_verilog(&circuit, &plain).unwrap();
+    generator::generate_annotated_magic_verilog(&circuit, &sites, &annotated).unwrap();
+    let (plain, annotated) = (fs::read_to_string(&plain).unwrap(), fs::read_to_string(&annotated).unwrap());
+    assert!(!plain.contains("(* row"));
+
+    // The attributes are the only lines added, one before each instance
+    let lines: Vec<&str> = annotated.lines().collect();
+    let attributes: Vec<usize> = (0..lines.len()).filter(|&l| lines[l].starts_with("  (* row = ")).collect();
+    assert_eq!(attributes.len(), circuit.num_gates);
+    for &l in &attributes {
+        assert!(lines[l + 1].starts_with("  not ") || lines[l + 1].starts_with("  nor "), "{}", lines[l + 1]);
+    }
+    let rest: Vec<&str> = lines.iter().filter(|l| !l.starts_with("  (* row = ")).copied().collect();
+    assert_eq!(rest, plain.lines().collect::<Vec<_>>());
+    // The last gate is an output NOT in the row of the NOR it inverts
+    assert!(annotated.contains("  (* row = 1, col = 6, level = 5, copies = 0 *)\n  not    g13  ( op_2 "), "{}", annotated);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,83 @@
+//tests/batch.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::collections::HashSet;
+
+use delphi::analysis::Line;
+use delphi::batch::BatchLayout;
+use delphi::{mapper, MAX_ROW};
+
+#[test]
+fn batch_layout_gives_every_bit_its_own_cell() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        // A single-row naive mapping of the larger circuits runs out of columns
+        let fits_one_row = circuit.num_gates < 900;
+        for naive in [true, false].into_iter().filter(|&n| !n || fits_one_row) {
+            let mapping = if naive {
+                mapper::create_naive_mapping(&mut circuit)
+            } else {
+                mapper::create_compact_mapping(&mut circuit)
+            };
+            let batch = BatchLayout::max_batch(&mapping).min(16);
+            let layout = BatchLayout::new(&circuit, &mapping, batch).unwrap();
+            assert!(batch * layout.rows_per_vector <= MAX_ROW, "{}", name);
+
+            // Vector 0 uses the mapping's own input cells; the others are copies further down
+            let mut cells = HashSet::new();
+            for vector in 0..batch {
+                for input in 0..circuit.num_inputs {
+                    let (row, col) = layout.cell(vector, input);
+                    assert_eq!(row / layout.rows_per_vector, vector, "{}", name);
+                    assert!(cells.insert((row, col)), "{}: {}x{} used twice", name, row, col);
+                    if vector == 0 {
+                        assert_eq!(mapping.cell(row, col).value, (delphi::MAX_GATES + input) as i32);
+                    }
+                }
+            }
+
+            // The writes cover each cell exactly once along their line
+            let mut written = HashSet::new();
+            for write in layout.init_writes() {
+                for &(at, vector, input) in &write.bits {
+                    let cell = match write.line {
+                        Line::Wordline => (write.index, at),
+                        Line::Bitline => (at, write.index),
+                    };
+                    assert_eq!(cell, layout.cell(vector, input));
+                    assert!(written.insert(cell));
+                }
+            }
+            assert_eq!(written, cells, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn batching_amortizes_the_schedule() {
+    let mut circuit = common::scheduled(&common::synthetic_netlist(32, 500, 0x5eed));
+    let levels = circuit.max_asap as usize;
+
+    // The naive mapping is a single row: vectors stack one per row and load column by column
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let single = BatchLayout::new(&circuit, &naive, 1).unwrap();
+    assert_eq!(single.init_writes().len(), 1);
+    assert_eq!(single.batched_cycles(), single.single_cycles());
+    assert_eq!(single.single_cycles(), 4 * levels + 1);
+
+    let layout = BatchLayout::new(&circuit, &naive, 400).unwrap();
+    assert_eq!(layout.rows_per_vector, 1);
+    assert_eq!(layout.init_writes().len(), circuit.num_inputs);
+    assert!(layout.throughput() > 100.0 * single.throughput());
+
+    // The compact mapping keeps its inputs in column 0, so one write loads the whole batch
+    let mut circuit = common::scheduled(&common::bundled("c7552"));
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    let fits = BatchLayout::max_batch(&compact);
+    let layout = BatchLayout::new(&circuit, &compact, fits).unwrap();
+    assert_eq!(layout.init_writes().len(), 1);
+    assert_eq!(layout.batched_cycles(), layout.single_cycles());
+    assert!(BatchLayout::new(&circuit, &compact, fits + 1).is_err());
+    assert!(BatchLayout::new(&circuit, &compact, 0).is_err());
+}
@@ -0,0 +1,78 @@
+//tests/bundle.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+use delphi::archive::{self, MANIFEST};
+use delphi::provenance::{self, ArtifactHeader};
+use delphi::{generator, mapper};
+
+// c17's Verilog and compact listing under `dir`, as a run lays them out,
+// and the header they were stamped with
+fn artifacts_c17(dir: &Path) -> (ArtifactHeader, Vec<(String, PathBuf)>) {
+    fs::create_dir_all(dir.join("magic")).unwrap();
+    fs::create_dir_all(dir.join("micro_ins_compact")).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let (verilog, listing) = (dir.join("magic/c17_magic.v"), dir.join("micro_ins_compact/c17_compact.txt"));
+    generator::generate_magic_verilog(&circuit, &verilog).unwrap();
+    generator::generate_micro_ops(&circuit, &mapping, false, &listing).unwrap();
+    let header = ArtifactHeader::new(&netlist, &["process".to_string(), "c17.txt".to_string()], &circuit).unwrap();
+    header.stamp(&verilog).unwrap();
+    header.stamp(&listing).unwrap();
+    (header, vec![("magic/c17_magic.v".to_string(), verilog), ("micro_ins_compact/c17_compact.txt".to_string(), listing)])
+}
+
+#[test]
+fn archives_hold_the_artifacts_and_their_manifest() {
+    let dir = env::temp_dir().join(format!("delphi-bundle-{}", process::id()));
+    let (header, files) = artifacts_c17(&dir);
+    let files: Vec<(String, &Path)> = files.iter().map(|(name, path)| (name.clone(), path.as_path())).collect();
+    let path = dir.join("c17.zip");
+    let written = archive::write_archive(&path, "c17", &header, &files).unwrap();
+    assert_eq!(archive::read_manifest(&path).unwrap(), written);
+    assert_eq!(written.header, header);
+
+    let entries = archive::read_archive(&path).unwrap();
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, [MANIFEST, "magic/c17_magic.v", "micro_ins_compact/c17_compact.txt"]);
+    for (((name, bytes), file), (_, source)) in entries[1..].iter().zip(&written.files).zip(&files) {
+        assert_eq!(name, &file.path);
+        assert_eq!(bytes, &fs::read(source).unwrap());
+        assert_eq!((file.bytes, file.hash.as_str()), (bytes.len() as u64, provenance::content_hash(bytes).as_str()));
+    }
+
+    // A file is archived once, and a damaged entry fails its CRC
+    let twice = [files[0].clone(), files[0].clone()];
+    assert!(archive::write_archive(dir.join("twice.zip"), "c17", &header, &twice).is_err());
+    let mut damaged = fs::read(&path).unwrap();
+    let name = damaged.windows(MANIFEST.len()).position(|w| w == MANIFEST.as_bytes()).unwrap();
+    damaged[name + MANIFEST.len() + 8] ^= 0xff;
+    fs::write(dir.join("damaged.zip"), damaged).unwrap();
+    assert!(archive::read_archive(dir.join("damaged.zip")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn archives_of_one_run_are_the_same_file() {
+    env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+    let dir = env::temp_dir().join(format!("delphi-bundle-same-{}", process::id()));
+    let (header, files) = artifacts_c17(&dir);
+    let files: Vec<(String, &Path)> = files.iter().map(|(name, path)| (name.clone(), path.as_path())).collect();
+    let (first, second) = (dir.join("first.zip"), dir.join("second.zip"));
+    archive::write_archive(&first, "c17", &header, &files).unwrap();
+    archive::write_archive(&second, "c17", &header, &files).unwrap();
+    let bytes = fs::read(&first).unwrap();
+    assert_eq!(bytes, fs::read(&second).unwrap());
+    // Every entry is dated 2023-11-14 22:13:20, in MS-DOS time then date
+    let local: Vec<usize> = bytes.windows(4).enumerate().filter(|(_, w)| w == &[0x50, 0x4b, 0x03, 0x04]).map(|(at, _)| at).collect();
+    assert_eq!(local.len(), 3);
+    for at in local {
+        assert_eq!(&bytes[at + 10..at + 14], [(22u16 << 11 | 13 << 5 | 10).to_le_bytes(), (43u16 << 9 | 11 << 5 | 14).to_le_bytes()].concat());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,51 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::chain::{self, ChainCycles};
+use delphi::{simulator, Circuit};
+
+// Two outputs from two inputs, chained onto c17's two outputs
+const STAGE: &str = "n3 = x0 x1\nn4 = n3 x0\nn10001 = n4\nn10002 = n3\n.end\n";
+
+fn stage(name: &str, text: &str) -> Circuit {
+    let mut circuit = common::parsed(text);
+    circuit.bench_name = name.to_string();
+    circuit
+}
+
+#[test]
+fn a_chain_computes_its_stages_in_turn() {
+    let stages = [stage("c17", &common::bundled("c17")), stage("a", STAGE), stage("b", STAGE)];
+    let linked = chain::link(&stages).unwrap();
+    assert_eq!(linked.bench_name, "c17_a_b");
+    assert_eq!(linked.num_inputs, 5);
+    assert_eq!(linked.num_gates, stages.iter().map(|s| s.num_gates).sum::<usize>());
+    assert_eq!(simulator::output_signals(&linked), simulator::output_signals(&stages[2]));
+
+    let vectors = simulator::exhaustive_vectors(linked.num_inputs).unwrap();
+    let mut expected = vectors.clone();
+    for stage in &stages {
+        expected = simulator::simulate_netlist(stage, &expected).unwrap();
+    }
+    assert_eq!(simulator::simulate_netlist(&linked, &vectors).unwrap(), expected);
+
+    let cycles = ChainCycles::measure(&stages, &linked).unwrap();
+    assert_eq!(cycles.passed, 4);
+    assert_eq!(cycles.separate(), cycles.stages.iter().map(|(_, c)| c).sum::<usize>());
+    assert!(cycles.chained < cycles.separate(), "{}", cycles);
+}
+
+#[test]
+fn stages_must_read_what_the_one_before_drives() {
+    let c17 = stage("c17", &common::bundled("c17"));
+    let small = stage("a", STAGE);
+    assert!(chain::link(std::slice::from_ref(&c17)).is_err());
+    // c17 reads five inputs, the stage drives two outputs
+    assert!(chain::link(&[small.clone(), c17.clone()]).is_err());
+
+    let one = stage("one", "n10001 = x0\n.end\n");
+    let linked = chain::link(&[c17, one]).unwrap();
+    assert_eq!(linked.diagnostics.iter().filter(|d| d.code == "unread-outputs").count(), 1);
+    let vectors = simulator::exhaustive_vectors(linked.num_inputs).unwrap();
+    assert_eq!(simulator::simulate_netlist(&linked, &vectors).unwrap().len(), vectors.len());
+}
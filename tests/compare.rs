@@ -0,0 +1,90 @@
+//tests/compare.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::compare::{Comparison, Metric, Thresholds, Verdict};
+use delphi::metrics::RunMetrics;
+use delphi::suite::{self, Benchmark, ResultRecord, ResultStream, RunSummary, Status, SuiteRow};
+
+fn row(name: &str, cycles: usize, total_ms: f64) -> SuiteRow {
+    let mut metrics = RunMetrics::new(&common::scheduled(&common::bundled("c17")));
+    (metrics.compact.rows, metrics.compact.cols) = (4, 10);
+    metrics.compact.cycles = cycles;
+    metrics.times.total_ms = total_ms;
+    status_row(name, Status::Done(Box::new(metrics)))
+}
+
+fn status_row(name: &str, status: Status) -> SuiteRow {
+    let benchmark = Benchmark { name: name.to_string(), category: "test".to_string(), inputs: None, outputs: None, file: None };
+    SuiteRow { benchmark, format: None, inputs: 5, outputs: 2, status }
+}
+
+#[test]
+fn circuits_are_flagged_past_their_thresholds() {
+    let failed = || Status::Failed("Failed to parse netlist".to_string());
+    let old = RunSummary::new("old", &[
+        row("same", 100, 10.0), row("slower", 100, 100.0), row("noisy", 100, 10.0),
+        row("longer", 100, 10.0), row("shorter", 100, 10.0), row("broken", 100, 10.0),
+        status_row("mended", failed()), row("dropped", 100, 10.0),
+    ]);
+    let new = RunSummary::new("new", &[
+        row("same", 100, 10.0), row("slower", 100, 300.0), row("noisy", 100, 30.0),
+        row("longer", 110, 10.0), row("shorter", 90, 10.0), status_row("broken", failed()),
+        row("mended", 100, 10.0), row("fresh", 100, 10.0),
+    ]);
+
+    let comparison = Comparison::new(&old, &new, &Thresholds::default());
+    let verdicts: Vec<(&str, &Verdict)> = comparison.circuits.iter().map(|c| (c.circuit.as_str(), &c.verdict)).collect();
+    assert_eq!(verdicts, vec![
+        ("same", &Verdict::Same),
+        ("slower", &Verdict::Regressed(vec![Metric::Runtime])),
+        // Tripled, but by less than the runtime floor
+        ("noisy", &Verdict::Same),
+        ("longer", &Verdict::Regressed(vec![Metric::Cycles])),
+        ("shorter", &Verdict::Improved),
+        ("broken", &Verdict::Failed),
+        ("mended", &Verdict::Fixed),
+        ("dropped", &Verdict::Removed),
+        ("fresh", &Verdict::Added),
+    ]);
+    let regressed: Vec<&str> = comparison.regressions().map(|c| c.circuit.as_str()).collect();
+    assert_eq!(regressed, vec!["slower", "longer", "broken"]);
+
+    let report = comparison.report();
+    assert!(report.starts_with("RUN COMPARISON: old -> new\n=============\n"), "{}", report);
+    assert!(report.contains("100 -> 110 (+10.0%)"), "{}", report);
+    assert!(report.contains("REGRESSED (cycles)"), "{}", report);
+    assert!(report.contains("Regressed      : 3 (slower, longer, broken)"), "{}", report);
+
+    // Loosened thresholds let the same changes through
+    let loose = Thresholds { cycles: 10.0, runtime: 300.0, ..Thresholds::default() };
+    let comparison = Comparison::new(&old, &new, &loose);
+    let regressed: Vec<&str> = comparison.regressions().map(|c| c.circuit.as_str()).collect();
+    assert_eq!(regressed, vec!["broken"]);
+}
+
+#[test]
+fn summaries_and_results_streams_load_alike() {
+    let dir = env::temp_dir().join(format!("delphi-compare-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rows = [row("a", 20, 5.0), status_row("b", Status::Failed("Failed to parse netlist".to_string()))];
+    let path = suite::write_summary_json(&dir, "run", &rows).unwrap();
+    let summary = RunSummary::load(&path).unwrap();
+    assert_eq!(summary, RunSummary::new("run", &rows));
+    assert_eq!(summary.circuits[1].error.as_deref(), Some("Failed to parse netlist"));
+
+    // The stream's last line for a circuit stands for it
+    let mut stream = ResultStream::open(&dir).unwrap();
+    for row in [&rows[1], &rows[0], &row("b", 30, 5.0)] {
+        stream.record(&ResultRecord::new(&row.benchmark.name, None, &row.status, 1.0)).unwrap();
+    }
+    let streamed = RunSummary::load(stream.path()).unwrap();
+    let cycles: Vec<(&str, Option<usize>)> = streamed.circuits.iter()
+        .map(|c| (c.circuit.as_str(), c.metrics.as_ref().map(|m| m.compact.cycles)))
+        .collect();
+    assert_eq!(cycles, vec![("b", Some(30)), ("a", Some(20))]);
+    assert!(RunSummary::load(dir.join("missing.json")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,100 @@
+//tests/cone.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::{mapper, scheduler, signal_id, signal_name, simulator, MAX_GATES};
+
+#[test]
+fn cones_compute_their_outputs() {
+    for (name, text) in common::circuits() {
+        let circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 5);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let outputs = simulator::output_signals(&circuit);
+
+        // One output alone, then every other output together
+        let picks: Vec<Vec<usize>> = vec![vec![outputs.len() / 2], (0..outputs.len()).step_by(2).collect()];
+        for pick in picks {
+            let selected: Vec<i32> = pick.iter().map(|&k| outputs[k]).collect();
+            let mut cone = circuit.extract_cone(&selected);
+            assert!(cone.num_gates <= circuit.num_gates, "{}", name);
+            assert_eq!(cone.num_inputs, circuit.num_inputs);
+            assert_eq!(simulator::output_signals(&cone), selected, "{}", name);
+            for (k, gate) in cone.gates.iter().enumerate() {
+                assert!(gate.inputs[..gate.fanin].iter().all(|&s| cone.driver_index(s).is_none_or(|d| d < k)), "{}", name);
+            }
+
+            let wanted: Vec<Vec<bool>> = expected.iter()
+                .map(|row| pick.iter().map(|&k| row[k]).collect())
+                .collect();
+            assert_eq!(simulator::simulate_netlist(&cone, &vectors).unwrap(), wanted, "{}", name);
+
+            // The cone schedules and maps on its own
+            scheduler::compute_asap_schedule(&mut cone);
+            scheduler::compute_alap_schedule(&mut cone);
+            scheduler::compute_list_schedule(&mut cone);
+            assert!(cone.max_asap <= circuit.max_asap);
+            let mapping = mapper::create_compact_mapping(&mut cone);
+            assert_eq!(simulator::simulate_mapping(&cone, &mapping, &vectors).unwrap(), wanted, "{}", name);
+        }
+
+        // All outputs keep every gate that reaches one
+        let whole = circuit.extract_cone(&outputs);
+        assert_eq!(simulator::simulate_netlist(&whole, &vectors).unwrap(), expected, "{}", name);
+    }
+}
+
+#[test]
+fn internal_signals_can_be_isolated() {
+    let circuit = common::parsed(&common::bundled("c17"));
+    // n9 = NOR(n8, n6), n8 = NOR(n7, n4), with n7 = NOT(x3), n4 = NOT(x2), n6 = NOT(x1)
+    let cone = circuit.extract_cone(&[9]);
+    let mut gates: Vec<i32> = cone.gates.iter().map(|g| g.out).collect();
+    gates.sort_unstable();
+    assert_eq!(gates, vec![4, 6, 7, 8, 9]);
+    assert_eq!(simulator::output_signals(&cone), vec![9]);
+    assert_eq!(cone.num_outputs, 1);
+
+    // Undriven signals add nothing
+    assert_eq!(circuit.extract_cone(&[9, 4000, MAX_GATES as i32]).num_gates, 5);
+    assert_eq!(circuit.extract_cone(&[]).num_gates, 0);
+
+    for id in [0, 9, 7999, MAX_GATES as i32, MAX_GATES as i32 + 12, -3] {
+        assert_eq!(signal_id(&signal_name(id)), Some(id));
+    }
+    assert_eq!(signal_id("42"), Some(42));
+    for name in ["", "n", "y3", "n8000", "x1000", "t0", "n-1"] {
+        assert_eq!(signal_id(name), None, "{}", name);
+    }
+}
+
+#[test]
+fn cone_queries_agree_with_the_schedule() {
+    for (name, text) in common::circuits() {
+        let circuit = common::scheduled(&text);
+        for i in (0..circuit.num_gates).step_by(53) {
+            let gate = &circuit.gates[i];
+            // Depth counts the gates on the longest input path, as ASAP levels do
+            assert_eq!(circuit.depth(i) as i32, gate.asap_level, "{} {}", name, signal_name(gate.out));
+
+            let fanin = circuit.fanin_cone(i);
+            assert!(fanin.contains(&i) && fanin.windows(2).all(|w| w[0] < w[1]), "{}", name);
+            assert!(fanin.len() <= circuit.extract_cone(&[gate.out]).num_gates, "{}", name);
+            for &j in [fanin.first(), fanin.last()].into_iter().flatten() {
+                assert!(circuit.fanout_cone(j).contains(&i), "{}", name);
+            }
+            for j in circuit.fanout_cone(i) {
+                assert!(j == i || circuit.gates[j].asap_level > gate.asap_level, "{}", name);
+            }
+        }
+    }
+
+    // n8 = NOR(n7, n4) reads two inverted inputs; n4 also feeds n5 and, through it, n10
+    let circuit = common::parsed(&common::bundled("c17"));
+    let gate = |name: &str| circuit.driver_index(signal_id(name).unwrap()).unwrap();
+    let names = |cone: Vec<usize>| cone.into_iter().map(|i| signal_name(circuit.gates[i].out)).collect::<Vec<_>>();
+    assert_eq!((circuit.depth(gate("n4")), circuit.depth(gate("n8")), circuit.depth(gate("n13"))), (1, 2, 4));
+    assert_eq!(names(circuit.fanin_cone(gate("n8"))), ["n4", "n7", "n8"]);
+    assert_eq!(circuit.fanout_cone(gate("n8")).len(), 7);
+    assert_eq!(circuit.fanout_cone(gate("n4")).len(), 9);
+}
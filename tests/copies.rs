@@ -0,0 +1,194 @@
+//tests/copies.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use std::{env, fs, process};
+
+use delphi::program::Program;
+use delphi::lint::{self, LintKind};
+use delphi::{generator, mapper, scheduler, simulator, verify, CrossbarMapping};
+
+// Copies of each signal per level, as the compact mapping placed them: each
+// copy sits just before the gate reading it
+fn mapped_copies(mapping: &CrossbarMapping) -> HashMap<(i32, i32), usize> {
+    let mut copies = HashMap::new();
+    for cell in mapping.occupied_cells().filter(|c| c.is_copy) {
+        let reader = mapping.cell(cell.idx as usize, cell.jdx as usize + 1);
+        *copies.entry((cell.value, reader.asap_level)).or_insert(0) += 1;
+    }
+    copies
+}
+
+#[test]
+fn copy_pressure_matches_the_compact_mapping() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let pressure = scheduler::copy_pressure(&circuit);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let copies = mapped_copies(&mapping);
+
+        assert_eq!(pressure.iter().map(|p| p.copies).sum::<usize>(), copies.values().sum::<usize>(), "{}", name);
+        for p in &pressure {
+            assert_eq!(copies.get(&(p.signal, p.peak_level)), Some(&p.peak), "{} {}", name, p.signal);
+            assert!(p.copies <= p.fanout);
+        }
+        assert!(pressure.windows(2).all(|w| w[0].copies >= w[1].copies), "{}", name);
+    }
+}
+
+#[test]
+fn smoothing_spreads_copies_within_the_depth() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 9);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let (depth, before) = (circuit.max_asap, scheduler::copy_pressure(&circuit));
+
+        let delayed = scheduler::smooth_copies(&mut circuit, 1);
+        let after = scheduler::copy_pressure(&circuit);
+        assert_eq!(circuit.max_asap, depth);
+        assert!(after.iter().all(|p| p.peak <= before.iter().find(|b| b.signal == p.signal).unwrap().peak), "{}", name);
+        let total = |pressure: &[scheduler::CopyPressure]| pressure.iter().map(|p| p.copies).sum::<usize>();
+        assert_eq!(total(&after), total(&before), "{}", name);
+        if name != "c17" {
+            assert!(delayed > 0, "{}", name);
+            let excess = |pressure: &[scheduler::CopyPressure]| pressure.iter().map(|p| p.peak - 1).sum::<usize>();
+            assert!(excess(&after) < excess(&before), "{}", name);
+        }
+
+        // Every gate still follows its operands, and the mapping computes the same
+        for gate in &circuit.gates[..circuit.num_gates] {
+            assert!(gate.asap_level <= depth);
+            for &input in &gate.inputs[..gate.fanin] {
+                assert!(circuit.driver(input).is_none_or(|d| d.asap_level < gate.asap_level), "{}", name);
+            }
+        }
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}", name);
+        assert_eq!(mapped_copies(&mapping).values().sum::<usize>(), total(&after), "{}", name);
+    }
+}
+
+#[test]
+fn copy_overhead_is_reported_in_the_metrics() {
+    let dir = env::temp_dir().join(format!("delphi-copies-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let per_level = scheduler::copies_per_level(&circuit);
+        assert_eq!(per_level.len(), circuit.max_asap as usize, "{}", name);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let copies: usize = mapped_copies(&mapping).values().sum();
+        assert_eq!(per_level.iter().sum::<usize>(), copies, "{}", name);
+
+        let path = dir.join(format!("{}_compact.txt", name));
+        let cycles = generator::generate_micro_ops(&circuit, &mapping, false, &path).unwrap().cycles();
+        let program = fs::read_to_string(&path).unwrap();
+        let emitted: usize = cycles.copies.iter().sum();
+        assert_eq!(emitted, copies, "{}", name);
+        assert!(program.contains(&format!("Copy Operations   : {}\n", emitted)), "{}", name);
+        assert!(program.contains(&format!("Copy Cycles       : {}\n", 2 * emitted)), "{}", name);
+        assert!(program.contains(&format!("Cycles with Copies: {} ", cycles.total() + 2 * emitted)), "{}", name);
+        let overhead = 200.0 * emitted as f64 / cycles.total() as f64;
+        assert!((cycles.copy_overhead() - overhead).abs() < 1e-9, "{}", name);
+    }
+
+    // Naive programs have no copies to report
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let path = dir.join("c17_naive.txt");
+    generator::generate_micro_ops(&circuit, &naive, true, &path).unwrap();
+    assert!(!fs::read_to_string(&path).unwrap().contains("Copy Operations"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reused_copies_are_written_once() {
+    let dir = env::temp_dir().join(format!("delphi-reuse-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 5);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let mut mapping = mapper::create_compact_mapping(&mut circuit);
+        let before = Program::from_mapping(&circuit, &mapping).unwrap().cycles();
+
+        let reuse = mapper::reuse_copies(&mut circuit, &mut mapping);
+        assert_eq!(reuse.before, before.copies.iter().sum::<usize>(), "{}", name);
+        if name != "c17" {
+            assert!(reuse.reused() > 0, "{}", name);
+        }
+        // No row is left holding two copies of one signal
+        let mut held = HashSet::new();
+        assert!(mapping.occupied_cells().filter(|c| c.is_copy).all(|c| held.insert((c.idx, c.value))), "{}", name);
+        assert_eq!(held.len(), reuse.after, "{}", name);
+
+        verify::verify_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}", name);
+        let path = dir.join(format!("{}_compact.txt", name));
+        let program = generator::generate_micro_ops(&circuit, &mapping, false, &path).unwrap();
+        assert_eq!(program.cycles().copies.iter().sum::<usize>(), reuse.after, "{}", name);
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{}", name);
+        assert!(lint::lint_micro_ops_text(&fs::read_to_string(&path).unwrap()).is_empty(), "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn listings_write_each_copy() {
+    let dir = env::temp_dir().join(format!("delphi-copy-ops-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits().into_iter().take(3) {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let path = dir.join(format!("{}_compact.txt", name));
+        let program = generator::generate_micro_ops(&circuit, &mapping, false, &path).unwrap();
+        let listing = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = listing.lines().filter(|l| l.starts_with("COPY ")).collect();
+        assert_eq!(lines.len(), program.cycles().copies.iter().sum::<usize>(), "{}", name);
+        assert!(lint::lint_micro_ops_text(&listing).is_empty(), "{}", name);
+    }
+
+    // A copy into a cell its level already copies to, a cycle later, and one
+    // of a cell no level has written yet
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let path = dir.join("c17_compact.txt");
+    generator::generate_micro_ops(&circuit, &mapping, false, &path).unwrap();
+    let listing = fs::read_to_string(&path).unwrap();
+    let copy = listing.lines().find(|l| l.starts_with("COPY ")).unwrap();
+    let twice = listing.replacen(copy, &format!("{}\n{}", copy, copy), 1);
+    let issues = lint::lint_micro_ops_text(&twice);
+    assert_eq!(issues.iter().map(|i| i.kind).collect::<Vec<_>>(), [LintKind::Clobber], "{:?}", issues);
+    let early = listing.replacen(copy, "COPY       4x6       0x2", 1);
+    let issues = lint::lint_micro_ops_text(&early);
+    assert_eq!(issues.iter().map(|i| i.kind).collect::<Vec<_>>(), [LintKind::ReadBeforeWrite], "{:?}", issues);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn folded_copies_count_in_the_reads_and_writes() {
+    let dir = env::temp_dir().join(format!("delphi-fold-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::synthetic_netlist(32, 500, 0x5eed));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let apart = Program::from_mapping(&circuit, &mapping).unwrap().cycles();
+    let copies: usize = apart.copies.iter().sum();
+    assert!(copies > 0);
+
+    circuit.fold_copies = true;
+    let path = dir.join("folded.txt");
+    let folded = generator::generate_micro_ops(&circuit, &mapping, false, &path).unwrap().cycles().folding(true);
+    assert_eq!(folded.reads(), apart.reads() + copies);
+    assert_eq!(folded.writes(), apart.writes() + copies);
+    assert_eq!(folded.total(), apart.total() + folded.copy_cycles());
+    assert!((folded.copy_overhead() - apart.copy_overhead()).abs() < 1e-9);
+
+    let listing = fs::read_to_string(&path).unwrap();
+    assert!(listing.contains(&format!("Read Operations   : {}\n", folded.reads())), "{}", listing);
+    assert!(listing.contains(&format!("Total Cycles      : {}\n", folded.total())), "{}", listing);
+    assert!(listing.contains(&format!("Copy Cycles       : {}, in the reads and writes (", folded.copy_cycles())), "{}", listing);
+    assert!(!listing.contains("Cycles with Copies"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,54 @@
+//tests/cost.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::cost::{CostExpr, Variable};
+use delphi::mapper;
+use delphi::metrics::{ProgramMetrics, RunMetrics};
+use delphi::program::Program;
+
+#[test]
+fn expressions_follow_the_usual_precedence() {
+    let circuit = RunMetrics { gates: 10, levels: 4, ..Default::default() };
+    let program = ProgramMetrics { rows: 3, cols: 5, copies: 6, cycles: 20, ..Default::default() };
+    let cost = |text: &str| text.parse::<CostExpr>().unwrap().evaluate(&circuit, &program).unwrap();
+    assert_eq!(cost("rows*2 + copies*0.5 + levels"), 13.0);
+    assert_eq!(cost("rows + cols * 2"), 13.0);
+    assert_eq!(cost("(rows + cols) * 2"), 16.0);
+    assert_eq!(cost("cycles - levels - 1"), 15.0);
+    assert_eq!(cost("-2^2 + 2^3^2 / area"), -4.0 + 512.0 / 15.0);
+    assert_eq!(cost("max(rows, cols, 4) - min(gates, copies) + log2(levels)"), 1.0);
+    assert_eq!(cost("  levels "), 4.0);
+
+    let expr: CostExpr = "area * 2 + energy_pj + area".parse().unwrap();
+    assert_eq!(expr.variables(), [Variable::Area, Variable::EnergyPj]);
+    assert!(expr.needs_tech());
+    assert!(expr.evaluate(&circuit, &program).unwrap_err().to_string().contains("`energy_pj` needs a technology"));
+    assert!("cycles / (cols - 5)".parse::<CostExpr>().unwrap().evaluate(&circuit, &program).is_err());
+
+    for (text, column, message) in [
+        ("rows*", 6, "expected a number, variable or `(` at the end"),
+        ("rows + copis", 8, "unknown variable `copis`"),
+        ("(rows", 6, "expected `)`"),
+        ("rows cols", 6, "unexpected `cols`"),
+        ("log2(rows, 2)", 14, "log2 takes one argument"),
+        ("1.2.3", 1, "`1.2.3` is not a number"),
+    ] {
+        let error = text.parse::<CostExpr>().unwrap_err().to_string();
+        assert!(error.contains(&format!("at column {}: {}", column, message)), "{}", error);
+    }
+}
+
+#[test]
+fn costs_read_a_runs_metrics() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let metrics = RunMetrics::new(&circuit);
+    let compact = ProgramMetrics::new(&program, &program.cycles(), None);
+    let expr: CostExpr = "rows*2 + copies*0.5 + levels".parse().unwrap();
+    assert_eq!(expr.to_string(), "rows*2 + copies*0.5 + levels");
+    assert_eq!(expr.evaluate(&metrics, &compact).unwrap(), (program.rows * 2) as f64 + compact.copies as f64 * 0.5 + circuit.max_asap as f64);
+    let area: CostExpr = "area".parse().unwrap();
+    assert_eq!(area.evaluate(&metrics, &compact).unwrap(), (program.rows * program.cols) as f64);
+}
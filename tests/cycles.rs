@@ -0,0 +1,125 @@
+//tests/cycles.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::lint::{self, LintKind, Place};
+use delphi::program::{self, Cell, Program};
+use delphi::{generator, mapper, MAX_COL};
+
+// Value of a "Name : value" line of the Metrics footer
+fn metric(program: &str, name: &str) -> usize {
+    program.lines()
+        .find_map(|l| l.strip_prefix(name).and_then(|r| r.split(':').nth(1)))
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or_else(|| panic!("no {} in the footer", name))
+}
+
+#[test]
+fn cycles_follow_the_emitted_ops() {
+    let dir = env::temp_dir().join(format!("delphi-cycles-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let (mapped, streamed) = (dir.join(format!("{}_mapped.txt", name)), dir.join(format!("{}_streamed.txt", name)));
+        let program = generator::generate_micro_ops(&circuit, &mapping, false, &mapped).unwrap();
+        assert_eq!(generator::stream_compact_micro_ops(&mut circuit, &streamed).unwrap(), program, "{}", name);
+        let cycles = program.cycles();
+        assert_eq!(cycles.levels, circuit.max_asap as usize, "{}", name);
+
+        let listing = fs::read_to_string(&mapped).unwrap();
+        assert_eq!(listing, fs::read_to_string(&streamed).unwrap(), "{}", name);
+        assert!(lint::lint_micro_ops_text(&listing).is_empty(), "{}", name);
+        let count = |prefix: &str| listing.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!(count("RESET"), cycles.resets, "{}", name);
+        assert_eq!(count("# Level:"), cycles.levels, "{}", name);
+        assert!(cycles.resets >= cycles.levels, "{}", name);
+
+        assert_eq!(metric(&listing, "Reset Operations"), cycles.resets, "{}", name);
+        assert_eq!(metric(&listing, "Write Operations"), 1 + cycles.resets + cycles.levels, "{}", name);
+        assert_eq!(metric(&listing, "Total Cycles"), 3 * cycles.levels + cycles.resets + 1, "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn naive_programs_run_one_gate_at_a_time() {
+    let dir = env::temp_dir().join(format!("delphi-serial-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        // Larger circuits do not fit the naive mapping's one row
+        if circuit.num_inputs + circuit.num_gates > MAX_COL {
+            continue;
+        }
+        let mapping = mapper::create_naive_mapping(&mut circuit);
+        let path = dir.join(format!("{}_naive.txt", name));
+        let program = generator::generate_micro_ops(&circuit, &mapping, true, &path).unwrap();
+        let (levelled, serial) = (program.cycles(), program.serialized_cycles());
+        assert_eq!(serial.levels, levelled.levels, "{}", name);
+        assert_eq!(serial.steps(), circuit.num_gates, "{}", name);
+        assert_eq!(serial.total() - levelled.total(), 3 * (circuit.num_gates - levelled.levels), "{}", name);
+
+        // The footer counts a read, a write and an evaluation per gate
+        let listing = fs::read_to_string(&path).unwrap();
+        assert_eq!(metric(&listing, "Read Operations"), circuit.num_gates, "{}", name);
+        assert_eq!(metric(&listing, "Evaluation Cycles"), circuit.num_gates, "{}", name);
+        assert_eq!(metric(&listing, "Total Cycles"), serial.total(), "{}", name);
+        assert!(listing.contains("Execution         : serialized"), "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resets_drive_one_row_or_column() {
+    let cells = |list: &[(u32, u32)]| list.iter().map(|&(row, col)| Cell { row, col }).collect::<Vec<_>>();
+    // Row 0 holds three of the cells, column 4 only two
+    let groups = program::reset_groups(&cells(&[(0, 4), (0, 5), (0, 6), (2, 4)]));
+    assert_eq!(groups, vec![cells(&[(0, 4), (0, 5), (0, 6)]), cells(&[(2, 4)])]);
+    assert_eq!(program::reset_groups(&cells(&[(1, 3), (2, 3)])), vec![cells(&[(1, 3), (2, 3)])]);
+    assert!(program::reset_groups(&[]).is_empty());
+
+    let program = "# Level:  1 ___\nRESET 0x1\n   0 False    0 /0                         1 True\n   1 False    0 /1                         1 True\nRESET 0x2 1x3\n";
+    let issues = lint::lint_micro_ops_text(program);
+    let kinds: Vec<(Place, LintKind)> = issues.iter().map(|i| (i.at, i.kind)).collect();
+    assert_eq!(kinds, vec![(Place::Line(4), LintKind::Uninitialized), (Place::Line(5), LintKind::Syntax)], "{:?}", issues);
+
+    // Listings without RESETs are not checked for them
+    let legacy = "# Level:  1 ___\n   0 False    0 /0                         1 True\n";
+    assert!(lint::lint_micro_ops_text(legacy).is_empty());
+}
+
+#[test]
+fn the_timeline_spans_every_cycle() {
+    let dir = env::temp_dir().join(format!("delphi-timeline-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        let cycles = program.cycles();
+        let timeline = program.timeline();
+        assert_eq!(timeline.len(), cycles.total() + cycles.copy_cycles(), "{}", name);
+        let phase = |p: &str| timeline.iter().filter(|u| u.phase == p).count();
+        assert_eq!(phase("reset"), cycles.resets, "{}", name);
+        assert_eq!(phase("evaluate"), cycles.levels, "{}", name);
+        assert_eq!(phase("copy"), cycles.copy_cycles(), "{}", name);
+
+        let area = program.rows * program.cols;
+        assert!(timeline.iter().enumerate().all(|(k, u)| u.cycle == k && u.live <= u.occupied && u.occupied <= area), "{}", name);
+        assert!(timeline.windows(2).all(|w| w[0].occupied <= w[1].occupied && w[0].level <= w[1].level), "{}", name);
+        // Every gate evaluates once, and the outputs are held to the end
+        let evaluated: usize = timeline.iter().filter(|u| u.phase == "evaluate").map(|u| u.active).sum();
+        assert_eq!(evaluated, circuit.num_gates, "{}", name);
+        assert!(timeline.last().unwrap().live >= program.outputs().count(), "{}", name);
+
+        let path = dir.join(format!("{}.csv", name));
+        generator::generate_timeline(&program, &path).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().count(), timeline.len() + 1, "{}", name);
+        assert!(csv.starts_with("cycle,level,phase,active_ops,occupied_cells,live_cells,crossbar_cells\n0,0,input,"), "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,48 @@
+//tests/decomposed.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::provenance::ArtifactHeader;
+use delphi::{generator, parser, simulator, Circuit};
+
+#[test]
+fn decomposed_netlists_read_back_as_the_scheduled_circuit() {
+    let dir = env::temp_dir().join(format!("delphi-decomposed-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let circuit = common::scheduled(&text);
+        let path = dir.join(format!("{}_decomposed.txt", name));
+        generator::generate_decomposed_netlist(&circuit, &path).unwrap();
+        // The provenance header is a comment the parser skips
+        let netlist = dir.join(format!("{}.txt", name));
+        fs::write(&netlist, &text).unwrap();
+        ArtifactHeader::new(&netlist, &["process".to_string()], &circuit).unwrap().stamp(&path).unwrap();
+
+        let mut read = Circuit::new();
+        parser::parse_netlist(&path, &mut read).unwrap();
+        assert_eq!((read.num_gates, read.num_outputs), (circuit.num_gates, circuit.num_outputs), "{}", name);
+        assert!(read.gates.iter().all(|g| g.out >= 0), "{}", name);
+        let read = common::scheduled(&fs::read_to_string(&path).unwrap());
+        let vectors = simulator::random_vectors(circuit.num_inputs, 64, 5);
+        assert_eq!(simulator::simulate_netlist(&read, &vectors).unwrap(),
+            simulator::simulate_netlist(&circuit, &vectors).unwrap(), "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn spelled_out_gates_are_one_gate_of_any_width() {
+    // Four variables are a cascade unless the line names the gate
+    let cascade = common::parsed("n10001 = x0 x1 x2\n");
+    assert_eq!(cascade.num_gates, 2);
+    let single = common::parsed("n10001 = NOR(x0, x1, x2)   # one gate\nn10002 = NOT(n1)\n");
+    assert_eq!(single.num_gates, 2);
+    assert_eq!(single.gates[0].fanin, 3);
+    assert_eq!(single.num_outputs, 2);
+
+    let mut circuit = Circuit::new();
+    let error = parser::parse_netlist_str("n10001 = NAND(x0, x1)\n", &mut circuit).unwrap_err();
+    assert!(format!("{:#}", error).contains("NAND gates cannot be read"), "{:#}", error);
+}
@@ -0,0 +1,91 @@
+//tests/determinism.rs
+// The parallel paths must reproduce the sequential artifacts bit for bit,
+// whatever the thread count
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+use std::path::PathBuf;
+
+use delphi::{generator, mapper, parallel, Circuit, CrossbarMapping, MemristiveGate};
+
+const THREAD_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+fn gate_maps(circuit: &Circuit) -> Vec<Option<Box<MemristiveGate>>> {
+    circuit.gates.iter().map(|g| g.gate_map.clone()).collect()
+}
+
+fn micro_ops(circuit: &Circuit, mapping: &CrossbarMapping, path: &PathBuf) -> String {
+    generator::generate_micro_ops(circuit, mapping, false, path).unwrap();
+    fs::read_to_string(path).unwrap()
+}
+
+fn scratch_dir() -> PathBuf {
+    let dir = env::temp_dir().join(format!("delphi-determinism-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn parallel_compact_mapping_matches_sequential_on_bench_suite() {
+    let dir = scratch_dir();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let sequential = mapper::create_compact_mapping(&mut circuit);
+        let sequential_maps = gate_maps(&circuit);
+        let sequential_ops = micro_ops(&circuit, &sequential, &dir.join(format!("{}_seq.txt", name)));
+
+        for threads in THREAD_COUNTS {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let parallel = pool.install(|| mapper::create_compact_mapping_parallel(&mut circuit));
+            assert!(parallel == sequential, "{}: crossbar differs with {} threads", name, threads);
+            assert!(gate_maps(&circuit) == sequential_maps, "{}: gate maps differ with {} threads", name, threads);
+
+            let path = dir.join(format!("{}_par{}.txt", name, threads));
+            assert_eq!(micro_ops(&circuit, &parallel, &path), sequential_ops,
+                "{}: micro-ops differ with {} threads", name, threads);
+        }
+    }
+}
+
+#[test]
+fn streamed_micro_ops_match_sequential_on_bench_suite() {
+    let dir = scratch_dir();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let sequential = mapper::create_compact_mapping(&mut circuit);
+        let expected = micro_ops(&circuit, &sequential, &dir.join(format!("{}_mapped.txt", name)));
+
+        for threads in THREAD_COUNTS {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let path = dir.join(format!("{}_stream{}.txt", name, threads));
+            pool.install(|| generator::stream_compact_micro_ops(&mut circuit, &path)).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), expected,
+                "{}: streamed micro-ops differ with {} threads", name, threads);
+        }
+    }
+}
+
+#[test]
+fn pools_handed_to_the_entry_points_give_the_same_artifacts() {
+    let dir = scratch_dir();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let sequential = mapper::create_compact_mapping(&mut circuit);
+        let expected = micro_ops(&circuit, &sequential, &dir.join(format!("{}_ref.txt", name)));
+
+        for threads in THREAD_COUNTS {
+            let pool = parallel::thread_pool(threads).unwrap();
+            assert_eq!(pool.install(rayon::current_num_threads), threads);
+            assert!(parallel::create_compact_mapping_in(&pool, &mut circuit) == sequential,
+                "{}: crossbar differs on a pool of {}", name, threads);
+            let path = dir.join(format!("{}_pool{}.txt", name, threads));
+            parallel::stream_compact_micro_ops_in(&pool, &mut circuit, &path).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), expected, "{}: micro-ops differ on a pool of {}", name, threads);
+        }
+    }
+    // Chunks are split across the threads of the calling pool
+    let pool = parallel::thread_pool(4).unwrap();
+    assert_eq!(pool.install(|| delphi::calculate_chunk_size(400)), 100);
+    assert_eq!(pool.install(|| delphi::calculate_chunk_size(20)), 20);
+}
@@ -0,0 +1,107 @@
+//tests/diagnostics.rs
+use std::{env, fs, process};
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::diagnostics::{Diagnostics, Severity};
+use delphi::mapper::{self, Placement};
+use delphi::tech::Periphery;
+use delphi::{parser, scheduler, Circuit};
+
+fn codes(circuit: &Circuit) -> Vec<(&'static str, &'static str)> {
+    circuit.diagnostics.iter().map(|d| (d.source, d.code)).collect()
+}
+
+#[test]
+fn collector_counts_and_merges_repeats() {
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.warn("mapper", "near-capacity", "The naive mapping uses 950 of 1000 columns");
+    diagnostics.info("scheduler", "clamped", "Copy budget 0 raised to 1 copy per signal and level");
+    // Remapping reports the same finding again
+    diagnostics.warn("mapper", "near-capacity", "The naive mapping uses 950 of 1000 columns");
+    diagnostics.warn("mapper", "near-capacity", "The compact mapping uses 460 of 500 rows");
+    diagnostics.error("suite", "failed", "c7552: out of columns");
+
+    assert_eq!(diagnostics.len(), 4);
+    assert_eq!((diagnostics.count(Severity::Error), diagnostics.count(Severity::Warning)), (1, 2));
+    assert!(diagnostics.has_errors());
+    assert_eq!(diagnostics.summary(), "1 error, 2 warnings, 1 info");
+
+    // Most severe first, one row per source and code
+    let table = diagnostics.table();
+    let rows: Vec<&str> = table.lines().skip(1).collect();
+    assert_eq!(rows.len(), 3, "{}", table);
+    assert!(rows[0].starts_with("error"), "{}", table);
+    assert!(rows[1].contains("near-capacity") && rows[1].ends_with("(+1 more)"), "{}", table);
+    assert!(rows[2].starts_with("info"), "{}", table);
+}
+
+#[test]
+fn json_report_lists_every_finding() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    scheduler::smooth_copies(&mut circuit, 0);
+    circuit.diagnostics.warn("mapper", "copy-overhead", "Copies add 120.0% to the compact program's cycles");
+
+    let path = env::temp_dir().join(format!("delphi-diagnostics-{}.json", process::id()));
+    circuit.diagnostics.write_json("c17", &path).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(report["circuit"], "c17");
+    assert_eq!(report["counts"]["warning"], 1);
+    assert_eq!(report["counts"]["info"], 1);
+    let items = report["diagnostics"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["severity"], "info");
+    assert_eq!(items[0]["code"], "clamped");
+    assert_eq!(items[1]["source"], "mapper");
+}
+
+#[test]
+fn parser_reports_unused_inputs_and_missing_outputs() {
+    let dir = env::temp_dir().join(format!("delphi-diagnostics-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let load = |name: &str, text: &str| {
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        let mut circuit = Circuit::new();
+        parser::load_netlist(&path, &mut circuit).unwrap();
+        circuit
+    };
+
+    // x1 and x3 are never read
+    let gaps = load("gaps.txt", "n5 = x0 x2\nn10001 = n5 x4\n");
+    assert_eq!(codes(&gaps), [("parser", "unused-input")]);
+    assert!(gaps.diagnostics.iter().next().unwrap().message.ends_with("x1 x3"));
+
+    let open = load("open.txt", "n5 = x0 x1\nn6 = n5\n");
+    assert_eq!(codes(&open), [("parser", "no-outputs")]);
+
+    assert!(load("clean.txt", &common::bundled("c17")).diagnostics.is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mappings_close_to_the_array_size_are_reported() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    assert!(circuit.diagnostics.is_empty());
+    let (rows, cols) = (mapping.max_idx as usize + 1, mapping.max_jdx as usize + 1);
+
+    let periphery = |rows: usize, cols: usize| Periphery {
+        max_row_activations: 19,
+        drivers: 64,
+        rows: Some(rows),
+        cols: Some(cols),
+        reserved_rows: Vec::new(),
+        reserved_cols: Vec::new(),
+        storage_rows: Vec::new(),
+        storage_cols: Vec::new(),
+    };
+    Placement::new(&periphery(2 * rows, 2 * cols)).apply(&mut circuit, &mapping).unwrap();
+    assert!(circuit.diagnostics.is_empty());
+    Placement::new(&periphery(2 * rows, cols)).apply(&mut circuit, &mapping).unwrap();
+    assert_eq!(codes(&circuit), [("mapper", "near-capacity")]);
+    assert!(circuit.diagnostics.iter().next().unwrap().message.contains("columns"));
+}
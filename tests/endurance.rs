@@ -0,0 +1,78 @@
+//tests/endurance.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::{env, fs, process};
+
+use delphi::endurance::{self, Rotation};
+use delphi::mapper::{self, Placement};
+use delphi::tech::Technology;
+use delphi::{generator, lint, simulator};
+
+#[test]
+fn rotated_placements_compute_the_netlist() {
+    for (name, text) in common::circuits().into_iter().filter(|(name, _)| *name != "synthetic_2400") {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 11);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+
+        let rotation = Rotation::new(&Placement::default(), &mapping, 5, None).unwrap();
+        assert_eq!(rotation.shifts[0], (0, 0));
+        assert_eq!((rotation.rows, rotation.cols), (mapping.max_idx as usize + 1, mapping.max_jdx as usize + 1));
+        for placement in &rotation.placements {
+            let placed = placement.place(&mapping).unwrap();
+            assert_eq!(placed.occupied_cells().count(), mapping.occupied_cells().count(), "{}", name);
+            assert!(placed.max_idx <= mapping.max_idx && placed.max_jdx <= mapping.max_jdx, "{}", name);
+            assert_eq!(simulator::simulate_mapping(&circuit, &placed, &vectors).unwrap(), expected, "{}", name);
+        }
+        assert!(rotation.max_writes_per_run(&mapping) < endurance::max_writes_per_run(&mapping) as f64, "{}", name);
+    }
+}
+
+#[test]
+fn rotation_spreads_writes_over_the_area() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    assert_eq!(endurance::max_writes_per_run(&mapping), 2);
+
+    // A single placement changes nothing
+    let single = Rotation::new(&Placement::default(), &mapping, 1, None).unwrap();
+    assert_eq!(single.max_writes_per_run(&mapping), 2.0);
+
+    // Room for four disjoint copies: no cell is written by two placements
+    let rotation = Rotation::new(&Placement::default(), &mapping, 4, Some((40, 40))).unwrap();
+    assert_eq!(rotation.max_writes_per_run(&mapping), 0.5);
+    let shifts: HashSet<_> = rotation.shifts.iter().collect();
+    assert_eq!(shifts.len(), 4);
+
+    assert!(Rotation::new(&Placement::default(), &mapping, 0, None).is_err());
+    assert!(Rotation::new(&Placement::default(), &mapping, 2, Some((2, 2))).is_err());
+    assert!(Rotation::new(&Placement::default(), &mapping, 2, Some((600, 40))).is_err());
+}
+
+#[test]
+fn rotation_programs_pass_the_linter() {
+    let dir = env::temp_dir().join(format!("delphi-endurance-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let tech = Technology::load(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tech/profiles.toml"), None).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c7552"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let rotation = Rotation::new(&Placement::default(), &mapping, 3, None).unwrap();
+    let programs: Vec<_> = (0..3).map(|k| dir.join(format!("c7552_rot{}.txt", k))).collect();
+    generator::generate_rotation(&circuit, &mapping, &rotation, Some(&tech), &programs, dir.join("c7552_rotation.txt")).unwrap();
+
+    let ops = |text: &str| text.lines().filter(|l| !l.starts_with('#') && l.split_whitespace().count() > 3).count();
+    let first = fs::read_to_string(&programs[0]).unwrap();
+    for path in &programs {
+        assert!(lint::lint_micro_ops(path).unwrap().is_empty(), "{:?}", path);
+        assert_eq!(ops(&fs::read_to_string(path).unwrap()), ops(&first));
+    }
+    let report = fs::read_to_string(dir.join("c7552_rotation.txt")).unwrap();
+    assert!(report.contains("run r executes placement r mod 3"), "{}", report);
+    assert!(report.contains("c7552_rot2.txt"), "{}", report);
+    assert!(report.contains("Device Lifetime"), "{}", report);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,87 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::passes::{FanoutProfile, FanoutTrees, PassManager};
+use delphi::program::Program;
+use delphi::{generator, mapper, scheduler, simulator};
+
+#[test]
+fn trees_bound_the_fanout_and_keep_the_function() {
+    for (name, text) in common::circuits().into_iter().skip(1).take(1) {
+        let circuit = common::parsed(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 9);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        for max_fanout in [2, 3] {
+            let before = FanoutProfile::measure(&circuit, max_fanout);
+            let mut split = circuit.clone();
+            let mut passes = PassManager::new();
+            passes.add(Box::new(FanoutTrees { max_fanout }));
+            let report = &passes.run(&mut split).unwrap()[0];
+            let after = FanoutProfile::measure(&split, max_fanout);
+            assert!(!before.overloaded.is_empty(), "{}", name);
+            assert!(after.overloaded.is_empty(), "{}: {:?}", name, after.overloaded);
+            assert!(after.max_fanout <= max_fanout, "{}", name);
+            assert!(after.levels > before.levels, "{}", name);
+            assert_eq!(report.gates_after, after.gates, "{}", name);
+            // Only NOTs are added, none of them an output
+            assert!(split.gates[circuit.num_gates..].iter().all(|g| g.fanin == 1 && !g.is_output && g.out < 0), "{}", name);
+            assert_eq!(simulator::simulate_netlist(&split, &vectors).unwrap(), expected, "{} at {}", name, max_fanout);
+
+            scheduler::compute_asap_schedule(&mut split);
+            scheduler::compute_alap_schedule(&mut split);
+            scheduler::compute_list_schedule(&mut split);
+            let mapping = mapper::create_compact_mapping(&mut split);
+            let program = Program::from_mapping(&split, &mapping).unwrap();
+            assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{} at {}", name, max_fanout);
+        }
+    }
+
+    // Five readers of x0 over two leaves: an inverter heads the tree so the
+    // leaves carry x0 itself. The `@dont_touch` reader keeps reading x0
+    let text = "n11 = x0 x1\nn12 = x0 x2\nn13 = x0 x1\nn14 = x0 x2\nn15 = x0 x1\nn16 = x0 x2 @dont_touch\n\
+        n10017 = n11 n12\nn10018 = n13 n14\nn10019 = n15 n16\n.end\n";
+    let mut circuit = common::parsed(text);
+    let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+    let mut passes = PassManager::new();
+    passes.add(Box::new(FanoutTrees { max_fanout: 3 }));
+    let report = &passes.run(&mut circuit).unwrap()[0];
+    assert_eq!(report.gates_after - report.gates_before, 3, "{}", report);
+    let x0 = delphi::signal_id("x0").unwrap();
+    let head = circuit.readers(x0).iter().map(|&i| &circuit.gates[i]).find(|g| g.fanin == 1).unwrap();
+    assert_eq!(circuit.readers(x0).len(), 2);
+    assert_eq!(circuit.readers(head.out).len(), 2);
+    assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(), expected);
+}
+
+#[test]
+fn the_stats_compare_the_circuit_before_and_after() {
+    let dir = env::temp_dir().join(format!("delphi-fanout-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::parsed(&common::synthetic_netlist(32, 500, 0x5eed));
+    let before = FanoutProfile::measure(&circuit, 4);
+    let mut passes = PassManager::new();
+    passes.add(Box::new(FanoutTrees { max_fanout: 4 }));
+    let report = &passes.run(&mut circuit).unwrap()[0];
+    assert!(report.to_string().contains(" trees for "), "{}", report);
+    let after = FanoutProfile::measure(&circuit, 4);
+
+    let path = dir.join("stats.txt");
+    fs::write(&path, "").unwrap();
+    generator::append_fanout_trees(&path, &circuit, 4, &before, &after).unwrap();
+    let stats = fs::read_to_string(&path).unwrap();
+    assert!(stats.starts_with("FANOUT TREES:\n"), "{}", stats);
+    assert!(stats.contains(&format!("Split Signals     : {} of {} over the limit\n", before.overloaded.len(), before.overloaded.len())), "{}", stats);
+    assert!(stats.contains(&format!("Tree NOTs         : {}\n", after.gates - before.gates)), "{}", stats);
+    assert!(stats.contains(&format!("{:<26}{:>10}{:>10}\n", "Most readers", before.max_fanout, after.max_fanout)), "{}", stats);
+    // Each split signal now feeds the head of its tree alone, or two to four
+    // nodes of its first stage
+    let (signal, readers) = before.overloaded[0];
+    let row = format!("{:<10}{:>10}", delphi::signal_name(signal), readers);
+    let line = stats.lines().find(|l| l.starts_with(&row)).unwrap();
+    let now: usize = line[row.len()..].trim().parse().unwrap();
+    assert!((1..=4).contains(&now), "{}", line);
+    fs::remove_dir_all(&dir).unwrap();
+}
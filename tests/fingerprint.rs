@@ -0,0 +1,70 @@
+//tests/fingerprint.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::Path;
+
+use delphi::fingerprint::{DuplicateIndex, Fingerprint};
+
+// The netlist with its gate lines and outputs in reverse order, two-operand
+// gates reading their operands the other way round, inputs numbered from
+// the other end and gates renumbered by `shift`
+fn scrambled(text: &str, inputs: usize, shift: usize) -> String {
+    let rename = |token: &str| match (token.strip_prefix('x'), token.strip_prefix('n').and_then(|k| k.parse::<usize>().ok())) {
+        (Some(k), _) => format!("x{}", inputs - 1 - k.parse::<usize>().unwrap()),
+        (_, Some(k)) if k < 10000 => format!("n{}", k + shift),
+        _ => token.to_string(),
+    };
+    let (gates, outputs): (Vec<&str>, Vec<&str>) = text.lines().filter(|l| l.contains('=')).partition(|l| l[1..l.find(' ').unwrap()].parse::<usize>().unwrap() < 10000);
+    let mut lines: Vec<String> = gates.iter().rev()
+        .map(|line| {
+            let (out, operands) = line.split_once(" = ").unwrap();
+            let mut operands: Vec<String> = operands.split_whitespace().map(rename).collect();
+            if operands.len() == 2 {
+                operands.reverse();
+            }
+            format!("{} = {}", rename(out), operands.join(" "))
+        })
+        .collect();
+    // Outputs keep their numbers, so the last takes the first's
+    let count = outputs.len();
+    lines.extend(outputs.iter().enumerate().rev().map(|(k, line)| {
+        let (_, signal) = line.split_once(" = ").unwrap();
+        format!("n{} = {}", 10001 + count - 1 - k, rename(signal))
+    }));
+    lines.join("\n") + "\n.end\n"
+}
+
+#[test]
+fn fingerprints_ignore_names_and_order_but_not_wiring() {
+    let c17 = common::parsed(&common::bundled("c17"));
+    let fingerprint = Fingerprint::of(&c17);
+    assert_eq!(Fingerprint::of(&common::parsed(&scrambled(&common::bundled("c17"), 5, 40))), fingerprint);
+    assert_eq!((fingerprint.inputs, fingerprint.outputs, fingerprint.gates), (5, 2, c17.num_gates));
+    // n13 reads n5 instead of n9
+    let rewired = common::bundled("c17").replace("n13 = n12 n9", "n13 = n12 n5");
+    assert_ne!(Fingerprint::of(&common::parsed(&rewired)), fingerprint);
+
+    let synthetic = common::synthetic_netlist(32, 500, 0x5eed);
+    let fingerprint = Fingerprint::of(&common::parsed(&synthetic));
+    assert_eq!(Fingerprint::of(&common::parsed(&scrambled(&synthetic, 32, 0))), fingerprint);
+    assert_ne!(Fingerprint::of(&common::parsed(&common::synthetic_netlist(32, 500, 0x5eee))), fingerprint);
+    // Same counts, one operand moved
+    let moved = synthetic.replacen("x0", "x1", 1);
+    assert_ne!(Fingerprint::of(&common::parsed(&moved)), fingerprint);
+}
+
+#[test]
+fn the_index_names_the_first_of_each_structure() {
+    let mut index = DuplicateIndex::new(true);
+    let mut copy = common::parsed(&scrambled(&common::bundled("c17"), 5, 40));
+    copy.bench_name = "c17_copy".to_string();
+    let mut c17 = common::parsed(&common::bundled("c17"));
+    c17.bench_name = "c17".to_string();
+    assert_eq!(index.check(&c17, Path::new("a/c17.txt")), None);
+    assert_eq!(index.check(&common::parsed(&common::synthetic_netlist(32, 500, 0x5eed)), Path::new("a/s.txt")), None);
+    for _ in 0..2 {
+        assert_eq!(index.check(&copy, Path::new("b/c17_copy.txt")), Some(("c17".to_string(), "a/c17.txt".into())));
+    }
+    assert_eq!(index.len(), 2);
+}
@@ -0,0 +1,255 @@
+//tests/formats.rs
+use std::{env, fs, process};
+
+use delphi::metrics::RunMetrics;
+use delphi::parser::{self, Format};
+use delphi::presynth::Presynth;
+use delphi::suite::{self, Status, Suite, SuiteRow};
+use delphi::{simulator, verify, Circuit};
+
+// Full adder a + b + c -> (sum, carry) in every supported format
+const FULL_ADDER_AAG: &str = "\
+aag 10 3 0 2 7
+2
+4
+6
+18
+21
+8 2 4
+10 3 5
+12 9 11
+14 12 6
+16 13 7
+18 15 17
+20 9 15
+";
+const FULL_ADDER_BLIF: &str = "\
+.model fa
+.inputs a b c
+.outputs s \\
+  co
+.names p c s
+10 1
+01 1
+.names a b c co
+11- 1
+1-1 1
+-11 1
+.names a b p   # defined after its reader
+01 1
+10 1
+.end
+";
+const FULL_ADDER_VERILOG: &str = "\
+module top ( x , s , co ) ;
+  input [2:0] x ;
+  output s , co ;
+  wire p ;
+  assign s = p ^ x[2] ;  /* reads p before it is assigned */
+  assign p = x[0] ^ x[1] ;
+  assign co = ( x[0] & x[1] ) | ~( ~x[2] | ~p ) ;
+endmodule
+";
+
+fn loaded(name: &str, data: &[u8]) -> Circuit {
+    let dir = env::temp_dir().join(format!("delphi-formats-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, data).unwrap();
+    let mut circuit = Circuit::new();
+    parser::load_netlist(&path, &mut circuit).unwrap();
+    verify::verify_netlist(&circuit).unwrap();
+    circuit
+}
+
+fn truth_table(circuit: &Circuit) -> Vec<Vec<bool>> {
+    let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+    simulator::simulate_netlist(circuit, &vectors).unwrap()
+}
+
+#[test]
+fn foreign_formats_lower_to_the_same_function() {
+    // The binary form of the AIGER file: inputs implicit, ANDs delta-encoded
+    let mut aig = b"aig 10 3 0 2 7\n18\n21\n".to_vec();
+    aig.extend([4, 2, 5, 2, 1, 2, 2, 6, 3, 6, 1, 2, 5, 6]);
+
+    let expected: Vec<Vec<bool>> = (0..8)
+        .map(|n: u32| vec![n.count_ones() % 2 == 1, n.count_ones() >= 2])
+        .collect();
+    for (name, data) in [
+        ("fa.aag", FULL_ADDER_AAG.as_bytes()),
+        ("fa.aig", &aig[..]),
+        ("fa.blif", FULL_ADDER_BLIF.as_bytes()),
+        ("fa.v", FULL_ADDER_VERILOG.as_bytes()),
+    ] {
+        let circuit = loaded(name, data);
+        assert_eq!((circuit.num_inputs, circuit.num_outputs), (3, 2), "{}", name);
+        assert_eq!(truth_table(&circuit), expected, "{}", name);
+    }
+}
+
+#[test]
+fn outputs_may_be_constants_inputs_or_repeated() {
+    let circuit = loaded("edge.v", b"\
+module top ( a , b , y ) ;
+  input a , b ;
+  output [5:0] y ;
+  wire t ;
+  assign t = a & ~b ;
+  assign y[0] = t ;
+  assign y[1] = t ;
+  assign y[2] = ~t ;
+  assign y[3] = b ;
+  assign y[4] = 1'b0 ;
+  assign y[5] = a | 1'b1 ;
+endmodule
+");
+    for (n, outputs) in truth_table(&circuit).into_iter().enumerate() {
+        let (a, b) = (n & 1 == 1, n & 2 == 2);
+        let t = a && !b;
+        assert_eq!(outputs, vec![t, t, !t, b, false, true]);
+    }
+
+    // Inputs that no output reads still count
+    let circuit = loaded("unused.blif", b".inputs a b c\n.outputs y\n.names b y\n1 1\n");
+    assert_eq!(circuit.num_inputs, 3);
+}
+
+#[test]
+fn xors_lower_to_four_nor_templates() {
+    // A parity chain and an XNOR of two inputs, also read complemented
+    let circuit = loaded("xor.v", b"\
+module top ( x , y , z , nz ) ;
+  input [15:0] x ;
+  output y , z , nz ;
+  assign y = x[0] ^ x[1] ^ x[2] ^ x[3] ^ x[4] ^ x[5] ^ x[6] ^ x[7] ^ x[8] ^ x[9] ^ x[10] ^ x[11] ^ x[12] ^ x[13] ^ x[14] ^ x[15] ;
+  assign z = ~( x[0] ^ x[2] ) ;
+  assign nz = x[0] ^ x[2] ;
+endmodule
+");
+    for (n, outputs) in truth_table(&circuit).into_iter().enumerate() {
+        let z = n & 1 == (n >> 2) & 1;
+        assert_eq!(outputs, vec![n.count_ones() % 2 == 1, z, !z], "{:016b}", n);
+    }
+    // 15 templates and an inverter for the parity, one template and an
+    // inverter for the other two
+    assert_eq!(circuit.num_gates, 15 * 4 + 1 + 4 + 1);
+    assert_eq!(circuit.gates.iter().filter(|g| g.fanin == 1).count(), 2);
+}
+
+#[test]
+fn files_of_several_modules_yield_a_circuit_each() {
+    let dir = env::temp_dir().join(format!("delphi-modules-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let blif = format!("{}.model inv\n.inputs a\n.outputs y\n.names a y\n0 1\n.end\n", FULL_ADDER_BLIF);
+    let verilog = format!("{}module inv ( a , y ) ; input a ; output y ; assign y = ~a ; endmodule\n", FULL_ADDER_VERILOG);
+    for (name, text, first) in [("lib.blif", blif, "fa"), ("lib.v", verilog, "top")] {
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+
+        let (_, circuits) = parser::load_netlists(&path, None).unwrap();
+        let names: Vec<&str> = circuits.iter().map(|c| c.bench_name.as_str()).collect();
+        assert_eq!(names, [format!("lib_{}", first), "lib_inv".to_string()], "{}", name);
+        assert_eq!(truth_table(&circuits[0]).len(), 8, "{}", name);
+        assert_eq!(truth_table(&circuits[1]), [[true], [false]], "{}", name);
+
+        // One module by name; the others are not parsed into the result
+        let (_, top) = parser::load_netlists(&path, Some("inv")).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].bench_name, "lib_inv");
+        assert!(parser::load_netlists(&path, Some("missing")).is_err(), "{}", name);
+        assert!(parser::load_netlist(&path, &mut Circuit::new()).is_err(), "{}", name);
+    }
+
+    // Names tell the artifacts apart, so they must be unique
+    assert!(parser::parse_blif_models(".model m\n.end\n.model m\n.end\n").is_err());
+    assert!(parser::parse_verilog_modules("module m ( ) ; endmodule module m ( ) ; endmodule").is_err());
+    // Single-circuit formats have no modules to select
+    let path = dir.join("fa.aag");
+    fs::write(&path, FULL_ADDER_AAG).unwrap();
+    assert!(parser::load_netlists(&path, Some("fa")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+type TextParser = fn(&str, &mut Circuit) -> anyhow::Result<()>;
+
+#[cfg(unix)]
+#[test]
+fn presynth_commands_hand_their_netlist_to_the_parser() {
+    let dir = env::temp_dir().join(format!("delphi-presynth-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // A BLIF file under an RTL name stands in for the design; cp for the synthesizer
+    let rtl = dir.join("adder.sv");
+    fs::write(&rtl, FULL_ADDER_BLIF).unwrap();
+    let synthesized = Presynth::new("cp {input} {output}", Format::Blif).unwrap().run(&rtl, None).unwrap();
+    assert!(synthesized.command.starts_with(&format!("cp {} ", rtl.display())));
+    assert!(synthesized.command.ends_with("adder.blif"), "{}", synthesized.command);
+    let circuit = &synthesized.circuits[0];
+    assert_eq!(circuit.bench_name, "adder");
+    assert_eq!(truth_table(circuit), truth_table(&loaded("fa.blif", FULL_ADDER_BLIF.as_bytes())));
+
+    // The declared format wins over the extension, and failures carry the tool's output
+    let error = Presynth::new("cp {input} {output}", Format::Verilog).unwrap().run(&rtl, None).unwrap_err();
+    assert!(format!("{:#}", error).contains("verilog netlist --presynth wrote"), "{:#}", error);
+    let error = Presynth::new("echo no license >&2; exit 3 # {output}", Format::Blif).unwrap().run(&rtl, None).unwrap_err();
+    assert!(format!("{:#}", error).contains("status 3"), "{:#}", error);
+    assert!(format!("{:#}", error).contains("no license"), "{:#}", error);
+    assert!(Presynth::new("true {output}", Format::Blif).unwrap().run(&rtl, None).is_err());
+    assert!(Presynth::new("yosys {input}", Format::Blif).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unsupported_or_broken_netlists_are_rejected() {
+    let cases: [(&str, TextParser); 5] = [
+        (".inputs a\n.outputs y\n.latch a y 0\n", parser::parse_blif),
+        (".inputs a\n.outputs y\n.names a z y\n11 1\n.names y z\n1 1\n", parser::parse_blif),
+        ("module m(a, y); input a; output y; assign y = a & b; endmodule", parser::parse_verilog),
+        ("module m(a, y); input a; output y; always y = a; endmodule", parser::parse_verilog),
+        ("module m(a, y); input a; output y; assign y = a + a; endmodule", parser::parse_verilog),
+    ];
+    for (text, parse) in cases {
+        assert!(parse(text, &mut Circuit::new()).is_err(), "{}", text);
+    }
+    assert!(parser::parse_aiger(b"aag 1 0 1 0 0\n2 3\n", &mut Circuit::new()).is_err());
+    assert!(parser::parse_aiger(b"aag 2 1 0 1 1\n2\n4\n4 4 2\n", &mut Circuit::new()).is_err());
+}
+
+#[test]
+fn suites_locate_benchmarks_and_summarize() {
+    let dir = env::temp_dir().join(format!("delphi-suite-{}", process::id()));
+    fs::create_dir_all(dir.join("arithmetic")).unwrap();
+    fs::write(dir.join("arithmetic/adder.v"), FULL_ADDER_VERILOG).unwrap();
+    fs::write(dir.join("arithmetic/adder.aag"), FULL_ADDER_AAG).unwrap();
+    fs::write(dir.join("ctrl.blif"), FULL_ADDER_BLIF).unwrap();
+
+    let manifest = Suite::Epfl.manifest();
+    assert_eq!(manifest.benchmarks.len(), 20);
+    let located = manifest.locate(&dir).unwrap();
+    let path = |name: &str| located.iter().find(|(b, _)| b.name == name).unwrap().1.clone();
+    assert_eq!(path("adder"), Some(dir.join("arithmetic/adder.aag")));
+    assert_eq!(path("ctrl"), Some(dir.join("ctrl.blif")));
+    assert_eq!(path("voter"), None);
+
+    let mut rows = Vec::new();
+    for (benchmark, path) in located.into_iter().filter(|(b, _)| ["adder", "ctrl", "voter"].contains(&b.name.as_str())) {
+        let status = match &path {
+            Some(path) => {
+                let mut circuit = Circuit::new();
+                parser::load_netlist(path, &mut circuit).unwrap();
+                assert_eq!(benchmark.check_interface(&circuit).len(), 2);
+                Status::Done(Box::new(RunMetrics::new(&circuit)))
+            }
+            None => Status::Missing,
+        };
+        let format = path.map(|p| Format::detect(p).unwrap());
+        rows.push(SuiteRow { benchmark, format, inputs: 3, outputs: 2, status });
+    }
+    let (text, csv) = suite::write_summary(&dir, "epfl", &rows).unwrap();
+    let text = fs::read_to_string(text).unwrap();
+    assert!(text.contains("Completed         : 2 of 3 (1 missing, 0 skipped, 0 failed)"), "{}", text);
+    let csv = fs::read_to_string(csv).unwrap();
+    assert_eq!(csv.lines().count(), 4);
+    assert!(csv.lines().nth(1).unwrap().starts_with("adder,arithmetic,aiger,3,2,"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,76 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::gates::{GateKind, MapRule, Split, GATES};
+use delphi::passes::{Decompose, Pass};
+use delphi::program::{Cell, MicroOp, Program};
+use delphi::{mapper, parser, resynth, scheduler, simulator, Circuit, Primitive, MAX_GATES};
+
+#[test]
+fn each_kind_has_one_consistent_entry() {
+    for (k, spec) in GATES.iter().enumerate() {
+        assert_eq!(spec.kind as usize, k, "{}", spec.name);
+        assert_eq!(GateKind::named(spec.name), Some(spec.kind));
+        assert_eq!(GateKind::named(spec.verilog), Some(spec.kind));
+        assert_eq!(spec.kind.to_string(), spec.name);
+        assert_eq!(spec.map == MapRule::OperandRow, *spec.fanin.end() == 1, "{}", spec.name);
+
+        let inputs: Vec<Cell> = (0..*spec.fanin.start() as u32).map(|col| Cell { row: 0, col }).collect();
+        let cell = Cell { row: 0, col: 9 };
+        match spec.kind.emit(&inputs, cell) {
+            MicroOp::ExecNot { input, cell: out } => assert_eq!((vec![input], out), (inputs, cell)),
+            MicroOp::ExecNor { inputs: operands, cell: out } => assert_eq!((operands, out), (inputs, cell)),
+            op => panic!("{} emits {:?}", spec.name, op),
+        }
+    }
+    assert_eq!(GateKind::named("xor"), None);
+    assert_eq!(GateKind::native_names(), ["NOT", "NOR"]);
+
+    // Truth tables over the four combinations of two inputs, one per bit
+    let (a, b) = (0b0011, 0b0101);
+    assert_eq!(GateKind::Not.eval([a]) & 0b1111, 0b1100);
+    assert_eq!(GateKind::Nor.eval([a, b]) & 0b1111, 0b1000);
+    assert_eq!(GateKind::Nand.eval([a, b]) & 0b1111, 0b1110);
+    for primitive in [Primitive::Nor, Primitive::Nand] {
+        assert_eq!(GateKind::of(primitive, 1), GateKind::Not);
+        assert_eq!(GateKind::of(primitive, 3), primitive.kind());
+        assert_eq!(primitive.eval([a, b]), primitive.kind().eval([a, b]));
+        assert_eq!(primitive.to_string(), primitive.kind().name());
+    }
+}
+
+#[test]
+fn the_flow_names_and_splits_gates_by_their_entry() {
+    let c17 = common::scheduled(&common::bundled("c17"));
+    let nand = resynth::to_nand(&c17).unwrap();
+    let gate = (0..nand.num_gates).find(|&i| nand.gates[i].fanin == 2).unwrap();
+    assert!(nand.describe_gate(gate).contains(" = NAND("), "{}", nand.describe_gate(gate));
+
+    // Wide NANDs regroup like wide NORs
+    let mut wide = nand.clone();
+    let mut inputs = wide.gates[gate].inputs[..2].to_vec();
+    inputs.extend((0..4).map(|k| (MAX_GATES + k) as i32));
+    wide.gates[gate].set_inputs(&inputs);
+    let vectors = simulator::exhaustive_vectors(wide.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&wide, &vectors).unwrap();
+    Decompose { max_fanin: 2 }.run(&mut wide).unwrap();
+    wide.num_gates = wide.gates.len();
+    wide.index_signals();
+    assert!(wide.gates.iter().all(|g| g.fanin <= 2));
+    assert_eq!(simulator::simulate_netlist(&wide, &vectors).unwrap(), expected);
+    assert_eq!(GateKind::Nand.spec().split, Split::Regroup);
+
+    // NAND programs keep the NOR op
+    let mut scheduled = nand.clone();
+    scheduler::compute_asap_schedule(&mut scheduled);
+    scheduler::compute_alap_schedule(&mut scheduled);
+    scheduler::compute_list_schedule(&mut scheduled);
+    let mapping = mapper::create_compact_mapping(&mut scheduled);
+    let program = Program::from_mapping(&scheduled, &mapping).unwrap();
+    assert_eq!(program.primitive, Primitive::Nand);
+    assert!(program.ops().any(|(_, op)| matches!(op, MicroOp::ExecNor { .. })));
+    assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), simulator::simulate_netlist(&nand, &vectors).unwrap());
+
+    let error = parser::parse_netlist_str("n10001 = NAND(x0, x1)\n.end\n", &mut Circuit::new()).unwrap_err();
+    assert!(format!("{:#}", error).contains("NAND gates cannot be read from a native netlist, only NOT and NOR"), "{:#}", error);
+}
@@ -0,0 +1,75 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::analysis::Hotspots;
+use delphi::program::{self, MicroOp, Program};
+use delphi::{generator, mapper, simulator};
+
+#[test]
+fn hotspots_rank_paths_reads_levels_and_rows() {
+    for (name, text) in common::circuits().into_iter().take(3) {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        let hotspots = Hotspots::find(&circuit, &program, &program::cell_signals(&mapping), 5);
+
+        let outputs = simulator::output_signals(&circuit);
+        let depth = outputs.iter().filter_map(|&s| circuit.driver(s)).map(|g| g.asap_level).max().unwrap();
+        assert_eq!(hotspots.paths[0].len() - 1, depth as usize, "{}", name);
+        for path in &hotspots.paths {
+            assert!(outputs.contains(&path[path.len() - 1]), "{}", name);
+            assert!(circuit.driver(path[0]).is_none(), "{}: {:?}", name, path);
+            for pair in path.windows(2) {
+                let reader = circuit.driver(pair[1]).unwrap();
+                assert!(reader.inputs[..reader.fanin].contains(&pair[0]), "{}: {:?}", name, path);
+            }
+        }
+        assert!(hotspots.paths.windows(2).all(|p| p[0].len() >= p[1].len()), "{}", name);
+
+        assert!(hotspots.fanout.windows(2).all(|f| f[0].1 >= f[1].1), "{}", name);
+        for &(signal, readers) in &hotspots.fanout {
+            assert_eq!(circuit.readers(signal).len(), readers, "{}", name);
+        }
+        assert!(hotspots.levels.windows(2).all(|l| l[0].1.len() >= l[1].1.len()), "{}", name);
+        for (level, outs) in &hotspots.levels {
+            assert!(outs.iter().all(|&s| circuit.driver(s).unwrap().asap_level == *level), "{}", name);
+        }
+    }
+
+    // With room for every row, the rows hold every copy
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let hotspots = Hotspots::find(&circuit, &program, &program::cell_signals(&mapping), usize::MAX);
+    let copies = program.ops().filter(|(_, op)| matches!(op, MicroOp::Copy { .. })).count();
+    assert_eq!(hotspots.rows.iter().map(|(_, copied)| copied.len()).sum::<usize>(), copies);
+    assert!(hotspots.rows.iter().flat_map(|(_, copied)| copied).all(|&s| s >= 0));
+    assert_eq!(hotspots.levels.iter().map(|(_, outs)| outs.len()).sum::<usize>(), circuit.num_gates);
+}
+
+#[test]
+fn the_stats_list_the_hotspots() {
+    let dir = env::temp_dir().join(format!("delphi-hotspots-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::synthetic_netlist(32, 500, 0x5eed));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let hotspots = Hotspots::find(&circuit, &program, &program::cell_signals(&mapping), 3);
+    assert!(hotspots.to_string().starts_with("deepest "), "{}", hotspots);
+
+    let path = dir.join("stats.txt");
+    fs::write(&path, "").unwrap();
+    generator::append_hotspots(&path, &hotspots).unwrap();
+    let stats = fs::read_to_string(&path).unwrap();
+    assert!(stats.starts_with("HOTSPOTS:\n"), "{}", stats);
+    let deepest = &hotspots.paths[0];
+    assert!(stats.contains(&format!("{} -> ", delphi::signal_name(deepest[0]))), "{}", stats);
+    // Busy levels list their first eight gates
+    let (_, outs) = &hotspots.levels[0];
+    assert!(outs.len() > 8);
+    assert!(stats.contains(&format!(" +{} more\n", outs.len() - 8)), "{}", stats);
+    assert_eq!(stats.lines().filter(|l| l.is_empty()).count(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,76 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::collections::HashSet;
+
+use delphi::initstate::{InitState, Known};
+use delphi::program::{Cell, MicroOp, Program};
+use delphi::{lint, mapper, signal_id, simulator, verify, Circuit};
+
+fn gate_cell(circuit: &Circuit, signal: &str) -> Cell {
+    let gate = &circuit.gates[circuit.drivers[&signal_id(signal).unwrap()]];
+    let placed = gate.gate_map.as_ref().unwrap();
+    Cell { row: placed.idx as u32, col: placed.jdx as u32 }
+}
+
+#[test]
+fn held_cells_stand_in_for_their_loads_and_gates() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+    mapper::create_compact_mapping(&mut circuit);
+
+    // x1 where it is loaded anyway and n8 where it is computed, then the
+    // cell n10 lands in at logic 1
+    let state = format!("# from the last run\n{}x0 x1\n{} n8\n", circuit.input_line(1), gate_cell(&circuit, "n8"));
+    InitState::parse(&state).unwrap().apply(&mut circuit).unwrap();
+    mapper::create_compact_mapping(&mut circuit);
+    let n10 = gate_cell(&circuit, "n10");
+    let mut state = circuit.init_state.clone();
+    state.cells.insert(n10, Known::Constant(true));
+    state.apply(&mut circuit).unwrap();
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    verify::verify_mapping(&circuit, &mapping).unwrap();
+
+    // n7 only feeds the held n8
+    let skipped: HashSet<i32> = ["n7", "n8"].iter().map(|s| signal_id(s).unwrap()).collect();
+    assert_eq!(circuit.init_state.unevaluated(&circuit), skipped);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let ops: Vec<&MicroOp> = program.ops().map(|(_, op)| op).collect();
+    assert_eq!(ops.iter().filter(|op| matches!(op, MicroOp::Held { .. })).count(), 2);
+    assert_eq!(ops.iter().filter(|op| matches!(op, MicroOp::InputLoad { input: 1, .. })).count(), 0);
+    assert!(ops.contains(&&MicroOp::Preset { cell: n10 }));
+    assert!(!ops.iter().any(|op| matches!(op, MicroOp::Init { cells } if cells.contains(&n10))));
+
+    assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected);
+    assert_eq!(simulator::simulate_program_on(&circuit, &program, &vectors).unwrap(), expected);
+    // Without the netlist nothing tells what n8 holds
+    assert!(simulator::simulate_program(&program, &vectors).is_err());
+
+    let mut bytes = Vec::new();
+    program.write_binary(&mut bytes).unwrap();
+    assert_eq!(Program::read_binary(&bytes).unwrap(), program);
+    let mut text = Vec::new();
+    program.write_text(&mut text, 1).unwrap();
+    let issues = lint::lint_micro_ops_text(&String::from_utf8(text).unwrap());
+    assert!(issues.is_empty(), "{:?}", issues);
+}
+
+#[test]
+fn states_the_circuit_cannot_hold_are_rejected() {
+    let circuit = common::scheduled(&common::bundled("c17"));
+    let rejected = |text: &str| InitState::parse(text).and_then(|state| state.apply(&mut circuit.clone())).is_err();
+
+    assert!(rejected("0x0 x0\n0x0 1\n"), "cell listed twice");
+    assert!(rejected("0x0 x0\n1x0 x0\n"), "signal held twice");
+    assert!(rejected("0x4 x0\n"), "input off column 0");
+    assert!(rejected("9x3 n99\n"), "undriven signal");
+    assert!(rejected("0x0 n8\n"), "gate in an input cell");
+    assert!(rejected("0x0 maybe\n"), "unknown value");
+
+    let mut held = circuit.clone();
+    InitState::parse("3x0 x0\n").unwrap().apply(&mut held).unwrap();
+    assert_eq!(held.input_line(0), 3);
+    let lines: HashSet<usize> = (0..held.num_inputs).map(|n| held.input_line(n)).collect();
+    assert_eq!(lines.len(), held.num_inputs);
+}
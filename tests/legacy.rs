@@ -0,0 +1,79 @@
+//tests/legacy.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::PathBuf;
+use std::{env, fs, process};
+
+use delphi::generator;
+use delphi::legacy::{self, Artifact};
+use delphi::mapper;
+use delphi::program::Program;
+
+// Results the C implementation published, under Results/
+fn published(path: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Results").join(path);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", path, e))
+}
+
+#[test]
+fn legacy_layout_matches_the_published_c17_results() {
+    let dir = env::temp_dir().join(format!("delphi-legacy-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+
+    let mut stats = Vec::new();
+    generator::write_legacy_stats(&circuit, &mut stats).unwrap();
+    let stats = String::from_utf8(stats).unwrap();
+    let reference = published("schedule_stats/iscas85_c17_stats.txt");
+    // The C implementation's list scheduler left gates out; its other schedules match
+    let list = reference.find("LIST SCHEDULE:").unwrap();
+    assert_eq!(stats[..list], reference[..list]);
+    assert_eq!(stats.lines().count(), reference.lines().count());
+
+    generator::generate_legacy_magic_verilog(&circuit, dir.join("c17_magic.v")).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("c17_magic.v")).unwrap(), published("magic/iscas85_c17_magic.v"));
+
+    for (kind, mapping) in [("naive", mapper::create_naive_mapping(&mut circuit)), ("compact", mapper::create_compact_mapping(&mut circuit))] {
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        let path = dir.join(format!("c17_{}.txt", kind));
+        generator::generate_legacy_micro_ops(&program, &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), published(&format!("micro_ins_{}/iscas85_c17_{}.txt", kind, kind)), "{}", kind);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn readers_cross_validate_current_listings_against_published_ones() {
+    let dir = env::temp_dir().join(format!("delphi-crossval-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    // Stats as the pipeline writes them, before mapping reorders the gates
+    generator::generate_stats(&circuit, dir.join("c17_stats.txt")).unwrap();
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let path = dir.join("c17_compact.txt");
+    generator::generate_micro_ops(&circuit, &mapping, false, &path).unwrap();
+
+    // The current listing has RESET lines, a read phase and its last level
+    let current = legacy::read_listing(&fs::read_to_string(&path).unwrap()).unwrap();
+    let reference = legacy::read_listing(&published("micro_ins_compact/iscas85_c17_compact.txt")).unwrap();
+    assert_eq!(current.levels.len(), reference.levels.len() + 1);
+    assert_eq!(current.levels[..reference.levels.len()], reference.levels[..]);
+    assert_eq!(reference.metric("Crossbar Size"), Some("5x7"));
+    let differences = legacy::compare(&Artifact::Listing(reference), &Artifact::Listing(current.clone())).unwrap();
+    assert_eq!(differences, ["Levels: 4 -> 5"]);
+
+    let published_stats = legacy::read_stats(&published("schedule_stats/iscas85_c7552_stats.txt")).unwrap();
+    assert_eq!(published_stats.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["ASAP", "ALAP", "LIST"]);
+    assert!(published_stats[..2].iter().all(|s| s.distribution.iter().sum::<usize>() > 0 && s.distribution.len() == s.levels as usize));
+    // The C implementation's list schedule of c7552 placed nothing
+    assert_eq!((published_stats[2].levels, published_stats[2].parallel_steps), (-1, -2));
+    let naive = legacy::read_listing(&published("micro_ins_naive/iscas85_c7552_naive.txt")).unwrap();
+    assert!(naive.levels.iter().all(|(_, gates)| gates.iter().all(|g| g.row == 0)));
+
+    let stats = Artifact::load(dir.join("c17_stats.txt")).unwrap();
+    let reference = Artifact::load(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Results/schedule_stats/iscas85_c17_stats.txt")).unwrap();
+    assert!(legacy::compare(&reference, &stats).unwrap().iter().all(|d| d.starts_with("LIST: ")));
+    assert!(legacy::compare(&reference, &Artifact::Listing(current)).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,48 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::output::{self, level_number, level_range};
+use delphi::{generator, lint, mapper};
+
+// The base is process-wide, as --level-base sets it, so one test covers both
+#[test]
+fn artifacts_number_levels_from_the_base() {
+    assert_eq!((level_number(0), level_number(1)), (0, 1));
+    assert_eq!(level_range(0, 2), "0-2");
+
+    let dir = env::temp_dir().join(format!("delphi-levels-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let write = |name: &str| {
+        let (listing, netlist) = (dir.join(format!("{}.txt", name)), dir.join(format!("{}.net", name)));
+        generator::generate_micro_ops(&circuit, &mapping, false, &listing).unwrap();
+        generator::generate_decomposed_netlist(&circuit, &netlist).unwrap();
+        (fs::read_to_string(&listing).unwrap(), fs::read_to_string(&netlist).unwrap())
+    };
+    let (one_listing, one_netlist) = write("one");
+
+    output::set_level_base(0);
+    assert_eq!((level_number(0), level_number(1), level_number(5)), (-1, 0, 4));
+    assert_eq!(level_range(0, 2), "inputs-1");
+    assert_eq!(level_range(3, 5), "2-4");
+    let (zero_listing, zero_netlist) = write("zero");
+    let gate = (0..circuit.num_gates).find(|&i| circuit.gates[i].asap_level == 1).unwrap();
+    assert!(circuit.describe_gate(gate).contains(", ASAP 0 "), "{}", circuit.describe_gate(gate));
+    output::set_level_base(1);
+
+    // Only the level numbers move, each down by one
+    let headers = |listing: &str| listing.lines()
+        .filter_map(|l| l.strip_prefix("# Level:"))
+        .map(|l| l.split_whitespace().next().unwrap().parse::<i32>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(headers(&zero_listing)[0], 0);
+    assert_eq!(headers(&zero_listing), headers(&one_listing).iter().map(|l| l - 1).collect::<Vec<_>>());
+    assert!(lint::lint_micro_ops_text(&zero_listing).is_empty());
+    assert_eq!(zero_listing.lines().count(), one_listing.lines().count());
+    assert!(zero_netlist.contains("# level 0\n") && !one_netlist.contains("# level 0\n"));
+    assert_eq!(zero_netlist.matches("# level 0\n").count(), one_netlist.matches("# level 1\n").count());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,149 @@
+//tests/lint.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+use delphi::lint::{self, LintIssue, LintKind, Place};
+use delphi::mapper;
+use delphi::program::{Cell, MicroOp, Program};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("delphi-lint-{}-{}", name, process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn compact(text: &str) -> Program {
+    let mut circuit = common::scheduled(text);
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    Program::from_mapping(&circuit, &mapping).unwrap()
+}
+
+// The program as a text listing with its footer, as JSON and as binary, and
+// the last two again without an extension
+fn lint_each_format(program: &Program, dir: &Path) -> Vec<(&'static str, Vec<LintIssue>)> {
+    let mut text = Vec::new();
+    program.write_text(&mut text, 1).unwrap();
+    let text = format!("{}\nMetrics\nCrossbar Size     : {}x{}\n", String::from_utf8(text).unwrap(), program.rows, program.cols);
+    let mut json = Vec::new();
+    program.write_json(&mut json).unwrap();
+    let mut binary = Vec::new();
+    program.write_binary(&mut binary).unwrap();
+    [("txt", "program.txt", text.into_bytes()), ("json", "program.json", json.clone()), ("bin", "program.bin", binary.clone()),
+        ("json", "json_program", json), ("bin", "binary_program", binary)]
+        .into_iter()
+        .map(|(format, name, bytes)| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            (format, lint::lint_micro_ops(&path).unwrap())
+        })
+        .collect()
+}
+
+// Position of op `op` of step `step` in `Program::ops`, from 1
+fn op_number(program: &Program, step: usize, op: usize) -> usize {
+    program.steps[..step].iter().map(|s| s.ops.len()).sum::<usize>() + op + 1
+}
+
+#[test]
+fn emitted_programs_pass_in_every_format() {
+    let dir = temp_dir("clean");
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mut programs = vec![compact(&text)];
+        // The naive mapping takes a column per gate
+        if circuit.num_gates < 500 {
+            let naive = mapper::create_naive_mapping(&mut circuit);
+            programs.push(Program::from_mapping(&circuit, &naive).unwrap());
+        }
+        for program in programs {
+            for (format, issues) in lint_each_format(&program, &dir) {
+                assert!(issues.is_empty(), "{} as {}: {:?}", name, format, issues);
+            }
+        }
+    }
+
+    // A JSON program is not read as a listing, nor an unreadable one linted
+    let path = dir.join("c17.json");
+    fs::write(&path, "{\"schema_version\": 1, \"steps\": 3}").unwrap();
+    let error = format!("{:#}", lint::lint_micro_ops(&path).unwrap_err());
+    assert!(error.starts_with("Failed to load program"), "{}", error);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn every_format_reports_each_violation() {
+    let dir = temp_dir("violations");
+    // c17's compact program: the input loads, levels 1 to 5, each starting
+    // with its Init, then the read phase. Levels 2 to 4 copy twice
+    let clean = compact(&common::bundled("c17"));
+    let cell = |program: &Program, step: usize, op: usize| match &program.steps[step].ops[op] {
+        MicroOp::ExecNor { cell, .. } | MicroOp::ExecNot { cell, .. } | MicroOp::Copy { cell, .. } => *cell,
+        other => panic!("{:?} writes no single cell", other),
+    };
+    let (first_nor, last_not) = (cell(&clean, 2, 3), cell(&clean, 5, 1));
+
+    type Corruption = Box<dyn Fn(&mut Program) -> (LintKind, usize, usize)>;
+    let corruptions: Vec<(&str, Corruption)> = vec![
+        ("a gate reads a cell the last level writes", Box::new(move |p| {
+            let MicroOp::ExecNor { inputs, .. } = &mut p.steps[2].ops[3] else { unreachable!() };
+            inputs[1] = last_not;
+            (LintKind::ReadBeforeWrite, 2, 3)
+        })),
+        ("two gates of a level write one cell", Box::new(move |p| {
+            let MicroOp::ExecNor { cell, .. } = &mut p.steps[2].ops[4] else { unreachable!() };
+            *cell = first_nor;
+            (LintKind::DoubleWrite, 2, 4)
+        })),
+        ("an Init sets a cell twice", Box::new(move |p| {
+            let MicroOp::Init { cells } = &mut p.steps[2].ops[0] else { unreachable!() };
+            cells.insert(0, first_nor);
+            (LintKind::DoubleWrite, 2, 0)
+        })),
+        ("a copy lands where the level copied before", Box::new(|p| {
+            let copy = p.steps[3].ops[1].clone();
+            p.steps[3].ops.insert(2, copy);
+            (LintKind::Clobber, 3, 2)
+        })),
+        ("a gate evaluates into a copy of its level", Box::new(move |p| {
+            let copied = cell(p, 2, 1);
+            let MicroOp::ExecNor { cell, .. } = &mut p.steps[2].ops[4] else { unreachable!() };
+            *cell = copied;
+            (LintKind::Clobber, 2, 1)
+        })),
+        ("the crossbar is declared a column short", Box::new(|p| {
+            p.cols -= 1;
+            (LintKind::OutOfBounds, 5, 0)
+        })),
+        ("a level repeats the one before", Box::new(|p| {
+            p.steps[3].level = 2;
+            (LintKind::LevelOrder, 3, 0)
+        })),
+        ("a level drops its Init", Box::new(|p| {
+            p.steps[5].ops.remove(0);
+            (LintKind::Uninitialized, 5, 0)
+        })),
+        ("an Init spans two rows and two columns", Box::new(|p| {
+            p.steps[5].ops[0] = MicroOp::Init { cells: vec![Cell { row: 0, col: 6 }, Cell { row: 1, col: 5 }] };
+            (LintKind::Syntax, 5, 0)
+        })),
+    ];
+
+    for (what, corrupt) in corruptions {
+        let mut program = clean.clone();
+        let (kind, step, op) = corrupt(&mut program);
+        let at = Place::Op(op_number(&program, step, op));
+        for (format, issues) in lint_each_format(&program, &dir) {
+            let found: Vec<&LintIssue> = issues.iter().filter(|i| i.kind == kind).collect();
+            assert!(!found.is_empty(), "{} as {}: {:?}", what, format, issues);
+            if format == "txt" {
+                assert!(found.iter().all(|i| matches!(i.at, Place::Line(_))), "{}: {:?}", what, found);
+            } else {
+                assert_eq!(found[0].at, at, "{} as {}: {:?}", what, format, issues);
+            }
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
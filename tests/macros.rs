@@ -0,0 +1,95 @@
+//tests/macros.rs
+use delphi::{mapper, parser, scheduler, simulator, verify, Circuit};
+
+fn parsed(text: &str) -> Circuit {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist_str(text, &mut circuit).unwrap();
+    parser::find_primary_inputs(&mut circuit);
+    circuit
+}
+
+fn word(bits: &[bool]) -> usize {
+    bits.iter().enumerate().map(|(k, &b)| (b as usize) << k).sum()
+}
+
+// Output bits for every input vector, exhaustively
+fn truth_table(circuit: &Circuit) -> Vec<(Vec<bool>, Vec<bool>)> {
+    verify::verify_netlist(circuit).unwrap();
+    let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+    let results = simulator::simulate_netlist(circuit, &vectors).unwrap();
+    vectors.into_iter().zip(results).collect()
+}
+
+#[test]
+fn adders_add() {
+    let circuit = parsed("ADD4 n10001..n10005 = x0..x3 x4..x7 x8\n.end\n");
+    assert_eq!(circuit.num_gates, 4 * 9);
+    assert_eq!(circuit.num_outputs, 5);
+    for (input, output) in truth_table(&circuit) {
+        let expected = word(&input[..4]) + word(&input[4..8]) + input[8] as usize;
+        assert_eq!(word(&output), expected);
+    }
+
+    // Half adder at bit 0, no carry out
+    let circuit = parsed("ADD3 n10001 n10002 n10003 = x0 x1 x2, x3 x4 x5\n.end\n");
+    assert_eq!(circuit.num_gates, 6 + 9 + 8);
+    for (input, output) in truth_table(&circuit) {
+        assert_eq!(word(&output), (word(&input[..3]) + word(&input[3..])) % 8);
+    }
+}
+
+#[test]
+fn muxes_select() {
+    let circuit = parsed("MUX4 n10001 = x0..x3 x4 x5\n.end\n");
+    assert_eq!(circuit.num_gates, 2 + 3 * 3);
+    for (input, output) in truth_table(&circuit) {
+        assert_eq!(output[0], input[word(&input[4..])]);
+    }
+}
+
+#[test]
+fn xor_trees_compute_parity() {
+    // Four and five leaves end on an XOR and an XNOR root respectively
+    let circuit = parsed("XOR_TREE n10001 = x0..x3\nXOR_TREE n10002 = x4 x3 x2 x1 x0\n.end\n");
+    assert_eq!(circuit.num_gates, 3 * 4 + 1 + 4 * 4);
+    for (input, output) in truth_table(&circuit) {
+        let parity = |bits: &[bool]| bits.iter().filter(|&&b| b).count() % 2 == 1;
+        assert_eq!(output, vec![parity(&input[..4]), parity(&input)]);
+    }
+}
+
+#[test]
+fn macros_mix_with_gates_and_map() {
+    let mut circuit = parsed("\
+n20 = x16
+ADD8 n30..n37 n38 = x0..x7 x8..x15 n20
+n10001 = n38 n37
+.end
+");
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule(&mut circuit);
+    verify::verify_schedule(&circuit).unwrap();
+
+    let vectors = simulator::random_vectors(circuit.num_inputs, 500, 3);
+    let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    verify::verify_mapping(&circuit, &mapping).unwrap();
+    assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected);
+}
+
+#[test]
+fn malformed_macros_are_rejected() {
+    for text in [
+        "ADD4 n10001..n10003 = x0..x7\n",
+        "ADD2 n1 n2 = x0 x1 x2\n",
+        "MUX4 n10001 = x0..x4\n",
+        "XOR_TREE n10001 = x0\n",
+        "XOR_TREE x3 = x0 x1\n",
+        "MUX2 n10001 = x0 n1..x2\n",
+        "ADD1 n5 n5 = x0 x1\n",
+    ] {
+        let mut circuit = Circuit::new();
+        assert!(parser::parse_netlist_str(text, &mut circuit).is_err(), "{}", text);
+    }
+}
@@ -0,0 +1,90 @@
+//tests/metrics.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::PathBuf;
+use std::{env, fs, process};
+
+use delphi::generator::{self, TechEstimate};
+use delphi::mapper;
+use delphi::metrics::{ProgramMetrics, RunMetrics};
+use delphi::suite::{self, Benchmark, ResultRecord, ResultStream, Status, SuiteRow};
+use delphi::tech::Technology;
+
+// Value of a "Name : value" line of the Metrics footer
+fn metric(listing: &str, name: &str) -> f64 {
+    listing.lines()
+        .find_map(|l| l.strip_prefix(name).and_then(|r| r.split(':').nth(1)))
+        .and_then(|v| v.split_whitespace().next())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("no {} in the footer", name))
+}
+
+#[test]
+fn program_metrics_agree_with_the_footers() {
+    let tech = Technology::load(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tech/profiles.toml"), None).unwrap();
+    let dir = env::temp_dir().join(format!("delphi-metrics-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+
+    for naive in [true, false] {
+        let mapping = if naive { mapper::create_naive_mapping(&mut circuit) } else { mapper::create_compact_mapping(&mut circuit) };
+        let path = dir.join(format!("c17_{}.txt", naive));
+        let program = generator::generate_micro_ops_with_tech(&circuit, &mapping, naive, Some(&tech), false, &path).unwrap();
+        let cycles = if naive { program.serialized_cycles() } else { program.cycles() };
+        let metrics = ProgramMetrics::new(&program, &cycles, Some(TechEstimate::new(&tech, &program, &cycles, naive)));
+
+        let listing = fs::read_to_string(&path).unwrap();
+        assert_eq!(metrics.cells, mapping.occupied_cells().count(), "naive {}", naive);
+        assert_eq!((metrics.rows, metrics.cols), (program.rows, program.cols), "naive {}", naive);
+        assert_eq!(metrics.cycles as f64, metric(&listing, "Total Cycles"), "naive {}", naive);
+        assert_eq!(metrics.tech_cycles.unwrap() as f64, metric(&listing, "Technology Cycles"), "naive {}", naive);
+        assert!((metrics.energy_pj.unwrap() - metric(&listing, "Energy")).abs() < 0.01, "naive {}", naive);
+        assert_eq!(metrics.copies == 0, naive, "naive {}", naive);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn the_suite_summary_adds_up_run_metrics() {
+    let row = |name: &str, cycles: usize| {
+        let mut metrics = RunMetrics::new(&common::scheduled(&common::bundled("c17")));
+        metrics.compact.cycles = cycles;
+        metrics.times.total_ms = 1500.0;
+        let benchmark = Benchmark { name: name.to_string(), category: "test".to_string(), inputs: None, outputs: None, file: None };
+        SuiteRow { benchmark, format: None, inputs: 5, outputs: 2, status: Status::Done(Box::new(metrics)) }
+    };
+    let dir = env::temp_dir().join(format!("delphi-metrics-suite-{}", process::id()));
+    let (text, csv) = suite::write_summary(&dir, "sum", &[row("a", 20), row("b", 80)]).unwrap();
+    let text = fs::read_to_string(text).unwrap();
+    assert!(text.contains("Total cycles      : 100"), "{}", text);
+    assert!(text.contains("Geomean cycles    : 40.0"), "{}", text);
+    let csv = fs::read_to_string(csv).unwrap();
+    assert!(csv.starts_with("Benchmark,Category,Format,PI,PO,Gates,NOT,Levels,Crossbar,Memristors,Cycles,Time (s),Status\n"), "{}", csv);
+    assert_eq!(csv.lines().nth(1).unwrap(), "a,test,-,5,2,13,7,5,0x0,0,20,1.50,ok");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn the_results_stream_gets_a_line_per_circuit() {
+    let dir = env::temp_dir().join(format!("delphi-metrics-stream-{}", process::id()));
+    let file = dir.join("c17.txt");
+    let done = Status::Done(Box::new(RunMetrics::new(&common::scheduled(&common::bundled("c17")))));
+    let failed = Status::Failed("Failed to parse netlist".to_string());
+    ResultStream::open(&dir).unwrap().record(&ResultRecord::new("c17", Some(&file), &done, 12.5)).unwrap();
+    // A later run appends to the stream
+    let mut stream = ResultStream::open(&dir).unwrap();
+    stream.record(&ResultRecord::new("bad", None, &failed, 0.5)).unwrap();
+
+    let text = fs::read_to_string(stream.path()).unwrap();
+    let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["status"], "done");
+    assert_eq!(lines[0]["duration_ms"], 12.5);
+    assert_eq!(lines[0]["metrics"]["gates"], 13);
+    assert!(lines[0].get("error").is_none());
+    assert_eq!((&lines[1]["circuit"], &lines[1]["status"]), (&"bad".into(), &"failed".into()));
+    assert_eq!(lines[1]["error"], "Failed to parse netlist");
+    assert!(lines[1]["file"].is_null() && lines[1].get("metrics").is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,121 @@
+//tests/monitor.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+use anyhow::{Result, bail};
+use delphi::metrics::RunMetrics;
+use delphi::monitor::{CampaignCounters, Monitor, Monitored, PushGateway};
+use delphi::serve::Runner;
+use delphi::suite::Status;
+
+fn done(gates: usize, naive: usize, compact: usize) -> Status {
+    let mut metrics = RunMetrics { gates, ..Default::default() };
+    (metrics.naive.cycles, metrics.compact.cycles) = (naive, compact);
+    Status::Done(Box::new(metrics))
+}
+
+#[test]
+fn counters_add_up_in_the_textfile() {
+    let mut counters = CampaignCounters::default();
+    assert!(counters.average_cycles(0).is_nan());
+    assert!(counters.exposition("epfl", Duration::ZERO).contains("delphi_average_cycles{campaign=\"epfl\",flow=\"compact\"} NaN\n"));
+    for status in [done(10, 40, 20), Status::Skipped, done(30, 60, 25), Status::Failed("x".to_string()), Status::Missing] {
+        counters.record(&status);
+    }
+    assert_eq!(counters, CampaignCounters { done: 2, skipped: 1, failed: 1, missing: 1, gates: 40, naive_cycles: 100, compact_cycles: 45 });
+
+    let text = counters.exposition("my \"suite\"", Duration::from_millis(1500));
+    for line in [
+        "# TYPE delphi_circuits_total counter",
+        "delphi_circuits_total{campaign=\"my \\\"suite\\\"\",status=\"done\"} 2",
+        "delphi_circuits_total{campaign=\"my \\\"suite\\\"\",status=\"failed\"} 1",
+        "delphi_gates_total{campaign=\"my \\\"suite\\\"\"} 40",
+        "delphi_average_cycles{campaign=\"my \\\"suite\\\"\",flow=\"naive\"} 50",
+        "delphi_average_cycles{campaign=\"my \\\"suite\\\"\",flow=\"compact\"} 22.5",
+        "delphi_elapsed_seconds{campaign=\"my \\\"suite\\\"\"} 1.5",
+    ] {
+        assert!(text.lines().any(|l| l == line), "{} not in\n{}", line, text);
+    }
+
+    // The textfile follows every circuit, without a staged copy left over
+    let dir = env::temp_dir().join(format!("delphi-monitor-{}", process::id()));
+    let path = dir.join("textfile/delphi.prom");
+    let monitor = Monitor::new("epfl", Some(path.clone()), None).unwrap();
+    monitor.emit().unwrap();
+    assert!(fs::read_to_string(&path).unwrap().contains("delphi_gates_total{campaign=\"epfl\"} 0\n"));
+    monitor.record(&done(7, 9, 3));
+    assert!(fs::read_to_string(&path).unwrap().contains("delphi_gates_total{campaign=\"epfl\"} 7\n"));
+    assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Fails on `--fail` and panics on `--panic`; otherwise measures the netlist
+struct Flaky;
+
+impl Runner for Flaky {
+    fn check(&self, _: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn run(&self, netlist: &Path, options: &[String], _: &Path) -> Result<Vec<RunMetrics>> {
+        match options.first().map(String::as_str) {
+            Some("--fail") => bail!("asked to fail"),
+            Some("--panic") => panic!("asked to panic"),
+            _ => Ok(vec![RunMetrics::new(&common::scheduled(&fs::read_to_string(netlist)?))]),
+        }
+    }
+}
+
+#[test]
+fn services_push_their_jobs_to_the_gateway() {
+    assert_eq!(PushGateway::parse("http://gw").unwrap().authority, "gw:9091");
+    let gateway = PushGateway::parse("http://gw:80/push/").unwrap();
+    assert_eq!(gateway.path("a b/c"), "/push/metrics/job/delphi/campaign/a%20b%2Fc");
+    assert!(PushGateway::parse("https://gw").is_err());
+
+    // A gateway that takes three pushes and keeps their request lines and bodies
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let gateway = thread::spawn(move || {
+        let mut pushes = Vec::new();
+        for stream in listener.incoming().take(3) {
+            let mut reader = BufReader::new(stream.unwrap());
+            let (mut request, mut line, mut length) = (String::new(), String::new(), 0);
+            reader.read_line(&mut request).unwrap();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                line.clear();
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+            pushes.push((request.trim_end().to_string(), String::from_utf8(body).unwrap()));
+        }
+        pushes
+    });
+
+    let dir = env::temp_dir().join(format!("delphi-monitor-serve-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+    let runner = Monitored { runner: Flaky, monitor: Monitor::new("serve", None, Some(&url)).unwrap() };
+    let gates = runner.run(&netlist, &[], &dir).unwrap()[0].gates;
+    assert!(runner.run(&netlist, &["--fail".to_string()], &dir).is_err());
+    // The panic goes on to the service, counted
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| runner.run(&netlist, &["--panic".to_string()], &dir))).is_err());
+    let counters = runner.monitor.counters();
+    assert_eq!((counters.done, counters.failed, counters.gates), (1, 2, gates as u64));
+
+    let pushes = gateway.join().unwrap();
+    assert!(pushes.iter().all(|(request, _)| request == "PUT /metrics/job/delphi/campaign/serve HTTP/1.1"));
+    assert!(pushes[2].1.contains("delphi_circuits_total{campaign=\"serve\",status=\"failed\"} 2\n"), "{}", pushes[2].1);
+    fs::remove_dir_all(&dir).unwrap();
+}
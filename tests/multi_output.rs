@@ -0,0 +1,107 @@
+//tests/multi_output.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::passes::{Decompose, PassManager, PassName};
+use delphi::program::Program;
+use delphi::{generator, mapper, parser, resynth, simulator, verify, Circuit};
+
+// n6 and n7 are further names of n5 and n2, output n3 is another name of n9
+const MULTI: &str = "\
+n5 n6 = x0 x1
+n10001 = n5 x2
+n10002 n7 = n6
+n8 = n7 x3
+n9 n10003 = NOR(n8, n6, x0)
+n10004 = n10003 n2
+";
+
+const SINGLE: &str = "\
+n5 = x0 x1
+n10001 = n5 x2
+n10002 = n5
+n8 = n2 x3
+n10003 = NOR(n8, n5, x0)
+n10004 = n3 n2
+";
+
+#[test]
+fn multi_output_gates_compute_like_their_single_output_netlist() {
+    let mut multi = common::scheduled(MULTI);
+    let mut single = common::scheduled(SINGLE);
+    assert_eq!((multi.num_gates, multi.num_outputs), (6, 4));
+    assert_eq!(multi.driver(6).unwrap().outs().collect::<Vec<_>>(), [5, 6]);
+    assert_eq!(multi.driver(3).unwrap().primary_outputs().collect::<Vec<_>>(), [3]);
+    assert_eq!(multi.driver_index(7), multi.driver_index(2));
+    assert_eq!(multi.cell_signal(7), 2);
+    assert_eq!(multi.gate_readers(multi.driver_index(5).unwrap()).count(), 3);
+    verify::verify_netlist(&multi).unwrap();
+    verify::verify_schedule(&multi).unwrap();
+
+    // Levels follow the readers of every signal a gate drives
+    let levels = |c: &Circuit| c.gates.iter().map(|g| (g.asap_level, g.alap_level, g.list_level)).collect::<Vec<_>>();
+    assert_eq!(levels(&multi), levels(&single));
+
+    let vectors = simulator::exhaustive_vectors(multi.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&single, &vectors).unwrap();
+    assert_eq!(simulator::simulate_netlist(&multi, &vectors).unwrap(), expected);
+    for naive in [true, false] {
+        let map = |c: &mut Circuit| if naive { mapper::create_naive_mapping(c) } else { mapper::create_compact_mapping(c) };
+        let (mapping, reference) = (map(&mut multi), map(&mut single));
+        verify::verify_mapping(&multi, &mapping).unwrap();
+        assert_eq!((mapping.max_idx, mapping.max_jdx), (reference.max_idx, reference.max_jdx), "naive {}", naive);
+        assert_eq!(simulator::simulate_mapping(&multi, &mapping, &vectors).unwrap(), expected, "naive {}", naive);
+        let program = Program::from_mapping(&multi, &mapping).unwrap();
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "naive {}", naive);
+    }
+}
+
+#[test]
+fn multi_output_gates_are_written_and_read_back() {
+    let circuit = common::scheduled(MULTI);
+    let describe = |signal| circuit.describe_gate(circuit.driver_index(signal).unwrap());
+    assert!(describe(6).starts_with("n5 n6 = NOR(x0, x1), ASAP 1"));
+    assert!(describe(3).starts_with("n9 n3 = NOR(n8, n6, x0) [output]"));
+
+    let dir = env::temp_dir().join(format!("delphi-multi-output-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let decomposed = dir.join("decomposed.txt");
+    generator::generate_decomposed_netlist(&circuit, &decomposed).unwrap();
+    let text = fs::read_to_string(&decomposed).unwrap();
+    assert!(text.contains("\nn9 n10003 = NOR(n8, n6, x0) "), "{}", text);
+    let read = common::scheduled(&text);
+    assert_eq!(read.gates.iter().map(|g| g.extra_outs.clone()).collect::<Vec<_>>(),
+        circuit.gates.iter().map(|g| g.extra_outs.clone()).collect::<Vec<_>>());
+    let vectors = simulator::random_vectors(circuit.num_inputs, 16, 3);
+    assert_eq!(simulator::simulate_netlist(&read, &vectors).unwrap(), simulator::simulate_netlist(&circuit, &vectors).unwrap());
+
+    // The other signals are assigned from the gate's
+    let verilog = dir.join("magic.v");
+    generator::generate_magic_verilog(&circuit, &verilog).unwrap();
+    let verilog = fs::read_to_string(&verilog).unwrap();
+    for line in ["  wire wr_6;", "  assign wr_6 = wr_5;", "  assign wr_7 = op_2;", "  assign op_3 = wr_9;", "  output op_4"] {
+        assert!(verilog.lines().any(|l| l == line), "{} not in\n{}", line, verilog);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    // A signal is driven once; passes and flows that rebuild gates stop
+    for (text, error) in [("n5 n5 = x0 x1\n", "n5 is driven twice"), ("n5 x1 = x0\n", "x1 cannot be driven")] {
+        let mut circuit = Circuit::new();
+        let message = format!("{:#}", parser::parse_netlist_str(text, &mut circuit).unwrap_err());
+        assert!(message.contains(error), "{}", message);
+    }
+    let mut rebuilt = common::parsed(MULTI);
+    let error = PassManager::from_names(&[PassName::Xor]).run(&mut rebuilt).unwrap_err().to_string();
+    assert_eq!(error, "Pass 'xor' does not take multi-output gates, and n5 drives n5 n6");
+    assert!(resynth::to_nand(&rebuilt).is_err());
+
+    // Splitting a wide gate leaves its signals on the gate that reads the groups
+    let mut split = PassManager::from_names(&[PassName::Commute]);
+    split.add(Box::new(Decompose { max_fanin: 2 }));
+    split.run(&mut rebuilt).unwrap();
+    assert_eq!(rebuilt.num_gates, 8);
+    assert_eq!(rebuilt.driver(3).unwrap().outs().collect::<Vec<_>>(), [9, 3]);
+    assert_eq!(simulator::simulate_netlist(&rebuilt, &vectors).unwrap(), simulator::simulate_netlist(&circuit, &vectors).unwrap());
+}
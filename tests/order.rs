@@ -0,0 +1,68 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::cmp::Reverse;
+use std::{env, fs, process};
+
+use delphi::{generator, mapper, scheduler, TableGate};
+
+#[test]
+fn the_orders_outlast_the_internal_sorts() {
+    for (name, text) in common::circuits().into_iter().take(3) {
+        let parsed = common::parsed(&text);
+        let netlist: Vec<i32> = parsed.gates[..parsed.num_gates].iter().map(|g| g.out).collect();
+        assert_eq!(parsed.gates_in_original_order(), (0..parsed.num_gates).collect::<Vec<_>>(), "{}", name);
+
+        let mut circuit = common::scheduled(&text);
+        scheduler::compute_list_schedule_for(&mut circuit, None);
+        mapper::create_compact_mapping(&mut circuit);
+        circuit.sort_gates_by_key(|g| Reverse(g.out));
+        let original: Vec<i32> = circuit.gates_in_original_order().iter().map(|&i| circuit.gates[i].out).collect();
+        assert_eq!(original, netlist, "{}", name);
+
+        let topological = circuit.gates_in_topological_order();
+        let mut position = vec![0; circuit.num_gates];
+        for (k, &i) in topological.iter().enumerate() {
+            position[i] = k;
+        }
+        for (i, gate) in circuit.gates[..circuit.num_gates].iter().enumerate() {
+            for &operand in &gate.inputs[..gate.fanin] {
+                assert!(circuit.driver_index(operand).is_none_or(|d| position[d] < position[i]), "{}", name);
+            }
+        }
+    }
+
+    // A gate added after a sort follows the gates recorded before it, and
+    // goes after its driver in topological order
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    circuit.sort_gates_by_key(|g| Reverse(g.asap_level));
+    let mut gate = TableGate { out: -1, ..Default::default() };
+    gate.set_inputs(&[circuit.gates[0].out]);
+    circuit.gates.insert(0, gate);
+    circuit.num_gates += 1;
+    circuit.index_signals();
+    assert_eq!(circuit.gates_in_original_order().last(), Some(&0));
+    let topological = circuit.gates_in_topological_order();
+    let driver = topological.iter().position(|&i| i == 1).unwrap();
+    assert!(topological.iter().position(|&i| i == 0).unwrap() > driver);
+}
+
+#[test]
+fn the_verilog_does_not_follow_the_mappers_sorts() {
+    let dir = env::temp_dir().join(format!("delphi-order-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::synthetic_netlist(32, 500, 0x5eed));
+    let write = |circuit: &delphi::Circuit, name: &str| {
+        generator::generate_magic_verilog(circuit, dir.join(format!("{}.v", name))).unwrap();
+        generator::generate_decomposed_netlist(circuit, dir.join(format!("{}.txt", name))).unwrap();
+        [".v", ".txt"].map(|ext| fs::read_to_string(dir.join(format!("{}{}", name, ext))).unwrap())
+    };
+    let before = write(&circuit, "before");
+
+    scheduler::compute_list_schedule_for(&mut circuit, None);
+    mapper::create_compact_mapping(&mut circuit);
+    assert_eq!(write(&circuit, "mapped"), before);
+    circuit.sort_gates_by_key(|g| Reverse(g.out));
+    assert_eq!(write(&circuit, "reversed"), before);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,63 @@
+//tests/output.rs
+use std::path::Path;
+use std::{env, fs, process};
+
+use delphi::output::{self, ArtifactPaths, ClobberPolicy, OutputLayout};
+
+#[test]
+fn per_bench_layout_keeps_each_circuit_in_its_own_directory() {
+    let root = Path::new("Results");
+    let paths = ArtifactPaths::new(root, "c17", OutputLayout::PerBench);
+    let bench = root.join("c17");
+    for path in paths.all().into_iter().chain([paths.pipeline.as_path(), paths.batch.as_path(), paths.metrics.as_path(),
+        paths.replicated_verilog.as_path(), paths.overlay.as_path(), paths.partition.as_path()]) {
+        assert_eq!(path.parent(), Some(bench.as_path()), "{:?}", path);
+    }
+    assert_eq!(paths.stats, bench.join("c17_stats.txt"));
+    assert_eq!(paths.magic, bench.join("c17_magic.v"));
+    assert_eq!(paths.naive, bench.join("c17_naive.txt"));
+    assert_eq!(paths.compact, bench.join("c17_compact.txt"));
+    assert_eq!(paths.tile_programs(2), [bench.join("c17_tile0.txt"), bench.join("c17_tile1.txt")]);
+    assert_eq!(paths.rotation_programs(1), [bench.join("c17_rot0.txt")]);
+    assert_eq!(paths.partition_programs(1), [bench.join("c17_part0.txt")]);
+
+    // The same names as by kind, in other directories
+    let by_kind = ArtifactPaths::new(root, "c17", OutputLayout::ByKind);
+    assert_eq!(by_kind.stats, root.join("schedule_stats/c17_stats.txt"));
+    assert_eq!(by_kind.compact, root.join("micro_ins_compact/c17_compact.txt"));
+    assert_eq!(by_kind.tile_programs(1), [root.join("micro_ins_pipelined/c17_tile0.txt")]);
+    for (per_bench, kind) in paths.all().into_iter().zip(by_kind.all()) {
+        assert_ne!(per_bench, kind);
+        assert_eq!(per_bench.file_name(), kind.file_name());
+    }
+
+    // Names that share a prefix still get directories of their own
+    let other = ArtifactPaths::new(root, "c17_b", OutputLayout::PerBench);
+    assert_eq!(other.stats, root.join("c17_b/c17_b_stats.txt"));
+}
+
+#[test]
+fn no_clobber_skips_circuits_with_artifacts_on_disk() {
+    let dir = env::temp_dir().join(format!("delphi-output-{}", process::id()));
+    for layout in [OutputLayout::ByKind, OutputLayout::PerBench] {
+        let paths = ArtifactPaths::new(&dir, "c17", layout);
+        paths.create_dirs().unwrap();
+        assert!(paths.all().iter().all(|p| p.parent().unwrap().is_dir()), "{:?}", layout);
+        assert!(paths.existing().is_empty());
+        assert_eq!(ClobberPolicy::NoClobber.blocking(&paths), None);
+
+        // Any one artifact is enough to skip, and only the four core ones count
+        fs::write(&paths.compact, "").unwrap();
+        output::create_parent(&paths.pipeline).unwrap();
+        fs::write(&paths.pipeline, "").unwrap();
+        assert_eq!(paths.existing(), [paths.compact.as_path()]);
+        assert_eq!(ClobberPolicy::NoClobber.blocking(&paths), Some(paths.compact.as_path()));
+        assert_eq!(ClobberPolicy::Overwrite.blocking(&paths), None);
+        assert_eq!(ClobberPolicy::default(), ClobberPolicy::Overwrite);
+
+        // Another circuit in the same directory is not held back
+        let other = ArtifactPaths::new(&dir, "c432", layout);
+        assert_eq!(ClobberPolicy::NoClobber.blocking(&other), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+//tests/overlay.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::overlay::{CellDiff, CellKind, Layer, Overlay};
+use delphi::program::{self, Program};
+use delphi::{generator, mapper, signal_id};
+
+#[test]
+fn a_program_overlaid_on_itself_is_all_same() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        let overlay = Overlay::new(Layer::new("a", &program), Layer::new("b", &program));
+        assert_eq!(overlay.count(CellDiff::Same), program.cells().len(), "{}", name);
+        assert_eq!(overlay.cells.len(), program.cells().len(), "{}", name);
+        assert!(overlay.moved().is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn naive_and_compact_mappings_overlay_by_signal() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    let first = Layer::new("naive", &Program::from_mapping(&circuit, &naive).unwrap())
+        .with_signals(&program::cell_signals(&naive));
+    let second = Layer::new("compact", &Program::from_mapping(&circuit, &compact).unwrap())
+        .with_signals(&program::cell_signals(&compact));
+    assert!(first.cells.values().all(|u| u.signal.is_some()));
+    let overlay = Overlay::new(first, second);
+
+    // Every cell of each layer is counted once
+    let same = overlay.count(CellDiff::Same) + overlay.count(CellDiff::Changed);
+    assert_eq!(same + overlay.count(CellDiff::OnlyFirst), overlay.first.cells.len());
+    assert_eq!(same + overlay.count(CellDiff::OnlySecond), overlay.second.cells.len());
+    assert_eq!((overlay.rows(), overlay.cols()), (5, 18));
+
+    // Both mappings load x0 into 0x0; everything else moves
+    let x0 = overlay.cells.iter().find(|(c, _)| (c.row, c.col) == (0, 0)).unwrap();
+    assert_eq!(*x0.1, CellDiff::Same);
+    let moved = overlay.moved();
+    let gates = overlay.first.cells.values().filter(|u| u.kind != CellKind::Copy).count();
+    assert_eq!(moved.len(), gates - 1);
+    let n1 = signal_id("n1").unwrap();
+    assert!(moved.iter().any(|&(s, a, b)| s == n1 && a != b));
+}
+
+#[test]
+fn overlays_of_emitted_programs_are_drawn_per_cell() {
+    let dir = env::temp_dir().join(format!("delphi-overlay-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    generator::export_program(&program, program::ProgramFormat::Json, dir.join("c17.json")).unwrap();
+    generator::export_program(&program, program::ProgramFormat::Binary, dir.join("c17.bin")).unwrap();
+    assert_eq!(Program::load(dir.join("c17.json")).unwrap(), program);
+    assert_eq!(Program::load(dir.join("c17.bin")).unwrap(), program);
+    assert!(Program::load(dir.join("c17.txt")).is_err());
+
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let naive = Program::from_mapping(&circuit, &naive).unwrap();
+    let overlay = Overlay::new(Layer::new("a<b", &naive), Layer::new("json", &Program::load(dir.join("c17.json")).unwrap()));
+    generator::generate_overlay(&overlay, "c17", dir.join("c17.svg")).unwrap();
+    let svg = fs::read_to_string(dir.join("c17.svg")).unwrap();
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"), "{}", svg);
+    assert_eq!(svg.matches("<title>").count(), overlay.cells.len() + overlay.moved().len());
+    assert!(svg.contains("a&lt;b only") && !svg.contains("a<b"), "{}", svg);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,61 @@
+//tests/paranoid.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use delphi::program::Program;
+use delphi::{mapper, verify, Circuit, CrossbarMapping};
+
+// Whether `check` trips an invariant, and its message if so
+fn violation(check: impl FnOnce()) -> Option<String> {
+    let caught = panic::catch_unwind(AssertUnwindSafe(check)).err()?;
+    Some(caught.downcast_ref::<String>().cloned().unwrap_or_default())
+}
+
+fn mapped() -> (Circuit, CrossbarMapping) {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    (circuit, mapping)
+}
+
+// Debug builds check invariants whatever the switch says, so only release
+// builds skip them with paranoid mode off
+#[test]
+fn paranoid_mode_catches_corrupted_mappings() {
+    // Two gates claim one cell
+    let (mut shared, mapping) = mapped();
+    let first = shared.gates[0].gate_map.clone();
+    shared.gates[1].gate_map = first;
+    assert!(verify::verify_mapping(&shared, &mapping).is_err());
+
+    // The last gate's first operand reads its own cell instead of its driver
+    let (circuit, mut misread) = mapped();
+    let last = circuit.gates.iter().filter(|g| g.gate_map.is_some()).max_by_key(|g| g.asap_level).unwrap();
+    let (i, j) = last.gate_map.as_ref().map(|m| (m.idx, m.jdx)).unwrap();
+    let operand = misread.cell_mut(i as usize, j as usize).inputs[0].as_mut().unwrap();
+    (operand.idx, operand.jdx) = (i, j);
+
+    for paranoid in [true, false] {
+        verify::set_paranoid(paranoid);
+        let checked = paranoid || cfg!(debug_assertions);
+        assert_eq!(verify::checks_enabled(), checked);
+
+        let message = violation(|| verify::check_placement(&shared, &mapping));
+        assert_eq!(message.is_some(), checked, "paranoid {}: {:?}", paranoid, message);
+        if let Some(message) = message {
+            assert!(message.starts_with("invariant violated: cell"), "{}", message);
+        }
+
+        let message = violation(|| { let _ = Program::from_mapping(&circuit, &misread); });
+        assert_eq!(message.is_some(), checked, "paranoid {}: {:?}", paranoid, message);
+        if let Some(message) = message {
+            assert!(message.contains(&format!("reads {}x{}", i, j)), "{}", message);
+        }
+
+        // The untouched mapping passes either way
+        let (circuit, mapping) = mapped();
+        assert_eq!(violation(|| verify::check_placement(&circuit, &mapping)), None);
+        assert_eq!(violation(|| { Program::from_mapping(&circuit, &mapping).unwrap(); }), None);
+    }
+}
@@ -0,0 +1,99 @@
+//tests/parser.rs
+use delphi::{parser, Circuit, MAX_GATES};
+
+fn parsed(text: &str) -> Circuit {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist_str(text, &mut circuit).unwrap();
+    parser::find_primary_inputs(&mut circuit);
+    circuit
+}
+
+#[test]
+fn output_audit_lists_each_kind_of_broken_output() {
+    // op_1 is driven twice, op_2 never, and n3 takes op_3's number without
+    // its mark. n5 is past the last output, so it is an ordinary gate
+    let circuit = parsed("\
+n10001 = NOR(x0, x1)
+n10001 = NOR(x1, x2)
+n3 = NOT(x2)
+n10004 = NOR(n3, x0)
+n5 = NOT(n10001)
+");
+    let audit = parser::audit_primary_outputs(&circuit);
+    assert_eq!(audit.declared, [1, 4]);
+    assert_eq!(audit.duplicates, [1]);
+    assert_eq!(audit.undriven, [2]);
+    assert_eq!(audit.unmarked, [3]);
+    assert!(!audit.is_clean());
+    assert_eq!(audit.messages(), [
+        "Output op_2 (n10002) is never driven",
+        "Output op_1 (n10001) is driven by more than one line",
+        "Signal n3 is numbered like output op_3 but not marked as an output",
+    ]);
+
+    // A driver listed three times is one duplicate
+    let circuit = parsed("n10001 = NOT(x0)\nn10001 = NOT(x1)\nn10001 = NOR(x0, x1)\n");
+    let audit = parser::audit_primary_outputs(&circuit);
+    assert_eq!((audit.declared.as_slice(), audit.duplicates.as_slice()), (&[1][..], &[1][..]));
+}
+
+#[test]
+fn output_audit_passes_contiguous_outputs() {
+    let audit = parser::audit_primary_outputs(&parsed("n10001 = NOR(x0, x1)\nn10002 = NOT(n3)\nn3 = NOR(x1, x2)\n"));
+    // n3 is numbered past the last output
+    assert_eq!(audit.declared, [1, 2]);
+    assert!(audit.is_clean(), "{:?}", audit);
+    assert!(audit.messages().is_empty());
+
+    // Without outputs there is nothing to cross-check
+    let audit = parser::audit_primary_outputs(&parsed("n1 = NOR(x0, x1)\nn2 = NOT(n1)\n"));
+    assert!(audit.declared.is_empty() && audit.is_clean(), "{:?}", audit);
+}
+
+// The gates of one line, as (output, inputs)
+fn gates_of(line: &str) -> Vec<(i32, Vec<i32>)> {
+    let circuit = parsed(line);
+    circuit.gates.iter().map(|g| (g.out, g.inputs[..g.fanin].to_vec())).collect()
+}
+
+fn parse_error(line: &str) -> String {
+    format!("{:#}", parser::parse_netlist_str(line, &mut Circuit::new()).unwrap_err())
+}
+
+#[test]
+fn variables_are_read_whatever_the_spacing_and_punctuation() {
+    let x = |k: i32| MAX_GATES as i32 + k;
+    let nor = vec![(5, vec![x(0), 3])];
+    for line in ["n5 = NOR(x0, n3)", "n5=NOR(x0,n3)", "  n5\t=  NOR ( x0 ,\tn3 )  ", "n5 = NOR(x0 n3)",
+        "n5 = NOR[x0; n3]", "n5 = NOR(x0,, n3,)", "n5 = NOR(x0, n3) # n7 x9", "n5 = nor(x0, n3)"] {
+        assert_eq!(gates_of(line), nor, "{:?}", line);
+    }
+
+    // x is an input and n a signal; output-biased n's are outputs read back
+    // as their signal, and any other letter glued on is skipped
+    assert_eq!(gates_of("n10002 = NOT(x7)"), [(2, vec![x(7)])]);
+    assert!(parsed("n10002 = NOT(x7)").gates[0].is_output);
+    assert_eq!(gates_of("n4 = NOR(n10002, x999)"), [(4, vec![2, x(999)])]);
+    assert_eq!(gates_of("n4 = NOR(ax1, nx2)"), [(4, vec![x(1), x(2)])]);
+    assert_eq!(gates_of("n4 = NOT(x007)"), [(4, vec![x(7)])]);
+    assert_eq!(gates_of("n6 = NOR(n0, x0)"), [(6, vec![0, x(0)])]);
+    assert_eq!(gates_of("n7999 = NOT(n17998)"), [(7999, vec![7998])]);
+}
+
+#[test]
+fn variables_out_of_range_are_rejected() {
+    for (line, expected) in [
+        ("n4 = NOT(x1000)", "Primary input x1000 exceeds the maximum of 1000 inputs"),
+        ("n8000 = NOT(x0)", "Signal n8000 collides with the primary input range"),
+        ("n4 = NOT(n9999)", "Signal n9999 collides with the primary input range"),
+        ("n18000 = NOT(x0)", "Variable index n18000 is out of range"),
+        ("n4 = NOT(x18000)", "Variable index x18000 is out of range"),
+        ("n4 = NOT(n99999999999999999999)", "Variable index n99999999999999999999 is out of range"),
+        ("x3 = NOT(x1)", "Primary input x3 cannot be driven by a gate"),
+        ("= NOT(x1)", "Missing output variable"),
+        ("n = NOT(x1)", "Missing output variable"),
+    ] {
+        let error = parse_error(line);
+        assert!(error.contains(expected), "{:?}: {}", line, error);
+    }
+}
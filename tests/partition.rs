@@ -0,0 +1,75 @@
+//tests/partition.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::lint;
+use delphi::program::Program;
+use delphi::{generator, mapper};
+
+#[test]
+fn parts_cover_the_program_within_the_budget() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        let total = program.ops().count();
+        for max_ops in [2, 40, total] {
+            let parts = program.partition(max_ops).unwrap();
+            assert_eq!(parts.first().map(|p| p.ops.start), Some(0), "{}", name);
+            assert_eq!(parts.last().map(|p| p.ops.end), Some(total), "{}", name);
+            assert!(parts.windows(2).all(|w| w[0].ops.end == w[1].ops.start), "{} {}", name, max_ops);
+            assert!(parts.iter().all(|p| !p.ops.is_empty() && p.ops.len() <= max_ops), "{} {}", name, max_ops);
+            assert!(parts.windows(2).all(|w| w[0].checkpoint == w[1].resume), "{} {}", name, max_ops);
+            assert!(parts[0].resume.is_empty() && parts[0].pending.is_empty(), "{}", name);
+            assert!(parts.last().unwrap().checkpoint.is_empty(), "{}", name);
+        }
+        assert_eq!(program.partition(total).unwrap().len(), 1, "{}", name);
+        assert!(program.partition(0).is_err());
+
+        // Parts cut at level boundaries whenever a whole level fits
+        let widest = program.steps.iter().map(|s| s.ops.len()).max().unwrap();
+        let boundaries: Vec<usize> = program.steps.iter()
+            .scan(0, |at, s| { *at += s.ops.len(); Some(*at) })
+            .collect();
+        for part in program.partition(widest).unwrap() {
+            assert!(part.pending.is_empty(), "{}", name);
+            assert!(boundaries.contains(&part.ops.end), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn part_programs_lint_clean() {
+    let dir = env::temp_dir().join(format!("delphi-partition-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = generator::generate_micro_ops(&circuit, &mapping, false, dir.join("whole.txt")).unwrap();
+        // Small budgets split levels; on the larger circuits they would write
+        // thousands of parts
+        let budgets = if name == "c17" { vec![3, 17] } else { vec![150] };
+        for max_ops in budgets {
+            let parts = program.partition(max_ops).unwrap();
+            let programs: Vec<_> = (0..parts.len()).map(|k| dir.join(format!("{}_part{}.txt", name, k))).collect();
+            let manifest = dir.join(format!("{}_partition.txt", name));
+            generator::generate_partitions(&program, &parts, max_ops, &programs, &manifest).unwrap();
+
+            let mut gates = 0;
+            for path in &programs {
+                let listing = fs::read_to_string(path).unwrap();
+                let issues = lint::lint_micro_ops_text(&listing);
+                assert!(issues.is_empty(), "{} {:?}: {:?}", name, path, issues);
+                gates += listing.lines().filter(|l| l.ends_with("True")).count();
+            }
+            // Every gate of the whole program runs in exactly one part
+            assert_eq!(gates, circuit.num_gates, "{} {}", name, max_ops);
+            let manifest = fs::read_to_string(&manifest).unwrap();
+            assert!(manifest.contains(&format!("Parts             : {}\n", parts.len())), "{}", name);
+            assert!(manifest.contains(&format!("{}_part{}.txt", name, parts.len() - 1)), "{}", name);
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,548 @@
+//tests/passes.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::passes::{ActiveLowInputs, Bind, Cluster, Commute, Decompose, DontCare, DontCareSet, Duplicate, InvertedReadout, MinCut, Minimize, Pass, PassManager, PassName, Xor};
+use delphi::program::Program;
+use delphi::{mapper, resynth, scheduler};
+use delphi::{simulator, verify, Circuit, MAX_FANIN, MAX_GATES};
+
+#[test]
+fn minimize_keeps_function_on_bench_suite() {
+    for (name, text) in common::circuits() {
+        let original = common::parsed(&text);
+        let mut circuit = common::parsed(&text);
+        let reports = PassManager::from_names(&[PassName::Minimize]).run(&mut circuit).unwrap();
+        assert_eq!(reports[0].gates_after, circuit.num_gates);
+        assert!(circuit.num_gates <= original.num_gates, "{}", name);
+        verify::verify_netlist(&circuit).unwrap();
+
+        assert_eq!(simulator::output_signals(&circuit), simulator::output_signals(&original), "{}", name);
+        let vectors = simulator::random_vectors(original.num_inputs, 512, 11);
+        assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+            simulator::simulate_netlist(&original, &vectors).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn minimize_collapses_redundant_sums_of_products() {
+    // x0 x1 + x0 !x1 written out gate by gate is just x0
+    let text = "\
+n2 = x0
+n3 = x1
+n4 = n2 n3
+n5 = n2 x1
+n6 = n4 n5
+n10001 = n6
+.end
+";
+    let mut circuit = common::parsed(text);
+    let report = Minimize::default().run(&mut circuit).unwrap();
+    assert_eq!((report.gates_before, report.gates_after), (6, 2));
+
+    let vectors = simulator::exhaustive_vectors(2).unwrap();
+    let outputs = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+    assert!(vectors.iter().zip(outputs).all(|(v, o)| o == vec![v[0]]));
+}
+
+#[test]
+fn minimize_leaves_wide_cones_alone() {
+    let text = "XOR_TREE n10001 = x0..x7\n.end\n";
+    let mut circuit = common::parsed(text);
+    let before = circuit.num_gates;
+    Minimize { max_inputs: 1 }.run(&mut circuit).unwrap();
+    assert_eq!(circuit.num_gates, before);
+}
+
+#[test]
+fn dont_care_keeps_function_on_care_inputs() {
+    for (name, text) in common::circuits() {
+        let original = common::parsed(&text);
+        let mut circuit = common::parsed(&text);
+        // x1 and x2 never differ
+        let width = original.num_inputs;
+        let cube = |a: char, b: char| format!("-{}{}{}\n", a, b, "-".repeat(width - 3));
+        let set = DontCareSet::parse(&(cube('0', '1') + &cube('1', '0'))).unwrap();
+        let report = DontCare { set }.run(&mut circuit).unwrap();
+        verify::verify_netlist(&circuit).unwrap();
+        assert!(circuit.num_gates <= original.num_gates, "{}", name);
+        assert_eq!(report.removed.len(), report.gates_before - report.gates_after, "{}", name);
+
+        let vectors: Vec<_> = simulator::random_vectors(width, 1024, 5).into_iter()
+            .map(|mut v| { v[2] = v[1]; v })
+            .collect();
+        assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+            simulator::simulate_netlist(&original, &vectors).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn dont_care_propagates_tied_inputs() {
+    // With x2 always high, n4 is constant 0 and n8, n5 reduce to x3 and x0
+    let mut circuit = common::parsed(&common::bundled("c17"));
+    let set = DontCareSet::parse("# x2 is tied high\n--0--\n").unwrap();
+    let report = DontCare { set }.run(&mut circuit).unwrap();
+    assert_eq!(report.removed, vec![3, 4, 5, 7, 8]);
+    assert_eq!(circuit.num_gates, 8);
+
+    assert!(DontCareSet::parse("01-\n1-\n").is_err());
+    assert!(DontCareSet::parse("0x1\n").is_err());
+    let set = DontCareSet::parse("0-0\n").unwrap();
+    assert!(DontCare { set }.run(&mut circuit).is_err());
+}
+
+#[test]
+fn dont_care_rebuilds_wide_gates() {
+    // With x0 tied high, n3 is constant 0 and n4 keeps its four other operands
+    let text = "\
+n3 = x0 x5
+n4 = n3 x1 x2 x3 x4
+n10001 = n4
+n10002 = n4 x1 x5
+.end
+";
+    let original = common::parsed(text);
+    let mut circuit = original.clone();
+    let set = DontCareSet::parse("# x0 is tied high\n0-----\n").unwrap();
+    let report = DontCare { set }.run(&mut circuit).unwrap();
+    verify::verify_netlist(&circuit).unwrap();
+    assert_eq!(report.removed, vec![3]);
+    assert_eq!((circuit.gates[0].out, circuit.gates[0].fanin), (4, 4));
+
+    let vectors: Vec<_> = simulator::exhaustive_vectors(original.num_inputs).unwrap().into_iter()
+        .filter(|v| v[0])
+        .collect();
+    assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+        simulator::simulate_netlist(&original, &vectors).unwrap());
+}
+
+#[test]
+fn bound_inputs_keep_function_and_shrink_the_program() {
+    for (name, text) in common::circuits() {
+        let original = common::parsed(&text);
+        let mut circuit = common::parsed(&text);
+        let bind = Bind::parse(&["x0=1", "x2=0"]).unwrap();
+        let report = bind.run(&mut circuit).unwrap();
+        verify::verify_netlist(&circuit).unwrap();
+        assert!(circuit.num_gates <= original.num_gates, "{}", name);
+        assert_eq!(simulator::output_signals(&circuit), simulator::output_signals(&original), "{}", name);
+        // Bound inputs are no longer read, except x0 by the tie-offs of constant outputs
+        assert!(circuit.gates.iter().all(|g| !g.inputs[..g.fanin].contains(&(MAX_GATES as i32 + 2))), "{}", name);
+        assert!(report.removed.len() >= report.gates_before - report.gates_after, "{}", name);
+
+        let vectors: Vec<_> = simulator::random_vectors(original.num_inputs, 1024, 7).into_iter()
+            .map(|mut v| { v[0] = true; v[2] = false; v })
+            .collect();
+        assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+            simulator::simulate_netlist(&original, &vectors).unwrap(), "{}", name);
+    }
+
+    // c17 keeps NOT x1, NOT x4 and the two outputs
+    let mut c17 = common::parsed(&common::bundled("c17"));
+    let before = resynth::FlowMetrics::measure(&c17);
+    Bind::parse(&["x0=1", "x2=0"]).unwrap().run(&mut c17).unwrap();
+    let after = resynth::FlowMetrics::measure(&c17);
+    assert_eq!((before.gates, after.gates), (13, 4));
+    assert!(after.levels < before.levels && after.cycles < before.cycles);
+
+    assert!(Bind::parse(&["x1=2"]).is_err());
+    assert!(Bind::parse(&["n5=1"]).is_err());
+    assert!(Bind::parse(&["x1=1", "x1=0"]).is_err());
+    assert!(Bind::parse(&["x5=1"]).unwrap().run(&mut c17).is_err());
+}
+
+#[test]
+fn bound_inputs_keep_wide_gates_wide() {
+    // Binding x0 low leaves n3 and n5 with four live operands each
+    let text = "\
+n3 = x0 x1 x2 x3 x4
+n4 = n3
+n5 = x0 n4 x1 x3 x5
+n10001 = n4
+n10002 = n5 x2
+.end
+";
+    let original = common::parsed(text);
+    let mut circuit = original.clone();
+    Bind::parse(&["x0=0"]).unwrap().run(&mut circuit).unwrap();
+    verify::verify_netlist(&circuit).unwrap();
+    assert_eq!(circuit.gates.iter().map(|g| g.fanin).max(), Some(4));
+
+    let vectors: Vec<_> = simulator::exhaustive_vectors(original.num_inputs).unwrap().into_iter()
+        .filter(|v| !v[0])
+        .collect();
+    assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+        simulator::simulate_netlist(&original, &vectors).unwrap());
+}
+
+// Schedules a circuit whose inputs or outputs a pass re-encoded and checks
+// that its mappings, programs and their encodings still compute `expected`
+fn assert_programs_compute(name: &str, circuit: &mut Circuit, vectors: &[Vec<bool>], expected: &[Vec<bool>]) -> Vec<Program> {
+    scheduler::compute_asap_schedule(circuit);
+    scheduler::compute_alap_schedule(circuit);
+    scheduler::compute_list_schedule(circuit);
+    assert_eq!(simulator::simulate_netlist(circuit, vectors).unwrap(), expected, "{}", name);
+    // The naive mapping's single row only fits the smaller circuits
+    let small = circuit.num_inputs + circuit.num_gates < 1000;
+    let mut programs = Vec::new();
+    for naive in [true, false].into_iter().filter(|&naive| small || !naive) {
+        let mapping = if naive { mapper::create_naive_mapping(circuit) } else { mapper::create_compact_mapping(circuit) };
+        assert_eq!(simulator::simulate_mapping(circuit, &mapping, vectors).unwrap(), expected, "{} naive {}", name, naive);
+        let program = Program::from_mapping(circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_program(&program, vectors).unwrap(), expected, "{} naive {}", name, naive);
+
+        // Both encodings keep each load's and read's polarity
+        let mut bytes = Vec::new();
+        program.write_binary(&mut bytes).unwrap();
+        assert_eq!(Program::read_binary(&bytes).unwrap(), program, "{}", name);
+        let mut json = Vec::new();
+        program.write_json(&mut json).unwrap();
+        assert_eq!(Program::read_json(std::str::from_utf8(&json).unwrap()).unwrap(), program, "{}", name);
+        programs.push(program);
+    }
+    programs
+}
+
+#[test]
+fn inverted_readout_keeps_function_and_saves_the_output_nots() {
+    for (name, text) in common::circuits() {
+        let original = common::parsed(&text);
+        let mut circuit = common::parsed(&text);
+        let report = InvertedReadout.run(&mut circuit).unwrap();
+        circuit.index_signals();
+        verify::verify_netlist(&circuit).unwrap();
+        assert_eq!(report.removed.len(), circuit.inverted_outputs.len(), "{}", name);
+        assert_eq!(circuit.num_gates + report.removed.len(), original.num_gates, "{}", name);
+        assert_eq!(simulator::output_signals(&circuit), simulator::output_signals(&original), "{}", name);
+
+        let vectors = simulator::random_vectors(original.num_inputs, 256, 5);
+        let expected = simulator::simulate_netlist(&original, &vectors).unwrap();
+        for program in assert_programs_compute(name, &mut circuit, &vectors, &expected) {
+            assert_eq!(program.inverted_slots().len(), report.removed.len(), "{}", name);
+        }
+    }
+
+    // Both outputs of c17 are inverters: each saves a cell and its evaluation
+    let mut c17 = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_naive_mapping(&mut c17);
+    let before = Program::from_mapping(&c17, &mapping).unwrap();
+    InvertedReadout.run(&mut c17).unwrap();
+    c17.index_signals();
+    scheduler::compute_asap_schedule(&mut c17);
+    let mapping = mapper::create_naive_mapping(&mut c17);
+    let after = Program::from_mapping(&c17, &mapping).unwrap();
+    assert_eq!(after.inverted_slots(), [0, 1]);
+    assert_eq!(before.cells().len() - after.cells().len(), 2);
+    assert!(after.serialized_cycles().total() < before.serialized_cycles().total());
+}
+
+#[test]
+fn active_low_inputs_keep_function_and_drop_input_nots() {
+    for (name, text) in common::circuits() {
+        let original = common::parsed(&text);
+        let mut circuit = common::parsed(&text);
+        let mut passes = PassManager::new();
+        passes.add(Box::new(ActiveLowInputs));
+        passes.add(Box::new(InvertedReadout));
+        let report = passes.run(&mut circuit).unwrap();
+        verify::verify_netlist(&circuit).unwrap();
+        assert!(report[0].gates_after <= report[0].gates_before, "{}", name);
+        // Only output NOTs and the one NOT serving the true value still read a stored input
+        for &input in &circuit.complemented_inputs {
+            let nots = circuit.readers((MAX_GATES + input) as i32).iter().filter(|&&i| circuit.gates[i].fanin == 1 && !circuit.gates[i].is_output).count();
+            assert!(nots <= 1, "{} x{}", name, input);
+        }
+
+        let vectors = simulator::random_vectors(original.num_inputs, 256, 9);
+        let expected = simulator::simulate_netlist(&original, &vectors).unwrap();
+        for program in assert_programs_compute(name, &mut circuit, &vectors, &expected) {
+            assert_eq!(program.complemented_inputs(), circuit.complemented_inputs.iter().copied().collect::<Vec<_>>(), "{}", name);
+        }
+    }
+
+    // Every input of c17 is read through one NOT
+    let mut c17 = common::parsed(&common::bundled("c17"));
+    let report = ActiveLowInputs.run(&mut c17).unwrap();
+    assert_eq!((report.gates_before, report.gates_after), (13, 8));
+    assert_eq!(c17.complemented_inputs.len(), 5);
+}
+
+// Parity of `inputs` inputs as a chain of five-gate XORs:
+// NOR(NOR(a, b), NOR(NOT a, NOT b)), followed by an output inverter
+fn sum_of_products_parity(inputs: usize) -> String {
+    let mut text = String::new();
+    let mut next = 100;
+    let mut gate = |operands: &[&str]| {
+        next += 1;
+        text.push_str(&format!("n{} = {}\n", next, operands.join(" ")));
+        format!("n{}", next)
+    };
+    let mut parity = "x0".to_string();
+    for k in 1..inputs {
+        let x = format!("x{}", k);
+        let (na, nb) = (gate(&[&parity]), gate(&[&x]));
+        let (p, q) = (gate(&[&parity, &x]), gate(&[&na, &nb]));
+        parity = gate(&[&p, &q]);
+    }
+    text.push_str(&format!("n10001 = {}\n.end\n", parity));
+    text
+}
+
+#[test]
+fn xor_pass_rebuilds_sum_of_products_xors() {
+    let original = common::parsed(&sum_of_products_parity(8));
+    let mut circuit = original.clone();
+    let report = Xor.run(&mut circuit).unwrap();
+    verify::verify_netlist(&circuit).unwrap();
+    // Chained XORs alternate between the template of the complement, which
+    // takes over the inverter of the XOR, and the XOR itself. The last one's
+    // inverter drives the output, so it is left as it is
+    assert_eq!((report.gates_before, report.gates_after), (36, 30));
+    assert_eq!(report.details, ["6 XORs rebuilt"]);
+    let vectors = simulator::exhaustive_vectors(8).unwrap();
+    assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+        simulator::simulate_netlist(&original, &vectors).unwrap());
+
+    for (name, text) in common::circuits() {
+        let original = common::parsed(&text);
+        let mut circuit = common::parsed(&text);
+        Xor.run(&mut circuit).unwrap();
+        verify::verify_netlist(&circuit).unwrap();
+        assert!(circuit.num_gates <= original.num_gates, "{}", name);
+        let vectors = simulator::random_vectors(original.num_inputs, 1024, 9);
+        assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(),
+            simulator::simulate_netlist(&original, &vectors).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn commute_saves_copies_without_changing_function() {
+    let copies = |mapping: &delphi::CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    for (name, text) in common::circuits() {
+        let mut original = common::scheduled(&text);
+        let before = copies(&mapper::create_compact_mapping(&mut original));
+
+        let mut circuit = common::scheduled(&text);
+        let report = PassManager::from_names(&[PassName::Commute]).run(&mut circuit).unwrap().remove(0);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let after = copies(&mapping);
+        assert!(after <= before, "{}: {} -> {}", name, before, after);
+        // The pass estimates the copies the mapper makes
+        assert!(report.details.contains(&format!("estimated copies {} -> {}", before, after)), "{}: {}", name, report);
+        if name.starts_with("synthetic") {
+            assert!(after < before, "{}: {}", name, report);
+        }
+
+        // Only operand order changes
+        assert_eq!(circuit.num_gates, original.num_gates);
+        for gate in &circuit.gates {
+            let was = original.driver(gate.out).unwrap();
+            let mut ops = [gate.inputs[0], gate.inputs[1]];
+            let mut old = [was.inputs[0], was.inputs[1]];
+            ops[..gate.fanin].sort_unstable();
+            old[..was.fanin].sort_unstable();
+            assert_eq!(ops[..gate.fanin], old[..was.fanin], "{}", name);
+        }
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 3);
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(),
+            simulator::simulate_netlist(&original, &vectors).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn clusters_share_a_row_run_and_save_copies() {
+    let copies = |mapping: &delphi::CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mut passes = PassManager::new();
+        passes.add(Box::new(Commute));
+        passes.run(&mut circuit).unwrap();
+        let original = circuit.clone();
+        let before = copies(&mapper::create_compact_mapping(&mut circuit));
+
+        let mut passes = PassManager::new();
+        passes.add(Box::new(Cluster::default()));
+        let report = passes.run(&mut circuit).unwrap().remove(0);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let after = copies(&mapping);
+        assert!(after <= before, "{}: {} -> {}", name, before, after);
+        assert!(report.details.contains(&format!("estimated copies {} -> {}", before, after)), "{}: {}", name, report);
+        assert!(report.details.contains(&format!("{} clusters", circuit.clusters.len())), "{}: {}", name, report);
+        if name != "c17" {
+            assert!(after < before, "{}: {}", name, report);
+        }
+
+        // Each cluster sits in one row, within the run of columns it reserved
+        for cluster in &circuit.clusters {
+            assert!((2..=4).contains(&cluster.len()), "{}: {:?}", name, cluster);
+            let gates: Vec<_> = cluster.iter().map(|&s| circuit.driver(s).unwrap()).collect();
+            let cells: Vec<_> = gates.iter().map(|g| g.gate_map.as_ref().unwrap()).collect();
+            assert!(cells.iter().all(|c| c.idx == cells[0].idx), "{}: {:?}", name, cluster);
+            let width: usize = gates.iter().map(|g| g.fanin).sum();
+            let (first, last) = (cells.iter().map(|c| c.jdx).min().unwrap(), cells.iter().map(|c| c.jdx).max().unwrap());
+            assert!(((last - first) as usize) < width, "{}: {:?}", name, cluster);
+        }
+
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 3);
+        let expected = simulator::simulate_netlist(&original, &vectors).unwrap();
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}", name);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{}", name);
+        // The parallel mapper reserves the same runs
+        let parallel = mapper::create_compact_mapping_parallel(&mut circuit);
+        assert_eq!(Program::from_mapping(&circuit, &parallel).unwrap(), program, "{}", name);
+    }
+}
+
+#[test]
+fn mincut_rows_copy_fewer_operands_in_the_same_width() {
+    let copies = |mapping: &delphi::CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mut passes = PassManager::new();
+        passes.add(Box::new(Commute));
+        passes.run(&mut circuit).unwrap();
+        let original = circuit.clone();
+        let default = mapper::create_compact_mapping(&mut circuit);
+        let before = copies(&default);
+
+        let mut passes = PassManager::new();
+        passes.add(Box::new(MinCut::default()));
+        let report = passes.run(&mut circuit).unwrap().remove(0);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let after = copies(&mapping);
+        // The partition counts copies and columns as the mapper makes them
+        assert!(report.details.contains(&format!("estimated copies {} -> {}", before, after)), "{}: {}", name, report);
+        assert!(mapping.max_jdx <= default.max_jdx, "{}: {}", name, report);
+        assert_eq!(mapping.max_idx, default.max_idx, "{}", name);
+        if name != "c17" {
+            assert!(after < before, "{}: {}", name, report);
+        }
+        for gate in &circuit.gates {
+            assert_eq!(gate.gate_map.as_ref().unwrap().idx as usize, circuit.planned_rows[&gate.out], "{}", name);
+        }
+
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 5);
+        let expected = simulator::simulate_netlist(&original, &vectors).unwrap();
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}", name);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{}", name);
+        // The parallel mapper leaves planned rows to the sequential one
+        let parallel = mapper::create_compact_mapping_parallel(&mut circuit);
+        assert_eq!(Program::from_mapping(&circuit, &parallel).unwrap(), program, "{}", name);
+    }
+}
+
+#[test]
+fn mincut_moves_a_gate_to_the_row_its_readers_share() {
+    // n1 follows x1 into its row, but its three readers sit in x0's row, so
+    // each copies it there; in x0's row only n1 copies x1. A chain of NOTs in
+    // x5's row leaves x0's row room for n1
+    let mut text = String::from("n1 = x0 x1\n");
+    for k in 0..3 {
+        text += &format!("n{} = x{} x0\nn{} = n1 n{}\n", 2 + k, 2 + k, 10006 + k, 2 + k);
+    }
+    text += "n50 = x5\n";
+    for k in 51..63 {
+        text += &format!("n{} = n{}\n", k, k - 1);
+    }
+    text += "n10063 = n62\n";
+    let mut circuit = common::scheduled(&text);
+    let copies = |mapping: &delphi::CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    assert_eq!(copies(&mapper::create_compact_mapping(&mut circuit)), 7);
+
+    let mut passes = PassManager::new();
+    passes.add(Box::new(MinCut::default()));
+    let report = passes.run(&mut circuit).unwrap().remove(0);
+    assert!(report.details.contains(&"estimated copies 7 -> 4".to_string()), "{}", report);
+    assert!(report.details.contains(&"widest row 15 -> 15".to_string()), "{}", report);
+    assert_eq!(circuit.planned_rows[&1], 0);
+    assert_eq!(copies(&mapper::create_compact_mapping(&mut circuit)), 4);
+}
+
+#[test]
+fn duplicates_replace_copies_of_a_gate_read_from_two_rows() {
+    let copies = |mapping: &delphi::CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    // n1 sits in x1's row; four readers sit in x0's row and four in x1's, so
+    // neither operand order saves its copies
+    let mut text = String::from("n1 = x0 x1\n");
+    for k in 0..4 {
+        text += &format!("n{} = x{} x0\nn{} = n1 n{}\n", 2 + k, 2 + k, 10006 + k, 2 + k);
+        text += &format!("n{} = x{} x1\nn{} = n1 n{}\n", 20 + k, 6 + k, 10030 + k, 20 + k);
+    }
+    let mut circuit = common::scheduled(&text);
+    let original = circuit.clone();
+    assert_eq!(copies(&mapper::create_compact_mapping(&mut circuit)), 13);
+
+    let mut passes = PassManager::new();
+    passes.add(Box::new(Duplicate));
+    let report = passes.run(&mut circuit).unwrap().remove(0);
+    assert_eq!(report.details, ["1 duplicates of n1", "estimated copies 13 -> 10"]);
+    assert_eq!(circuit.num_gates, original.num_gates + 1);
+    let duplicate = circuit.gates.last().unwrap();
+    assert_eq!((duplicate.inputs[1], duplicate.asap_level), (MAX_GATES as i32, 1));
+    assert_eq!(circuit.readers(duplicate.out).len(), 4);
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    assert_eq!(copies(&mapping), 10);
+
+    let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&original, &vectors).unwrap();
+    assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(), expected);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected);
+
+    // After commute, duplicates never add copies to the bench circuits
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mut passes = PassManager::new();
+        passes.add(Box::new(Commute));
+        passes.run(&mut circuit).unwrap();
+        let before = copies(&mapper::create_compact_mapping(&mut circuit));
+        let mut passes = PassManager::new();
+        passes.add(Box::new(Duplicate));
+        let report = passes.run(&mut circuit).unwrap().remove(0);
+        let after = copies(&mapper::create_compact_mapping(&mut circuit));
+        assert!(report.details.contains(&format!("estimated copies {} -> {}", before, after)), "{}: {}", name, report);
+        assert!(after <= before, "{}: {}", name, report);
+    }
+}
+
+#[test]
+fn wide_lines_decompose_to_the_device_fanin() {
+    let text = "\
+n3 = x0 x1 x2 x3 x4 x5 x6 x7 x8 x9 x10 x11
+n4 = x1
+n5 = n3 n4 x11 x2 x7
+n10001 = n5 x0 x3 x5 x6 x8 x9 x10 x11
+n10002 = n3
+.end
+";
+    // Lines wider than a crossbar cell are split while parsing
+    let original = common::parsed(text);
+    verify::verify_netlist(&original).unwrap();
+    assert!(original.gates.iter().all(|g| g.fanin <= MAX_FANIN));
+    assert_eq!(original.gates.iter().map(|g| g.fanin).max(), Some(MAX_FANIN));
+
+    let vectors = simulator::exhaustive_vectors(original.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&original, &vectors).unwrap();
+    for max_fanin in 2..=MAX_FANIN {
+        let mut circuit = original.clone();
+        let mut passes = PassManager::new();
+        passes.add(Box::new(Decompose { max_fanin }));
+        let report = passes.run(&mut circuit).unwrap().remove(0);
+        verify::verify_netlist(&circuit).unwrap();
+        assert!(circuit.gates.iter().all(|g| g.fanin <= max_fanin), "{}", report);
+        assert_eq!(simulator::simulate_netlist(&circuit, &vectors).unwrap(), expected, "{}", report);
+
+        // Wide gates map and run natively, copying every operand from another row
+        scheduler::compute_asap_schedule(&mut circuit);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        verify::verify_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected);
+        let mut binary = Vec::new();
+        program.write_binary(&mut binary).unwrap();
+        assert_eq!(Program::read_binary(&binary).unwrap(), program);
+    }
+}
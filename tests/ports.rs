@@ -0,0 +1,136 @@
+//tests/ports.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::mapper::{self, CompactPlan};
+use delphi::ports::{self, PortGroups};
+use delphi::program::{MicroOp, Program};
+use delphi::provenance::ArtifactHeader;
+use delphi::simulator;
+
+// Line of each input in a program: its row, or its column in a single row
+fn load_lines(program: &Program) -> Vec<usize> {
+    let mut lines = vec![0; program.num_inputs];
+    for (_, op) in program.ops() {
+        if let MicroOp::InputLoad { input, cell, .. } = op {
+            lines[*input] = if program.rows == 1 { cell.col } else { cell.row } as usize;
+        }
+    }
+    lines
+}
+
+#[test]
+fn grouping_files_name_ports_in_order() {
+    let groups = PortGroups::parse("# operands\na[3:0]\nb[0:3] = x7..x4   # reversed\ncarry\nflags = x10 x9\n").unwrap();
+    let described = ports::describe(&groups.groups);
+    assert_eq!(described, "a[3:0]=x0..x3 b[0:3]=x7..x4 carry=x8 flags=x10 x9");
+    // Ungrouped inputs follow the groups
+    assert_eq!(groups.lines(13), [0, 1, 2, 3, 7, 6, 5, 4, 8, 10, 9, 11, 12]);
+
+    for (text, error) in [
+        ("a[7:0] = x0..x3", "lists 4 inputs for 8 bits"),
+        ("a[1:0]\nb[1:0] = x1 x2", "x1 on line 2 is already in port a"),
+        ("a[1:0]\na[3:2]", "grouped twice"),
+        ("a[1:0] = n1 n2", "not a primary input"),
+        ("a[1:0 = x0 x1", "no closing ]"),
+        ("a = x0 x0", "lists an input twice"),
+    ] {
+        let message = format!("{:#}", PortGroups::parse(text).unwrap_err());
+        assert!(message.contains(error), "{}: {}", text, message);
+    }
+}
+
+#[test]
+fn grouped_inputs_sit_on_adjacent_lines_and_keep_function() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        // Bytes of inputs, each listed from its highest input down
+        let bytes: Vec<String> = (0..circuit.num_inputs / 8)
+            .map(|k| format!("w{}[7:0] = x{}..x{}", k, 8 * k + 7, 8 * k))
+            .collect();
+        let groups = PortGroups::parse(&bytes.join("\n")).unwrap();
+        groups.apply(&mut circuit).unwrap();
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 3);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+
+        let small = circuit.num_inputs + circuit.num_gates < 1000;
+        let mut programs = Vec::new();
+        for mapping in [
+            small.then(|| mapper::create_naive_mapping(&mut circuit)),
+            Some(mapper::create_compact_mapping(&mut circuit)),
+            Some(mapper::create_compact_mapping_parallel(&mut circuit)),
+        ].into_iter().flatten() {
+            assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}", name);
+            programs.push(Program::from_mapping(&circuit, &mapping).unwrap());
+        }
+        if let Some(plan) = CompactPlan::new(&circuit) {
+            programs.push(Program::from_plan(&circuit, &plan, None).unwrap());
+        }
+        for program in &programs {
+            assert_eq!(simulator::simulate_program(program, &vectors).unwrap(), expected, "{}", name);
+            let lines = load_lines(program);
+            assert_eq!(lines, circuit.input_lines, "{}", name);
+            // Bit 0 of each byte first, on consecutive lines
+            for (k, group) in groups.groups.iter().enumerate() {
+                let bits: Vec<usize> = group.inputs.iter().map(|&i| lines[i]).collect();
+                assert_eq!(bits, (8 * k..8 * k + 8).collect::<Vec<_>>(), "{} {}", name, group.name);
+            }
+        }
+    }
+}
+
+#[test]
+fn the_artifact_header_lists_the_ports() {
+    let dir = env::temp_dir().join(format!("delphi-ports-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let options = vec!["process".to_string()];
+    let plain = ArtifactHeader::new(&netlist, &options, &circuit).unwrap();
+    assert!(plain.lines().iter().all(|l| !l.starts_with("Ports")));
+
+    PortGroups::parse("a[1:0] = x4 x2\nb = x0").unwrap().apply(&mut circuit).unwrap();
+    let header = ArtifactHeader::new(&netlist, &options, &circuit).unwrap();
+    assert!(header.lines().contains(&"Ports     : a[1:0]=x4 x2 b=x0".to_string()), "{:?}", header.lines());
+    assert!(PortGroups::parse("a[7:0]").unwrap().apply(&mut circuit).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unused_inputs_take_no_line() {
+    // x1 and x3 are never read; x4 heads a port, so it keeps the first line
+    let text = "n10001 = x0 x2\nn10002 = x2 x4\nn5 = x4\nn10003 = n5 x0\n";
+    let mut circuit = common::scheduled(text);
+    PortGroups::parse("a[1:0] = x4 x3").unwrap().apply(&mut circuit).unwrap();
+    let unused = ports::unused_inputs(&circuit);
+    assert_eq!(unused, [1, 3]);
+    ports::drop_inputs(&mut circuit, &unused);
+    assert_eq!(circuit.mapped_inputs().collect::<Vec<_>>(), [0, 2, 4]);
+    assert_eq!((circuit.input_line(4), circuit.input_line(0), circuit.input_line(2)), (0, 1, 2));
+
+    let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    let parallel = mapper::create_compact_mapping_parallel(&mut circuit);
+    assert_eq!(naive.max_jdx as usize, 2 + circuit.num_gates);
+    assert_eq!(compact.max_idx, 2);
+    let plan = CompactPlan::new(&circuit).unwrap();
+    let mut programs = vec![Program::from_plan(&circuit, &plan, None).unwrap()];
+    for mapping in [&naive, &compact, &parallel] {
+        assert_eq!(simulator::simulate_mapping(&circuit, mapping, &vectors).unwrap(), expected);
+        programs.push(Program::from_mapping(&circuit, mapping).unwrap());
+    }
+    for program in &programs {
+        // Vectors keep a bit for every input
+        assert_eq!((program.num_inputs, program.loaded_inputs()), (5, 3));
+        assert_eq!(simulator::simulate_program(program, &vectors).unwrap(), expected);
+        assert_eq!(load_lines(program), [1, 0, 2, 0, 0]);
+    }
+
+    // A netlist that reads no input keeps them all
+    assert!(ports::unused_inputs(&common::parsed("n10001 = n2\nn2 = n1\n")).is_empty());
+}
@@ -0,0 +1,76 @@
+//tests/preset.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::mapper::RowPolicy;
+use delphi::passes::{Cluster, Commute, Duplicate, MinCut, PassManager, PassName};
+use delphi::preset::{Preset, Settings};
+use delphi::{mapper, scheduler, simulator, Circuit, CrossbarMapping};
+
+// The process pipeline as a preset sets it up, up to the compact mapping
+fn mapped(text: &str, settings: &Settings) -> (Circuit, CrossbarMapping) {
+    let mut circuit = common::parsed(text);
+    let mut passes = PassManager::new();
+    for &name in &settings.passes {
+        passes.add(name.create());
+    }
+    passes.run(&mut circuit).unwrap();
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule(&mut circuit);
+
+    let mut passes = PassManager::new();
+    if settings.commute {
+        passes.add(Box::new(Commute));
+    }
+    if settings.duplicate {
+        passes.add(Box::new(Duplicate));
+    }
+    if let Some(max_size) = settings.cluster {
+        passes.add(Box::new(Cluster { max_size }));
+    }
+    if settings.placement == RowPolicy::Mincut {
+        passes.add(Box::new(MinCut { passes: settings.mincut_passes }));
+    }
+    passes.run(&mut circuit).unwrap();
+    let mut mapping = mapper::create_compact_mapping(&mut circuit);
+    if settings.reuse_copies {
+        mapper::reuse_copies(&mut circuit, &mut mapping);
+    }
+    (circuit, mapping)
+}
+
+#[test]
+fn presets_add_effort_from_fast_to_quality() {
+    let [fast, balanced, quality] = [Preset::Fast, Preset::Balanced, Preset::Quality].map(Preset::settings);
+    assert!(fast.passes.is_empty() && !fast.commute && fast.parallel);
+    for (lower, higher) in [(&fast, &balanced), (&balanced, &quality)] {
+        assert!(lower.passes.iter().all(|p| higher.passes.contains(p)));
+        assert!(higher.passes.len() > lower.passes.len() || (higher.commute && !lower.commute));
+        assert!((higher.reuse_copies || !lower.reuse_copies) && (higher.duplicate || !lower.duplicate));
+    }
+    assert_eq!(quality.placement, RowPolicy::Mincut);
+    assert!(quality.mincut_passes > MinCut::default().passes);
+    assert_eq!(quality.passes, vec![PassName::Minimize, PassName::Xor]);
+
+    assert_eq!(Preset::Quality.to_string(), "quality");
+    assert_eq!(fast.to_string(), "no passes, netlist operand order, last-operand placement, parallel");
+    assert_eq!(quality.to_string(),
+        "passes minimize xor, operand order chosen, duplication, clusters of 4, min-cut placement (16 passes), copy reuse");
+}
+
+#[test]
+fn quality_maps_with_fewer_copies_than_fast() {
+    let copies = |mapping: &CrossbarMapping| mapping.occupied_cells().filter(|c| c.is_copy).count();
+    for (name, text) in common::circuits().into_iter().filter(|(name, _)| *name != "c17") {
+        let vectors = simulator::random_vectors(common::parsed(&text).num_inputs, 128, 9);
+        let expected = simulator::simulate_netlist(&common::parsed(&text), &vectors).unwrap();
+        let mut counts = Vec::new();
+        for preset in [Preset::Fast, Preset::Balanced, Preset::Quality] {
+            let (circuit, mapping) = mapped(&text, &preset.settings());
+            assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}: {}", name, preset);
+            counts.push(copies(&mapping));
+        }
+        assert!(counts[0] >= counts[1] && counts[1] > counts[2], "{}: {:?}", name, counts);
+    }
+}
@@ -0,0 +1,216 @@
+//tests/program.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::program::{Cell, MicroOp, Program, ProgramFormat, Step};
+use delphi::provenance::ArtifactHeader;
+use delphi::lint::{self, LintKind, Place};
+use delphi::{generator, mapper, resynth, scheduler, signal_id, signal_name, simulator, Primitive};
+
+#[test]
+fn programs_compute_the_netlist_in_every_encoding() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 13);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{}", name);
+
+        let mut json = Vec::new();
+        program.write_json(&mut json).unwrap();
+        assert_eq!(Program::read_json(std::str::from_utf8(&json).unwrap()).unwrap(), program, "{}", name);
+        let mut binary = Vec::new();
+        program.write_binary(&mut binary).unwrap();
+        assert_eq!(Program::read_binary(&binary).unwrap(), program, "{}", name);
+        assert!(binary.len() < json.len(), "{}", name);
+        assert!(Program::read_binary(&binary[..binary.len() - 1]).is_err());
+
+        // Copies are explicit in the IR
+        let copies = program.steps.iter().flat_map(|s| &s.ops).filter(|op| matches!(op, MicroOp::Copy { .. })).count();
+        assert_eq!(copies, mapping.occupied_cells().filter(|c| c.is_copy).count(), "{}", name);
+    }
+
+    // Naive and NAND programs too
+    let c17 = common::scheduled(&common::bundled("c17"));
+    let vectors = simulator::exhaustive_vectors(c17.num_inputs).unwrap();
+    let expected = simulator::simulate_netlist(&c17, &vectors).unwrap();
+    for (naive, primitive) in [(true, Primitive::Nor), (false, Primitive::Nand), (true, Primitive::Nand)] {
+        let mut circuit = if primitive == Primitive::Nand { resynth::to_nand(&c17).unwrap() } else { c17.clone() };
+        scheduler::compute_asap_schedule(&mut circuit);
+        scheduler::compute_alap_schedule(&mut circuit);
+        scheduler::compute_list_schedule(&mut circuit);
+        let mapping = if naive { mapper::create_naive_mapping(&mut circuit) } else { mapper::create_compact_mapping(&mut circuit) };
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(program.primitive, primitive);
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{} {}", naive, primitive);
+    }
+}
+
+#[test]
+fn the_simulator_rejects_broken_programs() {
+    let cell = |row, col| Cell { row, col };
+    let program = |ops: Vec<MicroOp>| Program {
+        primitive: Primitive::Nor,
+        num_inputs: 1,
+        rows: 1,
+        cols: 3,
+        steps: vec![
+            Step { level: 0, ops: vec![MicroOp::InputLoad { input: 0, cell: cell(0, 0), complemented: false }] },
+            Step { level: 1, ops },
+        ],
+    };
+    let vectors = vec![vec![false], vec![true]];
+    let not = MicroOp::ExecNot { input: cell(0, 0), cell: cell(0, 1) };
+    let read = MicroOp::Read { signal: 1, cell: cell(0, 1), slot: 0, inverted: false };
+
+    let good = program(vec![MicroOp::Init { cells: vec![cell(0, 1)] }, not.clone(), read.clone()]);
+    assert_eq!(simulator::simulate_program(&good, &vectors).unwrap(), vec![vec![true], vec![false]]);
+
+    let uninitialized = program(vec![not.clone(), read.clone()]);
+    let error = simulator::simulate_program(&uninitialized, &vectors).unwrap_err().to_string();
+    assert!(error.contains("without being initialized"), "{}", error);
+    let early = program(vec![MicroOp::Init { cells: vec![cell(0, 1)] }, read.clone(), not.clone()]);
+    assert!(simulator::simulate_program(&early, &vectors).is_err());
+    let unwritten = program(vec![MicroOp::Read { signal: 1, cell: cell(0, 2), slot: 0, inverted: false }]);
+    assert!(simulator::simulate_program(&unwritten, &vectors).is_err());
+    assert!(simulator::simulate_program(&good, &[vec![true, false]]).is_err());
+    let twice = program(vec![MicroOp::Init { cells: vec![cell(0, 1)] }, not.clone(), read.clone(), read.clone()]);
+    let error = simulator::simulate_program(&twice, &vectors).unwrap_err().to_string();
+    assert!(error.contains("read twice"), "{}", error);
+}
+
+#[test]
+fn outputs_are_read_into_numbered_slots() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+
+        // The read phase closes the program and fills the slots in output order
+        let phase = program.steps.last().unwrap();
+        assert_eq!(phase.level, 0, "{}", name);
+        assert!(phase.ops.iter().all(|op| matches!(op, MicroOp::Read { .. })), "{}", name);
+        let outputs: Vec<(usize, i32)> = program.outputs().map(|(slot, signal, _)| (slot, signal)).collect();
+        assert_eq!(outputs, simulator::output_signals(&circuit).into_iter().enumerate().collect::<Vec<_>>(), "{}", name);
+        for (_, signal, cell) in program.outputs() {
+            let driver = circuit.driver(signal).and_then(|g| g.gate_map.as_ref()).unwrap();
+            assert_eq!((cell.row, cell.col), (driver.idx as u32, driver.jdx as u32), "{}", name);
+        }
+
+        let mut listing = Vec::new();
+        program.write_text(&mut listing, 1).unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+        let reads: Vec<&str> = listing.lines().filter(|l| l.starts_with("READ")).collect();
+        assert_eq!(reads.len(), circuit.num_outputs, "{}", name);
+        assert!(lint::lint_micro_ops_text(&listing).is_empty(), "{}", name);
+        assert_eq!(program.clone().without_read_phase().outputs().count(), 0);
+    }
+
+    let listing = "# Level:  1 ___\n   0 False    0 /0                         1 True\n# Read phase ___\nREAD 0x1 0 n1\nREAD 0x1 0 n1\nREAD 0x2 5 n2\n";
+    let kinds: Vec<(Place, LintKind)> = lint::lint_micro_ops_text(listing).iter().map(|i| (i.at, i.kind)).collect();
+    assert_eq!(kinds, vec![(Place::Line(5), LintKind::DoubleWrite), (Place::Line(6), LintKind::ReadBeforeWrite), (Place::Line(6), LintKind::OutOfBounds)]);
+}
+
+#[test]
+fn annotated_listings_name_every_gate() {
+    let dir = env::temp_dir().join(format!("delphi-annotate-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let (plain, annotated, streamed) = (dir.join("plain.txt"), dir.join("annotated.txt"), dir.join("streamed.txt"));
+        generator::generate_micro_ops(&circuit, &mapping, false, &plain).unwrap();
+        generator::generate_micro_ops_with_tech(&circuit, &mapping, false, None, true, &annotated).unwrap();
+        generator::stream_compact_micro_ops_with_tech(&mut circuit, None, true, &streamed).unwrap();
+        let listing = fs::read_to_string(&annotated).unwrap();
+        assert_eq!(fs::read_to_string(&streamed).unwrap(), listing, "{}", name);
+        assert!(lint::lint_micro_ops_text(&listing).is_empty(), "{}", name);
+
+        // Without the legend and the comments it is the plain listing
+        let stripped: Vec<&str> = listing.lines()
+            .skip_while(|l| l.starts_with('#') && !l.starts_with("# Level:"))
+            .map(|l| l.split("  # ").next().unwrap())
+            .collect();
+        assert_eq!(stripped.join("\n") + "\n", fs::read_to_string(&plain).unwrap(), "{}", name);
+
+        // Each gate is named once, with its gate type
+        let mut named: Vec<&str> = listing.lines()
+            .filter(|l| !l.starts_with('#'))
+            .filter_map(|l| l.split_once("  # ").map(|(_, c)| c))
+            .filter_map(|c| c.split_once('('))
+            .map(|(lhs, _)| lhs)
+            .collect();
+        named.sort_unstable();
+        let mut gates: Vec<String> = circuit.gates[..circuit.num_gates].iter()
+            .map(|g| format!("{} = {}", signal_name(g.out), if g.fanin == 1 { "NOT" } else { "NOR" }))
+            .collect();
+        gates.sort_unstable();
+        assert_eq!(named, gates, "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gates_are_located_and_described_by_name() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let gate = |circuit: &delphi::Circuit, name: &str| circuit.driver_index(signal_id(name).unwrap()).unwrap();
+    assert_eq!(circuit.describe_gate(gate(&circuit, "n5")), "n5 = NOR(n4, n3), ASAP 2 ALAP 3 LIST 2, not mapped");
+    assert_eq!(circuit.describe_gate(circuit.num_gates), "no gate 13 (the circuit has 13)");
+
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let naive_cells: Vec<Option<Cell>> = (0..circuit.num_gates).map(|i| naive.location_of(&circuit, i)).collect();
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    for i in 0..circuit.num_gates {
+        let cell = compact.location_of(&circuit, i).unwrap();
+        let placed = circuit.gates[i].gate_map.as_ref().unwrap();
+        assert_eq!((cell.row, cell.col), (placed.idx as u32, placed.jdx as u32));
+        assert!(circuit.describe_gate(i).ends_with(&format!(", cell {}", cell)), "{}", circuit.describe_gate(i));
+    }
+    // The naive mapping keeps every gate in row 0
+    assert!(naive_cells.iter().all(|c| c.is_some_and(|c| c.row == 0)));
+    assert_eq!(compact.location_of(&circuit, circuit.num_gates), None);
+
+    let output = gate(&circuit, "n1");
+    let cell = compact.location_of(&circuit, output).unwrap();
+    assert_eq!(circuit.describe_gate(output), format!("n1 = NOT(n10) [output], ASAP 5 ALAP 5 LIST 5, cell {}", cell));
+}
+
+#[test]
+fn python_scripts_replay_the_program() {
+    let dir = env::temp_dir().join(format!("delphi-python-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let script = dir.join("c17_compact.py");
+    generator::export_program(&program, ProgramFormat::Python, &script).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+    ArtifactHeader::new(&netlist, &["process".to_string()], &circuit).unwrap().stamp(&script).unwrap();
+
+    // The header goes below the shebang, and every op is one tuple of STEPS
+    let text = fs::read_to_string(&script).unwrap();
+    assert!(text.starts_with("#!/usr/bin/env python3\n# Tool      : delphi"), "{}", &text[..80]);
+    let ops = program.steps.iter().map(|s| s.ops.len()).sum::<usize>();
+    let tuples = text.lines().filter(|l| l.starts_with("        (\"")).count();
+    assert_eq!(tuples, ops);
+    assert!(text.contains("OUTPUTS = [\"n1\", \"n2\"]"));
+
+    // Run it where NumPy is installed
+    let numpy = process::Command::new("python3").args(["-c", "import numpy"]).output();
+    if numpy.is_ok_and(|o| o.status.success()) {
+        let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+        let expected = simulator::simulate_program(&program, &vectors).unwrap();
+        let output = process::Command::new("python3").arg(&script).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let lines: Vec<String> = vectors.iter().zip(&expected)
+            .map(|(v, o)| format!("{} -> {}", simulator::format_vector(v), simulator::format_vector(o)))
+            .collect();
+        let printed: Vec<&str> = std::str::from_utf8(&output.stdout).unwrap().lines().skip(1).collect();
+        assert_eq!(printed, lines);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
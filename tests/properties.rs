@@ -0,0 +1,164 @@
+//tests/properties.rs
+use proptest::prelude::*;
+
+use std::{env, fs, process};
+
+use delphi::scheduler::Schedule;
+use delphi::{generator, mapper, parser, scheduler, simulator, verify, Circuit, TableGate};
+
+// Random netlist text in the native format: `num_inputs` primary inputs,
+// a mix of NOT, NOR and the 4/5-variable expansions, and up to three outputs
+fn netlist_strategy() -> impl Strategy<Value = String> {
+    (1usize..8, 1usize..40, 1usize..4, any::<u64>()).prop_map(|(inputs, gates, outputs, seed)| {
+        let mut rng = seed;
+        let mut next = move |bound: usize| {
+            // xorshift keeps generation deterministic for a given seed
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+            (rng % bound as u64) as usize
+        };
+
+        let mut signals: Vec<String> = (0..inputs).map(|i| format!("x{}", i)).collect();
+        let mut lines = Vec::new();
+        for g in 0..gates {
+            let arity = 1 + next(4);
+            let operands: Vec<String> = (0..arity)
+                .map(|_| signals[next(signals.len())].clone())
+                .collect();
+            let name = format!("n{}", outputs + 1 + g);
+            lines.push(format!("{} = {}", name, operands.join(" ")));
+            signals.push(name);
+        }
+
+        let internal = &signals[inputs..];
+        for o in 0..outputs.min(internal.len()) {
+            lines.push(format!("n{} = {}", 10001 + o, internal[internal.len() - 1 - o]));
+        }
+        lines.push(".end".to_string());
+        lines.join("\n")
+    })
+}
+
+fn build(text: &str) -> Circuit {
+    let mut circuit = Circuit::new();
+    parser::parse_netlist_str(text, &mut circuit).expect("generated netlist parses");
+    parser::find_primary_inputs(&mut circuit);
+    circuit
+}
+
+fn schedule(circuit: &mut Circuit) {
+    scheduler::compute_asap_schedule(circuit);
+    scheduler::compute_alap_schedule(circuit);
+    scheduler::compute_list_schedule(circuit);
+}
+
+proptest! {
+    // Every case allocates full-size crossbars, so keep the count modest
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn parsed_netlists_are_valid(text in netlist_strategy()) {
+        let circuit = build(&text);
+        verify::verify_netlist(&circuit).map_err(|e| TestCaseError::fail(e.to_string()))?;
+    }
+
+    #[test]
+    fn schedules_respect_dependencies(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        schedule(&mut circuit);
+        verify::verify_schedule(&circuit).map_err(|e| TestCaseError::fail(e.to_string()))?;
+    }
+
+    #[test]
+    fn schedule_levels_partition_the_gates(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        schedule(&mut circuit);
+        for (schedule, level) in [
+            (Schedule::asap(&circuit), (|g| g.asap_level) as fn(&TableGate) -> i32),
+            (Schedule::alap(&circuit), |g| g.alap_level),
+            (Schedule::list(&circuit), |g| g.list_level),
+        ] {
+            let mut seen = 0;
+            let mut previous = 0;
+            for (l, gates) in schedule.levels() {
+                prop_assert!(l > previous && !gates.is_empty());
+                prop_assert!(gates.windows(2).all(|w| w[0] < w[1]));
+                for &i in gates {
+                    prop_assert_eq!(level(&circuit.gates[i]), l);
+                    prop_assert_eq!(schedule.level_of(i), Some(l));
+                }
+                prop_assert_eq!(schedule.gates(l), gates);
+                seen += gates.len();
+                previous = l;
+            }
+            // Gates at levels below 1 belong to no level
+            prop_assert_eq!(seen, circuit.gates.iter().filter(|g| level(g) >= 1).count());
+            prop_assert_eq!(schedule.depth(), circuit.gates.iter().map(level).max().unwrap_or(0));
+            prop_assert_eq!(schedule.level_of(circuit.num_gates), None);
+        }
+    }
+
+    #[test]
+    fn scheduling_preserves_functionality(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+        let before = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        schedule(&mut circuit);
+        let after = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        prop_assert_eq!(before, after);
+    }
+
+    #[test]
+    fn naive_mapping_preserves_functionality(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        schedule(&mut circuit);
+        let mapping = mapper::create_naive_mapping(&mut circuit);
+        verify::verify_mapping(&circuit, &mapping).map_err(|e| TestCaseError::fail(e.to_string()))?;
+
+        let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+        let golden = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let mapped = simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap();
+        prop_assert_eq!(golden, mapped);
+    }
+
+    #[test]
+    fn compact_mapping_preserves_functionality(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        schedule(&mut circuit);
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        verify::verify_mapping(&circuit, &mapping).map_err(|e| TestCaseError::fail(e.to_string()))?;
+
+        let vectors = simulator::exhaustive_vectors(circuit.num_inputs).unwrap();
+        let golden = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let mapped = simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap();
+        prop_assert_eq!(golden, mapped);
+    }
+
+    #[test]
+    fn parallel_compact_mapping_matches_sequential(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        schedule(&mut circuit);
+        let sequential = mapper::create_compact_mapping(&mut circuit);
+        let sequential_maps: Vec<_> = circuit.gates.iter().map(|g| g.gate_map.clone()).collect();
+
+        let parallel = mapper::create_compact_mapping_parallel(&mut circuit);
+        let parallel_maps: Vec<_> = circuit.gates.iter().map(|g| g.gate_map.clone()).collect();
+        prop_assert_eq!(sequential, parallel);
+        prop_assert_eq!(sequential_maps, parallel_maps);
+    }
+
+    #[test]
+    fn streamed_micro_ops_match_compact_mapping(text in netlist_strategy()) {
+        let mut circuit = build(&text);
+        schedule(&mut circuit);
+        let dir = env::temp_dir().join(format!("delphi-properties-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (mapped, streamed) = (dir.join("mapped.txt"), dir.join("streamed.txt"));
+
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        generator::generate_micro_ops(&circuit, &mapping, false, &mapped).unwrap();
+        generator::stream_compact_micro_ops(&mut circuit, &streamed).unwrap();
+        prop_assert_eq!(fs::read_to_string(&mapped).unwrap(), fs::read_to_string(&streamed).unwrap());
+    }
+}
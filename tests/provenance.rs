@@ -0,0 +1,118 @@
+//tests/provenance.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::passes::{Decompose, Pass};
+use delphi::program::{Program, ProgramFormat};
+use delphi::provenance::{self, ArtifactHeader, ProvenanceReport};
+use delphi::{generator, lint, mapper, resynth, scheduler, MAX_GATES};
+
+#[test]
+fn every_artifact_kind_carries_the_header() {
+    let dir = env::temp_dir().join(format!("delphi-provenance-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let options = vec!["process".to_string(), netlist.display().to_string()];
+    let header = ArtifactHeader::new(&netlist, &options, &circuit).unwrap();
+    assert_eq!(header.input_hash, ArtifactHeader::new(&netlist, &options, &circuit).unwrap().input_hash);
+    assert_eq!((header.gates, header.inputs), (13, 5));
+    assert!(header.input_hash.starts_with("fnv1a64:") && header.timestamp.ends_with('Z'));
+
+    let (listing, verilog, json, binary) = (dir.join("c17_compact.txt"), dir.join("c17.v"), dir.join("c17.json"), dir.join("c17.bin"));
+    let program = generator::generate_micro_ops(&circuit, &mapping, false, &listing).unwrap();
+    generator::generate_magic_verilog(&circuit, &verilog).unwrap();
+    generator::export_program(&program, ProgramFormat::Json, &json).unwrap();
+    generator::export_program(&program, ProgramFormat::Binary, &binary).unwrap();
+    let raw = fs::read(&binary).unwrap();
+    for path in [&listing, &verilog, &json, &binary] {
+        header.stamp(path).unwrap();
+    }
+
+    let text = fs::read_to_string(&listing).unwrap();
+    assert!(text.starts_with(&format!("# Tool      : delphi {}\n", env!("CARGO_PKG_VERSION"))));
+    assert!(text.contains("# Circuit   : 13 gates, 5 inputs, 2 outputs\n"));
+    assert!(lint::lint_micro_ops_text(&text).is_empty());
+    assert!(fs::read_to_string(&verilog).unwrap().lines().take(6).all(|l| l.starts_with("// ")));
+
+    // Readers of JSON programs skip the header; binary programs are left as they were
+    let stamped = fs::read_to_string(&json).unwrap();
+    assert!(stamped.starts_with("{\"header\":{\"tool\":"));
+    assert_eq!(Program::read_json(&stamped).unwrap(), program);
+    assert_eq!(fs::read(&binary).unwrap(), raw);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+const LINES: &str = "# wide lines expand into several gates\n\
+n4 = x0 x1 x2 x3\n\
+n5 = n4 x1 x2 x3 x0 @critical\n\
+n6 = n4 n5 x0 x1 x2 x3\n\
+n10001 = n6\n\
+.end\n";
+
+#[test]
+fn gates_and_cells_keep_the_line_they_came_from() {
+    let mut circuit = common::parsed(LINES);
+    Decompose { max_fanin: 2 }.run(&mut circuit).unwrap();
+    circuit.num_gates = circuit.gates.len();
+    circuit.index_signals();
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::compute_alap_schedule(&mut circuit);
+
+    // The expanded and split gates share their line; annotations are left out
+    let lines: Vec<&str> = LINES.lines().collect();
+    for gate in &circuit.gates[..circuit.num_gates] {
+        let origin = gate.origin.as_ref().unwrap();
+        assert_eq!(lines[origin.line - 1].split('@').next().unwrap().trim(), origin.text);
+    }
+    for line in 2..=4 {
+        assert!(circuit.gates.iter().filter(|g| g.origin.as_ref().unwrap().line == line).count() > 1);
+    }
+    assert_eq!(circuit.origin(5).unwrap().to_string(), "line 3: n5 = n4 x1 x2 x3 x0");
+
+    // Copies have the origin of the value they hold, inputs none
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let cells = provenance::cell_origins(&circuit, &mapping);
+    assert!(cells.iter().any(|c| c.copy));
+    for cell in &cells {
+        let signal = mapping.cell(cell.row as usize, cell.col as usize).value;
+        assert_eq!(cell.origin.is_none(), signal >= MAX_GATES as i32, "{:?}", cell);
+        assert_eq!(cell.origin.as_ref(), circuit.origin(signal));
+    }
+
+    // NAND re-synthesis keeps the origins of the gates it rebuilds
+    let nand = resynth::to_nand(&common::parsed(LINES)).unwrap();
+    for gate in &nand.gates[..nand.num_gates] {
+        let inverts_input = gate.fanin == 1 && gate.inputs[0] >= MAX_GATES as i32 && !gate.is_output;
+        assert_eq!(gate.origin.is_none(), inverts_input, "{}", gate.out);
+    }
+    assert_eq!(nand.origin(1).unwrap().line, 5);
+}
+
+#[test]
+fn provenance_reports_read_back() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    let report = ProvenanceReport::new(&circuit, &naive, &compact);
+    assert_eq!(report.gates.len(), circuit.num_gates);
+    assert!(report.gates.iter().all(|g| g.origin.is_some()));
+    assert_eq!(report.naive.len(), naive.occupied_cells().count());
+
+    let dir = env::temp_dir().join(format!("delphi-origins-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("c17_provenance.json");
+    report.write_json(&path).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+    ArtifactHeader::new(&netlist, &[], &circuit).unwrap().stamp(&path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.contains("\"origin\":{\"line\":7,\"text\":\"n5 = n4 n3\"}"), "{}", text);
+    assert_eq!(ProvenanceReport::read_json(&path).unwrap(), report);
+    fs::remove_dir_all(&dir).unwrap();
+}
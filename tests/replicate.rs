@@ -0,0 +1,86 @@
+//tests/replicate.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::collections::HashSet;
+use std::{env, fs, process};
+
+use delphi::analysis::Line;
+use delphi::replicate::Replication;
+use delphi::{generator, mapper, MAX_COL};
+
+#[test]
+fn replication_gives_every_bit_its_own_column_group() {
+    for (name, text) in [("c17", common::bundled("c17")), ("synthetic", common::synthetic_netlist(12, 150, 3))] {
+        let mut circuit = common::scheduled(&text);
+        for mapping in [mapper::create_naive_mapping(&mut circuit), mapper::create_compact_mapping(&mut circuit)] {
+            let bits = Replication::max_bits(&mapping).min(8);
+            let replication = Replication::new(&circuit, &mapping, bits).unwrap();
+            assert!(bits * replication.cols_per_bit <= MAX_COL, "{}", name);
+
+            // Bit 0 uses the mapping's own input cells; the others sit in the same rows further right
+            let mut cells = HashSet::new();
+            for bit in 0..bits {
+                for input in 0..circuit.num_inputs {
+                    let (row, col) = replication.cell(bit, input);
+                    assert!(replication.columns(bit).contains(&col), "{}", name);
+                    assert_eq!((row, col - bit * replication.cols_per_bit), replication.cell(0, input), "{}", name);
+                    assert!(cells.insert((row, col)), "{}: {}x{} used twice", name, row, col);
+                    if bit == 0 {
+                        assert_eq!(mapping.cell(row, col).value, (delphi::MAX_GATES + input) as i32);
+                    }
+                }
+            }
+
+            let mut written = HashSet::new();
+            for write in replication.init_writes() {
+                for &(at, bit, input) in &write.bits {
+                    let cell = match write.line {
+                        Line::Wordline => (write.index, at),
+                        Line::Bitline => (at, write.index),
+                    };
+                    assert_eq!(cell, replication.cell(bit, input));
+                    assert!(written.insert(cell));
+                }
+            }
+            assert_eq!(written, cells, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn words_share_the_micro_ops_of_one_bit() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let levels = circuit.max_asap as usize;
+
+    // The naive mapping's single input row loads every bit at once
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let single = Replication::new(&circuit, &naive, 1).unwrap();
+    assert_eq!(single.cycles(), 4 * levels + 1);
+    let word = Replication::new(&circuit, &naive, 32).unwrap();
+    assert_eq!(word.cycles(), single.cycles());
+    assert!((word.throughput() - 32.0 * single.throughput()).abs() < 1e-9);
+
+    // The compact mapping's inputs share a column, so each bit takes a write
+    // until there are more bits than input rows
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    let fits = Replication::max_bits(&compact);
+    for bits in [1, 3, 16, fits] {
+        let word = Replication::new(&circuit, &compact, bits).unwrap();
+        assert_eq!(word.init_writes().len(), bits.min(circuit.num_inputs));
+        assert_eq!(word.cycles(), 4 * levels + bits.min(circuit.num_inputs));
+    }
+    assert!(Replication::new(&circuit, &compact, fits + 1).is_err());
+    assert!(Replication::new(&circuit, &compact, 0).is_err());
+
+    let dir = env::temp_dir().join(format!("delphi-replicate-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("c17_replicated.v");
+    generator::generate_replicated_verilog(&circuit, 4, &path).unwrap();
+    let verilog = fs::read_to_string(&path).unwrap();
+    assert!(verilog.contains("module module_name_x4 ("), "{}", verilog);
+    assert_eq!(verilog.matches("  input  [3:0] ip_").count(), circuit.num_inputs);
+    assert_eq!(verilog.matches("  output [3:0] op_").count(), 2);
+    assert!(verilog.contains(".ip_5(ip_5[b])"), "{}", verilog);
+    fs::remove_dir_all(&dir).unwrap();
+}
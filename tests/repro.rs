@@ -0,0 +1,65 @@
+//tests/repro.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::provenance;
+use delphi::repro::{self, Bundle, Role, MANIFEST};
+use delphi::{generator, mapper};
+
+// A bundle of c17 with its compact program as the only artifact
+fn bundle_c17(name: &str) -> std::path::PathBuf {
+    let dir = env::temp_dir().join(format!("delphi-repro-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let netlist = dir.join("c17.txt");
+    fs::write(&netlist, common::bundled("c17")).unwrap();
+
+    let mut bundle = Bundle::new(dir.join(format!("{}-staging", name))).unwrap();
+    bundle.add_file(&netlist, "input/c17.txt", Role::Input).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let listing = bundle.root().join("artifacts/micro_ins_compact/c17_compact.txt");
+    fs::create_dir_all(listing.parent().unwrap()).unwrap();
+    generator::generate_micro_ops(&circuit, &mapping, false, &listing).unwrap();
+    bundle.add_bytes("run.log", b"Compact micro-ops written to: artifacts/micro_ins_compact/c17_compact.txt\n", Role::Log).unwrap();
+    bundle.add_dir("artifacts", Role::Artifact).unwrap();
+    // A file is bundled once
+    assert!(bundle.add_bytes("input/c17.txt", b"", Role::Input).is_err());
+
+    let command = vec!["process".to_string(), "input/c17.txt".to_string(), "-o".to_string(), "artifacts".to_string()];
+    let path = dir.join(format!("{}.tar.gz", name));
+    bundle.finish(command, Vec::new(), &path).unwrap();
+    assert!(!dir.join(format!("{}-staging", name)).exists());
+    path
+}
+
+#[test]
+fn bundles_list_every_file_and_are_reproducible() {
+    env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+    let (first, second) = (bundle_c17("first"), bundle_c17("second"));
+
+    let manifest = repro::read_manifest(&first).unwrap();
+    assert_eq!(manifest.tool, provenance::tool());
+    assert_eq!((manifest.source_date_epoch, manifest.generated.as_str()), (1700000000, "2023-11-14T22:13:20Z"));
+    assert_eq!(manifest.command[1], "input/c17.txt");
+    let files: Vec<(&str, Role)> = manifest.files.iter().map(|f| (f.path.as_str(), f.role)).collect();
+    assert_eq!(files, [
+        ("input/c17.txt", Role::Input),
+        ("run.log", Role::Log),
+        ("artifacts/micro_ins_compact/c17_compact.txt", Role::Artifact),
+    ]);
+    let input = &manifest.files[0];
+    assert_eq!(input.hash, provenance::content_hash(common::bundled("c17").as_bytes()));
+    assert_eq!(input.bytes, common::bundled("c17").len() as u64);
+
+    // The same run packs into the same bytes, manifest first
+    assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(&first).unwrap()));
+    let names: Vec<String> = archive.entries().unwrap()
+        .map(|e| e.unwrap().path().unwrap().display().to_string())
+        .collect();
+    assert_eq!(names[0], MANIFEST);
+    assert_eq!(names.len(), 4);
+    fs::remove_dir_all(first.parent().unwrap()).unwrap();
+}
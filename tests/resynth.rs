@@ -0,0 +1,40 @@
+//tests/resynth.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::{mapper, resynth, scheduler, simulator, verify, Primitive};
+
+#[test]
+fn nand_form_is_equivalent_and_maps_correctly() {
+    for (name, text) in common::circuits() {
+        let nor = common::parsed(&text);
+        let mut nand = resynth::to_nand(&nor).unwrap();
+        assert_eq!(nand.primitive, Primitive::Nand);
+        verify::verify_netlist(&nand).unwrap();
+        assert_eq!(simulator::output_signals(&nand), simulator::output_signals(&nor), "{}", name);
+
+        let vectors = simulator::random_vectors(nor.num_inputs, 256, 5);
+        let expected = simulator::simulate_netlist(&nor, &vectors).unwrap();
+        assert_eq!(simulator::simulate_netlist(&nand, &vectors).unwrap(), expected, "{}", name);
+
+        scheduler::compute_asap_schedule(&mut nand);
+        scheduler::compute_alap_schedule(&mut nand);
+        scheduler::compute_list_schedule(&mut nand);
+        let mapping = mapper::create_compact_mapping(&mut nand);
+        verify::verify_mapping(&nand, &mapping).unwrap();
+        assert_eq!(simulator::simulate_mapping(&nand, &mapping, &vectors).unwrap(), expected, "{}", name);
+    }
+}
+
+#[test]
+fn inverted_nors_collapse_into_nands() {
+    // n10000 = OR(x0, x1), n10001 = NOR(x0, x1)
+    let nor = common::parsed("n2 = x0 x1\nn10000 = n2\nn10001 = x0 x1\n.end\n");
+    let nand = resynth::to_nand(&nor).unwrap();
+
+    let two_input = nand.gates.iter().filter(|g| g.fanin == 2).count();
+    let not = nand.gates.iter().filter(|g| g.fanin == 1).count();
+    // Both inputs inverted once, one NAND per output plus the NOR's inverter
+    assert_eq!((two_input, not), (2, 3));
+    assert!(resynth::to_nand(&nand).is_err());
+}
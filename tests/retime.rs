@@ -0,0 +1,121 @@
+//tests/retime.rs
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, fs, process};
+
+use delphi::parser::{self, Sequential};
+use delphi::retime::Retiming;
+use delphi::Circuit;
+
+// Ten levels of logic into three latches in a row, then the output
+const PIPELINE_BLIF: &str = "\
+.model pipe
+.inputs a b c d
+.outputs y
+.latch s1 q1 re clk 0
+.latch q1 q2 re clk 0
+.latch q2 q3 re clk 0
+.names a b t1
+11 1
+.names t1 c t2
+1- 1
+-1 1
+.names t2 d t3
+10 1
+01 1
+.names t3 a t4
+11 1
+.names t4 b t5
+1- 1
+-1 1
+.names t5 c s1
+11 1
+.names q3 y
+1 1
+.end
+";
+
+// A toggle enabled by a: q' = a & !q, as ASCII and binary AIGER
+const TOGGLE_AAG: &[u8] = b"aag 3 1 1 1 1\n2\n4 6\n4\n6 2 5\n";
+const TOGGLE_AIG: &[u8] = b"aig 3 1 1 1 1\n6 0\n4\n\x01\x03";
+
+// Tests run side by side, so each load writes a directory of its own
+fn load(name: &str, bytes: &[u8]) -> Sequential {
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+    let dir = env::temp_dir().join(format!("delphi-retime-{}-{}", process::id(), LOADS.fetch_add(1, Ordering::Relaxed)));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, bytes).unwrap();
+    let sequential = parser::load_sequential(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    sequential
+}
+
+#[test]
+fn latches_are_cut_open_for_the_sequential_reader_only() {
+    let pipe = load("pipe.blif", PIPELINE_BLIF.as_bytes());
+    assert_eq!(pipe.latches, 3);
+    assert_eq!((pipe.primary_inputs(), pipe.primary_outputs()), (4, 1));
+    assert_eq!((pipe.circuit.num_inputs, pipe.circuit.num_outputs), (7, 4));
+    assert_eq!(pipe.circuit.bench_name, "pipe");
+
+    for (name, bytes) in [("toggle.aag", TOGGLE_AAG), ("toggle.aig", TOGGLE_AIG)] {
+        let toggle = load(name, bytes);
+        assert_eq!(toggle.latches, 1, "{}", name);
+        assert_eq!((toggle.primary_inputs(), toggle.primary_outputs()), (1, 1), "{}", name);
+    }
+    let mut ascii = Circuit::new();
+    let mut binary = Circuit::new();
+    parser::parse_sequential_aiger(TOGGLE_AAG, &mut ascii).unwrap();
+    parser::parse_sequential_aiger(TOGGLE_AIG, &mut binary).unwrap();
+    assert_eq!(ascii.num_gates, binary.num_gates);
+
+    // The combinational readers still refuse latches
+    assert!(parser::parse_aiger(TOGGLE_AAG, &mut Circuit::new()).is_err());
+    assert!(parser::parse_blif(PIPELINE_BLIF, &mut Circuit::new()).is_err());
+    assert!(parser::parse_sequential_blif(".latch a\n", &mut Circuit::new()).is_err());
+    let native = env::temp_dir().join(format!("delphi-retime-{}.txt", process::id()));
+    fs::write(&native, "n10001 = NOR(x0, x1)\n").unwrap();
+    let error = format!("{:#}", parser::load_sequential(&native).unwrap_err());
+    assert!(error.contains("AIGER and BLIF"), "{}", error);
+    fs::remove_file(&native).unwrap();
+}
+
+#[test]
+fn registers_move_to_balance_the_clouds() {
+    let pipe = load("pipe.blif", PIPELINE_BLIF.as_bytes());
+    let retiming = Retiming::new(&pipe).unwrap();
+
+    // The logic sits before the first latch, and the latches after it only
+    // buffer: 16 levels over four clouds take 4 each at best
+    assert_eq!(Retiming::period(&retiming.before), 10);
+    assert_eq!(Retiming::period(&retiming.after), 4);
+    assert!(Retiming::slowest(&retiming.after) < Retiming::slowest(&retiming.before));
+    assert_eq!(retiming.registers.0, 3);
+    assert!(retiming.registers.1 > 3);
+    assert!(retiming.lags.iter().all(|&lag| lag >= 0), "{:?}", retiming.lags);
+
+    // Every gate lands in exactly one cloud, before and after
+    for clouds in [&retiming.before, &retiming.after] {
+        assert_eq!(clouds.iter().map(|c| c.gates).sum::<usize>(), pipe.circuit.num_gates);
+        assert!(clouds.iter().all(|c| c.depth >= 1 && c.depth <= c.gates && c.cycles > 0), "{:?}", clouds);
+    }
+    let report = retiming.to_string();
+    assert!(report.starts_with("pipe: period 10 -> 4 levels"), "{}", report);
+}
+
+#[test]
+fn loops_keep_their_registers() {
+    // The toggle's loop holds one register, so its gates stay in one cloud
+    // and nothing moves
+    let toggle = load("toggle.aag", TOGGLE_AAG);
+    let retiming = Retiming::new(&toggle).unwrap();
+    assert_eq!(Retiming::period(&retiming.after), Retiming::period(&retiming.before));
+    assert_eq!(retiming.before, retiming.after);
+    assert_eq!(retiming.registers, (1, 1));
+
+    // Without latches the whole netlist is one cloud between input and output
+    let combinational = load("and.blif", b".inputs a b\n.outputs y\n.names a b y\n11 1\n");
+    let retiming = Retiming::new(&combinational).unwrap();
+    assert_eq!(retiming.after.len(), 1);
+    assert_eq!(retiming.registers, (0, 0));
+}
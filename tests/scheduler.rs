@@ -0,0 +1,100 @@
+//tests/scheduler.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::PathBuf;
+
+use delphi::scheduler::{self, Schedule};
+use delphi::tech::Technology;
+use delphi::verify;
+
+#[test]
+fn alap_matches_the_fixpoint_reference() {
+    let mut netlists = common::circuits();
+    netlists.extend((0..8).map(|seed| ("synthetic", common::synthetic_netlist(12, 150, seed + 1))));
+    netlists.push(("duplicate_reads", "n3 = x0\nn4 = n3 n3\nn10001 = n4\nn10002 = n3\n.end\n".to_string()));
+
+    for (name, text) in netlists {
+        let mut expected = common::parsed(&text);
+        common::fixpoint_alap(&mut expected);
+
+        let mut circuit = common::parsed(&text);
+        scheduler::compute_alap_schedule(&mut circuit);
+        assert_eq!(circuit.max_alap, expected.max_alap, "{}", name);
+        let levels = |c: &delphi::Circuit| c.gates.iter().map(|g| g.alap_level).collect::<Vec<_>>();
+        assert_eq!(levels(&circuit), levels(&expected), "{}", name);
+
+        // Running it again starts afresh
+        scheduler::compute_alap_schedule(&mut circuit);
+        assert_eq!(levels(&circuit), levels(&expected), "{}", name);
+
+        scheduler::compute_asap_schedule(&mut circuit);
+        scheduler::compute_list_schedule(&mut circuit);
+        verify::verify_schedule(&circuit).unwrap();
+    }
+}
+
+#[test]
+fn list_schedules_fill_the_array_the_technology_allows() {
+    for (name, text) in common::circuits() {
+        let circuit = common::scheduled(&text);
+        // Unbounded by a technology, the list schedule is as deep as ASAP
+        assert!(circuit.gates.iter().all(|g| g.list_level >= 1), "{}", name);
+        assert_eq!(circuit.max_list, circuit.max_asap, "{}", name);
+        let widest = Schedule::list(&circuit).levels().map(|(_, g)| g.len()).max().unwrap();
+        assert!(widest <= scheduler::list_bound(&circuit, None), "{}", name);
+
+        // Halving the bound never makes the schedule shallower
+        let curve = scheduler::list_tradeoff(&circuit, [1, 2, 4, 8, 16]);
+        assert_eq!(curve[0].levels as usize, circuit.num_gates, "{}", name);
+        for pair in curve.windows(2) {
+            assert!(pair[1].levels <= pair[0].levels && pair[1].max_gates <= pair[1].bound, "{}: {:?}", name, pair);
+        }
+    }
+
+    // Four rows of which one is reserved, and three activations at once
+    let mut tech = Technology::load(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tech/profiles.toml"), None).unwrap();
+    tech.periphery.max_row_activations = 3;
+    tech.periphery.rows = Some(4);
+    tech.periphery.reserved_rows = vec![0];
+    assert_eq!(scheduler::list_gate_limit(Some(&tech)), 3);
+    tech.periphery.rows = Some(3);
+    assert_eq!(scheduler::list_gate_limit(Some(&tech)), 2);
+
+    let mut circuit = common::parsed(&common::bundled("c17"));
+    scheduler::compute_asap_schedule(&mut circuit);
+    scheduler::compute_alap_schedule(&mut circuit);
+    scheduler::compute_list_schedule_for(&mut circuit, Some(&tech));
+    verify::verify_schedule(&circuit).unwrap();
+    assert!(Schedule::list(&circuit).levels().all(|(_, gates)| gates.len() <= 2));
+    assert_eq!(circuit.max_list, 7);
+    assert!(circuit.diagnostics.iter().any(|d| d.code == "list-bound"));
+}
+
+#[test]
+fn asap_names_the_gates_a_loop_keeps_from_becoming_ready() {
+    // Lines in reverse order take one sweep per level and schedule the same
+    for (name, text) in common::circuits() {
+        let ordered = common::scheduled(&text);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.starts_with('.')).collect();
+        let reversed: String = lines.iter().rev().map(|l| format!("{}\n", l)).collect();
+        let mut circuit = common::parsed(&reversed);
+        scheduler::compute_asap_schedule(&mut circuit);
+        scheduler::ensure_scheduled(&circuit).unwrap();
+        assert!(!circuit.diagnostics.has_errors(), "{}", name);
+        assert_eq!(circuit.max_asap, ordered.max_asap, "{}", name);
+        // Temporaries are numbered in line order
+        for gate in circuit.gates.iter().filter(|g| g.out >= 0) {
+            assert_eq!(gate.asap_level, ordered.driver(gate.out).unwrap().asap_level, "{}", name);
+        }
+    }
+
+    let mut circuit = common::parsed("n1 = n2 x0\nn2 = n1 x1\nn3 = x0 x1\nn10004 = n3 n1\n");
+    scheduler::compute_asap_schedule(&mut circuit);
+    assert_eq!(scheduler::unready_gates(&circuit).len(), 3);
+    assert_eq!(circuit.driver(3).unwrap().asap_level, 1);
+    let message = format!("{:#}", scheduler::ensure_scheduled(&circuit).unwrap_err());
+    assert_eq!(message, "3 of 4 gates never became ready, on or behind a combinational loop: \
+        n1 (waits for n2), n2 (waits for n1), n4 (waits for n1); missing signals n1 n2");
+    assert!(circuit.diagnostics.iter().any(|d| d.code == "unready" && d.message == message));
+}
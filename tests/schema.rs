@@ -0,0 +1,67 @@
+//tests/schema.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::mapper::{self, MappedCrossbar, MappingReport};
+use delphi::metrics::RunMetrics;
+use delphi::program::Program;
+use delphi::scheduler::ScheduleReport;
+use delphi::schema::{self, SCHEMA_VERSION};
+
+#[test]
+fn json_artifacts_read_back_in_this_and_the_unversioned_schema() {
+    let dir = env::temp_dir().join(format!("delphi-schema-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let compact = mapper::create_compact_mapping(&mut circuit);
+
+    let metrics = RunMetrics::new(&circuit);
+    metrics.write_json(dir.join("metrics.json")).unwrap();
+    assert_eq!(RunMetrics::read_json(dir.join("metrics.json")).unwrap(), metrics);
+
+    let schedule = ScheduleReport::new(&circuit);
+    assert_eq!(schedule.gates.len(), circuit.num_gates);
+    schedule.write_json(dir.join("schedule.json")).unwrap();
+    assert_eq!(ScheduleReport::read_json(dir.join("schedule.json")).unwrap(), schedule);
+
+    let mapping = MappingReport { circuit: "c17".to_string(), naive: MappedCrossbar::new(&naive), compact: MappedCrossbar::new(&compact) };
+    assert_eq!(mapping.compact.cells.len(), compact.occupied_cells().count());
+    assert_eq!(mapping.compact.cells.iter().filter(|c| c.copy).count(), compact.occupied_cells().filter(|c| c.is_copy).count());
+    mapping.write_json(dir.join("mapping.json")).unwrap();
+    assert_eq!(MappingReport::read_json(dir.join("mapping.json")).unwrap(), mapping);
+
+    let program = Program::from_mapping(&circuit, &compact).unwrap();
+    let mut json = Vec::new();
+    program.write_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(&format!("{{\"schema_version\":{},", SCHEMA_VERSION)), "{}", json);
+    assert_eq!(Program::read_json(&json).unwrap(), program);
+
+    // Artifacts written before the field existed read as they did
+    let unversioned = json.replacen(&format!("\"schema_version\":{},", SCHEMA_VERSION), "", 1);
+    assert_eq!(schema::version_of(&serde_json::from_str(&unversioned).unwrap()).unwrap(), schema::UNVERSIONED);
+    assert_eq!(Program::read_json(&unversioned).unwrap(), program);
+    let text = fs::read_to_string(dir.join("metrics.json")).unwrap();
+    let old = text.replacen(&format!("\"schema_version\": {},", SCHEMA_VERSION), "", 1);
+    assert!(!old.contains("schema_version"), "{}", old);
+    fs::write(dir.join("old.json"), old).unwrap();
+    assert_eq!(RunMetrics::read_json(dir.join("old.json")).unwrap(), metrics);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn newer_schemas_are_refused() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let compact = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &compact).unwrap();
+    let mut json = Vec::new();
+    program.write_json(&mut json).unwrap();
+    let newer = String::from_utf8(json).unwrap()
+        .replacen(&format!("\"schema_version\":{}", SCHEMA_VERSION), &format!("\"schema_version\":{}", SCHEMA_VERSION + 1), 1);
+    let error = Program::read_json(&newer).unwrap_err();
+    assert!(format!("{:#}", error).contains(&format!("schema version {}, but this delphi reads up to version {}", SCHEMA_VERSION + 1, SCHEMA_VERSION)),
+        "{:#}", error);
+}
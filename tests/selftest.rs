@@ -0,0 +1,50 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::mapper;
+use delphi::program::{MicroOp, Program};
+use delphi::selftest::{SelfTest, Snapshot};
+
+#[test]
+fn the_parallel_paths_match_the_sequential_ones() {
+    for (name, text) in common::circuits().into_iter().take(3) {
+        let fits = name != "synthetic_2400";
+        let circuit = common::parsed(&text);
+        let test = SelfTest::run(&circuit, &[1, 3], 32, 7).unwrap();
+        assert_eq!(test.failures().count(), 0, "{}: {:?}", name, test.failures().collect::<Vec<_>>());
+        assert!(test.planned, "{}", name);
+        assert_eq!(test.naive, fits, "{}", name);
+        // Naive if it fits and compact, then per pool four comparisons and two programs
+        let programs = test.naive as usize + 1 + 2 * 2;
+        assert_eq!(test.checks.len(), programs + 2 * 4, "{}", name);
+        assert_eq!(test.programs, programs, "{}", name);
+        assert!(test.to_string().starts_with(&format!("{} checks passed, 32 vectors through {} programs", programs + 8, programs)), "{}", name);
+    }
+}
+
+#[test]
+fn snapshots_point_at_the_first_difference() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let program = Program::from_mapping(&circuit, &mapping).unwrap();
+    let reference = Snapshot::program(&program);
+    assert_eq!(reference.difference(&reference.clone()), None);
+
+    let mut swapped = program.clone();
+    let step = swapped.steps.iter_mut().find(|s| s.ops.iter().filter(|op| matches!(op, MicroOp::ExecNor { .. })).count() > 1).unwrap();
+    let first = step.ops.iter().position(|op| matches!(op, MicroOp::ExecNor { .. })).unwrap();
+    let last = step.ops.iter().rposition(|op| matches!(op, MicroOp::ExecNor { .. })).unwrap();
+    step.ops.swap(first, last);
+    let difference = reference.difference(&Snapshot::program(&swapped)).unwrap();
+    assert!(difference.starts_with("line ") && difference.contains("ExecNor"), "{}", difference);
+
+    let mut shorter = program.clone();
+    shorter.steps.pop();
+    let difference = reference.difference(&Snapshot::program(&shorter)).unwrap();
+    assert!(difference.ends_with(&format!("lines against {}", Snapshot::program(&shorter).0.len())), "{}", difference);
+
+    let mut moved = circuit.clone();
+    moved.gates[0].gate_map.as_mut().unwrap().jdx += 1;
+    let difference = Snapshot::mapping(&circuit, &mapping).difference(&Snapshot::mapping(&moved, &mapping)).unwrap();
+    assert!(difference.contains(&format!("`{} at ", delphi::signal_name(circuit.gates[0].out))), "{}", difference);
+}
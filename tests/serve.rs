@@ -0,0 +1,236 @@
+//tests/serve.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+use anyhow::{Result, bail, ensure};
+use delphi::metrics::RunMetrics;
+use delphi::serve::{self, JobStatus, Request, RequestError, Runner, Service};
+
+// Stands in for the pipeline: schedules the netlist and writes one artifact
+struct Scheduler;
+
+impl Runner for Scheduler {
+    fn check(&self, options: &[String]) -> Result<()> {
+        ensure!(options.iter().all(|o| o != "-o"), "leave out -o");
+        Ok(())
+    }
+
+    fn run(&self, netlist: &Path, options: &[String], dir: &Path) -> Result<Vec<RunMetrics>> {
+        if options.iter().any(|o| o == "--fail") {
+            bail!("asked to fail");
+        }
+        let circuit = common::scheduled(&fs::read_to_string(netlist)?);
+        fs::create_dir_all(dir.join("stats"))?;
+        fs::write(dir.join("stats").join("levels.txt"), format!("{}\n", circuit.max_asap))?;
+        Ok(vec![RunMetrics::new(&circuit)])
+    }
+}
+
+fn temp_root(name: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("delphi-serve-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&root);
+    root
+}
+
+fn request(method: &str, path: &str, body: &str) -> Request {
+    let content_type = Some("application/json".to_string());
+    Request { method: method.to_string(), path: path.to_string(), content_type, body: body.as_bytes().to_vec() }
+}
+
+fn submission(options: &[&str]) -> String {
+    serde_json::json!({ "name": "c17.txt", "netlist": common::bundled("c17"), "options": options }).to_string()
+}
+
+fn finished<R: Runner>(service: &Service<R>, id: u64) -> JobStatus {
+    for _ in 0..500 {
+        let status = service.job(id).unwrap().status;
+        if matches!(status, JobStatus::Done | JobStatus::Failed) {
+            return status;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("job {} never finished", id);
+}
+
+#[test]
+fn jobs_run_in_turn_and_serve_their_metrics_and_artifacts() {
+    let root = temp_root("jobs");
+    let service = Service::start(&root, Scheduler).unwrap();
+    let health = service.handle(&request("GET", "/health", ""));
+    assert_eq!(health.status, 200);
+
+    let accepted = service.handle(&request("POST", "/jobs", &submission(&[])));
+    assert_eq!(accepted.status, 202, "{}", String::from_utf8_lossy(&accepted.body));
+    let failing = service.handle(&request("POST", "/jobs", &submission(&["--fail"])));
+    assert_eq!(failing.status, 202);
+    assert_eq!(finished(&service, 1), JobStatus::Done);
+    assert_eq!(finished(&service, 2), JobStatus::Failed);
+    assert_eq!(service.job(2).unwrap().error.as_deref(), Some("asked to fail"));
+
+    let job = service.job(1).unwrap();
+    assert_eq!(job.artifacts, vec!["stats/levels.txt".to_string()]);
+    let metrics = service.handle(&request("GET", "/jobs/1/metrics", ""));
+    assert_eq!(metrics.status, 200);
+    let metrics: serde_json::Value = serde_json::from_slice(&metrics.body).unwrap();
+    assert_eq!(metrics["schema_version"], 1);
+    let circuit = common::scheduled(&common::bundled("c17"));
+    assert_eq!(metrics["circuits"][0]["gates"], circuit.num_gates);
+    let artifact = service.handle(&request("GET", "/jobs/1/artifacts/stats/levels.txt", ""));
+    assert_eq!((artifact.status, artifact.body), (200, format!("{}\n", circuit.max_asap).into_bytes()));
+
+    // Only listed artifacts are served, and a failed job has no metrics
+    for (method, path, body, status) in [
+        ("GET", "/jobs/2/metrics", "", 409),
+        ("GET", "/jobs/1/artifacts/../input/c17.txt", "", 404),
+        ("GET", "/jobs/7", "", 404),
+        ("DELETE", "/jobs/1", "", 405),
+        ("POST", "/jobs", "{", 400),
+    ] {
+        assert_eq!(service.handle(&request(method, path, body)).status, status, "{} {}", method, path);
+    }
+    let rejected = service.handle(&request("POST", "/jobs", &submission(&["-o"])));
+    assert_eq!(rejected.status, 400);
+    let escaping = serde_json::json!({ "name": "../c17.txt", "netlist": "" }).to_string();
+    assert_eq!(service.handle(&request("POST", "/jobs", &escaping)).status, 400);
+
+    // A submission that is not sent as JSON is refused before it is read
+    for content_type in [Some("text/plain"), Some("application/x-www-form-urlencoded"), None] {
+        let mut plain = request("POST", "/jobs", &submission(&[]));
+        plain.content_type = content_type.map(str::to_string);
+        assert_eq!(service.handle(&plain).status, 415, "{:?}", content_type);
+    }
+    let mut charset = request("POST", "/jobs", &submission(&[]));
+    charset.content_type = Some("Application/JSON; charset=utf-8".to_string());
+    assert_eq!(service.handle(&charset).status, 202);
+    assert_eq!(finished(&service, 3), JobStatus::Done);
+    let jobs: serde_json::Value = serde_json::from_slice(&service.handle(&request("GET", "/jobs", "")).body).unwrap();
+    assert_eq!(jobs["jobs"].as_array().unwrap().len(), 3);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn jobs_only_give_pipeline_options() {
+    serve::check_options(&[]).unwrap();
+    let taken = ["--placement", "mincut", "--pass=xor", "--cost", "cycles * 2", "--bundle", "c17.zip", "--no-clobber"];
+    serve::check_options(&taken.map(String::from)).unwrap();
+
+    // Commands, files the server would read, outside paths and server settings
+    for options in [
+        &["--presynth", "touch PWNED; cp {input} {output}", "--presynth-format", "native"][..],
+        &["--presynth=sh"],
+        &["--tech", "/etc/passwd"],
+        &["--dont-care=dc.txt"],
+        &["--input-groups", "groups.txt"],
+        &["--init-state", "state.txt"],
+        &["--parasitics", "wires.toml"],
+        &["--metrics-textfile", "/tmp/x.prom"],
+        &["--check-verilog"],
+        &["-o", "/tmp"],
+        &["-j4"],
+        &["--placement", "--", "--presynth"],
+    ] {
+        let options: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+        let error = serve::check_options(&options).unwrap_err().to_string();
+        assert!(error.starts_with("A job cannot give '-"), "{:?}: {}", options, error);
+    }
+}
+
+fn exchange(address: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+    exchange_as(address, method, path, "application/json", body)
+}
+
+fn exchange_as(address: &str, method: &str, path: &str, content_type: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        method, path, address, content_type, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, body.to_string())
+}
+
+#[test]
+fn the_server_answers_over_http() {
+    let root = temp_root("http");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server_root = root.clone();
+    thread::spawn(move || serve::serve(listener, &server_root, Scheduler));
+
+    let (status, body) = exchange(&address, "GET", "/health", "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"ok\""), "{}", body);
+    // A page in a browser can send text/plain without asking first
+    assert_eq!(exchange_as(&address, "POST", "/jobs", "text/plain", &submission(&[])).0, 415);
+    let (status, body) = exchange(&address, "POST", "/jobs", &submission(&[]));
+    assert_eq!(status, 202, "{}", body);
+    assert!(body.contains("\"queued\"") || body.contains("\"running\""), "{}", body);
+
+    let mut done = false;
+    for _ in 0..500 {
+        let (_, body) = exchange(&address, "GET", "/jobs/1", "");
+        if body.contains("\"done\"") {
+            done = true;
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(done);
+    let levels = common::scheduled(&common::bundled("c17")).max_asap;
+    assert_eq!(exchange(&address, "GET", "/jobs/1/artifacts/stats/levels.txt", ""), (200, format!("{}\n", levels)));
+    assert_eq!(exchange(&address, "GET", "/nowhere", "").0, 404);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+// Sends "X: v" header lines without end
+struct EndlessHeaders(usize);
+
+impl Read for EndlessHeaders {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let line = b"X: v\r\n";
+        for byte in buf.iter_mut() {
+            *byte = line[self.0 % line.len()];
+            self.0 += 1;
+        }
+        Ok(buf.len())
+    }
+}
+
+#[test]
+fn requests_are_read_within_their_limits() {
+    let read = serve::read_request(&b"POST /jobs HTTP/1.1\r\nContent-Type: application/json\r\ncontent-length: 2\r\n\r\n{}"[..]).unwrap();
+    assert_eq!((read.method.as_str(), read.path.as_str()), ("POST", "/jobs"));
+    assert_eq!((read.content_type.as_deref(), read.body), (Some("application/json"), b"{}".to_vec()));
+
+    // An endless line or an endless run of headers stops at the limit
+    let endless_line = b"GET /health HTTP/1.1\r\nX-Filler: ".chain(io::repeat(b'a'));
+    let headers = b"GET /health HTTP/1.1\r\n".chain(EndlessHeaders(0));
+    for error in [serve::read_request(endless_line).unwrap_err(), serve::read_request(io::repeat(b'G')).unwrap_err(), serve::read_request(headers).unwrap_err()] {
+        assert!(matches!(error, RequestError::HeadTooLarge), "{}", error);
+        assert_eq!(error.status(), 431);
+    }
+    let large = format!("POST /jobs HTTP/1.1\r\nContent-Length: {}\r\n\r\n", serve::MAX_BODY + 1);
+    let error = serve::read_request(large.as_bytes()).unwrap_err();
+    assert_eq!((error.status(), error.to_string()), (413, format!("A body of {} bytes is over the {} byte limit", serve::MAX_BODY + 1, serve::MAX_BODY)));
+    for malformed in ["\r\n\r\n", "GET\r\n\r\n", "POST /jobs HTTP/1.1\r\nContent-Length: many\r\n\r\n", "POST /jobs HTTP/1.1\r\nContent-Length: 9\r\n\r\n{}"] {
+        assert_eq!(serve::read_request(malformed.as_bytes()).unwrap_err().status(), 400, "{:?}", malformed);
+    }
+}
+
+#[test]
+fn job_paths_stay_in_the_job_directory() {
+    let dir = Path::new("/srv/delphi/job3/artifacts");
+    for (path, joined) in [("c17.zip", "c17.zip"), ("./out/c17.zip", "out/c17.zip"), ("{bench}.zip", "{bench}.zip")] {
+        assert_eq!(serve::job_path(dir, Path::new(path)).unwrap(), dir.join(joined));
+    }
+    for path in ["a/../../../x.zip", "../x.zip", "out/..", "/tmp/x.zip", "", "."] {
+        assert!(serve::job_path(dir, Path::new(path)).is_err(), "{}", path);
+    }
+}
@@ -0,0 +1,48 @@
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use delphi::mapper::{self, ColumnSpans};
+use delphi::program::Program;
+use delphi::{simulator, verify};
+
+#[test]
+fn the_compact_mapping_keeps_gates_within_the_span() {
+    for (name, text) in common::circuits() {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 5);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let free = ColumnSpans::new(&Program::from_mapping(&circuit, &mapping).unwrap());
+
+        circuit.max_span = Some(2);
+        let mut mapping = mapper::create_compact_mapping(&mut circuit);
+        verify::verify_mapping(&circuit, &mapping).unwrap();
+        assert_eq!(simulator::simulate_mapping(&circuit, &mapping, &vectors).unwrap(), expected, "{}", name);
+        let spans = ColumnSpans::new(&Program::from_mapping(&circuit, &mapping).unwrap());
+        assert!(spans.max() <= 2, "{}: {}", name, spans);
+        assert_eq!(spans.gates(), free.gates(), "{}", name);
+        assert!(spans.mean() <= free.mean(), "{}", name);
+
+        // Reused copies stay within the span too
+        mapper::reuse_copies(&mut circuit, &mut mapping);
+        let program = Program::from_mapping(&circuit, &mapping).unwrap();
+        assert!(ColumnSpans::new(&program).max() <= 2, "{}", name);
+        assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{}", name);
+    }
+}
+
+#[test]
+fn the_histogram_doubles_its_buckets() {
+    let mut circuit = common::scheduled(&common::synthetic_netlist(32, 500, 0x5eed));
+    let naive = mapper::create_naive_mapping(&mut circuit);
+    let spans = ColumnSpans::new(&Program::from_mapping(&circuit, &naive).unwrap());
+    assert!(spans.max() > 64);
+
+    let histogram = spans.histogram(spans.max());
+    assert_eq!(histogram[..4].iter().map(|(bucket, _)| *bucket).collect::<Vec<_>>(), [(1, 1), (2, 2), (3, 4), (5, 8)]);
+    let ((low, high), _) = histogram[histogram.len() - 1];
+    assert!((low..=high).contains(&spans.max()));
+    assert_eq!(histogram.iter().map(|(_, gates)| gates).sum::<usize>(), spans.gates());
+    // Wider buckets than the spans need stay empty
+    assert_eq!(spans.histogram(4 * spans.max()).iter().map(|(_, gates)| gates).sum::<usize>(), spans.gates());
+}
@@ -0,0 +1,70 @@
+//tests/steps.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::collections::HashSet;
+
+use delphi::mapper::{self, MappedCrossbar, Mapper};
+use delphi::program::Cell;
+use delphi::scheduler::{ScheduleStep, Scheduler};
+
+#[test]
+fn schedule_steps_take_one_level_at_a_time() {
+    for (name, text) in common::circuits() {
+        let circuit = common::scheduled(&text);
+        let steps: Vec<ScheduleStep> = Scheduler::Asap.steps(&circuit).collect();
+        assert_eq!(steps.len() as i32, circuit.max_asap, "{}", name);
+        for (k, step) in steps.iter().enumerate() {
+            assert_eq!(step.level, k as i32 + 1, "{}", name);
+            assert!(step.gates.iter().all(|&i| circuit.gates[i].asap_level == step.level), "{}", name);
+        }
+        assert_eq!(steps.iter().map(|s| s.gates.len()).sum::<usize>(), circuit.num_gates, "{}", name);
+
+        // A bounded list schedule never takes a gate before its operands
+        let mut scheduled = HashSet::new();
+        let mut steps = Scheduler::List(4).steps(&circuit);
+        while let Some(step) = steps.next() {
+            assert!(!step.gates.is_empty() && step.gates.len() <= 4, "{}", name);
+            for &i in &step.gates {
+                let gate = &circuit.gates[i];
+                assert!(gate.inputs[..gate.fanin].iter().all(|&s| circuit.driver_index(s).is_none_or(|d| scheduled.contains(&d))),
+                    "{}: {}", name, i);
+            }
+            scheduled.extend(step.gates);
+            assert!(steps.ready().all(|i| !scheduled.contains(&i)), "{}", name);
+        }
+        assert_eq!(scheduled.len(), circuit.num_gates, "{}", name);
+    }
+}
+
+#[test]
+fn mapping_steps_place_one_gate_at_a_time_and_resume() {
+    for (name, text) in [("c17", common::bundled("c17")), ("synthetic", common::synthetic_netlist(12, 150, 3))] {
+        let mut circuit = common::scheduled(&text);
+        let naive = MappedCrossbar::new(&mapper::create_naive_mapping(&mut circuit));
+        let compact = MappedCrossbar::new(&mapper::create_compact_mapping(&mut circuit));
+
+        let mut steps = Mapper::Compact.steps(&mut circuit);
+        assert_eq!(steps.len(), steps.circuit().num_gates, "{}", name);
+        let cells = steps.mapping().occupied_cells().count();
+        let mut placed = Vec::new();
+        let half = steps.len() / 2;
+        while placed.len() < half {
+            let step = steps.next().unwrap();
+            // Half the gates: the gate and its copies are in the mapping as the step returns
+            let gate = steps.mapping().cell(step.cell.row as usize, step.cell.col as usize);
+            assert!(!gate.is_copy && gate.fanin > 0, "{}", name);
+            assert!(step.copies.iter().all(|c| steps.mapping().cell(c.row as usize, c.col as usize).is_copy), "{}", name);
+            placed.push(step);
+        }
+        assert_eq!(steps.mapping().occupied_cells().count(),
+            cells + placed.iter().map(|p| 1 + p.copies.len()).sum::<usize>(), "{}", name);
+        assert_eq!(placed.iter().map(|p| p.gate).collect::<Vec<_>>(), (0..half).collect::<Vec<_>>(), "{}", name);
+        assert_eq!(MappedCrossbar::new(&steps.finish()), compact, "{}", name);
+
+        let steps = Mapper::Naive.steps(&mut circuit);
+        let placed: Vec<Cell> = steps.map(|p| { assert!(p.copies.is_empty()); p.cell }).collect();
+        assert!(placed.iter().all(|c| c.row == placed[0].row), "{}", name);
+        assert_eq!(MappedCrossbar::new(&Mapper::Naive.map(&mut circuit)), naive, "{}", name);
+    }
+}
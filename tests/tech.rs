@@ -0,0 +1,220 @@
+//tests/tech.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::path::PathBuf;
+use std::{env, fs, process};
+
+use delphi::mapper::{self, Placement, RegionUsage};
+use delphi::passes::{Cluster, Pass};
+use delphi::program::Program;
+use delphi::tech::{Technology, WireModel};
+use delphi::{generator, simulator};
+
+fn profiles() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tech/profiles.toml")
+}
+
+const SINGLE: &str = r#"
+[profile.only.device]
+read_ns = 1.0
+write_ns = 4.0
+switch_ns = 2.0
+read_pj = 0.1
+write_pj = 1.0
+switch_pj = 0.5
+endurance = 1000
+
+[profile.only.crossbar]
+max_row_activations = 4
+drivers = 8
+"#;
+
+#[test]
+fn bundled_profiles_load() {
+    let default = Technology::load(profiles(), None).unwrap();
+    assert_eq!(default.name, "hfo2");
+    let pcm = Technology::load(profiles(), Some("pcm")).unwrap();
+    assert_eq!(pcm.clock_ns(), 50.0);
+    assert!(Technology::load(profiles(), Some("missing")).is_err());
+}
+
+#[test]
+fn single_profile_is_the_default() {
+    let tech = Technology::from_toml(SINGLE, None).unwrap();
+    assert_eq!(tech.name, "only");
+    // Slowest operation sets the clock when none is given
+    assert_eq!(tech.clock_ns(), 4.0);
+    assert_eq!(tech.lifetime_runs(2), 500);
+}
+
+#[test]
+fn steps_split_on_periphery_limits() {
+    let tech = Technology::from_toml(SINGLE, None).unwrap();
+    assert_eq!(tech.step_cycles(0, 0), 1);
+    assert_eq!(tech.step_cycles(4, 8), 1);
+    assert_eq!(tech.step_cycles(5, 3), 2);
+    assert_eq!(tech.step_cycles(1, 17), 3);
+}
+
+#[test]
+fn invalid_profiles_are_rejected() {
+    assert!(Technology::from_toml(&SINGLE.replace("drivers = 8", "drivers = 0"), None).is_err());
+    assert!(Technology::from_toml(&SINGLE.replace("read_ns = 1.0", "read_ns = -1.0"), None).is_err());
+    assert!(Technology::from_toml(&SINGLE.replace("endurance", "lifetime"), None).is_err());
+    let fanin = |n: usize| Technology::from_toml(&SINGLE.replace("endurance = 1000", &format!("endurance = 1000\nmax_device_fanin = {}", n)), None);
+    assert_eq!(fanin(4).unwrap().max_device_fanin(), 4);
+    assert!(fanin(1).is_err() && fanin(6).is_err());
+    assert_eq!(Technology::from_toml(SINGLE, None).unwrap().max_device_fanin(), 2);
+}
+
+#[test]
+fn reserved_lines_are_validated() {
+    let reserved = SINGLE.replace("drivers = 8", "drivers = 8\nrows = 16\nreserved_rows = [0, 15]\nreserved_cols = [3]");
+    let tech = Technology::from_toml(&reserved, None).unwrap();
+    assert!(tech.periphery.constrains_placement());
+    assert!(!Technology::from_toml(SINGLE, None).unwrap().periphery.constrains_placement());
+
+    assert!(Technology::from_toml(&reserved.replace("[0, 15]", "[0, 16]"), None).is_err());
+    assert!(Technology::from_toml(&reserved.replace("rows = 16", "rows = 501"), None).is_err());
+    assert!(Technology::from_toml(&reserved.replace("[3]", "[1000]"), None).is_err());
+}
+
+#[test]
+fn mappings_avoid_reserved_lines() {
+    let tech = Technology::from_toml(&SINGLE.replace("drivers = 8",
+        "drivers = 8\nreserved_rows = [0, 2, 3]\nreserved_cols = [1, 4]"), None).unwrap();
+    let placement = Placement::new(&tech.periphery);
+
+    for (name, text) in common::circuits().into_iter().take(2) {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 5);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        for naive in [true, false] {
+            let mapping = if naive {
+                mapper::create_naive_mapping(&mut circuit)
+            } else {
+                mapper::create_compact_mapping(&mut circuit)
+            };
+            let placed = placement.apply(&mut circuit, &mapping).unwrap();
+            assert_eq!(placed.occupied_cells().count(), mapping.occupied_cells().count());
+            for cell in placed.occupied_cells() {
+                assert!(![0, 2, 3].contains(&cell.idx) && ![1, 4].contains(&cell.jdx),
+                    "{}: cell on reserved line {}x{}", name, cell.idx, cell.jdx);
+            }
+            assert_eq!(simulator::simulate_mapping(&circuit, &placed, &vectors).unwrap(), expected, "{}", name);
+            // Gates remember where they moved
+            for gate in circuit.gates.iter().take(circuit.num_gates) {
+                let cell = gate.gate_map.as_ref().unwrap();
+                assert_eq!(placed.cell(cell.idx as usize, cell.jdx as usize).value, gate.out);
+            }
+        }
+    }
+}
+
+#[test]
+fn gates_stay_out_of_storage_regions() {
+    let regions = SINGLE.replace("drivers = 8", "drivers = 8\nreserved_rows = [2]\nstorage_rows = [0, 1, 3]\nstorage_cols = [0, 5, 6]");
+    let tech = Technology::from_toml(&regions, None).unwrap();
+    assert!(tech.periphery.constrains_placement());
+    let placement = Placement::new(&tech.periphery);
+    let logical = placement.regions();
+    assert_eq!((logical.storage_rows.iter().copied().collect::<Vec<_>>(), logical.storage_cols.len()), (vec![0, 1, 2], 3));
+
+    for (name, text) in common::circuits().into_iter().take(2) {
+        let mut circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 128, 5);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+        // Mappings that ignore the region map put gates in storage cells
+        let mapping = mapper::create_compact_mapping(&mut circuit);
+        let error = placement.apply(&mut circuit, &mapping).unwrap_err();
+        assert!(error.to_string().contains("storage cell"), "{}: {}", name, error);
+
+        Cluster::default().run(&mut circuit).unwrap();
+        circuit.regions = logical.clone();
+        for naive in [true, false] {
+            let mapping = if naive {
+                mapper::create_naive_mapping(&mut circuit)
+            } else {
+                mapper::create_compact_mapping(&mut circuit)
+            };
+            let placed = placement.apply(&mut circuit, &mapping).unwrap();
+            for cell in placed.occupied_cells().filter(|c| c.fanin > 0) {
+                assert!(![0, 1, 3].contains(&cell.idx) && ![0, 5, 6].contains(&cell.jdx),
+                    "{}: gate in storage cell {}x{}", name, cell.idx, cell.jdx);
+            }
+            assert_eq!(simulator::simulate_mapping(&circuit, &placed, &vectors).unwrap(), expected, "{}", name);
+            let program = Program::from_mapping(&circuit, &placed).unwrap();
+            assert_eq!(simulator::simulate_program(&program, &vectors).unwrap(), expected, "{}", name);
+
+            // Inputs sit in storage: compact ones in column 0, naive ones partly
+            let usage = RegionUsage::new(&placement, &program);
+            assert_eq!(usage.compute_used + usage.storage_used, program.cells().len(), "{}", name);
+            assert!(usage.storage_used > 0 && usage.compute_used <= usage.compute_cells, "{}: {}", name, usage);
+        }
+        // The parallel mapper leaves storage regions to the sequential one
+        let sequential = mapper::create_compact_mapping(&mut circuit);
+        let parallel = mapper::create_compact_mapping_parallel(&mut circuit);
+        assert_eq!(Program::from_mapping(&circuit, &parallel).unwrap(), Program::from_mapping(&circuit, &sequential).unwrap(), "{}", name);
+    }
+
+    assert!(Technology::from_toml(&regions.replace("reserved_rows = [2]", "reserved_rows = [3]"), None).is_err());
+    assert!(Technology::from_toml(&regions.replace("[0, 5, 6]", "[0, 1000]"), None).is_err());
+    let all = SINGLE.replace("drivers = 8", "drivers = 8\nrows = 2\nstorage_rows = [0, 1]");
+    assert!(Technology::from_toml(&all, None).is_err());
+}
+
+#[test]
+fn reserved_lines_reduce_capacity() {
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let (rows, cols) = (mapping.max_idx as usize + 1, mapping.max_jdx as usize + 1);
+
+    let sized = SINGLE.replace("drivers = 8", &format!("drivers = 8\nrows = {}\ncols = {}", rows, cols));
+    let tech = Technology::from_toml(&sized, None).unwrap();
+    let placed = Placement::new(&tech.periphery).apply(&mut circuit, &mapping).unwrap();
+    assert_eq!((placed.max_idx, placed.max_jdx), (mapping.max_idx, mapping.max_jdx));
+
+    // The same array with one row or column reserved no longer fits
+    for extra in ["reserved_rows = [1]", "reserved_cols = [0]"] {
+        let tech = Technology::from_toml(&format!("{}{}\n", sized, extra), None).unwrap();
+        let error = Placement::new(&tech.periphery).apply(&mut circuit, &mapping).unwrap_err();
+        assert!(error.to_string().contains("are free"), "{}", error);
+    }
+}
+
+#[test]
+fn wires_add_delay_by_distance() {
+    let wires = WireModel::from_toml("distance = [2, 10, 20]\ndelay_ns = [0.1, 0.5, 0.7]\n").unwrap();
+    let delays: Vec<f64> = [0, 2, 6, 10, 15, 30].iter().map(|&d| wires.delay_ns(d)).collect();
+    let expected = [0.1, 0.1, 0.3, 0.5, 0.6, 0.9];
+    assert!(delays.iter().zip(expected).all(|(d, e)| (d - e).abs() < 1e-9), "{:?}", delays);
+    assert_eq!(WireModel::from_toml("distance = [4]\ndelay_ns = [0.2]\n").unwrap().delay_ns(100), 0.2);
+    for bad in ["distance = [4, 2]\ndelay_ns = [0.2, 0.3]\n", "distance = [4]\ndelay_ns = [0.2, 0.3]\n",
+                "distance = []\ndelay_ns = []\n", "distance = [1]\ndelay_ns = [-1.0]\n"] {
+        assert!(WireModel::from_toml(bad).is_err(), "{}", bad);
+    }
+
+    // Each level waits for its longest wire on top of its clock cycles
+    let dir = env::temp_dir().join(format!("delphi-wires-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut circuit = common::scheduled(&common::bundled("c17"));
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    let mut tech = Technology::from_toml(SINGLE, None).unwrap();
+    let plain = dir.join("plain.txt");
+    generator::generate_micro_ops_with_tech(&circuit, &mapping, false, Some(&tech), false, &plain).unwrap();
+    tech.wires = Some(WireModel::from_toml("distance = [0, 1]\ndelay_ns = [0.0, 1.0]\n").unwrap());
+    let wired = dir.join("wired.txt");
+    generator::generate_micro_ops_with_tech(&circuit, &mapping, false, Some(&tech), false, &wired).unwrap();
+
+    let value = |path: &PathBuf, name: &str| -> f64 {
+        let text = fs::read_to_string(path).unwrap();
+        let line = text.lines().find(|l| l.starts_with(name)).unwrap_or_else(|| panic!("no {}", name)).to_string();
+        line.split(':').nth(1).unwrap().split_whitespace().next().unwrap().parse().unwrap()
+    };
+    let delay = value(&wired, "Wire Delay");
+    assert!(delay > 0.0);
+    assert!((value(&wired, "Execution Time") - value(&plain, "Execution Time") - delay).abs() < 0.01);
+    assert!(!fs::read_to_string(&plain).unwrap().contains("Wire Delay"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,95 @@
+//tests/tiling.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::lint::Place;
+use delphi::tiling::TiledPipeline;
+use delphi::{generator, lint, simulator};
+
+#[test]
+fn pipelined_tiles_compute_the_netlist() {
+    // synthetic_2400 carries more live signals between levels than a tile has rows
+    for (name, text) in common::circuits().into_iter().filter(|(name, _)| *name != "synthetic_2400") {
+        let circuit = common::scheduled(&text);
+        let vectors = simulator::random_vectors(circuit.num_inputs, 256, 7);
+        let expected = simulator::simulate_netlist(&circuit, &vectors).unwrap();
+
+        for tiles in [1, 3, 8] {
+            let mut pipeline = TiledPipeline::new(&circuit, tiles).unwrap();
+            assert_eq!(pipeline.simulate(&vectors).unwrap(), expected, "{} in {} tiles", name, tiles);
+
+            // Bands are contiguous and every gate lands in exactly one
+            let bands: Vec<(i32, i32)> = pipeline.tiles.iter().map(|t| (t.first_level, t.last_level)).collect();
+            assert_eq!(bands[0].0, 1);
+            assert_eq!(bands.last().unwrap().1, circuit.max_asap);
+            assert!(bands.windows(2).all(|w| w[1].0 == w[0].1 + 1), "{}: {:?}", name, bands);
+            let gates: usize = pipeline.tiles.iter().map(|t| t.circuit.num_gates).sum();
+            assert_eq!(gates, circuit.num_gates);
+
+            // Each tile's own crossbar computes what it sends on
+            for tile in &mut pipeline.tiles {
+                let inputs = simulator::random_vectors(tile.circuit.num_inputs, 64, 3);
+                let netlist = simulator::simulate_netlist(&tile.circuit, &inputs).unwrap();
+                let mapping = delphi::mapper::create_compact_mapping(&mut tile.circuit);
+                assert_eq!(simulator::simulate_mapping(&tile.circuit, &mapping, &inputs).unwrap(), netlist,
+                    "{} tile {}-{}", name, tile.first_level, tile.last_level);
+            }
+        }
+    }
+}
+
+#[test]
+fn pipelining_trades_latency_for_throughput() {
+    let circuit = common::scheduled(&common::synthetic_netlist(32, 500, 0x5eed));
+    let single = TiledPipeline::new(&circuit, 1).unwrap();
+    assert_eq!(single.initiation_interval(), single.unpipelined_cycles() + 1);
+
+    let pipeline = TiledPipeline::new(&circuit, 4).unwrap();
+    assert_eq!(pipeline.tiles.len(), 4);
+    assert!(pipeline.initiation_interval() * 3 < pipeline.unpipelined_cycles());
+    assert!(pipeline.latency() >= pipeline.unpipelined_cycles());
+    assert!(pipeline.throughput() > 3.0 / pipeline.unpipelined_cycles() as f64);
+
+    // Outputs computed early are passed through to the last tile
+    let last = pipeline.tiles.last().unwrap();
+    assert_eq!(last.live_out, simulator::output_signals(&circuit));
+
+    // No more tiles than levels
+    let c17 = common::scheduled(&common::bundled("c17"));
+    let pipeline = TiledPipeline::new(&c17, 100).unwrap();
+    assert_eq!(pipeline.tiles.len(), c17.max_asap as usize);
+    assert!(pipeline.tiles.iter().all(|t| t.levels() == 1));
+    assert!(TiledPipeline::new(&c17, 0).is_err());
+
+    let wide = common::scheduled(&common::synthetic_netlist(32, 2400, 0x5eed));
+    let error = TiledPipeline::new(&wide, 3).unwrap_err().to_string();
+    assert!(error.contains("crossbar rows"), "{}", error);
+}
+
+#[test]
+fn tile_programs_pass_the_linter() {
+    let dir = env::temp_dir().join(format!("delphi-tiling-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let circuit = common::scheduled(&common::bundled("c7552"));
+    let mut pipeline = TiledPipeline::new(&circuit, 5).unwrap();
+    let programs: Vec<_> = (0..5).map(|k| dir.join(format!("c7552_tile{}.txt", k))).collect();
+    generator::generate_pipeline(&mut pipeline, &programs, dir.join("c7552_pipeline.txt")).unwrap();
+
+    for (tile, path) in pipeline.tiles.iter().zip(&programs) {
+        assert!(lint::lint_micro_ops(path).unwrap().is_empty(), "{:?}", path);
+        let text = fs::read_to_string(path).unwrap();
+        assert_eq!(text.lines().filter(|l| l.starts_with("RECV")).count(), tile.live_in.len());
+        assert_eq!(text.lines().filter(|l| l.starts_with("SEND")).count(), tile.live_out.len());
+    }
+    let report = fs::read_to_string(dir.join("c7552_pipeline.txt")).unwrap();
+    assert!(report.contains(&format!("Initiation interval: {} cycles", pipeline.initiation_interval())), "{}", report);
+
+    // A send from a cell the program never writes is caught
+    let broken = "RECV    0 x0\n# Level:  1 ___\n   0 False    0 /0                 1 True\nSEND 0x1 n2\nSEND 0x2 n3\nSYNC\n";
+    let issues = lint::lint_micro_ops_text(broken);
+    assert_eq!(issues.len(), 1, "{:?}", issues);
+    assert_eq!(issues[0].at, Place::Line(5));
+    fs::remove_dir_all(&dir).unwrap();
+}
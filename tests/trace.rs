@@ -0,0 +1,77 @@
+//tests/trace.rs
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use std::{env, fs, process};
+
+use delphi::generator;
+use delphi::mapper;
+use delphi::program::Program;
+use delphi::simulator;
+
+fn compact_program(text: &str) -> Program {
+    let mut circuit = common::scheduled(text);
+    let mapping = mapper::create_compact_mapping(&mut circuit);
+    Program::from_mapping(&circuit, &mapping).unwrap()
+}
+
+#[test]
+fn traces_follow_the_timeline_and_read_what_the_simulator_reads() {
+    for text in [common::bundled("c17"), common::synthetic_netlist(6, 80, 3)] {
+        let mut circuit = common::scheduled(&text);
+        for mapping in [mapper::create_naive_mapping(&mut circuit), mapper::create_compact_mapping(&mut circuit)] {
+            let program = Program::from_mapping(&circuit, &mapping).unwrap();
+            let timeline = program.timeline();
+            for vector in simulator::random_vectors(program.num_inputs, 4, 9) {
+                let frames = simulator::trace_program(&program, &vector).unwrap();
+                // Frames tile the timeline's cycles; each starts in its own phase
+                let mut cycle = 0;
+                for frame in &frames {
+                    assert_eq!(frame.cycle, cycle);
+                    if frame.phase != "read" {
+                        let usage = &timeline[frame.cycle];
+                        assert_eq!(usage.level, frame.level);
+                        assert_eq!(usage.phase, if frame.phase == "evaluate" { "read" } else { frame.phase });
+                    }
+                    cycle += frame.cycles;
+                }
+                assert_eq!(cycle, timeline.len());
+
+                // Each evaluation leaves its cells evaluated, and the reads fill
+                // the buffer the simulator returns
+                for frame in frames.iter().filter(|f| f.phase == "evaluate") {
+                    assert!(frame.writes.iter().all(|c| frame.cells[c].is_some()));
+                }
+                let last = frames.last().unwrap();
+                assert_eq!(last.phase, "read");
+                let outputs: Vec<bool> = last.outputs.iter().map(|o| o.unwrap()).collect();
+                assert_eq!(outputs, simulator::simulate_program(&program, std::slice::from_ref(&vector)).unwrap()[0]);
+                assert!(frames[..frames.len() - 1].iter().all(|f| f.outputs.iter().all(Option::is_none)));
+            }
+        }
+    }
+
+    let program = compact_program(&common::bundled("c17"));
+    let error = simulator::trace_program(&program, &[true]).unwrap_err();
+    assert!(error.to_string().contains("has 1 bits but the program has 5 inputs"), "{}", error);
+}
+
+#[test]
+fn initialized_cells_wait_until_their_level_evaluates() {
+    let program = compact_program(&common::bundled("c17"));
+    let frames = simulator::trace_program(&program, &[false, true, true, false, true]).unwrap();
+    let resets: Vec<_> = frames.iter().filter(|f| f.phase == "reset").collect();
+    assert!(!resets.is_empty());
+    for frame in resets {
+        assert!(frame.writes.iter().all(|c| frame.cells[c].is_none()), "{:?}", frame);
+    }
+
+    let dir = env::temp_dir().join(format!("delphi-trace-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("c17.svg");
+    generator::generate_animation(&program, &frames, "c17", 250, &path).unwrap();
+    let svg = fs::read_to_string(&path).unwrap();
+    assert_eq!(svg.matches("<g opacity=\"0\">").count(), frames.len());
+    assert!(svg.contains(&format!("dur=\"{}ms\"", 250 * frames.len())), "{}", svg);
+    fs::remove_dir_all(&dir).unwrap();
+}